@@ -0,0 +1,47 @@
+"""C++ side of a #[cxx::bridge] module
+
+Like cxx's own rust_cxx_bridge macro, but passes the cargo features to the
+code generator: the bridges gate items with #[cfg(feature = "...")], which
+cxx-build resolves from CARGO_FEATURE_* and cxxbridge needs as --cfg.
+"""
+
+load("@rules_cc//cc:defs.bzl", "cc_library")
+
+def cxx_bridge(name, src, features = [], deps = [], visibility = None):
+    """Generate `<src>.h` / `<src>.cc` and wrap them in cc_library targets
+
+    Args:
+        name: `name` holds the generated C++ code, `name/include` the header
+        src: the Rust file with the #[cxx::bridge] module
+        features: cargo features the Rust crate is built with
+        deps: cc_library targets with the headers the bridge include!s
+        visibility: visibility of both cc_library targets
+    """
+    cfgs = " ".join(["--cfg 'feature=\"%s\"'" % feature for feature in features])
+    native.genrule(
+        name = name + "/generated",
+        srcs = [src],
+        outs = [src + ".h", src + ".cc"],
+        cmd = "$(location @cxx.rs//:codegen) $(location {src}) {cfgs} -o $(location {src}.h) -o $(location {src}.cc)".format(
+            src = src,
+            cfgs = cfgs,
+        ),
+        tools = ["@cxx.rs//:codegen"],
+    )
+
+    cc_library(
+        name = name + "/include",
+        hdrs = [src + ".h"],
+        deps = deps + ["@cxx.rs//:core"],
+        visibility = visibility,
+    )
+
+    # alwayslink: the wrappers are called from C++ only, never from Rust
+    cc_library(
+        name = name,
+        srcs = [src + ".cc"],
+        deps = deps + [":%s/include" % name],
+        alwayslink = True,
+        linkstatic = True,
+        visibility = visibility,
+    )
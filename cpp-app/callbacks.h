@@ -0,0 +1,155 @@
+#pragma once
+
+#include <cstddef>
+#include <cstdint>
+#include <functional>
+#include <utility>
+
+#include "rust/cxx.h"
+
+// Callback objects that C++ hands to Rust.
+// cxx cannot pass C++ function pointers into Rust, so each callback is a
+// small C++ class wrapping a std::function. Rust sees it as an opaque type
+// and invokes it through a const member function.
+
+/// Progress reporting for long-running batch calls
+/// Rust calls on_progress(processed, total) while it works through a batch
+class ProgressCallback {
+private:
+    std::function<void(size_t, size_t)> fn_;
+
+public:
+    explicit ProgressCallback(std::function<void(size_t, size_t)> fn)
+        : fn_(std::move(fn)) {}
+
+    void on_progress(size_t processed, size_t total) const {
+        if (fn_) fn_(processed, total);
+    }
+};
+
+struct PersonInfo;  // bridge struct, defined in the cxx-generated person_ffi.rs.h
+
+/// Destination for results produced by the Rust ingest consumer
+/// Called on the Rust consumer thread - the function must be thread-safe
+class ResultSink {
+private:
+    std::function<void(const PersonInfo&)> fn_;
+
+public:
+    explicit ResultSink(std::function<void(const PersonInfo&)> fn)
+        : fn_(std::move(fn)) {}
+
+    void on_person_info(const PersonInfo& info) const {
+        if (fn_) fn_(info);
+    }
+};
+
+struct HealthAnalysis;               // bridge struct, defined in health_ffi.rs.h
+enum class JobStatus : std::uint8_t; // bridge enum, defined in health_ffi.rs.h
+
+/// Completion handler for jobs started with submit_analysis_with_callback
+/// Called on the dedicated Rust callback thread - the function must be
+/// thread-safe, typically it just posts the result to the host event loop.
+/// `results` is empty unless `status` is JobStatus::Completed.
+class JobCallback {
+private:
+    std::function<void(uint64_t, JobStatus, const rust::Vec<HealthAnalysis>&)> fn_;
+
+public:
+    explicit JobCallback(
+        std::function<void(uint64_t, JobStatus, const rust::Vec<HealthAnalysis>&)> fn)
+        : fn_(std::move(fn)) {}
+
+    void on_done(uint64_t job_id, JobStatus status,
+                 const rust::Vec<HealthAnalysis>& results) const {
+        if (fn_) fn_(job_id, status, results);
+    }
+};
+
+/// Alert hook registered with register_risk_observer
+/// Called synchronously on whichever thread ran the analysis, possibly
+/// several threads at once - the function must be thread-safe.
+class RiskObserver {
+private:
+    std::function<void(const HealthAnalysis&)> fn_;
+
+public:
+    explicit RiskObserver(std::function<void(const HealthAnalysis&)> fn)
+        : fn_(std::move(fn)) {}
+
+    void on_high_risk(const HealthAnalysis& analysis) const {
+        if (fn_) fn_(analysis);
+    }
+};
+
+struct PersonSnapshot;  // bridge struct, defined in person_ffi.rs.h
+
+/// Site-specific risk factor installed with set_extra_risk_hook
+/// The returned factor is multiplied into every risk score; negative or
+/// non-finite values are ignored. Called on any analysis thread, possibly
+/// several at once - the function must be thread-safe.
+class RiskFactorHook {
+private:
+    std::function<double(const PersonSnapshot&)> fn_;
+
+public:
+    explicit RiskFactorHook(std::function<double(const PersonSnapshot&)> fn)
+        : fn_(std::move(fn)) {}
+
+    double extra_risk(const PersonSnapshot& snapshot) const {
+        return fn_ ? fn_(snapshot) : 1.0;
+    }
+};
+
+struct BusEvent;  // bridge struct, defined in person_ffi.rs.h
+
+/// Event bus subscriber registered with subscribe(kind, ...)
+/// Called synchronously on the publishing thread, possibly several threads
+/// at once - the function must be thread-safe.
+class EventSubscriber {
+private:
+    std::function<void(const BusEvent&)> fn_;
+
+public:
+    explicit EventSubscriber(std::function<void(const BusEvent&)> fn)
+        : fn_(std::move(fn)) {}
+
+    void on_event(const BusEvent& event) const {
+        if (fn_) fn_(event);
+    }
+};
+
+/// Condition for filter_people
+/// Large inputs are filtered on several Rust threads at once - the function
+/// must be thread-safe and should not depend on call order.
+class PersonPredicate {
+private:
+    std::function<bool(const PersonSnapshot&)> fn_;
+
+public:
+    explicit PersonPredicate(std::function<bool(const PersonSnapshot&)> fn)
+        : fn_(std::move(fn)) {}
+
+    bool matches(const PersonSnapshot& person) const {
+        return fn_ ? fn_(person) : false;
+    }
+};
+
+struct SessionResult;  // bridge struct, defined in health_ffi.rs.h
+
+/// Result handler installed with set_session_sink
+/// Called on the session's Rust worker thread, one result at a time in
+/// push order - the function must be thread-safe with respect to the
+/// thread that pushes.
+class SessionSink {
+private:
+    std::function<void(const SessionResult&)> fn_;
+
+public:
+    explicit SessionSink(std::function<void(const SessionResult&)> fn)
+        : fn_(std::move(fn)) {}
+
+    void on_result(const SessionResult& result) const {
+        if (fn_) fn_(result);
+    }
+};
@@ -0,0 +1,45 @@
+#include "host_allocator.h"
+
+#include <cstdlib>
+
+#ifdef _WIN32
+#include <malloc.h>
+#endif
+
+// Default hooks backed by the system allocator. They are weak on GCC/Clang,
+// so a host application's own definitions take precedence at link time.
+// MSVC has no weak symbols: the defaults get their own names and
+// /alternatename resolves the hooks to them only if the host defines none.
+#if defined(_MSC_VER) && !defined(__clang__)
+#define RUST_LIB_WEAK
+#define rust_lib_host_alloc rust_lib_host_alloc_default
+#define rust_lib_host_free rust_lib_host_free_default
+#if defined(_M_IX86)
+#pragma comment(linker, "/alternatename:_rust_lib_host_alloc=_rust_lib_host_alloc_default")
+#pragma comment(linker, "/alternatename:_rust_lib_host_free=_rust_lib_host_free_default")
+#else
+#pragma comment(linker, "/alternatename:rust_lib_host_alloc=rust_lib_host_alloc_default")
+#pragma comment(linker, "/alternatename:rust_lib_host_free=rust_lib_host_free_default")
+#endif
+#else
+#define RUST_LIB_WEAK __attribute__((weak))
+#endif
+
+extern "C" RUST_LIB_WEAK void* rust_lib_host_alloc(std::size_t size, std::size_t align) {
+#ifdef _WIN32
+    return _aligned_malloc(size, align);
+#else
+    // posix_memalign needs at least pointer alignment
+    if (align < sizeof(void*)) align = sizeof(void*);
+    void* ptr = nullptr;
+    return posix_memalign(&ptr, align, size) == 0 ? ptr : nullptr;
+#endif
+}
+
+extern "C" RUST_LIB_WEAK void rust_lib_host_free(void* ptr, std::size_t, std::size_t) {
+#ifdef _WIN32
+    _aligned_free(ptr);
+#else
+    std::free(ptr);
+#endif
+}
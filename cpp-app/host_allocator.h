@@ -0,0 +1,24 @@
+#pragma once
+
+#include <cstddef>
+
+// Allocation hooks used by the Rust library when it is built with the
+// `host-allocator` feature. Every Rust heap allocation goes through them,
+// so host memory tracking and arena policies cover Rust memory too.
+//
+// The library ships weak default definitions (see host_allocator.cpp)
+// backed by the system allocator; define these functions in the host
+// application to replace them. They may be called from any thread, and
+// before main() runs.
+
+extern "C" {
+
+/// Allocate `size` bytes aligned to `align` (a power of two)
+/// Return nullptr on failure; Rust then reports an allocation error.
+void* rust_lib_host_alloc(std::size_t size, std::size_t align);
+
+/// Release memory from rust_lib_host_alloc; `size` and `align` match the
+/// values passed when it was allocated.
+void rust_lib_host_free(void* ptr, std::size_t size, std::size_t align);
+
+}
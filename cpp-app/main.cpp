@@ -1,13 +1,25 @@
 // Include our C++ definitions (existing C++ code)
 #include "person.h"
 
-// Include the cxx-generated header (for Rust FFI)
-#include "rust-lib/src/lib.rs.h"
+// Include the cxx-generated headers (for Rust FFI), one per bridge module
+#include "rust-lib/src/person_ffi.rs.h"
+#include "rust-lib/src/health_ffi.rs.h"
+#include "rust-lib/src/validation_ffi.rs.h"
+#include "rust/cxx.h"  // rust::Error, thrown by functions returning Result
+#include "registered_person.h"
 
 #include <iostream>
 #include <string>
 #include <memory>
+#include <vector>
+#include <future>
+#ifdef _WIN32
+#define WIN32_LEAN_AND_MEAN
+#define NOMINMAX
+#include <windows.h>  // LoadLibraryA/FreeLibrary stand in for dlopen/dlclose
+#else
 #include <dlfcn.h>  // For dlopen, dlsym, dlclose (dynamic loading)
+#endif
 
 // Dynamic library loader class
 class DynamicRustLibrary {
@@ -25,11 +37,22 @@ public:
     
     ~DynamicRustLibrary() {
         if (handle) {
+#ifdef _WIN32
+            FreeLibrary(static_cast<HMODULE>(handle));
+#else
             dlclose(handle);
+#endif
         }
     }
     
     bool load(const std::string& library_path) {
+#ifdef _WIN32
+        handle = LoadLibraryA(library_path.c_str());
+        if (!handle) {
+            std::cerr << "Cannot load library: error " << GetLastError() << std::endl;
+            return false;
+        }
+#else
         handle = dlopen(library_path.c_str(), RTLD_LAZY);
         if (!handle) {
             std::cerr << "Cannot load library: " << dlerror() << std::endl;
@@ -37,6 +60,7 @@ public:
         }
         
         dlerror(); // Clear any existing error
+#endif
         
         // Note: cxx bridge uses C++ name mangling, so symbols are complex
         // This is for demonstration - actual symbol names would need inspection via 'nm'
@@ -50,7 +74,11 @@ public:
     
     static void list_symbols(const std::string& library_path) {
         std::cout << "\nTo inspect library symbols, run:" << std::endl;
+#ifdef _WIN32
+        std::cout << "  dumpbin /exports " << library_path << std::endl;
+#else
         std::cout << "  nm -gU " << library_path << " | c++filt" << std::endl;
+#endif
     }
 };
 
@@ -59,6 +87,7 @@ void print_person_info(const PersonInfo& info, const std::string& name) {
     std::cout << "\n=== Person Information (from Rust analysis) ===" << std::endl;
     std::cout << "Name: " << name << std::endl;
     std::cout << "Name length: " << info.name_length << std::endl;
+    std::cout << "Name characters: " << info.name_graphemes << std::endl;
     std::cout << "City: " << std::string(info.city) << std::endl;
     std::cout << "Is adult: " << (info.is_adult ? "Yes" : "No") << std::endl;
     
@@ -85,7 +114,9 @@ void print_health_analysis(const HealthAnalysis& analysis, const std::string& na
     std::cout << "BMI: " << analysis.bmi << std::endl;
     std::cout << "Risk Score: " << analysis.risk_score << std::endl;
     std::cout << "City Risk Factor: " << analysis.city_risk_factor << std::endl;
+    std::cout << "Extra Risk Factor: " << analysis.extra_risk_factor << std::endl;
     std::cout << "Recommendation: " << std::string(analysis.recommendation) << std::endl;
+    std::cout << "For a German UI: " << std::string(format_analysis(analysis, "de-DE")) << std::endl;
     std::cout << "============================================\n" << std::endl;
 }
 
@@ -99,7 +130,13 @@ int main() {
     // Example 1: Simple greeting
     std::cout << "--- Example 1: String Handling ---" << std::endl;
     size_t length = greet_person("Alice");
-    std::cout << "Returned name length: " << length << "\n" << std::endl;
+    std::cout << "Returned name length: " << length << std::endl;
+
+    // Wide-string UIs (Win32) pass UTF-16 as is, with no UTF-8 detour
+    std::u16string wide_name = u"Zo\u00eb";
+    length = greet_person_utf16(rust::Slice<const uint16_t>(
+        reinterpret_cast<const uint16_t*>(wide_name.data()), wide_name.size()));
+    std::cout << "Returned UTF-16 length: " << length << "\n" << std::endl;
     
     // Example 2: Create C++ objects using our existing C++ classes
     std::cout << "--- Example 2: C++ Objects → Rust Processing ---" << std::endl;
@@ -114,6 +151,8 @@ int main() {
     auto person1 = std::make_unique<Person>(25, 1.75, "Bob Johnson", contact1);
     
     std::cout << "Created C++ Person: " << person1->name() << std::endl;
+    rust::Vec<uint16_t> wide = person_name_utf16(*person1);
+    std::cout << "Name as UTF-16: " << wide.size() << " code units" << std::endl;
     std::cout << "Age: " << person1->age() << ", Height: " << person1->height() << "m" << std::endl;
     std::cout << "City: " << person1->contact().address().city() << std::endl;
     
@@ -168,6 +207,10 @@ int main() {
     std::cout << "--- Example 7: Direct BMI Calculation (Pure Rust) ---" << std::endl;
     double bmi = calculate_bmi(70.0, 1.75);
     std::cout << "BMI for 70kg, 1.75m: " << bmi << std::endl;
+    double bmr = calculate_bmr(70.0, 1.75, 30, Sex::Male);
+    std::cout << "BMR for a 30-year-old man: " << bmr << " kcal/day, "
+              << calculate_tdee(bmr, ActivityLevel::Moderate) << " kcal/day moderately active"
+              << std::endl;
     std::cout << std::endl;
     
     // Example 8: Using C++ methods alongside Rust functions
@@ -182,6 +225,125 @@ int main() {
     auto addr3 = create_address("789 Pine Rd", "San Francisco", "94102");
     std::cout << "Created address: " << addr3->city() << std::endl;
     
+    // Example 10: Batch analysis with progress reporting
+    std::cout << "\n--- Example 10: Batch Analysis with Progress ---" << std::endl;
+    std::vector<PersonSnapshot> cohort;
+    std::vector<double> weights;
+    for (int i = 0; i < 10; ++i) {
+        cohort.push_back(snapshot_person(i % 2 == 0 ? *person1 : *person2));
+        weights.push_back(60.0 + i);
+    }
+    ProgressCallback progress([](size_t processed, size_t total) {
+        std::cout << "  progress: " << processed << "/" << total << std::endl;
+    });
+    rust::Vec<HealthAnalysis> batch = analyze_health_batch_with_progress(
+        rust::Slice<const PersonSnapshot>(cohort.data(), cohort.size()),
+        rust::Slice<const double>(weights.data(), weights.size()),
+        progress,
+        4,
+        0);  // no cancel token
+    std::cout << "Analyzed " << batch.size() << " people in one call" << std::endl;
+    
+    // Example 11: Background job with a cancel token
+    std::cout << "\n--- Example 11: Cancelling Background Work ---" << std::endl;
+    uint64_t token = create_cancel_token();
+    uint64_t job = submit_health_batch(
+        rust::Slice<const PersonSnapshot>(cohort.data(), cohort.size()),
+        rust::Slice<const double>(weights.data(), weights.size()),
+        token);
+    cancel(token);  // e.g. the window that asked for it was closed
+    while (job_status(job) == JobStatus::Queued || job_status(job) == JobStatus::Running) {
+    }
+    std::cout << "Job finished as: "
+              << (job_status(job) == JobStatus::Cancelled ? "Cancelled" : "Completed") << std::endl;
+    try {
+        take_job_results(job);
+    } catch (const rust::Error& e) {
+        std::cout << "take_job_results: " << e.what() << std::endl;
+    }
+    release_cancel_token(token);
+    
+    // Example 12: Telemetry ingest queue with backpressure
+    std::cout << "\n--- Example 12: Bounded Ingest Queue ---" << std::endl;
+    start_ingest(4, std::make_unique<ResultSink>([](const PersonInfo& info) {
+        std::cout << "  ingested: city=" << std::string(info.city)
+                  << " adult=" << (info.is_adult ? "yes" : "no") << std::endl;
+    }));
+    size_t rejected = 0;
+    for (const PersonSnapshot& snapshot : cohort) {
+        if (!enqueue_person(snapshot)) {
+            ++rejected;  // queue full - a real producer would retry or drop
+        }
+    }
+    stop_ingest();
+    std::cout << "Rejected by backpressure: " << rejected << std::endl;
+    
+    // Example 13: Completion callback instead of polling
+    std::cout << "\n--- Example 13: Async Job with Completion Callback ---" << std::endl;
+    std::promise<double> done;
+    std::future<double> risk = done.get_future();
+    submit_analysis_with_callback(*person1, weight1, 0, std::make_unique<JobCallback>(
+        [&done](uint64_t, JobStatus status, const rust::Vec<HealthAnalysis>& results) {
+            // Runs on the Rust callback thread - hand the value back to main
+            done.set_value(status == JobStatus::Completed ? results[0].risk_score : -1.0);
+        }));
+    std::cout << "Risk score delivered by callback: " << risk.get() << std::endl;
+    
+    // Example 14: Observer alerting on high-risk results
+    std::cout << "\n--- Example 14: High-Risk Observer ---" << std::endl;
+    uint64_t observer = register_risk_observer(1.5, std::make_unique<RiskObserver>(
+        [](const HealthAnalysis& analysis) {
+            std::cout << "  ALERT: risk score " << analysis.risk_score << std::endl;
+        }));
+    analyze_health(*person2, 40.0);  // minor and underweight: 1.5 * 1.3
+    unregister_risk_observer(observer);
+    
+    // Example 15: Event bus subscription
+    std::cout << "\n--- Example 15: Event Bus ---" << std::endl;
+    uint64_t subscription = subscribe(EventKind::PersonRegistered, std::make_unique<EventSubscriber>(
+        [](const BusEvent& event) {
+            std::cout << "  Event: person " << event.person_id << " registered" << std::endl;
+        }));
+    uint64_t registered = register_person(snapshot_person(*person1));
+    unsubscribe(subscription);
+    unregister_person(registered);
+    
+    // Example 16: Filtering with a C++ predicate
+    std::cout << "\n--- Example 16: Predicate Filter ---" << std::endl;
+    PersonPredicate adults([](const PersonSnapshot& person) { return person.age >= 18; });
+    rust::Vec<PersonSnapshot> matching = filter_people(
+        rust::Slice<const PersonSnapshot>(cohort.data(), cohort.size()), adults);
+    std::cout << matching.size() << " of " << cohort.size() << " people are adults" << std::endl;
+    
+    // Example 17: Registry entry tied to the C++ object's lifetime
+    std::cout << "\n--- Example 17: Destruction Notification ---" << std::endl;
+    uint64_t dropped_id = 0;
+    {
+        RegisteredPerson scoped(std::make_unique<Person>(40, 1.70, "Dana White", contact1));
+        dropped_id = scoped.id();
+        std::cout << "Registered " << scoped.person().name() << " as " << dropped_id << std::endl;
+    }
+    try {
+        get_registered_person(dropped_id);
+    } catch (const rust::Error& e) {
+        std::cout << "After destruction: " << e.what() << std::endl;
+    }
+    
+    // Example 18: Streaming session (kiosk mode)
+    std::cout << "\n--- Example 18: Streaming Session ---" << std::endl;
+    uint64_t session = open_session();
+    session_push(session, *person1, 75.0);
+    std::promise<void> second_result;
+    set_session_sink(session, std::make_unique<SessionSink>(
+        [&second_result](const SessionResult& result) {
+            std::cout << "  result #" << result.sequence
+                      << ": risk " << result.analysis.risk_score << std::endl;
+            if (result.sequence == 1) second_result.set_value();
+        }));
+    session_push(session, *person2, 55.0);
+    second_result.get_future().wait();
+    close_session(session);
+    
     std::cout << "\n✅ Demo completed successfully!" << std::endl;
     std::cout << "\n╔══════════════════════════════════════════════════════════╗" << std::endl;
     std::cout << "║         Key Integration Patterns Demonstrated            ║" << std::endl;
@@ -267,8 +429,7 @@ int main() {
     std::cout << "   • Separate .dylib/.so/.dll file" << std::endl;
     std::cout << "   • Smaller executable" << std::endl;
     std::cout << "   • Can update library without recompiling" << std::endl;
-    std::cout << "   • Change Cargo.toml: crate-type = [\"cdylib\"]" << std::endl;
-    std::cout << "   • Update CMakeLists.txt to use SHARED IMPORTED" << std::endl;
+    std::cout << "   • Configure with: cmake -DRUST_LIB_LINKAGE=SHARED" << std::endl;
     
     std::cout << "\n3. Runtime Loading (dlopen - advanced):" << std::endl;
     std::cout << "   • Load libraries conditionally" << std::endl;
@@ -278,5 +439,7 @@ int main() {
     std::cout << "\n💡 Recommendation: Use dynamic linking (option 2)" << std::endl;
     std::cout << "   See DYNAMIC_LINKING.md for detailed instructions" << std::endl;
     
+    // Wipe keys and cached personal data before exiting
+    scrub_sensitive_memory();
     return 0;
 }
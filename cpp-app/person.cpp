@@ -22,7 +22,7 @@ std::unique_ptr<Person> create_person(uint32_t age,
 }
 
 // Getter function implementations for FFI
-
+// @generated begin: cargo xtask codegen (cpp-app/person.schema.yaml)
 uint32_t get_person_age(const Person& person) {
     return person.age();
 }
@@ -62,4 +62,4 @@ const std::string& get_address_city(const Address& address) {
 const std::string& get_address_postal_code(const Address& address) {
     return address.postal_code();
 }
-
+// @generated end
@@ -96,6 +96,7 @@ std::unique_ptr<Person> create_person(uint32_t age,
                                      std::shared_ptr<ContactInfo> contact);
 
 // Getter functions for Rust FFI (these bridge the gap)
+// @generated begin: cargo xtask codegen (cpp-app/person.schema.yaml)
 uint32_t get_person_age(const Person& person);
 double get_person_height(const Person& person);
 const std::string& get_person_name(const Person& person);
@@ -108,4 +109,4 @@ const Address& get_contact_address(const ContactInfo& contact);
 const std::string& get_address_street(const Address& address);
 const std::string& get_address_city(const Address& address);
 const std::string& get_address_postal_code(const Address& address);
-
+// @generated end
@@ -0,0 +1,16 @@
+#include "cpp-app/person_snapshot.h"
+#include "rust-lib/src/person_ffi.rs.h"
+
+void fill_person_snapshot(const Person& person, PersonSnapshot& snapshot) {
+    const ContactInfo& contact = get_person_contact(person);
+    const Address& address = get_contact_address(contact);
+
+    snapshot.name = rust::String::lossy(get_person_name(person));
+    snapshot.age = get_person_age(person);
+    snapshot.height_m = get_person_height(person);
+    snapshot.email = rust::String::lossy(get_contact_email(contact));
+    snapshot.phone = rust::String::lossy(get_contact_phone(contact));
+    snapshot.street = rust::String::lossy(get_address_street(address));
+    snapshot.city = rust::String::lossy(get_address_city(address));
+    snapshot.postal_code = rust::String::lossy(get_address_postal_code(address));
+}
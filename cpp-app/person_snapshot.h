@@ -0,0 +1,13 @@
+#pragma once
+
+#include "cpp-app/person.h"
+
+struct PersonSnapshot;  // bridge struct, defined in the cxx-generated person_ffi.rs.h
+
+/// Copy every field of `person` into `snapshot` in one call from Rust
+/// snapshot_person otherwise crosses the bridge once per getter. Strings
+/// that are not valid UTF-8 get U+FFFD, as with to_string_lossy in Rust.
+/// Built with the cxx glue rather than with person.cpp, so it also serves a
+/// prebuilt libperson; a field added to person.schema.yaml is added here by
+/// hand.
+void fill_person_snapshot(const Person& person, PersonSnapshot& snapshot);
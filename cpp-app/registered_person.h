@@ -0,0 +1,48 @@
+#pragma once
+
+#include <cstdint>
+#include <memory>
+#include <utility>
+
+#include "person.h"
+#include "rust-lib/src/person_ffi.rs.h"
+
+/// A Person that stays registered with the Rust registry for its lifetime
+/// Registers a snapshot on construction and calls notify_person_dropped
+/// when destroyed, so the registry never keeps an id whose Person is gone.
+/// Call refresh() after modifying the person to update the stored snapshot.
+class RegisteredPerson {
+private:
+    std::unique_ptr<Person> person_;
+    uint64_t id_;
+
+public:
+    explicit RegisteredPerson(std::unique_ptr<Person> person)
+        : person_(std::move(person)),
+          id_(register_person(snapshot_person(*person_))) {}
+
+    ~RegisteredPerson() {
+        if (id_ != 0) notify_person_dropped(id_);
+    }
+
+    RegisteredPerson(const RegisteredPerson&) = delete;
+    RegisteredPerson& operator=(const RegisteredPerson&) = delete;
+
+    RegisteredPerson(RegisteredPerson&& other) noexcept
+        : person_(std::move(other.person_)), id_(std::exchange(other.id_, 0)) {}
+
+    RegisteredPerson& operator=(RegisteredPerson&& other) noexcept {
+        if (this != &other) {
+            if (id_ != 0) notify_person_dropped(id_);
+            person_ = std::move(other.person_);
+            id_ = std::exchange(other.id_, 0);
+        }
+        return *this;
+    }
+
+    uint64_t id() const { return id_; }
+    Person& person() { return *person_; }
+    const Person& person() const { return *person_; }
+
+    void refresh() { update_registered_person(id_, snapshot_person(*person_)); }
+};
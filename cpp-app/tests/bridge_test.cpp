@@ -0,0 +1,642 @@
+// GoogleTest suite: the Rust library as C++ callers see it
+//
+// Built and run by `cargo xtask test-all` (from rust-lib/) against the
+// release librust_lib.a, together with the Rust test suite. Covers the
+// generated headers, exceptions from Result-returning functions and the
+// callback classes of callbacks.h. test-all builds the library with the
+// `leak-check` feature, so every test must close or unregister whatever it
+// opens.
+
+#include "cpp-app/person.h"
+#include "cpp-app/callbacks.h"
+#include "rust-lib/src/person_ffi.rs.h"
+#include "rust-lib/src/health_ffi.rs.h"
+#include "rust-lib/src/validation_ffi.rs.h"
+#include "rust/cxx.h"
+
+#include <gtest/gtest.h>
+
+#include <algorithm>
+#include <cstdio>
+#include <filesystem>
+#include <fstream>
+#include <iterator>
+#include <memory>
+#include <string>
+#include <vector>
+
+namespace {
+
+std::shared_ptr<ContactInfo> contact(const std::string& email, const std::string& city) {
+    return std::make_shared<ContactInfo>(
+        email, "555-0100", std::make_shared<Address>("1 Main St", city, "02101"));
+}
+
+std::unique_ptr<Person> adult() {
+    return std::make_unique<Person>(30, 1.75, "Alice", contact("alice@example.com", "Boston"));
+}
+
+std::unique_ptr<Person> minor() {
+    return std::make_unique<Person>(12, 1.45, "Tim", contact("tim@example.com", "New York"));
+}
+
+rust::Slice<const PersonSnapshot> slice_of(const std::vector<PersonSnapshot>& people) {
+    return rust::Slice<const PersonSnapshot>(people.data(), people.size());
+}
+
+rust::Slice<const double> slice_of(const std::vector<double>& values) {
+    return rust::Slice<const double>(values.data(), values.size());
+}
+
+// Once every test has run, no job, session or registry id may be left open
+// (always passes unless the library was built with `leak-check`)
+class LeakCheck : public ::testing::Environment {
+public:
+    void TearDown() override { EXPECT_NO_THROW(assert_no_leaked_handles()); }
+};
+
+::testing::Environment* const leak_check = ::testing::AddGlobalTestEnvironment(new LeakCheck);
+
+}  // namespace
+
+TEST(Person, ProcessPerson) {
+    PersonInfo info = process_person(*adult());
+    EXPECT_TRUE(info.is_adult);
+    EXPECT_EQ(info.bmi_category, 1);
+    EXPECT_EQ(info.name_length, 5u);
+    EXPECT_EQ(info.name_graphemes, 5u);
+    EXPECT_EQ(std::string(info.city), "Boston");
+
+    EXPECT_FALSE(process_person(*minor()).is_adult);
+}
+
+TEST(Person, SnapshotCopiesEveryField) {
+    PersonSnapshot snapshot = snapshot_person(*adult());
+    EXPECT_EQ(std::string(snapshot.name), "Alice");
+    EXPECT_EQ(snapshot.age, 30u);
+    EXPECT_DOUBLE_EQ(snapshot.height_m, 1.75);
+    EXPECT_EQ(std::string(snapshot.email), "alice@example.com");
+    EXPECT_EQ(std::string(snapshot.street), "1 Main St");
+    EXPECT_EQ(std::string(snapshot.postal_code), "02101");
+}
+
+TEST(Person, Utf16NameRoundTrips) {
+    Person person(30, 1.75, "Zo\u00eb \U0001F44D", contact("zoe@example.com", "Boston"));
+    rust::Vec<uint16_t> wide = person_name_utf16(person);
+    std::u16string expected = u"Zo\u00eb \U0001F44D";
+    ASSERT_EQ(wide.size(), expected.size());
+    EXPECT_TRUE(std::equal(wide.begin(), wide.end(), expected.begin()));
+
+    EXPECT_EQ(greet_person_utf16(rust::Slice<const uint16_t>(wide.data(), wide.size())), 6u);
+}
+
+TEST(Person, Latin1SnapshotDecodesAccents) {
+    // As a legacy database layer returns it: ISO-8859-1, not UTF-8
+    Person person(30, 1.75, "M\xfcller", contact("mueller@example.com", "Z\xfcrich"));
+    PersonSnapshot snapshot = snapshot_person_with_encoding(person, "ISO-8859-1");
+    EXPECT_EQ(std::string(snapshot.name), "M\u00fcller");
+    EXPECT_EQ(std::string(snapshot.city), "Z\u00fcrich");
+
+    EXPECT_THROW(snapshot_person_with_encoding(person, "UTF-8"), rust::Error);
+    EXPECT_THROW(snapshot_person_with_encoding(person, "EBCDIC"), rust::Error);
+    const uint8_t euro[] = {0x80};
+    EXPECT_EQ(std::string(decode_legacy(rust::Slice<const uint8_t>(euro, 1), "cp1252")), "\u20ac");
+}
+
+TEST(Person, GeocodeAddressFromCityTable) {
+    GeoPoint boston = geocode_address(Address("1 Main St", "Boston", "02101"));
+    EXPECT_NEAR(boston.lat, 42.36, 0.01);
+    EXPECT_NEAR(boston.lon, -71.06, 0.01);
+    EXPECT_THROW(geocode_address(Address("1 Main St", "Atlantis", "00000")), rust::Error);
+}
+
+TEST(Person, TimezoneForAddress) {
+    EXPECT_EQ(std::string(timezone_for_address(Address("1 Main St", "Boston", "02101"))),
+              "America/New_York");
+    EXPECT_EQ(std::string(timezone_for_address(Address("1 Rue", "Paris", "75001"))),
+              "Europe/Paris");
+    EXPECT_THROW(timezone_for_address(Address("1 Main St", "Atlantis", "00000")), rust::Error);
+}
+
+TEST(Person, InferCountryFromContact) {
+    ContactInfo toronto("a@example.ca", "+1 416 555 0100",
+                        std::make_shared<Address>("1 King St", "Toronto", "M5V 3L9"));
+    CountryGuess guess = infer_country(toronto);
+    EXPECT_EQ(std::string(guess.code), "CA");
+    EXPECT_DOUBLE_EQ(guess.confidence, 1.0);
+
+    CountryGuess weak = infer_country(*contact("alice@example.com", "Boston"));
+    EXPECT_EQ(std::string(weak.code), "US");
+    EXPECT_DOUBLE_EQ(weak.confidence, 0.2);
+}
+
+TEST(Person, PostalCodeRegions) {
+    EXPECT_EQ(std::string(postal_region("02101-1234", "US")), "MA");
+    EXPECT_EQ(std::string(postal_region("M5V 3L9", "ca")), "ON");
+    EXPECT_TRUE(postal_region("02101", "DE").empty());
+
+    std::vector<PersonSnapshot> people = {snapshot_person(*adult()), snapshot_person(*minor())};
+    people[0].postal_code = "60601";
+    people[1].postal_code = "60602";
+    rust::Vec<RegionCount> counts = region_counts(slice_of(people), "US");
+    ASSERT_EQ(counts.size(), 1u);
+    EXPECT_EQ(std::string(counts[0].region), "IL");
+    EXPECT_EQ(counts[0].people, 2u);
+}
+
+TEST(Person, DistanceBetweenAddresses) {
+    Address boston("1 Main St", "Boston", "02101");
+    EXPECT_NEAR(distance_km(boston, Address("5 Broadway", "New York", "10004")), 306.1, 0.5);
+    EXPECT_DOUBLE_EQ(distance_km(boston, boston), 0.0);
+    EXPECT_THROW(distance_km(boston, Address("1 Main St", "Atlantis", "00000")), rust::Error);
+}
+
+TEST(Person, AddressSimilarity) {
+    Address home("12 Main St.", "Boston", "02101");
+    EXPECT_NEAR(address_similarity(home, Address("12 MAIN STREET", "boston", "02101-1234")),
+                1.0, 1e-9);
+    EXPECT_NEAR(address_similarity(home, Address("14 Main St", "Boston", "02101")), 0.7, 1e-9);
+    EXPECT_DOUBLE_EQ(address_similarity(home, Address("1 Elm Rd", "Chicago", "60601")), 0.0);
+}
+
+TEST(Person, ServiceAreas) {
+    load_service_areas(R"({"type": "FeatureCollection", "features": [
+        {"type": "Feature", "id": "greater-boston", "properties": {},
+         "geometry": {"type": "Polygon", "coordinates":
+           [[[-71.3, 42.2], [-70.9, 42.2], [-70.9, 42.5], [-71.3, 42.5], [-71.3, 42.2]]]}}]})");
+    EXPECT_TRUE(is_in_service_area(Address("1 Main St", "Boston", "02101"), "greater-boston"));
+    EXPECT_FALSE(is_in_service_area(Address("5 Broadway", "New York", "10004"), "greater-boston"));
+    EXPECT_THROW(is_in_service_area(Address("1 Main St", "Boston", "02101"), "nowhere"), rust::Error);
+    EXPECT_THROW(load_service_areas(R"({"type": "Feature"})"), rust::Error);
+}
+
+TEST(Person, RedactSnapshot) {
+    PersonSnapshot person = snapshot_person(*adult());
+    PersonSnapshot masked = redact_snapshot(person, RedactionPolicy::Mask);
+    EXPECT_EQ(std::string(masked.name), "A.");
+    EXPECT_EQ(std::string(masked.email), "a***@example.com");
+    EXPECT_EQ(std::string(masked.phone), "***-**00");
+    EXPECT_EQ(std::string(masked.city), "Boston");
+    EXPECT_EQ(std::string(redact_snapshot(person, RedactionPolicy::Remove).name), "[redacted]");
+    EXPECT_EQ(std::string(redact_snapshot(person, RedactionPolicy::Keep).name), "Alice");
+
+    set_log_redaction(RedactionPolicy::Keep);
+    EXPECT_EQ(greet_person("Alice"), 5u);
+    set_log_redaction(RedactionPolicy::Mask);
+}
+
+TEST(Person, AnonymousId) {
+    std::string key = "0123456789abcdef0123456789abcdef";
+    EXPECT_THROW(set_anonymization_key(rust::Slice<const uint8_t>(
+                     reinterpret_cast<const uint8_t*>(key.data()), 8)),
+                 rust::Error);
+    set_anonymization_key(rust::Slice<const uint8_t>(
+        reinterpret_cast<const uint8_t*>(key.data()), key.size()));
+
+    std::string id(anonymous_id(*adult()));
+    EXPECT_EQ(id.size(), 64u);
+    EXPECT_EQ(std::string(anonymous_snapshot_id(snapshot_person(*adult()))), id);
+    EXPECT_NE(std::string(anonymous_id(*minor())), id);
+
+    // Scrubbing forgets the key until it is set again
+    scrub_sensitive_memory();
+    EXPECT_THROW(anonymous_id(*adult()), rust::Error);
+    set_anonymization_key(rust::Slice<const uint8_t>(
+        reinterpret_cast<const uint8_t*>(key.data()), key.size()));
+    EXPECT_EQ(std::string(anonymous_id(*adult())), id);
+}
+
+TEST(Person, AuditTrail) {
+    drain_audit_log();
+    snapshot_person(*adult());
+    EXPECT_EQ(drain_audit_log().size(), 0u);
+
+    set_audit_enabled(true);
+    set_audit_caller("gtest");
+    snapshot_person(*adult());
+    validate_contact(adult()->contact());
+    analyze_health(*adult(), 70.0);
+    set_audit_enabled(false);
+
+    rust::Vec<AuditEntry> entries = drain_audit_log();
+    ASSERT_EQ(entries.size(), 2u);
+    EXPECT_EQ(std::string(entries[0].operation), "snapshot_person");
+    EXPECT_EQ(std::string(entries[0].caller), "gtest");
+    EXPECT_EQ(entries[0].fields.size(), 8u);
+    EXPECT_EQ(std::string(entries[1].operation), "validate_contact");
+    EXPECT_EQ(std::string(entries[1].fields[0]), "email");
+    EXPECT_GE(entries[1].timestamp_ms, entries[0].timestamp_ms);
+    set_audit_caller("");
+}
+
+TEST(Health, AnalyzeHealth) {
+    HealthAnalysis healthy = analyze_health(*adult(), 70.0);
+    EXPECT_NEAR(healthy.bmi, 22.86, 0.01);
+    EXPECT_DOUBLE_EQ(healthy.risk_score, 1.0);
+    EXPECT_DOUBLE_EQ(healthy.city_risk_factor, 1.0);
+
+    HealthAnalysis risky = analyze_health(*minor(), 40.0);
+    EXPECT_DOUBLE_EQ(risky.city_risk_factor, 1.2);
+    EXPECT_GT(risky.risk_score, 1.5);
+}
+
+TEST(Health, EnergyExpenditure) {
+    // Mifflin-St Jeor: 10 * 70 + 6.25 * 175 - 5 * 30, then +5 or -161
+    EXPECT_DOUBLE_EQ(calculate_bmr(70.0, 1.75, 30, Sex::Male), 1648.75);
+    EXPECT_DOUBLE_EQ(calculate_bmr(70.0, 1.75, 30, Sex::Female), 1482.75);
+    EXPECT_DOUBLE_EQ(calculate_bmr(70.0, 0.0, 30, Sex::Male), 0.0);
+    EXPECT_DOUBLE_EQ(calculate_tdee(1000.0, ActivityLevel::Sedentary), 1200.0);
+    EXPECT_DOUBLE_EQ(calculate_tdee(1000.0, ActivityLevel::VeryActive), 1900.0);
+}
+
+TEST(Health, FormatAnalysisUsesLocaleSeparators) {
+    HealthAnalysis healthy = analyze_health(*adult(), 70.0);
+    EXPECT_EQ(std::string(format_analysis(healthy, "en-US")), "BMI 22.9 kg/m² · risk 1.00");
+    EXPECT_EQ(std::string(format_analysis(healthy, "de_DE.UTF-8")), "BMI 22,9 kg/m² · risk 1,00");
+}
+
+TEST(Health, LocalizedRecommendationPluralizes) {
+    // Over 65, underweight and in New York: three factors
+    Person person(70, 1.90, "Bob", contact("bob@example.com", "New York"));
+    HealthAnalysis risky = analyze_health(person, 55.0);
+    EXPECT_EQ(risky.risk_factors, 3u);
+    EXPECT_EQ(std::string(risky.recommendation),
+              "Elevated risk factors. Recommend consultation with healthcare provider. "
+              "3 risk factors found.");
+    // Ends "... 3 фактора риска.", the Russian plural for 2 to 4
+    std::string russian(localized_recommendation(risky, "ru-RU"));
+    EXPECT_NE(russian.find("3 \u0444\u0430\u043a\u0442\u043e\u0440\u0430 "), std::string::npos);
+    EXPECT_EQ(std::string(localized_recommendation(risky, "ja")), std::string(risky.recommendation));
+}
+
+TEST(Health, ReportIsolatesRightToLeftNames) {
+    PersonSnapshot person = snapshot_person(*adult());
+    person.name = "\u05d3\u05d5\u05d3";
+    std::vector<PersonSnapshot> people = {person};
+    std::vector<HealthAnalysis> analyses = {analyze_health(*adult(), 70.0)};
+    rust::Slice<const HealthAnalysis> results(analyses.data(), analyses.size());
+
+    EXPECT_EQ(std::string(format_report_bidi_safe(slice_of(people), results, "en")),
+              "\u2068\u05d3\u05d5\u05d3\u2069 (\u2068Boston\u2069): BMI 22.9 kg/m\u00b2 \u00b7 risk 1.00\n");
+    EXPECT_THROW(format_report_bidi_safe(slice_of(people), {}, "en"), rust::Error);
+}
+
+TEST(Health, InvalidConfigKeepsTheModel) {
+    double before = analyze_health(*adult(), 70.0).risk_score;
+    EXPECT_THROW(load_config("/nonexistent/rust_lib.toml"), rust::Error);
+    ConfigReport report = load_config_with_report("/nonexistent/rust_lib.toml");
+    EXPECT_FALSE(report.valid);
+    ASSERT_EQ(report.issues.size(), 1u);
+    EXPECT_EQ(std::string(report.issues[0].file), "/nonexistent/rust_lib.toml");
+    EXPECT_EQ(analyze_health(*adult(), 70.0).risk_score, before);
+}
+
+TEST(Health, DefaultConfigIsATomlFile) {
+    std::string dumped(dump_default_config());
+    EXPECT_EQ(dumped.rfind("# rust-lib ", 0), 0u);
+    EXPECT_NE(dumped.find("[city_risk]"), std::string::npos);
+}
+
+TEST(Health, RepeatedAnalysisIsCached) {
+    Person person(41, 1.68, "Cache Test", contact("cache@example.com", "Boston"));
+    CacheStats before = cache_stats();
+    HealthAnalysis first = analyze_health(person, 66.0);
+    HealthAnalysis again = analyze_health(person, 66.0);
+    CacheStats after = cache_stats();
+
+    EXPECT_DOUBLE_EQ(first.risk_score, again.risk_score);
+    EXPECT_EQ(std::string(first.recommendation), std::string(again.recommendation));
+    EXPECT_GE(after.hits, before.hits + 1);
+    EXPECT_EQ(std::string(get_option("cache_size")), std::to_string(after.capacity));
+    clear_analysis_cache();
+    EXPECT_EQ(cache_stats().entries, 0u);
+}
+
+TEST(Health, LocaleOptionTranslatesRecommendations) {
+    ASSERT_EQ(std::string(get_option("locale")), "en");
+    set_option("locale", "de-CH");
+    std::string german(analyze_health(*adult(), 70.0).recommendation);
+    set_option("locale", "en");
+    EXPECT_NE(german, std::string(analyze_health(*adult(), 70.0).recommendation));
+    EXPECT_THROW(set_option("no_such_option", "1"), rust::Error);
+    EXPECT_EQ(std::string(get_option("no_such_option")), "");
+}
+
+TEST(Health, BatchMatchesSingleAnalysis) {
+    std::vector<PersonSnapshot> people = {snapshot_person(*adult()), snapshot_person(*minor())};
+    std::vector<double> weights = {70.0, 40.0};
+
+    rust::Vec<HealthAnalysis> results = analyze_health_batch(slice_of(people), slice_of(weights), 0);
+    ASSERT_EQ(results.size(), 2u);
+    EXPECT_DOUBLE_EQ(results[0].risk_score, analyze_health(*adult(), 70.0).risk_score);
+    EXPECT_DOUBLE_EQ(results[1].risk_score, analyze_health(*minor(), 40.0).risk_score);
+}
+
+TEST(Health, PooledBatchLooksUpItsStrings) {
+    std::vector<PersonSnapshot> people = {snapshot_person(*adult()), snapshot_person(*adult())};
+    std::vector<double> weights = {70.0, 70.0};
+
+    PooledBatch batch = analyze_health_batch_pooled(slice_of(people), slice_of(weights), 0);
+    ASSERT_EQ(batch.results.size(), 2u);
+    EXPECT_EQ(batch.results[0].recommendation, batch.results[1].recommendation);
+    EXPECT_EQ(std::string(get_string(batch.table_id, batch.results[0].recommendation)),
+              std::string(analyze_health(*adult(), 70.0).recommendation));
+    EXPECT_EQ(std::string(get_string(batch.table_id, batch.results[1].city)),
+              std::string(people[1].city));
+    EXPECT_THROW(get_string(batch.table_id, 99), rust::Error);
+    EXPECT_TRUE(release_string_table(batch.table_id));
+}
+
+TEST(Health, BatchContextServesRepeatedBatches) {
+    BatchCapacity capacity{};
+    capacity.people = 2;
+    uint64_t context = create_batch_context(capacity);
+    std::vector<PersonSnapshot> people = {snapshot_person(*adult()), snapshot_person(*minor())};
+    std::vector<double> weights = {70.0, 40.0};
+
+    for (int round = 0; round < 2; ++round) {
+        BatchAnalysis batch = analyze_health_batch_in_context(
+            context, slice_of(people), slice_of(weights), AnalysisOptions{}, 0);
+        ASSERT_EQ(batch.results.size(), 2u);
+        EXPECT_DOUBLE_EQ(batch.results[1].risk_score, analyze_health(*minor(), 40.0).risk_score);
+
+        PooledBatch pooled =
+            analyze_health_batch_pooled_in_context(context, slice_of(people), slice_of(weights), 0);
+        EXPECT_EQ(std::string(get_string(pooled.table_id, pooled.results[0].city)),
+                  std::string(people[0].city));
+        EXPECT_TRUE(release_string_table(pooled.table_id));
+    }
+    EXPECT_TRUE(release_batch_context(context));
+    EXPECT_THROW(analyze_health_batch_in_context(context, slice_of(people), slice_of(weights),
+                                                 AnalysisOptions{}, 0),
+                 rust::Error);
+}
+
+TEST(Health, CityRiskTableFromJson) {
+    // Only a table equal to the default is set, as other tests analyze
+    set_city_risk_table(R"({"New York": 1.2, "Los Angeles": 1.1})");
+    EXPECT_DOUBLE_EQ(analyze_health(*adult(), 70.0).city_risk_factor, 1.0);
+    EXPECT_THROW(set_city_risk_table(R"({"New York": 0})"), rust::Error);
+    EXPECT_THROW(set_city_risk_table("/nonexistent/city_risk.csv"), rust::Error);
+}
+
+TEST(Health, MemoryReportCountsUnreleasedTables) {
+    std::vector<PersonSnapshot> people = {snapshot_person(*adult())};
+    std::vector<double> weights = {70.0};
+    PooledBatch batch = analyze_health_batch_pooled(slice_of(people), slice_of(weights), 0);
+
+    MemoryReport report = memory_report();
+    EXPECT_GT(report.string_table_bytes, 0u);
+    EXPECT_GE(report.total_bytes, report.string_table_bytes + report.registry_bytes);
+    EXPECT_TRUE(release_string_table(batch.table_id));
+}
+
+TEST(Health, BatchWithMismatchedWeightsThrows) {
+    std::vector<PersonSnapshot> people = {snapshot_person(*adult())};
+    std::vector<double> weights;
+    EXPECT_THROW(analyze_health_batch(slice_of(people), slice_of(weights), 0), rust::Error);
+}
+
+TEST(Health, ProgressIsReportedAtTheEnd) {
+    std::vector<PersonSnapshot> people(3, snapshot_person(*adult()));
+    std::vector<double> weights(3, 70.0);
+    size_t last_processed = 0;
+    ProgressCallback progress([&](size_t processed, size_t) { last_processed = processed; });
+
+    analyze_health_batch_with_progress(slice_of(people), slice_of(weights), progress, 1, 0);
+    EXPECT_EQ(last_processed, 3u);
+}
+
+TEST(Health, OptionsTakePoundsAndExplainScores) {
+    AnalysisOptions options{};
+    options.units = Units::Imperial;
+    options.include_explanations = true;
+
+    ExplainedAnalysis result = analyze_health_with_options(*adult(), 154.0, options);
+    EXPECT_NEAR(result.analysis.bmi, analyze_health(*adult(), 154.0 * 0.45359237).bmi, 1e-9);
+    EXPECT_NE(std::string(result.explanation).find("risk "), std::string::npos);
+
+    std::vector<PersonSnapshot> people = {snapshot_person(*adult())};
+    std::vector<double> weights = {154.0};
+    BatchAnalysis batch = analyze_health_batch_with_options(slice_of(people), slice_of(weights),
+                                                            options, 0);
+    ASSERT_EQ(batch.explanations.size(), 1u);
+    EXPECT_EQ(std::string(batch.explanations[0]), std::string(result.explanation));
+
+    options.model = "no-such-model";
+    EXPECT_THROW(analyze_health_with_options(*adult(), 154.0, options), rust::Error);
+}
+
+TEST(Health, LazyRecommendationRendersFromItsId) {
+    AnalysisOptions options{};
+    options.lazy_recommendations = true;
+
+    ExplainedAnalysis lazy = analyze_health_with_options(*adult(), 70.0, options);
+    HealthAnalysis rendered = analyze_health(*adult(), 70.0);
+    EXPECT_TRUE(lazy.analysis.recommendation.empty());
+    EXPECT_EQ(lazy.analysis.recommendation_id, rendered.recommendation_id);
+    EXPECT_EQ(std::string(render_recommendation(lazy.analysis.recommendation_id, "en")),
+              std::string(localized_recommendation(rendered, "en")));
+    EXPECT_THROW(render_recommendation(3, "en"), rust::Error);
+}
+
+TEST(Health, RiskObserverSeesHighRisk) {
+    int alerts = 0;
+    uint64_t observer = register_risk_observer(
+        1.5, std::make_unique<RiskObserver>([&](const HealthAnalysis&) { ++alerts; }));
+    analyze_health(*adult(), 70.0);
+    analyze_health(*minor(), 40.0);
+    EXPECT_TRUE(unregister_risk_observer(observer));
+    EXPECT_EQ(alerts, 1);
+}
+
+TEST(Validation, ValidateContact) {
+    EXPECT_TRUE(validate_contact(adult()->contact()));
+    EXPECT_FALSE(validate_contact(*contact("not-an-email", "Boston")));
+}
+
+TEST(Validation, FullReportListsEveryProblem) {
+    Person person(30, 1.75, "Alice", contact("not-an-email", ""));
+    ValidationReport report = validate_person_full(person);
+    EXPECT_FALSE(report.valid);
+    EXPECT_GE(report.issues.size(), 2u);
+}
+
+TEST(Validation, LenientProfileDowngradesContactProblems) {
+    Person person(30, 1.75, "Alice", contact("not-an-email", "Boston"));
+    set_validation_profile("lenient");
+    ValidationReport report = validate_person_full(person);
+    set_validation_profile("strict");
+    EXPECT_TRUE(report.valid);
+    ASSERT_EQ(report.issues.size(), 1u);
+    EXPECT_EQ(report.issues[0].severity, Severity::Warning);
+    EXPECT_FALSE(validate_person_full(person).valid);
+    EXPECT_THROW(set_validation_profile("kiosk"), rust::Error);
+}
+
+TEST(Registry, RegisterLookupUnregister) {
+    uint64_t id = register_person(snapshot_person(*adult()));
+    EXPECT_EQ(std::string(get_registered_person(id).name), "Alice");
+    EXPECT_TRUE(unregister_person(id));
+    EXPECT_FALSE(unregister_person(id));
+    EXPECT_THROW(get_registered_person(id), rust::Error);
+}
+
+TEST(Registry, GroupHouseholds) {
+    PersonSnapshot first = snapshot_person(*adult());
+    first.street = "12 Household Ln.";
+    PersonSnapshot second = snapshot_person(*minor());
+    second.street = "12 HOUSEHOLD LANE";
+    second.city = "BOSTON";
+    uint64_t ids[] = {register_person(first), register_person(second)};
+
+    bool found = false;
+    for (const Household& household : group_households()) {
+        if (std::string(household.address_key).find("household lane") == std::string::npos) {
+            continue;
+        }
+        found = true;
+        ASSERT_EQ(household.member_ids.size(), 2u);
+        EXPECT_EQ(household.member_ids[0], ids[0]);
+        EXPECT_EQ(household.member_ids[1], ids[1]);
+    }
+    EXPECT_TRUE(found);
+    EXPECT_TRUE(unregister_person(ids[0]));
+    EXPECT_TRUE(unregister_person(ids[1]));
+}
+
+TEST(Registry, ExportAndEraseSubject) {
+    uint64_t id = register_person(snapshot_person(*adult()));
+    std::string data(export_subject_data(id));
+    EXPECT_NE(data.find("\"status\":\"registered\""), std::string::npos);
+    EXPECT_NE(data.find("alice@example.com"), std::string::npos);
+
+    EraseReport report = erase_subject(id);
+    EXPECT_TRUE(report.registry_entry);
+    EXPECT_FALSE(report.destroyed_record);
+    EXPECT_THROW(get_registered_person(id), rust::Error);
+    data = std::string(export_subject_data(id));
+    EXPECT_NE(data.find("\"status\":\"unknown\""), std::string::npos);
+    EXPECT_EQ(data.find("alice@example.com"), std::string::npos);
+    EXPECT_FALSE(erase_subject(id).registry_entry);
+}
+
+TEST(Registry, RetentionPolicy) {
+    uint64_t id = register_person(snapshot_person(*adult()));
+    RetentionReport preview = preview_retention(30);
+    EXPECT_EQ(std::count(preview.expired_persons.begin(), preview.expired_persons.end(), id), 0);
+    EXPECT_EQ(preview_retention(0).expired_persons.size(), 0u);
+
+    set_retention_policy(30);
+    RetentionReport report = enforce_retention();
+    EXPECT_EQ(std::count(report.expired_persons.begin(), report.expired_persons.end(), id), 0);
+    EXPECT_NO_THROW(get_registered_person(id));
+    set_retention_policy(0);
+    EXPECT_EQ(enforce_retention().cached_analyses, 0u);
+    EXPECT_TRUE(unregister_person(id));
+}
+
+TEST(Registry, ConsentFlags) {
+    uint64_t id = register_person(snapshot_person(*adult()));
+    EXPECT_FALSE(get_consent(id).analysis);
+    EXPECT_THROW(analyze_registered(id, 70.0), rust::Error);
+    uint64_t ids[] = {id};
+    EXPECT_THROW(export_registered(rust::Slice<const uint64_t>(ids, 1), "/nonexistent/out.csv",
+                                   ExportFormat::Csv),
+                 rust::Error);
+
+    set_consent(id, ConsentFlags{true, false, false});
+    EXPECT_TRUE(get_consent(id).analysis);
+    EXPECT_FALSE(get_consent(id).sharing);
+    EXPECT_EQ(analyze_registered(id, 70.0).bmi, analyze_health(*adult(), 70.0).bmi);
+
+    EXPECT_TRUE(unregister_person(id));
+    EXPECT_FALSE(get_consent(id).analysis);
+    EXPECT_THROW(set_consent(id, ConsentFlags{true, true, true}), rust::Error);
+}
+
+TEST(Registry, UniqueRegistrationIgnoresEncoding) {
+    PersonSnapshot composed = snapshot_person(*adult());
+    composed.name = "Jos\u00e9 Unique";
+    PersonSnapshot decomposed = composed;
+    decomposed.name = "Jose\u0301 Unique";
+    EXPECT_TRUE(person_equals(composed, decomposed, false));
+    EXPECT_EQ(hash_person(composed, false), hash_person(decomposed, false));
+
+    uint64_t id = register_person_unique(composed, false);
+    EXPECT_EQ(register_person_unique(decomposed, false), id);
+    EXPECT_TRUE(unregister_person(id));
+}
+
+TEST(Registry, UniqueRegistrationAcrossScripts) {
+    EXPECT_EQ(std::string(transliterate_name("Ελένη Παπαδοπούλου")), "Eleni Papadopoulou");
+
+    PersonSnapshot latin = snapshot_person(*adult());
+    latin.name = "Ivan Unikalnyi";
+    PersonSnapshot cyrillic = latin;
+    cyrillic.name = "Иван Уникальный";
+    MatchOptions options{false, true};
+    EXPECT_FALSE(person_equals(latin, cyrillic, true));
+    EXPECT_TRUE(person_equals_with_options(latin, cyrillic, options));
+    EXPECT_EQ(hash_person_with_options(latin, options), hash_person_with_options(cyrillic, options));
+
+    uint64_t id = register_person_unique_with_options(latin, options);
+    EXPECT_EQ(register_person_unique_with_options(cyrillic, options), id);
+    EXPECT_TRUE(unregister_person(id));
+}
+
+TEST(Export, EncryptedFieldsDecryptInTheirRow) {
+    std::vector<uint8_t> key(32, 7);
+    EXPECT_THROW(set_export_key(rust::Slice<const uint8_t>(key.data(), 16)), rust::Error);
+    set_export_key(rust::Slice<const uint8_t>(key.data(), key.size()));
+    std::vector<PersonSnapshot> people = {snapshot_person(*adult()), snapshot_person(*minor())};
+    std::string path =
+        (std::filesystem::temp_directory_path() / "rust_lib_bridge_test_encrypted.csv").string();
+    EXPECT_EQ(export_people(slice_of(people), path, ExportFormat::Csv), 2u);
+
+    // The header, then Alice in row 0 and Tim in row 1
+    std::ifstream file(path);
+    std::string line;
+    for (int i = 0; i < 3; ++i) {
+        std::getline(file, line);
+    }
+    EXPECT_EQ(line.find("tim@example.com"), std::string::npos);
+    // An encrypted value has no comma to quote; the email is column 3
+    std::string email = line;
+    for (int column = 0; column < 3; ++column) {
+        email = email.substr(email.find(',') + 1);
+    }
+    email = email.substr(0, email.find(','));
+    EXPECT_EQ(std::string(decrypt_export_field("email", 1, email)), "tim@example.com");
+    EXPECT_THROW(decrypt_export_field("email", 0, email), rust::Error);
+    EXPECT_THROW(decrypt_export_field("phone", 1, email), rust::Error);
+
+    clear_export_key();
+    EXPECT_THROW(decrypt_export_field("email", 1, email), rust::Error);
+    std::remove(path.c_str());
+}
+
+TEST(Export, ParquetFileIsComplete) {
+    std::vector<PersonSnapshot> people = {snapshot_person(*adult()), snapshot_person(*minor())};
+    std::string path =
+        (std::filesystem::temp_directory_path() / "rust_lib_bridge_test.parquet").string();
+    EXPECT_EQ(export_people(slice_of(people), path, ExportFormat::Parquet), 2u);
+
+    // A Parquet file starts and ends with its magic number
+    std::ifstream file(path, std::ios::binary);
+    std::string bytes((std::istreambuf_iterator<char>(file)), std::istreambuf_iterator<char>());
+    ASSERT_GT(bytes.size(), 8u);
+    EXPECT_EQ(bytes.substr(0, 4), "PAR1");
+    EXPECT_EQ(bytes.substr(bytes.size() - 4), "PAR1");
+    EXPECT_NE(bytes.find("alice@example.com"), std::string::npos);
+    file.close();
+    std::remove(path.c_str());
+}
+
+TEST(Filter, PredicateRunsInCpp) {
+    std::vector<PersonSnapshot> people = {snapshot_person(*adult()), snapshot_person(*minor())};
+    PersonPredicate adults([](const PersonSnapshot& person) { return person.age >= 18; });
+    rust::Vec<PersonSnapshot> matching = filter_people(slice_of(people), adults);
+    ASSERT_EQ(matching.size(), 1u);
+    EXPECT_EQ(std::string(matching[0].name), "Alice");
+}
@@ -0,0 +1,108 @@
+// C suite: the `c-abi` functions as a C-only host sees them
+//
+// Built and run by `cargo xtask test-all` (from rust-lib/) with a C
+// compiler against rust-lib/include/rust_lib_c.h and the release
+// librust_lib.a, so a header that drifts from src/c_abi.rs fails here.
+
+#include "rust-lib/include/rust_lib_c.h"
+
+#include <math.h>
+#include <stdio.h>
+#include <string.h>
+
+static int failures = 0;
+
+#define CHECK(condition)                                                                  \
+    do {                                                                                  \
+        if (!(condition)) {                                                               \
+            fprintf(stderr, "%s:%d: CHECK failed: %s\n", __FILE__, __LINE__, #condition); \
+            failures++;                                                                   \
+        }                                                                                 \
+    } while (0)
+
+static RustLibPersonSnapshot person(uint32_t age, const char *city) {
+    RustLibPersonSnapshot snapshot = {0};
+    snapshot.name = "Alice";
+    snapshot.age = age;
+    snapshot.height_m = 1.75;
+    snapshot.city = city;
+    return snapshot;
+}
+
+static void test_bmi(void) {
+    CHECK(fabs(rust_lib_calculate_bmi(70.0, 1.75) - 22.857) < 0.001);
+    CHECK(rust_lib_calculate_bmi(70.0, 0.0) == 0.0);
+}
+
+static void test_analyze_snapshot(void) {
+    RustLibPersonSnapshot senior = person(70, "New York");
+    RustLibHealthAnalysis analysis;
+    CHECK(rust_lib_analyze_snapshot(&senior, 70.0, &analysis) == RUST_LIB_STATUS_OK);
+    CHECK(analysis.city_risk_factor == 1.2);
+    CHECK(analysis.risk_factors == 2);
+    CHECK(fabs(analysis.risk_score - 1.5 * 1.2 * analysis.extra_risk_factor) < 1e-9);
+
+    CHECK(rust_lib_analyze_snapshot(NULL, 70.0, &analysis) == RUST_LIB_STATUS_NULL_ARGUMENT);
+    senior.name = "M\xfcller";
+    CHECK(rust_lib_analyze_snapshot(&senior, 70.0, &analysis) == RUST_LIB_STATUS_INVALID_UTF8);
+}
+
+static void test_batch(void) {
+    RustLibPersonSnapshot people[] = {person(30, "Boston"), person(30, "Los Angeles")};
+    double weights[] = {70.0, 90.0};
+    RustLibHealthAnalysis analyses[2];
+    CHECK(rust_lib_analyze_snapshot_batch(people, weights, 2, analyses) == RUST_LIB_STATUS_OK);
+    CHECK(analyses[0].bmi == rust_lib_calculate_bmi(70.0, 1.75));
+    CHECK(analyses[1].city_risk_factor == 1.1);
+    CHECK(rust_lib_analyze_snapshot_batch(NULL, NULL, 0, NULL) == RUST_LIB_STATUS_OK);
+}
+
+static void test_recommendation(void) {
+    RustLibHealthAnalysis analysis = {0};
+    analysis.risk_score = 1.0;
+    char text[256];
+    size_t length = 0;
+    CHECK(rust_lib_recommendation(&analysis, "en", text, sizeof text, &length) ==
+          RUST_LIB_STATUS_OK);
+    CHECK(strcmp(text, "Excellent health profile. Maintain current lifestyle. "
+                       "No risk factors found.") == 0);
+    CHECK(length == strlen(text));
+
+    // Too small: cut and NUL-terminated, with the full length reported
+    char short_text[10];
+    CHECK(rust_lib_recommendation(&analysis, NULL, short_text, sizeof short_text, &length) ==
+          RUST_LIB_STATUS_BUFFER_TOO_SMALL);
+    CHECK(strcmp(short_text, "Excellent") == 0);
+    CHECK(length == strlen(text));
+}
+
+static void test_validators(void) {
+    RustLibContact contact = {"a@example.com", "555-0100", "Boston", "021"};
+    RustLibContactValidation checked;
+    CHECK(rust_lib_validate_contact(&contact, &checked) == RUST_LIB_STATUS_OK);
+    CHECK(!checked.valid && checked.email_valid && checked.phone_valid && checked.city_valid);
+    CHECK(!checked.postal_valid);
+    contact.postal_code = "02101";
+    CHECK(rust_lib_validate_contact(&contact, &checked) == RUST_LIB_STATUS_OK && checked.valid);
+
+    RustLibPersonSnapshot nobody = person(200, NULL);
+    RustLibPersonValidation fields;
+    CHECK(rust_lib_validate_person(&nobody, &fields) == RUST_LIB_STATUS_OK);
+    CHECK(!fields.valid && fields.name_valid && fields.height_valid);
+    CHECK(!fields.age_valid && !fields.city_valid && !fields.email_valid);
+    CHECK(rust_lib_validate_person(NULL, &fields) == RUST_LIB_STATUS_NULL_ARGUMENT);
+}
+
+int main(void) {
+    test_bmi();
+    test_analyze_snapshot();
+    test_batch();
+    test_recommendation();
+    test_validators();
+    if (failures > 0) {
+        fprintf(stderr, "%d checks failed\n", failures);
+        return 1;
+    }
+    printf("c_abi_test: all checks passed\n");
+    return 0;
+}
@@ -0,0 +1,35 @@
+// swift-tools-version:5.7
+//
+// The health engine for Swift: the `c-abi` functions of rust_lib, imported
+// through include/module.modulemap and wrapped in swift/Sources/RustLib.
+// Build the library first, then point the linker at it:
+//
+//   cargo build --release --features c-abi
+//   swift test -Xlinker -Ltarget/release
+import PackageDescription
+
+let package = Package(
+    name: "RustLib",
+    platforms: [.iOS(.v13), .macOS(.v11)],
+    products: [
+        .library(name: "RustLib", targets: ["RustLib"]),
+    ],
+    targets: [
+        .systemLibrary(name: "RustLibC", path: "include"),
+        .target(
+            name: "RustLib",
+            dependencies: ["RustLibC"],
+            path: "swift/Sources/RustLib",
+            // The static library contains the cxx glue, which needs the C++ runtime
+            linkerSettings: [
+                .linkedLibrary("c++", .when(platforms: [.iOS, .macOS])),
+                .linkedLibrary("stdc++", .when(platforms: [.linux])),
+            ]
+        ),
+        .testTarget(
+            name: "RustLibTests",
+            dependencies: ["RustLib"],
+            path: "swift/Tests/RustLibTests"
+        ),
+    ]
+)
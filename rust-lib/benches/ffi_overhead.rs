@@ -0,0 +1,145 @@
+//! Cost of crossing the bridge: `cargo bench [-- FILTER]`
+//!
+//! - getter: one C++ getter call, the unit every other number is made of
+//! - process_person (six getter calls) against snapshot_person (one
+//!   fill_person_snapshot call that copies every string), the price of the
+//!   single-snapshot approach
+//! - analyze_health on a C++ Person against snapshot + one-item batch
+//! - 1000 people: per-item analyze_health against one analyze_health_batch
+//!   over snapshots taken beforehand
+//! - 10 pooled batches of 100 people, each on fresh buffers against all on
+//!   the buffers of one batch context
+//!
+//! The timing loop is a small stand-in with criterion's shape (warm-up,
+//! sampled batches, median per iteration), so the bench needs no extra
+//! dependency. People come from the integration test fixtures.
+
+// Nothing to measure without C++; `stub-cpp` builds an empty bench
+#![cfg_attr(feature = "stub-cpp", allow(dead_code, unused_imports))]
+
+#[cfg(not(feature = "stub-cpp"))]
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rust_lib::{
+    analyze_health, analyze_health_batch, analyze_health_batch_pooled,
+    analyze_health_batch_pooled_in_context, create_batch_context, ffi, process_person,
+    release_batch_context, release_string_table, snapshot_person,
+};
+
+const WARM_UP: Duration = Duration::from_millis(200);
+const SAMPLES: usize = 20;
+const SAMPLE_TIME: Duration = Duration::from_millis(50);
+const COHORT: usize = 1000;
+
+#[cfg(feature = "stub-cpp")]
+fn main() {}
+
+#[cfg(not(feature = "stub-cpp"))]
+fn main() {
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with('-'));
+    let bench = |name: &str, routine: &mut dyn FnMut()| {
+        if filter.as_deref().is_none_or(|filter| name.contains(filter)) {
+            println!("{name:<40} {}", format_time(median_time(routine)));
+        }
+    };
+
+    let person = common::adult_person();
+    let snapshot = snapshot_person(&person);
+
+    bench("getter/get_person_age", &mut || {
+        black_box(ffi::get_person_age(black_box(&person)));
+    });
+    bench("person/process_person", &mut || {
+        black_box(process_person(black_box(&person)));
+    });
+    bench("person/snapshot_person", &mut || {
+        black_box(snapshot_person(black_box(&person)));
+    });
+    bench("health/analyze_health", &mut || {
+        black_box(analyze_health(black_box(&person), 70.0));
+    });
+    bench("health/snapshot_then_batch_of_one", &mut || {
+        let snapshot = snapshot_person(black_box(&person));
+        black_box(analyze_health_batch(&[snapshot], &[70.0], 0).unwrap());
+    });
+    bench("health/batch_of_one_from_snapshot", &mut || {
+        black_box(analyze_health_batch(std::slice::from_ref(&snapshot), &[70.0], 0).unwrap());
+    });
+
+    let people: Vec<_> = (0..COHORT).map(|_| common::adult_person()).collect();
+    let snapshots: Vec<_> = people.iter().map(|person| snapshot_person(person)).collect();
+    let weights = vec![70.0; COHORT];
+    bench("cohort_1000/per_item_analyze_health", &mut || {
+        for person in &people {
+            black_box(analyze_health(person, 70.0));
+        }
+    });
+    bench("cohort_1000/snapshot_all", &mut || {
+        for person in &people {
+            black_box(snapshot_person(person));
+        }
+    });
+    bench("cohort_1000/analyze_health_batch", &mut || {
+        black_box(analyze_health_batch(black_box(&snapshots), &weights, 0).unwrap());
+    });
+
+    let clinics: Vec<_> = snapshots.chunks(100).zip(weights.chunks(100)).collect();
+    bench("small_batches/pooled", &mut || {
+        for &(people, weights) in &clinics {
+            let batch = analyze_health_batch_pooled(black_box(people), weights, 0).unwrap();
+            release_string_table(batch.table_id);
+        }
+    });
+    let context = create_batch_context(&ffi::BatchCapacity {
+        people: 100,
+        distinct_strings: 8,
+    });
+    bench("small_batches/pooled_in_context", &mut || {
+        for &(people, weights) in &clinics {
+            let batch =
+                analyze_health_batch_pooled_in_context(context, black_box(people), weights, 0)
+                    .unwrap();
+            release_string_table(batch.table_id);
+        }
+    });
+    release_batch_context(context);
+}
+
+/// Median time of one call, over SAMPLES batches sized to SAMPLE_TIME
+fn median_time(routine: &mut dyn FnMut()) -> Duration {
+    let start = Instant::now();
+    let mut warm_up_calls = 0u32;
+    while start.elapsed() < WARM_UP {
+        routine();
+        warm_up_calls += 1;
+    }
+    let per_call = WARM_UP / warm_up_calls.max(1);
+    let batch = (SAMPLE_TIME.as_nanos() / per_call.as_nanos().max(1)).max(1) as u32;
+
+    let mut samples: Vec<Duration> = (0..SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..batch {
+                routine();
+            }
+            start.elapsed() / batch
+        })
+        .collect();
+    samples.sort();
+    samples[SAMPLES / 2]
+}
+
+fn format_time(time: Duration) -> String {
+    let nanos = time.as_secs_f64() * 1e9;
+    if nanos < 1e3 {
+        format!("{nanos:>8.1} ns")
+    } else if nanos < 1e6 {
+        format!("{:>8.2} µs", nanos / 1e3)
+    } else {
+        format!("{:>8.2} ms", nanos / 1e6)
+    }
+}
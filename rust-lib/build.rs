@@ -1,16 +1,755 @@
+/// Bridge modules, each generating rust-lib/src/<name>.rs.h
+const BRIDGES: [&str; 3] = ["src/person_ffi.rs", "src/health_ffi.rs", "src/validation_ffi.rs"];
+
+/// Bridge of the integration tests' C++ fixtures
+const TEST_FIXTURES_BRIDGE: &str = "tests/common/mod.rs";
+
 fn main() {
+    // With `stub-cpp` the getters are Rust functions (src/stub_cpp.rs) and
+    // nothing on the C++ side is compiled, generated or linked
+    if std::env::var_os("CARGO_FEATURE_STUB_CPP").is_some() {
+        return;
+    }
+
     // Get the project root directory (parent of rust-lib)
     let project_root = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
         .parent()
         .expect("Failed to get project root");
     
-    cxx_build::bridge("src/lib.rs")
-        .flag_if_supported("-std=c++14")
-        .include(project_root)  // Add project root to include path for cpp-app/person.h
-        .file("../cpp-app/person.cpp")  // Include the C++ implementation file
-        .compile("rust_lib_cxx");
+    let mut build = cxx_build::bridges(BRIDGES);
+    // The application sources get the same settings but are compiled on
+    // their own, so their objects can be cached (see compile_cached)
+    let mut app = cc::Build::new();
+    app.cpp(true);
+    // C++ fixtures of the integration tests (see compile_test_fixtures)
+    let mut fixtures = cxx_build::bridge(TEST_FIXTURES_BRIDGE);
+    let person_include = person_header_shim();
+    let deps = cxx_dependencies();
+    for build in [&mut build, &mut app, &mut fixtures] {
+        // A prebuilt libperson's person.h shadows the demo's cpp-app/person.h
+        if let Some(person_include) = &person_include {
+            build.include(person_include);
+        }
+        build.include(project_root);  // Add project root to include path for cpp-app/person.h
+        for include_dir in &deps.include_dirs {
+            build.include(include_dir);
+        }
+        for flag in &deps.flags {
+            build.flag(flag);
+        }
+        configure_target(build);
+        configure_cxx_flags(build);
+        configure_compiler_wrapper(build);
+    }
+    for flag in sanitizer_flags(&build) {
+        build.flag(&flag);
+        app.flag(&flag);
+        fixtures.flag(&flag);
+    }
+
+    // With `external-cpp` the host build owns the application's C++ objects
+    // and compiles them with its own flags; only the cxx glue is built here
+    let mut app_sources = Vec::new();
+    if std::env::var_os("CARGO_FEATURE_EXTERNAL_CPP").is_none() {
+        // A prebuilt libperson replaces the demo implementation
+        if std::env::var_os("PERSON_LIB_DIR").is_none() {
+            app_sources.push(("../cpp-app/person.cpp", "../cpp-app/person.h"));
+        }
+
+        // Weak default allocation hooks; the host may define its own
+        if std::env::var_os("CARGO_FEATURE_HOST_ALLOCATOR").is_some() {
+            app_sources.push(("../cpp-app/host_allocator.cpp", "../cpp-app/host_allocator.h"));
+        }
+    }
+    for object in compile_cached(&app, &app_sources, &deps.manifests) {
+        build.object(object);
+    }
 
-    println!("cargo:rerun-if-changed=src/lib.rs");
+    // Part of the glue, not of the application: it reads a Person through
+    // the getters, whichever library provides them
+    build.file("../cpp-app/person_snapshot.cpp");
+
+    compile_test_fixtures(fixtures);
+
+    // Keep every bridge object in the cdylib, referenced from Rust or not
+    build.link_lib_modifier("+whole-archive");
+    build.compile("rust_lib_cxx");
+    // After the glue, which references it, so static linking resolves
+    link_prebuilt_person();
+    deps.link();
+
+    export_headers();
+    configure_shared_library();
+
+    for bridge in BRIDGES {
+        println!("cargo:rerun-if-changed={bridge}");
+    }
     println!("cargo:rerun-if-changed=../cpp-app/person.h");
     println!("cargo:rerun-if-changed=../cpp-app/person.cpp");
+    println!("cargo:rerun-if-changed=../cpp-app/host_allocator.h");
+    println!("cargo:rerun-if-changed=../cpp-app/host_allocator.cpp");
+    println!("cargo:rerun-if-changed=../cpp-app/person_snapshot.h");
+    println!("cargo:rerun-if-changed=../cpp-app/person_snapshot.cpp");
+    println!("cargo:rerun-if-changed={TEST_FIXTURES_BRIDGE}");
+    println!("cargo:rerun-if-changed=tests/test_fixtures.h");
+    println!("cargo:rerun-if-changed=tests/test_fixtures.cpp");
+    println!("cargo:rerun-if-env-changed=RUST_LIB_HEADER_DIR");
+    println!("cargo:rerun-if-env-changed=ANDROID_NDK_HOME");
+    println!("cargo:rerun-if-env-changed=ANDROID_NDK_ROOT");
+    println!("cargo:rerun-if-env-changed=ANDROID_PLATFORM");
+    println!("cargo:rerun-if-env-changed=PERSON_LIB_DIR");
+    println!("cargo:rerun-if-env-changed=PERSON_LIB_NAME");
+    println!("cargo:rerun-if-env-changed=PERSON_INCLUDE_DIR");
+    println!("cargo:rerun-if-env-changed=RUST_LIB_SANITIZE");
+    println!("cargo:rerun-if-env-changed=RUST_LIB_CXX_STD");
+    println!("cargo:rerun-if-env-changed=CXX_WRAPPER");
+    println!("cargo:rerun-if-env-changed=RUST_LIB_CXX_DEPS");
+    println!("cargo:rerun-if-env-changed=RUST_LIB_CONAN_DIR");
+    println!("cargo:rerun-if-env-changed=VCPKG_ROOT");
+    println!("cargo:rerun-if-env-changed=VCPKG_INSTALLED_DIR");
+    println!("cargo:rerun-if-env-changed=VCPKG_TARGET_TRIPLET");
+}
+
+/// Sanitizers the C++ compilers know, by their `-fsanitize=` names
+const CXX_SANITIZERS: [&str; 6] = ["address", "hwaddress", "memory", "thread", "leak", "undefined"];
+
+/// Flags that instrument the cxx glue and person.cpp with sanitizers
+///
+/// Enabled by the `sanitize` feature or by `RUST_LIB_SANITIZE`, a list such
+/// as `address,undefined`. Without the list, the C++ side uses the
+/// sanitizers Rust is built with (`RUSTFLAGS=-Zsanitizer=...`, nightly)
+/// plus UBSan, or ASan and UBSan if Rust is not instrumented. Runtimes Rust
+/// does not link itself are added to the link of tests and the cdylib; an
+/// application linking the staticlib passes the same `-fsanitize=` flags.
+fn sanitizer_flags(build: &cc::Build) -> Vec<String> {
+    let requested = std::env::var("RUST_LIB_SANITIZE").ok().filter(|list| !list.is_empty());
+    if requested.is_none() && std::env::var_os("CARGO_FEATURE_SANITIZE").is_none() {
+        return Vec::new();
+    }
+
+    let rust_sanitizers: Vec<String> = std::env::var("CARGO_CFG_SANITIZE")
+        .map(|list| list.split(',').map(str::to_string).collect())
+        .unwrap_or_default();
+    let mut sanitizers: Vec<String> = match requested {
+        Some(list) => list.split(',').map(|name| name.trim().to_string()).collect(),
+        None if rust_sanitizers.is_empty() => vec!["address".to_string(), "undefined".to_string()],
+        None => rust_sanitizers.iter().cloned().chain(["undefined".to_string()]).collect(),
+    };
+    sanitizers.retain(|name| {
+        let known = CXX_SANITIZERS.contains(&name.as_str());
+        if !known {
+            println!("cargo:warning=ignoring sanitizer `{name}`, unknown to the C++ compiler");
+        }
+        known
+    });
+
+    let compiler = build.get_compiler();
+    if compiler.is_like_msvc() {
+        // cl.exe only has ASan
+        if sanitizers.iter().any(|name| name != "address") {
+            println!("cargo:warning=MSVC supports only the address sanitizer");
+        }
+        if sanitizers.iter().any(|name| name == "address") {
+            return vec!["/fsanitize=address".to_string(), "/Zi".to_string()];
+        }
+        return Vec::new();
+    }
+
+    if sanitizers.is_empty() {
+        return Vec::new();
+    }
+    let flags = vec![
+        format!("-fsanitize={}", sanitizers.join(",")),
+        "-fno-sanitize-recover=undefined".to_string(),
+        "-fno-omit-frame-pointer".to_string(),
+        "-g".to_string(),
+    ];
+
+    if rust_sanitizers.is_empty() {
+        println!(
+            "cargo:warning=Rust code is not instrumented; use RUSTFLAGS=-Zsanitizer=address \
+             (nightly) to catch memory bugs on both sides of the bridge"
+        );
+    } else if !compiler.is_like_clang() {
+        // rustc links LLVM's sanitizer runtimes, which GCC's instrumentation
+        // does not match
+        println!("cargo:warning=use clang for the C++ side of a sanitized Rust build");
+    }
+
+    let extra: Vec<&str> = sanitizers
+        .iter()
+        .map(String::as_str)
+        .filter(|name| !rust_sanitizers.iter().any(|rust| rust == name))
+        .collect();
+    if extra.is_empty() {
+        return flags;
+    }
+    if compiler.is_like_clang() {
+        println!("cargo:rustc-link-arg=-fsanitize={}", extra.join(","));
+    } else {
+        // rustc links with -nodefaultlibs, which drops GCC's sanitizer
+        // runtimes (libasan, libubsan, ...) even with -fsanitize=
+        for name in extra {
+            let runtime = match name {
+                "address" => "asan",
+                "hwaddress" => "hwasan",
+                "memory" => "msan",
+                "thread" => "tsan",
+                "leak" => "lsan",
+                _ => "ubsan",
+            };
+            println!("cargo:rustc-link-lib=dylib={runtime}");
+        }
+    }
+    flags
+}
+
+/// Compile the C++ fixtures of the integration tests (tests/common/mod.rs)
+///
+/// Integration tests cannot have a build script of their own, so the
+/// archive is built here. It is linked `-bundle`: the test binaries pull in
+/// what they use, while the staticlib does not carry it and the cdylib
+/// references none of it.
+fn compile_test_fixtures(mut fixtures: cc::Build) {
+    fixtures
+        .file("tests/test_fixtures.cpp")
+        .link_lib_modifier("-bundle")
+        .compile("rust_lib_test_fixtures");
+}
+
+/// Compile the application's C++ sources, reusing objects of earlier builds
+///
+/// Cargo reruns this script whenever a bridge changes, and in a fresh
+/// OUT_DIR after a feature or profile change; `cc` then recompiles every
+/// file. Objects are kept in <profile dir>/cxx-cache, keyed by a hash of the
+/// source, its header, the target, the compiler and every flag, so e.g.
+/// person.cpp is only rebuilt when one of those changes. `manifests` stand
+/// in for the headers of third-party packages: a reinstall rewrites them.
+/// `cargo clean` empties the cache.
+fn compile_cached(
+    app: &cc::Build,
+    sources: &[(&str, &str)],
+    manifests: &[std::path::PathBuf],
+) -> Vec<std::path::PathBuf> {
+    use std::hash::{Hash, Hasher};
+
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    // OUT_DIR is <profile dir>/build/<package>-<hash>/out
+    let cache_dir = out_dir.ancestors().nth(3).unwrap().join("cxx-cache");
+    let tool = app.get_compiler();
+
+    sources
+        .iter()
+        .map(|&(source, header)| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            std::env::var("TARGET").unwrap().hash(&mut hasher);
+            tool.path().hash(&mut hasher);
+            tool.args().hash(&mut hasher);
+            for input in [source, header] {
+                std::fs::read(input)
+                    .unwrap_or_else(|e| panic!("Failed to read {input}: {e}"))
+                    .hash(&mut hasher);
+            }
+            for manifest in manifests {
+                std::fs::read(manifest).unwrap_or_default().hash(&mut hasher);
+            }
+
+            let stem = std::path::Path::new(source).file_stem().unwrap().to_string_lossy();
+            let cached = cache_dir.join(format!("{stem}-{:016x}.o", hasher.finish()));
+            if !cached.is_file() {
+                let mut single = app.clone();
+                let object = single.file(source).compile_intermediates().remove(0);
+                std::fs::create_dir_all(&cache_dir).expect("Failed to create cxx-cache");
+                // Publish atomically; parallel builds may race on the same key
+                let partial = cached.with_extension(format!("{}.tmp", std::process::id()));
+                std::fs::copy(&object, &partial).expect("Failed to cache object");
+                std::fs::rename(&partial, &cached).expect("Failed to cache object");
+            }
+            cached
+        })
+        .collect()
+}
+
+/// Run the C++ compiler through `$CXX_WRAPPER` (ccache, sccache, ...)
+///
+/// `CXX="ccache c++"` and `RUSTC_WRAPPER=sccache` already work through cc.
+/// CXX_WRAPPER wraps whichever compiler was picked, the Android NDK clang
+/// included, by pointing cc at a small forwarding script in OUT_DIR.
+fn configure_compiler_wrapper(build: &mut cc::Build) {
+    let Some(wrapper) = std::env::var("CXX_WRAPPER").ok().filter(|wrapper| !wrapper.is_empty())
+    else {
+        return;
+    };
+    let compiler = build.get_compiler();
+    if cfg!(windows) || compiler.is_like_msvc() {
+        println!("cargo:warning=CXX_WRAPPER needs a Unix host; use RUSTC_WRAPPER=sccache");
+        return;
+    }
+
+    let script = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("cxx-wrapper");
+    let contents = format!(
+        "#!/bin/sh\nexec '{wrapper}' '{}' \"$@\"\n",
+        compiler.path().display()
+    );
+    std::fs::write(&script, contents).expect("Failed to write compiler wrapper");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))
+            .expect("Failed to write compiler wrapper");
+    }
+    build.compiler(script);
+}
+
+/// Link the prebuilt C++ Person library from `$PERSON_LIB_DIR`, if set
+///
+/// The library is `libperson` unless `PERSON_LIB_NAME` says otherwise. A
+/// static archive (lib<name>.a / <name>.lib) is bundled into the Rust
+/// staticlib; otherwise the shared library is linked and must be found at
+/// runtime like any other dependency. Relative paths, here and in
+/// `PERSON_INCLUDE_DIR`, are taken relative to rust-lib/.
+fn link_prebuilt_person() {
+    let Some(lib_dir) = std::env::var_os("PERSON_LIB_DIR") else {
+        return;
+    };
+    let lib_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(lib_dir);
+    let name = std::env::var("PERSON_LIB_NAME").unwrap_or("person".to_string());
+
+    let kind = if is_static_lib(&lib_dir, &name) { "static" } else { "dylib" };
+
+    println!("cargo:rustc-link-search=native={}", lib_dir.display());
+    println!("cargo:rustc-link-lib={kind}={name}");
+}
+
+/// Whether `dir` holds a static archive of `name` (lib<name>.a / <name>.lib)
+fn is_static_lib(dir: &std::path::Path, name: &str) -> bool {
+    [format!("lib{name}.a"), format!("{name}.lib")]
+        .iter()
+        .any(|file| dir.join(file).is_file())
+}
+
+/// Compile and link settings of third-party C++ packages
+#[derive(Default)]
+struct CxxDeps {
+    include_dirs: Vec<std::path::PathBuf>,
+    /// Other Cflags, e.g. `-D` defines from a .pc file
+    flags: Vec<String>,
+    lib_dirs: Vec<std::path::PathBuf>,
+    libs: Vec<String>,
+    /// Files rewritten whenever the packages are (re)installed
+    manifests: Vec<std::path::PathBuf>,
+}
+
+impl CxxDeps {
+    /// Link the libraries after the glue that references them; archives
+    /// are bundled into the staticlib like a prebuilt libperson
+    fn link(&self) {
+        for lib_dir in &self.lib_dirs {
+            println!("cargo:rustc-link-search=native={}", lib_dir.display());
+        }
+        for name in &self.libs {
+            let is_static = self.lib_dirs.iter().any(|dir| is_static_lib(dir, name));
+            let kind = if is_static { "static" } else { "dylib" };
+            println!("cargo:rustc-link-lib={kind}={name}");
+        }
+    }
+}
+
+/// Third-party C++ packages listed in `RUST_LIB_CXX_DEPS`, from conan or
+/// vcpkg
+///
+/// - conan: `RUST_LIB_CONAN_DIR` is the generators folder of `conan install`
+///   with the `PkgConfigDeps` generator. Each entry names a .pc file there;
+///   its Cflags, Libs and Requires are followed.
+/// - vcpkg: otherwise `VCPKG_INSTALLED_DIR` (manifest mode, usually
+///   `vcpkg_installed`) or `$VCPKG_ROOT/installed` is searched for the
+///   triplet `VCPKG_TARGET_TRIPLET`, by default derived from the Rust
+///   target. Its include directory is added, and each entry is a library
+///   in its lib directory; entries without one are header-only.
+///
+/// Relative paths are taken relative to rust-lib/.
+fn cxx_dependencies() -> CxxDeps {
+    let mut deps = CxxDeps::default();
+    let names: Vec<String> = std::env::var("RUST_LIB_CXX_DEPS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
+    if names.is_empty() {
+        return deps;
+    }
+    let manifest_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    if let Some(conan_dir) = std::env::var_os("RUST_LIB_CONAN_DIR") {
+        let conan_dir = manifest_dir.join(conan_dir);
+        let mut seen = Vec::new();
+        for name in &names {
+            read_pkg_config(&conan_dir, name, &mut seen, &mut deps);
+        }
+        return deps;
+    }
+
+    let installed = match std::env::var_os("VCPKG_INSTALLED_DIR") {
+        Some(dir) => manifest_dir.join(dir),
+        None => match std::env::var_os("VCPKG_ROOT") {
+            Some(root) => manifest_dir.join(root).join("installed"),
+            None => panic!(
+                "RUST_LIB_CXX_DEPS needs RUST_LIB_CONAN_DIR, VCPKG_INSTALLED_DIR or VCPKG_ROOT"
+            ),
+        },
+    };
+    let triplet = std::env::var("VCPKG_TARGET_TRIPLET").unwrap_or_else(|_| vcpkg_triplet());
+    let prefix = installed.join(&triplet);
+    assert!(
+        prefix.is_dir(),
+        "No vcpkg packages for triplet {triplet} in {}",
+        installed.display()
+    );
+
+    let lib_dir = prefix.join("lib");
+    for name in names {
+        let has_library = [
+            format!("lib{name}.a"),
+            format!("{name}.lib"),
+            format!("lib{name}.so"),
+            format!("lib{name}.dylib"),
+        ]
+        .iter()
+        .any(|file| lib_dir.join(file).is_file());
+        if has_library {
+            deps.libs.push(name);
+        }
+    }
+    deps.include_dirs.push(prefix.join("include"));
+    deps.lib_dirs.push(lib_dir);
+    // vcpkg records every install and removal here
+    deps.manifests.push(installed.join("vcpkg").join("status"));
+    deps
+}
+
+/// vcpkg's triplet for the Rust target
+///
+/// Windows uses the static libraries with the /MD runtime, the one the
+/// Rust staticlib links against.
+fn vcpkg_triplet() -> String {
+    let arch = match std::env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default().as_str() {
+        "x86_64" => "x64",
+        "x86" => "x86",
+        "aarch64" => "arm64",
+        "arm" => "arm",
+        other => panic!("No vcpkg triplet for {other}; set VCPKG_TARGET_TRIPLET"),
+    };
+    let os = match std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default().as_str() {
+        "linux" => "linux",
+        "macos" => "osx",
+        "ios" => "ios",
+        "android" => "android",
+        "windows" => "windows-static-md",
+        other => panic!("No vcpkg triplet for {other}; set VCPKG_TARGET_TRIPLET"),
+    };
+    format!("{arch}-{os}")
+}
+
+/// Add the settings of `<dir>/<name>.pc` and the packages it requires
+///
+/// Only what conan's PkgConfigDeps writes is understood: `key=value`
+/// variables, `${key}` references, and `-I`, `-L` and `-l` in Cflags and
+/// Libs; other Cflags are passed to the compiler as they are.
+fn read_pkg_config(dir: &std::path::Path, name: &str, seen: &mut Vec<String>, deps: &mut CxxDeps) {
+    if seen.iter().any(|known| known == name) {
+        return;
+    }
+    seen.push(name.to_string());
+
+    let path = dir.join(format!("{name}.pc"));
+    let contents = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Failed to read {}: {e}", path.display()));
+    println!("cargo:rerun-if-changed={}", path.display());
+    deps.manifests.push(path);
+
+    let mut variables: Vec<(String, String)> = Vec::new();
+    let expand = |value: &str, variables: &[(String, String)]| {
+        variables.iter().fold(value.to_string(), |value, (key, replacement)| {
+            value.replace(&format!("${{{key}}}"), replacement)
+        })
+    };
+    for line in contents.lines().map(str::trim) {
+        // `prefix=C:/...`: whichever separator comes first
+        let Some(split) = line.find([':', '=']) else {
+            continue;
+        };
+        let (key, value) = (&line[..split], &line[split + 1..]);
+        if line.as_bytes()[split] == b':' {
+            let value = expand(value.trim(), &variables);
+            match key {
+                "Cflags" => {
+                    for flag in value.split_whitespace() {
+                        match flag.strip_prefix("-I") {
+                            Some(include_dir) => deps.include_dirs.push(include_dir.into()),
+                            None => deps.flags.push(flag.to_string()),
+                        }
+                    }
+                }
+                "Libs" => {
+                    for flag in value.split_whitespace() {
+                        if let Some(lib_dir) = flag.strip_prefix("-L") {
+                            deps.lib_dirs.push(lib_dir.into());
+                        } else if let Some(lib) = flag.strip_prefix("-l") {
+                            deps.libs.push(lib.to_string());
+                        }
+                    }
+                }
+                "Requires" => {
+                    // `a, b >= 1.0` -> a, b
+                    for required in value.split([',', ' ']).filter(|word| !word.is_empty()) {
+                        if required.starts_with(|c: char| c.is_alphabetic()) {
+                            read_pkg_config(dir, required, seen, deps);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        } else {
+            let value = expand(value.trim(), &variables);
+            variables.push((key.trim().to_string(), value));
+        }
+    }
+}
+
+/// Include directory that makes `cpp-app/person.h` resolve to
+/// `$PERSON_INCLUDE_DIR/person.h`, if set
+///
+/// The bridges include the header by its in-repo path; a forwarding header
+/// under OUT_DIR keeps that path working for a library that installs a
+/// plain person.h.
+fn person_header_shim() -> Option<std::path::PathBuf> {
+    let include_dir = std::env::var_os("PERSON_INCLUDE_DIR")?;
+    let header = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join(include_dir)
+        .join("person.h");
+    assert!(header.is_file(), "PERSON_INCLUDE_DIR has no person.h: {}", header.display());
+    println!("cargo:rerun-if-changed={}", header.display());
+
+    let shim_root = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("person-include");
+    let shim = shim_root.join("cpp-app").join("person.h");
+    std::fs::create_dir_all(shim.parent().unwrap()).expect("Failed to create person.h shim");
+    // Forward slashes: valid in #include on every platform, unlike `\`
+    let target = header.display().to_string().replace('\\', "/");
+    std::fs::write(&shim, format!("#pragma once\n#include \"{target}\"\n"))
+        .expect("Failed to write person.h shim");
+    Some(shim_root)
+}
+
+/// C++ standards `RUST_LIB_CXX_STD` accepts; cxx itself needs C++11+
+const CXX_STANDARDS: [&str; 6] = ["11", "14", "17", "20", "23", "26"];
+
+/// C++ standard when `RUST_LIB_CXX_STD` is not set
+const DEFAULT_CXX_STD: &str = "14";
+
+/// Language and exception flags in the compiler's own syntax
+///
+/// cl.exe (and clang-cl) ignore `-std=`; they need `/std:`, `/EHsc` for
+/// C++ exceptions (cxx throws `rust::Error`) and `/utf-8` for the UTF-8
+/// sources and generated headers.
+///
+/// `RUST_LIB_CXX_STD` (`17`, or `c++17`) selects the standard. Unlike the
+/// default, an explicit choice the compiler rejects fails the build.
+fn configure_cxx_flags(build: &mut cc::Build) {
+    let requested = std::env::var("RUST_LIB_CXX_STD").ok().filter(|std| !std.is_empty());
+    let std = requested.as_deref().unwrap_or(DEFAULT_CXX_STD);
+    let std = std.trim_start_matches("c++");
+    if !CXX_STANDARDS.contains(&std) {
+        panic!(
+            "RUST_LIB_CXX_STD={std} is not a C++ standard; use one of {}",
+            CXX_STANDARDS.join(", ")
+        );
+    }
+
+    let compiler = build.get_compiler();
+    let flag = if compiler.is_like_msvc() {
+        build.flag("/EHsc").flag("/utf-8");
+        match std {
+            // cl.exe has no switch below C++14, and names C++23 on c++latest
+            "11" | "14" => "/std:c++14".to_string(),
+            "23" | "26" => "/std:c++latest".to_string(),
+            _ => format!("/std:c++{std}"),
+        }
+    } else {
+        format!("-std=c++{std}")
+    };
+
+    if requested.is_some() && !build.is_flag_supported(&flag).unwrap_or(false) {
+        panic!(
+            "RUST_LIB_CXX_STD={std}: {} does not support `{flag}`",
+            compiler.path().display()
+        );
+    }
+    if compiler.is_like_msvc() {
+        build.flag(flag);
+    } else {
+        build.flag_if_supported(flag);
+    }
+}
+
+/// Minimum Android API level when `ANDROID_PLATFORM` is not set
+const DEFAULT_ANDROID_API: &str = "21";
+
+/// Target-specific compiler setup for cross builds
+///
+/// `cc` already honours `CXX`, `AR`, `CXXFLAGS` and their per-target forms
+/// (`CXX_aarch64_linux_android`, `TARGET_CXX`, ...); iOS sysroots come from
+/// `SDKROOT` or `xcrun`. On top of that:
+/// - `RUST_LIB_SYSROOT[_<target>]` adds `--sysroot` (`-isysroot` on Apple)
+/// - Android uses the NDK clang and llvm-ar from `ANDROID_NDK_HOME` for API
+///   level `ANDROID_PLATFORM`, unless a per-target CXX / AR is given. The
+///   plain `CXX` is skipped there, as it names the host compiler.
+fn configure_target(build: &mut cc::Build) {
+    let target = std::env::var("TARGET").unwrap();
+    let target_os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let target_vendor = std::env::var("CARGO_CFG_TARGET_VENDOR").unwrap_or_default();
+
+    if let Some(sysroot) = target_env("RUST_LIB_SYSROOT", &target) {
+        if target_vendor == "apple" {
+            build.flag("-isysroot").flag(&sysroot);
+        } else {
+            build.flag(format!("--sysroot={sysroot}"));
+        }
+    }
+
+    if target_os == "android" {
+        let ndk = std::env::var("ANDROID_NDK_HOME").or_else(|_| std::env::var("ANDROID_NDK_ROOT"));
+        if let Ok(ndk) = ndk {
+            let bin = android_ndk_bin(std::path::Path::new(&ndk));
+            let api = std::env::var("ANDROID_PLATFORM").unwrap_or(DEFAULT_ANDROID_API.to_string());
+            // Accept both "24" and the CMake-style "android-24"
+            let api = api.trim_start_matches("android-");
+            // armv7 is the one triple whose clang name differs from rustc's
+            let clang_target = target.replace("armv7-linux-androideabi", "armv7a-linux-androideabi");
+
+            if target_env("CXX", &target).is_none() {
+                build.compiler(bin.join(format!("{clang_target}{api}-clang++")));
+            }
+            if target_env("AR", &target).is_none() {
+                build.archiver(bin.join("llvm-ar"));
+            }
+        }
+    }
+}
+
+/// `$NDK/toolchains/llvm/prebuilt/<host>/bin`
+fn android_ndk_bin(ndk: &std::path::Path) -> std::path::PathBuf {
+    // Mac NDKs ship darwin-x86_64 binaries for both Intel and Apple silicon
+    let host = match std::env::consts::OS {
+        "macos" => "darwin-x86_64",
+        "windows" => "windows-x86_64",
+        _ => "linux-x86_64",
+    };
+    ndk.join("toolchains").join("llvm").join("prebuilt").join(host).join("bin")
+}
+
+/// `<name>_<target>`, `<name>_<target_with_underscores>` or `TARGET_<name>`,
+/// the lookup order `cc` uses for per-target variables
+fn target_env(name: &str, target: &str) -> Option<String> {
+    let keys = [
+        format!("{name}_{target}"),
+        format!("{name}_{}", target.replace('-', "_")),
+        format!("TARGET_{name}"),
+    ];
+    keys.iter().find_map(|key| {
+        println!("cargo:rerun-if-env-changed={key}");
+        std::env::var(key).ok()
+    })
+}
+
+/// person.cpp functions that are not declared in any bridge
+const PERSON_CPP_EXPORTS: [&str; 3] = ["create_address", "create_contact_info", "create_person"];
+
+/// Soname and exported symbols of the cdylib on ELF targets
+///
+/// rustc hides every cdylib symbol that Rust itself does not export, which
+/// includes the C++ wrapper cxx generates for each bridge function. The
+/// version script written here re-exports the C++ API: one pattern per
+/// bridge function, the person.cpp factories, and the `rust::` runtime
+/// (with its typeinfo, so `rust::Error` can be caught in the application).
+/// The soname carries the major version: librust_lib.so.<major>. Android
+/// packages only load unversioned lib*.so files, so there it stays
+/// librust_lib.so.
+fn configure_shared_library() {
+    let target_os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    if !matches!(target_os.as_str(), "linux" | "android") {
+        return;
+    }
+
+    let major = env!("CARGO_PKG_VERSION_MAJOR");
+    let soname = if target_os == "android" {
+        "librust_lib.so".to_string()
+    } else {
+        format!("librust_lib.so.{major}")
+    };
+    // Demangled patterns; `name*` also matches any overload of `name`
+    let mut cpp_symbols: Vec<String> = Vec::new();
+    cpp_symbols.extend(PERSON_CPP_EXPORTS.iter().map(|name| format!("{name}*")));
+    for bridge in BRIDGES {
+        let source = std::fs::read_to_string(bridge).expect("Failed to read bridge");
+        cpp_symbols.extend(bridge_functions(&source).map(|name| format!("{name}*")));
+    }
+
+    let mut map = format!("RUST_LIB_{major} {{\n    global:\n");
+    // The rust:: runtime by mangled name: members, const members, typeinfo,
+    // typeinfo name and vtables (demangled globs miss some operators)
+    for prefix in ["_ZN4rust", "_ZNK4rust", "_ZTIN4rust", "_ZTSN4rust", "_ZTVN4rust"] {
+        map.push_str(&format!("        {prefix}*;\n"));
+    }
+    map.push_str("        extern \"C++\" {\n");
+    for symbol in &cpp_symbols {
+        map.push_str(&format!("            {symbol};\n"));
+    }
+    map.push_str("        };\n};\n");
+
+    let map_path = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("rust_lib.map");
+    std::fs::write(&map_path, map).expect("Failed to write version script");
+
+    println!("cargo:rustc-cdylib-link-arg=-Wl,-soname,{soname}");
+    // The exported C++ API is unreferenced from Rust; keep the linker from
+    // discarding it (archive members are kept by the +whole-archive above)
+    println!("cargo:rustc-cdylib-link-arg=-Wl,--no-gc-sections");
+    println!("cargo:rustc-cdylib-link-arg=-Wl,--version-script={}", map_path.display());
+}
+
+/// Names of the free functions declared in a bridge file
+/// Methods (`self: &T`) are inline in callbacks.h and need no export
+fn bridge_functions(source: &str) -> impl Iterator<Item = &str> + '_ {
+    source.lines().filter_map(|line| {
+        let rest = line.trim_start().strip_prefix("fn ")?;
+        let (name, params) = rest.split_once('(')?;
+        (!params.trim_start().starts_with("self")).then_some(name)
+    })
+}
+
+/// Copy the generated headers to `$RUST_LIB_HEADER_DIR`, if set
+///
+/// The layout mirrors the include paths used in C++ code:
+///   $RUST_LIB_HEADER_DIR/rust-lib/src/<bridge>.rs.h
+///   $RUST_LIB_HEADER_DIR/rust/cxx.h
+/// so the application build adds one include directory instead of digging
+/// through target/. A relative path is taken relative to rust-lib/.
+fn export_headers() {
+    let Some(header_dir) = std::env::var_os("RUST_LIB_HEADER_DIR") else {
+        return;
+    };
+    let header_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(header_dir);
+    let generated = std::path::Path::new(&std::env::var("OUT_DIR").unwrap())
+        .join("cxxbridge")
+        .join("include");
+
+    let bridge_headers = BRIDGES.iter().map(|bridge| format!("rust-lib/{bridge}.h"));
+    for header in bridge_headers.chain(["rust/cxx.h".to_string()]) {
+        let destination = header_dir.join(&header);
+        std::fs::create_dir_all(destination.parent().unwrap())
+            .expect("Failed to create RUST_LIB_HEADER_DIR");
+        // cxx-build leaves symlinks in OUT_DIR; copy the real contents
+        std::fs::copy(generated.join(&header), &destination)
+            .unwrap_or_else(|e| panic!("Failed to export {header}: {e}"));
+    }
 }
@@ -0,0 +1,191 @@
+// <auto-generated>
+// C# bindings for rust_lib's plain C API (the `c-abi` feature), generated
+// by `cargo xtask codegen` from rust-lib/include/rust_lib_c.h; do not
+// edit by hand.
+// </auto-generated>
+
+using System.Runtime.InteropServices;
+
+namespace RustLib;
+
+/// <summary>
+/// Outcome of the fallible functions
+/// </summary>
+public enum RustLibStatus : int
+{
+    Ok = 0,
+    /// <summary>
+    /// A required pointer was null
+    /// </summary>
+    NullArgument = 1,
+    /// <summary>
+    /// A string was not valid UTF-8
+    /// </summary>
+    InvalidUtf8 = 2,
+    /// <summary>
+    /// The text did not fit; the buffer holds as much of it as did
+    /// </summary>
+    BufferTooSmall = 3,
+    /// <summary>
+    /// An internal error; the outputs are unspecified
+    /// </summary>
+    Panic = 4,
+}
+
+/// <summary>
+/// A person's fields, borrowed from the caller for the duration of a call
+/// </summary>
+[StructLayout(LayoutKind.Sequential)]
+public unsafe struct RustLibPersonSnapshot
+{
+    public byte* name;
+    public uint age;
+    public double height_m;
+    public byte* email;
+    public byte* phone;
+    public byte* street;
+    public byte* city;
+    public byte* postal_code;
+}
+
+/// <summary>
+/// HealthAnalysis without its recommendation text; see
+/// rust_lib_recommendation
+/// </summary>
+[StructLayout(LayoutKind.Sequential)]
+public struct RustLibHealthAnalysis
+{
+    public double bmi;
+    public double risk_score;
+    public double city_risk_factor;
+    /// <summary>
+    /// Factor from the host's RiskFactorHook (1.0 when none is set)
+    /// </summary>
+    public double extra_risk_factor;
+    /// <summary>
+    /// How many of age, BMI, city and the hook raised the score
+    /// </summary>
+    public uint risk_factors;
+}
+
+/// <summary>
+/// The contact fields `rust_lib_validate_contact` checks, borrowed like a
+/// snapshot's
+/// </summary>
+[StructLayout(LayoutKind.Sequential)]
+public unsafe struct RustLibContact
+{
+    public byte* email;
+    public byte* phone;
+    public byte* city;
+    public byte* postal_code;
+}
+
+/// <summary>
+/// Result of `rust_lib_validate_contact`: 1 where a rule holds, 0 where not
+///
+/// Bytes rather than `bool` keep the struct blittable for .NET.
+/// </summary>
+[StructLayout(LayoutKind.Sequential)]
+public struct RustLibContactValidation
+{
+    /// <summary>
+    /// All four fields are valid
+    /// </summary>
+    public byte valid;
+    public byte email_valid;
+    public byte phone_valid;
+    public byte city_valid;
+    public byte postal_valid;
+}
+
+/// <summary>
+/// Result of `rust_lib_validate_person`: 1 where a field is valid, 0 where
+/// validate_person_full reports an issue
+/// </summary>
+[StructLayout(LayoutKind.Sequential)]
+public struct RustLibPersonValidation
+{
+    /// <summary>
+    /// Every field is valid
+    /// </summary>
+    public byte valid;
+    public byte name_valid;
+    public byte age_valid;
+    public byte height_valid;
+    public byte email_valid;
+    public byte phone_valid;
+    public byte city_valid;
+    public byte postal_valid;
+}
+
+public static unsafe class NativeMethods
+{
+    private const string Library = "rust_lib";
+
+    /// <summary>
+    /// BMI for a weight and height; 0 for a height of 0 or less
+    /// </summary>
+    [DllImport(Library, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+    public static extern double rust_lib_calculate_bmi(double weight_kg, double height_m);
+
+    /// <summary>
+    /// Health analysis of one person, written to `*out`
+    ///
+    /// # Safety
+    /// `person` and `out` are null or valid, and `person`'s strings are null or
+    /// NUL-terminated.
+    /// </summary>
+    [DllImport(Library, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+    public static extern RustLibStatus rust_lib_analyze_snapshot(RustLibPersonSnapshot* person, double weight_kg, RustLibHealthAnalysis* @out);
+
+    /// <summary>
+    /// Health analysis of `count` people, `out[i]` for `people[i]` weighing
+    /// `weights_kg[i]`
+    ///
+    /// The pointers may be null when `count` is 0.
+    ///
+    /// # Safety
+    /// `people` and `weights_kg` point to `count` readable elements and `out`
+    /// to `count` writable ones; every string is null or NUL-terminated.
+    /// </summary>
+    [DllImport(Library, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+    public static extern RustLibStatus rust_lib_analyze_snapshot_batch(RustLibPersonSnapshot* people, double* weights_kg, nuint count, RustLibHealthAnalysis* @out);
+
+    /// <summary>
+    /// The recommendation for `analysis` in `locale` ("" or null for English),
+    /// written to `buf` as a NUL-terminated string
+    ///
+    /// `*out_len`, if `out_len` is not null, receives the full text length in
+    /// bytes without the NUL, so a caller can retry with a larger buffer after
+    /// `RUST_LIB_STATUS_BUFFER_TOO_SMALL`. A short buffer still receives the
+    /// text cut at a character boundary.
+    ///
+    /// # Safety
+    /// `analysis` is null or valid, `locale` is null or NUL-terminated, `buf`
+    /// points to `buf_len` writable bytes (or is null when `buf_len` is 0), and
+    /// `out_len` is null or writable.
+    /// </summary>
+    [DllImport(Library, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+    public static extern RustLibStatus rust_lib_recommendation(RustLibHealthAnalysis* analysis, byte* locale, byte* buf, nuint buf_len, nuint* out_len);
+
+    /// <summary>
+    /// The contact rules of validate_contact, field by field, written to `*out`
+    ///
+    /// # Safety
+    /// `contact` and `out` are null or valid, and `contact`'s strings are null
+    /// or NUL-terminated.
+    /// </summary>
+    [DllImport(Library, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+    public static extern RustLibStatus rust_lib_validate_contact(RustLibContact* contact, RustLibContactValidation* @out);
+
+    /// <summary>
+    /// Every field rule of validate_person_full, written to `*out`
+    ///
+    /// # Safety
+    /// `person` and `out` are null or valid, and `person`'s strings are null or
+    /// NUL-terminated.
+    /// </summary>
+    [DllImport(Library, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]
+    public static extern RustLibStatus rust_lib_validate_person(RustLibPersonSnapshot* person, RustLibPersonValidation* @out);
+}
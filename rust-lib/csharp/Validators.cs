@@ -0,0 +1,90 @@
+// Managed entry points for the validators over the generated NativeMethods
+//
+// Strings are passed to rust_lib as NUL-terminated UTF-8, pinned for the
+// duration of the call.
+
+using System;
+using System.Text;
+
+namespace RustLib;
+
+/// <summary>
+/// A RustLibStatus other than Ok
+/// </summary>
+public sealed class RustLibException : Exception
+{
+    public RustLibStatus Status { get; }
+
+    public RustLibException(RustLibStatus status)
+        : base($"rust_lib returned {status}")
+    {
+        Status = status;
+    }
+}
+
+public static unsafe class Validators
+{
+    /// <summary>
+    /// The contact rules of the C++ app's validate_contact, field by field
+    /// </summary>
+    public static RustLibContactValidation ValidateContact(
+        string email, string phone, string city, string postalCode)
+    {
+        fixed (byte* emailText = Utf8(email), phoneText = Utf8(phone), cityText = Utf8(city),
+               postalText = Utf8(postalCode))
+        {
+            var contact = new RustLibContact
+            {
+                email = emailText,
+                phone = phoneText,
+                city = cityText,
+                postal_code = postalText,
+            };
+            RustLibContactValidation result;
+            Check(NativeMethods.rust_lib_validate_contact(&contact, &result));
+            return result;
+        }
+    }
+
+    /// <summary>
+    /// Every field rule of validate_person_full
+    /// </summary>
+    public static RustLibPersonValidation ValidatePerson(
+        string name, uint age, double heightM, string email, string phone, string street,
+        string city, string postalCode)
+    {
+        fixed (byte* nameText = Utf8(name), emailText = Utf8(email), phoneText = Utf8(phone),
+               streetText = Utf8(street), cityText = Utf8(city), postalText = Utf8(postalCode))
+        {
+            var person = new RustLibPersonSnapshot
+            {
+                name = nameText,
+                age = age,
+                height_m = heightM,
+                email = emailText,
+                phone = phoneText,
+                street = streetText,
+                city = cityText,
+                postal_code = postalText,
+            };
+            RustLibPersonValidation result;
+            Check(NativeMethods.rust_lib_validate_person(&person, &result));
+            return result;
+        }
+    }
+
+    private static byte[] Utf8(string text)
+    {
+        var bytes = new byte[Encoding.UTF8.GetByteCount(text) + 1];
+        Encoding.UTF8.GetBytes(text, 0, text.Length, bytes, 0);
+        return bytes;
+    }
+
+    private static void Check(RustLibStatus status)
+    {
+        if (status != RustLibStatus.Ok)
+        {
+            throw new RustLibException(status);
+        }
+    }
+}
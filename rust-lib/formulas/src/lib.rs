@@ -0,0 +1,140 @@
+//! The health formulas and contact rules, with no FFI at all
+//!
+//! rust-lib's engine, and through it the C++ bridge and the C facade, calls
+//! these functions; so does the WebAssembly module in rust-lib/wasm/. This
+//! crate has no dependencies and no C++, which is what lets the web front
+//! end build the very same formulas for wasm32.
+
+/// Calculate BMI from weight and height
+///
+/// Pure Rust calculation - no C++ interaction
+pub fn calculate_bmi(weight_kg: f64, height_m: f64) -> f64 {
+    if height_m <= 0.0 {
+        return 0.0;
+    }
+    weight_kg / (height_m * height_m)
+}
+
+/// Sex as the BMR equation distinguishes it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sex {
+    Female,
+    Male,
+}
+
+/// Physical activity level, for the TDEE multiplier
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActivityLevel {
+    /// Desk work, little or no exercise
+    Sedentary,
+    /// Exercise 1-3 days a week
+    Light,
+    /// Exercise 3-5 days a week
+    Moderate,
+    /// Exercise 6-7 days a week
+    Active,
+    /// Hard daily exercise or a physical job
+    VeryActive,
+}
+
+impl ActivityLevel {
+    /// BMR multiplier (the Harris-Benedict activity factors)
+    pub fn factor(self) -> f64 {
+        match self {
+            ActivityLevel::Sedentary => 1.2,
+            ActivityLevel::Light => 1.375,
+            ActivityLevel::Moderate => 1.55,
+            ActivityLevel::Active => 1.725,
+            ActivityLevel::VeryActive => 1.9,
+        }
+    }
+}
+
+/// Basal metabolic rate in kcal/day, by the Mifflin-St Jeor equation
+///
+/// 0 for a weight or height of 0 or less, like calculate_bmi; never
+/// negative.
+pub fn calculate_bmr(weight_kg: f64, height_m: f64, age: u32, sex: Sex) -> f64 {
+    if weight_kg <= 0.0 || height_m <= 0.0 {
+        return 0.0;
+    }
+    let offset = match sex {
+        Sex::Female => -161.0,
+        Sex::Male => 5.0,
+    };
+    // 6.25 per centimeter of height
+    let bmr = 10.0 * weight_kg + 625.0 * height_m - 5.0 * f64::from(age) + offset;
+    bmr.max(0.0)
+}
+
+/// Total daily energy expenditure in kcal/day: the BMR times the activity
+/// factor
+pub fn calculate_tdee(bmr: f64, activity: ActivityLevel) -> f64 {
+    bmr * activity.factor()
+}
+
+// Contact field rules, shared with validate_contact and validate_person_full
+
+pub fn email_valid(email: &str) -> bool {
+    email.contains('@') && email.len() > 3
+}
+
+pub fn phone_valid(phone: &str) -> bool {
+    phone.len() >= 7
+}
+
+pub fn city_valid(city: &str) -> bool {
+    !city.is_empty()
+}
+
+pub fn postal_valid(postal_code: &str) -> bool {
+    postal_code.len() >= 5
+}
+
+/// All four contact rules, as validate_contact applies them
+pub fn contact_valid(email: &str, phone: &str, city: &str, postal_code: &str) -> bool {
+    email_valid(email) && phone_valid(phone) && city_valid(city) && postal_valid(postal_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calculate_bmi() {
+        let bmi = calculate_bmi(70.0, 1.75);
+        assert!((bmi - 22.86).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_calculate_bmi_zero_height() {
+        let bmi = calculate_bmi(70.0, 0.0);
+        assert_eq!(bmi, 0.0);
+    }
+
+    #[test]
+    fn test_calculate_bmr() {
+        // 10 * 70 + 6.25 * 175 - 5 * 30, then +5 or -161
+        assert_eq!(calculate_bmr(70.0, 1.75, 30, Sex::Male), 1648.75);
+        assert_eq!(calculate_bmr(70.0, 1.75, 30, Sex::Female), 1482.75);
+        assert_eq!(calculate_bmr(70.0, 0.0, 30, Sex::Male), 0.0);
+        assert_eq!(calculate_bmr(-1.0, 1.75, 30, Sex::Male), 0.0);
+        assert_eq!(calculate_bmr(1.0, 0.1, 150, Sex::Female), 0.0);
+    }
+
+    #[test]
+    fn test_calculate_tdee() {
+        let bmr = calculate_bmr(70.0, 1.75, 30, Sex::Male);
+        assert!((calculate_tdee(bmr, ActivityLevel::Sedentary) - 1978.5).abs() < 1e-9);
+        assert!((calculate_tdee(bmr, ActivityLevel::Moderate) - 2555.5625).abs() < 1e-9);
+        assert_eq!(calculate_tdee(0.0, ActivityLevel::VeryActive), 0.0);
+    }
+
+    #[test]
+    fn test_contact_valid() {
+        assert!(contact_valid("a@example.com", "555-0100", "Boston", "02101"));
+        assert!(!contact_valid("a@example.com", "555-0100", "", "02101"));
+        assert!(!contact_valid("nobody", "555-0100", "Boston", "02101"));
+        assert!(!contact_valid("a@example.com", "555", "Boston", "021"));
+    }
+}
@@ -0,0 +1,25 @@
+//! Email, phone, city and postal code rules, alone and through
+//! validate_contact on a real C++ ContactInfo
+//!
+//! The input is split at newlines into the four fields; bytes that are not
+//! UTF-8 are replaced, as the C++ network layer would have to.
+
+#![no_main]
+
+#[path = "../../tests/common/mod.rs"]
+mod common;
+
+use libfuzzer_sys::fuzz_target;
+use rust_lib::{city_valid, email_valid, phone_valid, postal_valid, validate_contact};
+
+fuzz_target!(|data: &[u8]| {
+    let input = String::from_utf8_lossy(data);
+    let mut fields = input.splitn(4, '\n');
+    let mut next = || fields.next().unwrap_or("");
+    let (email, phone, city, postal_code) = (next(), next(), next(), next());
+
+    let expected =
+        email_valid(email) && phone_valid(phone) && city_valid(city) && postal_valid(postal_code);
+    let contact = common::contact_with(email, phone, city, postal_code);
+    assert_eq!(validate_contact(&contact), expected);
+});
@@ -0,0 +1,23 @@
+//! PersonSnapshot JSON parsing, then analysis of whatever parsed
+//!
+//! A snapshot that parses must serialize back to the same JSON value and
+//! must be analysable without a panic, whatever its numbers (NaN heights
+//! cannot come from JSON, but zero, negative and huge ones can).
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_lib::{analyze_health_batch, ffi};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(snapshot) = serde_json::from_slice::<ffi::PersonSnapshot>(data) else {
+        return;
+    };
+
+    let json = serde_json::to_value(&snapshot).unwrap();
+    let reparsed: ffi::PersonSnapshot = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&reparsed).unwrap(), json);
+
+    let analyses = analyze_health_batch(&[snapshot], &[70.0], 0).unwrap();
+    assert_eq!(analyses.len(), 1);
+});
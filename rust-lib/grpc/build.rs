@@ -0,0 +1,8 @@
+fn main() {
+    if std::env::var_os("PROTOC").is_none() {
+        let protoc =
+            protoc_bin_vendored::protoc_bin_path().expect("no vendored protoc for this host");
+        std::env::set_var("PROTOC", protoc);
+    }
+    tonic_build::compile_protos("proto/rust_lib.proto").expect("compiling proto/rust_lib.proto");
+}
@@ -0,0 +1,190 @@
+//! gRPC service `rust_lib.v1.Analysis` over rust-lib's engine
+//!
+//! Remote services call AnalyzeHealth and ValidatePerson
+//! (proto/rust_lib.proto) and get what the C++ app gets from the bridge's
+//! analyze_health and validate_person_full, because both RPCs run
+//! rust_lib::analyze_snapshot and rust_lib::validate_snapshot.
+//!
+//! `start_grpc_server` binds the address and serves it from a background
+//! thread with its own tokio runtime, like the ingest queue's consumer, so
+//! a synchronous host keeps running; `stop_grpc_server` shuts it down.
+//!
+//! Each call is admitted through rust-lib's service limits under its gRPC
+//! path, "/rust_lib.v1.Analysis/AnalyzeHealth" say, sharing the in-flight
+//! cap with the HTTP server. A refused call fails with RESOURCE_EXHAUSTED
+//! past a rate limit and UNAVAILABLE past an in-flight cap.
+
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+use rust_lib::{ffi, Error};
+use tokio::sync::oneshot;
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("rust_lib.v1");
+}
+
+use proto::analysis_server::{Analysis, AnalysisServer};
+
+struct Server {
+    addr: SocketAddr,
+    shutdown: oneshot::Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+static SERVER: Mutex<Option<Server>> = Mutex::new(None);
+
+/// The Analysis service; `start_grpc_server` serves it, and hosts with
+/// their own tonic server can add it to theirs
+#[derive(Debug, Default)]
+pub struct AnalysisService;
+
+#[tonic::async_trait]
+impl Analysis for AnalysisService {
+    async fn analyze_health(
+        &self,
+        request: Request<proto::AnalyzeHealthRequest>,
+    ) -> Result<Response<proto::HealthAnalysis>, Status> {
+        let _admission = admit("/rust_lib.v1.Analysis/AnalyzeHealth")?;
+        let request = request.into_inner();
+        let person = request
+            .person
+            .ok_or_else(|| Status::invalid_argument("person is missing"))?;
+        let analysis = rust_lib::analyze_snapshot(&person.into(), request.weight_kg);
+        Ok(Response::new(analysis.into()))
+    }
+
+    async fn validate_person(
+        &self,
+        request: Request<proto::PersonSnapshot>,
+    ) -> Result<Response<proto::ValidationReport>, Status> {
+        let _admission = admit("/rust_lib.v1.Analysis/ValidatePerson")?;
+        let report = rust_lib::validate_snapshot(&request.into_inner().into());
+        Ok(Response::new(report.into()))
+    }
+}
+
+/// Admit a call to `method` under the service limits
+// The handlers return tonic's Status as it is, so this does too
+#[allow(clippy::result_large_err)]
+fn admit(method: &str) -> Result<rust_lib::Admission, Status> {
+    rust_lib::admit_request(method).map_err(|refused| match refused {
+        Error::RateLimited(_) => Status::resource_exhausted(refused.to_string()),
+        _ => Status::unavailable(refused.to_string()),
+    })
+}
+
+/// Serve the Analysis service on `addr` (e.g. "127.0.0.1:50051") until
+/// `stop_grpc_server`
+///
+/// Returns once the address is bound. Port 0 picks a free port; see
+/// `grpc_server_addr`.
+pub fn start_grpc_server(addr: &str) -> Result<(), Error> {
+    let mut server = SERVER.lock().unwrap_or_else(|e| e.into_inner());
+    if server.is_some() {
+        return Err(Error::AlreadyRunning("gRPC server"));
+    }
+    let addr: SocketAddr = addr
+        .parse()
+        .map_err(|e| Error::InvalidArgument(format!("gRPC address {addr:?}: {e}")))?;
+    let listener = TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    let addr = listener.local_addr()?;
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+
+    let (shutdown, stopped) = oneshot::channel::<()>();
+    let thread = thread::spawn(move || {
+        runtime.block_on(async move {
+            let listener = tokio::net::TcpListener::from_std(listener)
+                .expect("a non-blocking std listener converts to tokio's");
+            let incoming = tokio_stream::wrappers::TcpListenerStream::new(listener);
+            let serve = tonic::transport::Server::builder()
+                .add_service(AnalysisServer::new(AnalysisService))
+                .serve_with_incoming_shutdown(incoming, async {
+                    stopped.await.ok();
+                });
+            if let Err(error) = serve.await {
+                eprintln!("rust-lib gRPC server on {addr} failed: {error}");
+            }
+        });
+    });
+
+    *server = Some(Server {
+        addr,
+        shutdown,
+        thread,
+    });
+    Ok(())
+}
+
+/// The address the running server is bound to
+pub fn grpc_server_addr() -> Option<SocketAddr> {
+    let server = SERVER.lock().unwrap_or_else(|e| e.into_inner());
+    server.as_ref().map(|server| server.addr)
+}
+
+/// Stop the server, after the calls in progress finish; a no-op when none
+/// is running
+pub fn stop_grpc_server() {
+    let server = SERVER.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(Server {
+        shutdown, thread, ..
+    }) = server
+    {
+        let _ = shutdown.send(());
+        let _ = thread.join();
+    }
+}
+
+impl From<proto::PersonSnapshot> for ffi::PersonSnapshot {
+    fn from(person: proto::PersonSnapshot) -> Self {
+        ffi::PersonSnapshot {
+            name: person.name,
+            age: person.age,
+            height_m: person.height_m,
+            email: person.email,
+            phone: person.phone,
+            street: person.street,
+            city: person.city,
+            postal_code: person.postal_code,
+        }
+    }
+}
+
+impl From<ffi::HealthAnalysis> for proto::HealthAnalysis {
+    fn from(analysis: ffi::HealthAnalysis) -> Self {
+        proto::HealthAnalysis {
+            bmi: analysis.bmi,
+            risk_score: analysis.risk_score,
+            recommendation: analysis.recommendation,
+            city_risk_factor: analysis.city_risk_factor,
+            extra_risk_factor: analysis.extra_risk_factor,
+            risk_factors: analysis.risk_factors,
+        }
+    }
+}
+
+impl From<ffi::ValidationReport> for proto::ValidationReport {
+    fn from(report: ffi::ValidationReport) -> Self {
+        proto::ValidationReport {
+            valid: report.valid,
+            issues: report
+                .issues
+                .into_iter()
+                .map(|issue| proto::ValidationIssue {
+                    severity: if issue.severity == ffi::Severity::Error {
+                        proto::Severity::Error
+                    } else {
+                        proto::Severity::Warning
+                    } as i32,
+                    field: issue.field,
+                    message: issue.message,
+                })
+                .collect(),
+        }
+    }
+}
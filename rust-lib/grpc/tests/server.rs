@@ -0,0 +1,66 @@
+//! The server over a real socket, through the generated client
+
+use rust_lib_grpc::proto::analysis_client::AnalysisClient;
+use rust_lib_grpc::proto::{AnalyzeHealthRequest, PersonSnapshot, Severity};
+use rust_lib_grpc::{grpc_server_addr, start_grpc_server, stop_grpc_server};
+
+fn person(age: u32, city: &str) -> PersonSnapshot {
+    PersonSnapshot {
+        name: "Alice".to_string(),
+        age,
+        height_m: 1.75,
+        email: "alice@example.com".to_string(),
+        phone: "555-0100".to_string(),
+        street: "1 Main St".to_string(),
+        city: city.to_string(),
+        postal_code: "02101".to_string(),
+    }
+}
+
+// The server is a process-wide singleton, so its whole lifecycle is one test
+#[tokio::test(flavor = "multi_thread")]
+async fn test_server_lifecycle() {
+    assert!(start_grpc_server("not an address").is_err());
+    start_grpc_server("127.0.0.1:0").unwrap();
+    assert!(start_grpc_server("127.0.0.1:0").is_err());
+    let addr = grpc_server_addr().unwrap();
+
+    let mut client = AnalysisClient::connect(format!("http://{addr}")).await.unwrap();
+    let analysis = client
+        .analyze_health(AnalyzeHealthRequest {
+            person: Some(person(70, "Los Angeles")),
+            weight_kg: 70.0,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let expected = rust_lib::analyze_snapshot(&person(70, "Los Angeles").into(), 70.0);
+    assert_eq!(analysis.risk_score, expected.risk_score);
+    assert_eq!(analysis.recommendation, expected.recommendation);
+    assert_eq!(analysis.risk_factors, 2);
+
+    let missing = client
+        .analyze_health(AnalyzeHealthRequest {
+            person: None,
+            weight_kg: 70.0,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(missing.code(), tonic::Code::InvalidArgument);
+
+    let report = client.validate_person(person(200, "")).await.unwrap().into_inner();
+    assert!(!report.valid);
+    let fields: Vec<_> = report.issues.iter().map(|issue| issue.field.as_str()).collect();
+    assert_eq!(fields, ["age", "city"]);
+    // Errors in every validation profile
+    assert!(report.issues.iter().all(|issue| issue.severity() == Severity::Error));
+
+    rust_lib::set_endpoint_limits("/rust_lib.v1.Analysis/ValidatePerson", 0.001, 1, 0).unwrap();
+    client.validate_person(person(30, "Boston")).await.unwrap();
+    let limited = client.validate_person(person(30, "Boston")).await.unwrap_err();
+    assert_eq!(limited.code(), tonic::Code::ResourceExhausted);
+    rust_lib::clear_service_limits();
+
+    tokio::task::spawn_blocking(stop_grpc_server).await.unwrap();
+    assert!(grpc_server_addr().is_none());
+}
@@ -0,0 +1,190 @@
+/* Plain C API of rust_lib, built with the `c-abi` feature (see src/c_abi.rs). */
+
+#ifndef RUST_LIB_C_H
+#define RUST_LIB_C_H
+
+/* Generated by cbindgen from rust-lib/src/c_abi.rs; do not edit by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * Outcome of the fallible functions
+ */
+typedef enum {
+  RUST_LIB_STATUS_OK = 0,
+  /**
+   * A required pointer was null
+   */
+  RUST_LIB_STATUS_NULL_ARGUMENT = 1,
+  /**
+   * A string was not valid UTF-8
+   */
+  RUST_LIB_STATUS_INVALID_UTF8 = 2,
+  /**
+   * The text did not fit; the buffer holds as much of it as did
+   */
+  RUST_LIB_STATUS_BUFFER_TOO_SMALL = 3,
+  /**
+   * An internal error; the outputs are unspecified
+   */
+  RUST_LIB_STATUS_PANIC = 4,
+} RustLibStatus;
+
+/**
+ * A person's fields, borrowed from the caller for the duration of a call
+ */
+typedef struct {
+  const char *name;
+  uint32_t age;
+  double height_m;
+  const char *email;
+  const char *phone;
+  const char *street;
+  const char *city;
+  const char *postal_code;
+} RustLibPersonSnapshot;
+
+/**
+ * HealthAnalysis without its recommendation text; see
+ * rust_lib_recommendation
+ */
+typedef struct {
+  double bmi;
+  double risk_score;
+  double city_risk_factor;
+  /**
+   * Factor from the host's RiskFactorHook (1.0 when none is set)
+   */
+  double extra_risk_factor;
+  /**
+   * How many of age, BMI, city and the hook raised the score
+   */
+  uint32_t risk_factors;
+} RustLibHealthAnalysis;
+
+/**
+ * The contact fields `rust_lib_validate_contact` checks, borrowed like a
+ * snapshot's
+ */
+typedef struct {
+  const char *email;
+  const char *phone;
+  const char *city;
+  const char *postal_code;
+} RustLibContact;
+
+/**
+ * Result of `rust_lib_validate_contact`: 1 where a rule holds, 0 where not
+ *
+ * Bytes rather than `bool` keep the struct blittable for .NET.
+ */
+typedef struct {
+  /**
+   * All four fields are valid
+   */
+  uint8_t valid;
+  uint8_t email_valid;
+  uint8_t phone_valid;
+  uint8_t city_valid;
+  uint8_t postal_valid;
+} RustLibContactValidation;
+
+/**
+ * Result of `rust_lib_validate_person`: 1 where a field is valid, 0 where
+ * validate_person_full reports an issue
+ */
+typedef struct {
+  /**
+   * Every field is valid
+   */
+  uint8_t valid;
+  uint8_t name_valid;
+  uint8_t age_valid;
+  uint8_t height_valid;
+  uint8_t email_valid;
+  uint8_t phone_valid;
+  uint8_t city_valid;
+  uint8_t postal_valid;
+} RustLibPersonValidation;
+
+#ifdef __cplusplus
+extern "C" {
+#endif  // __cplusplus
+
+/**
+ * BMI for a weight and height; 0 for a height of 0 or less
+ */
+double rust_lib_calculate_bmi(double weight_kg, double height_m);
+
+/**
+ * Health analysis of one person, written to `*out`
+ *
+ * # Safety
+ * `person` and `out` are null or valid, and `person`'s strings are null or
+ * NUL-terminated.
+ */
+RustLibStatus rust_lib_analyze_snapshot(const RustLibPersonSnapshot *person,
+                                        double weight_kg,
+                                        RustLibHealthAnalysis *out);
+
+/**
+ * Health analysis of `count` people, `out[i]` for `people[i]` weighing
+ * `weights_kg[i]`
+ *
+ * The pointers may be null when `count` is 0.
+ *
+ * # Safety
+ * `people` and `weights_kg` point to `count` readable elements and `out`
+ * to `count` writable ones; every string is null or NUL-terminated.
+ */
+RustLibStatus rust_lib_analyze_snapshot_batch(const RustLibPersonSnapshot *people,
+                                              const double *weights_kg,
+                                              size_t count,
+                                              RustLibHealthAnalysis *out);
+
+/**
+ * The recommendation for `analysis` in `locale` ("" or null for English),
+ * written to `buf` as a NUL-terminated string
+ *
+ * `*out_len`, if `out_len` is not null, receives the full text length in
+ * bytes without the NUL, so a caller can retry with a larger buffer after
+ * `RUST_LIB_STATUS_BUFFER_TOO_SMALL`. A short buffer still receives the
+ * text cut at a character boundary.
+ *
+ * # Safety
+ * `analysis` is null or valid, `locale` is null or NUL-terminated, `buf`
+ * points to `buf_len` writable bytes (or is null when `buf_len` is 0), and
+ * `out_len` is null or writable.
+ */
+RustLibStatus rust_lib_recommendation(const RustLibHealthAnalysis *analysis,
+                                      const char *locale,
+                                      char *buf,
+                                      size_t buf_len,
+                                      size_t *out_len);
+
+/**
+ * The contact rules of validate_contact, field by field, written to `*out`
+ *
+ * # Safety
+ * `contact` and `out` are null or valid, and `contact`'s strings are null
+ * or NUL-terminated.
+ */
+RustLibStatus rust_lib_validate_contact(const RustLibContact *contact,
+                                        RustLibContactValidation *out);
+
+/**
+ * Every field rule of validate_person_full, written to `*out`
+ *
+ * # Safety
+ * `person` and `out` are null or valid, and `person`'s strings are null or
+ * NUL-terminated.
+ */
+RustLibStatus rust_lib_validate_person(const RustLibPersonSnapshot *person,
+                                       RustLibPersonValidation *out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RUST_LIB_C_H */
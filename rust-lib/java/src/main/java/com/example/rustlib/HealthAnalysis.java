@@ -0,0 +1,28 @@
+package com.example.rustlib;
+
+/** Result of {@link RustLib#analyze}, with the fields of the bridge's HealthAnalysis. */
+public final class HealthAnalysis {
+    public final double bmi;
+    public final double riskScore;
+    public final String recommendation;
+    public final double cityRiskFactor;
+    public final double extraRiskFactor;
+    public final int riskFactors;
+
+    HealthAnalysis(double bmi, double riskScore, String recommendation, double cityRiskFactor,
+            double extraRiskFactor, int riskFactors) {
+        this.bmi = bmi;
+        this.riskScore = riskScore;
+        this.recommendation = recommendation;
+        this.cityRiskFactor = cityRiskFactor;
+        this.extraRiskFactor = extraRiskFactor;
+        this.riskFactors = riskFactors;
+    }
+
+    @Override
+    public String toString() {
+        return String.format(java.util.Locale.ROOT,
+                "HealthAnalysis(bmi=%.1f, riskScore=%.2f, riskFactors=%d)", bmi, riskScore,
+                riskFactors);
+    }
+}
@@ -0,0 +1,33 @@
+package com.example.rustlib;
+
+/**
+ * A person as the health analysis reads them, with the fields of the
+ * bridge's PersonSnapshot. Null strings read as empty.
+ */
+public final class PersonSnapshot {
+    public final String name;
+    public final int age;
+    public final double heightM;
+    public final String email;
+    public final String phone;
+    public final String street;
+    public final String city;
+    public final String postalCode;
+
+    public PersonSnapshot(String name, int age, double heightM, String email, String phone,
+            String street, String city, String postalCode) {
+        this.name = name;
+        this.age = age;
+        this.heightM = heightM;
+        this.email = email;
+        this.phone = phone;
+        this.street = street;
+        this.city = city;
+        this.postalCode = postalCode;
+    }
+
+    /** Only the fields the risk score reads; the contact fields are empty. */
+    public PersonSnapshot(String name, int age, double heightM, String city) {
+        this(name, age, heightM, "", "", "", city, "");
+    }
+}
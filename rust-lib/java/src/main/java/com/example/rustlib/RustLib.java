@@ -0,0 +1,86 @@
+package com.example.rustlib;
+
+import java.util.Locale;
+
+/**
+ * rust-lib's health engine for Android and other JVM hosts.
+ *
+ * <p>Loads librust_lib (built with the {@code jni} feature) and wraps its
+ * natives, which take plain fields and primitive arrays (see
+ * rust-lib/src/jni.rs), in typed methods. Results match the C++ app's for
+ * the same person.
+ */
+public final class RustLib {
+    static {
+        System.loadLibrary("rust_lib");
+    }
+
+    /** Strings per person in analyzeBatch's text array, as jni.rs reads them. */
+    private static final int PERSON_TEXTS = 6;
+    /** Doubles per person in analyzeBatch's result. */
+    private static final int ANALYSIS_VALUES = 5;
+
+    private RustLib() {}
+
+    /** BMI for a weight and height; 0 for a height of 0 or less. */
+    public static native double calculateBmi(double weightKg, double heightM);
+
+    private static native double[] analyzeBatch(
+            String[] text, int[] ages, double[] heightsM, double[] weightsKg);
+
+    private static native String recommendation(
+            double riskScore, int riskFactors, String locale);
+
+    /** Health analysis of one person, as the C++ app's analyze_health. */
+    public static HealthAnalysis analyze(PersonSnapshot person, double weightKg) {
+        return analyzeBatch(new PersonSnapshot[] {person}, new double[] {weightKg})[0];
+    }
+
+    /**
+     * Health analysis of many people in one native call, recommendations in
+     * the default locale.
+     *
+     * @throws IllegalArgumentException if the arrays differ in length
+     */
+    public static HealthAnalysis[] analyzeBatch(PersonSnapshot[] people, double[] weightsKg) {
+        return analyzeBatch(people, weightsKg, Locale.getDefault());
+    }
+
+    /**
+     * Health analysis of many people in one native call, recommendations in
+     * {@code locale}.
+     *
+     * @throws IllegalArgumentException if the arrays differ in length
+     */
+    public static HealthAnalysis[] analyzeBatch(
+            PersonSnapshot[] people, double[] weightsKg, Locale locale) {
+        String[] text = new String[people.length * PERSON_TEXTS];
+        int[] ages = new int[people.length];
+        double[] heightsM = new double[people.length];
+        for (int i = 0; i < people.length; i++) {
+            PersonSnapshot person = people[i];
+            int at = i * PERSON_TEXTS;
+            text[at] = person.name;
+            text[at + 1] = person.email;
+            text[at + 2] = person.phone;
+            text[at + 3] = person.street;
+            text[at + 4] = person.city;
+            text[at + 5] = person.postalCode;
+            ages[i] = person.age;
+            heightsM[i] = person.heightM;
+        }
+
+        double[] values = analyzeBatch(text, ages, heightsM, weightsKg);
+        String language = locale.toLanguageTag();
+        HealthAnalysis[] analyses = new HealthAnalysis[people.length];
+        for (int i = 0; i < people.length; i++) {
+            int at = i * ANALYSIS_VALUES;
+            double riskScore = values[at + 1];
+            int riskFactors = (int) values[at + 4];
+            analyses[i] = new HealthAnalysis(values[at], riskScore,
+                    recommendation(riskScore, riskFactors, language), values[at + 2],
+                    values[at + 3], riskFactors);
+        }
+        return analyses;
+    }
+}
@@ -0,0 +1,83 @@
+package com.example.rustlib;
+
+import java.util.Locale;
+
+/**
+ * Java suite: RustLib over the jni natives, as an Android host calls them.
+ *
+ * <p>Built and run by {@code cargo xtask test-all} (from rust-lib/) against
+ * the release librust_lib.so, so a native whose signature drifts from
+ * RustLib.java fails here. A plain main so it needs no JUnit.
+ */
+public final class RustLibTest {
+    private static int failures = 0;
+
+    private static void check(boolean condition, String what) {
+        if (!condition) {
+            System.err.println("RustLibTest: CHECK failed: " + what);
+            failures++;
+        }
+    }
+
+    private static void testBmi() {
+        check(Math.abs(RustLib.calculateBmi(70.0, 1.75) - 22.857) < 0.001, "bmi");
+        check(RustLib.calculateBmi(70.0, 0.0) == 0.0, "bmi of zero height");
+    }
+
+    private static void testAnalyze() {
+        HealthAnalysis analysis =
+                RustLib.analyze(new PersonSnapshot("Alice", 70, 1.75, "New York"), 70.0);
+        check(analysis.cityRiskFactor == 1.2, "city risk factor");
+        check(analysis.riskFactors == 2, "risk factors");
+        check(Math.abs(analysis.riskScore - 1.5 * 1.2 * analysis.extraRiskFactor) < 1e-9,
+                "risk score");
+        check(analysis.bmi == RustLib.calculateBmi(70.0, 1.75), "bmi matches calculateBmi");
+        check(analysis.toString().startsWith("HealthAnalysis(bmi=22.9, "), "toString");
+
+        // Names outside the BMP survive the UTF-16 copy; nulls read as empty
+        PersonSnapshot unusual = new PersonSnapshot("\uD83D\uDE00 M\u00FCller", 30, 1.75, null);
+        check(RustLib.analyze(unusual, 70.0).cityRiskFactor == 1.0, "null city");
+    }
+
+    private static void testBatch() {
+        PersonSnapshot[] people = {
+            new PersonSnapshot("Alice", 30, 1.75, "Boston"),
+            new PersonSnapshot("Bob", 30, 1.75, "Los Angeles"),
+        };
+        HealthAnalysis[] analyses =
+                RustLib.analyzeBatch(people, new double[] {70.0, 90.0}, Locale.ENGLISH);
+        check(analyses.length == 2, "batch length");
+        check(analyses[0].bmi == RustLib.calculateBmi(70.0, 1.75), "batch bmi");
+        check(analyses[1].cityRiskFactor == 1.1, "batch city risk factor");
+        check(RustLib.analyzeBatch(new PersonSnapshot[0], new double[0]).length == 0,
+                "empty batch");
+
+        try {
+            RustLib.analyzeBatch(people, new double[] {70.0});
+            check(false, "mismatched weights throw");
+        } catch (IllegalArgumentException expected) {
+            check(expected.getMessage().contains("weights"), "mismatch message");
+        }
+    }
+
+    private static void testRecommendation() {
+        HealthAnalysis analysis = RustLib.analyzeBatch(
+                new PersonSnapshot[] {new PersonSnapshot("Alice", 30, 1.75, "Boston")},
+                new double[] {70.0}, Locale.ENGLISH)[0];
+        check(analysis.recommendation.equals(
+                      "Excellent health profile. Maintain current lifestyle. No risk factors found."),
+                "english recommendation: " + analysis.recommendation);
+    }
+
+    public static void main(String[] args) {
+        testBmi();
+        testAnalyze();
+        testBatch();
+        testRecommendation();
+        if (failures > 0) {
+            System.err.println("RustLibTest: " + failures + " check(s) failed");
+            System.exit(1);
+        }
+        System.out.println("RustLibTest: all checks passed");
+    }
+}
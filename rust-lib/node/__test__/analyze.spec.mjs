@@ -0,0 +1,49 @@
+// node:test suite for the addon; `npm run build && npm test` in
+// rust-lib/node/. The expected values are those of the Rust tests in
+// rust-lib/src/engine.rs.
+
+import assert from "node:assert/strict";
+import { createRequire } from "node:module";
+import test from "node:test";
+
+const { analyzeHealth, calculateBmi } = createRequire(import.meta.url)("../index.js");
+
+const person = (fields) =>
+  JSON.stringify({
+    name: "Alice",
+    age: 30,
+    height_m: 1.75,
+    email: "",
+    phone: "",
+    street: "",
+    city: "Boston",
+    postal_code: "",
+    weight_kg: 70,
+    ...fields,
+  });
+
+test("calculateBmi", () => {
+  assert.ok(Math.abs(calculateBmi(70, 1.75) - 22.857) < 0.001);
+  assert.equal(calculateBmi(70, 0), 0);
+});
+
+test("analyzeHealth resolves with the analysis", async () => {
+  const analysis = await analyzeHealth(person({ age: 70, city: "Los Angeles" }));
+  assert.equal(analysis.cityRiskFactor, 1.1);
+  assert.equal(analysis.riskFactors, 2);
+  assert.ok(Math.abs(analysis.riskScore - 1.5 * 1.1) < 1e-9);
+  assert.ok(analysis.recommendation.endsWith("2 risk factors found."));
+});
+
+test("analyzeHealth runs requests concurrently", async () => {
+  const analyses = await Promise.all([70, 80, 90].map((weight_kg) => analyzeHealth(person({ weight_kg }))));
+  assert.deepEqual(
+    analyses.map((analysis) => analysis.bmi),
+    [70, 80, 90].map((weight) => calculateBmi(weight, 1.75)),
+  );
+});
+
+test("analyzeHealth rejects bad JSON", async () => {
+  await assert.rejects(analyzeHealth("{"), /invalid snapshot JSON/);
+  await assert.rejects(analyzeHealth(JSON.stringify({ name: "Alice" })), /invalid snapshot JSON/);
+});
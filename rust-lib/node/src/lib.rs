@@ -0,0 +1,83 @@
+//! Node.js addon `@rust-lib/node`: the health engine for the admin tool
+//!
+//! A napi-rs layer over rust-lib's engine (src/engine.rs), so the
+//! Electron admin tool analyzes people with the code the C++ app calls
+//! instead of spawning the app. N-API is ABI-stable, so one build loads in
+//! Node and in Electron alike.
+//!
+//! `analyzeHealth` takes a person as JSON, in the shape of an NDJSON export
+//! line plus `weight_kg`, and resolves on the libuv thread pool so a large
+//! batch never blocks the UI.
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use rust_lib::ffi;
+use serde::Deserialize;
+
+/// analyzeHealth's argument: the PersonSnapshot fields and the weight
+#[derive(Deserialize)]
+struct SnapshotRequest {
+    #[serde(flatten)]
+    person: ffi::PersonSnapshot,
+    weight_kg: f64,
+}
+
+/// Result of `analyzeHealth`, with the fields of the bridge's
+/// HealthAnalysis (camelCase in JavaScript)
+#[napi(object)]
+pub struct HealthAnalysis {
+    pub bmi: f64,
+    pub risk_score: f64,
+    pub recommendation: String,
+    pub city_risk_factor: f64,
+    pub extra_risk_factor: f64,
+    pub risk_factors: u32,
+}
+
+impl From<ffi::HealthAnalysis> for HealthAnalysis {
+    fn from(analysis: ffi::HealthAnalysis) -> Self {
+        HealthAnalysis {
+            bmi: analysis.bmi,
+            risk_score: analysis.risk_score,
+            recommendation: analysis.recommendation,
+            city_risk_factor: analysis.city_risk_factor,
+            extra_risk_factor: analysis.extra_risk_factor,
+            risk_factors: analysis.risk_factors,
+        }
+    }
+}
+
+pub struct AnalyzeHealth {
+    snapshot_json: String,
+}
+
+#[napi]
+impl Task for AnalyzeHealth {
+    type Output = ffi::HealthAnalysis;
+    type JsValue = HealthAnalysis;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let request: SnapshotRequest = serde_json::from_str(&self.snapshot_json)
+            .map_err(|e| Error::new(Status::InvalidArg, format!("invalid snapshot JSON: {e}")))?;
+        Ok(rust_lib::analyze_snapshot(&request.person, request.weight_kg))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output.into())
+    }
+}
+
+/// Health analysis of one person, as the C++ app's analyze_health
+///
+/// Rejects with an InvalidArg error when the JSON is malformed or lacks a
+/// field.
+#[napi]
+pub fn analyze_health(snapshot_json: String) -> AsyncTask<AnalyzeHealth> {
+    AsyncTask::new(AnalyzeHealth { snapshot_json })
+}
+
+/// BMI for a weight and height; 0 for a height of 0 or less
+#[napi]
+pub fn calculate_bmi(weight_kg: f64, height_m: f64) -> f64 {
+    rust_lib::calculate_bmi(weight_kg, height_m)
+}
@@ -0,0 +1,97 @@
+//! Python module `rust_lib`: the health model for notebooks
+//!
+//! A thin pyo3 layer over rust-lib's engine (src/engine.rs), the code the
+//! C++ app calls through the cxx bridge, so a notebook gets the same BMI,
+//! risk score and validation result for the same person. People are passed
+//! as keyword arguments, one per PersonSnapshot field.
+
+use pyo3::prelude::*;
+use rust_lib::ffi;
+
+/// Result of `analyze_health`, with the fields of the bridge's
+/// HealthAnalysis
+#[pyclass(name = "HealthAnalysis", module = "rust_lib", frozen, get_all)]
+struct HealthAnalysis {
+    bmi: f64,
+    risk_score: f64,
+    recommendation: String,
+    city_risk_factor: f64,
+    extra_risk_factor: f64,
+    risk_factors: u32,
+}
+
+impl From<ffi::HealthAnalysis> for HealthAnalysis {
+    fn from(analysis: ffi::HealthAnalysis) -> Self {
+        HealthAnalysis {
+            bmi: analysis.bmi,
+            risk_score: analysis.risk_score,
+            recommendation: analysis.recommendation,
+            city_risk_factor: analysis.city_risk_factor,
+            extra_risk_factor: analysis.extra_risk_factor,
+            risk_factors: analysis.risk_factors,
+        }
+    }
+}
+
+#[pymethods]
+impl HealthAnalysis {
+    fn __repr__(&self) -> String {
+        format!(
+            "HealthAnalysis(bmi={:.1}, risk_score={:.2}, risk_factors={})",
+            self.bmi, self.risk_score, self.risk_factors
+        )
+    }
+}
+
+/// BMI for a weight and height; 0 for a height of 0 or less
+#[pyfunction]
+fn calculate_bmi(weight_kg: f64, height_m: f64) -> f64 {
+    rust_lib::calculate_bmi(weight_kg, height_m)
+}
+
+/// Health analysis of one person, as the C++ app's analyze_health
+#[pyfunction]
+#[pyo3(signature = (
+    *, age, height_m, weight_kg, name = "", email = "", phone = "", street = "",
+    city = "", postal_code = ""
+))]
+#[allow(clippy::too_many_arguments)]
+fn analyze_health(
+    age: u32,
+    height_m: f64,
+    weight_kg: f64,
+    name: &str,
+    email: &str,
+    phone: &str,
+    street: &str,
+    city: &str,
+    postal_code: &str,
+) -> HealthAnalysis {
+    let person = ffi::PersonSnapshot {
+        name: name.to_string(),
+        age,
+        height_m,
+        email: email.to_string(),
+        phone: phone.to_string(),
+        street: street.to_string(),
+        city: city.to_string(),
+        postal_code: postal_code.to_string(),
+    };
+    rust_lib::analyze_snapshot(&person, weight_kg).into()
+}
+
+/// The C++ app's validate_contact rules on plain strings
+#[pyfunction]
+fn validate_contact(email: &str, phone: &str, city: &str, postal_code: &str) -> bool {
+    rust_lib::contact_fields_valid(email, phone, city, postal_code)
+}
+
+#[pymodule]
+#[pyo3(name = "rust_lib")]
+fn rust_lib_python(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<HealthAnalysis>()?;
+    module.add_function(wrap_pyfunction!(calculate_bmi, module)?)?;
+    module.add_function(wrap_pyfunction!(analyze_health, module)?)?;
+    module.add_function(wrap_pyfunction!(validate_contact, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,36 @@
+# pytest suite for the `rust_lib` Python module
+#
+# Run after `maturin develop` in rust-lib/python/. The expected values are
+# those of the Rust tests in rust-lib/src/engine.rs, which the C++ app's
+# results come from too.
+
+import pytest
+
+import rust_lib
+
+
+def test_calculate_bmi():
+    assert rust_lib.calculate_bmi(70.0, 1.75) == pytest.approx(22.857, abs=0.001)
+    assert rust_lib.calculate_bmi(70.0, 0.0) == 0.0
+
+
+def test_analyze_health():
+    analysis = rust_lib.analyze_health(
+        age=70, height_m=1.75, weight_kg=70.0, name="Alice", city="Los Angeles"
+    )
+    assert analysis.city_risk_factor == 1.1
+    assert analysis.risk_factors == 2
+    assert analysis.risk_score == pytest.approx(1.5 * 1.1)
+    assert analysis.recommendation.endswith("2 risk factors found.")
+    assert repr(analysis) == "HealthAnalysis(bmi=22.9, risk_score=1.65, risk_factors=2)"
+
+
+def test_analyze_health_takes_keywords_only():
+    with pytest.raises(TypeError):
+        rust_lib.analyze_health(30, 1.75, 70.0)
+
+
+def test_validate_contact():
+    assert rust_lib.validate_contact("a@example.com", "555-0100", "Boston", "02101")
+    assert not rust_lib.validate_contact("a@example.com", "555-0100", "", "02101")
+    assert not rust_lib.validate_contact("nobody", "555-0100", "Boston", "02101")
@@ -0,0 +1,327 @@
+//! How alike two addresses are
+//!
+//! `address_similarity` scores two addresses from 0 (nothing in common) to
+//! 1 (the same after normalization), for finding duplicates and grouping
+//! people into households. Each field is compared as a set of tokens,
+//! NFC-normalized, case-folded and without accents, split at anything but
+//! letters and digits, and with the usual abbreviations spelled out. So
+//! "12 Main St." matches "12 main street" and "Zürich" matches "Zurich".
+//!
+//! The street weighs most, then the city, then the postal code. House
+//! numbers (the street's first number) must agree: "12 Main St" and
+//! "14 Main St" are on one street but at different doors, and get half
+//! of the street's share at most. Postal codes are compared without
+//! spaces, a ZIP+4 by its first five digits. A field that is empty in
+//! both addresses is left out, and one empty in only one is a mismatch.
+//!
+//! `address_key` is the normalized address itself, for grouping by equal
+//! addresses (see households.rs).
+
+use std::collections::BTreeSet;
+
+#[cfg(not(feature = "stub-cpp"))]
+use crate::ffi;
+use crate::geocode::AddressQuery;
+use crate::normalize::{combining_class, decompose, nfc_casefold};
+
+const STREET_WEIGHT: f64 = 0.6;
+const CITY_WEIGHT: f64 = 0.25;
+const POSTAL_WEIGHT: f64 = 0.15;
+
+/// Abbreviations of street names and their spelled-out token, sorted
+const STREET_WORDS: &[(&str, &str)] = &[
+    ("apt", "apartment"),
+    ("av", "avenue"),
+    ("ave", "avenue"),
+    ("blvd", "boulevard"),
+    ("ct", "court"),
+    ("dr", "drive"),
+    ("e", "east"),
+    ("hwy", "highway"),
+    ("ln", "lane"),
+    ("n", "north"),
+    ("pl", "place"),
+    ("rd", "road"),
+    ("s", "south"),
+    ("sq", "square"),
+    ("st", "street"),
+    ("ste", "suite"),
+    ("str", "strasse"),
+    ("w", "west"),
+];
+
+/// Abbreviations in city names, sorted: "St. Louis", "Ft. Worth"
+const CITY_WORDS: &[(&str, &str)] = &[
+    ("ft", "fort"),
+    ("mt", "mount"),
+    ("st", "saint"),
+    ("ste", "sainte"),
+];
+
+/// Similarity of two addresses from 0 to 1; symmetric, and 1 for equal
+/// addresses that are not empty
+pub fn address_parts_similarity(a: &AddressQuery<'_>, b: &AddressQuery<'_>) -> f64 {
+    let fields = [
+        (STREET_WEIGHT, street_similarity(a.street, b.street)),
+        (CITY_WEIGHT, token_similarity(a.city, b.city, CITY_WORDS)),
+        (
+            POSTAL_WEIGHT,
+            postal_similarity(a.postal_code, b.postal_code),
+        ),
+    ];
+    let (mut score, mut weight) = (0.0, 0.0);
+    for (field_weight, similarity) in fields {
+        if let Some(similarity) = similarity {
+            score += field_weight * similarity;
+            weight += field_weight;
+        }
+    }
+    if weight == 0.0 {
+        0.0
+    } else {
+        score / weight
+    }
+}
+
+/// An address normalized as address_parts_similarity compares it, as
+/// "street, city, postal code": "12 main street, boston, 02101"
+///
+/// Spellings that differ only in case, accents, punctuation and the
+/// abbreviations of the tables give the same key. Empty for an address
+/// without any of the three.
+pub fn address_key(address: &AddressQuery<'_>) -> String {
+    let (street, city, postal_code) = key_parts(address);
+    if street.is_empty() && city.is_empty() && postal_code.is_empty() {
+        return String::new();
+    }
+    format!("{street}, {city}, {postal_code}")
+}
+
+/// The address_key of an address that locates one dwelling, with a street
+/// and a city or postal code left after normalization; None for any other
+#[cfg(feature = "registry")]
+pub(crate) fn dwelling_key(address: &AddressQuery<'_>) -> Option<String> {
+    let (street, city, postal_code) = key_parts(address);
+    let placed = !street.is_empty() && (!city.is_empty() || !postal_code.is_empty());
+    placed.then(|| format!("{street}, {city}, {postal_code}"))
+}
+
+/// Street, city and postal code of an address key
+fn key_parts(address: &AddressQuery<'_>) -> (String, String, String) {
+    (
+        tokens(address.street, STREET_WORDS).join(" "),
+        tokens(address.city, CITY_WORDS).join(" "),
+        postal_key(address.postal_code),
+    )
+}
+
+/// The normalized tokens of `text`, abbreviations replaced from `words`
+fn tokens(text: &str, words: &[(&str, &str)]) -> Vec<String> {
+    let plain: String = decompose(nfc_casefold(text).chars())
+        .into_iter()
+        .filter(|&c| combining_class(c) == 0)
+        .collect();
+    plain
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(
+            |token| match words.binary_search_by_key(&token, |&(short, _)| short) {
+                Ok(index) => words[index].1.to_string(),
+                Err(_) => token.to_string(),
+            },
+        )
+        .collect()
+}
+
+/// Shared tokens over all tokens; None when both have none
+fn jaccard(a: &BTreeSet<String>, b: &BTreeSet<String>) -> Option<f64> {
+    let union = a.union(b).count();
+    (union > 0).then(|| a.intersection(b).count() as f64 / union as f64)
+}
+
+fn token_similarity(a: &str, b: &str, words: &[(&str, &str)]) -> Option<f64> {
+    let a = tokens(a, words).into_iter().collect();
+    let b = tokens(b, words).into_iter().collect();
+    jaccard(&a, &b)
+}
+
+/// Token similarity of two streets; different house numbers are left out
+/// of the tokens and halve the result
+fn street_similarity(a: &str, b: &str) -> Option<f64> {
+    let mut a: BTreeSet<_> = tokens(a, STREET_WORDS).into_iter().collect();
+    let mut b: BTreeSet<_> = tokens(b, STREET_WORDS).into_iter().collect();
+    let house_number = |tokens: &BTreeSet<String>| {
+        tokens
+            .iter()
+            .find(|token| token.chars().any(|c| c.is_ascii_digit()))
+            .cloned()
+    };
+    let other_door = match (house_number(&a), house_number(&b)) {
+        (Some(x), Some(y)) if x != y => {
+            a.remove(&x);
+            b.remove(&y);
+            true
+        }
+        _ => false,
+    };
+    let similarity = jaccard(&a, &b)?;
+    Some(if other_door {
+        similarity / 2.0
+    } else {
+        similarity
+    })
+}
+
+/// A postal code without spaces, in upper case, and a ZIP+4 "02101-1234"
+/// by its ZIP
+fn postal_key(code: &str) -> String {
+    let compact: String = code
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    match compact.split_once('-') {
+        Some((zip, plus_four)) if zip.len() == 5 && digits(zip) && digits(plus_four) => {
+            zip.to_string()
+        }
+        _ => compact,
+    }
+}
+
+fn postal_similarity(a: &str, b: &str) -> Option<f64> {
+    let (a, b) = (postal_key(a), postal_key(b));
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => None,
+        _ => Some(if a == b { 1.0 } else { 0.0 }),
+    }
+}
+
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn address_similarity(a: &ffi::Address, b: &ffi::Address) -> f64 {
+    fn query(address: &ffi::Address) -> AddressQuery<'_> {
+        AddressQuery {
+            street: ffi::get_address_street(address).to_str().unwrap_or(""),
+            city: ffi::get_address_city(address).to_str().unwrap_or(""),
+            postal_code: ffi::get_address_postal_code(address).to_str().unwrap_or(""),
+        }
+    }
+    address_parts_similarity(&query(a), &query(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn similarity(a: (&str, &str, &str), b: (&str, &str, &str)) -> f64 {
+        let query = |(street, city, postal_code)| AddressQuery {
+            street,
+            city,
+            postal_code,
+        };
+        let forward = address_parts_similarity(&query(a), &query(b));
+        assert_eq!(forward, address_parts_similarity(&query(b), &query(a)));
+        forward
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-12, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn test_spellings_of_one_address_match() {
+        let address = ("12 Main St.", "Boston", "02101");
+        assert_close(similarity(address, address), 1.0);
+        assert_close(
+            similarity(address, ("12 MAIN STREET", "boston", "02101-1234")),
+            1.0,
+        );
+        assert_close(
+            similarity(
+                ("Bahnhofstraße 3", "Zürich", "8001"),
+                ("BAHNHOFSTRASSE 3", "Zurich", "8001"),
+            ),
+            1.0,
+        );
+        assert_close(
+            similarity(
+                ("1 N Broadway", "St. Louis", "63102"),
+                ("1 North Broadway", "Saint Louis", "63102"),
+            ),
+            1.0,
+        );
+        // Precomposed and combining accents
+        assert_close(
+            similarity(
+                ("Rua Augusta", "S\u{e3}o Paulo", ""),
+                ("Rua Augusta", "Sa\u{303}o Paulo", ""),
+            ),
+            1.0,
+        );
+    }
+
+    #[test]
+    fn test_differences_lower_the_score() {
+        let home = ("12 Main St", "Boston", "02101");
+        // Next door: half the street
+        assert_close(
+            similarity(home, ("14 Main St", "Boston", "02101")),
+            0.6 * 0.5 + 0.25 + 0.15,
+        );
+        // Another flat in the building: 3 of 5 street tokens
+        assert_close(
+            similarity(home, ("12 Main St Apt 4", "Boston", "02101")),
+            0.6 * 0.6 + 0.25 + 0.15,
+        );
+        // The same street name in another city
+        assert_close(similarity(home, ("12 Main St", "Chicago", "60601")), 0.6);
+        assert_close(similarity(home, ("1 Elm Rd", "Chicago", "60601")), 0.0);
+    }
+
+    #[test]
+    fn test_empty_fields() {
+        // Only the street is known on both sides
+        assert_close(
+            similarity(("12 Main St", "", ""), ("12 Main St", "", "")),
+            1.0,
+        );
+        // A city on one side only is a mismatch
+        assert_close(
+            similarity(("12 Main St", "Boston", ""), ("12 Main St", "", "")),
+            0.6 / 0.85,
+        );
+        assert_close(similarity(("", "", ""), ("", "", "")), 0.0);
+        assert_close(similarity(("", " ,. ", ""), ("", "", "  ")), 0.0);
+    }
+
+    #[test]
+    fn test_address_key() {
+        let key = |street, city, postal_code| {
+            address_key(&AddressQuery {
+                street,
+                city,
+                postal_code,
+            })
+        };
+        assert_eq!(
+            key("12 Main St.", "Boston", "02101-1234"),
+            "12 main street, boston, 02101"
+        );
+        assert_eq!(
+            key("12  MAIN STREET", "boston", "02101"),
+            key("12 Main St.", "Boston", "02101-1234")
+        );
+        assert_eq!(
+            key("Bahnhofstraße 3", "Zürich", ""),
+            "bahnhofstrasse 3, zurich, "
+        );
+        assert_eq!(key("", " ", ""), "");
+    }
+
+    #[test]
+    fn test_abbreviation_tables_are_sorted() {
+        for words in [STREET_WORDS, CITY_WORDS] {
+            assert!(words.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Global allocator backed by host-provided hooks
+//!
+//! Built only with the `host-allocator` feature. Every Rust heap
+//! allocation in the library then goes through `rust_lib_host_alloc` and
+//! `rust_lib_host_free` (declared in cpp-app/host_allocator.h), so the host
+//! application's memory tracking and arena policies see Rust memory too.
+//! Weak default definitions backed by the system allocator are compiled
+//! in, so the feature links even when the host does not define the hooks.
+
+use std::alloc::{GlobalAlloc, Layout};
+
+extern "C" {
+    fn rust_lib_host_alloc(size: usize, align: usize) -> *mut u8;
+    fn rust_lib_host_free(ptr: *mut u8, size: usize, align: usize);
+}
+
+struct HostAllocator;
+
+// The hooks' contract (host_allocator.h) matches GlobalAlloc: a null
+// return means failure, and free receives the original size and alignment.
+unsafe impl GlobalAlloc for HostAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        rust_lib_host_alloc(layout.size(), layout.align())
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        rust_lib_host_free(ptr, layout.size(), layout.align())
+    }
+}
+
+#[global_allocator]
+static HOST_ALLOCATOR: HostAllocator = HostAllocator;
@@ -0,0 +1,463 @@
+//! Memoized results of analyze_health
+//!
+//! The C++ UI analyzes the person on screen again on every redraw, almost
+//! always with the same fields and weight. analyze_health therefore keeps
+//! the last `cache_size` analyses (an option, see runtime_options.rs; 1024
+//! initially, 0 turns the cache off) by a hash of every person field and
+//! the weight, and answers a repeated call from the cache. Observers and
+//! the event bus still see every call, so the cache is invisible but for
+//! the time saved and `cache_stats()`.
+//!
+//! A result is only reused under the configuration and locale it was
+//! computed with. The locale is part of the hash, so each locale has an
+//! entry of its own; the configuration is not, so after a switch the next
+//! call recomputes the analysis and replaces the entry in place, keeping
+//! its position in the eviction order. The RiskFactorHook is assumed to give the same factor
+//! for the same person; setting or clearing it empties the cache, and a
+//! host whose factors change otherwise calls clear_analysis_cache. The
+//! oldest entry is dropped to make room, which for a redraw loop over a
+//! few people is as good as least-recently-used and needs no bookkeeping
+//! on a hit.
+//!
+//! Each entry remembers when it was computed, so that a retention policy
+//! (see retention.rs) can expire old analyses.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "registry")]
+use std::time::SystemTime;
+
+use crate::config::RiskModelConfig;
+use crate::ffi;
+use crate::memory::{self, HeapSize};
+use crate::person_like::PersonLike;
+use crate::state::Global;
+use crate::zeroize::Zeroize;
+
+/// The `cache_size` a process starts with
+pub(crate) const DEFAULT_CAPACITY: usize = 1024;
+
+struct Entry {
+    person: ffi::PersonSnapshot,
+    weight_kg: f64,
+    /// What the analysis was computed under
+    config: Arc<RiskModelConfig>,
+    locale: Arc<str>,
+    analysis: ffi::HealthAnalysis,
+    /// For the retention policy, which needs the registry
+    #[cfg(feature = "registry")]
+    computed_at: SystemTime,
+}
+
+impl Entry {
+    fn answers(
+        &self,
+        person: &impl PersonLike,
+        weight_kg: f64,
+        config: &Arc<RiskModelConfig>,
+        locale: &str,
+    ) -> bool {
+        // Another person with the same hash is a miss, not a wrong answer
+        self.weight_kg.to_bits() == weight_kg.to_bits()
+            && Arc::ptr_eq(&self.config, config)
+            && *self.locale == *locale
+            && same_person(&self.person, person)
+    }
+}
+
+struct Cache {
+    entries: HashMap<u64, Entry>,
+    /// Keys of `entries`, oldest first
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl Cache {
+    fn new(capacity: usize) -> Self {
+        Cache {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn get(
+        &self,
+        key: u64,
+        person: &impl PersonLike,
+        weight_kg: f64,
+        config: &Arc<RiskModelConfig>,
+        locale: &str,
+    ) -> Option<ffi::HealthAnalysis> {
+        let entry = self.entries.get(&key)?;
+        entry
+            .answers(person, weight_kg, config, locale)
+            .then(|| entry.analysis.clone())
+    }
+
+    fn insert(&mut self, key: u64, entry: Entry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.insert(key, entry).is_none() {
+            self.order.push_back(key);
+        }
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.entries.len() > self.capacity {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    /// Remove the entries for exactly `person`; returns how many
+    #[cfg_attr(not(feature = "registry"), allow(dead_code))]
+    fn remove_person(&mut self, person: &ffi::PersonSnapshot) -> usize {
+        self.remove_where(|entry| same_person(&entry.person, person))
+    }
+
+    /// Remove the entries `remove` is true for; returns how many
+    #[cfg_attr(not(feature = "registry"), allow(dead_code))]
+    fn remove_where(&mut self, remove: impl Fn(&Entry) -> bool) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| !remove(entry));
+        let Cache { entries, order, .. } = self;
+        order.retain(|key| entries.contains_key(key));
+        before - self.entries.len()
+    }
+}
+
+static CACHE: Global<Cache> = Global::new(|| Cache::new(DEFAULT_CAPACITY));
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// The hash the cache files `person`, `weight_kg` and `locale` under
+fn key(person: &impl PersonLike, weight_kg: f64, locale: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    locale.hash(&mut hasher);
+    person.name().hash(&mut hasher);
+    person.age().hash(&mut hasher);
+    person.height_m().to_bits().hash(&mut hasher);
+    person.email().hash(&mut hasher);
+    person.phone().hash(&mut hasher);
+    person.street().hash(&mut hasher);
+    person.city().hash(&mut hasher);
+    person.postal_code().hash(&mut hasher);
+    weight_kg.to_bits().hash(&mut hasher);
+    hasher.finish()
+}
+
+fn same_person(cached: &ffi::PersonSnapshot, person: &impl PersonLike) -> bool {
+    cached.name == person.name()
+        && cached.age == person.age()
+        && cached.height_m.to_bits() == person.height_m().to_bits()
+        && cached.email == person.email()
+        && cached.phone == person.phone()
+        && cached.street == person.street()
+        && cached.city == person.city()
+        && cached.postal_code == person.postal_code()
+}
+
+/// The analysis of `person` at `weight_kg` under `config` and `locale`:
+/// from the cache, or from `compute` and then cached
+///
+/// `compute` runs without the lock held, as it may call into C++.
+pub(crate) fn get_or_compute(
+    person: &impl PersonLike,
+    weight_kg: f64,
+    config: &Arc<RiskModelConfig>,
+    locale: &str,
+    compute: impl FnOnce() -> ffi::HealthAnalysis,
+) -> ffi::HealthAnalysis {
+    let key = key(person, weight_kg, locale);
+    let cached = CACHE.read().get(key, person, weight_kg, config, locale);
+    if let Some(analysis) = cached {
+        HITS.fetch_add(1, Ordering::Relaxed);
+        return analysis;
+    }
+    MISSES.fetch_add(1, Ordering::Relaxed);
+    let analysis = compute();
+    if capacity() > 0 {
+        let entry = Entry {
+            person: person.to_snapshot(),
+            weight_kg,
+            config: Arc::clone(config),
+            locale: Arc::from(locale),
+            analysis: analysis.clone(),
+            #[cfg(feature = "registry")]
+            computed_at: SystemTime::now(),
+        };
+        CACHE.write().insert(key, entry);
+    }
+    analysis
+}
+
+/// Keep at most `capacity` analyses; 0 turns the cache off
+pub(crate) fn set_capacity(capacity: usize) {
+    let mut cache = CACHE.write();
+    cache.capacity = capacity;
+    cache.trim();
+}
+
+/// The `cache_size` option
+pub(crate) fn capacity() -> usize {
+    CACHE.read().capacity
+}
+
+/// Forget every cached analysis; the counters keep counting
+pub(crate) fn clear_analysis_cache() {
+    CACHE.write().clear();
+}
+
+/// clear_analysis_cache, wiping the cached people first
+pub(crate) fn scrub() {
+    let mut cache = CACHE.write();
+    cache
+        .entries
+        .values_mut()
+        .for_each(|entry| entry.person.zeroize());
+    cache.clear();
+}
+
+/// The cached analyses of exactly `person`, with their weights and
+/// locales, oldest first
+#[cfg(all(feature = "registry", feature = "serde"))]
+pub(crate) fn cached_analyses_of(
+    person: &ffi::PersonSnapshot,
+) -> Vec<(f64, Arc<str>, ffi::HealthAnalysis)> {
+    let cache = CACHE.read();
+    cache
+        .order
+        .iter()
+        .filter_map(|key| cache.entries.get(key))
+        .filter(|entry| same_person(&entry.person, person))
+        .map(|entry| (entry.weight_kg, Arc::clone(&entry.locale), entry.analysis.clone()))
+        .collect()
+}
+
+/// Forget the cached analyses of exactly `person`; returns how many
+#[cfg(feature = "registry")]
+pub(crate) fn forget_person(person: &ffi::PersonSnapshot) -> usize {
+    CACHE.write().remove_person(person)
+}
+
+/// Whether a retention sweep expires `entry`: computed before `cutoff`, or
+/// for one of `people`
+#[cfg(feature = "registry")]
+fn expired(entry: &Entry, cutoff: SystemTime, people: &[ffi::PersonSnapshot]) -> bool {
+    entry.computed_at < cutoff || people.iter().any(|person| same_person(&entry.person, person))
+}
+
+/// How many cached analyses expire_analyses would forget
+#[cfg(feature = "registry")]
+pub(crate) fn expired_count(cutoff: SystemTime, people: &[ffi::PersonSnapshot]) -> usize {
+    CACHE
+        .read()
+        .entries
+        .values()
+        .filter(|entry| expired(entry, cutoff, people))
+        .count()
+}
+
+/// Forget the analyses computed before `cutoff` and those of `people`;
+/// returns how many
+#[cfg(feature = "registry")]
+pub(crate) fn expire_analyses(cutoff: SystemTime, people: &[ffi::PersonSnapshot]) -> usize {
+    CACHE
+        .write()
+        .remove_where(|entry| expired(entry, cutoff, people))
+}
+
+/// Heap of the cached analyses and the people they are for
+pub(crate) fn heap_bytes() -> usize {
+    let cache = CACHE.read();
+    let entries: usize = cache
+        .entries
+        .values()
+        .map(|entry| entry.person.heap_bytes() + entry.analysis.heap_bytes() + entry.locale.len())
+        .sum();
+    memory::table_bytes::<(u64, Entry)>(cache.entries.capacity())
+        + entries
+        + cache.order.capacity() * size_of::<u64>()
+}
+
+/// Hits and misses since the process started, and the cache's fill
+pub(crate) fn cache_stats() -> ffi::CacheStats {
+    let cache = CACHE.read();
+    ffi::CacheStats {
+        hits: HITS.load(Ordering::Relaxed),
+        misses: MISSES.load(Ordering::Relaxed),
+        entries: cache.entries.len(),
+        capacity: cache.capacity,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+    use crate::engine::compute_health;
+
+    fn entry(
+        person: &ffi::PersonSnapshot,
+        weight_kg: f64,
+        config: &Arc<RiskModelConfig>,
+        locale: &str,
+    ) -> Entry {
+        Entry {
+            person: person.clone(),
+            weight_kg,
+            config: Arc::clone(config),
+            locale: Arc::from(locale),
+            analysis: compute_health(person, weight_kg),
+            #[cfg(feature = "registry")]
+            computed_at: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_cache_keeps_the_newest_entries() {
+        let config = Arc::new(RiskModelConfig::default());
+        let mut cache = Cache::new(2);
+        let people: Vec<_> = (0..3)
+            .map(|age| snapshot(20 + age, 1.75, "Boston"))
+            .collect();
+        for person in &people {
+            cache.insert(key(person, 70.0, "en"), entry(person, 70.0, &config, "en"));
+        }
+        assert_eq!(cache.entries.len(), 2);
+        let lookup = |cache: &Cache, person: &ffi::PersonSnapshot, weight_kg: f64| {
+            cache.get(
+                key(person, weight_kg, "en"),
+                person,
+                weight_kg,
+                &config,
+                "en",
+            )
+        };
+        assert!(lookup(&cache, &people[0], 70.0).is_none());
+        let hit = lookup(&cache, &people[2], 70.0).unwrap();
+        assert_eq!(hit.bmi, compute_health(&people[2], 70.0).bmi);
+        assert!(lookup(&cache, &people[2], 71.0).is_none());
+
+        // The same key for another person must not answer for it
+        let mut other = people[2].clone();
+        other.city = "New York".to_string();
+        let key = key(&people[2], 70.0, "en");
+        assert!(cache.get(key, &other, 70.0, &config, "en").is_none());
+
+        cache.capacity = 1;
+        cache.trim();
+        assert!(lookup(&cache, &people[1], 70.0).is_none());
+        assert!(lookup(&cache, &people[2], 70.0).is_some());
+    }
+
+    #[test]
+    fn test_entries_are_bound_to_config_and_locale() {
+        let config = Arc::new(RiskModelConfig::default());
+        let person = snapshot(40, 1.80, "Boston");
+        let key = key(&person, 80.0, "en");
+        let mut cache = Cache::new(4);
+        cache.insert(key, entry(&person, 80.0, &config, "en"));
+        assert!(cache.get(key, &person, 80.0, &config, "en").is_some());
+        assert!(cache.get(key, &person, 80.0, &config, "de").is_none());
+
+        // An equal but reloaded configuration is another model
+        let reloaded = Arc::new(RiskModelConfig::default());
+        assert!(cache.get(key, &person, 80.0, &reloaded, "en").is_none());
+
+        // Removing a person takes every weight and locale, and no one else
+        let other = snapshot(41, 1.80, "Boston");
+        let other_key = super::key(&other, 80.0, "en");
+        cache.insert(super::key(&person, 81.0, "de"), entry(&person, 81.0, &config, "de"));
+        cache.insert(other_key, entry(&other, 80.0, &config, "en"));
+        assert_eq!(cache.remove_person(&person), 2);
+        assert_eq!(cache.order, [other_key]);
+        assert_eq!(cache.remove_person(&person), 0);
+
+        // Expiry by age, or by person
+        #[cfg(feature = "registry")]
+        {
+            let day = std::time::Duration::from_secs(86_400);
+            let mut old = entry(&person, 80.0, &config, "en");
+            old.computed_at -= 2 * day;
+            cache.insert(key, old);
+            cache.insert(super::key(&person, 82.0, "en"), entry(&person, 82.0, &config, "en"));
+            let cutoff = SystemTime::now() - day;
+            assert_eq!(cache.remove_where(|e| expired(e, cutoff, &[])), 1);
+            let people = std::slice::from_ref(&person);
+            assert_eq!(cache.remove_where(|e| expired(e, cutoff, people)), 1);
+            assert_eq!(cache.order, [other_key]);
+        }
+
+        let mut off = Cache::new(0);
+        off.insert(key, entry(&person, 80.0, &config, "en"));
+        assert!(off.entries.is_empty());
+    }
+
+    // The cache is process-wide; this person is analyzed by no other test
+    #[test]
+    fn test_get_or_compute_counts_hits() {
+        let mut person = snapshot(33, 1.70, "Boston");
+        person.name = "cache-hits".to_string();
+        let config = crate::config::current();
+        let mut computed = 0;
+        for _ in 0..3 {
+            let before = cache_stats();
+            let analysis = get_or_compute(&person, 65.0, &config, "en", || {
+                computed += 1;
+                compute_health(&person, 65.0)
+            });
+            assert_eq!(analysis.bmi, compute_health(&person, 65.0).bmi);
+            let after = cache_stats();
+            assert!(after.hits + after.misses > before.hits + before.misses);
+        }
+        assert_eq!(computed, 1);
+        assert_eq!(cache_stats().capacity, DEFAULT_CAPACITY);
+    }
+
+    // This person is analyzed by no other test either
+    #[test]
+    fn test_config_switch_recomputes() {
+        let mut person = snapshot(34, 1.70, "Boston");
+        person.name = "cache-config-switch".to_string();
+        let (first, second) = (
+            Arc::new(RiskModelConfig::default()),
+            Arc::new(RiskModelConfig::default()),
+        );
+        let mut computed = Vec::new();
+        for (config, bmi) in [(&first, 1.0), (&second, 2.0), (&first, 3.0), (&first, 4.0)] {
+            let analysis = get_or_compute(&person, 65.0, config, "en", || {
+                computed.push(bmi);
+                ffi::HealthAnalysis {
+                    bmi,
+                    ..compute_health(&person, 65.0)
+                }
+            });
+            assert_eq!(analysis.bmi, *computed.last().unwrap());
+        }
+        // Never the other configuration's analysis, and one entry for both
+        assert_eq!(computed, [1.0, 2.0, 3.0]);
+        let cache = CACHE.read();
+        let entries: Vec<_> = cache
+            .entries
+            .values()
+            .filter(|entry| same_person(&entry.person, &person))
+            .collect();
+        assert_eq!(entries.len(), 1);
+        assert!(Arc::ptr_eq(&entries[0].config, &first));
+    }
+}
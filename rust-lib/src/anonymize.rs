@@ -0,0 +1,155 @@
+//! Anonymous person ids for analytics
+//!
+//! `anonymous_id` is the HMAC-SHA-256 of a person's name and email under a
+//! secret key the host sets with `set_anonymization_key`, as 64 lowercase
+//! hex digits. The same person gets the same id in every export, run and
+//! process that uses the key, so records can be joined; without the key
+//! the id cannot be computed from a name, nor turned back into one.
+//!
+//! Name and email are trimmed, NFC-normalized and case-folded first, so
+//! "José" with a combining accent and "JOSÉ" give one id. Nothing else
+//! goes in: an id survives a birthday, a move or a new phone number.
+//! Changing the key changes every id.
+//!
+//! HMAC and SHA-256 are the RustCrypto `hmac` and `sha2` crates. The key
+//! and the normalized name and email are wiped once used (see zeroize.rs);
+//! the HMAC state derived from the key, on the stack while an id is
+//! computed, is not, as those crates do not wipe it.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::error::Error;
+use crate::ffi;
+use crate::normalize::nfc_casefold;
+use crate::state::Global;
+use crate::zeroize::Zeroizing;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Shorter keys are refused; HMAC is only as strong as its key
+const MIN_KEY_BYTES: usize = 16;
+
+/// Prefixed to the message, so a future change of the inputs can get a
+/// new version without colliding with these ids
+const VERSION: &[u8] = b"rust-lib anonymous id v1\xff";
+
+/// The key as set; None until then
+static KEY: Global<Option<Zeroizing<Vec<u8>>>> = Global::new(|| None);
+
+/// Use `key` for every later anonymous_id
+pub(crate) fn set_anonymization_key(key: &[u8]) -> Result<(), Error> {
+    if key.len() < MIN_KEY_BYTES {
+        return Err(Error::InvalidArgument(format!(
+            "anonymization key has {} bytes; at least {MIN_KEY_BYTES} are needed",
+            key.len()
+        )));
+    }
+    // Copied once, at its exact length, so the only copy is the one wiped
+    *KEY.write() = Some(Zeroizing::new(key.to_vec()));
+    Ok(())
+}
+
+/// Wipe and forget the key; anonymous_id fails until it is set again
+pub(crate) fn clear_anonymization_key() {
+    *KEY.write() = None;
+}
+
+/// The anonymous id of a person with this name and email
+pub fn anonymous_id_for(name: &str, email: &str) -> Result<String, Error> {
+    let key = KEY.read();
+    let key = key
+        .as_deref()
+        .ok_or(Error::NotConfigured("anonymization key"))?;
+    let name = Zeroizing::new(nfc_casefold(name.trim()));
+    let email = Zeroizing::new(nfc_casefold(email.trim()));
+    let mac = hmac_sha256(key, &[VERSION, name.as_bytes(), b"\xff", email.as_bytes()]);
+    Ok(hex(&mac))
+}
+
+pub(crate) fn anonymous_snapshot_id(snapshot: &ffi::PersonSnapshot) -> Result<String, Error> {
+    anonymous_id_for(&snapshot.name, &snapshot.email)
+}
+
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn anonymous_id(person: &ffi::Person) -> Result<String, Error> {
+    crate::audit::record("anonymous_id", 0, &["name", "email"]);
+    let contact = ffi::get_person_contact(person);
+    anonymous_id_for(
+        &ffi::get_person_name(person).to_string_lossy(),
+        &ffi::get_contact_email(contact).to_string_lossy(),
+    )
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// HMAC-SHA-256 (RFC 2104) over the concatenation of `message`
+fn hmac_sha256(key: &[u8], message: &[&[u8]]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any length");
+    message.iter().for_each(|part| mac.update(part));
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231, test cases 2 and 6
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", &[b"what do ya want ", b"for nothing?"])),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex(&hmac_sha256(
+                &[0xaa; 131],
+                &[b"Test Using Larger Than Block-Size Key - Hash Key First"]
+            )),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    // The key is process-wide, so this is the only test that sets it
+    #[test]
+    fn test_anonymous_ids() {
+        assert_eq!(
+            anonymous_id_for("Jos\u{e9} Garc\u{ed}a", "jose@example.com"),
+            Err(Error::NotConfigured("anonymization key"))
+        );
+        assert_eq!(
+            set_anonymization_key(b"short"),
+            Err(Error::InvalidArgument(
+                "anonymization key has 5 bytes; at least 16 are needed".to_string()
+            ))
+        );
+        set_anonymization_key(b"0123456789abcdef").unwrap();
+        let id = anonymous_id_for("Jos\u{e9} Garc\u{ed}a", "jose@example.com").unwrap();
+        // The id of the message layout above, which must not change
+        assert_eq!(
+            id,
+            "4087aa51e229a1eb8089b8e6c2fdb83356a3e10218a0422a5f2b6a7a415f3f4e"
+        );
+        assert_eq!(
+            anonymous_id_for(" JOSE\u{301} GARCI\u{301}A ", "Jose@Example.com").unwrap(),
+            id
+        );
+        assert_ne!(
+            anonymous_id_for("Jos\u{e9} Garc\u{ed}a", "jose.g@example.com").unwrap(),
+            id
+        );
+        // Name and email do not run into each other
+        assert_ne!(
+            anonymous_id_for("ab", "c").unwrap(),
+            anonymous_id_for("a", "bc").unwrap()
+        );
+
+        set_anonymization_key(&[7; 100]).unwrap();
+        assert_ne!(
+            anonymous_id_for("Jos\u{e9} Garc\u{ed}a", "jose@example.com").unwrap(),
+            id
+        );
+    }
+}
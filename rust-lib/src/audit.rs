@@ -0,0 +1,130 @@
+//! Audit trail of reads of personal data
+//!
+//! With auditing on (`set_audit_enabled(true)`), every function that hands
+//! a person's fields to its caller or checks their contact data records an
+//! `AuditEntry`: the caller's tag, the time, the function and the fields
+//! read, and the registry id when it is by id. The compliance team collects
+//! the entries with `drain_audit_log`.
+//!
+//! The tag is per thread: a host sets it with `set_audit_caller` when a
+//! thread starts serving a user or a job ("ui:alice", "nightly-export"),
+//! and it stays until set again. Reads through the HTTP server are tagged
+//! "http". Analyses read the fields too but return only derived scores,
+//! so they are not recorded.
+//!
+//! Auditing is off until enabled, and costs one atomic load per read then.
+//! The log holds at most `MAX_ENTRIES`; beyond that the oldest entries are
+//! dropped, so drain it more often than that many reads.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ffi;
+use crate::state::Global;
+
+/// Entries kept between drains
+const MAX_ENTRIES: usize = 100_000;
+
+/// The fields of a snapshot, in the order of PersonSnapshot
+pub(crate) const PERSON_FIELDS: &[&str] = &[
+    "name",
+    "age",
+    "height_m",
+    "email",
+    "phone",
+    "street",
+    "city",
+    "postal_code",
+];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static LOG: Global<VecDeque<ffi::AuditEntry>> = Global::new(VecDeque::new);
+
+thread_local! {
+    static CALLER: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Start or stop recording; entries recorded so far stay until drained
+pub(crate) fn set_audit_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Tag the calling thread's later reads with `caller`
+pub(crate) fn set_audit_caller(caller: &str) {
+    CALLER.with(|tag| {
+        let mut tag = tag.borrow_mut();
+        tag.clear();
+        tag.push_str(caller);
+    });
+}
+
+/// The entries recorded since the last drain, oldest first
+pub(crate) fn drain_audit_log() -> Vec<ffi::AuditEntry> {
+    LOG.write().drain(..).collect()
+}
+
+/// Record that `operation` read `fields`, of registered person
+/// `person_id` (0 when not read by id)
+pub(crate) fn record(operation: &str, person_id: u64, fields: &[&str]) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let timestamp_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64);
+    let entry = ffi::AuditEntry {
+        caller: CALLER.with(|tag| tag.borrow().clone()),
+        timestamp_ms,
+        operation: operation.to_string(),
+        person_id,
+        fields: fields.iter().map(|field| field.to_string()).collect(),
+    };
+    let mut log = LOG.write();
+    if log.len() == MAX_ENTRIES {
+        log.pop_front();
+    }
+    log.push_back(entry);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The switch and the log are process-wide, so this is the only test
+    // that turns auditing on, and it looks only at its own operations
+    #[test]
+    fn test_records_while_enabled() {
+        let ours = |entries: Vec<ffi::AuditEntry>| -> Vec<ffi::AuditEntry> {
+            entries
+                .into_iter()
+                .filter(|entry| entry.operation.starts_with("audit-test"))
+                .collect()
+        };
+        record("audit-test-off", 0, &["name"]);
+
+        set_audit_enabled(true);
+        set_audit_caller("compliance-test");
+        record("audit-test-snapshot", 0, PERSON_FIELDS);
+        std::thread::spawn(|| record("audit-test-other-thread", 7, &["email"]))
+            .join()
+            .unwrap();
+        set_audit_enabled(false);
+        record("audit-test-off", 0, &["name"]);
+
+        let entries = ours(drain_audit_log());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].caller, "compliance-test");
+        assert_eq!(entries[0].operation, "audit-test-snapshot");
+        assert_eq!(entries[0].fields.len(), 8);
+        assert!(entries[0].timestamp_ms > 1_600_000_000_000);
+        // Another thread has its own tag
+        assert_eq!(entries[1].caller, "");
+        assert_eq!(
+            (entries[1].person_id, &*entries[1].fields),
+            (7, &["email".to_string()][..])
+        );
+        assert!(ours(drain_audit_log()).is_empty());
+    }
+}
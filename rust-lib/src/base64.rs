@@ -0,0 +1,67 @@
+//! Standard base64 with padding (RFC 4648), for Basic credentials and
+//! encrypted export fields
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = chunk.iter().enumerate().fold(0u32, |triple, (i, &byte)| {
+            triple | (byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// The bytes of `text`; None unless it is base64 as `encode` writes it
+#[cfg(feature = "crypto")]
+pub(crate) fn decode(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let value = |c: u8| ALPHABET.iter().position(|&a| a == c).map(|v| v as u32);
+    let chunks = text.len() / 4;
+    let mut decoded = Vec::with_capacity(chunks * 3);
+    for (index, chunk) in text.as_bytes().chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && index + 1 != chunks) {
+            return None;
+        }
+        let mut triple = 0;
+        for (i, &c) in chunk[..4 - padding].iter().enumerate() {
+            triple |= value(c)? << (18 - 6 * i);
+        }
+        decoded.extend_from_slice(&triple.to_be_bytes()[1..4 - padding]);
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"ab"), "YWI=");
+        assert_eq!(encode(b"alice:s3cret"), "YWxpY2U6czNjcmV0");
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn test_decode() {
+        for bytes in [&b""[..], b"a", b"ab", b"abc", b"\x00\xff\x10\x80"] {
+            assert_eq!(decode(&encode(bytes)).as_deref(), Some(bytes));
+        }
+        for invalid in ["YWI", "YW=I", "Y===", "YQ==YQ==", "YW*="] {
+            assert_eq!(decode(invalid), None, "{invalid}");
+        }
+    }
+}
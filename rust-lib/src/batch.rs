@@ -0,0 +1,461 @@
+//! Batch entry points over person snapshots
+//!
+//! Batches work on `PersonSnapshot`s rather than opaque C++ objects, so the
+//! whole run stays on the Rust side once the snapshots have been taken.
+
+use std::time::Instant;
+
+use crate::cancel::CancelFlag;
+use crate::config::{self, RiskModelConfig};
+use crate::engine::{compute_health_from, compute_health_pooled};
+use crate::error::Error;
+use crate::ffi;
+use crate::kernels::{self, BodyRisk, Chunk};
+use crate::metrics::{self, FfiCall};
+use crate::person_like::PersonLike;
+use crate::string_table::{self, StringTableBuilder};
+
+/// When a batch loop stops or reports, independent of what it computes
+#[derive(Clone, Default)]
+pub(crate) struct BatchControl {
+    pub(crate) cancel: CancelFlag,
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) report_every: usize,
+}
+
+impl BatchControl {
+    pub(crate) fn with_cancel(cancel: CancelFlag) -> Self {
+        BatchControl {
+            cancel,
+            ..Default::default()
+        }
+    }
+}
+
+/// Buffers of a batch call, kept between the calls of a BatchContext (see
+/// batch_context.rs); a call without a context starts from empty ones
+#[derive(Default)]
+pub(crate) struct Scratch {
+    chunk: Chunk,
+    strings: StringTableBuilder,
+    /// The strings and bytes a pooled batch makes room for up front: the
+    /// caller's hint, then the largest table so far
+    string_room: (usize, usize),
+}
+
+impl Scratch {
+    pub(crate) fn with_capacity(capacity: &ffi::BatchCapacity) -> Self {
+        Scratch {
+            chunk: Chunk::with_capacity(capacity.people),
+            strings: StringTableBuilder::default(),
+            string_room: (capacity.distinct_strings, 0),
+        }
+    }
+
+    pub(crate) fn heap_bytes(&self) -> usize {
+        self.chunk.heap_bytes() + self.strings.heap_bytes()
+    }
+}
+
+/// Health analysis for many people at once
+pub fn analyze_health_batch(
+    people: &[ffi::PersonSnapshot],
+    weights_kg: &[f64],
+    cancel_token: u64,
+) -> Result<Vec<ffi::HealthAnalysis>, Error> {
+    metrics::ffi_call(FfiCall::AnalyzeHealthBatch);
+    let control = BatchControl::with_cancel(CancelFlag::for_token(cancel_token));
+    run_batch(people, weights_kg, &control, |_, _| {}).map(|batch| batch.results)
+}
+
+/// Health analysis for many people at once, as `options` ask for it
+///
+/// `weights` are in `options.units`. Unlike cancellation, running out of
+/// time is not an error: whatever was computed so far is returned with
+/// `timed_out` set.
+pub fn analyze_health_batch_with_options(
+    people: &[ffi::PersonSnapshot],
+    weights: &[f64],
+    options: &ffi::AnalysisOptions,
+    cancel_token: u64,
+) -> Result<ffi::BatchAnalysis, Error> {
+    metrics::ffi_call(FfiCall::AnalyzeHealthBatch);
+    options.check()?;
+    let control = BatchControl {
+        cancel: CancelFlag::for_token(cancel_token),
+        deadline: options.deadline(),
+        report_every: 0,
+    };
+    run_batch_with(people, weights, options, &control, |_, _| {})
+}
+
+/// Health analysis for many people at once, reporting progress to C++
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn analyze_health_batch_with_progress(
+    people: &[ffi::PersonSnapshot],
+    weights_kg: &[f64],
+    progress: &ffi::ProgressCallback,
+    report_every: usize,
+    cancel_token: u64,
+) -> Result<Vec<ffi::HealthAnalysis>, Error> {
+    metrics::ffi_call(FfiCall::AnalyzeHealthBatch);
+    let control = BatchControl {
+        report_every,
+        ..BatchControl::with_cancel(CancelFlag::for_token(cancel_token))
+    };
+    run_batch(people, weights_kg, &control, |processed, total| {
+        progress.on_progress(processed, total)
+    })
+    .map(|batch| batch.results)
+}
+
+/// Check that every person has a matching weight
+pub(crate) fn check_weights<P>(people: &[P], weights_kg: &[f64]) -> Result<(), Error> {
+    if people.len() != weights_kg.len() {
+        return Err(Error::LengthMismatch {
+            what: "weights_kg",
+            expected: people.len(),
+            actual: weights_kg.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Shared batch loop
+///
+/// `on_progress` fires after every `report_every` items and always once when
+/// the batch finishes. A `report_every` of 0 means "only at the end".
+/// The cancel flag and deadline are checked before each item; cancellation
+/// discards the results, the deadline keeps what was computed so far.
+pub(crate) fn run_batch(
+    people: &[impl PersonLike],
+    weights_kg: &[f64],
+    control: &BatchControl,
+    on_progress: impl FnMut(usize, usize),
+) -> Result<ffi::BatchAnalysis, Error> {
+    let options = ffi::AnalysisOptions::default();
+    run_batch_with(people, weights_kg, &options, control, on_progress)
+}
+
+/// run_batch with the analysis knobs of `options`, checked by the caller;
+/// its timeout is the caller's to put in `control`
+pub(crate) fn run_batch_with(
+    people: &[impl PersonLike],
+    weights: &[f64],
+    options: &ffi::AnalysisOptions,
+    control: &BatchControl,
+    on_progress: impl FnMut(usize, usize),
+) -> Result<ffi::BatchAnalysis, Error> {
+    run_batch_in(
+        &mut Scratch::default(),
+        people,
+        weights,
+        options,
+        control,
+        on_progress,
+    )
+}
+
+/// run_batch_with on the buffers of `scratch`
+pub(crate) fn run_batch_in(
+    scratch: &mut Scratch,
+    people: &[impl PersonLike],
+    weights: &[f64],
+    options: &ffi::AnalysisOptions,
+    control: &BatchControl,
+    on_progress: impl FnMut(usize, usize),
+) -> Result<ffi::BatchAnalysis, Error> {
+    check_weights(people, weights)?;
+
+    let locale = options.recommendation_locale();
+    let explain = options.include_explanations;
+    let mut results = Vec::with_capacity(people.len());
+    let mut explanations = Vec::with_capacity(if explain { people.len() } else { 0 });
+    let timed_out = drive(
+        &mut scratch.chunk,
+        people,
+        weights,
+        options,
+        control,
+        on_progress,
+        |person, body, config| {
+            let (analysis, explanation) =
+                compute_health_from(person, body, config, locale.as_deref(), explain);
+            results.push(analysis);
+            explanations.extend(explanation);
+        },
+    )?;
+
+    Ok(ffi::BatchAnalysis {
+        results,
+        timed_out,
+        explanations,
+    })
+}
+
+/// analyze_health_batch, with the strings of the results in a string
+/// table (see string_table.rs)
+pub fn analyze_health_batch_pooled(
+    people: &[ffi::PersonSnapshot],
+    weights_kg: &[f64],
+    cancel_token: u64,
+) -> Result<ffi::PooledBatch, Error> {
+    metrics::ffi_call(FfiCall::AnalyzeHealthBatch);
+    pooled_batch_in(&mut Scratch::default(), people, weights_kg, cancel_token)
+}
+
+/// analyze_health_batch_pooled on the buffers of `scratch`
+pub(crate) fn pooled_batch_in(
+    scratch: &mut Scratch,
+    people: &[ffi::PersonSnapshot],
+    weights_kg: &[f64],
+    cancel_token: u64,
+) -> Result<ffi::PooledBatch, Error> {
+    check_weights(people, weights_kg)?;
+
+    let options = ffi::AnalysisOptions::default();
+    let control = BatchControl::with_cancel(CancelFlag::for_token(cancel_token));
+    let locale = options.locale();
+    let (strings, bytes) = scratch.string_room;
+    scratch.strings.reserve(strings, bytes);
+    let mut results = Vec::with_capacity(people.len());
+    let outcome = drive(
+        &mut scratch.chunk,
+        people,
+        weights_kg,
+        &options,
+        &control,
+        |_, _| {},
+        |person, body, config| {
+            results.push(compute_health_pooled(
+                person,
+                body,
+                config,
+                &locale,
+                &mut scratch.strings,
+            ));
+        },
+    );
+    // A cancelled batch leaves the builder empty for the next one
+    let table = scratch.strings.take();
+    outcome?;
+
+    let (strings, bytes) = &mut scratch.string_room;
+    *strings = (*strings).max(table.len());
+    *bytes = (*bytes).max(table.bytes());
+    Ok(ffi::PooledBatch {
+        table_id: string_table::publish(table),
+        results,
+    })
+}
+
+/// The loop of every batch: `analyze` runs for each person in order until
+/// the end, the deadline or cancellation; returns whether the deadline
+/// stopped it
+///
+/// The whole batch is analyzed under the configuration current when it
+/// starts. BMIs and the age and BMI factors are computed a chunk ahead of
+/// the items (see kernels.rs).
+fn drive<P: PersonLike>(
+    chunk: &mut Chunk,
+    people: &[P],
+    weights: &[f64],
+    options: &ffi::AnalysisOptions,
+    control: &BatchControl,
+    mut on_progress: impl FnMut(usize, usize),
+    mut analyze: impl FnMut(&P, BodyRisk, &RiskModelConfig),
+) -> Result<bool, Error> {
+    let total = people.len();
+    let report_every = control.report_every;
+    let config = config::current();
+    let mut processed = 0;
+    let mut timed_out = false;
+
+    for (i, person) in people.iter().enumerate() {
+        if i % kernels::CHUNK == 0 {
+            chunk.fill(&people[i..], &weights[i..], options, &config);
+        }
+        if control.cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        if control
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            timed_out = true;
+            break;
+        }
+
+        analyze(person, chunk.get(i % kernels::CHUNK), &config);
+
+        processed = i + 1;
+        if report_every > 0 && processed % report_every == 0 && processed != total {
+            on_progress(processed, total);
+        }
+    }
+
+    on_progress(processed, total);
+    Ok(timed_out)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn snapshot(age: u32, height_m: f64, city: &str) -> ffi::PersonSnapshot {
+        ffi::PersonSnapshot {
+            name: "Test".to_string(),
+            age,
+            height_m,
+            email: "test@example.com".to_string(),
+            phone: "555-0000".to_string(),
+            street: "1 Test St".to_string(),
+            city: city.to_string(),
+            postal_code: "00000".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_batch_matches_single_analysis() {
+        let people = vec![snapshot(30, 1.75, "New York"), snapshot(70, 1.60, "Boston")];
+        let results = analyze_health_batch(&people, &[70.0, 80.0], 0).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert!((results[0].risk_score - 1.2).abs() < 1e-9);
+        assert!((results[1].bmi - 80.0 / (1.60 * 1.60)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pooled_batch_matches_batch() {
+        let people = vec![
+            snapshot(30, 1.75, "New York"),
+            snapshot(70, 1.60, "Boston"),
+            snapshot(31, 1.75, "New York"),
+        ];
+        let weights = [70.0, 80.0, 70.0];
+        let batch = analyze_health_batch(&people, &weights, 0).unwrap();
+        let pooled = analyze_health_batch_pooled(&people, &weights, 0).unwrap();
+
+        let string = |index| string_table::get_string(pooled.table_id, index).unwrap();
+        for ((person, result), analysis) in people.iter().zip(&batch).zip(&pooled.results) {
+            assert_eq!(analysis.bmi, result.bmi);
+            assert_eq!(analysis.risk_score, result.risk_score);
+            assert_eq!(analysis.risk_factors, result.risk_factors);
+            assert_eq!(string(analysis.recommendation), result.recommendation);
+            assert_eq!(string(analysis.city), person.city);
+        }
+        let (first, third) = (&pooled.results[0], &pooled.results[2]);
+        assert_eq!(first.city, third.city);
+        assert_eq!(first.recommendation, third.recommendation);
+        assert!(string_table::release_string_table(pooled.table_id));
+
+        assert!(analyze_health_batch_pooled(&people, &[], 0).is_err());
+    }
+
+    #[test]
+    fn test_batch_length_mismatch() {
+        let people = vec![snapshot(30, 1.75, "Boston")];
+        assert!(analyze_health_batch(&people, &[], 0).is_err());
+    }
+
+    #[test]
+    fn test_progress_reported_every_n() {
+        let people: Vec<_> = (0..7).map(|_| snapshot(30, 1.75, "Boston")).collect();
+        let weights = vec![70.0; 7];
+        let mut calls = Vec::new();
+
+        let control = BatchControl {
+            report_every: 3,
+            ..Default::default()
+        };
+        run_batch(&people, &weights, &control, |done, total| {
+            calls.push((done, total))
+        })
+        .unwrap();
+
+        assert_eq!(calls, vec![(3, 7), (6, 7), (7, 7)]);
+    }
+
+    #[test]
+    fn test_expired_deadline_returns_partial_result() {
+        let people: Vec<_> = (0..5).map(|_| snapshot(30, 1.75, "Boston")).collect();
+        let control = BatchControl {
+            deadline: Some(Instant::now()),
+            ..Default::default()
+        };
+
+        let batch = run_batch(&people, &[70.0; 5], &control, |_, _| {}).unwrap();
+        assert!(batch.timed_out);
+        assert!(batch.results.len() < 5);
+    }
+
+    #[test]
+    fn test_no_timeout_runs_to_completion() {
+        let people: Vec<_> = (0..5).map(|_| snapshot(30, 1.75, "Boston")).collect();
+        let options = ffi::AnalysisOptions::default();
+
+        let batch = analyze_health_batch_with_options(&people, &[70.0; 5], &options, 0).unwrap();
+        assert!(!batch.timed_out);
+        assert_eq!(batch.results.len(), 5);
+        assert!(batch.explanations.is_empty());
+    }
+
+    #[test]
+    fn test_options_reach_every_analysis() {
+        let people = vec![snapshot(30, 1.75, "Boston"), snapshot(70, 1.60, "Boston")];
+        let options = ffi::AnalysisOptions::builder()
+            .units(ffi::Units::Imperial)
+            .locale("es")
+            .include_explanations(true)
+            .build();
+
+        let batch =
+            analyze_health_batch_with_options(&people, &[154.0, 176.0], &options, 0).unwrap();
+        let in_kg = analyze_health_batch(&people, &[154.0 * 0.453_592_37, 176.0 * 0.453_592_37], 0)
+            .unwrap();
+        assert!((batch.results[1].bmi - in_kg[1].bmi).abs() < 1e-9);
+        assert!(batch.results[0].recommendation.contains("riesgo"));
+        assert_eq!(batch.explanations.len(), 2);
+        assert!(batch.explanations[1].contains("age 70"));
+
+        let unknown = ffi::AnalysisOptions::builder().model("v2").build();
+        assert!(analyze_health_batch_with_options(&people, &[70.0; 2], &unknown, 0).is_err());
+    }
+
+    #[test]
+    fn test_lazy_recommendations_render_from_their_id() {
+        let people = vec![snapshot(30, 1.75, "Boston"), snapshot(70, 1.60, "Boston")];
+        let options = ffi::AnalysisOptions::builder()
+            .locale("de")
+            .lazy_recommendations(true)
+            .build();
+        let lazy = analyze_health_batch_with_options(&people, &[70.0, 80.0], &options, 0).unwrap();
+        let rendered = analyze_health_batch_with_options(
+            &people,
+            &[70.0, 80.0],
+            &ffi::AnalysisOptions::builder().locale("de").build(),
+            0,
+        )
+        .unwrap();
+
+        for (lazy, rendered) in lazy.results.iter().zip(&rendered.results) {
+            assert!(lazy.recommendation.is_empty());
+            assert_eq!(lazy.recommendation_id, rendered.recommendation_id);
+            assert_eq!(
+                crate::render_recommendation(lazy.recommendation_id, "de").unwrap(),
+                rendered.recommendation
+            );
+        }
+    }
+
+    #[test]
+    fn test_cancelled_batch_stops() {
+        let people: Vec<_> = (0..3).map(|_| snapshot(30, 1.75, "Boston")).collect();
+        let token = crate::cancel::create_cancel_token();
+        crate::cancel::cancel(token);
+
+        let result = analyze_health_batch(&people, &[70.0; 3], token);
+        assert_eq!(result.unwrap_err(), Error::Cancelled);
+        crate::cancel::release_cancel_token(token);
+    }
+}
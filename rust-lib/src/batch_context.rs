@@ -0,0 +1,190 @@
+//! Batch scratch space that outlives one call
+//!
+//! A host that runs many small batches in a loop (a cohort split per clinic,
+//! a queue drained a few hundred people at a time) grows the same buffers
+//! on every call: the kernel's chunk, and for pooled batches the string
+//! table and its lookups. A batch context keeps them. C++ creates one with
+//! `create_batch_context`, saying how many people and distinct strings a
+//! batch should have room for, passes its id to the `*_in_context` entry
+//! points, and frees it with `release_batch_context`. Buffers are cleared
+//! between calls rather than freed, and grow to the largest batch seen.
+//!
+//! The results themselves are handed to C++, so they are allocated per
+//! call as always, at their exact size. Two calls on one context take
+//! turns; calls on different contexts run in parallel.
+
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::batch::{self, BatchControl, Scratch};
+use crate::cancel::CancelFlag;
+use crate::error::Error;
+use crate::ffi;
+use crate::leak_check::{self, Handle};
+use crate::memory;
+use crate::metrics::{self, FfiCall};
+use crate::state::Global;
+
+struct Context {
+    scratch: Mutex<Scratch>,
+    /// The scratch's heap as of the last call, so memory_report need not
+    /// wait for a batch in progress
+    heap_bytes: AtomicUsize,
+}
+
+impl Context {
+    /// Run `batch` on the scratch space, once the call before is done
+    fn run<T>(&self, batch: impl FnOnce(&mut Scratch) -> T) -> T {
+        // A batch that panicked leaves buffers that are merely cleared next time
+        let mut scratch = self.scratch.lock().unwrap_or_else(PoisonError::into_inner);
+        let result = batch(&mut scratch);
+        self.heap_bytes
+            .store(scratch.heap_bytes(), Ordering::Relaxed);
+        result
+    }
+}
+
+static CONTEXTS: Global<HashMap<u64, Arc<Context>>> = Global::new(HashMap::new);
+
+static NEXT_CONTEXT: AtomicU64 = AtomicU64::new(1);
+
+fn context(context_id: u64) -> Result<Arc<Context>, Error> {
+    CONTEXTS
+        .read()
+        .get(&context_id)
+        .cloned()
+        .ok_or(Error::UnknownBatchContext(context_id))
+}
+
+pub fn create_batch_context(capacity: &ffi::BatchCapacity) -> u64 {
+    let id = NEXT_CONTEXT.fetch_add(1, Ordering::Relaxed);
+    let scratch = Scratch::with_capacity(capacity);
+    let context = Context {
+        heap_bytes: AtomicUsize::new(scratch.heap_bytes()),
+        scratch: Mutex::new(scratch),
+    };
+    CONTEXTS.write().insert(id, Arc::new(context));
+    leak_check::issued(Handle::BatchContext, id);
+    id
+}
+
+/// Free a context; a batch still using it finishes first
+pub fn release_batch_context(context_id: u64) -> bool {
+    let released = CONTEXTS.write().remove(&context_id).is_some();
+    if released {
+        leak_check::released(Handle::BatchContext, context_id);
+    }
+    released
+}
+
+/// analyze_health_batch_with_options on the buffers of `context_id`
+pub fn analyze_health_batch_in_context(
+    context_id: u64,
+    people: &[ffi::PersonSnapshot],
+    weights: &[f64],
+    options: &ffi::AnalysisOptions,
+    cancel_token: u64,
+) -> Result<ffi::BatchAnalysis, Error> {
+    metrics::ffi_call(FfiCall::AnalyzeHealthBatch);
+    let context = context(context_id)?;
+    options.check()?;
+    let control = BatchControl {
+        cancel: CancelFlag::for_token(cancel_token),
+        deadline: options.deadline(),
+        report_every: 0,
+    };
+    context
+        .run(|scratch| batch::run_batch_in(scratch, people, weights, options, &control, |_, _| {}))
+}
+
+/// analyze_health_batch_pooled on the buffers of `context_id`
+pub fn analyze_health_batch_pooled_in_context(
+    context_id: u64,
+    people: &[ffi::PersonSnapshot],
+    weights_kg: &[f64],
+    cancel_token: u64,
+) -> Result<ffi::PooledBatch, Error> {
+    metrics::ffi_call(FfiCall::AnalyzeHealthBatch);
+    context(context_id)?
+        .run(|scratch| batch::pooled_batch_in(scratch, people, weights_kg, cancel_token))
+}
+
+/// Heap of every context not yet released
+pub(crate) fn heap_bytes() -> usize {
+    let contexts = CONTEXTS.read();
+    let buffers: usize = contexts
+        .values()
+        .map(|context| context.heap_bytes.load(Ordering::Relaxed))
+        .sum();
+    memory::table_bytes::<(u64, Arc<Context>)>(contexts.capacity())
+        + contexts.len() * size_of::<Context>()
+        + buffers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+    use crate::string_table::{get_string, release_string_table};
+
+    #[test]
+    fn test_context_matches_one_off_batches() {
+        let context = create_batch_context(&ffi::BatchCapacity {
+            people: 8,
+            distinct_strings: 4,
+        });
+        let people = vec![snapshot(30, 1.75, "Boston"), snapshot(70, 1.60, "Chicago")];
+        let options = ffi::AnalysisOptions::builder()
+            .include_explanations(true)
+            .build();
+        let one_off =
+            batch::analyze_health_batch_with_options(&people, &[70.0, 80.0], &options, 0).unwrap();
+
+        for _ in 0..3 {
+            let batch =
+                analyze_health_batch_in_context(context, &people, &[70.0, 80.0], &options, 0)
+                    .unwrap();
+            assert_eq!(batch.explanations, one_off.explanations);
+            assert_eq!(batch.results[1].risk_score, one_off.results[1].risk_score);
+
+            // Each pooled batch gets a table of its own
+            let pooled =
+                analyze_health_batch_pooled_in_context(context, &people, &[70.0, 80.0], 0).unwrap();
+            assert_eq!(
+                get_string(pooled.table_id, pooled.results[1].city).unwrap(),
+                "Chicago"
+            );
+            assert!(release_string_table(pooled.table_id));
+        }
+
+        assert!(release_batch_context(context));
+        assert!(!release_batch_context(context));
+        assert_eq!(
+            analyze_health_batch_pooled_in_context(context, &people, &[70.0, 80.0], 0).unwrap_err(),
+            Error::UnknownBatchContext(context)
+        );
+    }
+
+    #[test]
+    fn test_cancelled_batch_leaves_the_context_usable() {
+        let context = create_batch_context(&ffi::BatchCapacity::default());
+        let people = vec![snapshot(30, 1.75, "Boston")];
+        let token = crate::cancel::create_cancel_token();
+        crate::cancel::cancel(token);
+        assert_eq!(
+            analyze_health_batch_pooled_in_context(context, &people, &[70.0], token).unwrap_err(),
+            Error::Cancelled
+        );
+        crate::cancel::release_cancel_token(token);
+
+        let pooled = analyze_health_batch_pooled_in_context(context, &people, &[70.0], 0).unwrap();
+        assert_eq!(
+            get_string(pooled.table_id, pooled.results[0].city).unwrap(),
+            "Boston"
+        );
+        release_string_table(pooled.table_id);
+        release_batch_context(context);
+    }
+}
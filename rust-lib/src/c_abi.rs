@@ -0,0 +1,482 @@
+//! Plain C facade for hosts that cannot use cxx
+//!
+//! Built with the `c-abi` feature. The cxx bridge needs C++ on the host
+//! side; firmware written in C gets the same BMI, health analysis and
+//! validators through the `extern "C"` functions here, which take and fill
+//! POD structs declared in include/rust_lib_c.h. The structs are also
+//! blittable, so .NET calls the same functions through csharp/RustLib.cs,
+//! which `cargo xtask codegen` generates from the header. That header is cbindgen output for
+//! this file (`cbindgen --config cbindgen.toml --output
+//! include/rust_lib_c.h` in rust-lib/), checked in so C builds need no
+//! Rust tooling; regenerate it when a signature here changes.
+//!
+//! Strings are NUL-terminated UTF-8 owned by the caller, and a null string
+//! reads as "". Results are written to caller memory, so nothing returned
+//! has to be freed. A panic stops at the boundary as
+//! `RUST_LIB_STATUS_PANIC` instead of unwinding into C.
+
+use std::ffi::{c_char, CStr};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::ffi;
+
+/// Outcome of the fallible functions
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RustLibStatus {
+    Ok = 0,
+    /// A required pointer was null
+    NullArgument = 1,
+    /// A string was not valid UTF-8
+    InvalidUtf8 = 2,
+    /// The text did not fit; the buffer holds as much of it as did
+    BufferTooSmall = 3,
+    /// An internal error; the outputs are unspecified
+    Panic = 4,
+}
+
+/// A person's fields, borrowed from the caller for the duration of a call
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RustLibPersonSnapshot {
+    pub name: *const c_char,
+    pub age: u32,
+    pub height_m: f64,
+    pub email: *const c_char,
+    pub phone: *const c_char,
+    pub street: *const c_char,
+    pub city: *const c_char,
+    pub postal_code: *const c_char,
+}
+
+/// HealthAnalysis without its recommendation text; see
+/// rust_lib_recommendation
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RustLibHealthAnalysis {
+    pub bmi: f64,
+    pub risk_score: f64,
+    pub city_risk_factor: f64,
+    /// Factor from the host's RiskFactorHook (1.0 when none is set)
+    pub extra_risk_factor: f64,
+    /// How many of age, BMI, city and the hook raised the score
+    pub risk_factors: u32,
+}
+
+impl From<&ffi::HealthAnalysis> for RustLibHealthAnalysis {
+    fn from(analysis: &ffi::HealthAnalysis) -> Self {
+        RustLibHealthAnalysis {
+            bmi: analysis.bmi,
+            risk_score: analysis.risk_score,
+            city_risk_factor: analysis.city_risk_factor,
+            extra_risk_factor: analysis.extra_risk_factor,
+            risk_factors: analysis.risk_factors,
+        }
+    }
+}
+
+/// The contact fields `rust_lib_validate_contact` checks, borrowed like a
+/// snapshot's
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RustLibContact {
+    pub email: *const c_char,
+    pub phone: *const c_char,
+    pub city: *const c_char,
+    pub postal_code: *const c_char,
+}
+
+/// Result of `rust_lib_validate_contact`: 1 where a rule holds, 0 where not
+///
+/// Bytes rather than `bool` keep the struct blittable for .NET.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RustLibContactValidation {
+    /// All four fields are valid
+    pub valid: u8,
+    pub email_valid: u8,
+    pub phone_valid: u8,
+    pub city_valid: u8,
+    pub postal_valid: u8,
+}
+
+/// Result of `rust_lib_validate_person`: 1 where a field is valid, 0 where
+/// validate_person_full reports an issue
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RustLibPersonValidation {
+    /// Every field is valid
+    pub valid: u8,
+    pub name_valid: u8,
+    pub age_valid: u8,
+    pub height_valid: u8,
+    pub email_valid: u8,
+    pub phone_valid: u8,
+    pub city_valid: u8,
+    pub postal_valid: u8,
+}
+
+/// Runs `body`, turning a panic into `RustLibStatus::Panic`
+fn guarded(body: impl FnOnce() -> Result<(), RustLibStatus>) -> RustLibStatus {
+    match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => RustLibStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => RustLibStatus::Panic,
+    }
+}
+
+/// # Safety
+/// `text` is null or points to a NUL-terminated string.
+unsafe fn text(text: *const c_char) -> Result<String, RustLibStatus> {
+    if text.is_null() {
+        return Ok(String::new());
+    }
+    CStr::from_ptr(text)
+        .to_str()
+        .map(str::to_string)
+        .map_err(|_| RustLibStatus::InvalidUtf8)
+}
+
+impl RustLibPersonSnapshot {
+    /// # Safety
+    /// Every string field is null or NUL-terminated.
+    unsafe fn snapshot(&self) -> Result<ffi::PersonSnapshot, RustLibStatus> {
+        Ok(ffi::PersonSnapshot {
+            name: text(self.name)?,
+            age: self.age,
+            height_m: self.height_m,
+            email: text(self.email)?,
+            phone: text(self.phone)?,
+            street: text(self.street)?,
+            city: text(self.city)?,
+            postal_code: text(self.postal_code)?,
+        })
+    }
+}
+
+/// BMI for a weight and height; 0 for a height of 0 or less
+#[no_mangle]
+pub extern "C" fn rust_lib_calculate_bmi(weight_kg: f64, height_m: f64) -> f64 {
+    crate::calculate_bmi(weight_kg, height_m)
+}
+
+/// Health analysis of one person, written to `*out`
+///
+/// # Safety
+/// `person` and `out` are null or valid, and `person`'s strings are null or
+/// NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn rust_lib_analyze_snapshot(
+    person: *const RustLibPersonSnapshot,
+    weight_kg: f64,
+    out: *mut RustLibHealthAnalysis,
+) -> RustLibStatus {
+    guarded(|| {
+        let (Some(person), Some(out)) = (person.as_ref(), out.as_mut()) else {
+            return Err(RustLibStatus::NullArgument);
+        };
+        let snapshot = person.snapshot()?;
+        *out = (&crate::compute_health(&snapshot, weight_kg)).into();
+        Ok(())
+    })
+}
+
+/// Health analysis of `count` people, `out[i]` for `people[i]` weighing
+/// `weights_kg[i]`
+///
+/// The pointers may be null when `count` is 0.
+///
+/// # Safety
+/// `people` and `weights_kg` point to `count` readable elements and `out`
+/// to `count` writable ones; every string is null or NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn rust_lib_analyze_snapshot_batch(
+    people: *const RustLibPersonSnapshot,
+    weights_kg: *const f64,
+    count: usize,
+    out: *mut RustLibHealthAnalysis,
+) -> RustLibStatus {
+    guarded(|| {
+        if count == 0 {
+            return Ok(());
+        }
+        if people.is_null() || weights_kg.is_null() || out.is_null() {
+            return Err(RustLibStatus::NullArgument);
+        }
+        let snapshots = std::slice::from_raw_parts(people, count)
+            .iter()
+            .map(|person| person.snapshot())
+            .collect::<Result<Vec<_>, _>>()?;
+        let weights_kg = std::slice::from_raw_parts(weights_kg, count);
+        // Same lengths and no cancel token: the batch cannot fail
+        let analyses = crate::analyze_health_batch(&snapshots, weights_kg, 0)
+            .expect("batch without cancel token failed");
+        let out = std::slice::from_raw_parts_mut(out, count);
+        for (slot, analysis) in out.iter_mut().zip(&analyses) {
+            *slot = analysis.into();
+        }
+        Ok(())
+    })
+}
+
+/// The recommendation for `analysis` in `locale` ("" or null for English),
+/// written to `buf` as a NUL-terminated string
+///
+/// `*out_len`, if `out_len` is not null, receives the full text length in
+/// bytes without the NUL, so a caller can retry with a larger buffer after
+/// `RUST_LIB_STATUS_BUFFER_TOO_SMALL`. A short buffer still receives the
+/// text cut at a character boundary.
+///
+/// # Safety
+/// `analysis` is null or valid, `locale` is null or NUL-terminated, `buf`
+/// points to `buf_len` writable bytes (or is null when `buf_len` is 0), and
+/// `out_len` is null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn rust_lib_recommendation(
+    analysis: *const RustLibHealthAnalysis,
+    locale: *const c_char,
+    buf: *mut c_char,
+    buf_len: usize,
+    out_len: *mut usize,
+) -> RustLibStatus {
+    guarded(|| {
+        let Some(analysis) = analysis.as_ref() else {
+            return Err(RustLibStatus::NullArgument);
+        };
+        if buf.is_null() && buf_len > 0 {
+            return Err(RustLibStatus::NullArgument);
+        }
+        let locale = text(locale)?;
+        let recommendation =
+            crate::templates::recommendation(analysis.risk_score, analysis.risk_factors, &locale);
+        if let Some(out_len) = out_len.as_mut() {
+            *out_len = recommendation.len();
+        }
+        if buf_len == 0 {
+            return Err(RustLibStatus::BufferTooSmall);
+        }
+
+        let mut copied = recommendation.len().min(buf_len - 1);
+        while !recommendation.is_char_boundary(copied) {
+            copied -= 1;
+        }
+        let buf = std::slice::from_raw_parts_mut(buf.cast::<u8>(), buf_len);
+        buf[..copied].copy_from_slice(&recommendation.as_bytes()[..copied]);
+        buf[copied] = 0;
+        if copied < recommendation.len() {
+            return Err(RustLibStatus::BufferTooSmall);
+        }
+        Ok(())
+    })
+}
+
+/// The contact rules of validate_contact, field by field, written to `*out`
+///
+/// # Safety
+/// `contact` and `out` are null or valid, and `contact`'s strings are null
+/// or NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn rust_lib_validate_contact(
+    contact: *const RustLibContact,
+    out: *mut RustLibContactValidation,
+) -> RustLibStatus {
+    guarded(|| {
+        let (Some(contact), Some(out)) = (contact.as_ref(), out.as_mut()) else {
+            return Err(RustLibStatus::NullArgument);
+        };
+        let email = text(contact.email)?;
+        let phone = text(contact.phone)?;
+        let city = text(contact.city)?;
+        let postal_code = text(contact.postal_code)?;
+        *out = RustLibContactValidation {
+            valid: crate::contact_fields_valid(&email, &phone, &city, &postal_code).into(),
+            email_valid: crate::email_valid(&email).into(),
+            phone_valid: crate::phone_valid(&phone).into(),
+            city_valid: crate::city_valid(&city).into(),
+            postal_valid: crate::postal_valid(&postal_code).into(),
+        };
+        Ok(())
+    })
+}
+
+/// Every field rule of validate_person_full, written to `*out`
+///
+/// # Safety
+/// `person` and `out` are null or valid, and `person`'s strings are null or
+/// NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn rust_lib_validate_person(
+    person: *const RustLibPersonSnapshot,
+    out: *mut RustLibPersonValidation,
+) -> RustLibStatus {
+    guarded(|| {
+        let (Some(person), Some(out)) = (person.as_ref(), out.as_mut()) else {
+            return Err(RustLibStatus::NullArgument);
+        };
+        let report = crate::validate_snapshot(&person.snapshot()?);
+        let field_valid = |field: &str| {
+            let error =
+                |i: &ffi::ValidationIssue| i.field == field && i.severity == ffi::Severity::Error;
+            u8::from(!report.issues.iter().any(error))
+        };
+        *out = RustLibPersonValidation {
+            valid: report.valid.into(),
+            name_valid: field_valid("name"),
+            age_valid: field_valid("age"),
+            height_valid: field_valid("height_m"),
+            email_valid: field_valid("email"),
+            phone_valid: field_valid("phone"),
+            city_valid: field_valid("city"),
+            postal_valid: field_valid("postal_code"),
+        };
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    fn person(city: &CStr) -> RustLibPersonSnapshot {
+        RustLibPersonSnapshot {
+            name: c"Alice".as_ptr(),
+            age: 70,
+            height_m: 1.75,
+            email: ptr::null(),
+            phone: ptr::null(),
+            street: ptr::null(),
+            city: city.as_ptr(),
+            postal_code: ptr::null(),
+        }
+    }
+
+    #[test]
+    fn test_analyze_snapshot_matches_bridge() {
+        let person = person(c"New York");
+        let mut out = RustLibHealthAnalysis::default();
+        let status = unsafe { rust_lib_analyze_snapshot(&person, 70.0, &mut out) };
+        assert_eq!(status, RustLibStatus::Ok);
+
+        let expected = crate::compute_health(&unsafe { person.snapshot() }.unwrap(), 70.0);
+        assert_eq!(out, (&expected).into());
+        assert_eq!(out.city_risk_factor, 1.2);
+        assert_eq!(rust_lib_calculate_bmi(70.0, 1.75), expected.bmi);
+    }
+
+    #[test]
+    fn test_batch() {
+        let people = [person(c"Boston"), person(c"Los Angeles")];
+        let mut out = [RustLibHealthAnalysis::default(); 2];
+        let status = unsafe {
+            rust_lib_analyze_snapshot_batch(
+                people.as_ptr(),
+                [70.0, 90.0].as_ptr(),
+                2,
+                out.as_mut_ptr(),
+            )
+        };
+        assert_eq!(status, RustLibStatus::Ok);
+        assert_eq!(out[1].city_risk_factor, 1.1);
+        assert!(out[1].bmi > out[0].bmi);
+
+        let empty = unsafe {
+            rust_lib_analyze_snapshot_batch(ptr::null(), ptr::null(), 0, ptr::null_mut())
+        };
+        assert_eq!(empty, RustLibStatus::Ok);
+    }
+
+    #[test]
+    fn test_bad_arguments() {
+        let mut out = RustLibHealthAnalysis::default();
+        let status = unsafe { rust_lib_analyze_snapshot(ptr::null(), 70.0, &mut out) };
+        assert_eq!(status, RustLibStatus::NullArgument);
+
+        let latin1 = c"M\xfcller";
+        let status = unsafe { rust_lib_analyze_snapshot(&person(latin1), 70.0, &mut out) };
+        assert_eq!(status, RustLibStatus::InvalidUtf8);
+    }
+
+    #[test]
+    fn test_validators() {
+        let contact = RustLibContact {
+            email: c"a@example.com".as_ptr(),
+            phone: c"555".as_ptr(),
+            city: c"Boston".as_ptr(),
+            postal_code: ptr::null(),
+        };
+        let mut out = RustLibContactValidation::default();
+        let status = unsafe { rust_lib_validate_contact(&contact, &mut out) };
+        assert_eq!(status, RustLibStatus::Ok);
+        assert_eq!(
+            out,
+            RustLibContactValidation {
+                valid: 0,
+                email_valid: 1,
+                phone_valid: 0,
+                city_valid: 1,
+                postal_valid: 0,
+            }
+        );
+
+        let mut person = person(c"");
+        person.height_m = 0.0;
+        let mut out = RustLibPersonValidation::default();
+        let status = unsafe { rust_lib_validate_person(&person, &mut out) };
+        assert_eq!(status, RustLibStatus::Ok);
+        assert_eq!((out.valid, out.name_valid, out.age_valid), (0, 1, 1));
+        assert_eq!(
+            (out.height_valid, out.city_valid, out.email_valid),
+            (0, 0, 0)
+        );
+
+        let status = unsafe { rust_lib_validate_person(&person, ptr::null_mut()) };
+        assert_eq!(status, RustLibStatus::NullArgument);
+    }
+
+    #[test]
+    fn test_recommendation_buffer() {
+        let analysis = RustLibHealthAnalysis {
+            risk_score: 2.34,
+            risk_factors: 3,
+            ..Default::default()
+        };
+        let expected = crate::templates::recommendation(2.34, 3, "ru");
+        let mut len = 0;
+        let mut buf = [1 as c_char; 256];
+        let status = unsafe {
+            rust_lib_recommendation(
+                &analysis,
+                c"ru".as_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut len,
+            )
+        };
+        assert_eq!(status, RustLibStatus::Ok);
+        assert_eq!(len, expected.len());
+        assert_eq!(
+            unsafe { CStr::from_ptr(buf.as_ptr()) }.to_str(),
+            Ok(expected.as_str())
+        );
+
+        // Cyrillic is two bytes per letter: a cut never splits one
+        let status = unsafe {
+            rust_lib_recommendation(
+                &analysis,
+                c"ru".as_ptr(),
+                buf.as_mut_ptr(),
+                4,
+                ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, RustLibStatus::BufferTooSmall);
+        assert_eq!(unsafe { CStr::from_ptr(buf.as_ptr()) }.to_str(), Ok("П"));
+
+        let status = unsafe {
+            rust_lib_recommendation(&analysis, ptr::null(), ptr::null_mut(), 0, &mut len)
+        };
+        assert_eq!(status, RustLibStatus::BufferTooSmall);
+        assert_eq!(len, crate::templates::recommendation(2.34, 3, "en").len());
+    }
+}
@@ -0,0 +1,81 @@
+//! Cancellation tokens shared between C++ and Rust
+//!
+//! C++ creates a token, passes it into batch or async calls, and calls
+//! `cancel(token)` to stop them. Token 0 is never issued and means
+//! "not cancellable".
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::leak_check::{self, Handle};
+use crate::state::Global;
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+static TOKENS: Global<HashMap<u64, Arc<AtomicBool>>> = Global::new(HashMap::new);
+
+/// Rust-side view of a token, checked by long-running loops
+#[derive(Clone, Default)]
+pub(crate) struct CancelFlag(Option<Arc<AtomicBool>>);
+
+impl CancelFlag {
+    /// Look up the flag for a token; 0 and unknown tokens never cancel
+    pub(crate) fn for_token(token: u64) -> Self {
+        CancelFlag(TOKENS.read().get(&token).cloned())
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.0
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+}
+
+pub(crate) fn create_cancel_token() -> u64 {
+    let token = NEXT_TOKEN.fetch_add(1, Ordering::Relaxed);
+    TOKENS
+        .write()
+        .insert(token, Arc::new(AtomicBool::new(false)));
+    leak_check::issued(Handle::CancelToken, token);
+    token
+}
+
+pub(crate) fn cancel(token: u64) {
+    // Setting the flag is atomic, so a read lock is enough
+    if let Some(flag) = TOKENS.read().get(&token) {
+        flag.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Work that already holds the flag keeps seeing its final state
+pub(crate) fn release_cancel_token(token: u64) {
+    if TOKENS.write().remove(&token).is_some() {
+        leak_check::released(Handle::CancelToken, token);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_sets_flag() {
+        let token = create_cancel_token();
+        let flag = CancelFlag::for_token(token);
+        assert!(!flag.is_cancelled());
+
+        cancel(token);
+        assert!(flag.is_cancelled());
+
+        release_cancel_token(token);
+        assert!(flag.is_cancelled());
+        assert!(!CancelFlag::for_token(token).is_cancelled());
+    }
+
+    #[test]
+    fn test_token_zero_never_cancels() {
+        cancel(0);
+        assert!(!CancelFlag::for_token(0).is_cancelled());
+    }
+}
@@ -0,0 +1,1215 @@
+//! Thresholds and factors of the health model, from a TOML file
+//!
+//! `load_config` replaces the built-in values for every analysis and
+//! validation that starts afterwards. They are in default_config.toml,
+//! which explains each key; the library embeds that file, and
+//! `dump_default_config` returns it so operators can start their own from
+//! the exact defaults of the version they run. A test holds the file and
+//! `RiskModelConfig::default` to the same values.
+//!
+//! Every key is optional and keeps its default when left out, but a
+//! `[city_risk]` table replaces the whole default one. Unknown tables and
+//! keys are errors, so a typo cannot silently fall back to a default, and
+//! so are values out of range: a factor or cutoff that is not positive,
+//! cutoffs out of order. The whole file is checked either way, and
+//! `load_config_with_report` returns each problem with its line.
+//! No TOML crate is vendored: the file is parsed here, and only tables of
+//! `key = value` pairs with numbers and basic strings are accepted.
+//!
+//! For twelve-factor deployments every key can also be set through the
+//! environment, as `RUST_LIB_<TABLE>_<KEY>`: `RUST_LIB_BMI_OVERWEIGHT_FROM=27`
+//! or `RUST_LIB_VALIDATION_STRICTNESS=lenient`, say. `RUST_LIB_CITY_RISK`
+//! replaces the city table with a list like `New York=1.2;Los Angeles=1.1`.
+//! From lowest to highest precedence the values come from:
+//!
+//! 1. the built-in defaults
+//! 2. the file of the last `load_config`
+//! 3. the environment, read at the first analysis and at every load_config
+//!
+//! Invalid variables make load_config fail; before any load_config they are
+//! reported on stderr and ignored, as there is no caller to tell.
+//!
+//! A city table of thousands of rows is easier to keep as data of its own
+//! than in the TOML file: `set_city_risk_table` replaces the table in
+//! effect with one from a CSV or JSON file, or from JSON text, and keeps
+//! the other settings. Cities not in the table get a factor of 1.0.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::sync::Arc;
+
+use crate::error::Error;
+use crate::events;
+use crate::ffi;
+use crate::state::Global;
+
+/// The validation profile, which sets how severe each failed rule is
+/// (see validation.rs)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Strictness {
+    /// Every problem is an error
+    Strict,
+    /// A missing contact field is only a warning
+    Standard,
+    /// Contact fields may be missing, and a malformed one is a warning
+    Lenient,
+}
+
+impl Strictness {
+    const ALL: [Strictness; 3] = [
+        Strictness::Strict,
+        Strictness::Standard,
+        Strictness::Lenient,
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Strictness::Strict => "strict",
+            Strictness::Standard => "standard",
+            Strictness::Lenient => "lenient",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Strictness> {
+        Strictness::ALL
+            .into_iter()
+            .find(|profile| profile.name() == name)
+    }
+}
+
+/// The model's tunable numbers; see the module docs for their meaning
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RiskModelConfig {
+    pub(crate) underweight_bmi: f64,
+    pub(crate) overweight_bmi: f64,
+    pub(crate) assumed_weight_kg: f64,
+    pub(crate) adult_age: u32,
+    pub(crate) senior_age: u32,
+    pub(crate) age_risk_factor: f64,
+    pub(crate) bmi_risk_factor: f64,
+    pub(crate) city_risk: BTreeMap<String, f64>,
+    pub(crate) strictness: Strictness,
+    pub(crate) max_age: u32,
+    pub(crate) max_height_m: f64,
+}
+
+impl Default for RiskModelConfig {
+    fn default() -> Self {
+        RiskModelConfig {
+            underweight_bmi: 18.5,
+            overweight_bmi: 25.0,
+            assumed_weight_kg: 70.0,
+            adult_age: 18,
+            senior_age: 65,
+            age_risk_factor: 1.5,
+            bmi_risk_factor: 1.3,
+            city_risk: [("New York", 1.2), ("Los Angeles", 1.1)]
+                .into_iter()
+                .map(|(city, factor)| (city.to_string(), factor))
+                .collect(),
+            strictness: Strictness::Strict,
+            max_age: 150,
+            max_height_m: 3.0,
+        }
+    }
+}
+
+impl RiskModelConfig {
+    /// The risk factor of living in `city`
+    pub(crate) fn city_risk(&self, city: &str) -> f64 {
+        self.city_risk.get(city).copied().unwrap_or(1.0)
+    }
+
+    /// Set `table.key`, one of SETTINGS
+    fn set(&mut self, table: &str, key: &str, value: &Value) -> Result<(), String> {
+        let number = || {
+            value
+                .number()
+                .ok_or_else(|| format!("{key} must be a number"))
+        };
+        let count = || {
+            value
+                .count()
+                .ok_or_else(|| format!("{key} must be a whole number"))
+        };
+        match (table, key) {
+            ("bmi", "underweight_below") => self.underweight_bmi = number()?,
+            ("bmi", "overweight_from") => self.overweight_bmi = number()?,
+            ("bmi", "assumed_weight_kg") => self.assumed_weight_kg = number()?,
+            ("age", "adult_from") => self.adult_age = count()?,
+            ("age", "senior_above") => self.senior_age = count()?,
+            ("risk", "age_factor") => self.age_risk_factor = number()?,
+            ("risk", "bmi_factor") => self.bmi_risk_factor = number()?,
+            ("validation", "strictness") => {
+                self.strictness = match value {
+                    Value::String(name) => Strictness::from_name(name),
+                    _ => None,
+                }
+                .ok_or(PROFILES)?
+            }
+            ("validation", "max_age") => self.max_age = count()?,
+            ("validation", "max_height_m") => self.max_height_m = number()?,
+            (table, key) => return Err(format!("unknown setting {table}.{key}")),
+        }
+        Ok(())
+    }
+}
+
+/// Where the value of a setting came from
+#[derive(Clone, Debug, PartialEq)]
+enum Origin {
+    Default,
+    /// A line of the config file
+    Line(usize),
+    /// An environment variable, by name
+    Env(String),
+}
+
+/// One problem with a configuration, and where it was found
+#[derive(Debug, PartialEq)]
+struct Issue {
+    origin: Origin,
+    /// `table.key`, or "" for a line that sets nothing
+    setting: String,
+    message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.origin {
+            Origin::Default => write!(f, "{}", self.message),
+            Origin::Line(line) => write!(f, "line {line}: {}", self.message),
+            Origin::Env(name) => write!(f, "{name}: {}", self.message),
+        }
+    }
+}
+
+/// A configuration being put together from the defaults, a file and the
+/// environment, with the origin of each setting and every problem so far
+#[derive(Default)]
+struct Draft {
+    config: RiskModelConfig,
+    origins: HashMap<String, Origin>,
+    issues: Vec<Issue>,
+}
+
+impl Draft {
+    /// The defaults with the settings of a TOML document applied
+    fn from_toml(text: &str) -> Self {
+        let (entries, issues) = parse_toml(text);
+        let mut draft = Draft {
+            issues,
+            ..Draft::default()
+        };
+        let mut cities = None;
+        for Entry {
+            line,
+            table,
+            key,
+            value,
+        } in entries
+        {
+            let (setting, result) = match (table.as_str(), key.as_str()) {
+                ("city_risk", city) => {
+                    let result = match value.number() {
+                        Some(factor) => {
+                            cities
+                                .get_or_insert_with(BTreeMap::new)
+                                .insert(city.to_string(), factor);
+                            Ok(())
+                        }
+                        None => Err(format!("{city:?} must be a number")),
+                    };
+                    (format!("city_risk.{city}"), result)
+                }
+                ("", key) => (key.to_string(), Err(format!("{key} is outside any table"))),
+                (table, key) => (
+                    format!("{table}.{key}"),
+                    draft.config.set(table, key, &value),
+                ),
+            };
+            draft.record(setting, Origin::Line(line), result);
+        }
+        if let Some(cities) = cities {
+            draft.config.city_risk = cities;
+        }
+        draft
+    }
+
+    /// This with the RUST_LIB_* overrides that `var` finds applied
+    fn with_env(mut self, var: impl Fn(&str) -> Option<String>) -> Self {
+        for (table, key) in SETTINGS {
+            let name = env_name(table, key);
+            let Some(raw) = var(&name) else {
+                continue;
+            };
+            // Plain text reads as a string: RUST_LIB_VALIDATION_STRICTNESS=lenient
+            let value =
+                parse_value(raw.trim()).unwrap_or_else(|| Value::String(raw.trim().to_string()));
+            let result = self.config.set(table, key, &value);
+            self.record(format!("{table}.{key}"), Origin::Env(name), result);
+        }
+        if let Some(raw) = var(CITY_RISK_VAR) {
+            let origin = Origin::Env(CITY_RISK_VAR.to_string());
+            match parse_city_list(&raw) {
+                Ok(cities) => {
+                    self.origins
+                        .retain(|setting, _| !setting.starts_with("city_risk."));
+                    for city in cities.keys() {
+                        self.origins
+                            .insert(format!("city_risk.{city}"), origin.clone());
+                    }
+                    self.config.city_risk = cities;
+                }
+                Err(message) => self.issues.push(Issue {
+                    origin,
+                    setting: "city_risk".to_string(),
+                    message,
+                }),
+            }
+        }
+        self
+    }
+
+    fn record(&mut self, setting: String, origin: Origin, result: Result<(), String>) {
+        match result {
+            Ok(()) => {
+                self.origins.insert(setting, origin);
+            }
+            Err(message) => self.issues.push(Issue {
+                origin,
+                setting,
+                message,
+            }),
+        }
+    }
+
+    fn origin(&self, setting: &str) -> Origin {
+        self.origins
+            .get(setting)
+            .cloned()
+            .unwrap_or(Origin::Default)
+    }
+
+    /// Add an issue for every value the model cannot work with
+    fn check(&mut self) {
+        let config = &self.config;
+        let mut problems = Vec::new();
+        let positive = [
+            ("bmi.underweight_below", config.underweight_bmi),
+            ("bmi.overweight_from", config.overweight_bmi),
+            ("bmi.assumed_weight_kg", config.assumed_weight_kg),
+            ("risk.age_factor", config.age_risk_factor),
+            ("risk.bmi_factor", config.bmi_risk_factor),
+            ("validation.max_height_m", config.max_height_m),
+        ]
+        .map(|(setting, value)| (setting.to_string(), value));
+        let cities = config
+            .city_risk
+            .iter()
+            .map(|(city, &factor)| (format!("city_risk.{city}"), factor));
+        for (setting, value) in positive.into_iter().chain(cities) {
+            if !(value.is_finite() && value > 0.0) {
+                let message = format!("{setting} must be a positive number, not {value}");
+                problems.push((setting, message));
+            }
+        }
+
+        // Each pair is reported on the line of whichever was set
+        let ordered = [
+            (
+                "bmi.underweight_below",
+                "below",
+                "bmi.overweight_from",
+                config.underweight_bmi < config.overweight_bmi,
+            ),
+            (
+                "age.adult_from",
+                "at most",
+                "age.senior_above",
+                config.adult_age <= config.senior_age,
+            ),
+            (
+                "age.senior_above",
+                "at most",
+                "validation.max_age",
+                config.senior_age <= config.max_age,
+            ),
+        ];
+        for (lower, relation, upper, ok) in ordered {
+            if !ok {
+                let setting = match self.origin(lower) {
+                    Origin::Default => upper,
+                    _ => lower,
+                };
+                let message = format!("{lower} must be {relation} {upper}");
+                problems.push((setting.to_string(), message));
+            }
+        }
+
+        for (setting, message) in problems {
+            let origin = self.origin(&setting);
+            self.issues.push(Issue {
+                origin,
+                setting,
+                message,
+            });
+        }
+    }
+
+    /// The configuration, or every problem with it in file order, then the
+    /// environment's
+    fn finish(mut self) -> Result<RiskModelConfig, Vec<Issue>> {
+        self.check();
+        if self.issues.is_empty() {
+            return Ok(self.config);
+        }
+        self.issues.sort_by_key(|issue| match issue.origin {
+            Origin::Line(line) => (0, line),
+            Origin::Env(_) => (1, 0),
+            Origin::Default => (2, 0),
+        });
+        Err(self.issues)
+    }
+}
+
+const PROFILES: &str = "strictness must be \"strict\", \"standard\" or \"lenient\"";
+
+/// Every `table.key` besides the `[city_risk]` entries
+const SETTINGS: [(&str, &str); 10] = [
+    ("bmi", "underweight_below"),
+    ("bmi", "overweight_from"),
+    ("bmi", "assumed_weight_kg"),
+    ("age", "adult_from"),
+    ("age", "senior_above"),
+    ("risk", "age_factor"),
+    ("risk", "bmi_factor"),
+    ("validation", "strictness"),
+    ("validation", "max_age"),
+    ("validation", "max_height_m"),
+];
+
+/// Replaces `[city_risk]`: "New York=1.2;Los Angeles=1.1"
+const CITY_RISK_VAR: &str = "RUST_LIB_CITY_RISK";
+
+/// The variable overriding `table.key`: RUST_LIB_BMI_OVERWEIGHT_FROM
+fn env_name(table: &str, key: &str) -> String {
+    format!("RUST_LIB_{table}_{key}").to_uppercase()
+}
+
+fn parse_city_list(list: &str) -> Result<BTreeMap<String, f64>, String> {
+    let mut cities = BTreeMap::new();
+    for item in list.split(';').filter(|item| !item.trim().is_empty()) {
+        let (city, factor) = item
+            .rsplit_once('=')
+            .ok_or_else(|| format!("expected City=factor, got {item:?}"))?;
+        let factor = factor
+            .trim()
+            .parse()
+            .map_err(|_| format!("{:?} is not a number", factor.trim()))?;
+        cities.insert(city.trim().to_string(), factor);
+    }
+    Ok(cities)
+}
+
+/// A CSV city table: `city,factor` rows with an optional `city,factor`
+/// header, blank lines and `#` comment lines. A city holding a comma is
+/// written in double quotes, with `""` for a quote in it
+fn parse_city_csv(text: &str) -> Result<BTreeMap<String, f64>, Vec<Issue>> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut cities = BTreeMap::new();
+    let mut issues = Vec::new();
+    let mut first_row = true;
+    for (index, raw) in text.lines().enumerate() {
+        let row = raw.trim();
+        if row.is_empty() || row.starts_with('#') {
+            continue;
+        }
+        if std::mem::take(&mut first_row) && row.eq_ignore_ascii_case("city,factor") {
+            continue;
+        }
+        let mut issue = |setting: String, message: String| {
+            issues.push(Issue {
+                origin: Origin::Line(index + 1),
+                setting,
+                message,
+            })
+        };
+        match parse_csv_row(row) {
+            Ok((city, factor)) => {
+                let setting = format!("city_risk.{city}");
+                if let Err(message) = check_city_factor(&setting, factor) {
+                    issue(setting, message);
+                } else if cities.insert(city, factor).is_some() {
+                    issue(setting, "the city is listed twice".to_string());
+                }
+            }
+            Err(message) => issue(String::new(), message),
+        }
+    }
+    if issues.is_empty() {
+        Ok(cities)
+    } else {
+        Err(issues)
+    }
+}
+
+/// The city and factor of one CSV row
+fn parse_csv_row(row: &str) -> Result<(String, f64), String> {
+    let (city, rest) = match row.strip_prefix('"') {
+        Some(quoted) => parse_quoted_field(quoted).ok_or("unclosed quote")?,
+        None => {
+            let end = row.find(',').unwrap_or(row.len());
+            (row[..end].trim_end().to_string(), &row[end..])
+        }
+    };
+    let factor = rest
+        .trim_start()
+        .strip_prefix(',')
+        .ok_or("expected city,factor")?
+        .trim();
+    if city.is_empty() {
+        return Err("the city is empty".to_string());
+    }
+    let factor = factor
+        .parse()
+        .map_err(|_| format!("{factor:?} is not a number"))?;
+    Ok((city, factor))
+}
+
+/// A CSV field after its opening quote, and the rest of the row after the
+/// closing one
+fn parse_quoted_field(text: &str) -> Option<(String, &str)> {
+    let mut field = String::new();
+    let mut rest = text;
+    loop {
+        let close = rest.find('"')?;
+        field.push_str(&rest[..close]);
+        rest = &rest[close + 1..];
+        match rest.strip_prefix('"') {
+            Some(after) => {
+                field.push('"');
+                rest = after;
+            }
+            None => return Some((field, rest)),
+        }
+    }
+}
+
+/// A JSON city table: an object of city names and factors
+#[cfg(feature = "serde")]
+fn parse_city_json(text: &str) -> Result<BTreeMap<String, f64>, Vec<Issue>> {
+    let issue = |setting: String, message: String| Issue {
+        origin: Origin::Default,
+        setting,
+        message,
+    };
+    let cities: BTreeMap<String, f64> = serde_json::from_str(text).map_err(|e| {
+        vec![issue(
+            String::new(),
+            format!("expected {{\"City\": factor}}: {e}"),
+        )]
+    })?;
+    let issues: Vec<Issue> = cities
+        .iter()
+        .filter_map(|(city, &factor)| {
+            let setting = format!("city_risk.{city}");
+            let message = check_city_factor(&setting, factor).err()?;
+            Some(issue(setting, message))
+        })
+        .collect();
+    if issues.is_empty() {
+        Ok(cities)
+    } else {
+        Err(issues)
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+fn parse_city_json(_: &str) -> Result<BTreeMap<String, f64>, Vec<Issue>> {
+    Err(vec![Issue {
+        origin: Origin::Default,
+        setting: String::new(),
+        message: "JSON city tables need the serde feature".to_string(),
+    }])
+}
+
+/// The rule Draft::check applies to `[city_risk]`
+fn check_city_factor(setting: &str, factor: f64) -> Result<(), String> {
+    if factor.is_finite() && factor > 0.0 {
+        Ok(())
+    } else {
+        Err(format!("{setting} must be a positive number, not {factor}"))
+    }
+}
+
+fn environment(name: &str) -> Option<String> {
+    env::var(name).ok()
+}
+
+/// The defaults with the environment's overrides; invalid overrides are
+/// reported on stderr and ignored, as there is no caller to fail
+fn from_environment() -> RiskModelConfig {
+    match Draft::default().with_env(environment).finish() {
+        Ok(config) => config,
+        Err(issues) => {
+            let why = joined(&issues);
+            eprintln!("rust-lib: ignoring the RUST_LIB_* configuration: {why}");
+            RiskModelConfig::default()
+        }
+    }
+}
+
+fn joined(issues: &[Issue]) -> String {
+    let issues: Vec<String> = issues.iter().map(Issue::to_string).collect();
+    issues.join("; ")
+}
+
+static CONFIG: Global<Arc<RiskModelConfig>> = Global::new(|| Arc::new(from_environment()));
+
+/// The configuration in effect; read once per analysis, so a concurrent
+/// load_config never mixes two configs in one result
+pub(crate) fn current() -> Arc<RiskModelConfig> {
+    Arc::clone(&CONFIG.read())
+}
+
+/// Read the TOML file at `path` and make it the configuration, publishing
+/// ConfigReloaded; on any error the configuration in effect stays, and the
+/// error lists every problem found
+pub(crate) fn load_config(path: &str) -> Result<(), Error> {
+    let text = fs::read_to_string(path).map_err(|e| Error::Io(format!("{path}: {e}")))?;
+    let config = Draft::from_toml(&text)
+        .with_env(environment)
+        .finish()
+        .map_err(|issues| Error::InvalidArgument(format!("{path}: {}", joined(&issues))))?;
+    apply(path, config);
+    Ok(())
+}
+
+/// default_config.toml, the defaults as a TOML file
+const DEFAULT_CONFIG: &str = include_str!("default_config.toml");
+
+/// The defaults as a config file, headed with the library's version
+pub(crate) fn dump_default_config() -> String {
+    format!(
+        "# rust-lib {} defaults, from dump_default_config\n{DEFAULT_CONFIG}",
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// Switch validation to the profile `name`, keeping the rest of the
+/// configuration; a later load_config sets the file's profile again
+pub(crate) fn set_validation_profile(name: &str) -> Result<(), Error> {
+    let profile = Strictness::from_name(name)
+        .ok_or_else(|| Error::InvalidArgument(format!("{name:?}: {PROFILES}")))?;
+    let mut config = CONFIG.write();
+    if config.strictness != profile {
+        *config = Arc::new(RiskModelConfig {
+            strictness: profile,
+            ..RiskModelConfig::clone(&config)
+        });
+    }
+    Ok(())
+}
+
+/// Make the cities in `source` the city table, keeping the other settings;
+/// a later load_config sets the file's table again
+///
+/// `source` is JSON text when it starts with '{', and otherwise the path
+/// of a JSON file if it ends in ".json" and of a CSV file if not. On any
+/// error the table in effect stays, and the error lists every problem.
+pub(crate) fn set_city_risk_table(source: &str) -> Result<(), Error> {
+    let (name, cities) = if source.trim_start().starts_with('{') {
+        ("city risk JSON", parse_city_json(source))
+    } else {
+        let text = fs::read_to_string(source).map_err(|e| Error::Io(format!("{source}: {e}")))?;
+        let cities = if source.ends_with(".json") {
+            parse_city_json(&text)
+        } else {
+            parse_city_csv(&text)
+        };
+        (source, cities)
+    };
+    let cities =
+        cities.map_err(|issues| Error::InvalidArgument(format!("{name}: {}", joined(&issues))))?;
+    let mut config = CONFIG.write();
+    *config = Arc::new(RiskModelConfig {
+        city_risk: cities,
+        ..RiskModelConfig::clone(&config)
+    });
+    Ok(())
+}
+
+/// load_config, reporting each problem of the file separately instead of
+/// throwing; the configuration is only replaced when the report is valid
+pub(crate) fn load_config_with_report(path: &str) -> ffi::ConfigReport {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            let issue = ffi::ConfigIssue {
+                file: path.to_string(),
+                line: 0,
+                setting: String::new(),
+                message: e.to_string(),
+            };
+            return ffi::ConfigReport {
+                valid: false,
+                issues: vec![issue],
+            };
+        }
+    };
+    match Draft::from_toml(&text).with_env(environment).finish() {
+        Ok(config) => {
+            apply(path, config);
+            ffi::ConfigReport {
+                valid: true,
+                issues: Vec::new(),
+            }
+        }
+        Err(issues) => report(path, issues),
+    }
+}
+
+fn apply(path: &str, config: RiskModelConfig) {
+    *CONFIG.write() = Arc::new(config);
+    events::config_reloaded(path);
+}
+
+/// `issues` of the file at `path` as the bridge reports them
+fn report(path: &str, issues: Vec<Issue>) -> ffi::ConfigReport {
+    let issues = issues
+        .into_iter()
+        .map(|issue| {
+            let (file, line) = match issue.origin {
+                Origin::Default => (path.to_string(), 0),
+                Origin::Line(line) => (path.to_string(), line),
+                Origin::Env(name) => (name, 0),
+            };
+            ffi::ConfigIssue {
+                file,
+                line: u32::try_from(line).unwrap_or(u32::MAX),
+                setting: issue.setting,
+                message: issue.message,
+            }
+        })
+        .collect();
+    ffi::ConfigReport {
+        valid: false,
+        issues,
+    }
+}
+
+/// A value of the accepted TOML subset
+#[derive(Debug, PartialEq)]
+enum Value {
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+impl Value {
+    fn number(&self) -> Option<f64> {
+        match *self {
+            Value::Integer(value) => Some(value as f64),
+            Value::Float(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn count(&self) -> Option<u32> {
+        match *self {
+            Value::Integer(value) => u32::try_from(value).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// One `key = value` line, under the `[table]` before it ("" for none)
+struct Entry {
+    line: usize,
+    table: String,
+    key: String,
+    value: Value,
+}
+
+/// The entries of `text`, and an issue for every line that is none
+fn parse_toml(text: &str) -> (Vec<Entry>, Vec<Issue>) {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut issues = Vec::new();
+    // (table, key) of the entries, so a long [city_risk] stays linear
+    let mut seen = HashSet::new();
+    // None under an invalid header, whose keys are skipped
+    let mut table = Some(String::new());
+    for (index, raw) in text.lines().enumerate() {
+        let line = index + 1;
+        let mut issue = |setting: String, message: String| {
+            issues.push(Issue {
+                origin: Origin::Line(line),
+                setting,
+                message,
+            })
+        };
+        let content = strip_comment(raw).trim();
+        if content.is_empty() {
+            continue;
+        }
+        if let Some(name) = content.strip_prefix('[') {
+            table = parse_header(name)
+                .map_err(|message| issue(String::new(), message))
+                .ok();
+            continue;
+        }
+        let Some(table) = &table else {
+            continue;
+        };
+        let (key, value) = match parse_entry(content) {
+            Ok(entry) => entry,
+            Err(message) => {
+                issue(String::new(), message);
+                continue;
+            }
+        };
+        if !seen.insert((table.clone(), key.clone())) {
+            issue(format!("{table}.{key}"), format!("{key} is set twice"));
+            continue;
+        }
+        entries.push(Entry {
+            line,
+            table: table.clone(),
+            key,
+            value,
+        });
+    }
+    (entries, issues)
+}
+
+/// The name of a `[table]` header, given the text after its '['
+fn parse_header(name: &str) -> Result<String, String> {
+    let name = name
+        .strip_suffix(']')
+        .filter(|name| !name.starts_with('['))
+        .ok_or("expected a [table] header")?;
+    bare_key(name.trim()).ok_or_else(|| "invalid table name".to_string())
+}
+
+/// The key and value of a `key = value` line
+fn parse_entry(content: &str) -> Result<(String, Value), String> {
+    let split = if content.starts_with('"') {
+        split_quoted_key(content)
+    } else {
+        content.split_once('=')
+    };
+    let (key, value) = split.ok_or("expected key = value")?;
+    let key = match key.trim() {
+        quoted if quoted.starts_with('"') => parse_string(quoted).ok_or("invalid quoted key")?,
+        bare => bare_key(bare).ok_or("invalid key")?,
+    };
+    let value =
+        parse_value(value.trim()).ok_or("unsupported value; use a number or a \"string\"")?;
+    Ok((key, value))
+}
+
+/// `line` up to a `#` that is not inside a string
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '\\' if in_string => {
+                escaped = !escaped;
+                continue;
+            }
+            '"' if !escaped => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+/// Split `"key" = value` after the closing quote, as the key may hold '='
+fn split_quoted_key(content: &str) -> Option<(&str, &str)> {
+    let close = content[1..].find('"')? + 2;
+    let (key, rest) = content.split_at(close);
+    Some((key, rest.trim_start().strip_prefix('=')?))
+}
+
+fn bare_key(key: &str) -> Option<String> {
+    let valid = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    valid.then(|| key.to_string())
+}
+
+fn parse_value(text: &str) -> Option<Value> {
+    if text.starts_with('"') {
+        return parse_string(text).map(Value::String);
+    }
+    // TOML allows underscores between digits: 1_000
+    let digits = text.replace('_', "");
+    if let Ok(integer) = digits.parse::<i64>() {
+        return Some(Value::Integer(integer));
+    }
+    let float_like = digits
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'));
+    float_like
+        .then(|| digits.parse::<f64>().ok())
+        .flatten()
+        .map(Value::Float)
+}
+
+/// A basic string, `"..."` with backslash escapes, and nothing after it
+fn parse_string(text: &str) -> Option<String> {
+    let mut characters = text.strip_prefix('"')?.chars();
+    let mut string = String::new();
+    loop {
+        match characters.next()? {
+            '"' => break,
+            '\\' => string.push(match characters.next()? {
+                'n' => '\n',
+                't' => '\t',
+                '"' => '"',
+                '\\' => '\\',
+                _ => return None,
+            }),
+            character => string.push(character),
+        }
+    }
+    characters.as_str().trim().is_empty().then_some(string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_from_toml() {
+        let config = Draft::from_toml(
+            r#"
+            # Clinic in Denver
+            [bmi]
+            overweight_from = 27   # integers are numbers too
+
+            [age]
+            adult_from = 21
+
+            [city_risk]
+            "Denver" = 0.9
+            "São Paulo # centro" = 1.05
+
+            [validation]
+            strictness = "lenient"
+            "#,
+        )
+        .finish()
+        .unwrap();
+        assert_eq!(config.overweight_bmi, 27.0);
+        assert_eq!(config.underweight_bmi, 18.5);
+        assert_eq!(config.adult_age, 21);
+        assert_eq!(config.city_risk("Denver"), 0.9);
+        assert_eq!(config.city_risk("São Paulo # centro"), 1.05);
+        // The table replaces the default cities
+        assert_eq!(config.city_risk("New York"), 1.0);
+        assert_eq!(config.strictness, Strictness::Lenient);
+
+        assert_eq!(
+            Draft::from_toml("").finish().unwrap(),
+            RiskModelConfig::default()
+        );
+    }
+
+    #[test]
+    fn test_from_toml_errors() {
+        for (text, error) in [
+            (
+                "[bmi]\nunderweight = 18",
+                "line 2: unknown setting bmi.underweight",
+            ),
+            ("adult_from = 18", "line 1: adult_from is outside any table"),
+            (
+                "[age]\nadult_from = \"18\"",
+                "line 2: adult_from must be a whole number",
+            ),
+            (
+                "[age]\nadult_from = -1",
+                "line 2: adult_from must be a whole number",
+            ),
+            (
+                "[age]\nadult_from = 70",
+                "line 2: age.adult_from must be at most age.senior_above",
+            ),
+            (
+                "[risk]\nage_factor = 0",
+                "line 2: risk.age_factor must be a positive number, not 0",
+            ),
+            ("[risk]\nage_factor = [1, 2]", "line 2: unsupported value"),
+            ("[[cities]]", "line 1: expected a [table] header"),
+            ("[bmi]\nassumed_weight_kg", "line 2: expected key = value"),
+            (
+                "[age]\nadult_from = 1\nadult_from = 2",
+                "line 3: adult_from is set twice",
+            ),
+            (
+                "[validation]\nstrictness = \"loose\"",
+                "line 2: strictness must be",
+            ),
+        ] {
+            let issues = Draft::from_toml(text).finish().unwrap_err();
+            assert_eq!(issues.len(), 1, "{text:?}: {issues:?}");
+            let why = issues[0].to_string();
+            assert!(why.starts_with(error), "{text:?}: {why}");
+        }
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        assert_eq!(
+            env_name("bmi", "overweight_from"),
+            "RUST_LIB_BMI_OVERWEIGHT_FROM"
+        );
+        let env: HashMap<&str, &str> = [
+            ("RUST_LIB_AGE_ADULT_FROM", "21"),
+            ("RUST_LIB_VALIDATION_STRICTNESS", "lenient"),
+            ("RUST_LIB_CITY_RISK", "Denver=0.9; São Paulo = 1.05;"),
+        ]
+        .into();
+        let lookup = |name: &str| env.get(name).map(|value| value.to_string());
+
+        // The environment wins over the file
+        let file = Draft::from_toml("[age]\nadult_from = 19\nsenior_above = 70");
+        let config = file.with_env(lookup).finish().unwrap();
+        assert_eq!((config.adult_age, config.senior_age), (21, 70));
+        assert_eq!(config.strictness, Strictness::Lenient);
+        assert_eq!(config.city_risk("São Paulo"), 1.05);
+        assert_eq!(config.city_risk("New York"), 1.0);
+
+        for (name, value, error) in [
+            (
+                "RUST_LIB_AGE_ADULT_FROM",
+                "adult",
+                "RUST_LIB_AGE_ADULT_FROM: adult_from must be",
+            ),
+            (
+                "RUST_LIB_CITY_RISK",
+                "Denver",
+                "RUST_LIB_CITY_RISK: expected City=factor",
+            ),
+            (
+                "RUST_LIB_CITY_RISK",
+                "Denver=low",
+                "RUST_LIB_CITY_RISK: \"low\" is not a number",
+            ),
+        ] {
+            let lookup = |wanted: &str| (wanted == name).then(|| value.to_string());
+            let issues = Draft::default().with_env(lookup).finish().unwrap_err();
+            let why = joined(&issues);
+            assert!(why.starts_with(error), "{name}={value}: {why}");
+        }
+    }
+
+    #[test]
+    fn test_report_lists_every_problem() {
+        let text = "[bmi]\nunderweight_below = 26\nunknown = 1\n\n[age]\nadult_from = \"18\"\n[city_risk]\n\"Denver\" = -0.5\n[oops\nsenior_above = 200\n";
+        let env = |name: &str| (name == "RUST_LIB_RISK_AGE_FACTOR").then(|| "high".to_string());
+        let issues = Draft::from_toml(text).with_env(env).finish().unwrap_err();
+        let found: Vec<(Origin, &str)> = issues
+            .iter()
+            .map(|issue| (issue.origin.clone(), issue.setting.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                // The cutoffs are out of order: the file set the lower one
+                (Origin::Line(2), "bmi.underweight_below"),
+                (Origin::Line(3), "bmi.unknown"),
+                (Origin::Line(6), "age.adult_from"),
+                (Origin::Line(8), "city_risk.Denver"),
+                // The keys under the broken header are skipped
+                (Origin::Line(9), ""),
+                (
+                    Origin::Env("RUST_LIB_RISK_AGE_FACTOR".to_string()),
+                    "risk.age_factor"
+                ),
+            ]
+        );
+
+        let report = report("risk.toml", issues);
+        assert!(!report.valid);
+        let first = &report.issues[0];
+        assert_eq!((first.file.as_str(), first.line), ("risk.toml", 2));
+        assert_eq!(
+            first.message,
+            "bmi.underweight_below must be below bmi.overweight_from"
+        );
+        let last = report.issues.last().unwrap();
+        assert_eq!(
+            (last.file.as_str(), last.line),
+            ("RUST_LIB_RISK_AGE_FACTOR", 0)
+        );
+
+        // Out of range against a default, the setting that was given is named
+        let issues = Draft::from_toml("[validation]\nmax_age = 60")
+            .finish()
+            .unwrap_err();
+        assert_eq!(issues[0].origin, Origin::Line(2));
+        assert_eq!(issues[0].setting, "validation.max_age");
+    }
+
+    #[test]
+    fn test_default_config_matches_the_defaults() {
+        let dumped = dump_default_config();
+        assert!(dumped.starts_with(&format!("# rust-lib {} ", env!("CARGO_PKG_VERSION"))));
+        let config = Draft::from_toml(&dumped).finish().unwrap();
+        assert_eq!(config, RiskModelConfig::default());
+        // Every setting is in the file, not left to the defaults
+        let (entries, _) = parse_toml(DEFAULT_CONFIG);
+        for (table, key) in SETTINGS {
+            assert!(
+                entries.iter().any(|e| e.table == table && e.key == key),
+                "{table}.{key}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_city_csv() {
+        let cities = parse_city_csv(
+            "\u{feff}City,Factor\n\
+             # Clinic regions\n\
+             Denver, 0.9\n\
+             \n\
+             \"Washington, D.C.\",1.1\n\
+             \"The \"\"Big\"\" Apple\" , 1.2\n",
+        )
+        .unwrap();
+        assert_eq!(
+            cities.into_iter().collect::<Vec<_>>(),
+            [
+                ("Denver".to_string(), 0.9),
+                ("The \"Big\" Apple".to_string(), 1.2),
+                ("Washington, D.C.".to_string(), 1.1),
+            ]
+        );
+
+        let issues =
+            parse_city_csv("Denver,0.9\nDenver,1\nBoston\nMiami,high\nRome,0\n\"Oslo,1\n,1\n")
+                .unwrap_err();
+        let found: Vec<String> = issues.iter().map(Issue::to_string).collect();
+        assert_eq!(
+            found,
+            [
+                "line 2: the city is listed twice",
+                "line 3: expected city,factor",
+                "line 4: \"high\" is not a number",
+                "line 5: city_risk.Rome must be a positive number, not 0",
+                "line 6: unclosed quote",
+                "line 7: the city is empty",
+            ]
+        );
+
+        // Thousands of cities, as a national table has
+        let text: String = (0..5000)
+            .map(|index| format!("City {index},1.{index:04}\n"))
+            .collect();
+        let cities = parse_city_csv(&text).unwrap();
+        assert_eq!(cities.len(), 5000);
+        assert_eq!(cities["City 4321"], 1.4321);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_parse_city_json() {
+        let cities = parse_city_json(r#"{"Denver": 0.9, "São Paulo": 1}"#).unwrap();
+        assert_eq!(cities["São Paulo"], 1.0);
+
+        let issues = parse_city_json(r#"{"Denver": -1}"#).unwrap_err();
+        assert_eq!(
+            issues[0].to_string(),
+            "city_risk.Denver must be a positive number, not -1"
+        );
+        let issues = parse_city_json(r#"["Denver"]"#).unwrap_err();
+        assert!(issues[0].message.starts_with("expected {\"City\": factor}"));
+    }
+
+    // Like test_load_config, only tables equal to the default are set
+    #[test]
+    fn test_set_city_risk_table() {
+        let path = std::env::temp_dir().join(format!("rust_lib_cities_{}.csv", std::process::id()));
+        fs::write(&path, "city,factor\nNew York,1.2\nLos Angeles,1.1\n").unwrap();
+        set_city_risk_table(path.to_str().unwrap()).unwrap();
+        assert_eq!(*current(), RiskModelConfig::default());
+
+        fs::write(&path, "New York,1.2\nLos Angeles,none\n").unwrap();
+        let error = set_city_risk_table(path.to_str().unwrap()).unwrap_err();
+        assert_eq!(
+            error,
+            Error::InvalidArgument(format!(
+                "{}: line 2: \"none\" is not a number",
+                path.display()
+            ))
+        );
+        assert_eq!(*current(), RiskModelConfig::default());
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            set_city_risk_table(path.to_str().unwrap()),
+            Err(Error::Io(_))
+        ));
+
+        #[cfg(feature = "serde")]
+        {
+            set_city_risk_table(r#" {"New York": 1.2, "Los Angeles": 1.1}"#).unwrap();
+            assert_eq!(*current(), RiskModelConfig::default());
+            assert!(set_city_risk_table("{}x").is_err());
+        }
+    }
+
+    // Strict is the default, so setting it changes nothing for other tests
+    #[test]
+    fn test_set_validation_profile() {
+        set_validation_profile("strict").unwrap();
+        assert_eq!(current().strictness, Strictness::Strict);
+        assert!(matches!(
+            set_validation_profile("Strict"),
+            Err(Error::InvalidArgument(_))
+        ));
+        for profile in Strictness::ALL {
+            assert_eq!(Strictness::from_name(profile.name()), Some(profile));
+        }
+    }
+
+    // The configuration is process-wide, so only files that change nothing
+    // are loaded here; analyses of parallel tests must not see a change
+    #[test]
+    fn test_load_config() {
+        let path =
+            std::env::temp_dir().join(format!("rust_lib_config_{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "[age]\nadult_from = 18\n[city_risk]\n\"New York\" = 1.2\n\"Los Angeles\" = 1.1\n",
+        )
+        .unwrap();
+        load_config(path.to_str().unwrap()).unwrap();
+        assert_eq!(*current(), RiskModelConfig::default());
+
+        fs::write(&path, "[age]\nadult_from = 99\n").unwrap();
+        let error = load_config(path.to_str().unwrap()).unwrap_err();
+        assert!(matches!(error, Error::InvalidArgument(_)));
+        let report = load_config_with_report(path.to_str().unwrap());
+        assert!(!report.valid);
+        assert_eq!(report.issues[0].line, 2);
+        assert_eq!(*current(), RiskModelConfig::default());
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            load_config(path.to_str().unwrap()),
+            Err(Error::Io(_))
+        ));
+        let report = load_config_with_report(path.to_str().unwrap());
+        assert!(!report.valid && report.issues.len() == 1);
+    }
+}
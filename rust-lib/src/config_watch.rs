@@ -0,0 +1,130 @@
+//! Reload the configuration when its file changes (`watch` feature)
+//!
+//! `watch_config` loads the TOML file like load_config and then polls its
+//! modification time and size; on a change a background thread loads it
+//! again, which swaps the configuration in one step and publishes
+//! `ConfigReloaded` (see events.rs) so the host can refresh what it shows.
+//! notify would deliver file system events instead, but is not a
+//! dependency, and a poll every second or so costs one stat call.
+//!
+//! An edit that leaves the file invalid keeps the configuration in effect
+//! and is reported on stderr, as no caller waits for the reload. Editors
+//! that save by replacing the file are fine: a poll that finds no file is
+//! skipped.
+
+use std::fs;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use crate::config;
+use crate::error::Error;
+use crate::state::{Global, StopSignal};
+
+struct Watcher {
+    stop: Arc<StopSignal>,
+    worker: JoinHandle<()>,
+}
+
+static WATCHER: Global<Option<Watcher>> = Global::new(|| None);
+
+/// What a poll compares: the modification time and the size
+type Fingerprint = (SystemTime, u64);
+
+fn fingerprint(path: &str) -> Option<Fingerprint> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Load the TOML file at `path`, and again whenever it changes, checking
+/// every `poll_interval_ms`; replaces any watch started before
+pub(crate) fn watch_config(path: &str, poll_interval_ms: u64) -> Result<(), Error> {
+    if poll_interval_ms == 0 {
+        return Err(Error::InvalidArgument(
+            "poll_interval_ms must be at least 1".to_string(),
+        ));
+    }
+    stop_watching_config();
+    let seen = fingerprint(path);
+    config::load_config(path)?;
+
+    let stop = Arc::new(StopSignal::default());
+    let worker = {
+        let (path, stop) = (path.to_string(), Arc::clone(&stop));
+        let interval = Duration::from_millis(poll_interval_ms);
+        thread::spawn(move || run(&path, seen, &stop, interval))
+    };
+    *WATCHER.write() = Some(Watcher { stop, worker });
+    Ok(())
+}
+
+/// The worker: reload on every change of the fingerprint
+fn run(path: &str, mut seen: Option<Fingerprint>, stop: &StopSignal, interval: Duration) {
+    while !stop.wait(interval) {
+        let Some(now) = fingerprint(path) else {
+            continue;
+        };
+        if seen == Some(now) {
+            continue;
+        }
+        seen = Some(now);
+        if let Err(e) = config::load_config(path) {
+            eprintln!("rust-lib: keeping the configuration in effect: {e}");
+        }
+    }
+}
+
+/// Stop watching and join the watch thread; the configuration stays
+pub(crate) fn stop_watching_config() {
+    let watcher = WATCHER.write().take();
+    if let Some(watcher) = watcher {
+        watcher.stop.stop();
+        let _ = watcher.worker.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events;
+    use crate::ffi;
+    use std::sync::mpsc;
+
+    // The configuration is process-wide, so both versions of the file hold
+    // the defaults; the change of size is what the watcher notices
+    #[test]
+    fn test_watch_config_reloads_on_change() {
+        let path = std::env::temp_dir().join(format!("rust_lib_watch_{}.toml", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        assert!(watch_config(&path, 10).is_err());
+        fs::write(&path, "[age]\nadult_from = 18\n").unwrap();
+        assert!(watch_config(&path, 0).is_err());
+
+        let (sender, reloads) = mpsc::channel();
+        let id = {
+            let path = path.clone();
+            events::add_handler(
+                ffi::EventKind::ConfigReloaded,
+                Arc::new(move |event: &ffi::BusEvent| {
+                    if event.detail == path {
+                        let _ = sender.send(());
+                    }
+                }),
+            )
+        };
+        watch_config(&path, 10).unwrap();
+        reloads.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        fs::write(&path, "[age]\nadult_from = 18\nsenior_above = 65\n").unwrap();
+        reloads.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        // An invalid edit is not loaded, so nothing is published
+        fs::write(&path, "[age]\nadult_from = \"eighteen\"\n").unwrap();
+        assert!(reloads.recv_timeout(Duration::from_millis(200)).is_err());
+
+        stop_watching_config();
+        assert!(WATCHER.read().is_none());
+        events::unsubscribe(id);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+//! Consent of registered persons
+//!
+//! The host records what each registered person agreed to with
+//! `set_consent`. The functions that work on registered persons by id
+//! honor it:
+//!
+//! - `analyze_registered` needs `analysis` consent and otherwise fails with
+//!   `Error::ConsentDenied`. Without `sharing` consent the analysis still
+//!   runs but is kept from the webhook and remote sync (see observers.rs).
+//! - `export_registered` needs `exports` consent of every person, and
+//!   writes nothing if one of them lacks it.
+//! - `GET /registry` of the HTTP server (http.rs) lists only the persons
+//!   with `sharing` consent.
+//!
+//! A person for whom nothing was recorded has consented to nothing. The
+//! record goes when the person is unregistered, destroyed or erased. The
+//! functions taking a C++ Person or a snapshot, and the other HTTP and
+//! gRPC endpoints, which take snapshots, remain the host's to use as its
+//! own consent rules allow.
+
+use std::collections::HashMap;
+
+use crate::audit;
+use crate::engine::compute_health_cached;
+use crate::error::Error;
+use crate::export::export_people;
+use crate::ffi;
+use crate::observers;
+use crate::registry;
+use crate::state::Global;
+
+static CONSENT: Global<HashMap<u64, ffi::ConsentFlags>> = Global::new(HashMap::new);
+
+/// Record what registered person `person_id` consents to, replacing what
+/// was recorded before
+pub(crate) fn set_consent(person_id: u64, flags: ffi::ConsentFlags) -> Result<(), Error> {
+    let mut consent = CONSENT.write();
+    // Checked under the lock, so an unregistration cannot slip in between
+    registry::lookup(person_id)?;
+    consent.insert(person_id, flags);
+    Ok(())
+}
+
+/// What `person_id` consents to; nothing unless recorded
+pub(crate) fn get_consent(person_id: u64) -> ffi::ConsentFlags {
+    CONSENT
+        .read()
+        .get(&person_id)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Drop the record of `person_id`; whether there was one
+pub(crate) fn forget(person_id: u64) -> bool {
+    CONSENT.write().remove(&person_id).is_some()
+}
+
+/// The analysis of registered person `person_id` at `weight_kg`, as
+/// analyze_health gives it, if they consented
+pub(crate) fn analyze_registered(
+    person_id: u64,
+    weight_kg: f64,
+) -> Result<ffi::HealthAnalysis, Error> {
+    let person = registry::lookup(person_id)?;
+    let consent = get_consent(person_id);
+    if !consent.analysis {
+        return Err(denied(person_id, "analysis"));
+    }
+    Ok(if consent.sharing {
+        compute_health_cached(&person, weight_kg)
+    } else {
+        observers::without_sharing(|| compute_health_cached(&person, weight_kg))
+    })
+}
+
+/// Export registered persons `person_ids` to `path`, as export_people does
+/// their snapshots, if every one of them consented
+pub(crate) fn export_registered(
+    person_ids: &[u64],
+    path: &str,
+    format: ffi::ExportFormat,
+) -> Result<u64, Error> {
+    let mut people = Vec::with_capacity(person_ids.len());
+    for &person_id in person_ids {
+        let person = registry::lookup(person_id)?;
+        if !get_consent(person_id).exports {
+            return Err(denied(person_id, "export"));
+        }
+        people.push(person);
+    }
+    for &person_id in person_ids {
+        audit::record("export_registered", person_id, audit::PERSON_FIELDS);
+    }
+    export_people(&people, path, format)
+}
+
+fn denied(person_id: u64, purpose: &'static str) -> Error {
+    Error::ConsentDenied { person_id, purpose }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+
+    fn consent(analysis: bool, exports: bool, sharing: bool) -> ffi::ConsentFlags {
+        ffi::ConsentFlags {
+            analysis,
+            exports,
+            sharing,
+        }
+    }
+
+    #[test]
+    fn test_analysis_needs_consent() {
+        let id = registry::register_person(snapshot(30, 1.75, "Boston"));
+        assert_eq!(
+            analyze_registered(id, 70.0).unwrap_err(),
+            Error::ConsentDenied {
+                person_id: id,
+                purpose: "analysis"
+            }
+        );
+        assert_eq!(
+            analyze_registered(id, 70.0).unwrap_err().to_string(),
+            format!("person {id} has not consented to analysis")
+        );
+
+        set_consent(id, consent(true, false, false)).unwrap();
+        let analysis = analyze_registered(id, 70.0).unwrap();
+        assert_eq!(
+            analysis.bmi,
+            crate::analyze_snapshot(&snapshot(30, 1.75, "Boston"), 70.0).bmi
+        );
+
+        registry::unregister_person(id);
+        assert!(!get_consent(id).analysis);
+        assert_eq!(
+            set_consent(id, consent(true, true, true)),
+            Err(Error::UnknownPerson(id))
+        );
+    }
+
+    #[test]
+    fn test_export_needs_everyones_consent() {
+        let ids = [
+            registry::register_person(snapshot(30, 1.75, "Boston")),
+            registry::register_person(snapshot(40, 1.80, "Chicago")),
+        ];
+        set_consent(ids[0], consent(false, true, false)).unwrap();
+        let path = std::env::temp_dir()
+            .join(format!("rust-lib-{}-consent.csv", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+
+        assert_eq!(
+            export_registered(&ids, &path, ffi::ExportFormat::Csv),
+            Err(Error::ConsentDenied {
+                person_id: ids[1],
+                purpose: "export"
+            })
+        );
+        assert!(!std::path::Path::new(&path).exists());
+
+        set_consent(ids[1], consent(false, true, false)).unwrap();
+        assert_eq!(
+            export_registered(&ids, &path, ffi::ExportFormat::Csv),
+            Ok(2)
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(registry::notify_person_dropped(ids[0]));
+        assert!(!get_consent(ids[0]).exports);
+        registry::unregister_person(ids[1]);
+    }
+}
@@ -0,0 +1,235 @@
+//! The country of a contact, guessed from its phone number and postal code
+//!
+//! The C++ model has no country field, so `infer_country` weighs two
+//! signals instead. The phone number's country code is the strongest;
+//! without one, a ten-digit number reads as North American. The postal
+//! code's format adds weight: "M5V 3L9" is Canadian and "SW1A 1AA"
+//! British, while five digits fit the US as well as much of Europe.
+//!
+//! Each signal has a weight and the countries it fits. A country scores
+//! the weights of the signals that fit it, and the best one wins, ties
+//! going to the country listed first. A signal that fits another country
+//! only halves the score, so a guess from conflicting data comes out
+//! weak. No signal at all is the empty code with confidence 0.
+
+use std::slice;
+
+use crate::ffi;
+
+/// A phone number with its country code ("+44 20 ...", "0044 20 ...")
+const COUNTRY_CODE: f64 = 0.6;
+/// Ten digits, or eleven starting with 1, without a country code
+const NORTH_AMERICAN_NUMBER: f64 = 0.3;
+/// A postal format only one country uses
+const DISTINCT_POSTAL: f64 = 0.4;
+/// A postal format several countries share
+const SHARED_POSTAL: f64 = 0.2;
+
+/// Calling codes and countries; the codes are prefix-free, so at most one
+/// matches. "1" is resolved by area code in `north_american`
+const CALLING_CODES: &[(&str, &str)] = &[
+    ("1", "US"),
+    ("30", "GR"),
+    ("31", "NL"),
+    ("33", "FR"),
+    ("34", "ES"),
+    ("39", "IT"),
+    ("41", "CH"),
+    ("43", "AT"),
+    ("44", "GB"),
+    ("45", "DK"),
+    ("46", "SE"),
+    ("49", "DE"),
+    ("52", "MX"),
+    ("55", "BR"),
+    ("61", "AU"),
+    ("7", "RU"),
+    ("81", "JP"),
+    ("86", "CN"),
+    ("91", "IN"),
+];
+
+/// Canadian area codes; the rest of +1 counts as the US
+const CANADIAN_AREA_CODES: &[u16] = &[
+    204, 226, 236, 249, 250, 263, 289, 306, 343, 354, 365, 367, 368, 382, 403, 416, 418, 428, 431,
+    437, 438, 450, 468, 474, 506, 514, 519, 548, 579, 581, 584, 587, 604, 613, 639, 647, 672, 683,
+    705, 709, 742, 753, 778, 780, 782, 807, 819, 825, 867, 873, 879, 902, 905,
+];
+
+/// Countries of the shared postal formats, the likelier first
+const FIVE_DIGITS: &[&str] = &["US", "DE", "FR", "ES", "IT", "MX"];
+const FOUR_DIGITS: &[&str] = &["AU", "AT", "BE", "CH", "DK"];
+const SIX_DIGITS: &[&str] = &["RU", "IN", "CN"];
+const THREE_SPACE_TWO: &[&str] = &["SE", "GR"];
+
+/// One piece of evidence: its weight and the countries it fits
+type Signal = (f64, &'static [&'static str]);
+
+/// The likeliest country of a contact with this phone number and postal
+/// code, as an ISO 3166-1 alpha-2 code
+pub fn guess_country(phone: &str, postal_code: &str) -> ffi::CountryGuess {
+    let signals: Vec<Signal> = [phone_signal(phone), postal_signal(postal_code)]
+        .into_iter()
+        .flatten()
+        .collect();
+    let score = |country: &str| -> f64 {
+        signals
+            .iter()
+            .filter(|(_, countries)| countries.contains(&country))
+            .map(|(weight, _)| weight)
+            .sum()
+    };
+    let mut best: Option<(&str, f64)> = None;
+    for &country in signals.iter().flat_map(|(_, countries)| countries.iter()) {
+        let country_score = score(country);
+        if best.is_none_or(|(_, best_score)| country_score > best_score) {
+            best = Some((country, country_score));
+        }
+    }
+    let Some((code, score)) = best else {
+        return ffi::CountryGuess::default();
+    };
+    let contradicted = signals
+        .iter()
+        .any(|(_, countries)| !countries.contains(&code));
+    ffi::CountryGuess {
+        code: code.to_string(),
+        confidence: if contradicted { score / 2.0 } else { score }.min(1.0),
+    }
+}
+
+fn phone_signal(phone: &str) -> Option<Signal> {
+    let phone = phone.trim();
+    let digits: String = phone.chars().filter(char::is_ascii_digit).collect();
+    let international = if phone.starts_with('+') {
+        Some(digits.as_str())
+    } else {
+        digits.strip_prefix("00")
+    };
+    if let Some(number) = international {
+        let entry = CALLING_CODES
+            .iter()
+            .find(|(code, _)| number.starts_with(code))?;
+        let countries = match entry.0 {
+            "1" => north_american(&number[1..])?,
+            _ => slice::from_ref(&entry.1),
+        };
+        return Some((COUNTRY_CODE, countries));
+    }
+    let national = match digits.len() {
+        10 => &digits[..],
+        11 => digits.strip_prefix('1')?,
+        _ => return None,
+    };
+    Some((NORTH_AMERICAN_NUMBER, north_american(national)?))
+}
+
+/// US or Canada for a ten-digit North American number
+fn north_american(number: &str) -> Option<&'static [&'static str]> {
+    if number.len() != 10 || number.starts_with(['0', '1']) {
+        return None;
+    }
+    let area_code: u16 = number[..3].parse().ok()?;
+    Some(if CANADIAN_AREA_CODES.contains(&area_code) {
+        &["CA"]
+    } else {
+        &["US"]
+    })
+}
+
+fn postal_signal(postal_code: &str) -> Option<Signal> {
+    let code = postal_code.trim().to_ascii_uppercase();
+    // A digit, letter or anything else per character: "M5V 3L9" is "ADA DAD"
+    let shape: String = code
+        .chars()
+        .map(|c| match c {
+            '0'..='9' => 'D',
+            'A'..='Z' => 'A',
+            other => other,
+        })
+        .collect();
+    let countries: &'static [&'static str] = match shape.as_str() {
+        "ADA DAD" | "ADADAD" => &["CA"],
+        "DDDD AA" | "DDDDAA" => &["NL"],
+        "DDD-DDDD" => &["JP"],
+        "DDDDD-DDDD" => &["US"],
+        "DDDDD-DDD" => &["BR"],
+        _ if british(&shape) => &["GB"],
+        "DDDDD" => return Some((SHARED_POSTAL, FIVE_DIGITS)),
+        "DDDD" => return Some((SHARED_POSTAL, FOUR_DIGITS)),
+        "DDDDDD" => return Some((SHARED_POSTAL, SIX_DIGITS)),
+        "DDD DD" => return Some((SHARED_POSTAL, THREE_SPACE_TWO)),
+        _ => return None,
+    };
+    Some((DISTINCT_POSTAL, countries))
+}
+
+/// "SW1A 1AA", "M1 1AE": an outward code of a letter or two, a digit and
+/// an optional letter or digit, then a digit and two letters
+fn british(shape: &str) -> bool {
+    let compact = shape.replace(' ', "");
+    let Some(outward) = compact.strip_suffix("DAA") else {
+        return false;
+    };
+    matches!(outward, "AD" | "ADD" | "ADA" | "AAD" | "AADD" | "AADA")
+}
+
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn infer_country(contact: &ffi::ContactInfo) -> ffi::CountryGuess {
+    crate::audit::record("infer_country", 0, &["phone", "postal_code"]);
+    let address = ffi::get_contact_address(contact);
+    guess_country(
+        ffi::get_contact_phone(contact).to_str().unwrap_or(""),
+        ffi::get_address_postal_code(address).to_str().unwrap_or(""),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guess(phone: &str, postal_code: &str) -> (String, f64) {
+        let guess = guess_country(phone, postal_code);
+        (guess.code, guess.confidence)
+    }
+
+    #[test]
+    fn test_signals_that_agree_add_up() {
+        assert_eq!(guess("+1 416 555 0100", "M5V 3L9"), ("CA".into(), 1.0));
+        assert_eq!(guess("+1 (617) 555-0100", "02101-1234"), ("US".into(), 1.0));
+        assert_eq!(guess("0049 30 1234567", "10115"), ("DE".into(), 0.8));
+        assert_eq!(guess("+44 20 7946 0958", "SW1A 1AA"), ("GB".into(), 1.0));
+        assert_eq!(guess("617-555-0100", "02101"), ("US".into(), 0.5));
+        assert_eq!(guess("1-604-555-0100", ""), ("CA".into(), 0.3));
+    }
+
+    #[test]
+    fn test_one_signal_or_none() {
+        assert_eq!(guess("+81 3-1234-5678", ""), ("JP".into(), 0.6));
+        assert_eq!(guess("", "1012 AB"), ("NL".into(), 0.4));
+        assert_eq!(guess("", "m1 1ae"), ("GB".into(), 0.4));
+        // Shared formats go to the first country listed
+        assert_eq!(guess("", "02101"), ("US".into(), 0.2));
+        assert_eq!(guess("555-0104", "1050"), ("AU".into(), 0.2));
+        assert_eq!(guess("555-0104", "n/a"), (String::new(), 0.0));
+        assert_eq!(guess("+999 123", "ABCDE"), (String::new(), 0.0));
+    }
+
+    #[test]
+    fn test_conflicting_signals_halve_the_score() {
+        assert_eq!(guess("+44 20 7946 0958", "M5V 3L9"), ("GB".into(), 0.3));
+        // Five digits fit France, so a French number is not contradicted
+        assert_eq!(guess("+33 1 23 45 67 89", "75001"), ("FR".into(), 0.8));
+    }
+
+    #[test]
+    fn test_calling_codes() {
+        // Prefix-free, so the first match is the only one
+        for &(a, _) in CALLING_CODES {
+            for &(b, _) in CALLING_CODES {
+                assert!(a == b || !b.starts_with(a), "{a} and {b}");
+            }
+        }
+        assert!(CANADIAN_AREA_CODES.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
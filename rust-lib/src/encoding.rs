@@ -0,0 +1,122 @@
+//! Decoding of C++ strings that are not UTF-8
+//!
+//! Some C++ database layers still return ISO-8859-1 in their std::strings.
+//! Read as UTF-8, every accented byte becomes U+FFFD, so "Müller" arrives
+//! as "M\u{fffd}ller". `decode_legacy` and `snapshot_person_with_encoding`
+//! take the encoding the host knows the bytes to be in.
+//!
+//! Encoding names are matched like the WHATWG labels: ASCII case and the
+//! punctuation in "ISO-8859-1" / "iso_8859_1" do not matter.
+
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum LegacyEncoding {
+    Utf8,
+    /// ISO-8859-1: every byte is the code point of the same value
+    Latin1,
+    /// ISO-8859-15: Latin-1 with €, Š, š, Ž, ž, Œ, œ and Ÿ
+    Latin9,
+    /// Windows-1252: Latin-1 with printable characters in 0x80..=0x9F
+    Windows1252,
+}
+
+/// Windows-1252 bytes 0x80..=0x9F; the five unassigned ones map to the
+/// C1 control of the same value, as browsers decode them
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž', '\u{8f}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}', 'ž', 'Ÿ',
+];
+
+impl LegacyEncoding {
+    pub(crate) fn from_name(name: &str) -> Result<Self, Error> {
+        let key: String = name
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        match key.as_str() {
+            "utf8" => Ok(LegacyEncoding::Utf8),
+            "iso88591" | "latin1" | "l1" => Ok(LegacyEncoding::Latin1),
+            "iso885915" | "latin9" | "l9" => Ok(LegacyEncoding::Latin9),
+            "windows1252" | "cp1252" => Ok(LegacyEncoding::Windows1252),
+            _ => Err(Error::InvalidArgument(format!("unknown encoding {name:?}"))),
+        }
+    }
+
+    fn decode_byte(self, byte: u8) -> char {
+        match (self, byte) {
+            (LegacyEncoding::Windows1252, 0x80..=0x9F) => WINDOWS_1252_HIGH[byte as usize - 0x80],
+            (LegacyEncoding::Latin9, 0xA4) => '€',
+            (LegacyEncoding::Latin9, 0xA6) => 'Š',
+            (LegacyEncoding::Latin9, 0xA8) => 'š',
+            (LegacyEncoding::Latin9, 0xB4) => 'Ž',
+            (LegacyEncoding::Latin9, 0xB8) => 'ž',
+            (LegacyEncoding::Latin9, 0xBC) => 'Œ',
+            (LegacyEncoding::Latin9, 0xBD) => 'œ',
+            (LegacyEncoding::Latin9, 0xBE) => 'Ÿ',
+            _ => char::from(byte),
+        }
+    }
+
+    /// `bytes` as a String; only UTF-8 can fail
+    pub(crate) fn decode(self, bytes: &[u8]) -> Result<String, Error> {
+        if self == LegacyEncoding::Utf8 {
+            return std::str::from_utf8(bytes)
+                .map(str::to_string)
+                .map_err(|error| {
+                    Error::InvalidArgument(format!("invalid UTF-8 at byte {}", error.valid_up_to()))
+                });
+        }
+        Ok(bytes.iter().map(|&byte| self.decode_byte(byte)).collect())
+    }
+}
+
+/// `bytes` decoded from the named encoding
+pub(crate) fn decode_legacy(bytes: &[u8], encoding: &str) -> Result<String, Error> {
+    LegacyEncoding::from_name(encoding)?.decode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latin1() {
+        assert_eq!(decode_legacy(b"M\xfcller", "ISO-8859-1").unwrap(), "Müller");
+        assert_eq!(decode_legacy(b"Jos\xe9", "latin1").unwrap(), "José");
+        // 0x80..=0x9F are C1 controls in ISO-8859-1
+        assert_eq!(decode_legacy(b"\x80", "iso_8859_1").unwrap(), "\u{80}");
+        let all: Vec<u8> = (0..=255).collect();
+        let decoded = decode_legacy(&all, "latin1").unwrap();
+        assert!(decoded.chars().map(u32::from).eq(0..=255));
+    }
+
+    #[test]
+    fn test_latin9_and_windows_1252() {
+        assert_eq!(decode_legacy(b"\xa4 \xbd", "ISO-8859-15").unwrap(), "€ œ");
+        assert_eq!(decode_legacy(b"\x80 \x9c", "windows-1252").unwrap(), "€ œ");
+        assert_eq!(decode_legacy(b"\x93ok\x94", "CP1252").unwrap(), "“ok”");
+        assert_eq!(decode_legacy(b"\x81", "cp1252").unwrap(), "\u{81}");
+        // Both agree with Latin-1 outside their replaced bytes
+        assert_eq!(decode_legacy(b"\xe9", "cp1252").unwrap(), "é");
+        assert_eq!(decode_legacy(b"\xe9", "latin9").unwrap(), "é");
+    }
+
+    #[test]
+    fn test_utf8_is_strict() {
+        assert_eq!(decode_legacy("Zoë".as_bytes(), "UTF-8").unwrap(), "Zoë");
+        assert_eq!(
+            decode_legacy(b"caf\xe9", "utf8"),
+            Err(Error::InvalidArgument("invalid UTF-8 at byte 3".into()))
+        );
+    }
+
+    #[test]
+    fn test_unknown_encoding() {
+        assert_eq!(
+            decode_legacy(b"abc", "EBCDIC"),
+            Err(Error::InvalidArgument("unknown encoding \"EBCDIC\"".into()))
+        );
+    }
+}
@@ -0,0 +1,367 @@
+//! The health model and contact rules, without C++
+//!
+//! Everything here reads plain Rust values or a `PersonLike`, never a C++
+//! object, so the cxx bridge, the C facade (src/c_abi.rs), the Python
+//! module in rust-lib/python/ and the gRPC service in rust-lib/grpc/ all
+//! run these same formulas. The bridge's
+//! entry points in lib.rs read a C++ Person and call in here. The formulas
+//! that need no PersonSnapshot either (BMI, BMR/TDEE, contact field rules)
+//! live one level further down, in the dependency-free rust-lib-formulas
+//! crate that the WebAssembly build uses.
+
+use crate::config::RiskModelConfig;
+use crate::ffi;
+use crate::kernels::{self, BodyRisk};
+use crate::person_like::PersonLike;
+use crate::string_table::StringTableBuilder;
+use crate::{
+    analysis_cache, config, events, metrics, observers, risk_hooks, runtime_options, templates,
+    validation,
+};
+use rust_lib_formulas::calculate_bmi;
+
+/// Core PersonInfo logic shared by process_person and the ingest queue
+pub(crate) fn compute_person_info(person: &impl PersonLike) -> ffi::PersonInfo {
+    let config = config::current();
+
+    // Rust logic - determine if person is an adult
+    let is_adult = person.age() >= config.adult_age;
+
+    // Calculate BMI category (simplified with assumed weight); a zero
+    // height gives BMI 0 as in calculate_bmi, not infinity
+    let bmi = calculate_bmi(config.assumed_weight_kg, person.height_m());
+
+    let bmi_category = if bmi < config.underweight_bmi {
+        0 // underweight
+    } else if bmi < config.overweight_bmi {
+        1 // normal
+    } else {
+        2 // overweight
+    };
+
+    // Return bridge struct
+    ffi::PersonInfo {
+        is_adult,
+        bmi_category,
+        name_length: person.name().len(),
+        name_graphemes: crate::graphemes::grapheme_count(person.name()),
+        city: person.city().to_string(),
+    }
+}
+
+/// Core health model shared by the single-person and batch entry points
+pub(crate) fn compute_health(person: &impl PersonLike, weight_kg: f64) -> ffi::HealthAnalysis {
+    // The recommendation is in the language of the locale option (see
+    // templates.rs)
+    compute_health_with(person, weight_kg, Some(&runtime_options::locale()), false).0
+}
+
+/// compute_health with the recommendation in `locale` (left empty without
+/// one, for render_recommendation), and with the explanation of the risk
+/// score when `explain` is set
+///
+/// The explanation is for support staff and logs, so it is English in
+/// every locale: "risk 1.65 = age 1.50 × BMI 1.00 × city 1.10 × host 1.00
+/// (age 70, BMI 22.9)".
+pub(crate) fn compute_health_with(
+    person: &impl PersonLike,
+    weight_kg: f64,
+    locale: Option<&str>,
+    explain: bool,
+) -> (ffi::HealthAnalysis, Option<String>) {
+    let timer = metrics::analysis_started();
+    let (analysis, explanation) = assess(person, weight_kg, &config::current(), locale, explain);
+    publish(&analysis);
+    metrics::analysis_finished(timer);
+    (analysis, explanation)
+}
+
+/// compute_health for analyze_health, answered from the analysis cache
+/// when the person was analyzed at this weight before (see
+/// analysis_cache.rs)
+pub(crate) fn compute_health_cached(
+    person: &impl PersonLike,
+    weight_kg: f64,
+) -> ffi::HealthAnalysis {
+    let timer = metrics::analysis_started();
+    let config = config::current();
+    let locale = runtime_options::locale();
+    let analysis = analysis_cache::get_or_compute(person, weight_kg, &config, &locale, || {
+        assess(person, weight_kg, &config, Some(&locale), false).0
+    });
+    publish(&analysis);
+    metrics::analysis_finished(timer);
+    analysis
+}
+
+/// compute_health_with for a batch, whose BMI and age and BMI factors
+/// were computed in bulk under `config` (see kernels.rs)
+pub(crate) fn compute_health_from(
+    person: &impl PersonLike,
+    body: BodyRisk,
+    config: &RiskModelConfig,
+    locale: Option<&str>,
+    explain: bool,
+) -> (ffi::HealthAnalysis, Option<String>) {
+    let timer = metrics::analysis_started();
+    let (analysis, explanation) = assess_body(person, body, config, locale, explain);
+    publish(&analysis);
+    metrics::analysis_finished(timer);
+    (analysis, explanation)
+}
+
+/// compute_health_from for analyze_health_batch_pooled: the
+/// recommendation and the person's city go into `strings`, each distinct
+/// one once, and the analysis refers to them
+///
+/// Observers get the analysis with its recommendation copied in, as
+/// anywhere else; without observers no String is made per person.
+pub(crate) fn compute_health_pooled(
+    person: &impl PersonLike,
+    body: BodyRisk,
+    config: &RiskModelConfig,
+    locale: &str,
+    strings: &mut StringTableBuilder,
+) -> ffi::PooledAnalysis {
+    let timer = metrics::analysis_started();
+    let mut analysis = score(person, body, config);
+    let (risk_score, risk_factors) = (analysis.risk_score, analysis.risk_factors);
+    let recommendation = strings.recommendation(analysis.recommendation_id, || {
+        templates::recommendation(risk_score, risk_factors, locale)
+    });
+    let city = strings.intern(person.city());
+    if observers::any() {
+        analysis.recommendation = strings.get(recommendation).to_string();
+    }
+    publish(&analysis);
+    metrics::analysis_finished(timer);
+    ffi::PooledAnalysis {
+        bmi: analysis.bmi,
+        risk_score,
+        recommendation,
+        city,
+        city_risk_factor: analysis.city_risk_factor,
+        extra_risk_factor: analysis.extra_risk_factor,
+        risk_factors,
+    }
+}
+
+/// The model itself, without the observers, events and metrics around it
+fn assess(
+    person: &impl PersonLike,
+    weight_kg: f64,
+    config: &RiskModelConfig,
+    locale: Option<&str>,
+    explain: bool,
+) -> (ffi::HealthAnalysis, Option<String>) {
+    // BMI, and the risk calculation (this is where Rust shines); the
+    // cutoffs and factors are configurable, see config.rs
+    let body = kernels::body_risk(person.age(), person.height_m(), weight_kg, config);
+    assess_body(person, body, config, locale, explain)
+}
+
+/// assess from the person's BodyRisk on
+fn assess_body(
+    person: &impl PersonLike,
+    body: BodyRisk,
+    config: &RiskModelConfig,
+    locale: Option<&str>,
+    explain: bool,
+) -> (ffi::HealthAnalysis, Option<String>) {
+    let mut analysis = score(person, body, config);
+    let ffi::HealthAnalysis {
+        bmi,
+        risk_score,
+        city_risk_factor: city_risk,
+        extra_risk_factor: extra_risk,
+        risk_factors,
+        ..
+    } = analysis;
+    let BodyRisk {
+        age_risk, bmi_risk, ..
+    } = body;
+
+    // Generate recommendation based on analysis, unless the caller renders
+    // it later from the id
+    if let Some(locale) = locale {
+        analysis.recommendation = templates::recommendation(risk_score, risk_factors, locale);
+    }
+    let explanation = explain.then(|| {
+        let age = person.age();
+        format!(
+            "risk {risk_score:.2} = age {age_risk:.2} × BMI {bmi_risk:.2} × city {city_risk:.2} \
+             × host {extra_risk:.2} (age {age}, BMI {bmi:.1})"
+        )
+    });
+    (analysis, explanation)
+}
+
+/// The numbers of the analysis; the recommendation is left empty, but for
+/// its id
+fn score(
+    person: &impl PersonLike,
+    body: BodyRisk,
+    config: &RiskModelConfig,
+) -> ffi::HealthAnalysis {
+    let BodyRisk {
+        bmi,
+        age_risk,
+        bmi_risk,
+    } = body;
+
+    // City-based risk factor (demonstrating string processing)
+    let city_risk = config.city_risk(person.city());
+
+    // Site-specific factor supplied by the C++ host, if it registered one
+    let extra_risk = risk_hooks::extra_risk(person);
+
+    let risk_score = age_risk * bmi_risk * city_risk * extra_risk;
+    let risk_factors = [age_risk, bmi_risk, city_risk, extra_risk]
+        .iter()
+        .filter(|&&factor| factor > 1.0)
+        .count() as u32;
+
+    ffi::HealthAnalysis {
+        bmi,
+        risk_score,
+        recommendation: String::new(),
+        city_risk_factor: city_risk,
+        extra_risk_factor: extra_risk,
+        risk_factors,
+        recommendation_id: templates::recommendation_id(risk_score, risk_factors),
+    }
+}
+
+/// Tell the observers and the event bus about `analysis`
+fn publish(analysis: &ffi::HealthAnalysis) {
+    // Alert any registered C++ observers about high-risk results
+    observers::notify(analysis);
+    events::analysis_completed(analysis);
+}
+
+/// Health analysis of a Rust-owned snapshot, as analyze_health gives for
+/// the C++ Person it was taken from
+pub fn analyze_snapshot(person: &ffi::PersonSnapshot, weight_kg: f64) -> ffi::HealthAnalysis {
+    compute_health(person, weight_kg)
+}
+
+/// Every field rule of validate_person_full on a Rust-owned snapshot
+pub fn validate_snapshot(person: &ffi::PersonSnapshot) -> ffi::ValidationReport {
+    validation::validate_reported(person)
+}
+
+/// The rules of validate_contact on plain strings
+pub fn contact_fields_valid(email: &str, phone: &str, city: &str, postal_code: &str) -> bool {
+    rust_lib_formulas::contact_valid(email, phone, city, postal_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::person_like::MockPerson;
+
+    #[test]
+    fn test_person_info_from_mock() {
+        let minor = MockPerson {
+            age: 12,
+            height_m: 1.45,
+            name: "Tim",
+            ..Default::default()
+        };
+        let info = compute_person_info(&minor);
+        assert!(!info.is_adult);
+        assert_eq!(info.bmi_category, 2);
+        assert_eq!(info.name_length, 3);
+        assert_eq!(info.name_graphemes, 3);
+        assert_eq!(info.city, "Boston");
+    }
+
+    #[test]
+    fn test_health_from_mock() {
+        let senior = MockPerson {
+            age: 70,
+            city: "Los Angeles",
+            ..Default::default()
+        };
+        let analysis = compute_health(&senior, 70.0);
+        assert_eq!(analysis.city_risk_factor, 1.1);
+        assert!((analysis.risk_score - 1.5 * 1.1 * analysis.extra_risk_factor).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_health_explanation() {
+        let senior = MockPerson {
+            age: 70,
+            city: "Los Angeles",
+            ..Default::default()
+        };
+        let (analysis, explanation) = compute_health_with(&senior, 70.0, Some("de"), true);
+        let explanation = explanation.unwrap();
+        assert!(explanation.starts_with("risk "), "{explanation}");
+        assert!(
+            explanation.contains("age 1.50 × BMI 1.00 × city 1.10"),
+            "{explanation}"
+        );
+        assert!(explanation.ends_with(&format!("(age 70, BMI {:.1})", analysis.bmi)));
+        assert!(analysis.recommendation.contains("Risikofaktor"));
+        assert_eq!(
+            compute_health_with(&senior, 70.0, Some("en"), false).1,
+            None
+        );
+    }
+
+    #[test]
+    fn test_cached_health_matches_computed() {
+        let person = MockPerson {
+            name: "engine-cached",
+            age: 52,
+            ..Default::default()
+        };
+        let computed = compute_health(&person, 88.0);
+        for _ in 0..2 {
+            let cached = compute_health_cached(&person, 88.0);
+            assert_eq!(cached.risk_score, computed.risk_score);
+            assert_eq!(cached.recommendation, computed.recommendation);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_matches_mock() {
+        let senior = MockPerson {
+            age: 70,
+            ..Default::default()
+        };
+        let analysis = analyze_snapshot(&senior.to_snapshot(), 90.0);
+        assert_eq!(analysis.bmi, compute_health(&senior, 90.0).bmi);
+        assert_eq!(analysis.risk_factors, 2);
+    }
+
+    #[test]
+    fn test_validate_snapshot() {
+        let mut person = MockPerson::default().to_snapshot();
+        assert!(validate_snapshot(&person).valid);
+        person.age = 200;
+        let report = validate_snapshot(&person);
+        assert!(!report.valid);
+        assert_eq!(report.issues[0].field, "age");
+    }
+
+    #[test]
+    fn test_contact_fields_valid() {
+        assert!(contact_fields_valid(
+            "a@example.com",
+            "555-0100",
+            "Boston",
+            "02101"
+        ));
+        assert!(!contact_fields_valid(
+            "a@example.com",
+            "555-0100",
+            "",
+            "02101"
+        ));
+        assert!(!contact_fields_valid(
+            "nobody", "555-0100", "Boston", "02101"
+        ));
+    }
+}
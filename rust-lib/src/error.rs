@@ -0,0 +1,123 @@
+//! Error type returned by fallible bridge functions
+//!
+//! cxx turns an `Err` into a `rust::Error` exception on the C++ side,
+//! with the `Display` text as its `what()` message.
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// Two parallel input slices had different lengths
+    LengthMismatch {
+        what: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    /// The work was stopped through its cancel token
+    Cancelled,
+    /// No job with this id exists (never submitted, or already collected)
+    UnknownJob(u64),
+    /// The job is still queued or running
+    JobRunning(u64),
+    /// The job produced a different kind of output than was asked for
+    WrongJobKind(u64),
+    /// Reading or writing a file failed
+    Io(String),
+    /// No person with this id is registered
+    #[cfg(feature = "registry")]
+    UnknownPerson(u64),
+    /// The C++ Person behind this id was destroyed
+    #[cfg(feature = "registry")]
+    PersonDropped(u64),
+    /// The registered person has not consented to this use of their data
+    #[cfg(feature = "registry")]
+    ConsentDenied {
+        person_id: u64,
+        purpose: &'static str,
+    },
+    /// No session with this id is open
+    UnknownSession(u64),
+    /// No string table with this id exists (never built, or released)
+    UnknownStringTable(u64),
+    /// No batch context with this id exists (never created, or released)
+    UnknownBatchContext(u64),
+    /// An argument was outside its accepted range
+    InvalidArgument(String),
+    /// The geocoder has no coordinates for this address
+    AddressNotFound(String),
+    /// No time zone is known for a geocoded address
+    TimezoneNotFound(String),
+    /// No service area with this id is loaded
+    UnknownServiceArea(String),
+    /// A subsystem was used before being configured
+    NotConfigured(&'static str),
+    /// A subsystem was started twice
+    AlreadyRunning(&'static str),
+    /// The endpoint's rate limit was reached (see service_limits.rs)
+    RateLimited(String),
+    /// Too many requests were in flight for the endpoint to take another
+    Overloaded(String),
+    /// A background job panicked, with the panic's message
+    Panicked(String),
+    /// Ids still open at a leak check, e.g. "session 3"
+    #[cfg(feature = "leak-check")]
+    LeakedHandles(Vec<String>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::LengthMismatch {
+                what,
+                expected,
+                actual,
+            } => write!(f, "{what}: expected {expected} entries, got {actual}"),
+            Error::Cancelled => write!(f, "operation was cancelled"),
+            Error::UnknownJob(id) => write!(f, "unknown job id {id}"),
+            Error::JobRunning(id) => write!(f, "job {id} has not finished yet"),
+            Error::WrongJobKind(id) => write!(f, "job {id} produced a different kind of output"),
+            Error::Io(msg) => write!(f, "I/O error: {msg}"),
+            #[cfg(feature = "registry")]
+            Error::UnknownPerson(id) => write!(f, "unknown person id {id}"),
+            #[cfg(feature = "registry")]
+            Error::PersonDropped(id) => write!(f, "person {id} has been destroyed"),
+            #[cfg(feature = "registry")]
+            Error::ConsentDenied { person_id, purpose } => {
+                write!(f, "person {person_id} has not consented to {purpose}")
+            }
+            Error::UnknownSession(id) => write!(f, "unknown session id {id}"),
+            Error::UnknownStringTable(id) => write!(f, "unknown string table id {id}"),
+            Error::UnknownBatchContext(id) => write!(f, "unknown batch context id {id}"),
+            Error::InvalidArgument(msg) => write!(f, "invalid argument: {msg}"),
+            Error::AddressNotFound(msg) => write!(f, "address not found: {msg}"),
+            Error::TimezoneNotFound(msg) => write!(f, "time zone not found: {msg}"),
+            Error::UnknownServiceArea(id) => write!(f, "unknown service area {id:?}"),
+            Error::NotConfigured(what) => write!(f, "{what} is not configured"),
+            Error::AlreadyRunning(what) => write!(f, "{what} is already running"),
+            Error::RateLimited(endpoint) => write!(f, "rate limit of {endpoint} reached"),
+            Error::Overloaded(endpoint) => {
+                write!(f, "too many requests in flight to take one for {endpoint}")
+            }
+            Error::Panicked(msg) => write!(f, "job panicked: {msg}"),
+            #[cfg(feature = "leak-check")]
+            Error::LeakedHandles(open) => {
+                write!(f, "{} leaked handles: {}", open.len(), open.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error.to_string())
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for Error {
+    fn from(error: parquet::errors::ParquetError) -> Self {
+        Error::Io(format!("Parquet: {error}"))
+    }
+}
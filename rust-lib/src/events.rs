@@ -0,0 +1,196 @@
+//! Event bus between Rust subsystems and C++ subscribers
+//!
+//! Rust code publishes typed events (`BusEvent`) at well-defined points;
+//! C++ subscribes per event kind with an `EventSubscriber`. Subscribers run
+//! synchronously on the publishing thread, after any internal lock has been
+//! released, so a subscriber may call back into the bridge.
+//!
+//! Published today:
+//! - `PersonRegistered` by `register_person` (person_id set)
+//! - `AnalysisCompleted` for every health analysis (risk_score set)
+//! - `ValidationFailed` by `validate_person_full` and re-validation alerts
+//!   (detail lists the fields with errors; person_id set when known)
+//! - `ConfigReloaded` by `load_config` and the config watcher (detail is
+//!   the file's path)
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::ffi;
+use crate::leak_check::{self, Handle};
+use crate::state::Global;
+
+type Handler = Arc<dyn Fn(&ffi::BusEvent) + Send + Sync>;
+
+struct Subscription {
+    id: u64,
+    kind: ffi::EventKind,
+    handler: Handler,
+}
+
+static SUBSCRIPTIONS: Global<Vec<Subscription>> = Global::new(Vec::new);
+
+/// Fast path: publishing is free while nobody is subscribed
+static SUBSCRIPTION_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+static NEXT_SUBSCRIPTION: AtomicU64 = AtomicU64::new(1);
+
+// Subscribers run on whichever thread publishes, possibly concurrently;
+// callbacks.h documents that the wrapped C++ function must allow this.
+#[cfg(not(feature = "stub-cpp"))]
+unsafe impl Send for ffi::EventSubscriber {}
+#[cfg(not(feature = "stub-cpp"))]
+unsafe impl Sync for ffi::EventSubscriber {}
+
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn subscribe(
+    kind: ffi::EventKind,
+    subscriber: cxx::UniquePtr<ffi::EventSubscriber>,
+) -> u64 {
+    add_handler(
+        kind,
+        Arc::new(move |event| {
+            if let Some(subscriber) = subscriber.as_ref() {
+                subscriber.on_event(event);
+            }
+        }),
+    )
+}
+
+pub(crate) fn add_handler(kind: ffi::EventKind, handler: Handler) -> u64 {
+    let id = NEXT_SUBSCRIPTION.fetch_add(1, Ordering::Relaxed);
+    let mut subscriptions = SUBSCRIPTIONS.write();
+    subscriptions.push(Subscription { id, kind, handler });
+    SUBSCRIPTION_COUNT.store(subscriptions.len(), Ordering::Release);
+    leak_check::issued(Handle::Subscription, id);
+    id
+}
+
+pub(crate) fn unsubscribe(id: u64) -> bool {
+    let mut subscriptions = SUBSCRIPTIONS.write();
+    let before = subscriptions.len();
+    subscriptions.retain(|subscription| subscription.id != id);
+    SUBSCRIPTION_COUNT.store(subscriptions.len(), Ordering::Release);
+    leak_check::released(Handle::Subscription, id);
+    subscriptions.len() != before
+}
+
+/// Whether anything listens for `kind`; lets publishers skip building events
+pub(crate) fn has_subscribers(kind: ffi::EventKind) -> bool {
+    SUBSCRIPTION_COUNT.load(Ordering::Acquire) > 0
+        && SUBSCRIPTIONS.read().iter().any(|s| s.kind == kind)
+}
+
+pub(crate) fn publish(event: ffi::BusEvent) {
+    if SUBSCRIPTION_COUNT.load(Ordering::Acquire) == 0 {
+        return;
+    }
+
+    let handlers: Vec<Handler> = SUBSCRIPTIONS
+        .read()
+        .iter()
+        .filter(|subscription| subscription.kind == event.kind)
+        .map(|subscription| Arc::clone(&subscription.handler))
+        .collect();
+
+    for handler in handlers {
+        handler(&event);
+    }
+}
+
+// Typed constructors used by the publishers
+
+#[cfg(feature = "registry")]
+pub(crate) fn person_registered(person_id: u64) {
+    publish(ffi::BusEvent {
+        kind: ffi::EventKind::PersonRegistered,
+        person_id,
+        risk_score: 0.0,
+        detail: String::new(),
+    });
+}
+
+pub(crate) fn analysis_completed(analysis: &ffi::HealthAnalysis) {
+    publish(ffi::BusEvent {
+        kind: ffi::EventKind::AnalysisCompleted,
+        person_id: 0,
+        risk_score: analysis.risk_score,
+        detail: String::new(),
+    });
+}
+
+pub(crate) fn validation_failed(person_id: u64, issues: &[ffi::ValidationIssue]) {
+    if !has_subscribers(ffi::EventKind::ValidationFailed) {
+        return;
+    }
+    let fields: Vec<&str> = issues
+        .iter()
+        .filter(|issue| issue.severity == ffi::Severity::Error)
+        .map(|issue| issue.field.as_str())
+        .collect();
+    publish(ffi::BusEvent {
+        kind: ffi::EventKind::ValidationFailed,
+        person_id,
+        risk_score: 0.0,
+        detail: fields.join(","),
+    });
+}
+
+pub(crate) fn config_reloaded(path: &str) {
+    publish(ffi::BusEvent {
+        kind: ffi::EventKind::ConfigReloaded,
+        person_id: 0,
+        risk_score: 0.0,
+        detail: path.to_string(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+    use std::sync::Mutex;
+
+    #[cfg(feature = "registry")]
+    #[test]
+    fn test_subscribers_receive_only_their_kind() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let id = add_handler(
+            ffi::EventKind::PersonRegistered,
+            Arc::new(move |event: &ffi::BusEvent| sink.lock().unwrap().push(event.person_id)),
+        );
+
+        let person_id = crate::registry::register_person(snapshot(30, 1.75, "Boston"));
+        validation_failed(person_id, &[]);
+        assert!(unsubscribe(id));
+        let other_id = crate::registry::register_person(snapshot(30, 1.75, "Boston"));
+
+        // Other tests register persons concurrently, so look for ours only
+        let seen = seen.lock().unwrap();
+        assert!(seen.contains(&person_id));
+        assert!(!seen.contains(&other_id));
+        crate::registry::unregister_person(person_id);
+        crate::registry::unregister_person(other_id);
+    }
+
+    #[test]
+    fn test_validation_failed_lists_fields() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let id = add_handler(
+            ffi::EventKind::ValidationFailed,
+            Arc::new(move |event: &ffi::BusEvent| {
+                if event.person_id == u64::MAX {
+                    sink.lock().unwrap().push(event.detail.clone());
+                }
+            }),
+        );
+
+        let report = crate::validation::validate_fields(&snapshot(30, 0.0, ""));
+        validation_failed(u64::MAX, &report.issues);
+        unsubscribe(id);
+
+        assert_eq!(*seen.lock().unwrap(), vec!["height_m,city".to_string()]);
+    }
+}
@@ -0,0 +1,391 @@
+//! File exporters for person snapshots
+//!
+//! CSV and NDJSON are written here, and Parquet, with the `parquet`
+//! feature, by export_parquet.rs. Exports are written to a temporary file
+//! next to `path`, named after it, the process id and a counter, and
+//! renamed into place once complete, so a cancelled or failed export never
+//! leaves a truncated file at `path` and concurrent exports to one path
+//! never share a temporary file. Large exports should go through
+//! `submit_export`, which runs on the batch worker pool instead of the
+//! calling (often UI) thread.
+//!
+//! With the `crypto` feature and an export key set, the personal fields
+//! are written encrypted (see export_crypto.rs). The CSV and NDJSON rows
+//! are buffered in memory that is wiped once written, and a background
+//! export wipes its copy of the people when done (see zeroize.rs).
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::cancel::CancelFlag;
+use crate::error::Error;
+use crate::ffi;
+use crate::jobs::{spawn_job, JobOutput};
+use crate::zeroize::Zeroizing;
+
+const CSV_HEADER: &str = "name,age,height_m,email,phone,street,city,postal_code";
+
+/// Bytes of rows collected before they are written to the file
+const BUFFER_BYTES: usize = 64 * 1024;
+
+/// Temporary files created by this process so far
+static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// Export synchronously; returns the number of rows written
+pub(crate) fn export_people(
+    people: &[ffi::PersonSnapshot],
+    path: &str,
+    format: ffi::ExportFormat,
+) -> Result<u64, Error> {
+    write_export(people, path, format, &CancelFlag::default())
+}
+
+/// Export on the worker pool; collect the row count with `take_export_result`
+pub(crate) fn submit_export(
+    people: &[ffi::PersonSnapshot],
+    path: &str,
+    format: ffi::ExportFormat,
+    cancel_token: u64,
+) -> Result<u64, Error> {
+    check_format(format)?;
+
+    let people = Zeroizing::new(people.to_vec());
+    let path = path.to_string();
+    let cancel = CancelFlag::for_token(cancel_token);
+
+    Ok(spawn_job(
+        ffi::JobPriority::Batch,
+        move || write_export(&people, &path, format, &cancel).map(JobOutput::Exported),
+        None,
+    ))
+}
+
+fn check_format(format: ffi::ExportFormat) -> Result<(), Error> {
+    match format {
+        ffi::ExportFormat::Csv | ffi::ExportFormat::Ndjson => Ok(()),
+        #[cfg(feature = "parquet")]
+        ffi::ExportFormat::Parquet => Ok(()),
+        #[cfg(not(feature = "parquet"))]
+        ffi::ExportFormat::Parquet => Err(Error::InvalidArgument(
+            "Parquet export needs the `parquet` feature".to_string(),
+        )),
+        _ => Err(Error::InvalidArgument(format!(
+            "unknown export format {}",
+            format.repr
+        ))),
+    }
+}
+
+fn write_export(
+    people: &[ffi::PersonSnapshot],
+    path: &str,
+    format: ffi::ExportFormat,
+    cancel: &CancelFlag,
+) -> Result<u64, Error> {
+    check_format(format)?;
+
+    let tmp_path = temp_path_for(path);
+    let result = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp_path)
+        .map_err(Error::from)
+        .and_then(|file| {
+            let mut out = WipingWriter::new(file);
+            match format {
+                #[cfg(feature = "parquet")]
+                ffi::ExportFormat::Parquet => {
+                    crate::export_parquet::write_parquet(people, &mut out, cancel)?
+                }
+                _ => write_rows(people, &mut out, format, cancel)?,
+            }
+            out.flush()?;
+            Ok(people.len() as u64)
+        });
+
+    match result {
+        Ok(rows) => {
+            fs::rename(&tmp_path, path)?;
+            Ok(rows)
+        }
+        Err(error) => {
+            let _ = fs::remove_file(&tmp_path);
+            Err(error)
+        }
+    }
+}
+
+/// A temporary file of this process's own in `path`'s directory, so the
+/// rename is within one file system
+fn temp_path_for(path: &str) -> PathBuf {
+    let n = TEMP_FILES.fetch_add(1, Ordering::Relaxed);
+    PathBuf::from(format!("{path}.{}.{n}.tmp", std::process::id()))
+}
+
+fn write_rows(
+    people: &[ffi::PersonSnapshot],
+    out: &mut impl Write,
+    format: ffi::ExportFormat,
+    cancel: &CancelFlag,
+) -> Result<(), Error> {
+    if format == ffi::ExportFormat::Csv {
+        writeln!(out, "{CSV_HEADER}")?;
+    }
+    // One key for the whole file, even if the host changes it meanwhile
+    #[cfg(feature = "crypto")]
+    let key = crate::export_crypto::export_key();
+
+    // The row number is only needed to encrypt
+    for (_row, person) in (0u64..).zip(people) {
+        if cancel.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+        #[cfg(feature = "crypto")]
+        let person = &*crate::export_crypto::for_export(key.as_deref(), person, _row)?;
+        if format == ffi::ExportFormat::Csv {
+            write_csv_row(out, person)?;
+        } else {
+            write_ndjson_row(out, person)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+fn write_ndjson_row(out: &mut impl Write, person: &ffi::PersonSnapshot) -> Result<(), Error> {
+    serde_json::to_writer(&mut *out, person).map_err(|e| Error::Io(e.to_string()))?;
+    writeln!(out)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn write_ndjson_row(_: &mut impl Write, _: &ffi::PersonSnapshot) -> Result<(), Error> {
+    Err(Error::InvalidArgument(
+        "NDJSON export needs the `serde` feature".to_string(),
+    ))
+}
+
+/// A BufWriter whose buffer is wiped when dropped
+///
+/// A row is appended whole unless it alone exceeds the buffer, so the
+/// buffer never grows and leaves no copy behind.
+struct WipingWriter<W: Write> {
+    inner: W,
+    buffer: Zeroizing<Vec<u8>>,
+}
+
+impl<W: Write> WipingWriter<W> {
+    fn new(inner: W) -> Self {
+        WipingWriter {
+            inner,
+            buffer: Zeroizing::new(Vec::with_capacity(BUFFER_BYTES)),
+        }
+    }
+
+    fn write_buffer(&mut self) -> std::io::Result<()> {
+        self.inner.write_all(&self.buffer)?;
+        // Overwritten by the next rows, and wiped with the buffer's memory
+        // on drop
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for WipingWriter<W> {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        if self.buffer.len() + bytes.len() > self.buffer.capacity() {
+            self.write_buffer()?;
+        }
+        if bytes.len() > self.buffer.capacity() {
+            return self.inner.write(bytes);
+        }
+        self.buffer.extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.write_buffer()?;
+        self.inner.flush()
+    }
+}
+
+fn write_csv_row(out: &mut impl Write, person: &ffi::PersonSnapshot) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "{},{},{},{},{},{},{},{}",
+        *csv_field(&person.name),
+        person.age,
+        person.height_m,
+        *csv_field(&person.email),
+        *csv_field(&person.phone),
+        *csv_field(&person.street),
+        *csv_field(&person.city),
+        *csv_field(&person.postal_code),
+    )
+}
+
+/// Quote a CSV field when it contains a delimiter, quote or line break
+///
+/// Sized for the worst case up front, so the field is never copied by a
+/// reallocation, and wiped once written
+fn csv_field(value: &str) -> Zeroizing<String> {
+    let mut field = Zeroizing::new(String::with_capacity(2 * value.len() + 2));
+    if value.contains([',', '"', '\n', '\r']) {
+        field.push('"');
+        for c in value.chars() {
+            if c == '"' {
+                field.push('"');
+            }
+            field.push(c);
+        }
+        field.push('"');
+    } else {
+        field.push_str(value);
+    }
+    field
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+    use std::env;
+
+    fn temp_path(name: &str) -> String {
+        env::temp_dir()
+            .join(format!("rust-lib-{}-{name}", std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn test_csv_quotes_special_characters() {
+        assert_eq!(*csv_field("plain"), "plain");
+        assert_eq!(*csv_field("Smith, John"), "\"Smith, John\"");
+        assert_eq!(*csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_export_csv_and_ndjson() {
+        let mut people = vec![snapshot(30, 1.75, "Boston"), snapshot(40, 1.80, "New York")];
+        people[1].name = "Doe, Jane".to_string();
+
+        let csv_path = temp_path("people.csv");
+        assert_eq!(
+            export_people(&people, &csv_path, ffi::ExportFormat::Csv),
+            Ok(2)
+        );
+        let csv = fs::read_to_string(&csv_path).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[2].starts_with("\"Doe, Jane\",40,"));
+
+        let json_path = temp_path("people.ndjson");
+        assert_eq!(
+            export_people(&people, &json_path, ffi::ExportFormat::Ndjson),
+            Ok(2)
+        );
+        let ndjson = fs::read_to_string(&json_path).unwrap();
+        let first: serde_json::Value =
+            serde_json::from_str(ndjson.lines().next().unwrap()).unwrap();
+        assert_eq!(first["city"], "Boston");
+
+        fs::remove_file(csv_path).unwrap();
+        fs::remove_file(json_path).unwrap();
+    }
+
+    #[test]
+    fn test_wiping_writer_passes_everything_through() {
+        let mut out = WipingWriter::new(Vec::new());
+        let long = vec![b'x'; BUFFER_BYTES + 1];
+        out.write_all(b"short,").unwrap();
+        out.write_all(&long).unwrap();
+        out.write_all(b",end").unwrap();
+        out.flush().unwrap();
+        assert_eq!(out.inner.len(), 6 + long.len() + 4);
+        assert!(out.inner.starts_with(b"short,x"));
+        assert!(out.inner.ends_with(b"x,end"));
+        assert!(out.buffer.is_empty());
+    }
+
+    #[test]
+    fn test_cancelled_export_leaves_no_file() {
+        let path = temp_path("cancelled.csv");
+        let token = crate::cancel::create_cancel_token();
+        crate::cancel::cancel(token);
+
+        let result = write_export(
+            &[snapshot(30, 1.75, "Boston")],
+            &path,
+            ffi::ExportFormat::Csv,
+            &CancelFlag::for_token(token),
+        );
+        assert_eq!(result, Err(Error::Cancelled));
+        assert!(!PathBuf::from(&path).exists());
+        assert_eq!(temp_files_of(&path), Vec::<String>::new());
+        crate::cancel::release_cancel_token(token);
+    }
+
+    /// The temporary files left next to `path`
+    fn temp_files_of(path: &str) -> Vec<String> {
+        let path = PathBuf::from(path);
+        let prefix = format!("{}.", path.file_name().unwrap().to_string_lossy());
+        fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with(&prefix) && name.ends_with(".tmp"))
+            .collect()
+    }
+
+    #[test]
+    fn test_concurrent_exports_to_one_path() {
+        let path = temp_path("concurrent.csv");
+        let exports: Vec<_> = (1..=4)
+            .map(|rows| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let people = vec![snapshot(30, 1.75, "Boston"); rows * 1000];
+                    export_people(&people, &path, ffi::ExportFormat::Csv)
+                })
+            })
+            .collect();
+        for export in exports {
+            assert!(export.join().unwrap().is_ok());
+        }
+        // One export's file, whole, and no temporary file of any
+        let lines = fs::read_to_string(&path).unwrap().lines().count();
+        assert!([1001, 2001, 3001, 4001].contains(&lines), "{lines} lines");
+        assert_eq!(temp_files_of(&path), Vec::<String>::new());
+        assert_ne!(temp_path_for(&path), temp_path_for(&path));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_async_export_job() {
+        let path = temp_path("async.ndjson");
+        let job = submit_export(
+            &[snapshot(30, 1.75, "Boston")],
+            &path,
+            ffi::ExportFormat::Ndjson,
+            0,
+        )
+        .unwrap();
+
+        let rows = loop {
+            match crate::jobs::take_export_result(job) {
+                Err(Error::JobRunning(_)) => std::thread::yield_now(),
+                other => break other,
+            }
+        };
+        assert_eq!(rows, Ok(1));
+        assert_eq!(
+            crate::jobs::take_job_results(job).unwrap_err(),
+            Error::UnknownJob(job)
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
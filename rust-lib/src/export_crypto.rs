@@ -0,0 +1,299 @@
+//! Encrypted personal fields in exports
+//!
+//! With the `crypto` feature and a key set by `set_export_key`, the
+//! exporters encrypt the name, email, phone and street of every row with
+//! AES-256-GCM (the RustCrypto `aes-gcm` crate) and write each as `enc:v1:`
+//! and the base64 of nonce, ciphertext and tag. Age, height, city and
+//! postal code stay readable for aggregate analyses. The associated data is
+//! the field's name and the row's number in the export, so a value moved to
+//! another column or another row fails to decrypt. `decrypt_export_field`
+//! reverses it under the same key, given both.
+//!
+//! A nonce is 8 bytes drawn from the OS's random source (`getrandom`) when
+//! the key is set and a 4-byte counter, so one key setting encrypts at most
+//! 2^32 fields; set the key again to go on. The row number does not tell
+//! one export from another, so values swapped between the same rows of two
+//! exports under one key still decrypt.
+//!
+//! The host owns the key: the library keeps it in memory only, until
+//! `clear_export_key` or the next `set_export_key`. The cipher wipes its
+//! key schedule when dropped, and a field's plain text is wiped once
+//! sealed (see zeroize.rs).
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use aes_gcm::aead::AeadInPlace;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce, Tag};
+
+use crate::base64;
+use crate::error::Error;
+use crate::ffi;
+use crate::state::Global;
+use crate::zeroize::Zeroizing;
+
+/// What an encrypted field starts with
+const PREFIX: &str = "enc:v1:";
+const NONCE_BYTES: usize = 12;
+const TAG_BYTES: usize = 16;
+
+/// An export key, expanded, with its nonces
+pub(crate) struct ExportKey {
+    cipher: Aes256Gcm,
+    salt: [u8; 8],
+    next_nonce: AtomicU64,
+}
+
+static KEY: Global<Option<Arc<ExportKey>>> = Global::new(|| None);
+
+/// Encrypt the sensitive fields of every later export with `key`, 32 bytes
+pub(crate) fn set_export_key(key: &[u8]) -> Result<(), Error> {
+    let key: &[u8; 32] = key.try_into().map_err(|_| {
+        Error::InvalidArgument(format!("an export key has 32 bytes, not {}", key.len()))
+    })?;
+    let mut salt = [0; 8];
+    getrandom::getrandom(&mut salt)
+        .map_err(|e| Error::Io(format!("no random nonce for the export key: {e}")))?;
+    *KEY.write() = Some(Arc::new(ExportKey::new(key, salt)));
+    Ok(())
+}
+
+/// Export in plain text again
+pub(crate) fn clear_export_key() {
+    *KEY.write() = None;
+}
+
+/// The key in effect, held by an export from start to end
+pub(crate) fn export_key() -> Option<Arc<ExportKey>> {
+    KEY.read().clone()
+}
+
+/// The plain text of a value an export encrypted as `field` of row `row`,
+/// counting data rows from 0
+///
+/// Throws if no key is set, the value is not an encrypted field, or it
+/// does not authenticate: another key, field or row, or altered bytes.
+pub fn decrypt_export_field(field: &str, row: u64, value: &str) -> Result<String, Error> {
+    export_key()
+        .ok_or(Error::NotConfigured("export key"))?
+        .open(field, row, value)
+}
+
+/// The associated data of `field` in row `row`
+///
+/// The field names are fixed and contain no NUL, so the NUL ends the name.
+fn associated_data(field: &str, row: u64) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(field.len() + 9);
+    aad.extend_from_slice(field.as_bytes());
+    aad.push(0);
+    aad.extend_from_slice(&row.to_be_bytes());
+    aad
+}
+
+impl ExportKey {
+    fn new(key: &[u8; 32], salt: [u8; 8]) -> ExportKey {
+        ExportKey {
+            cipher: Aes256Gcm::new(key.into()),
+            salt,
+            next_nonce: AtomicU64::new(0),
+        }
+    }
+
+    fn open(&self, field: &str, row: u64, value: &str) -> Result<String, Error> {
+        let invalid = || Error::InvalidArgument(format!("{field}: not an encrypted export field"));
+        let sealed = value
+            .strip_prefix(PREFIX)
+            .and_then(base64::decode)
+            .filter(|sealed| sealed.len() >= NONCE_BYTES + TAG_BYTES)
+            .ok_or_else(invalid)?;
+        let (nonce, rest) = sealed.split_at(NONCE_BYTES);
+        let (ciphertext, tag) = rest.split_at(rest.len() - TAG_BYTES);
+        let mut text = Zeroizing::new(ciphertext.to_vec());
+        self.cipher
+            .decrypt_in_place_detached(
+                Nonce::from_slice(nonce),
+                &associated_data(field, row),
+                &mut text,
+                Tag::from_slice(tag),
+            )
+            .map_err(|_| {
+                Error::InvalidArgument(format!(
+                    "{field}: the encrypted value does not authenticate under the export key"
+                ))
+            })?;
+        std::str::from_utf8(&text)
+            .map(str::to_string)
+            .map_err(|_| invalid())
+    }
+
+    /// `person`, row `row` of an export, with its sensitive fields
+    /// encrypted
+    pub(crate) fn seal_snapshot(
+        &self,
+        person: &ffi::PersonSnapshot,
+        row: u64,
+    ) -> Result<ffi::PersonSnapshot, Error> {
+        // Field by field, so no copy of the plain text is left behind
+        Ok(ffi::PersonSnapshot {
+            name: self.seal("name", row, &person.name)?,
+            age: person.age,
+            height_m: person.height_m,
+            email: self.seal("email", row, &person.email)?,
+            phone: self.seal("phone", row, &person.phone)?,
+            street: self.seal("street", row, &person.street)?,
+            city: person.city.clone(),
+            postal_code: person.postal_code.clone(),
+        })
+    }
+
+    fn seal(&self, field: &str, row: u64, value: &str) -> Result<String, Error> {
+        let counter = self.next_nonce.fetch_add(1, Ordering::Relaxed);
+        let counter = u32::try_from(counter).map_err(|_| {
+            Error::InvalidArgument(
+                "the export key has encrypted 2^32 fields; set it again".to_string(),
+            )
+        })?;
+        let mut nonce = [0; NONCE_BYTES];
+        nonce[..8].copy_from_slice(&self.salt);
+        nonce[8..].copy_from_slice(&counter.to_be_bytes());
+        self.seal_with_nonce(&nonce, &associated_data(field, row), value.as_bytes())
+    }
+
+    fn seal_with_nonce(
+        &self,
+        nonce: &[u8; NONCE_BYTES],
+        aad: &[u8],
+        text: &[u8],
+    ) -> Result<String, Error> {
+        // Sized up front, so the plain text is never copied by a
+        // reallocation; wiped in case encryption fails halfway
+        let mut sealed = Zeroizing::new(Vec::with_capacity(NONCE_BYTES + text.len() + TAG_BYTES));
+        sealed.extend_from_slice(nonce);
+        sealed.extend_from_slice(text);
+        let tag = self
+            .cipher
+            .encrypt_in_place_detached(nonce.into(), aad, &mut sealed[NONCE_BYTES..])
+            .map_err(|_| Error::InvalidArgument("a field too long to encrypt".to_string()))?;
+        sealed.extend_from_slice(&tag);
+        Ok(format!("{PREFIX}{}", base64::encode(&sealed)))
+    }
+}
+
+/// `person`, row `row` of an export, as the export writes it: sealed under
+/// `key`, if there is one
+pub(crate) fn for_export<'a>(
+    key: Option<&ExportKey>,
+    person: &'a ffi::PersonSnapshot,
+    row: u64,
+) -> Result<Cow<'a, ffi::PersonSnapshot>, Error> {
+    Ok(match key {
+        Some(key) => Cow::Owned(key.seal_snapshot(person, row)?),
+        None => Cow::Borrowed(person),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// Ciphertext and tag of GCM under `key`, from the sealed form
+    fn gcm(key: &str, nonce: &str, aad: &str, text: &str) -> (Vec<u8>, Vec<u8>) {
+        let key = ExportKey::new(&bytes(key).try_into().unwrap(), [0; 8]);
+        let nonce: [u8; NONCE_BYTES] = bytes(nonce).try_into().unwrap();
+        let sealed = key
+            .seal_with_nonce(&nonce, &bytes(aad), &bytes(text))
+            .unwrap();
+        let sealed = base64::decode(sealed.strip_prefix(PREFIX).unwrap()).unwrap();
+        let (ciphertext, tag) = sealed[NONCE_BYTES..].split_at(sealed.len() - NONCE_BYTES - 16);
+        (ciphertext.to_vec(), tag.to_vec())
+    }
+
+    // The sealed layout around the crate's cipher, against the AES-256 test
+    // case 16 of the GCM specification
+    #[test]
+    fn test_gcm_vector() {
+        assert_eq!(
+            gcm(
+                "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+                "cafebabefacedbaddecaf888",
+                "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+                "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+                 1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39",
+            ),
+            (
+                bytes(
+                    "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+                     8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662"
+                ),
+                bytes("76fc6ece0f4e1768cddf8853bb2d551b")
+            )
+        );
+    }
+
+    // Setting the process-wide key would encrypt the exports of tests
+    // running alongside, so these seal under keys of their own
+    #[test]
+    fn test_fields_round_trip() {
+        assert!(matches!(
+            set_export_key(&[1; 16]),
+            Err(Error::InvalidArgument(_))
+        ));
+        let key = ExportKey::new(&[7; 32], [1; 8]);
+
+        let sealed = key.seal("email", 3, "ren\u{e9}e@example.com").unwrap();
+        assert!(sealed.starts_with(PREFIX));
+        assert_ne!(key.seal("email", 3, "ren\u{e9}e@example.com").unwrap(), sealed);
+        assert_eq!(
+            key.open("email", 3, &sealed).unwrap(),
+            "ren\u{e9}e@example.com"
+        );
+        assert_eq!(
+            key.open("email", 0, &key.seal("email", 0, "").unwrap())
+                .unwrap(),
+            ""
+        );
+
+        // Another column or row, altered bytes, plain text and another key
+        // are refused
+        assert!(key.open("phone", 3, &sealed).is_err());
+        assert!(key.open("email", 4, &sealed).is_err());
+        let mut altered = sealed.clone().into_bytes();
+        let middle = altered.len() / 2;
+        altered[middle] = if altered[middle] == b'A' { b'B' } else { b'A' };
+        assert!(key
+            .open("email", 3, &String::from_utf8(altered).unwrap())
+            .is_err());
+        assert!(key.open("email", 3, "ren\u{e9}e@example.com").is_err());
+        assert!(ExportKey::new(&[8; 32], [1; 8])
+            .open("email", 3, &sealed)
+            .is_err());
+    }
+
+    #[test]
+    fn test_seal_snapshot() {
+        let key = ExportKey::new(&[7; 32], [2; 8]);
+        let person = crate::batch::tests::snapshot(30, 1.75, "Boston");
+        let sealed = key.seal_snapshot(&person, 5).unwrap();
+        assert_eq!(key.open("name", 5, &sealed.name).unwrap(), person.name);
+        assert_eq!(key.open("email", 5, &sealed.email).unwrap(), person.email);
+        assert_eq!(key.open("phone", 5, &sealed.phone).unwrap(), person.phone);
+        assert_eq!(key.open("street", 5, &sealed.street).unwrap(), person.street);
+        // The fields analyses need stay readable
+        assert_eq!(
+            (
+                sealed.age,
+                sealed.city.as_str(),
+                sealed.postal_code.as_str()
+            ),
+            (30, "Boston", "00000")
+        );
+    }
+}
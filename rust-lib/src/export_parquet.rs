@@ -0,0 +1,173 @@
+//! Parquet exporter (`parquet` feature)
+//!
+//! `ExportFormat::Parquet` writes the columns of the CSV export, in its
+//! order, as one Parquet file: the strings as UTF8 byte arrays, age as a
+//! UINT_32 and height as a DOUBLE, none of them nullable. Rows are written
+//! in row groups of `ROW_GROUP_ROWS`, dictionary-encoded where that pays
+//! and uncompressed, through the file writer of the Apache `parquet` crate
+//! without Arrow. With an export key, the sealed fields are their `enc:v1:`
+//! strings, as in the CSV export (see export_crypto.rs).
+//!
+//! The crate keeps a row group's values and pages in buffers of its own,
+//! which are not wiped like the CSV and NDJSON rows are (see zeroize.rs).
+
+use std::borrow::Cow;
+use std::io::Write;
+use std::sync::Arc;
+
+use parquet::data_type::{ByteArray, ByteArrayType, DataType, DoubleType, Int32Type};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedFileWriter, SerializedRowGroupWriter};
+use parquet::schema::parser::parse_message_type;
+
+use crate::cancel::CancelFlag;
+use crate::error::Error;
+use crate::ffi;
+
+/// Rows buffered before they are written as a row group
+const ROW_GROUP_ROWS: usize = 64 * 1024;
+
+const SCHEMA: &str = "
+    message person {
+        required binary name (UTF8);
+        required int32 age (UINT_32);
+        required double height_m;
+        required binary email (UTF8);
+        required binary phone (UTF8);
+        required binary street (UTF8);
+        required binary city (UTF8);
+        required binary postal_code (UTF8);
+    }
+";
+
+/// Write `people` to `out` as a Parquet file
+pub(crate) fn write_parquet(
+    people: &[ffi::PersonSnapshot],
+    out: impl Write + Send,
+    cancel: &CancelFlag,
+) -> Result<(), Error> {
+    let schema = Arc::new(parse_message_type(SCHEMA)?);
+    let mut writer = SerializedFileWriter::new(out, schema, Arc::new(WriterProperties::default()))?;
+    // One key for the whole file, even if the host changes it meanwhile
+    #[cfg(feature = "crypto")]
+    let key = crate::export_crypto::export_key();
+
+    let mut rows = (0u64..).zip(people).peekable();
+    while rows.peek().is_some() {
+        let mut group = Vec::with_capacity(ROW_GROUP_ROWS.min(people.len()));
+        // The row number is only needed to encrypt
+        for (_row, person) in rows.by_ref().take(ROW_GROUP_ROWS) {
+            if cancel.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+            #[cfg(feature = "crypto")]
+            let person = crate::export_crypto::for_export(key.as_deref(), person, _row)?;
+            #[cfg(not(feature = "crypto"))]
+            let person = Cow::Borrowed(person);
+            group.push(person);
+        }
+        write_row_group(&mut writer, &group)?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+fn write_row_group<W: Write + Send>(
+    writer: &mut SerializedFileWriter<W>,
+    people: &[Cow<'_, ffi::PersonSnapshot>],
+) -> Result<(), Error> {
+    let strings = |field: fn(&ffi::PersonSnapshot) -> &str| -> Vec<ByteArray> {
+        people.iter().map(|person| field(person).into()).collect()
+    };
+    let mut group = writer.next_row_group()?;
+    write_column::<ByteArrayType, _>(&mut group, &strings(|person| &person.name))?;
+    // A UINT_32 is stored in an INT32, bit for bit
+    let ages: Vec<i32> = people.iter().map(|person| person.age as i32).collect();
+    write_column::<Int32Type, _>(&mut group, &ages)?;
+    let heights: Vec<f64> = people.iter().map(|person| person.height_m).collect();
+    write_column::<DoubleType, _>(&mut group, &heights)?;
+    write_column::<ByteArrayType, _>(&mut group, &strings(|person| &person.email))?;
+    write_column::<ByteArrayType, _>(&mut group, &strings(|person| &person.phone))?;
+    write_column::<ByteArrayType, _>(&mut group, &strings(|person| &person.street))?;
+    write_column::<ByteArrayType, _>(&mut group, &strings(|person| &person.city))?;
+    write_column::<ByteArrayType, _>(&mut group, &strings(|person| &person.postal_code))?;
+    group.close()?;
+    Ok(())
+}
+
+/// Write the next column of `group`, which must be of type `T`
+fn write_column<T: DataType, W: Write + Send>(
+    group: &mut SerializedRowGroupWriter<'_, W>,
+    values: &[T::T],
+) -> Result<(), ParquetError> {
+    let mut column = group
+        .next_column()?
+        .ok_or_else(|| ParquetError::General("more columns than the schema".to_string()))?;
+    column.typed::<T>().write_batch(values, None, None)?;
+    column.close()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field;
+    use std::fs::{self, File};
+
+    #[test]
+    fn test_round_trip() {
+        let mut people = vec![snapshot(30, 1.75, "Boston"); ROW_GROUP_ROWS + 1];
+        people[ROW_GROUP_ROWS].name = "Doe, Jane".to_string();
+        people[ROW_GROUP_ROWS].age = u32::MAX;
+
+        let path =
+            std::env::temp_dir().join(format!("rust-lib-{}-people.parquet", std::process::id()));
+        let written = crate::export::export_people(
+            &people,
+            path.to_str().unwrap(),
+            ffi::ExportFormat::Parquet,
+        );
+        assert_eq!(written, Ok(people.len() as u64));
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+        assert_eq!(
+            reader.metadata().file_metadata().num_rows(),
+            people.len() as i64
+        );
+
+        let last = reader.get_row_iter(None).unwrap().last().unwrap().unwrap();
+        let columns: Vec<(&str, &Field)> = last
+            .get_column_iter()
+            .map(|(name, field)| (name.as_str(), field))
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                ("name", &Field::Str("Doe, Jane".to_string())),
+                ("age", &Field::UInt(u32::MAX)),
+                ("height_m", &Field::Double(1.75)),
+                ("email", &Field::Str(people[0].email.clone())),
+                ("phone", &Field::Str(people[0].phone.clone())),
+                ("street", &Field::Str(people[0].street.clone())),
+                ("city", &Field::Str("Boston".to_string())),
+                ("postal_code", &Field::Str("00000".to_string())),
+            ]
+        );
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_cancelled() {
+        let token = crate::cancel::create_cancel_token();
+        crate::cancel::cancel(token);
+        let result = write_parquet(
+            &[snapshot(30, 1.75, "Boston")],
+            Vec::new(),
+            &CancelFlag::for_token(token),
+        );
+        assert_eq!(result, Err(Error::Cancelled));
+        crate::cancel::release_cancel_token(token);
+    }
+}
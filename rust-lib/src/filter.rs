@@ -0,0 +1,83 @@
+//! Filtering people with a predicate supplied by C++
+//!
+//! C++ expresses the condition as a `PersonPredicate`; Rust owns the
+//! iteration. Large inputs are split into contiguous chunks tested on
+//! scoped threads, so the predicate may run on several threads at once.
+//! The result keeps the input order.
+
+use std::thread;
+
+use crate::ffi;
+
+/// Inputs smaller than this are filtered on the calling thread
+const PARALLEL_THRESHOLD: usize = 1024;
+
+// The predicate is shared by the scoped filter threads for the duration of
+// the call; callbacks.h documents the thread-safety requirement.
+#[cfg(not(feature = "stub-cpp"))]
+unsafe impl Sync for ffi::PersonPredicate {}
+
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn filter_people(
+    people: &[ffi::PersonSnapshot],
+    predicate: &ffi::PersonPredicate,
+) -> Vec<ffi::PersonSnapshot> {
+    filter_with(people, |person| predicate.matches(person))
+}
+
+pub(crate) fn filter_with(
+    people: &[ffi::PersonSnapshot],
+    predicate: impl Fn(&ffi::PersonSnapshot) -> bool + Sync,
+) -> Vec<ffi::PersonSnapshot> {
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    if people.len() < PARALLEL_THRESHOLD || workers == 1 {
+        return people.iter().filter(|p| predicate(p)).cloned().collect();
+    }
+
+    let chunk_size = people.len().div_ceil(workers);
+    let predicate = &predicate;
+    thread::scope(|scope| {
+        let handles: Vec<_> = people
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || chunk.iter().filter(|p| predicate(p)).collect::<Vec<_>>())
+            })
+            .collect();
+
+        // Joining in spawn order keeps the input order
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("filter thread panicked"))
+            .cloned()
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+
+    #[test]
+    fn test_filter_keeps_matches_in_order() {
+        let people: Vec<_> = (0..3000)
+            .map(|i| snapshot(i % 100, 1.75, "Boston"))
+            .collect();
+
+        let adults = filter_with(&people, |person| person.age >= 18);
+
+        assert_eq!(adults.len(), 30 * 82);
+        assert!(adults.iter().all(|person| person.age >= 18));
+        assert!(adults
+            .windows(2)
+            .all(|pair| { pair[0].age < pair[1].age || (pair[0].age == 99 && pair[1].age == 18) }));
+    }
+
+    #[test]
+    fn test_filter_small_input() {
+        let people = vec![snapshot(30, 1.75, "Boston"), snapshot(40, 1.80, "Denver")];
+        let boston = filter_with(&people, |person| person.city == "Boston");
+        assert_eq!(boston.len(), 1);
+        assert_eq!(boston[0].age, 30);
+    }
+}
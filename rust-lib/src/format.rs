@@ -0,0 +1,320 @@
+//! Health analyses rendered for display in the host's locale
+//!
+//! `format_analysis` gives the C++ UI a ready-made line such as
+//! "BMI 22,9 kg/m² · risk 1,56" for German, so number formatting does not
+//! have to be duplicated in every host. Locales are BCP 47 tags or POSIX
+//! locale names ("de-CH", "fr_FR.UTF-8"); anything unrecognized, including
+//! "C" and "", is formatted as English.
+//!
+//! `format_report_bidi_safe` puts one such line per person after their name
+//! and city. An Arabic or Hebrew name would otherwise pull the numbers
+//! next to it into its right-to-left run ("BMI" ending up after "22.9"), so
+//! every field is wrapped in a Unicode bidi isolate (UAX #9).
+
+use crate::error::Error;
+use crate::ffi;
+
+/// FIRST STRONG ISOLATE: direction taken from the field's own text
+const FSI: char = '\u{2068}';
+/// POP DIRECTIONAL ISOLATE: ends the FSI
+const PDI: char = '\u{2069}';
+
+/// How one locale writes numbers and the BMI unit
+struct NumberStyle {
+    decimal: char,
+    /// Between groups of three integer digits
+    group: &'static str,
+    bmi_unit: &'static str,
+}
+
+const ENGLISH: NumberStyle = NumberStyle {
+    decimal: '.',
+    group: ",",
+    bmi_unit: "kg/m²",
+};
+
+/// Languages that write numbers differently from English
+const LANGUAGES: &[(&str, NumberStyle)] = &[
+    ("cs", comma_decimal("\u{a0}")),
+    ("da", comma_decimal(".")),
+    ("de", comma_decimal(".")),
+    ("el", comma_decimal(".")),
+    ("es", comma_decimal(".")),
+    ("fi", comma_decimal("\u{a0}")),
+    ("fr", comma_decimal("\u{202f}")),
+    ("it", comma_decimal(".")),
+    ("nb", comma_decimal("\u{a0}")),
+    ("nl", comma_decimal(".")),
+    ("pl", comma_decimal("\u{a0}")),
+    ("pt", comma_decimal(".")),
+    (
+        "ru",
+        NumberStyle {
+            bmi_unit: "кг/м²",
+            ..comma_decimal("\u{a0}")
+        },
+    ),
+    ("sv", comma_decimal("\u{a0}")),
+    ("tr", comma_decimal(".")),
+    (
+        "uk",
+        NumberStyle {
+            bmi_unit: "кг/м²",
+            ..comma_decimal("\u{a0}")
+        },
+    ),
+];
+
+/// Regions that differ from their language's usual style
+const REGIONS: &[(&str, &str, NumberStyle)] = &[
+    ("de", "CH", swiss()),
+    ("it", "CH", swiss()),
+    ("pt", "PT", comma_decimal("\u{a0}")),
+];
+
+const fn comma_decimal(group: &'static str) -> NumberStyle {
+    NumberStyle {
+        decimal: ',',
+        group,
+        bmi_unit: ENGLISH.bmi_unit,
+    }
+}
+
+const fn swiss() -> NumberStyle {
+    NumberStyle {
+        decimal: '.',
+        group: "’",
+        bmi_unit: ENGLISH.bmi_unit,
+    }
+}
+
+/// Language and region of a locale name, e.g. ("pt", "BR") for "pt_BR.UTF-8"
+pub(crate) fn parse_locale(locale: &str) -> (String, String) {
+    let tag = locale.split(['.', '@']).next().unwrap_or_default();
+    let mut parts = tag.split(['-', '_']);
+    let language = parts.next().unwrap_or_default().to_ascii_lowercase();
+    // Skip a script subtag such as "Latn" in "sr-Latn-RS"
+    let region = parts
+        .find(|part| part.len() != 4)
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    (language, region)
+}
+
+fn number_style(locale: &str) -> &'static NumberStyle {
+    let (language, region) = parse_locale(locale);
+    REGIONS
+        .iter()
+        .find(|(l, r, _)| *l == language && *r == region)
+        .map(|(_, _, style)| style)
+        .or_else(|| {
+            LANGUAGES
+                .iter()
+                .find(|(l, _)| *l == language)
+                .map(|(_, style)| style)
+        })
+        .unwrap_or(&ENGLISH)
+}
+
+/// `value` rounded to `decimals` places with the locale's separators
+fn format_number(value: f64, decimals: usize, style: &NumberStyle) -> String {
+    if !value.is_finite() {
+        return "—".to_string();
+    }
+    let plain = format!("{:.*}", decimals, value.abs());
+    let (integer, fraction) = plain.split_once('.').unwrap_or((&plain, ""));
+
+    let mut out = String::with_capacity(plain.len() + 4);
+    // Rounding can leave "-0.0"; that is shown as 0
+    if value < 0.0 && plain.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+        out.push('-');
+    }
+    for (index, digit) in integer.chars().enumerate() {
+        if index > 0 && (integer.len() - index).is_multiple_of(3) {
+            out.push_str(style.group);
+        }
+        out.push(digit);
+    }
+    if !fraction.is_empty() {
+        out.push(style.decimal);
+        out.push_str(fraction);
+    }
+    out
+}
+
+/// BMI and risk score of `analysis` for display in `locale`
+pub(crate) fn format_analysis(analysis: &ffi::HealthAnalysis, locale: &str) -> String {
+    let style = number_style(locale);
+    format!(
+        "BMI {} {} · risk {}",
+        format_number(analysis.bmi, 1, style),
+        style.bmi_unit,
+        format_number(analysis.risk_score, 2, style)
+    )
+}
+
+/// Embeddings, overrides and isolates; one left in a name could end our
+/// isolate early or reorder the rest of the line
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+/// `text` as an isolate, with its own bidi controls removed
+fn push_isolated(out: &mut String, text: &str) {
+    out.push(FSI);
+    out.extend(text.chars().filter(|&c| !is_bidi_control(c)));
+    out.push(PDI);
+}
+
+/// One line per person, "name (city): BMI ... · risk ...", with every field
+/// isolated so mixed-direction text renders in order in any text widget
+///
+/// `analyses[i]` belongs to `people[i]`. Each line's base direction is
+/// left-to-right, taken from "BMI" since isolated text does not count.
+pub(crate) fn format_report_bidi_safe(
+    people: &[ffi::PersonSnapshot],
+    analyses: &[ffi::HealthAnalysis],
+    locale: &str,
+) -> Result<String, Error> {
+    if people.len() != analyses.len() {
+        return Err(Error::LengthMismatch {
+            what: "analyses",
+            expected: people.len(),
+            actual: analyses.len(),
+        });
+    }
+    let mut report = String::new();
+    for (person, analysis) in people.iter().zip(analyses) {
+        push_isolated(&mut report, &person.name);
+        report.push_str(" (");
+        push_isolated(&mut report, &person.city);
+        report.push_str("): ");
+        report.push_str(&format_analysis(analysis, locale));
+        report.push('\n');
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+    use crate::golden::assert_golden;
+
+    fn analysis(bmi: f64, risk_score: f64) -> ffi::HealthAnalysis {
+        ffi::HealthAnalysis {
+            bmi,
+            risk_score,
+            recommendation: String::new(),
+            city_risk_factor: 1.0,
+            extra_risk_factor: 1.0,
+            risk_factors: 0,
+            recommendation_id: 0,
+        }
+    }
+
+    #[test]
+    fn test_format_analysis_by_locale() {
+        let typical = analysis(22.857, 1.56);
+        assert_eq!(
+            format_analysis(&typical, "en-US"),
+            "BMI 22.9 kg/m² · risk 1.56"
+        );
+        assert_eq!(
+            format_analysis(&typical, "de_DE.UTF-8"),
+            "BMI 22,9 kg/m² · risk 1,56"
+        );
+        assert_eq!(
+            format_analysis(&typical, "ru"),
+            "BMI 22,9 кг/м² · risk 1,56"
+        );
+        assert_eq!(
+            format_analysis(&typical, "de-CH"),
+            format_analysis(&typical, "en")
+        );
+    }
+
+    #[test]
+    fn test_unknown_locales_format_as_english() {
+        let typical = analysis(22.857, 1.56);
+        for locale in ["", "C", "POSIX", "xx-YY", "ja_JP"] {
+            assert_eq!(
+                format_analysis(&typical, locale),
+                "BMI 22.9 kg/m² · risk 1.56"
+            );
+        }
+    }
+
+    #[test]
+    fn test_number_grouping_and_edge_values() {
+        let german = number_style("de");
+        assert_eq!(format_number(1234567.891, 2, german), "1.234.567,89");
+        assert_eq!(format_number(999.996, 2, german), "1.000,00");
+        assert_eq!(
+            format_number(-1234.5, 1, number_style("fr")),
+            "-1\u{202f}234,5"
+        );
+        assert_eq!(format_number(1234.5, 1, number_style("de-CH")), "1’234.5");
+        assert_eq!(format_number(-0.01, 1, &ENGLISH), "0.0");
+        assert_eq!(format_number(f64::NAN, 1, &ENGLISH), "—");
+        assert_eq!(format_number(f64::INFINITY, 2, german), "—");
+    }
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(parse_locale("pt_BR.UTF-8"), ("pt".into(), "BR".into()));
+        assert_eq!(parse_locale("sr-Latn-RS"), ("sr".into(), "RS".into()));
+        assert_eq!(parse_locale("de_CH@euro"), ("de".into(), "CH".into()));
+        assert_eq!(parse_locale("FR"), ("fr".into(), String::new()));
+    }
+
+    fn person(name: &str, city: &str) -> ffi::PersonSnapshot {
+        let mut person = snapshot(30, 1.75, city);
+        person.name = name.to_string();
+        person
+    }
+
+    /// The report with its invisible controls spelled out for review
+    fn visible(report: &str) -> String {
+        report.replace(FSI, "<FSI>").replace(PDI, "<PDI>")
+    }
+
+    #[test]
+    fn test_report_isolates_every_field() {
+        let people = [
+            person("Alice", "Boston"),
+            person("مريم", "القاهرة"),
+            person("דוד 2", "תל אביב"),
+            // Stray controls in the data are dropped, not balanced
+            person("Eve\u{202e}\u{2069}", "Lyon"),
+        ];
+        let analyses: Vec<_> = [(22.857, 1.0), (31.02, 1.56), (18.1, 1.3), (24.0, 1.1)]
+            .into_iter()
+            .map(|(bmi, risk)| analysis(bmi, risk))
+            .collect();
+
+        let report = format_report_bidi_safe(&people, &analyses, "en").unwrap();
+        assert_golden("report_bidi_safe", &visible(&report));
+        assert!(report.lines().all(|line| line.matches(FSI).count() == 2));
+
+        let german = format_report_bidi_safe(&people[..1], &analyses[..1], "de").unwrap();
+        assert_eq!(
+            visible(&german),
+            "<FSI>Alice<PDI> (<FSI>Boston<PDI>): BMI 22,9 kg/m² · risk 1,00\n"
+        );
+    }
+
+    #[test]
+    fn test_report_length_mismatch() {
+        let people = [person("Alice", "Boston")];
+        assert_eq!(
+            format_report_bidi_safe(&people, &[], "en"),
+            Err(Error::LengthMismatch {
+                what: "analyses",
+                expected: 1,
+                actual: 0,
+            })
+        );
+        assert_eq!(format_report_bidi_safe(&[], &[], "en").unwrap(), "");
+    }
+}
@@ -0,0 +1,201 @@
+//! Coordinates for addresses
+//!
+//! `geocode_address` asks the current `Geocoder` backend for the latitude
+//! and longitude of an address. The default backend is `OfflineGeocoder`, a
+//! built-in table of city centres that needs no network; with the
+//! `nominatim` feature the host can switch to a Nominatim server instead
+//! (see nominatim.rs), and Rust code can plug in any backend of its own
+//! with `set_geocoder`. `distance_km` geocodes two addresses and returns
+//! the great-circle distance between them.
+
+use std::sync::Arc;
+
+use crate::error::Error;
+use crate::ffi;
+use crate::state::Global;
+
+/// The parts of an address a geocoder looks at
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AddressQuery<'a> {
+    pub street: &'a str,
+    pub city: &'a str,
+    pub postal_code: &'a str,
+}
+
+/// A geocoding backend
+///
+/// Called from any thread, possibly concurrently. An address the backend
+/// has no coordinates for is `Error::AddressNotFound`.
+pub trait Geocoder: Send + Sync {
+    fn geocode(&self, address: &AddressQuery<'_>) -> Result<ffi::GeoPoint, Error>;
+}
+
+/// City centres, matched on the city alone
+///
+/// Precise enough for distances between cities and regional risk, not for
+/// anything within one city.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OfflineGeocoder;
+
+/// (city, latitude, longitude)
+pub(crate) const CITIES: &[(&str, f64, f64)] = &[
+    ("Athens", 37.9838, 23.7275),
+    ("Atlanta", 33.7490, -84.3880),
+    ("Berlin", 52.5200, 13.4050),
+    ("Boston", 42.3601, -71.0589),
+    ("Chicago", 41.8781, -87.6298),
+    ("Dallas", 32.7767, -96.7970),
+    ("Denver", 39.7392, -104.9903),
+    ("Houston", 29.7604, -95.3698),
+    ("London", 51.5074, -0.1278),
+    ("Los Angeles", 34.0522, -118.2437),
+    ("Madrid", 40.4168, -3.7038),
+    ("Miami", 25.7617, -80.1918),
+    ("Moscow", 55.7558, 37.6173),
+    ("New York", 40.7128, -74.0060),
+    ("Paris", 48.8566, 2.3522),
+    ("Philadelphia", 39.9526, -75.1652),
+    ("Phoenix", 33.4484, -112.0740),
+    ("Rome", 41.9028, 12.4964),
+    ("San Antonio", 29.4241, -98.4936),
+    ("San Diego", 32.7157, -117.1611),
+    ("San Francisco", 37.7749, -122.4194),
+    ("Seattle", 47.6062, -122.3321),
+    ("Sydney", -33.8688, 151.2093),
+    ("Tokyo", 35.6762, 139.6503),
+    ("Toronto", 43.6532, -79.3832),
+    ("Washington", 38.9072, -77.0369),
+];
+
+impl Geocoder for OfflineGeocoder {
+    fn geocode(&self, address: &AddressQuery<'_>) -> Result<ffi::GeoPoint, Error> {
+        let city = address.city.trim();
+        CITIES
+            .iter()
+            .find(|(name, _, _)| name.eq_ignore_ascii_case(city))
+            .map(|&(_, lat, lon)| ffi::GeoPoint { lat, lon })
+            .ok_or_else(|| Error::AddressNotFound(format!("no offline entry for city {city:?}")))
+    }
+}
+
+/// None: the OfflineGeocoder
+static GEOCODER: Global<Option<Arc<dyn Geocoder>>> = Global::new(|| None);
+
+/// Send every later geocode_address to `geocoder`
+pub fn set_geocoder(geocoder: Arc<dyn Geocoder>) {
+    *GEOCODER.write() = Some(geocoder);
+}
+
+/// Go back to the built-in city table
+pub(crate) fn use_offline_geocoder() {
+    *GEOCODER.write() = None;
+}
+
+/// Coordinates of `address` from the current backend
+pub fn geocode(address: &AddressQuery<'_>) -> Result<ffi::GeoPoint, Error> {
+    // Clone out of the lock so a slow backend does not hold it
+    let geocoder = GEOCODER.read().clone();
+    match geocoder {
+        Some(geocoder) => geocoder.geocode(address),
+        None => OfflineGeocoder.geocode(address),
+    }
+}
+
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn geocode_address(address: &ffi::Address) -> Result<ffi::GeoPoint, Error> {
+    geocode(&AddressQuery {
+        street: ffi::get_address_street(address).to_str().unwrap_or(""),
+        city: ffi::get_address_city(address).to_str().unwrap_or(""),
+        postal_code: ffi::get_address_postal_code(address).to_str().unwrap_or(""),
+    })
+}
+
+/// Mean radius of the earth
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Great-circle distance between two points by the haversine formula,
+/// treating the earth as a sphere (within 0.5% of the WGS 84 ellipsoid)
+pub fn haversine_km(a: &ffi::GeoPoint, b: &ffi::GeoPoint) -> f64 {
+    let (lat_a, lat_b) = (a.lat.to_radians(), b.lat.to_radians());
+    let half_dlat = (lat_b - lat_a) / 2.0;
+    let half_dlon = (b.lon - a.lon).to_radians() / 2.0;
+    let h = half_dlat.sin().powi(2) + lat_a.cos() * lat_b.cos() * half_dlon.sin().powi(2);
+    // Rounding can push h just above 1 for antipodal points
+    2.0 * EARTH_RADIUS_KM * h.sqrt().min(1.0).asin()
+}
+
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn distance_km(a: &ffi::Address, b: &ffi::Address) -> Result<f64, Error> {
+    Ok(haversine_km(&geocode_address(a)?, &geocode_address(b)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn city(city: &str) -> AddressQuery<'_> {
+        AddressQuery {
+            street: "",
+            city,
+            postal_code: "",
+        }
+    }
+
+    #[test]
+    fn test_offline_geocoder() {
+        let boston = OfflineGeocoder.geocode(&city("Boston")).unwrap();
+        assert_eq!((boston.lat, boston.lon), (42.3601, -71.0589));
+        let madrid = OfflineGeocoder.geocode(&city(" madrid ")).unwrap();
+        assert_eq!((madrid.lat, madrid.lon), (40.4168, -3.7038));
+
+        let error = OfflineGeocoder.geocode(&city("Atlantis")).unwrap_err();
+        assert!(matches!(error, Error::AddressNotFound(_)));
+        assert!(OfflineGeocoder.geocode(&city("")).is_err());
+    }
+
+    // The backend is process-wide, but no other test geocodes
+    #[test]
+    fn test_set_geocoder() {
+        struct Fixed;
+        impl Geocoder for Fixed {
+            fn geocode(&self, _: &AddressQuery<'_>) -> Result<ffi::GeoPoint, Error> {
+                Ok(ffi::GeoPoint { lat: 1.0, lon: 2.0 })
+            }
+        }
+
+        set_geocoder(Arc::new(Fixed));
+        assert_eq!(geocode(&city("Atlantis")).unwrap().lon, 2.0);
+        use_offline_geocoder();
+        assert!(geocode(&city("Atlantis")).is_err());
+        assert_eq!(geocode(&city("Boston")).unwrap().lat, 42.3601);
+    }
+
+    #[test]
+    fn test_haversine_km() {
+        let centre = |name| OfflineGeocoder.geocode(&city(name)).unwrap();
+        let (boston, new_york) = (centre("Boston"), centre("New York"));
+        assert!((haversine_km(&boston, &new_york) - 306.1).abs() < 0.5);
+        assert_eq!(
+            haversine_km(&boston, &new_york),
+            haversine_km(&new_york, &boston)
+        );
+        assert!((haversine_km(&centre("London"), &centre("Paris")) - 343.6).abs() < 0.5);
+        assert_eq!(haversine_km(&boston, &boston), 0.0);
+
+        // Across the antimeridian and between the poles
+        let point = |lat, lon| ffi::GeoPoint { lat, lon };
+        let one_degree = haversine_km(&point(0.0, 179.5), &point(0.0, -179.5));
+        assert!((one_degree - 111.2).abs() < 0.1);
+        let pole_to_pole = haversine_km(&point(90.0, 0.0), &point(-90.0, 0.0));
+        assert!((pole_to_pole - std::f64::consts::PI * EARTH_RADIUS_KM).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_city_table_is_sorted_and_in_range() {
+        assert!(CITIES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        for &(name, lat, lon) in CITIES {
+            assert!((-90.0..=90.0).contains(&lat), "{name}");
+            assert!((-180.0..=180.0).contains(&lon), "{name}");
+        }
+    }
+}
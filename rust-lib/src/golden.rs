@@ -0,0 +1,92 @@
+//! Golden-file tests for user-facing text
+//!
+//! `assert_golden("name", text)` compares `text` with
+//! src/snapshots/<name>.snap. On a mismatch or a missing file the new text is
+//! written next to it as <name>.snap.new and the test fails, so a wording
+//! change shows up as a file diff to review. Accept every pending change with
+//! `RUST_LIB_BLESS=1 cargo test`, then commit the .snap files.
+//!
+//! Recommendations are covered here; other user-facing text, such as the
+//! report in format.rs, calls `assert_golden` from its own tests.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::compute_health;
+use crate::person_like::MockPerson;
+
+pub(crate) fn assert_golden(name: &str, actual: &str) {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src")
+        .join("snapshots");
+    let golden = dir.join(format!("{name}.snap"));
+    let pending = dir.join(format!("{name}.snap.new"));
+
+    if std::env::var_os("RUST_LIB_BLESS").is_some_and(|bless| bless != "0") {
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&golden, actual).unwrap();
+        let _ = fs::remove_file(&pending);
+        return;
+    }
+
+    match fs::read_to_string(&golden) {
+        Ok(expected) if expected == actual => {
+            let _ = fs::remove_file(&pending);
+        }
+        expected => {
+            fs::write(&pending, actual).unwrap();
+            let first_difference = expected
+                .as_deref()
+                .unwrap_or("")
+                .lines()
+                .zip(actual.lines())
+                .position(|(expected, actual)| expected != actual)
+                .map_or("a line was added or removed".to_string(), |line| {
+                    format!("line {} differs", line + 1)
+                });
+            panic!(
+                "{name}: output does not match {} ({}); review {} and rerun with \
+                 RUST_LIB_BLESS=1 to accept it",
+                golden.display(),
+                if expected.is_ok() {
+                    first_difference
+                } else {
+                    "no snapshot yet".to_string()
+                },
+                pending.display()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_recommendations() {
+    let cases = [
+        (30, 1.75, 70.0, "Boston"),
+        (30, 1.75, 70.0, "Los Angeles"),
+        (30, 1.75, 70.0, "New York"),
+        (30, 1.75, 95.0, "Boston"),
+        (30, 1.75, 95.0, "New York"),
+        (12, 1.45, 40.0, "Boston"),
+        (70, 1.60, 80.0, "New York"),
+        (40, 0.0, 70.0, "Boston"),
+    ];
+
+    let mut text = String::new();
+    for (age, height_m, weight, city) in cases {
+        let person = MockPerson {
+            age,
+            height_m,
+            city,
+            // Not one of the names the risk hook test reacts to
+            name: "golden",
+            ..MockPerson::default()
+        };
+        let analysis = compute_health(&person, weight);
+        text.push_str(&format!(
+            "age {age}, {height_m} m, {weight} kg, {city}: BMI {:.1}, risk {:.2}\n  {}\n",
+            analysis.bmi, analysis.risk_score, analysis.recommendation
+        ));
+    }
+    assert_golden("recommendations", &text);
+}
@@ -0,0 +1,179 @@
+//! Extended grapheme clusters (UAX #29): what a reader sees as one character
+//!
+//! "👩‍👩‍👧" is five code points and eighteen UTF-8 bytes but one symbol on
+//! screen, and "é" may be one code point or two. `grapheme_count` is what
+//! `PersonInfo.name_graphemes` reports, so a UI can size and truncate names
+//! the way they are displayed. The break properties come from the generated
+//! `unicode_tables.rs`.
+
+use std::cmp::Ordering;
+
+use crate::normalize::{S_BASE, S_COUNT, T_COUNT};
+use crate::unicode_tables::{GraphemeBreak, EXTENDED_PICTOGRAPHIC, GRAPHEME_BREAKS};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Other,
+    Break(GraphemeBreak),
+    /// Hangul syllable without a trailing consonant
+    Lv,
+    /// Hangul syllable with a trailing consonant
+    Lvt,
+}
+
+fn in_range(c: u32, first: u32, last: u32) -> Ordering {
+    if last < c {
+        Ordering::Less
+    } else if first > c {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    }
+}
+
+fn class(c: char) -> Class {
+    let c = c as u32;
+    if (S_BASE..S_BASE + S_COUNT).contains(&c) {
+        return if (c - S_BASE).is_multiple_of(T_COUNT) {
+            Class::Lv
+        } else {
+            Class::Lvt
+        };
+    }
+    GRAPHEME_BREAKS
+        .binary_search_by(|&(first, last, _)| in_range(c, first, last))
+        .map_or(Class::Other, |index| Class::Break(GRAPHEME_BREAKS[index].2))
+}
+
+fn is_extended_pictographic(c: char) -> bool {
+    EXTENDED_PICTOGRAPHIC
+        .binary_search_by(|&(first, last)| in_range(c as u32, first, last))
+        .is_ok()
+}
+
+/// What the rules need to know about the text before a possible break
+#[derive(Default)]
+struct Context {
+    /// The cluster so far ends in Extended_Pictographic Extend*
+    pictographic: bool,
+    /// ... followed by a ZWJ (GB11)
+    pictographic_zwj: bool,
+    /// Regional indicators directly before; flags pair them up (GB12/13)
+    regional_indicators: usize,
+}
+
+impl Context {
+    fn advance(&mut self, c: char, class: Class) {
+        use GraphemeBreak::*;
+        self.pictographic_zwj = self.pictographic && class == Class::Break(Zwj);
+        self.pictographic =
+            is_extended_pictographic(c) || (self.pictographic && class == Class::Break(Extend));
+        self.regional_indicators = if class == Class::Break(RegionalIndicator) {
+            self.regional_indicators + 1
+        } else {
+            0
+        };
+    }
+}
+
+/// Whether there is a grapheme boundary between `before` and `after`
+fn is_boundary(before: Class, after: Class, after_char: char, context: &Context) -> bool {
+    use Class::{Break, Lv, Lvt};
+    use GraphemeBreak::*;
+    match (before, after) {
+        (Break(Cr), Break(Lf)) => false,
+        (Break(Control | Cr | Lf), _) | (_, Break(Control | Cr | Lf)) => true,
+        (Break(L), Break(L | V) | Lv | Lvt) => false,
+        (Lv | Break(V), Break(V | T)) => false,
+        (Lvt | Break(T), Break(T)) => false,
+        (_, Break(Extend | Zwj | SpacingMark)) | (Break(Prepend), _) => false,
+        (Break(Zwj), _) if context.pictographic_zwj => !is_extended_pictographic(after_char),
+        (Break(RegionalIndicator), Break(RegionalIndicator)) => {
+            context.regional_indicators.is_multiple_of(2)
+        }
+        _ => true,
+    }
+}
+
+/// Iterator over the extended grapheme clusters of a string
+pub(crate) struct Graphemes<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let mut chars = self.rest.char_indices();
+        let (_, first) = chars.next()?;
+        let mut before = class(first);
+        let mut context = Context::default();
+        context.advance(first, before);
+
+        let end = chars
+            .find(|&(_, c)| {
+                let after = class(c);
+                if is_boundary(before, after, c, &context) {
+                    return true;
+                }
+                context.advance(c, after);
+                before = after;
+                false
+            })
+            .map_or(self.rest.len(), |(index, _)| index);
+        let (cluster, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(cluster)
+    }
+}
+
+pub(crate) fn graphemes(text: &str) -> Graphemes<'_> {
+    Graphemes { rest: text }
+}
+
+/// Number of user-perceived characters in `text`
+pub(crate) fn grapheme_count(text: &str) -> usize {
+    if text.is_ascii() && !text.contains('\r') {
+        return text.len();
+    }
+    graphemes(text).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clusters(text: &str) -> Vec<&str> {
+        graphemes(text).collect()
+    }
+
+    #[test]
+    fn test_combining_marks_and_hangul() {
+        assert_eq!(clusters("Jose\u{301}"), ["J", "o", "s", "e\u{301}"]);
+        assert_eq!(grapheme_count("Jos\u{e9}"), 4);
+        // Conjoining jamo and a precomposed syllable with a trailing jamo
+        assert_eq!(
+            clusters("\u{1100}\u{1161}\u{11a8}\u{ac00}\u{11a8}"),
+            ["\u{1100}\u{1161}\u{11a8}", "\u{ac00}\u{11a8}"]
+        );
+        // Devanagari spacing vowel sign stays with its consonant
+        assert_eq!(grapheme_count("\u{915}\u{93f}"), 1);
+    }
+
+    #[test]
+    fn test_emoji() {
+        assert_eq!(grapheme_count("👩\u{200d}👩\u{200d}👧"), 1);
+        assert_eq!(grapheme_count("👍\u{1f3fd}"), 1);
+        // Flags pair regional indicators two by two
+        assert_eq!(clusters("🇨🇦🇯🇵🇫"), ["🇨🇦", "🇯🇵", "🇫"]);
+        // A ZWJ not after a pictograph does not join
+        assert_eq!(grapheme_count("a\u{200d}👍"), 2);
+    }
+
+    #[test]
+    fn test_ascii_and_line_breaks() {
+        assert_eq!(grapheme_count(""), 0);
+        assert_eq!(grapheme_count("Alice"), 5);
+        assert_eq!(clusters("a\r\nb\n\r"), ["a", "\r\n", "b", "\n", "\r"]);
+    }
+}
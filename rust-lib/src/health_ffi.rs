@@ -0,0 +1,709 @@
+//! Bridge for health analysis and the work scheduled around it
+//!
+//! Single and batch analysis, background jobs, exports, sessions, and the
+//! C++ hooks that observe or adjust the risk model.
+
+#[cxx::bridge]
+mod bridge {
+    // Types owned by person_ffi
+    unsafe extern "C++" {
+        include!("rust-lib/src/person_ffi.rs.h");
+
+        type PersonSnapshot = crate::person_ffi::PersonSnapshot;
+    }
+
+    #[cfg(not(feature = "stub-cpp"))]
+    unsafe extern "C++" {
+        include!("rust-lib/src/person_ffi.rs.h");
+
+        type Person = crate::person_ffi::Person;
+    }
+
+    // ============================================================================
+    // C++ CALLBACKS - Defined in C++ code (callbacks.h)
+    // Rust holds a reference and calls back into C++ while it works
+    // ============================================================================
+    #[cfg(not(feature = "stub-cpp"))]
+    unsafe extern "C++" {
+        include!("cpp-app/callbacks.h");
+
+        type ProgressCallback;
+
+        fn on_progress(self: &ProgressCallback, processed: usize, total: usize);
+
+        type JobCallback;
+
+        fn on_done(
+            self: &JobCallback,
+            job_id: u64,
+            status: JobStatus,
+            results: &Vec<HealthAnalysis>,
+        );
+
+        type RiskObserver;
+
+        fn on_high_risk(self: &RiskObserver, analysis: &HealthAnalysis);
+
+        type RiskFactorHook;
+
+        fn extra_risk(self: &RiskFactorHook, snapshot: &PersonSnapshot) -> f64;
+
+        type SessionSink;
+
+        fn on_result(self: &SessionSink, result: &SessionResult);
+    }
+
+    // ============================================================================
+    // BRIDGE STRUCTS - For data exchange between Rust and C++
+    // ============================================================================
+
+    /// Health analysis result - new Rust functionality
+    #[derive(Clone, Debug)]
+    struct HealthAnalysis {
+        bmi: f64,
+        risk_score: f64,
+        recommendation: String,
+        city_risk_factor: f64,
+        /// Factor from the host's RiskFactorHook (1.0 when none is set)
+        extra_risk_factor: f64,
+        /// How many of age, BMI, city and the hook raised the score
+        risk_factors: u32,
+        /// Which recommendation this is, in any language, for
+        /// render_recommendation; equal ids are equal recommendations
+        recommendation_id: u32,
+    }
+
+    /// The unit of the weights passed to the extended entry points
+    #[derive(Debug, Default)]
+    enum Units {
+        /// Kilograms
+        #[default]
+        Metric,
+        /// Pounds
+        Imperial,
+    }
+
+    /// Knobs for the extended analysis entry points
+    /// Value-initialized options ask for what the plain entry points do.
+    /// An empty `locale` means the locale option, an empty `model` (or
+    /// "default") the configured risk model, and a `timeout_ms` of 0 no
+    /// deadline. New knobs are added here rather than as parameters.
+    #[derive(Clone, Debug, Default)]
+    struct AnalysisOptions {
+        units: Units,
+        locale: String,
+        timeout_ms: u64,
+        model: String,
+        /// Fill in how each risk score was arrived at
+        include_explanations: bool,
+        /// Leave `recommendation` empty; render_recommendation makes it
+        /// from `recommendation_id` when it is needed
+        lazy_recommendations: bool,
+    }
+
+    /// One analysis, with its explanation when the options asked for one
+    #[derive(Clone, Debug)]
+    struct ExplainedAnalysis {
+        analysis: HealthAnalysis,
+        explanation: String,
+    }
+
+    /// Batch output that may cover only part of the input
+    /// `timed_out` is set when the deadline stopped the run early; `results`
+    /// then holds the analyses for the first `results.size()` people.
+    /// `explanations[i]` explains `results[i]`; empty unless requested
+    #[derive(Clone, Debug)]
+    struct BatchAnalysis {
+        results: Vec<HealthAnalysis>,
+        timed_out: bool,
+        explanations: Vec<String>,
+    }
+
+    /// A HealthAnalysis whose strings live in its batch's string table
+    /// `recommendation` and `city` (of the person) are indexes for
+    /// get_string(table_id, index)
+    #[derive(Clone, Debug)]
+    struct PooledAnalysis {
+        bmi: f64,
+        risk_score: f64,
+        recommendation: u32,
+        city: u32,
+        city_risk_factor: f64,
+        extra_risk_factor: f64,
+        risk_factors: u32,
+    }
+
+    /// Output of analyze_health_batch_pooled
+    /// Each distinct string of `results` is stored once, in the table
+    /// `table_id`, until release_string_table
+    #[derive(Clone, Debug)]
+    struct PooledBatch {
+        table_id: u64,
+        results: Vec<PooledAnalysis>,
+    }
+
+    /// What a batch context makes room for up front; 0 leaves a buffer
+    /// to grow on the first batch
+    #[derive(Clone, Debug, Default)]
+    struct BatchCapacity {
+        /// People per batch
+        people: usize,
+        /// Distinct recommendations and cities per pooled batch
+        distinct_strings: usize,
+    }
+
+    /// How the analysis cache of analyze_health has done
+    /// `hits` and `misses` count calls since the process started; `entries`
+    /// analyses are cached, of at most `capacity` (the cache_size option)
+    #[derive(Clone, Debug)]
+    struct CacheStats {
+        hits: u64,
+        misses: u64,
+        entries: usize,
+        capacity: usize,
+    }
+
+    /// What the string interner of batch jobs holds
+    /// `strings` distinct cities and email domains of `bytes` in all;
+    /// `hits` of the `lookups` since the process started found a string
+    /// to share
+    #[derive(Clone, Debug)]
+    struct InternerStats {
+        strings: usize,
+        bytes: usize,
+        lookups: u64,
+        hits: u64,
+    }
+
+    /// Heap bytes the engine holds, by owner
+    /// Estimated from the buffers' sizes, without allocator overhead;
+    /// `job_bytes` are the copied inputs of unfinished jobs and the results
+    /// nobody has taken yet, and `registry_bytes` is 0 without the registry
+    #[derive(Clone, Debug)]
+    struct MemoryReport {
+        registry_bytes: usize,
+        analysis_cache_bytes: usize,
+        interner_bytes: usize,
+        string_table_bytes: usize,
+        job_bytes: usize,
+        batch_context_bytes: usize,
+        total_bytes: usize,
+    }
+
+    /// One problem found in a configuration
+    /// `file` is the config file, or the RUST_LIB_* variable that set the
+    /// value; `line` is 0 when the problem is not on one line, and
+    /// `setting` ("bmi.overweight_from") empty when it is about no setting
+    #[derive(Clone, Debug)]
+    struct ConfigIssue {
+        file: String,
+        line: u32,
+        setting: String,
+        message: String,
+    }
+
+    /// Every problem of a configuration; `valid` when there is none
+    #[derive(Clone, Debug)]
+    struct ConfigReport {
+        valid: bool,
+        issues: Vec<ConfigIssue>,
+    }
+
+    /// Lifecycle of an asynchronous job
+    enum JobStatus {
+        Running,
+        Completed,
+        Failed,
+        Cancelled,
+        Unknown,
+        /// Waiting for a free worker
+        Queued,
+    }
+
+    /// File formats supported by the exporters
+    enum ExportFormat {
+        /// Comma-separated values with a header row
+        Csv,
+        /// One JSON object per line
+        Ndjson,
+        /// Apache Parquet, one column per field, with the `parquet` feature
+        Parquet,
+    }
+
+    /// One result delivered by a streaming session
+    /// `sequence` is the value session_push returned for this person
+    #[derive(Clone, Debug)]
+    struct SessionResult {
+        sequence: u64,
+        analysis: HealthAnalysis,
+    }
+
+    /// Sex as the BMR equation distinguishes it
+    enum Sex {
+        Female,
+        Male,
+    }
+
+    /// Physical activity level, for the TDEE multiplier
+    enum ActivityLevel {
+        /// Desk work, little or no exercise
+        Sedentary,
+        /// Exercise 1-3 days a week
+        Light,
+        /// Exercise 3-5 days a week
+        Moderate,
+        /// Exercise 6-7 days a week
+        Active,
+        /// Hard daily exercise or a physical job
+        VeryActive,
+    }
+
+    /// Scheduling class of an asynchronous job
+    /// Interactive jobs always start before queued batch jobs
+    enum JobPriority {
+        Interactive,
+        Batch,
+    }
+
+    // ============================================================================
+    // RUST FUNCTIONS - New functionality exposed to C++
+    // ============================================================================
+    extern "Rust" {
+        /// Perform health analysis on a C++ Person object
+        /// Demonstrates: New Rust functionality working with existing C++ types
+        /// Repeated calls for the same person and weight are answered from
+        /// a cache; observers and subscribers still see every call
+        #[cfg(not(feature = "stub-cpp"))]
+        fn analyze_health(person: &Person, weight_kg: f64) -> HealthAnalysis;
+
+        /// Hit and miss counts and the fill of analyze_health's cache
+        fn cache_stats() -> CacheStats;
+
+        /// Forget every cached analysis, for a RiskFactorHook whose
+        /// factors changed
+        fn clear_analysis_cache();
+
+        /// How many cities and email domains batch jobs share, and how
+        /// often a person found theirs already there
+        fn interner_stats() -> InternerStats;
+
+        /// What the registry, the analysis cache, the interner, string
+        /// tables and jobs hold, for a diagnostics panel; an owner that
+        /// keeps growing is leaking
+        fn memory_report() -> MemoryReport;
+
+        /// Calculate BMI - pure Rust calculation
+        fn calculate_bmi(weight_kg: f64, height_m: f64) -> f64;
+
+        /// Basal metabolic rate in kcal/day (Mifflin-St Jeor); 0 for a
+        /// weight or height of 0 or less
+        fn calculate_bmr(weight_kg: f64, height_m: f64, age: u32, sex: Sex) -> f64;
+
+        /// Total daily energy expenditure in kcal/day: `bmr` times the
+        /// activity factor (1.2 sedentary to 1.9 very active)
+        fn calculate_tdee(bmr: f64, activity: ActivityLevel) -> f64;
+
+        /// BMI and risk score as one display line, e.g. "BMI 22,9 kg/m² · risk 1,56"
+        /// `locale` is a BCP 47 or POSIX name; unknown locales format as English
+        fn format_analysis(analysis: &HealthAnalysis, locale: &str) -> String;
+
+        /// One format_analysis line per person, after their name and city
+        /// Names and cities are bidi-isolated, so Arabic or Hebrew text does
+        /// not reorder the numbers; `analyses[i]` belongs to `people[i]`
+        fn format_report_bidi_safe(
+            people: &[PersonSnapshot],
+            analyses: &[HealthAnalysis],
+            locale: &str,
+        ) -> Result<String>;
+
+        /// The recommendation in `locale`'s language, with correct plurals
+        /// ("1 risk factor", "2 фактора риска"); English when untranslated
+        fn localized_recommendation(analysis: &HealthAnalysis, locale: &str) -> String;
+
+        /// The recommendation of `recommendation_id` in `locale`'s language;
+        /// throws for an id no analysis gives
+        fn render_recommendation(recommendation_id: u32, locale: &str) -> Result<String>;
+
+        /// Health analysis for many people at once
+        /// `weights_kg[i]` is the weight of `people[i]`; mismatched lengths throw
+        /// Pass 0 as `cancel_token` for a batch that cannot be cancelled
+        fn analyze_health_batch(
+            people: &[PersonSnapshot],
+            weights_kg: &[f64],
+            cancel_token: u64,
+        ) -> Result<Vec<HealthAnalysis>>;
+
+        /// Same as analyze_health_batch, reporting progress to C++
+        /// `progress` is invoked after every `report_every` items and once at the end
+        #[cfg(not(feature = "stub-cpp"))]
+        fn analyze_health_batch_with_progress(
+            people: &[PersonSnapshot],
+            weights_kg: &[f64],
+            progress: &ProgressCallback,
+            report_every: usize,
+            cancel_token: u64,
+        ) -> Result<Vec<HealthAnalysis>>;
+
+        /// analyze_health with every field of `options` but `timeout_ms`,
+        /// which only batches honor
+        /// Throws for an unknown locale or model
+        #[cfg(not(feature = "stub-cpp"))]
+        fn analyze_health_with_options(
+            person: &Person,
+            weight: f64,
+            options: &AnalysisOptions,
+        ) -> Result<ExplainedAnalysis>;
+
+        /// Batch analysis honoring `options`, `weights` in `options.units`
+        /// On timeout, returns the partial results with `timed_out = true`
+        fn analyze_health_batch_with_options(
+            people: &[PersonSnapshot],
+            weights: &[f64],
+            options: &AnalysisOptions,
+            cancel_token: u64,
+        ) -> Result<BatchAnalysis>;
+
+        /// analyze_health_batch with each distinct recommendation and city
+        /// stored once, in a string table, for runs too large for a String
+        /// per person
+        /// Release the table once the strings have been read
+        fn analyze_health_batch_pooled(
+            people: &[PersonSnapshot],
+            weights_kg: &[f64],
+            cancel_token: u64,
+        ) -> Result<PooledBatch>;
+
+        /// String `index` of a pooled batch's table; throws for an unknown
+        /// table or index
+        fn get_string(table_id: u64, index: u32) -> Result<String>;
+
+        /// Free a pooled batch's string table; false if the id was unknown
+        fn release_string_table(table_id: u64) -> bool;
+
+        /// Create buffers for repeated batch calls, sized by `capacity`,
+        /// and return their id
+        fn create_batch_context(capacity: &BatchCapacity) -> u64;
+
+        /// analyze_health_batch_with_options reusing the buffers of a batch
+        /// context; throws for an unknown context
+        fn analyze_health_batch_in_context(
+            context_id: u64,
+            people: &[PersonSnapshot],
+            weights: &[f64],
+            options: &AnalysisOptions,
+            cancel_token: u64,
+        ) -> Result<BatchAnalysis>;
+
+        /// analyze_health_batch_pooled reusing the buffers of a batch
+        /// context; each call still returns a table of its own
+        fn analyze_health_batch_pooled_in_context(
+            context_id: u64,
+            people: &[PersonSnapshot],
+            weights_kg: &[f64],
+            cancel_token: u64,
+        ) -> Result<PooledBatch>;
+
+        /// Free a batch context; false if the id was unknown
+        fn release_batch_context(context_id: u64) -> bool;
+
+        /// Create a token that can stop batch and async work from C++
+        fn create_cancel_token() -> u64;
+
+        /// Request cancellation - work holding the token stops at the next item
+        fn cancel(token: u64);
+
+        /// Forget a token once the work using it has finished
+        fn release_cancel_token(token: u64);
+
+        /// Run analyze_health as an interactive background job and return its id
+        #[cfg(not(feature = "stub-cpp"))]
+        fn submit_analysis(person: &Person, weight_kg: f64, cancel_token: u64) -> u64;
+
+        /// Run analyze_health as an interactive background job, reporting to `on_done`
+        /// The callback runs on a dedicated Rust callback thread, never the caller's
+        #[cfg(not(feature = "stub-cpp"))]
+        fn submit_analysis_with_callback(
+            person: &Person,
+            weight_kg: f64,
+            cancel_token: u64,
+            on_done: UniquePtr<JobCallback>,
+        ) -> Result<u64>;
+
+        /// Run a batch analysis as a batch-priority background job and return its id
+        fn submit_health_batch(
+            people: &[PersonSnapshot],
+            weights_kg: &[f64],
+            cancel_token: u64,
+        ) -> Result<u64>;
+
+        /// Same as submit_health_batch with an explicit scheduling class
+        fn submit_health_batch_with_priority(
+            people: &[PersonSnapshot],
+            weights_kg: &[f64],
+            priority: JobPriority,
+            cancel_token: u64,
+        ) -> Result<u64>;
+
+        /// Write snapshots to `path`; returns the number of rows written
+        /// Blocks the caller - prefer submit_export for large exports
+        fn export_people(
+            people: &[PersonSnapshot],
+            path: &str,
+            format: ExportFormat,
+        ) -> Result<u64>;
+
+        /// Write snapshots to `path` as a batch-priority background job
+        /// Poll with job_status, then collect the row count with take_export_result
+        fn submit_export(
+            people: &[PersonSnapshot],
+            path: &str,
+            format: ExportFormat,
+            cancel_token: u64,
+        ) -> Result<u64>;
+
+        /// Row count of a finished export job; forgets the job
+        fn take_export_result(job_id: u64) -> Result<u64>;
+
+        /// analyze_health of a registered person, if they consented to
+        /// analysis; kept from the webhook and remote sync unless they
+        /// consented to sharing too. Throws ConsentDenied otherwise
+        #[cfg(feature = "registry")]
+        fn analyze_registered(person_id: u64, weight_kg: f64) -> Result<HealthAnalysis>;
+
+        /// export_people of registered persons, if every one of them
+        /// consented to export; writes nothing otherwise
+        #[cfg(feature = "registry")]
+        fn export_registered(
+            person_ids: &[u64],
+            path: &str,
+            format: ExportFormat,
+        ) -> Result<u64>;
+
+        /// Encrypt the name, email, phone and street of later exports with
+        /// AES-256-GCM under `key`, 32 bytes; throws for another length
+        #[cfg(feature = "crypto")]
+        fn set_export_key(key: &[u8]) -> Result<()>;
+
+        /// Export personal fields in plain text again
+        #[cfg(feature = "crypto")]
+        fn clear_export_key();
+
+        /// Plain text of the encrypted `field` of data row `row` (from 0)
+        /// of an export, under the current key
+        /// Throws for another key, field or row, or altered data
+        #[cfg(feature = "crypto")]
+        fn decrypt_export_field(field: &str, row: u64, value: &str) -> Result<String>;
+
+        /// Wipe and forget the export and anonymization keys and the cached
+        /// analyses; call on shutdown (see zeroize.rs)
+        fn scrub_sensitive_memory();
+
+        /// Current state of a submitted job
+        fn job_status(job_id: u64) -> JobStatus;
+
+        /// Collect the results of a finished job and forget it
+        /// Throws if the job is still running, failed, or was cancelled
+        fn take_job_results(job_id: u64) -> Result<Vec<HealthAnalysis>>;
+
+        /// Call `callback` for every analysis whose risk score exceeds `threshold`
+        /// Runs synchronously on the analyzing thread; returns an observer id
+        #[cfg(not(feature = "stub-cpp"))]
+        fn register_risk_observer(threshold: f64, callback: UniquePtr<RiskObserver>) -> u64;
+
+        /// Stop notifying an observer; false if the id was unknown
+        fn unregister_risk_observer(id: u64) -> bool;
+
+        /// Route the webhook, sync and geocoding requests through a proxy,
+        /// set the CA bundle and client certificate (PEM files) for https,
+        /// and their timeouts, from JSON like
+        /// {"proxy_url": "http://user:pw@proxy:3128", "connect_timeout_ms": 5000,
+        ///  "timeout_ms": 30000, "ca_bundle": "/etc/ssl/corp.pem"}; "{}"
+        /// restores direct connections, the built-in CA roots and 10 s
+        /// timeouts. Throws for unknown fields and unreadable PEM files
+        #[cfg(any(feature = "nominatim", feature = "webhook", feature = "sync"))]
+        fn set_network_config(json: &str) -> Result<()>;
+
+        /// POST every analysis whose risk score exceeds `threshold` to `url`
+        /// as JSON, from a background thread that retries with backoff
+        /// Replaces any previous webhook; throws for a URL other than http://
+        /// or https://
+        #[cfg(feature = "webhook")]
+        fn configure_webhook(url: &str, threshold: f64) -> Result<()>;
+
+        /// Stop sending webhook alerts; undelivered ones are dropped
+        #[cfg(feature = "webhook")]
+        fn disable_webhook();
+
+        /// Record every analysis in the offline queue file at `queue_path`
+        /// and upload the records to `url` in the background
+        /// Records already in the file are kept; replaces any previous sync
+        #[cfg(feature = "sync")]
+        fn configure_sync(url: &str, queue_path: &str) -> Result<()>;
+
+        /// Upload every pending record now and return how many were sent
+        /// Throws if sync is not configured or the upload fails
+        #[cfg(feature = "sync")]
+        fn sync_now() -> Result<usize>;
+
+        /// Records waiting for upload; 0 when sync is not configured
+        #[cfg(feature = "sync")]
+        fn pending_upload_count() -> usize;
+
+        /// Stop recording and uploading; the queue file keeps what is pending
+        #[cfg(feature = "sync")]
+        fn disable_sync();
+
+        /// Install a C++ hook whose factor is multiplied into every risk score
+        /// Replaces any previous hook
+        #[cfg(not(feature = "stub-cpp"))]
+        fn set_extra_risk_hook(hook: UniquePtr<RiskFactorHook>);
+
+        /// Remove the extra risk hook; scores go back to the built-in model
+        fn clear_extra_risk_hook();
+
+        /// Replace the model's cutoffs, age limits, city factors and
+        /// validation strictness with those of the TOML file at `path`
+        /// Applies to analyses started afterwards; throws, keeping the
+        /// configuration in effect, if the file is unreadable or invalid
+        fn load_config(path: &str) -> Result<()>;
+
+        /// load_config, but returning every problem of the file and the
+        /// environment instead of throwing; the configuration is replaced
+        /// only when the report is valid
+        fn load_config_with_report(path: &str) -> ConfigReport;
+
+        /// Replace the city risk factors with those of `source`: JSON text
+        /// like {"Denver": 0.9}, a .json file of it, or any other file as
+        /// CSV of `city,factor` rows; cities not listed get 1.0
+        /// Keeps the other settings; throws, keeping the table in effect,
+        /// if the source is unreadable or invalid
+        fn set_city_risk_table(source: &str) -> Result<()>;
+
+        /// The built-in configuration as a commented TOML file, headed
+        /// with this library's version, to start a config file from
+        fn dump_default_config() -> String;
+
+        /// Change an engine setting: "locale" (of recommendations),
+        /// "worker_threads" (background job pool, at least 2),
+        /// "validation_profile" (as set_validation_profile) or
+        /// "cache_size" (analyses analyze_health keeps, 0 for none)
+        /// Throws for unknown keys and invalid values
+        fn set_option(key: &str, value: &str) -> Result<()>;
+
+        /// An engine setting as set_option takes it; empty for unknown keys
+        fn get_option(key: &str) -> String;
+
+        /// load_config, then load the file again whenever it changes
+        /// Checks every `poll_interval_ms`; each reload publishes
+        /// ConfigReloaded, and an invalid edit keeps the configuration in
+        /// effect. Replaces any watch started before
+        #[cfg(feature = "watch")]
+        fn watch_config(path: &str, poll_interval_ms: u64) -> Result<()>;
+
+        /// Stop watching the config file; the configuration stays
+        #[cfg(feature = "watch")]
+        fn stop_watching_config();
+
+        /// Open a streaming session and return its id
+        fn open_session() -> u64;
+
+        /// Deliver the session's results to `sink` from now on
+        /// Results buffered before the call are flushed to it first
+        #[cfg(not(feature = "stub-cpp"))]
+        fn set_session_sink(session_id: u64, sink: UniquePtr<SessionSink>) -> Result<()>;
+
+        /// Queue a person for analysis; returns its sequence number
+        /// Results arrive in push order, on the sink or via session_pull
+        #[cfg(not(feature = "stub-cpp"))]
+        fn session_push(session_id: u64, person: &Person, weight_kg: f64) -> Result<u64>;
+
+        /// Results buffered since the last pull (sessions without a sink)
+        fn session_pull(session_id: u64) -> Result<Vec<SessionResult>>;
+
+        /// End a session, discarding undelivered results; false if unknown
+        fn close_session(session_id: u64) -> bool;
+    }
+}
+
+pub use bridge::{
+    ActivityLevel, AnalysisOptions, BatchAnalysis, BatchCapacity, CacheStats, ConfigIssue, ConfigReport,
+    ExplainedAnalysis, ExportFormat, HealthAnalysis, InternerStats, JobPriority, JobStatus,
+    MemoryReport, PooledAnalysis, PooledBatch, SessionResult, Sex, Units,
+};
+#[cfg(not(feature = "stub-cpp"))]
+pub use bridge::{JobCallback, ProgressCallback, RiskFactorHook, RiskObserver, SessionSink};
+
+#[cfg(not(feature = "stub-cpp"))]
+use crate::{analyze_health, analyze_health_with_options};
+#[cfg(not(feature = "stub-cpp"))]
+use crate::batch::analyze_health_batch_with_progress;
+use crate::analysis_cache::{cache_stats, clear_analysis_cache};
+use crate::batch::{
+    analyze_health_batch, analyze_health_batch_pooled, analyze_health_batch_with_options,
+};
+use crate::batch_context::{
+    analyze_health_batch_in_context, analyze_health_batch_pooled_in_context,
+    create_batch_context, release_batch_context,
+};
+use crate::calculate_bmi;
+use crate::intern::interner_stats;
+use crate::memory::memory_report;
+use crate::cancel::{cancel, create_cancel_token, release_cancel_token};
+use crate::config::{
+    dump_default_config, load_config, load_config_with_report, set_city_risk_table,
+};
+#[cfg(feature = "watch")]
+use crate::config_watch::{stop_watching_config, watch_config};
+#[cfg(feature = "registry")]
+use crate::consent::{analyze_registered, export_registered};
+use crate::export::{export_people, submit_export};
+#[cfg(feature = "crypto")]
+use crate::export_crypto::{clear_export_key, decrypt_export_field, set_export_key};
+use crate::format::{format_analysis, format_report_bidi_safe};
+#[cfg(any(feature = "nominatim", feature = "webhook", feature = "sync"))]
+use crate::http_client::set_network_config;
+use crate::jobs::{
+    job_status, submit_health_batch, submit_health_batch_with_priority, take_export_result,
+    take_job_results,
+};
+#[cfg(not(feature = "stub-cpp"))]
+use crate::jobs::{submit_analysis, submit_analysis_with_callback};
+#[cfg(not(feature = "stub-cpp"))]
+use crate::observers::register_risk_observer;
+use crate::observers::unregister_risk_observer;
+#[cfg(feature = "sync")]
+use crate::remote_sync::{configure_sync, disable_sync, pending_upload_count, sync_now};
+use crate::risk_hooks::clear_extra_risk_hook;
+#[cfg(not(feature = "stub-cpp"))]
+use crate::risk_hooks::set_extra_risk_hook;
+use crate::runtime_options::{get_option, set_option};
+use crate::session::{close_session, open_session, session_pull};
+use crate::string_table::{get_string, release_string_table};
+#[cfg(not(feature = "stub-cpp"))]
+use crate::session::{session_push, set_session_sink};
+use crate::templates::{localized_recommendation, render_recommendation};
+#[cfg(feature = "webhook")]
+use crate::webhook::{configure_webhook, disable_webhook};
+use crate::zeroize::scrub_sensitive_memory;
+
+// The formulas crate has its own enums, as it cannot depend on cxx. Values
+// C++ casts in from outside the enums read as Female and Sedentary.
+
+fn calculate_bmr(weight_kg: f64, height_m: f64, age: u32, sex: Sex) -> f64 {
+    let sex = match sex {
+        Sex::Male => rust_lib_formulas::Sex::Male,
+        _ => rust_lib_formulas::Sex::Female,
+    };
+    rust_lib_formulas::calculate_bmr(weight_kg, height_m, age, sex)
+}
+
+fn calculate_tdee(bmr: f64, activity: ActivityLevel) -> f64 {
+    let activity = match activity {
+        ActivityLevel::Light => rust_lib_formulas::ActivityLevel::Light,
+        ActivityLevel::Moderate => rust_lib_formulas::ActivityLevel::Moderate,
+        ActivityLevel::Active => rust_lib_formulas::ActivityLevel::Active,
+        ActivityLevel::VeryActive => rust_lib_formulas::ActivityLevel::VeryActive,
+        _ => rust_lib_formulas::ActivityLevel::Sedentary,
+    };
+    rust_lib_formulas::calculate_tdee(bmr, activity)
+}
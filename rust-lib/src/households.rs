@@ -0,0 +1,128 @@
+//! Households: registered persons grouped by address
+//!
+//! `group_households` puts every registered person whose address has the
+//! same `address_key` into one household: the address after the
+//! normalization of `address_similarity`, so "12 Main St." and
+//! "12 MAIN STREET" share one. Grouping is on equal keys only; a flat
+//! number or a different house number makes another household.
+//!
+//! Only an address with a street and a city or postal code, all after
+//! normalization, places a person in a household. Everyone with only a
+//! city, say, would otherwise share one with the whole town; such persons
+//! are in none.
+
+use std::collections::BTreeMap;
+
+use crate::address_similarity::dwelling_key;
+use crate::ffi;
+use crate::geocode::AddressQuery;
+use crate::registry::registry_entries;
+
+/// The households of the registry, sorted by address key, with their
+/// member ids in ascending order; one-person households included
+pub(crate) fn group_households() -> Vec<ffi::Household> {
+    let mut households: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+    for (id, person) in registry_entries() {
+        let key = dwelling_key(&AddressQuery {
+            street: &person.street,
+            city: &person.city,
+            postal_code: &person.postal_code,
+        });
+        if let Some(key) = key {
+            households.entry(key).or_default().push(id);
+        }
+    }
+    households
+        .into_iter()
+        .map(|(address_key, mut member_ids)| {
+            member_ids.sort_unstable();
+            ffi::Household {
+                address_key,
+                member_ids,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+    use crate::registry::{register_person, unregister_person};
+
+    #[test]
+    fn test_group_households() {
+        // Streets no other test registers, since tests share the registry
+        let person = |street: &str, city: &str, postal_code: &str| {
+            let mut person = snapshot(40, 1.70, city);
+            person.street = street.to_string();
+            person.postal_code = postal_code.to_string();
+            register_person(person)
+        };
+        let ids = [
+            person("12 Household Ln.", "Boston", "02101"),
+            person("12 HOUSEHOLD LANE", "boston", "02101-1234"),
+            person("14 Household Lane", "Boston", "02101"),
+            person("12 Household Lane", "Boston", "02101"),
+        ];
+        let nowhere = person("", "", "");
+
+        let households: Vec<_> = group_households()
+            .into_iter()
+            .filter(|household| household.address_key.contains("household lane"))
+            .map(|household| (household.address_key, household.member_ids))
+            .collect();
+        assert_eq!(
+            households,
+            [
+                (
+                    "12 household lane, boston, 02101".to_string(),
+                    vec![ids[0], ids[1], ids[3]]
+                ),
+                ("14 household lane, boston, 02101".to_string(), vec![ids[2]]),
+            ]
+        );
+        assert!(group_households()
+            .iter()
+            .all(|household| !household.member_ids.contains(&nowhere)));
+
+        for id in ids.into_iter().chain([nowhere]) {
+            assert!(unregister_person(id));
+        }
+    }
+
+    #[test]
+    fn test_incomplete_addresses_are_in_no_household() {
+        // A town and postal code no other test registers
+        let person = |street: &str, city: &str, postal_code: &str| {
+            let mut person = snapshot(40, 1.70, city);
+            person.street = street.to_string();
+            person.postal_code = postal_code.to_string();
+            register_person(person)
+        };
+        let ids = [
+            // No street, or one that normalizes to nothing
+            person("", "Streetless Town", "09876"),
+            person("", "Streetless Town", "09876"),
+            person(" -- ", "Streetless Town", "09876"),
+            // A street and nothing to place it
+            person("7 Streetless Road", "", ""),
+        ];
+        let placed = person("7 Streetless Road", "", "09876");
+
+        let households = group_households();
+        for id in ids {
+            assert!(households
+                .iter()
+                .all(|household| !household.member_ids.contains(&id)));
+        }
+        assert!(households.iter().any(|household| {
+            household.address_key == "7 streetless road, , 09876"
+                && household.member_ids == [placed]
+        }));
+
+        for id in ids.into_iter().chain([placed]) {
+            assert!(unregister_person(id));
+        }
+    }
+}
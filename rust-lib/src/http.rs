@@ -0,0 +1,358 @@
+//! Embedded HTTP server (`http` feature)
+//!
+//! `start_http_server` lets the C++ app expose its Rust engine to tools on
+//! the local network as JSON over HTTP/1.1:
+//!
+//! - `POST /analyze`: a snapshot plus `weight_kg` -> HealthAnalysis
+//! - `POST /validate`: a snapshot -> ValidationReport
+//! - `GET /registry`: every registered person with `sharing` consent,
+//!   `[{"id", "person"}]`
+//! - `POST /registry`: a snapshot to register -> `{"id"}`
+//! - `GET /openapi.json`: the OpenAPI 3 description of these (openapi.rs)
+//!
+//! Snapshots have the fields of an NDJSON export line. Errors are
+//! `{"error": message}` with a 4xx status, or 503 for a request over an
+//! in-flight cap, also answered to new connections while the servers are
+//! at the global cap; rate limits answer 429 (see service_limits.rs). The
+//! server is http_server.rs, so the feature adds no dependency beyond
+//! `serde`; it is meant for a trusted network, with no TLS or
+//! authentication.
+
+use std::net::SocketAddr;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::Error;
+use crate::ffi;
+use crate::http_server::{Reply, Request, Server, Service};
+use crate::openapi;
+use crate::service_limits;
+use crate::state::Global;
+
+static HTTP: Global<Option<Server>> = Global::new(|| None);
+
+/// POST /analyze's body
+#[derive(Deserialize)]
+struct AnalyzeRequest {
+    #[serde(flatten)]
+    person: ffi::PersonSnapshot,
+    weight_kg: f64,
+}
+
+struct Response {
+    status: u16,
+    body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Response { status: 200, body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Response {
+            status,
+            body: json!({ "error": message.into() }),
+        }
+    }
+}
+
+/// The JSON API as a Service
+struct Api;
+
+impl Service for Api {
+    fn respond(&self, request: &Request) -> Reply {
+        let path = request.path.split('?').next().unwrap_or_default();
+        let _admission = match service_limits::admit_request(path) {
+            Ok(admission) => admission,
+            Err(refused) => {
+                let status = match refused {
+                    Error::RateLimited(_) => 429,
+                    _ => 503,
+                };
+                return Response::error(status, refused.to_string()).into();
+            }
+        };
+        route(&request.method, &request.path, &request.body).into()
+    }
+
+    fn reject(&self, status: u16, message: &str) -> Reply {
+        Response::error(status, message).into()
+    }
+
+    // Refused before the body is read, so a flood costs no memory; the
+    // admission above still decides for the connections let through
+    fn shed(&self) -> Option<Reply> {
+        service_limits::at_capacity().then(|| {
+            let refused = Error::Overloaded("HTTP API".to_string());
+            Response::error(503, refused.to_string()).into()
+        })
+    }
+}
+
+impl From<Response> for Reply {
+    fn from(response: Response) -> Self {
+        Reply {
+            status: response.status,
+            content_type: "application/json",
+            body: response.body.to_string().into_bytes(),
+        }
+    }
+}
+
+pub(crate) fn start_http_server(addr: &str) -> Result<(), Error> {
+    start(addr).map(|_| ())
+}
+
+/// Bind `addr` and start accepting; the bound address, for port 0
+fn start(addr: &str) -> Result<SocketAddr, Error> {
+    let mut server = HTTP.write();
+    if server.is_some() {
+        return Err(Error::AlreadyRunning("HTTP server"));
+    }
+    let bound = Server::bind(addr, "HTTP", Api)?;
+    let addr = bound.addr();
+    *server = Some(bound);
+    Ok(addr)
+}
+
+/// Stop accepting and join the server's accept thread; requests in
+/// progress still get their response
+pub(crate) fn stop_http_server() {
+    let server = HTTP.write().take();
+    if let Some(server) = server {
+        server.stop();
+    }
+}
+
+/// The response to one request
+fn route(method: &str, path: &str, body: &[u8]) -> Response {
+    let path = path.split('?').next().unwrap_or(path);
+    match (method, path) {
+        ("POST", "/analyze") => parse(body).map_or_else(
+            |response| response,
+            |request: AnalyzeRequest| {
+                let analysis = crate::analyze_snapshot(&request.person, request.weight_kg);
+                Response::ok(json!(analysis))
+            },
+        ),
+        ("POST", "/validate") => parse(body).map_or_else(
+            |response| response,
+            |person: ffi::PersonSnapshot| {
+                Response::ok(report_json(&crate::validate_snapshot(&person)))
+            },
+        ),
+        #[cfg(feature = "registry")]
+        ("GET", "/registry") => {
+            let mut entries = crate::registry::registry_entries();
+            // Serving a person to the network is sharing them
+            entries.retain(|&(id, _)| crate::consent::get_consent(id).sharing);
+            entries.sort_by_key(|&(id, _)| id);
+            crate::audit::set_audit_caller("http");
+            for &(id, _) in &entries {
+                crate::audit::record("GET /registry", id, crate::audit::PERSON_FIELDS);
+            }
+            let entries: Vec<Value> = entries
+                .into_iter()
+                .map(|(id, person)| json!({ "id": id, "person": person }))
+                .collect();
+            Response::ok(Value::Array(entries))
+        }
+        #[cfg(feature = "registry")]
+        ("POST", "/registry") => parse(body).map_or_else(
+            |response| response,
+            |person: ffi::PersonSnapshot| {
+                Response::ok(json!({ "id": crate::registry::register_person(person) }))
+            },
+        ),
+        ("GET", "/openapi.json") => Response::ok(openapi::document()),
+        (_, "/analyze" | "/validate") => Response::error(405, "use POST"),
+        #[cfg(feature = "registry")]
+        (_, "/registry") => Response::error(405, "use GET or POST"),
+        (_, "/openapi.json") => Response::error(405, "use GET"),
+        _ => Response::error(404, format!("no endpoint {path}")),
+    }
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(body: &[u8]) -> Result<T, Response> {
+    serde_json::from_slice(body).map_err(|e| Response::error(400, format!("invalid JSON: {e}")))
+}
+
+fn report_json(report: &ffi::ValidationReport) -> Value {
+    let issues: Vec<Value> = report
+        .issues
+        .iter()
+        .map(|issue| {
+            let severity = if issue.severity == ffi::Severity::Error {
+                "error"
+            } else {
+                "warning"
+            };
+            json!({ "field": issue.field, "message": issue.message, "severity": severity })
+        })
+        .collect();
+    json!({ "valid": report.valid, "issues": issues })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    fn person_json(age: u32, city: &str) -> Value {
+        serde_json::to_value(snapshot(age, 1.75, city)).unwrap()
+    }
+
+    #[test]
+    fn test_analyze_and_validate() {
+        let mut request = person_json(70, "Los Angeles");
+        request["weight_kg"] = json!(70.0);
+        let response = route("POST", "/analyze", request.to_string().as_bytes());
+        assert_eq!(response.status, 200);
+        let expected = crate::analyze_snapshot(&snapshot(70, 1.75, "Los Angeles"), 70.0);
+        assert_eq!(response.body, json!(expected));
+        assert_eq!(response.body["risk_factors"], json!(2));
+
+        let response = route(
+            "POST",
+            "/validate",
+            person_json(200, "").to_string().as_bytes(),
+        );
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body["valid"], json!(false));
+        assert_eq!(response.body["issues"][0]["field"], json!("age"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let missing_weight = person_json(30, "Boston").to_string();
+        let response = route("POST", "/analyze", missing_weight.as_bytes());
+        assert_eq!(response.status, 400);
+        assert!(response.body["error"]
+            .as_str()
+            .unwrap()
+            .contains("weight_kg"));
+
+        assert_eq!(route("GET", "/analyze", b"").status, 405);
+        assert_eq!(route("GET", "/nothing", b"").status, 404);
+        assert_eq!(route("POST", "/validate", b"{").status, 400);
+    }
+
+    // The server is a process-wide singleton, so its whole lifecycle over a
+    // real socket is this one test
+    fn sorted_keys(object: &Value) -> Vec<&String> {
+        let mut keys: Vec<&String> = object.as_object().unwrap().keys().collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_openapi_matches_handlers() {
+        let document = route("GET", "/openapi.json", b"").body;
+        for (path, operations) in document["paths"].as_object().unwrap() {
+            for method in operations.as_object().unwrap().keys() {
+                let status = route(&method.to_uppercase(), path, b"{}").status;
+                assert!(![404, 405].contains(&status), "{method} {path}: {status}");
+            }
+        }
+
+        let schemas = &document["components"]["schemas"];
+        let mut request = person_json(30, "Boston");
+        assert_eq!(
+            sorted_keys(&schemas["PersonSnapshot"]["properties"]),
+            sorted_keys(&request)
+        );
+        request["weight_kg"] = json!(70.0);
+        assert_eq!(
+            sorted_keys(&schemas["AnalyzeRequest"]["properties"]),
+            sorted_keys(&request)
+        );
+        let analysis = route("POST", "/analyze", request.to_string().as_bytes()).body;
+        assert_eq!(
+            sorted_keys(&schemas["HealthAnalysis"]["properties"]),
+            sorted_keys(&analysis)
+        );
+        let report = route("POST", "/validate", request.to_string().as_bytes()).body;
+        assert_eq!(
+            sorted_keys(&schemas["ValidationReport"]["properties"]),
+            sorted_keys(&report)
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let request = |path: &str| Request {
+            method: "GET".to_string(),
+            path: path.to_string(),
+            body: Vec::new(),
+        };
+        // An endpoint no other test requests
+        crate::set_endpoint_limits("/test/http", 0.001, 1, 0).unwrap();
+        assert_eq!(Api.respond(&request("/test/http")).status, 404);
+        let limited = Api.respond(&request("/test/http?again"));
+        assert_eq!(limited.status, 429);
+        assert!(String::from_utf8(limited.body)
+            .unwrap()
+            .contains("rate limit"));
+    }
+
+    #[cfg(feature = "registry")]
+    #[test]
+    fn test_registry_needs_sharing_consent() {
+        let shared = crate::registry::register_person(snapshot(30, 1.75, "Boston"));
+        let denied = crate::registry::register_person(snapshot(30, 1.75, "Boston"));
+        let flags = |sharing| ffi::ConsentFlags {
+            analysis: true,
+            exports: true,
+            sharing,
+        };
+        crate::consent::set_consent(shared, flags(true)).unwrap();
+        crate::consent::set_consent(denied, flags(false)).unwrap();
+
+        let response = route("GET", "/registry", b"");
+        assert_eq!(response.status, 200);
+        let ids: Vec<u64> = response
+            .body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["id"].as_u64().unwrap())
+            .collect();
+        assert!(ids.contains(&shared));
+        assert!(!ids.contains(&denied));
+
+        for id in [shared, denied] {
+            assert!(crate::registry::unregister_person(id));
+        }
+    }
+
+    #[test]
+    fn test_server_lifecycle() {
+        assert!(start("not an address").is_err());
+        let addr = start("127.0.0.1:0").unwrap();
+        assert!(matches!(
+            start("127.0.0.1:0"),
+            Err(Error::AlreadyRunning(_))
+        ));
+
+        let body = person_json(30, "Boston").to_string();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST /validate HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(r#"{"issues":[],"valid":true}"#));
+
+        stop_http_server();
+        assert!(HTTP.read().is_none());
+        stop_http_server();
+    }
+}
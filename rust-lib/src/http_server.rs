@@ -0,0 +1,395 @@
+//! Minimal HTTP/1.1 server for the embedded endpoints
+//!
+//! The JSON API (http.rs) and the metrics endpoint (metrics.rs) each bind
+//! a `Server` with their own `Service`. The server speaks only what those
+//! need: one request per connection, bodies with a Content-Length, on
+//! std::net. There is no TLS or authentication; it is meant for a trusted
+//! network.
+//!
+//! A fixed pool of `WORKERS` threads serves the connections, which wait in
+//! a queue of `MAX_QUEUED` while all are busy. A connection beyond those is
+//! answered 503 by the accept thread and closed, so a flood of clients
+//! costs neither threads nor memory. A request must arrive whole within
+//! `REQUEST_DEADLINE`, however slowly its bytes trickle in.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TrySendError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+/// A client that stops sending for this long is dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// The time a client has to send its whole request
+const REQUEST_DEADLINE: Duration = Duration::from_secs(15);
+/// A refused client that does not take its refusal for this long is
+/// dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Threads serving connections, per server
+const WORKERS: usize = 8;
+/// Connections waiting for a worker, per server
+const MAX_QUEUED: usize = 64;
+
+pub(crate) struct Request {
+    pub(crate) method: String,
+    /// With any query string
+    pub(crate) path: String,
+    // The metrics endpoint only serves GET
+    #[cfg_attr(not(feature = "http"), allow(dead_code))]
+    pub(crate) body: Vec<u8>,
+}
+
+pub(crate) struct Reply {
+    pub(crate) status: u16,
+    pub(crate) content_type: &'static str,
+    pub(crate) body: Vec<u8>,
+}
+
+/// What a server answers
+pub(crate) trait Service: Send + Sync + 'static {
+    fn respond(&self, request: &Request) -> Reply;
+
+    /// The answer to a request that could not be read
+    fn reject(&self, status: u16, message: &str) -> Reply;
+
+    /// The answer refusing a new connection before its request is read,
+    /// while the service is at capacity
+    fn shed(&self) -> Option<Reply> {
+        None
+    }
+}
+
+/// A listening server; dropping it without `stop` leaves it running
+pub(crate) struct Server {
+    addr: SocketAddr,
+    stopping: Arc<AtomicBool>,
+    acceptor: JoinHandle<()>,
+}
+
+impl Server {
+    /// Bind `addr` ("127.0.0.1:9100", say) and start accepting; `what`
+    /// names the server in errors
+    pub(crate) fn bind(addr: &str, what: &str, service: impl Service) -> Result<Self, Error> {
+        let addr: SocketAddr = addr
+            .parse()
+            .map_err(|e| Error::InvalidArgument(format!("{what} address {addr:?}: {e}")))?;
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let spawn_failed = |e| Error::Io(format!("{what} server thread: {e}"));
+
+        let service = Arc::new(service);
+        // Workers end when the acceptor drops the sender
+        let (queue, queued) = mpsc::sync_channel::<TcpStream>(MAX_QUEUED);
+        let queued = Arc::new(Mutex::new(queued));
+        for _ in 0..WORKERS {
+            let (service, queued) = (Arc::clone(&service), Arc::clone(&queued));
+            thread::Builder::new()
+                .name(format!("rust-lib-{what}-worker"))
+                .spawn(move || work(&queued, &*service))
+                .map_err(spawn_failed)?;
+        }
+
+        let stopping = Arc::new(AtomicBool::new(false));
+        let acceptor = {
+            let stopping = Arc::clone(&stopping);
+            thread::Builder::new()
+                .name(format!("rust-lib-{what}-acceptor"))
+                .spawn(move || {
+                    for stream in listener.incoming() {
+                        if stopping.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        // A failed accept concerns that client only
+                        let Ok(stream) = stream else { continue };
+                        let refusal = match service.shed() {
+                            Some(reply) => (stream, reply),
+                            None => match queue.try_send(stream) {
+                                Err(TrySendError::Full(stream)) => {
+                                    (stream, service.reject(503, "too many connections"))
+                                }
+                                _ => continue,
+                            },
+                        };
+                        let (stream, reply) = refusal;
+                        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                        write_reply(stream, &reply);
+                    }
+                })
+                .map_err(spawn_failed)?
+        };
+        Ok(Server {
+            addr,
+            stopping,
+            acceptor,
+        })
+    }
+
+    /// The bound address, with the actual port when bound to port 0
+    pub(crate) fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stop accepting and join the acceptor thread; requests in progress
+    /// and queued still get their response
+    pub(crate) fn stop(self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        // Wake the blocking accept; a wildcard address is reached locally
+        let mut wake = self.addr;
+        if wake.ip().is_unspecified() {
+            wake.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        }
+        let _ = TcpStream::connect(wake);
+        let _ = self.acceptor.join();
+    }
+}
+
+/// Serve queued connections until the queue closes
+fn work(queued: &Mutex<Receiver<TcpStream>>, service: &dyn Service) {
+    loop {
+        // Held only while waiting, so one idle worker waits at a time
+        let next = queued.lock().unwrap_or_else(PoisonError::into_inner).recv();
+        let Ok(stream) = next else { return };
+        // A service that panics fails its request, not the worker; the
+        // connection closes without a response
+        let _ = catch_unwind(AssertUnwindSafe(|| serve(stream, service)));
+    }
+}
+
+fn serve(stream: TcpStream, service: &dyn Service) {
+    let reply = match read_request(Deadline::new(&stream)) {
+        Ok(request) => service.respond(&request),
+        Err((status, message)) => service.reject(status, message),
+    };
+    write_reply(stream, &reply);
+}
+
+fn write_reply(mut stream: TcpStream, reply: &Reply) {
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n",
+        reply.status,
+        reason(reply.status),
+        reply.content_type,
+        reply.body.len()
+    );
+    // The client may already be gone; nothing to do about it then
+    let _ = stream
+        .write_all(head.as_bytes())
+        .and_then(|()| stream.write_all(&reply.body));
+}
+
+/// A connection's reads, failing once `REQUEST_DEADLINE` has passed since
+/// it was served
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    until: Instant,
+}
+
+impl<'a> Deadline<'a> {
+    fn new(stream: &'a TcpStream) -> Self {
+        Deadline {
+            stream,
+            until: Instant::now() + REQUEST_DEADLINE,
+        }
+    }
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left.min(READ_TIMEOUT)))?;
+        self.stream.read(buf)
+    }
+}
+
+/// One request, or the status and message to reject it with
+fn read_request(stream: impl Read) -> Result<Request, (u16, &'static str)> {
+    let bad_request = |_| (400, "malformed request");
+    let mut reader = BufReader::new(stream.take((MAX_HEADER_BYTES + MAX_BODY_BYTES) as u64));
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(bad_request)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path), Some(_version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err((400, "malformed request line"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut header_bytes = line.len();
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        header_bytes += reader.read_line(&mut line).map_err(bad_request)?;
+        if header_bytes > MAX_HEADER_BYTES {
+            return Err((431, "headers too large"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| (400, "invalid Content-Length"))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return Err((413, "body too large"));
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(bad_request)?;
+    Ok(Request { method, path, body })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_request() {
+        let request =
+            b"POST /validate?pretty HTTP/1.1\r\nHost: x\r\ncontent-length: 2\r\n\r\n{}trailing";
+        let request = read_request(&request[..]).ok().unwrap();
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "/validate?pretty")
+        );
+        assert_eq!(request.body, b"{}");
+
+        let too_large = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_BYTES + 1
+        );
+        assert_eq!(read_request(too_large.as_bytes()).err().unwrap().0, 413);
+        assert_eq!(read_request(&b"nonsense\r\n\r\n"[..]).err().unwrap().0, 400);
+    }
+
+    /// Answers 200, and panics for /panic
+    struct Echo;
+
+    impl Service for Echo {
+        fn respond(&self, request: &Request) -> Reply {
+            assert_ne!(request.path, "/panic", "the service failed");
+            self.reject(200, "ok")
+        }
+
+        fn reject(&self, status: u16, message: &str) -> Reply {
+            Reply {
+                status,
+                content_type: "text/plain",
+                body: message.as_bytes().to_vec(),
+            }
+        }
+    }
+
+    /// At capacity throughout
+    struct Busy;
+
+    impl Service for Busy {
+        fn respond(&self, _: &Request) -> Reply {
+            unreachable!("a busy service reads no request")
+        }
+
+        fn reject(&self, status: u16, message: &str) -> Reply {
+            Echo.reject(status, message)
+        }
+
+        fn shed(&self) -> Option<Reply> {
+            Some(self.reject(503, "busy"))
+        }
+    }
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        response
+    }
+
+    #[test]
+    fn test_panic_keeps_workers() {
+        let server = Server::bind("127.0.0.1:0", "test", Echo).unwrap();
+        // More panics than workers
+        for _ in 0..=WORKERS {
+            assert_eq!(get(server.addr(), "/panic"), "");
+        }
+        assert!(get(server.addr(), "/").starts_with("HTTP/1.1 200 OK"));
+        server.stop();
+    }
+
+    /// Whether `client` has been answered 503, without waiting
+    fn answered_503(mut client: &TcpStream) -> bool {
+        client.set_nonblocking(true).unwrap();
+        let mut head = [0; 12];
+        client.read_exact(&mut head).is_ok() && head == *b"HTTP/1.1 503"
+    }
+
+    #[test]
+    fn test_connection_cap() {
+        let server = Server::bind("127.0.0.1:0", "test", Echo).unwrap();
+        // Idle clients hold every worker and queue slot until their read
+        // times out, so those beyond are refused at once
+        let clients: Vec<TcpStream> = (0..WORKERS + MAX_QUEUED + 8)
+            .map(|_| TcpStream::connect(server.addr()).unwrap())
+            .collect();
+        thread::sleep(Duration::from_millis(300));
+        let refused = clients.iter().filter(|client| answered_503(client)).count();
+        assert!(refused >= 8, "{refused} connections refused");
+        drop(clients);
+        assert!(get(server.addr(), "/").starts_with("HTTP/1.1 200 OK"));
+        server.stop();
+    }
+
+    #[test]
+    fn test_shed_before_reading() {
+        let server = Server::bind("127.0.0.1:0", "test", Busy).unwrap();
+        let response = get(server.addr(), "/");
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable"));
+        assert!(response.ends_with("busy"));
+        server.stop();
+    }
+
+    #[test]
+    fn test_request_deadline() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n").unwrap();
+        // Bytes are waiting, but the deadline has passed
+        let mut deadline = Deadline::new(&stream);
+        deadline.until = Instant::now();
+        let mut byte = [0];
+        assert_eq!(
+            deadline.read(&mut byte).unwrap_err().kind(),
+            io::ErrorKind::TimedOut
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! Producer/consumer ingest queue
+//!
+//! C++ producers push snapshots with `enqueue_person`; a single Rust
+//! consumer thread turns each one into a `PersonInfo` and hands it to the
+//! C++ `ResultSink`. The queue is bounded: when it is full `enqueue_person`
+//! returns false immediately and the producer decides whether to retry,
+//! drop, or slow down.
+
+use std::sync::mpsc::{self, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use crate::error::Error;
+use crate::ffi;
+use crate::state::Global;
+
+// The sink is only ever called from the consumer thread; callbacks.h
+// documents that the wrapped C++ function must tolerate that.
+#[cfg(not(feature = "stub-cpp"))]
+unsafe impl Send for ffi::ResultSink {}
+
+struct Ingest {
+    sender: SyncSender<ffi::PersonSnapshot>,
+    consumer: JoinHandle<()>,
+}
+
+static INGEST: Global<Option<Ingest>> = Global::new(|| None);
+
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn start_ingest(
+    capacity: usize,
+    sink: cxx::UniquePtr<ffi::ResultSink>,
+) -> Result<(), Error> {
+    if sink.is_null() {
+        return Err(Error::InvalidArgument("ingest sink is null".to_string()));
+    }
+    start_with(capacity, move |info| sink.on_person_info(&info))
+}
+
+/// Start the consumer with an arbitrary Rust sink
+fn start_with(
+    capacity: usize,
+    mut sink: impl FnMut(ffi::PersonInfo) + Send + 'static,
+) -> Result<(), Error> {
+    if capacity == 0 {
+        return Err(Error::InvalidArgument(
+            "ingest capacity must be at least 1".to_string(),
+        ));
+    }
+
+    let mut ingest = INGEST.write();
+    if ingest.is_some() {
+        return Err(Error::AlreadyRunning("ingest"));
+    }
+
+    let (sender, receiver) = mpsc::sync_channel::<ffi::PersonSnapshot>(capacity);
+    let consumer = thread::spawn(move || {
+        for snapshot in receiver {
+            sink(crate::compute_person_info(&snapshot));
+        }
+    });
+
+    *ingest = Some(Ingest { sender, consumer });
+    Ok(())
+}
+
+pub(crate) fn enqueue_person(snapshot: ffi::PersonSnapshot) -> bool {
+    match INGEST.read().as_ref() {
+        Some(ingest) => ingest.sender.try_send(snapshot).is_ok(),
+        None => false,
+    }
+}
+
+pub(crate) fn stop_ingest() {
+    let ingest = INGEST.write().take();
+    if let Some(Ingest { sender, consumer }) = ingest {
+        // Dropping the sender ends the consumer loop once the queue drains
+        drop(sender);
+        let _ = consumer.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+    use std::sync::mpsc::channel;
+
+    // The ingest queue is a process-wide singleton, so everything that
+    // starts it lives in this one test.
+    #[test]
+    fn test_ingest_lifecycle() {
+        assert!(!enqueue_person(snapshot(30, 1.75, "Boston")));
+        assert!(start_with(0, |_| {}).is_err());
+
+        let (gate_tx, gate_rx) = channel::<()>();
+        let (out_tx, out_rx) = channel();
+        start_with(1, move |info| {
+            gate_rx.recv().ok();
+            out_tx.send(info).unwrap();
+        })
+        .unwrap();
+        assert_eq!(start_with(1, |_| {}), Err(Error::AlreadyRunning("ingest")));
+
+        // First item is taken by the (blocked) consumer, second fills the
+        // queue, third is rejected
+        assert!(enqueue_person(snapshot(30, 1.75, "Boston")));
+        while !enqueue_person(snapshot(16, 1.60, "Boston")) {
+            thread::yield_now();
+        }
+        assert!(!enqueue_person(snapshot(40, 1.80, "Boston")));
+
+        gate_tx.send(()).unwrap();
+        gate_tx.send(()).unwrap();
+        stop_ingest();
+
+        let results: Vec<_> = out_rx.iter().collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_adult);
+        assert!(!results[1].is_adult);
+        assert!(!enqueue_person(snapshot(30, 1.75, "Boston")));
+    }
+}
@@ -0,0 +1,210 @@
+//! Shared copies of the strings a batch repeats
+//!
+//! A batch job outlives the call that submitted it, so it copies its people
+//! (see jobs.rs). Most of a cohort lives in a handful of cities and has its
+//! email at a handful of domains, and copying each person's own "New York"
+//! would allocate it a million times in a 1M-person run. `intern_people`
+//! copies people into `InternedPerson`s instead, which share one `Arc<str>`
+//! per distinct city and email domain with every other person of every
+//! batch in flight.
+//!
+//! The interner holds the strings it handed out; ones that no batch uses
+//! any more are dropped when the next batch is interned. `interner_stats()`
+//! tells how much was shared.
+
+use std::collections::HashSet;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use crate::ffi;
+use crate::memory::{self, HeapSize};
+use crate::person_like::PersonLike;
+use crate::state::Global;
+
+static STRINGS: Global<HashSet<Arc<str>>> = Global::new(HashSet::new);
+static LOOKUPS: AtomicU64 = AtomicU64::new(0);
+static HITS: AtomicU64 = AtomicU64::new(0);
+
+/// The shared copy of `text`, made if there is none yet
+fn intern(strings: &mut HashSet<Arc<str>>, text: &str) -> Arc<str> {
+    LOOKUPS.fetch_add(1, Ordering::Relaxed);
+    if let Some(shared) = strings.get(text) {
+        HITS.fetch_add(1, Ordering::Relaxed);
+        return Arc::clone(shared);
+    }
+    let shared: Arc<str> = Arc::from(text);
+    strings.insert(Arc::clone(&shared));
+    shared
+}
+
+/// Copy `people` for work that outlives the call, sharing their cities
+/// and email domains
+pub(crate) fn intern_people(people: &[ffi::PersonSnapshot]) -> Vec<InternedPerson> {
+    let mut strings = STRINGS.write();
+    // What only the interner holds belongs to batches that have finished
+    strings.retain(|shared| Arc::strong_count(shared) > 1);
+    people
+        .iter()
+        .map(|person| InternedPerson::new(&mut strings, person))
+        .collect()
+}
+
+/// What the interner holds and how often it could share
+pub(crate) fn interner_stats() -> ffi::InternerStats {
+    let strings = STRINGS.read();
+    ffi::InternerStats {
+        strings: strings.len(),
+        bytes: strings.iter().map(|shared| shared.len()).sum(),
+        lookups: LOOKUPS.load(Ordering::Relaxed),
+        hits: HITS.load(Ordering::Relaxed),
+    }
+}
+
+/// Heap of the shared strings, each with the counts in front of it; the
+/// people referring to them belong to their jobs
+pub(crate) fn heap_bytes() -> usize {
+    let strings = STRINGS.read();
+    memory::table_bytes::<Arc<str>>(strings.capacity())
+        + strings
+            .iter()
+            .map(|shared| 2 * size_of::<usize>() + shared.len())
+            .sum::<usize>()
+}
+
+/// A PersonSnapshot whose city and email domain are shared
+pub(crate) struct InternedPerson {
+    name: Box<str>,
+    age: u32,
+    height_m: f64,
+    /// The email up to the last '@', or all of it without one
+    email_local: Box<str>,
+    email_domain: Option<Arc<str>>,
+    /// `email_local@email_domain`, joined the first time it is read
+    email: OnceLock<Box<str>>,
+    phone: Box<str>,
+    street: Box<str>,
+    city: Arc<str>,
+    postal_code: Box<str>,
+}
+
+impl InternedPerson {
+    fn new(strings: &mut HashSet<Arc<str>>, person: &ffi::PersonSnapshot) -> Self {
+        let (email_local, email_domain) = match person.email.rsplit_once('@') {
+            Some((local, domain)) => (local, Some(intern(strings, domain))),
+            None => (person.email.as_str(), None),
+        };
+        InternedPerson {
+            name: person.name.as_str().into(),
+            age: person.age,
+            height_m: person.height_m,
+            email_local: email_local.into(),
+            email_domain,
+            email: OnceLock::new(),
+            phone: person.phone.as_str().into(),
+            street: person.street.as_str().into(),
+            city: intern(strings, &person.city),
+            postal_code: person.postal_code.as_str().into(),
+        }
+    }
+}
+
+/// The person's own strings; the shared ones count for the interner
+impl HeapSize for InternedPerson {
+    fn heap_bytes(&self) -> usize {
+        let email = self.email.get().map_or(0, HeapSize::heap_bytes);
+        [
+            &self.name,
+            &self.email_local,
+            &self.phone,
+            &self.street,
+            &self.postal_code,
+        ]
+        .iter()
+        .map(|text| text.heap_bytes())
+        .sum::<usize>()
+            + email
+    }
+}
+
+impl PersonLike for InternedPerson {
+    fn age(&self) -> u32 {
+        self.age
+    }
+
+    fn height_m(&self) -> f64 {
+        self.height_m
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    // The analysis never reads the email, so most batches never join it
+    fn email(&self) -> &str {
+        self.email.get_or_init(|| match &self.email_domain {
+            Some(domain) => format!("{}@{domain}", self.email_local).into(),
+            None => self.email_local.clone(),
+        })
+    }
+
+    fn phone(&self) -> &str {
+        &self.phone
+    }
+
+    fn street(&self) -> &str {
+        &self.street
+    }
+
+    fn city(&self) -> &str {
+        &self.city
+    }
+
+    fn postal_code(&self) -> &str {
+        &self.postal_code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+
+    #[test]
+    fn test_interned_people_share_cities_and_domains() {
+        let mut people: Vec<_> = (0..4)
+            .map(|i| snapshot(20 + i, 1.75, "Interned City"))
+            .collect();
+        people[1].email = "first.last@Intern.example".to_string();
+        people[2].email = "no address".to_string();
+        people[3].email = "a@b@intern.example".to_string();
+
+        let before = interner_stats();
+        let interned = intern_people(&people);
+        let after = interner_stats();
+        assert!(after.lookups >= before.lookups + 7);
+        assert!(after.hits >= before.hits + 3);
+
+        assert!(Arc::ptr_eq(&interned[0].city, &interned[3].city));
+        for (person, copy) in people.iter().zip(&interned) {
+            let copy = copy.to_snapshot();
+            assert_eq!(
+                (copy.name, copy.age, copy.email, copy.city),
+                (
+                    person.name.clone(),
+                    person.age,
+                    person.email.clone(),
+                    person.city.clone()
+                )
+            );
+            assert_eq!(copy.postal_code, person.postal_code);
+        }
+        assert_eq!(interned[3].email_domain.as_deref(), Some("intern.example"));
+        assert!(interned[2].email_domain.is_none());
+
+        // Once the batch is gone, the next one drops its strings
+        drop(interned);
+        intern_people(&[]);
+        assert!(!STRINGS.read().contains("Interned City"));
+    }
+}
@@ -0,0 +1,326 @@
+//! JNI entry points for Java and Kotlin hosts
+//!
+//! Built with the `jni` feature. These are the natives of
+//! `com.example.rustlib.RustLib` (rust-lib/java/), through which the
+//! Android app runs the same health engine as the C++ app instead of its
+//! own Kotlin copy. Java passes plain fields and primitive arrays and
+//! RustLib.java builds its PersonSnapshot and HealthAnalysis objects from
+//! them, so only a dozen JNI functions are needed. They are called through
+//! their slots in the JNIEnv function table, whose layout jni.h has kept
+//! since Java 6, rather than through a bindings crate.
+//!
+//! Errors and panics become Java exceptions; a panic never unwinds into
+//! the JVM.
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::error::Error;
+use crate::ffi;
+use crate::templates;
+
+type JObject = *mut c_void;
+type JInt = i32;
+type JSize = i32;
+
+/// `JNIEnv*`: a pointer to the JVM's function table pointer
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub struct JniEnv(*mut *const *const c_void);
+
+// Slots in JNINativeInterface_ (jni.h)
+const FIND_CLASS: usize = 6;
+const THROW_NEW: usize = 14;
+const DELETE_LOCAL_REF: usize = 23;
+const NEW_STRING: usize = 163;
+const GET_STRING_LENGTH: usize = 164;
+const GET_ARRAY_LENGTH: usize = 171;
+const GET_OBJECT_ARRAY_ELEMENT: usize = 173;
+const NEW_DOUBLE_ARRAY: usize = 182;
+const GET_INT_ARRAY_REGION: usize = 203;
+const GET_DOUBLE_ARRAY_REGION: usize = 206;
+const SET_DOUBLE_ARRAY_REGION: usize = 214;
+const GET_STRING_REGION: usize = 220;
+
+/// Values per person in analyzeBatch's result, in HealthAnalysis field
+/// order: bmi, riskScore, cityRiskFactor, extraRiskFactor, riskFactors
+const ANALYSIS_VALUES: usize = 5;
+/// Strings per person in analyzeBatch's `text`: name, email, phone,
+/// street, city, postalCode
+const PERSON_TEXTS: usize = 6;
+
+/// A Java exception to raise: its class and message
+struct Throw(&'static CStr, String);
+
+impl From<Error> for Throw {
+    fn from(error: Error) -> Self {
+        Throw(c"java/lang/IllegalArgumentException", error.to_string())
+    }
+}
+
+impl JniEnv {
+    /// The table entry at `slot`, as the function type `F` it holds
+    unsafe fn function<F: Copy>(&self, slot: usize) -> F {
+        let table = *self.0;
+        std::mem::transmute_copy(&*table.add(slot))
+    }
+
+    unsafe fn throw(&self, Throw(class, message): Throw) {
+        let find_class: extern "system" fn(JniEnv, *const c_char) -> JObject =
+            self.function(FIND_CLASS);
+        let throw_new: extern "system" fn(JniEnv, JObject, *const c_char) -> JInt =
+            self.function(THROW_NEW);
+        let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+        let class = find_class(*self, class.as_ptr());
+        // Without the class, FindClass has already thrown NoClassDefFoundError
+        if !class.is_null() {
+            throw_new(*self, class, message.as_ptr());
+        }
+    }
+
+    unsafe fn delete_local_ref(&self, object: JObject) {
+        let delete: extern "system" fn(JniEnv, JObject) = self.function(DELETE_LOCAL_REF);
+        delete(*self, object)
+    }
+
+    /// A java.lang.String's text; null reads as ""
+    ///
+    /// Copied as UTF-16, since GetStringUTFChars's "modified UTF-8" is not
+    /// valid UTF-8 for characters outside the BMP.
+    unsafe fn string(&self, string: JObject) -> String {
+        if string.is_null() {
+            return String::new();
+        }
+        let length: extern "system" fn(JniEnv, JObject) -> JSize = self.function(GET_STRING_LENGTH);
+        let region: extern "system" fn(JniEnv, JObject, JSize, JSize, *mut u16) =
+            self.function(GET_STRING_REGION);
+        let length = length(*self, string);
+        let mut units = vec![0u16; length.max(0) as usize];
+        region(*self, string, 0, length, units.as_mut_ptr());
+        String::from_utf16_lossy(&units)
+    }
+
+    unsafe fn new_string(&self, text: &str) -> JObject {
+        let new_string: extern "system" fn(JniEnv, *const u16, JSize) -> JObject =
+            self.function(NEW_STRING);
+        let units: Vec<u16> = text.encode_utf16().collect();
+        new_string(*self, units.as_ptr(), units.len() as JSize)
+    }
+
+    unsafe fn array_len(&self, array: JObject, what: &str) -> Result<usize, Throw> {
+        if array.is_null() {
+            return Err(Throw(
+                c"java/lang/NullPointerException",
+                format!("{what} is null"),
+            ));
+        }
+        let length: extern "system" fn(JniEnv, JObject) -> JSize = self.function(GET_ARRAY_LENGTH);
+        Ok(length(*self, array) as usize)
+    }
+
+    unsafe fn ints(&self, array: JObject, what: &str) -> Result<Vec<JInt>, Throw> {
+        let region: extern "system" fn(JniEnv, JObject, JSize, JSize, *mut JInt) =
+            self.function(GET_INT_ARRAY_REGION);
+        let mut values = vec![0; self.array_len(array, what)?];
+        region(*self, array, 0, values.len() as JSize, values.as_mut_ptr());
+        Ok(values)
+    }
+
+    unsafe fn doubles(&self, array: JObject, what: &str) -> Result<Vec<f64>, Throw> {
+        let region: extern "system" fn(JniEnv, JObject, JSize, JSize, *mut f64) =
+            self.function(GET_DOUBLE_ARRAY_REGION);
+        let mut values = vec![0.0; self.array_len(array, what)?];
+        region(*self, array, 0, values.len() as JSize, values.as_mut_ptr());
+        Ok(values)
+    }
+
+    unsafe fn new_doubles(&self, values: &[f64]) -> JObject {
+        let new_array: extern "system" fn(JniEnv, JSize) -> JObject =
+            self.function(NEW_DOUBLE_ARRAY);
+        let set_region: extern "system" fn(JniEnv, JObject, JSize, JSize, *const f64) =
+            self.function(SET_DOUBLE_ARRAY_REGION);
+        let array = new_array(*self, values.len() as JSize);
+        // Null: OutOfMemoryError is pending
+        if !array.is_null() {
+            set_region(*self, array, 0, values.len() as JSize, values.as_ptr());
+        }
+        array
+    }
+
+    /// Every element of a String[]
+    unsafe fn strings(&self, array: JObject, what: &str) -> Result<Vec<String>, Throw> {
+        let element: extern "system" fn(JniEnv, JObject, JSize) -> JObject =
+            self.function(GET_OBJECT_ARRAY_ELEMENT);
+        let length = self.array_len(array, what)?;
+        let mut strings = Vec::with_capacity(length);
+        for index in 0..length {
+            let string = element(*self, array, index as JSize);
+            strings.push(self.string(string));
+            // Local references are limited; a large batch would exhaust them
+            self.delete_local_ref(string);
+        }
+        Ok(strings)
+    }
+
+    /// Runs a native method's body, raising its error or panic in Java
+    ///
+    /// Java ignores the return value while an exception is pending, so
+    /// failures return null.
+    unsafe fn call(&self, body: impl FnOnce() -> Result<JObject, Throw>) -> JObject {
+        match catch_unwind(AssertUnwindSafe(body)) {
+            Ok(Ok(object)) => object,
+            Ok(Err(throw)) => {
+                self.throw(throw);
+                std::ptr::null_mut()
+            }
+            Err(_) => {
+                let message = "panic in rust_lib".to_string();
+                self.throw(Throw(c"java/lang/RuntimeException", message));
+                std::ptr::null_mut()
+            }
+        }
+    }
+}
+
+/// Parallel arrays from RustLib.analyzeBatch, as snapshots
+fn snapshots(text: &[String], ages: &[JInt], heights_m: &[f64]) -> Vec<ffi::PersonSnapshot> {
+    text.chunks_exact(PERSON_TEXTS)
+        .zip(ages.iter().zip(heights_m))
+        .map(|(text, (&age, &height_m))| ffi::PersonSnapshot {
+            name: text[0].clone(),
+            age: age.max(0) as u32,
+            height_m,
+            email: text[1].clone(),
+            phone: text[2].clone(),
+            street: text[3].clone(),
+            city: text[4].clone(),
+            postal_code: text[5].clone(),
+        })
+        .collect()
+}
+
+fn check_length(what: &'static str, expected: usize, actual: usize) -> Result<(), Error> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(Error::LengthMismatch {
+            what,
+            expected,
+            actual,
+        })
+    }
+}
+
+/// `static native double calculateBmi(double weightKg, double heightM)`
+#[no_mangle]
+pub extern "system" fn Java_com_example_rustlib_RustLib_calculateBmi(
+    _env: JniEnv,
+    _class: JObject,
+    weight_kg: f64,
+    height_m: f64,
+) -> f64 {
+    crate::calculate_bmi(weight_kg, height_m)
+}
+
+/// `static native double[] analyzeBatch(String[] text, int[] ages,
+/// double[] heightsM, double[] weightsKg)`
+///
+/// `text` holds PERSON_TEXTS strings per person; the result holds
+/// ANALYSIS_VALUES doubles per person.
+///
+/// # Safety
+/// Called by the JVM with a valid `env` and arguments of these types.
+#[no_mangle]
+pub unsafe extern "system" fn Java_com_example_rustlib_RustLib_analyzeBatch(
+    env: JniEnv,
+    _class: JObject,
+    text: JObject,
+    ages: JObject,
+    heights_m: JObject,
+    weights_kg: JObject,
+) -> JObject {
+    env.call(|| {
+        let ages = env.ints(ages, "ages")?;
+        let heights_m = env.doubles(heights_m, "heightsM")?;
+        let weights_kg = env.doubles(weights_kg, "weightsKg")?;
+        let text = env.strings(text, "text")?;
+        check_length("heightsM", ages.len(), heights_m.len())?;
+        check_length("text", ages.len() * PERSON_TEXTS, text.len())?;
+
+        let people = snapshots(&text, &ages, &heights_m);
+        let analyses = crate::analyze_health_batch(&people, &weights_kg, 0)?;
+        let mut values = Vec::with_capacity(analyses.len() * ANALYSIS_VALUES);
+        for analysis in &analyses {
+            values.extend([
+                analysis.bmi,
+                analysis.risk_score,
+                analysis.city_risk_factor,
+                analysis.extra_risk_factor,
+                f64::from(analysis.risk_factors),
+            ]);
+        }
+        Ok(env.new_doubles(&values))
+    })
+}
+
+/// `static native String recommendation(double riskScore, int riskFactors,
+/// String locale)`
+///
+/// # Safety
+/// Called by the JVM with a valid `env` and arguments of these types.
+#[no_mangle]
+pub unsafe extern "system" fn Java_com_example_rustlib_RustLib_recommendation(
+    env: JniEnv,
+    _class: JObject,
+    risk_score: f64,
+    risk_factors: JInt,
+    locale: JObject,
+) -> JObject {
+    env.call(|| {
+        let locale = env.string(locale);
+        let risk_factors = risk_factors.max(0) as u32;
+        let text = templates::recommendation(risk_score, risk_factors, &locale);
+        Ok(env.new_string(&text))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshots_read_person_texts_per_person() {
+        let text: Vec<String> = [
+            "Alice",
+            "a@example.com",
+            "555-0100",
+            "1 Main St",
+            "Boston",
+            "02101",
+        ]
+        .iter()
+        .chain(&["Bob", "", "", "", "Los Angeles", ""])
+        .map(|text| text.to_string())
+        .collect();
+        let people = snapshots(&text, &[30, -1], &[1.75, 1.8]);
+        assert_eq!(people.len(), 2);
+        assert_eq!(people[0].postal_code, "02101");
+        assert_eq!(people[1].name, "Bob");
+        assert_eq!(people[1].city, "Los Angeles");
+        assert_eq!(people[1].age, 0);
+        assert_eq!(people[1].height_m, 1.8);
+    }
+
+    #[test]
+    fn test_check_length() {
+        assert!(check_length("text", 12, 12).is_ok());
+        let error = check_length("heightsM", 2, 1).unwrap_err();
+        assert!(matches!(
+            error,
+            Error::LengthMismatch {
+                what: "heightsM",
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,408 @@
+//! Asynchronous analysis and export jobs
+//!
+//! Submissions run on the worker pool in `scheduler`, interactive jobs ahead
+//! of batch jobs. C++ polls with `job_status` and collects the output once
+//! with `take_job_results` (analyses) or `take_export_result` (exports).
+//! Passing a cancel token lets C++ stop a job it no longer cares about,
+//! so the thread exits instead of running to completion unobserved.
+//!
+//! Jobs submitted with a `JobCallback` skip the polling step: their result
+//! is handed to the callback on a single dedicated callback thread, so a
+//! host event loop only ever has to marshal from one known thread.
+//!
+//! A job that panics fails with `Error::Panicked` and its worker goes on
+//! with the next job.
+
+use std::collections::HashMap;
+use std::any::Any;
+use std::mem::size_of;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use crate::batch::{check_weights, run_batch, BatchControl};
+use crate::cancel::CancelFlag;
+use crate::error::Error;
+use crate::ffi;
+use crate::intern;
+use crate::leak_check::{self, Handle};
+use crate::memory::{self, HeapSize};
+use crate::person_like::PersonLike;
+use crate::scheduler;
+use crate::state::Global;
+
+/// What a finished job produced
+pub(crate) enum JobOutput {
+    Analyses(Vec<ffi::HealthAnalysis>),
+    /// Number of rows written by an export
+    Exported(u64),
+}
+
+pub(crate) type JobResult = Result<JobOutput, Error>;
+
+enum JobState {
+    Queued,
+    Running,
+    Finished(JobResult),
+}
+
+static NEXT_JOB: AtomicU64 = AtomicU64::new(1);
+
+static JOBS: Global<HashMap<u64, JobState>> = Global::new(HashMap::new);
+
+/// Heap of the inputs that queued and running jobs copied
+static INPUT_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// A job's share of INPUT_BYTES, given back when the job drops its input
+struct InputBytes(usize);
+
+impl InputBytes {
+    fn new(bytes: usize) -> Self {
+        INPUT_BYTES.fetch_add(bytes, Ordering::Relaxed);
+        InputBytes(bytes)
+    }
+}
+
+impl Drop for InputBytes {
+    fn drop(&mut self) {
+        INPUT_BYTES.fetch_sub(self.0, Ordering::Relaxed);
+    }
+}
+
+/// Name of the thread that runs every completion callback
+pub(crate) const CALLBACK_THREAD_NAME: &str = "rust-lib-callbacks";
+
+type Completion = Box<dyn FnOnce() + Send>;
+
+static CALLBACK_THREAD: Global<Option<Sender<Completion>>> = Global::new(|| None);
+
+// Completion callbacks run on the callback thread rather than the thread
+// that created them; callbacks.h documents the thread-safety requirement.
+#[cfg(not(feature = "stub-cpp"))]
+unsafe impl Send for ffi::JobCallback {}
+
+/// Sender for the callback thread, starting the thread on first use
+fn callback_sender() -> Sender<Completion> {
+    if let Some(sender) = CALLBACK_THREAD.read().as_ref() {
+        return sender.clone();
+    }
+    CALLBACK_THREAD
+        .write()
+        .get_or_insert_with(spawn_callback_thread)
+        .clone()
+}
+
+fn spawn_callback_thread() -> Sender<Completion> {
+    let (sender, receiver) = mpsc::channel::<Completion>();
+    thread::Builder::new()
+        .name(CALLBACK_THREAD_NAME.to_string())
+        .spawn(move || {
+            for completion in receiver {
+                // A panicking callback must not stop the later ones
+                let _ = catch_unwind(AssertUnwindSafe(completion));
+            }
+        })
+        .expect("failed to spawn callback thread");
+    sender
+}
+
+fn status_of(result: &JobResult) -> ffi::JobStatus {
+    match result {
+        Ok(_) => ffi::JobStatus::Completed,
+        Err(Error::Cancelled) => ffi::JobStatus::Cancelled,
+        Err(_) => ffi::JobStatus::Failed,
+    }
+}
+
+/// Queue `work` on the worker pool and return its job id
+///
+/// Without `on_done` the result is stored for `take_job_results`. With it,
+/// the job is forgotten as soon as it finishes and `on_done` receives the
+/// result on the callback thread.
+pub(crate) fn spawn_job(
+    priority: ffi::JobPriority,
+    work: impl FnOnce() -> JobResult + Send + 'static,
+    on_done: Option<Box<dyn FnOnce(u64, JobResult) + Send>>,
+) -> u64 {
+    let id = NEXT_JOB.fetch_add(1, Ordering::Relaxed);
+    JOBS.write().insert(id, JobState::Queued);
+    if on_done.is_none() {
+        leak_check::issued(Handle::Job, id);
+    }
+
+    scheduler::schedule(
+        priority,
+        Box::new(move || {
+            JOBS.write().insert(id, JobState::Running);
+            let result = catch_unwind(AssertUnwindSafe(work))
+                .unwrap_or_else(|panic| Err(Error::Panicked(panic_message(&*panic))));
+            match on_done {
+                None => {
+                    JOBS.write().insert(id, JobState::Finished(result));
+                }
+                Some(on_done) => {
+                    JOBS.write().remove(&id);
+                    // The callback thread never exits, so sending cannot fail
+                    let _ = callback_sender().send(Box::new(move || on_done(id, result)));
+                }
+            }
+        }),
+    );
+
+    id
+}
+
+/// The message a panic was raised with
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "a panic without a message".to_string())
+}
+
+fn run_job(people: &[impl PersonLike], weights_kg: &[f64], cancel: CancelFlag) -> JobResult {
+    let control = BatchControl::with_cancel(cancel);
+    run_batch(people, weights_kg, &control, |_, _| {})
+        .map(|batch| JobOutput::Analyses(batch.results))
+}
+
+/// Adapt a C++ JobCallback to the Rust completion signature
+#[cfg(not(feature = "stub-cpp"))]
+fn deliver_to(
+    callback: cxx::UniquePtr<ffi::JobCallback>,
+) -> Box<dyn FnOnce(u64, JobResult) + Send> {
+    Box::new(move |id, result| {
+        let status = status_of(&result);
+        let results = match result {
+            Ok(JobOutput::Analyses(results)) => results,
+            _ => Vec::new(),
+        };
+        if let Some(callback) = callback.as_ref() {
+            callback.on_done(id, status, &results);
+        }
+    })
+}
+
+pub(crate) fn submit_analysis(person: &ffi::Person, weight_kg: f64, cancel_token: u64) -> u64 {
+    let snapshot = crate::snapshot_person(person);
+    let cancel = CancelFlag::for_token(cancel_token);
+
+    spawn_job(
+        ffi::JobPriority::Interactive,
+        move || run_job(&[snapshot], &[weight_kg], cancel),
+        None,
+    )
+}
+
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn submit_analysis_with_callback(
+    person: &ffi::Person,
+    weight_kg: f64,
+    cancel_token: u64,
+    on_done: cxx::UniquePtr<ffi::JobCallback>,
+) -> Result<u64, Error> {
+    if on_done.is_null() {
+        return Err(Error::InvalidArgument("job callback is null".to_string()));
+    }
+
+    let snapshot = crate::snapshot_person(person);
+    let cancel = CancelFlag::for_token(cancel_token);
+
+    Ok(spawn_job(
+        ffi::JobPriority::Interactive,
+        move || run_job(&[snapshot], &[weight_kg], cancel),
+        Some(deliver_to(on_done)),
+    ))
+}
+
+pub(crate) fn submit_health_batch(
+    people: &[ffi::PersonSnapshot],
+    weights_kg: &[f64],
+    cancel_token: u64,
+) -> Result<u64, Error> {
+    submit_health_batch_with_priority(people, weights_kg, ffi::JobPriority::Batch, cancel_token)
+}
+
+pub(crate) fn submit_health_batch_with_priority(
+    people: &[ffi::PersonSnapshot],
+    weights_kg: &[f64],
+    priority: ffi::JobPriority,
+    cancel_token: u64,
+) -> Result<u64, Error> {
+    check_weights(people, weights_kg)?;
+
+    let people = intern::intern_people(people);
+    let weights_kg = weights_kg.to_vec();
+    let cancel = CancelFlag::for_token(cancel_token);
+    let input = InputBytes::new(people.heap_bytes() + weights_kg.capacity() * size_of::<f64>());
+
+    Ok(spawn_job(
+        priority,
+        move || {
+            let _input = input;
+            run_job(&people, &weights_kg, cancel)
+        },
+        None,
+    ))
+}
+
+/// Heap of the batch inputs of unfinished jobs and of the results that
+/// wait for take_job_results
+pub(crate) fn heap_bytes() -> usize {
+    let jobs = JOBS.read();
+    let results: usize = jobs
+        .values()
+        .map(|job| match job {
+            JobState::Finished(Ok(JobOutput::Analyses(results))) => results.heap_bytes(),
+            _ => 0,
+        })
+        .sum();
+    memory::table_bytes::<(u64, JobState)>(jobs.capacity())
+        + results
+        + INPUT_BYTES.load(Ordering::Relaxed)
+}
+
+pub(crate) fn job_status(job_id: u64) -> ffi::JobStatus {
+    match JOBS.read().get(&job_id) {
+        None => ffi::JobStatus::Unknown,
+        Some(JobState::Queued) => ffi::JobStatus::Queued,
+        Some(JobState::Running) => ffi::JobStatus::Running,
+        Some(JobState::Finished(result)) => status_of(result),
+    }
+}
+
+pub(crate) fn take_job_results(job_id: u64) -> Result<Vec<ffi::HealthAnalysis>, Error> {
+    take_output(job_id, |output| match output {
+        JobOutput::Analyses(results) => Ok(results),
+        other => Err(other),
+    })
+}
+
+pub(crate) fn take_export_result(job_id: u64) -> Result<u64, Error> {
+    take_output(job_id, |output| match output {
+        JobOutput::Exported(rows) => Ok(rows),
+        other => Err(other),
+    })
+}
+
+/// Remove a finished job and extract its output
+///
+/// `extract` hands the output back when the job produced a different kind;
+/// the job is then put back so the matching `take_*` call still works.
+fn take_output<T>(
+    job_id: u64,
+    extract: impl FnOnce(JobOutput) -> Result<T, JobOutput>,
+) -> Result<T, Error> {
+    let mut jobs = JOBS.write();
+    match jobs.remove(&job_id) {
+        None => Err(Error::UnknownJob(job_id)),
+        Some(state @ (JobState::Queued | JobState::Running)) => {
+            jobs.insert(job_id, state);
+            Err(Error::JobRunning(job_id))
+        }
+        Some(JobState::Finished(Err(error))) => {
+            leak_check::released(Handle::Job, job_id);
+            Err(error)
+        }
+        Some(JobState::Finished(Ok(output))) => match extract(output) {
+            Ok(value) => {
+                leak_check::released(Handle::Job, job_id);
+                Ok(value)
+            }
+            Err(output) => {
+                jobs.insert(job_id, JobState::Finished(Ok(output)));
+                Err(Error::WrongJobKind(job_id))
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+    use std::time::Duration;
+
+    fn wait_for(job_id: u64) -> ffi::JobStatus {
+        loop {
+            match job_status(job_id) {
+                ffi::JobStatus::Queued | ffi::JobStatus::Running => {
+                    thread::sleep(Duration::from_millis(1))
+                }
+                status => return status,
+            }
+        }
+    }
+
+    #[test]
+    fn test_batch_job_completes() {
+        let people = vec![snapshot(30, 1.75, "Boston"), snapshot(40, 1.80, "New York")];
+        let job = submit_health_batch(&people, &[70.0, 80.0], 0).unwrap();
+
+        assert!(wait_for(job) == ffi::JobStatus::Completed);
+        assert_eq!(take_job_results(job).unwrap().len(), 2);
+        assert!(job_status(job) == ffi::JobStatus::Unknown);
+    }
+
+    #[test]
+    fn test_cancelled_job_reports_cancelled() {
+        let people = vec![snapshot(30, 1.75, "Boston")];
+        let token = crate::cancel::create_cancel_token();
+        crate::cancel::cancel(token);
+
+        let job = submit_health_batch(&people, &[70.0], token).unwrap();
+
+        assert!(wait_for(job) == ffi::JobStatus::Cancelled);
+        assert_eq!(take_job_results(job).unwrap_err(), Error::Cancelled);
+        crate::cancel::release_cancel_token(token);
+    }
+
+    #[test]
+    fn test_callback_runs_on_callback_thread() {
+        let (tx, rx) = mpsc::channel();
+        let job = spawn_job(
+            ffi::JobPriority::Interactive,
+            || Ok(JobOutput::Analyses(Vec::new())),
+            Some(Box::new(move |id, result: JobResult| {
+                let thread_name = thread::current().name().map(str::to_string);
+                tx.send((id, result.is_ok(), thread_name)).unwrap();
+            })),
+        );
+
+        let (id, ok, thread_name) = rx.recv().unwrap();
+        assert_eq!(id, job);
+        assert!(ok);
+        assert_eq!(thread_name.as_deref(), Some(CALLBACK_THREAD_NAME));
+        assert!(job_status(job) == ffi::JobStatus::Unknown);
+    }
+
+    #[test]
+    fn test_panicking_job_fails() {
+        let job = spawn_job(
+            ffi::JobPriority::Batch,
+            || panic!("analysis failed"),
+            None,
+        );
+        assert!(wait_for(job) == ffi::JobStatus::Failed);
+        assert_eq!(
+            take_job_results(job).unwrap_err(),
+            Error::Panicked("analysis failed".to_string())
+        );
+
+        let people = vec![snapshot(30, 1.75, "Boston")];
+        let job = submit_health_batch(&people, &[70.0], 0).unwrap();
+        assert!(wait_for(job) == ffi::JobStatus::Completed);
+        assert_eq!(take_job_results(job).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_job() {
+        assert!(job_status(u64::MAX) == ffi::JobStatus::Unknown);
+        assert_eq!(
+            take_job_results(u64::MAX).unwrap_err(),
+            Error::UnknownJob(u64::MAX)
+        );
+    }
+}
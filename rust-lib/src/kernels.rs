@@ -0,0 +1,418 @@
+//! The arithmetic of the health model, a chunk of people at a time
+//!
+//! BMI and the age and BMI risk factors depend on three numbers per person
+//! and nothing else, so batches compute them for `CHUNK` people at once
+//! from plain arrays. With the `simd` feature that loop uses the vector
+//! unit every x86-64 (SSE2) and AArch64 (NEON) CPU has, two people per
+//! instruction; elsewhere, and without the feature, it is the scalar
+//! `body_risk` of the single-person path. Both give bit-identical results:
+//! the same IEEE operations in the same order, and the same treatment of
+//! NaN and a height of 0 or less.
+//!
+//! The city factor, the host's hook and the recommendation read strings
+//! and stay per person (see engine.rs).
+
+use std::mem::size_of;
+
+use crate::config::RiskModelConfig;
+use crate::ffi;
+use crate::person_like::PersonLike;
+
+/// People per kernel call in a batch; small enough to stay in L1
+pub(crate) const CHUNK: usize = 256;
+
+/// BMI and the factors that follow from age and BMI alone
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct BodyRisk {
+    pub(crate) bmi: f64,
+    pub(crate) age_risk: f64,
+    pub(crate) bmi_risk: f64,
+}
+
+/// BodyRisk of one person; a height of 0 or less gives BMI 0
+pub(crate) fn body_risk(
+    age: u32,
+    height_m: f64,
+    weight_kg: f64,
+    config: &RiskModelConfig,
+) -> BodyRisk {
+    let bmi = if height_m > 0.0 {
+        weight_kg / (height_m * height_m)
+    } else {
+        0.0
+    };
+    let age_risk = if !(config.adult_age..=config.senior_age).contains(&age) {
+        config.age_risk_factor
+    } else {
+        1.0
+    };
+    let bmi_risk = if !(config.underweight_bmi..=config.overweight_bmi).contains(&bmi) {
+        config.bmi_risk_factor
+    } else {
+        1.0
+    };
+    BodyRisk {
+        bmi,
+        age_risk,
+        bmi_risk,
+    }
+}
+
+/// The inputs of the kernel for up to CHUNK people, and its results
+#[derive(Default)]
+pub(crate) struct Chunk {
+    ages: Vec<f64>,
+    heights_m: Vec<f64>,
+    weights_kg: Vec<f64>,
+    risks: Vec<BodyRisk>,
+}
+
+impl Chunk {
+    /// A chunk with room for `people`, up to CHUNK
+    pub(crate) fn with_capacity(people: usize) -> Self {
+        let people = people.min(CHUNK);
+        Chunk {
+            ages: Vec::with_capacity(people),
+            heights_m: Vec::with_capacity(people),
+            weights_kg: Vec::with_capacity(people),
+            risks: Vec::with_capacity(people),
+        }
+    }
+
+    /// Compute the BodyRisks of the first CHUNK of `people`, with
+    /// `weights` in `options.units`
+    pub(crate) fn fill(
+        &mut self,
+        people: &[impl PersonLike],
+        weights: &[f64],
+        options: &ffi::AnalysisOptions,
+        config: &RiskModelConfig,
+    ) {
+        let count = people.len().min(CHUNK);
+        self.ages.clear();
+        self.heights_m.clear();
+        self.weights_kg.clear();
+        self.ages.reserve(count);
+        self.heights_m.reserve(count);
+        self.weights_kg.reserve(count);
+        for (person, &weight) in people.iter().zip(weights).take(CHUNK) {
+            self.ages.push(f64::from(person.age()));
+            self.heights_m.push(person.height_m());
+            self.weights_kg.push(options.weight_kg(weight));
+        }
+        self.risks.clear();
+        self.risks.reserve(count);
+        body_risks(
+            &self.ages,
+            &self.heights_m,
+            &self.weights_kg,
+            config,
+            &mut self.risks,
+        );
+    }
+
+    /// The BodyRisk of the `index`th person of the chunk
+    pub(crate) fn get(&self, index: usize) -> BodyRisk {
+        self.risks[index]
+    }
+
+    pub(crate) fn heap_bytes(&self) -> usize {
+        (self.ages.capacity() + self.heights_m.capacity() + self.weights_kg.capacity())
+            * size_of::<f64>()
+            + self.risks.capacity() * size_of::<BodyRisk>()
+    }
+}
+
+/// body_risk for each person, appended to `out`
+///
+/// Ages come as f64, which holds every u32 exactly, so that they compare in
+/// the same registers as BMIs.
+pub(crate) fn body_risks(
+    ages: &[f64],
+    heights_m: &[f64],
+    weights_kg: &[f64],
+    config: &RiskModelConfig,
+    out: &mut Vec<BodyRisk>,
+) {
+    debug_assert!(ages.len() == heights_m.len() && ages.len() == weights_kg.len());
+    out.reserve(ages.len());
+    #[cfg(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64")))]
+    let done = vector::body_risks(ages, heights_m, weights_kg, config, out);
+    #[cfg(not(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+    let done = 0;
+    // What is left over a whole number of vectors, or everything
+    for i in done..ages.len() {
+        out.push(body_risk(
+            ages[i] as u32,
+            heights_m[i],
+            weights_kg[i],
+            config,
+        ));
+    }
+}
+
+/// Two people per instruction: SSE2 on x86-64, NEON on AArch64, both part
+/// of the baseline of their architecture
+#[cfg(all(feature = "simd", any(target_arch = "x86_64", target_arch = "aarch64")))]
+mod vector {
+    use super::BodyRisk;
+    use crate::config::RiskModelConfig;
+
+    #[cfg(target_arch = "aarch64")]
+    use std::arch::aarch64::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    /// The lane operations the kernel needs, on two f64s
+    #[cfg(target_arch = "x86_64")]
+    mod lanes {
+        use super::*;
+
+        pub(super) type F64x2 = __m128d;
+
+        #[inline(always)]
+        pub(super) unsafe fn load(values: &[f64], at: usize) -> F64x2 {
+            _mm_loadu_pd(values[at..at + 2].as_ptr())
+        }
+
+        #[inline(always)]
+        pub(super) unsafe fn store(value: F64x2) -> [f64; 2] {
+            let mut out = [0.0; 2];
+            _mm_storeu_pd(out.as_mut_ptr(), value);
+            out
+        }
+
+        #[inline(always)]
+        pub(super) unsafe fn splat(value: f64) -> F64x2 {
+            _mm_set1_pd(value)
+        }
+
+        #[inline(always)]
+        pub(super) unsafe fn mul(a: F64x2, b: F64x2) -> F64x2 {
+            _mm_mul_pd(a, b)
+        }
+
+        #[inline(always)]
+        pub(super) unsafe fn div(a: F64x2, b: F64x2) -> F64x2 {
+            _mm_div_pd(a, b)
+        }
+
+        /// All ones where a > b, false for NaN
+        #[inline(always)]
+        pub(super) unsafe fn gt(a: F64x2, b: F64x2) -> F64x2 {
+            _mm_cmpgt_pd(a, b)
+        }
+
+        /// All ones where a <= b, false for NaN
+        #[inline(always)]
+        pub(super) unsafe fn le(a: F64x2, b: F64x2) -> F64x2 {
+            _mm_cmple_pd(a, b)
+        }
+
+        #[inline(always)]
+        pub(super) unsafe fn and(a: F64x2, b: F64x2) -> F64x2 {
+            _mm_and_pd(a, b)
+        }
+
+        /// `a` where `mask` is set, else `b`
+        #[inline(always)]
+        pub(super) unsafe fn select(mask: F64x2, a: F64x2, b: F64x2) -> F64x2 {
+            _mm_or_pd(_mm_and_pd(mask, a), _mm_andnot_pd(mask, b))
+        }
+    }
+
+    /// The lane operations the kernel needs, on two f64s
+    #[cfg(target_arch = "aarch64")]
+    mod lanes {
+        use super::*;
+
+        pub(super) type F64x2 = float64x2_t;
+
+        #[inline(always)]
+        pub(super) unsafe fn load(values: &[f64], at: usize) -> F64x2 {
+            vld1q_f64(values[at..at + 2].as_ptr())
+        }
+
+        #[inline(always)]
+        pub(super) unsafe fn store(value: F64x2) -> [f64; 2] {
+            let mut out = [0.0; 2];
+            vst1q_f64(out.as_mut_ptr(), value);
+            out
+        }
+
+        #[inline(always)]
+        pub(super) unsafe fn splat(value: f64) -> F64x2 {
+            vdupq_n_f64(value)
+        }
+
+        #[inline(always)]
+        pub(super) unsafe fn mul(a: F64x2, b: F64x2) -> F64x2 {
+            vmulq_f64(a, b)
+        }
+
+        #[inline(always)]
+        pub(super) unsafe fn div(a: F64x2, b: F64x2) -> F64x2 {
+            vdivq_f64(a, b)
+        }
+
+        /// All ones where a > b, false for NaN
+        #[inline(always)]
+        pub(super) unsafe fn gt(a: F64x2, b: F64x2) -> F64x2 {
+            vreinterpretq_f64_u64(vcgtq_f64(a, b))
+        }
+
+        /// All ones where a <= b, false for NaN
+        #[inline(always)]
+        pub(super) unsafe fn le(a: F64x2, b: F64x2) -> F64x2 {
+            vreinterpretq_f64_u64(vcleq_f64(a, b))
+        }
+
+        #[inline(always)]
+        pub(super) unsafe fn and(a: F64x2, b: F64x2) -> F64x2 {
+            vreinterpretq_f64_u64(vandq_u64(
+                vreinterpretq_u64_f64(a),
+                vreinterpretq_u64_f64(b),
+            ))
+        }
+
+        /// `a` where `mask` is set, else `b`
+        #[inline(always)]
+        pub(super) unsafe fn select(mask: F64x2, a: F64x2, b: F64x2) -> F64x2 {
+            vbslq_f64(vreinterpretq_u64_f64(mask), a, b)
+        }
+    }
+
+    use lanes::*;
+
+    /// body_risks over the whole vectors of the input; returns how many
+    /// people it did
+    pub(super) fn body_risks(
+        ages: &[f64],
+        heights_m: &[f64],
+        weights_kg: &[f64],
+        config: &RiskModelConfig,
+        out: &mut Vec<BodyRisk>,
+    ) -> usize {
+        let done = ages.len() / 2 * 2;
+        // SAFETY: SSE2 and NEON are always present on their architectures,
+        // and `load` bounds-checks its slice
+        unsafe {
+            let zero = splat(0.0);
+            let one = splat(1.0);
+            let adult_age = splat(f64::from(config.adult_age));
+            let senior_age = splat(f64::from(config.senior_age));
+            let age_factor = splat(config.age_risk_factor);
+            let underweight = splat(config.underweight_bmi);
+            let overweight = splat(config.overweight_bmi);
+            let bmi_factor = splat(config.bmi_risk_factor);
+
+            for at in (0..done).step_by(2) {
+                let age = load(ages, at);
+                let height = load(heights_m, at);
+                let weight = load(weights_kg, at);
+
+                // The quotient of a lane with no height is discarded
+                let bmi = and(gt(height, zero), div(weight, mul(height, height)));
+                let adult = and(le(adult_age, age), le(age, senior_age));
+                let age_risk = select(adult, one, age_factor);
+                let normal = and(le(underweight, bmi), le(bmi, overweight));
+                let bmi_risk = select(normal, one, bmi_factor);
+
+                let [bmi, age_risk, bmi_risk] = [store(bmi), store(age_risk), store(bmi_risk)];
+                for lane in 0..2 {
+                    out.push(BodyRisk {
+                        bmi: bmi[lane],
+                        age_risk: age_risk[lane],
+                        bmi_risk: bmi_risk[lane],
+                    });
+                }
+            }
+        }
+        done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Heights, weights and ages at and around every cutoff, plus the
+    /// values the scalar path special-cases
+    fn edge_cases(config: &RiskModelConfig) -> (Vec<f64>, Vec<f64>, Vec<f64>) {
+        let heights = [1.75, 0.0, -1.2, f64::NAN, f64::INFINITY, 1e-160, 2.10];
+        let weights = [70.0, 80.0, f64::NAN, 0.0, -5.0, f64::INFINITY, 1e300];
+        let ages = [
+            0,
+            config.adult_age - 1,
+            config.adult_age,
+            config.senior_age,
+            config.senior_age + 1,
+            u32::MAX,
+        ];
+        let mut columns = (Vec::new(), Vec::new(), Vec::new());
+        for &height in &heights {
+            for &weight in &weights {
+                for &age in &ages {
+                    columns.0.push(f64::from(age));
+                    columns.1.push(height);
+                    columns.2.push(weight);
+                }
+            }
+        }
+        // BMIs of exactly the cutoffs at a height of 1 m
+        for bmi in [config.underweight_bmi, config.overweight_bmi] {
+            for weight in [
+                bmi,
+                f64::from_bits(bmi.to_bits() - 1),
+                f64::from_bits(bmi.to_bits() + 1),
+            ] {
+                columns.0.push(40.0);
+                columns.1.push(1.0);
+                columns.2.push(weight);
+            }
+        }
+        // An odd count leaves one person for the scalar tail
+        columns.0.push(30.0);
+        columns.1.push(1.80);
+        columns.2.push(75.0);
+        columns
+    }
+
+    #[test]
+    fn test_bulk_matches_single_person() {
+        let config = RiskModelConfig::default();
+        let (ages, heights, weights) = edge_cases(&config);
+        assert_eq!(ages.len() % 2, 1);
+
+        let mut bulk = Vec::new();
+        body_risks(&ages, &heights, &weights, &config, &mut bulk);
+        assert_eq!(bulk.len(), ages.len());
+        for (i, risk) in bulk.iter().enumerate() {
+            let single = body_risk(ages[i] as u32, heights[i], weights[i], &config);
+            // Bit for bit, NaN BMIs included
+            assert_eq!(risk.bmi.to_bits(), single.bmi.to_bits(), "person {i}");
+            assert_eq!(risk.age_risk, single.age_risk, "person {i}");
+            assert_eq!(risk.bmi_risk, single.bmi_risk, "person {i}");
+        }
+    }
+
+    #[test]
+    fn test_chunk_converts_units() {
+        let config = RiskModelConfig::default();
+        let people = vec![
+            crate::batch::tests::snapshot(30, 1.75, "Boston"),
+            crate::batch::tests::snapshot(70, 1.60, "Boston"),
+        ];
+        let options = ffi::AnalysisOptions::builder()
+            .units(ffi::Units::Imperial)
+            .build();
+        let mut chunk = Chunk::default();
+        chunk.fill(&people, &[154.0, 176.0], &options, &config);
+        let senior = chunk.get(1);
+        assert_eq!(
+            senior,
+            body_risk(70, 1.60, options.weight_kg(176.0), &config)
+        );
+        assert_eq!(senior.age_risk, config.age_risk_factor);
+    }
+}
@@ -0,0 +1,111 @@
+//! Leak check for the ids the bridge hands out (`leak-check` feature)
+//!
+//! Every id that C++ has to give back is recorded when issued and forgotten
+//! when it is closed, collected or unregistered: polled jobs, sessions,
+//! registry entries, cancel tokens, event subscriptions, risk observers,
+//! string tables and batch contexts.
+//! `assert_no_leaked_handles` fails, listing what is still open, so a test
+//! suite can call it at the end to catch a missing close or unregister.
+//! Jobs with a completion callback are not tracked; they clean up after
+//! themselves. Without the feature nothing is tracked and the assertion
+//! always passes.
+
+#[cfg(feature = "leak-check")]
+use std::collections::BTreeSet;
+
+use crate::error::Error;
+#[cfg(feature = "leak-check")]
+use crate::state::Global;
+
+/// Kinds of id that must be handed back
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Handle {
+    /// Collected with take_job_results / take_export_result
+    Job,
+    Session,
+    #[cfg(feature = "registry")]
+    RegisteredPerson,
+    CancelToken,
+    Subscription,
+    RiskObserver,
+    StringTable,
+    BatchContext,
+}
+
+#[cfg(feature = "leak-check")]
+static OPEN: Global<BTreeSet<(Handle, u64)>> = Global::new(BTreeSet::new);
+
+#[cfg(feature = "leak-check")]
+pub(crate) fn issued(handle: Handle, id: u64) {
+    OPEN.write().insert((handle, id));
+}
+
+#[cfg(not(feature = "leak-check"))]
+pub(crate) fn issued(_: Handle, _: u64) {}
+
+#[cfg(feature = "leak-check")]
+pub(crate) fn released(handle: Handle, id: u64) {
+    OPEN.write().remove(&(handle, id));
+}
+
+#[cfg(not(feature = "leak-check"))]
+pub(crate) fn released(_: Handle, _: u64) {}
+
+/// Fail with `Error::LeakedHandles` if any tracked id is still open
+#[cfg(feature = "leak-check")]
+pub fn assert_no_leaked_handles() -> Result<(), Error> {
+    let open = OPEN.read();
+    if open.is_empty() {
+        return Ok(());
+    }
+    let name = |handle| match handle {
+        Handle::Job => "job",
+        Handle::Session => "session",
+        #[cfg(feature = "registry")]
+        Handle::RegisteredPerson => "registered person",
+        Handle::CancelToken => "cancel token",
+        Handle::Subscription => "subscription",
+        Handle::RiskObserver => "risk observer",
+        Handle::StringTable => "string table",
+        Handle::BatchContext => "batch context",
+    };
+    Err(Error::LeakedHandles(
+        open.iter()
+            .map(|&(handle, id)| format!("{} {id}", name(handle)))
+            .collect(),
+    ))
+}
+
+#[cfg(not(feature = "leak-check"))]
+pub fn assert_no_leaked_handles() -> Result<(), Error> {
+    Ok(())
+}
+
+#[cfg(all(test, feature = "leak-check"))]
+mod tests {
+    use super::*;
+    use crate::session::{close_session, open_session};
+
+    fn is_open(what: &str) -> bool {
+        match assert_no_leaked_handles() {
+            Ok(()) => false,
+            Err(Error::LeakedHandles(open)) => open.iter().any(|handle| handle == what),
+            Err(other) => panic!("unexpected error {other}"),
+        }
+    }
+
+    // Other tests open handles concurrently, so only this test's own ids
+    // are checked, not that nothing at all is open
+    #[test]
+    fn test_open_handles_are_listed_until_released() {
+        let session = open_session();
+        let token = crate::cancel::create_cancel_token();
+        assert!(is_open(&format!("session {session}")));
+        assert!(is_open(&format!("cancel token {token}")));
+
+        assert!(close_session(session));
+        crate::cancel::release_cancel_token(token);
+        assert!(!is_open(&format!("session {session}")));
+        assert!(!is_open(&format!("cancel token {token}")));
+    }
+}
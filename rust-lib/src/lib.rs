@@ -1,78 +1,150 @@
-#[cxx::bridge]
-mod ffi {
-    // ============================================================================
-    // OPAQUE C++ TYPES - Defined in C++ code (person.h)
-    // Rust can hold references but cannot see inside these types
-    // ============================================================================
-    unsafe extern "C++" {
-        include!("cpp-app/person.h");
-        
-        // Opaque C++ types - these are existing C++ classes
-        // Rust cannot see inside these types, only hold references
-        type Person;
-        type ContactInfo;
-        type Address;
-        
-        // Getter functions to access C++ object data from Rust
-        // These are the bridge between opaque C++ types and Rust
-        fn get_person_age(person: &Person) -> u32;
-        fn get_person_height(person: &Person) -> f64;
-        fn get_person_name(person: &Person) -> &CxxString;
-        fn get_person_contact(person: &Person) -> &ContactInfo;
-        
-        fn get_contact_email(contact: &ContactInfo) -> &CxxString;
-        fn get_contact_phone(contact: &ContactInfo) -> &CxxString;
-        fn get_contact_address(contact: &ContactInfo) -> &Address;
-        
-        fn get_address_street(address: &Address) -> &CxxString;
-        fn get_address_city(address: &Address) -> &CxxString;
-        fn get_address_postal_code(address: &Address) -> &CxxString;
-    }
+// Without the C++ entry points some helpers behind them go unused
+#![cfg_attr(feature = "stub-cpp", allow(dead_code))]
 
-    // ============================================================================
-    // BRIDGE STRUCTS - For data exchange between Rust and C++
-    // These are new structs created specifically for passing results
-    // ============================================================================
-    
-    /// Result struct with computed information from Rust
-    /// This is NOT a C++ type - it's a bridge type for communication
-    struct PersonInfo {
-        is_adult: bool,
-        bmi_category: u8,    // 0=underweight, 1=normal, 2=overweight
-        name_length: usize,
-        city: String,        // Extracted from nested C++ structs
-    }
-    
-    /// Health analysis result - new Rust functionality
-    struct HealthAnalysis {
-        bmi: f64,
-        risk_score: f64,
-        recommendation: String,
-        city_risk_factor: f64,
-    }
+mod address_similarity;
+mod anonymize;
+#[cfg(feature = "host-allocator")]
+mod allocator;
+mod analysis_cache;
+mod audit;
+#[cfg(any(feature = "crypto", feature = "nominatim", feature = "webhook", feature = "sync"))]
+mod base64;
+mod batch;
+mod batch_context;
+#[cfg(feature = "c-abi")]
+mod c_abi;
+mod cancel;
+mod config;
+#[cfg(feature = "watch")]
+mod config_watch;
+#[cfg(feature = "registry")]
+mod consent;
+mod country;
+mod encoding;
+mod engine;
+mod error;
+mod events;
+mod export;
+#[cfg(feature = "crypto")]
+mod export_crypto;
+#[cfg(feature = "parquet")]
+mod export_parquet;
+mod filter;
+mod format;
+mod geocode;
+#[cfg(test)]
+mod golden;
+mod graphemes;
+mod health_ffi;
+#[cfg(feature = "registry")]
+mod households;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(any(feature = "nominatim", feature = "webhook", feature = "sync"))]
+mod http_client;
+#[cfg(any(feature = "http", feature = "metrics"))]
+mod http_server;
+mod ingest;
+mod intern;
+#[cfg(feature = "jni")]
+mod jni;
+mod jobs;
+mod kernels;
+mod leak_check;
+mod memory;
+mod metrics;
+#[cfg(feature = "nominatim")]
+mod nominatim;
+mod normalize;
+mod observers;
+#[cfg(feature = "http")]
+mod openapi;
+mod options;
+mod person_ffi;
+mod person_like;
+#[cfg(test)]
+mod properties;
+mod redaction;
+mod regions;
+#[cfg(feature = "sync")]
+mod remote_sync;
+#[cfg(feature = "registry")]
+mod registry;
+#[cfg(feature = "registry")]
+mod retention;
+#[cfg(feature = "registry")]
+mod revalidation;
+mod risk_hooks;
+mod runtime_options;
+mod scheduler;
+mod service_areas;
+mod service_limits;
+mod session;
+#[cfg(feature = "serde")]
+mod snapshot_serde;
+#[cfg(test)]
+mod soak;
+mod state;
+#[cfg(feature = "stub-cpp")]
+mod stub_cpp;
+mod string_table;
+#[cfg(feature = "registry")]
+mod subject_rights;
+mod templates;
+mod timezone;
+mod transliterate;
+#[rustfmt::skip]
+mod unicode_tables;
+mod validation;
+mod validation_ffi;
+#[cfg(feature = "webhook")]
+mod webhook;
+mod zeroize;
 
-    // ============================================================================
-    // RUST FUNCTIONS - New functionality exposed to C++
-    // These work with opaque C++ types and return bridge structs
-    // ============================================================================
-    extern "Rust" {
-        /// Process a C++ Person object and return computed information
-        /// Demonstrates: Rust receiving opaque C++ type and extracting data via getters
-        fn process_person(person: &Person) -> PersonInfo;
-        
-        /// Perform health analysis on a C++ Person object
-        /// Demonstrates: New Rust functionality working with existing C++ types
-        fn analyze_health(person: &Person, weight_kg: f64) -> HealthAnalysis;
-        
-        /// Simple greeting function
-        fn greet_person(name: &str) -> usize;
-        
-        /// Calculate BMI - pure Rust calculation
-        fn calculate_bmi(weight_kg: f64, height_m: f64) -> f64;
-        
-        /// Validate contact info - demonstrates deep access into nested C++ objects
-        fn validate_contact(contact: &ContactInfo) -> bool;
-    }
+pub use address_similarity::{address_key, address_parts_similarity};
+pub use anonymize::anonymous_id_for;
+pub use batch::{
+    analyze_health_batch, analyze_health_batch_pooled, analyze_health_batch_with_options,
+};
+pub use batch_context::{
+    analyze_health_batch_in_context, analyze_health_batch_pooled_in_context, create_batch_context,
+    release_batch_context,
+};
+pub use country::guess_country;
+pub use engine::{analyze_snapshot, contact_fields_valid, validate_snapshot};
+pub use error::Error;
+#[cfg(feature = "crypto")]
+pub use export_crypto::decrypt_export_field;
+pub use geocode::{geocode, haversine_km, set_geocoder, AddressQuery, Geocoder, OfflineGeocoder};
+pub use leak_check::assert_no_leaked_handles;
+pub use options::AnalysisOptionsBuilder;
+pub use redaction::redact_snapshot;
+pub use regions::{region_counts, region_for_postal};
+pub use rust_lib_formulas::{calculate_bmi, calculate_bmr, calculate_tdee};
+pub use service_areas::point_in_service_area;
+pub use service_limits::{
+    admit_request, clear_service_limits, set_endpoint_limits, set_service_max_in_flight, Admission,
+};
+pub use string_table::{get_string, release_string_table};
+pub use templates::render_recommendation;
+pub use timezone::timezone_at;
+pub use validation::{city_valid, email_valid, phone_valid, postal_valid};
+use encoding::LegacyEncoding;
+use engine::{compute_health, compute_health_cached, compute_health_with, compute_person_info};
+use metrics::FfiCall;
+use person_like::CxxPerson;
+
+// The C++ bridge is split by area: the Person model (person_ffi), health
+// analysis (health_ffi) and validation (validation_ffi). Each generates
+// its own rust-lib/src/<name>.rs.h header. Rust code reaches every bridge
+// type through this one module; it is public for the integration tests,
+// whose own bridge refers to the C++ types.
+pub mod ffi {
+    pub use crate::health_ffi::*;
+    pub use crate::person_ffi::*;
+    pub use crate::validation_ffi::*;
+    #[cfg(feature = "stub-cpp")]
+    pub use crate::stub_cpp::*;
 }
 
 // ============================================================================
@@ -87,43 +159,13 @@ mod ffi {
 /// 2. Use getter functions to extract needed data
 /// 3. Perform Rust logic
 /// 4. Return bridge struct with results
-fn process_person(person: &ffi::Person) -> ffi::PersonInfo {
+///
+/// The getters borrow the C++ strings in place; the one allocation is the
+/// `city` String of the result
+pub fn process_person(person: &ffi::Person) -> ffi::PersonInfo {
+    metrics::ffi_call(FfiCall::ProcessPerson);
     // Extract data from C++ object using getter functions
-    let age = ffi::get_person_age(person);
-    let height = ffi::get_person_height(person);
-    let name = ffi::get_person_name(person);
-    let contact = ffi::get_person_contact(person);
-    let address = ffi::get_contact_address(contact);
-    let city = ffi::get_address_city(address);
-    
-    // Rust logic - determine if person is an adult
-    let is_adult = age >= 18;
-    
-    // Calculate BMI category (simplified with assumed weight)
-    let assumed_weight_kg = 70.0;
-    let bmi = assumed_weight_kg / (height * height);
-    
-    let bmi_category = if bmi < 18.5 {
-        0 // underweight
-    } else if bmi < 25.0 {
-        1 // normal
-    } else {
-        2 // overweight
-    };
-    
-    // Get name length - CxxString can be used like &str in Rust
-    let name_length = name.to_str().map(|s| s.len()).unwrap_or(0);
-    
-    // Extract city from nested C++ objects
-    let city_string = city.to_str().unwrap_or("Unknown").to_string();
-    
-    // Return bridge struct
-    ffi::PersonInfo {
-        is_adult,
-        bmi_category,
-        name_length,
-        city: city_string,
-    }
+    compute_person_info(&CxxPerson::new(person))
 }
 
 /// Perform comprehensive health analysis
@@ -133,80 +175,85 @@ fn process_person(person: &ffi::Person) -> ffi::PersonInfo {
 /// - Machine learning crates
 /// - Concurrent processing
 /// - Safe data validation
-fn analyze_health(person: &ffi::Person, weight_kg: f64) -> ffi::HealthAnalysis {
-    // Extract data from C++ Person object
-    let age = ffi::get_person_age(person);
-    let height = ffi::get_person_height(person);
-    let contact = ffi::get_person_contact(person);
-    let address = ffi::get_contact_address(contact);
-    let city = ffi::get_address_city(address);
-    
-    // Calculate BMI
-    let bmi = if height > 0.0 {
-        weight_kg / (height * height)
-    } else {
-        0.0
-    };
-    
-    // Complex risk calculation (this is where Rust shines)
-    let age_risk = if age < 18 || age > 65 { 1.5 } else { 1.0 };
-    let bmi_risk = if bmi < 18.5 || bmi > 25.0 { 1.3 } else { 1.0 };
-    
-    // City-based risk factor (demonstrating string processing)
-    let city_str = city.to_str().unwrap_or("");
-    let city_risk = match city_str {
-        "New York" => 1.2,
-        "Los Angeles" => 1.1,
-        _ => 1.0,
-    };
-    
-    let risk_score = age_risk * bmi_risk * city_risk;
-    
-    // Generate recommendation based on analysis
-    let recommendation = if risk_score < 1.2 {
-        "Excellent health profile. Maintain current lifestyle.".to_string()
-    } else if risk_score < 1.5 {
-        "Good health. Consider minor lifestyle adjustments.".to_string()
-    } else {
-        "Elevated risk factors. Recommend consultation with healthcare provider.".to_string()
-    };
-    
-    ffi::HealthAnalysis {
-        bmi,
-        risk_score,
-        recommendation,
-        city_risk_factor: city_risk,
-    }
+///
+/// Repeated calls for the same person and weight are answered from a
+/// cache; see analysis_cache.rs
+pub fn analyze_health(person: &ffi::Person, weight_kg: f64) -> ffi::HealthAnalysis {
+    metrics::ffi_call(FfiCall::AnalyzeHealth);
+    // Read the C++ Person object in place
+    compute_health_cached(&CxxPerson::new(person), weight_kg)
+}
+
+/// analyze_health as `options` ask for it
+///
+/// `weight` is in `options.units`. The explanation is empty unless
+/// `options.include_explanations` is set, and the recommendation is empty
+/// if `options.lazy_recommendations` is; `options.timeout_ms` is for
+/// batches, as one analysis takes microseconds.
+pub fn analyze_health_with_options(
+    person: &ffi::Person,
+    weight: f64,
+    options: &ffi::AnalysisOptions,
+) -> Result<ffi::ExplainedAnalysis, Error> {
+    metrics::ffi_call(FfiCall::AnalyzeHealth);
+    options.check()?;
+    let (analysis, explanation) = compute_health_with(
+        &CxxPerson::new(person),
+        options.weight_kg(weight),
+        options.recommendation_locale().as_deref(),
+        options.include_explanations,
+    );
+    Ok(ffi::ExplainedAnalysis {
+        analysis,
+        explanation: explanation.unwrap_or_default(),
+    })
 }
 
 /// Greet a person by name
 /// 
-/// Simple function demonstrating string handling
+/// Simple function demonstrating string handling. The printed name is
+/// redacted by the log policy (see redaction.rs).
 fn greet_person(name: &str) -> usize {
     if name.is_empty() {
         println!("Hello, stranger!");
         return 0;
     }
     
-    println!("Hello from Rust, {}!", name);
+    println!("Hello from Rust, {}!", redaction::name_for_log(name));
     name.len()
 }
 
-/// Calculate BMI from weight and height
-/// 
-/// Pure Rust calculation - no C++ interaction
-fn calculate_bmi(weight_kg: f64, height_m: f64) -> f64 {
-    if height_m <= 0.0 {
-        return 0.0;
-    }
-    weight_kg / (height_m * height_m)
+/// Greet a person by a UTF-16 name, as wide-string (Win32) hosts hold it
+///
+/// Unpaired surrogates become U+FFFD. Returns the length in UTF-16 code
+/// units, the unit the caller measures its strings in.
+fn greet_person_utf16(name: &[u16]) -> usize {
+    greet_person(&String::from_utf16_lossy(name));
+    name.len()
+}
+
+/// The person's name as UTF-16, for wide-string (Win32) hosts
+///
+/// Converted once from the std::string's UTF-8; invalid bytes become
+/// U+FFFD as in snapshot_person.
+pub fn person_name_utf16(person: &ffi::Person) -> Vec<u16> {
+    audit::record("person_name_utf16", 0, &["name"]);
+    ffi::get_person_name(person)
+        .to_string_lossy()
+        .encode_utf16()
+        .collect()
 }
 
 /// Validate contact information
 /// 
 /// Demonstrates deep access into nested C++ objects:
 /// ContactInfo -> Address -> fields
-fn validate_contact(contact: &ffi::ContactInfo) -> bool {
+///
+/// The fields are borrowed from the std::strings, so the check allocates
+/// nothing (tests/allocations.rs holds it to that)
+pub fn validate_contact(contact: &ffi::ContactInfo) -> bool {
+    metrics::ffi_call(FfiCall::ValidateContact);
+    audit::record("validate_contact", 0, &["email", "phone", "city", "postal_code"]);
     // Extract data from nested C++ objects
     let email = ffi::get_contact_email(contact);
     let phone = ffi::get_contact_phone(contact);
@@ -214,19 +261,54 @@ fn validate_contact(contact: &ffi::ContactInfo) -> bool {
     let city = ffi::get_address_city(address);
     let postal_code = ffi::get_address_postal_code(address);
     
-    // Rust validation logic
-    let email_str = email.to_str().unwrap_or("");
-    let phone_str = phone.to_str().unwrap_or("");
-    let city_str = city.to_str().unwrap_or("");
-    let postal_str = postal_code.to_str().unwrap_or("");
-    
-    // Simple validation rules
-    let email_valid = email_str.contains('@') && email_str.len() > 3;
-    let phone_valid = phone_str.len() >= 7;
-    let city_valid = !city_str.is_empty();
-    let postal_valid = postal_str.len() >= 5;
-    
-    email_valid && phone_valid && city_valid && postal_valid
+    // Rust validation logic (shared with validate_person_full)
+    engine::contact_fields_valid(
+        email.to_str().unwrap_or(""),
+        phone.to_str().unwrap_or(""),
+        city.to_str().unwrap_or(""),
+        postal_code.to_str().unwrap_or(""),
+    )
+}
+
+/// Copy all fields of a C++ Person into a Rust-owned snapshot
+/// 
+/// Walks Person -> ContactInfo -> Address once so later processing
+/// (batching, queues, threads) never touches the C++ object again.
+/// fill_person_snapshot copies the fields on the C++ side, in one call
+/// across the bridge instead of one per getter.
+pub fn snapshot_person(person: &ffi::Person) -> ffi::PersonSnapshot {
+    metrics::ffi_call(FfiCall::SnapshotPerson);
+    audit::record("snapshot_person", 0, audit::PERSON_FIELDS);
+    let mut snapshot = ffi::PersonSnapshot::default();
+    ffi::fill_person_snapshot(person, &mut snapshot);
+    snapshot
+}
+
+/// snapshot_person for a C++ layer whose strings are in `encoding`
+///
+/// For example "ISO-8859-1" from a legacy database; see encoding.rs for the
+/// accepted names. Unknown encodings are an error, and so are invalid
+/// bytes with "UTF-8", where snapshot_person would substitute U+FFFD.
+pub fn snapshot_person_with_encoding(
+    person: &ffi::Person,
+    encoding: &str,
+) -> Result<ffi::PersonSnapshot, Error> {
+    let encoding = LegacyEncoding::from_name(encoding)?;
+    audit::record("snapshot_person_with_encoding", 0, audit::PERSON_FIELDS);
+    let text = |bytes: &[u8]| encoding.decode(bytes);
+    let contact = ffi::get_person_contact(person);
+    let address = ffi::get_contact_address(contact);
+
+    Ok(ffi::PersonSnapshot {
+        name: text(ffi::get_person_name(person).as_bytes())?,
+        age: ffi::get_person_age(person),
+        height_m: ffi::get_person_height(person),
+        email: text(ffi::get_contact_email(contact).as_bytes())?,
+        phone: text(ffi::get_contact_phone(contact).as_bytes())?,
+        street: text(ffi::get_address_street(address).as_bytes())?,
+        city: text(ffi::get_address_city(address).as_bytes())?,
+        postal_code: text(ffi::get_address_postal_code(address).as_bytes())?,
+    })
 }
 
 // ============================================================================
@@ -250,17 +332,15 @@ mod tests {
     }
 
     #[test]
-    fn test_calculate_bmi() {
-        let bmi = calculate_bmi(70.0, 1.75);
-        assert!((bmi - 22.86).abs() < 0.01);
-    }
-    
-    #[test]
-    fn test_calculate_bmi_zero_height() {
-        let bmi = calculate_bmi(70.0, 0.0);
-        assert_eq!(bmi, 0.0);
+    fn test_greet_person_utf16() {
+        let name: Vec<u16> = "José 👍".encode_utf16().collect();
+        assert_eq!(greet_person_utf16(&name), 7);
+        assert_eq!(greet_person_utf16(&[]), 0);
+        // A lone surrogate is replaced, not an error
+        assert_eq!(greet_person_utf16(&[0x41, 0xd800]), 2);
     }
 
-    // Note: Tests involving C++ types would need C++ test framework
-    // or integration tests. Pure Rust functions can be unit tested here.
+    // Tests on C++ objects are integration tests: tests/cpp_types.rs, with
+    // the fixtures of tests/test_fixtures.cpp. Pure Rust functions can be
+    // unit tested here; the health model's tests are in engine.rs.
 }
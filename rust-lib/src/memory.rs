@@ -0,0 +1,120 @@
+//! How much heap the engine holds, for the host's diagnostics
+//!
+//! `memory_report()` asks each owner of long-lived data (the registry, the
+//! analysis cache, the interner, pooled string tables, the jobs and batch
+//! contexts) what it holds. The numbers are estimates from the buffers'
+//! lengths and capacities, not the allocator's view: there is no allocator
+//! overhead in them, and a hash table counts as its buckets. That is precise enough to
+//! see which owner grows, which is what a leak looks like from C++.
+
+use std::mem::size_of;
+
+use crate::ffi;
+use crate::{analysis_cache, batch_context, intern, jobs, string_table};
+
+/// Heap a value owns beyond its own `size_of`
+pub(crate) trait HeapSize {
+    fn heap_bytes(&self) -> usize;
+}
+
+impl HeapSize for String {
+    fn heap_bytes(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl HeapSize for Box<str> {
+    fn heap_bytes(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_bytes(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(T::heap_bytes).sum::<usize>()
+    }
+}
+
+impl HeapSize for ffi::PersonSnapshot {
+    fn heap_bytes(&self) -> usize {
+        [
+            &self.name,
+            &self.email,
+            &self.phone,
+            &self.street,
+            &self.city,
+            &self.postal_code,
+        ]
+        .iter()
+        .map(|text| text.heap_bytes())
+        .sum()
+    }
+}
+
+impl HeapSize for ffi::HealthAnalysis {
+    fn heap_bytes(&self) -> usize {
+        self.recommendation.heap_bytes()
+    }
+}
+
+/// The buckets of a hash table of `capacity` entries of type `T`, with
+/// its one control byte per bucket
+pub(crate) fn table_bytes<T>(capacity: usize) -> usize {
+    capacity * (size_of::<T>() + 1)
+}
+
+pub(crate) fn memory_report() -> ffi::MemoryReport {
+    #[cfg(feature = "registry")]
+    let registry_bytes = crate::registry::heap_bytes();
+    #[cfg(not(feature = "registry"))]
+    let registry_bytes = 0;
+
+    let mut report = ffi::MemoryReport {
+        registry_bytes,
+        analysis_cache_bytes: analysis_cache::heap_bytes(),
+        interner_bytes: intern::heap_bytes(),
+        string_table_bytes: string_table::heap_bytes(),
+        job_bytes: jobs::heap_bytes(),
+        batch_context_bytes: batch_context::heap_bytes(),
+        total_bytes: 0,
+    };
+    report.total_bytes = report.registry_bytes
+        + report.analysis_cache_bytes
+        + report.interner_bytes
+        + report.string_table_bytes
+        + report.job_bytes
+        + report.batch_context_bytes;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+
+    #[test]
+    fn test_heap_bytes_of_snapshots() {
+        let person = snapshot(30, 1.75, "Boston");
+        // "Test", "test@example.com", "555-0000", "1 Test St", "Boston", "00000"
+        assert_eq!(person.heap_bytes(), 4 + 16 + 8 + 9 + 6 + 5);
+
+        let mut people = Vec::with_capacity(4);
+        people.push(person);
+        assert_eq!(
+            people.heap_bytes(),
+            4 * size_of::<ffi::PersonSnapshot>() + 48
+        );
+    }
+
+    // The owners are process-wide, so only lower bounds are certain here
+    #[test]
+    fn test_report_sees_a_string_table() {
+        let mut strings = string_table::StringTableBuilder::default();
+        strings.intern(&"x".repeat(4096));
+        let id = string_table::publish(strings.take());
+        let after = memory_report();
+        assert!(after.string_table_bytes >= 4096);
+        assert!(after.total_bytes >= after.string_table_bytes + after.analysis_cache_bytes);
+        string_table::release_string_table(id);
+    }
+}
@@ -0,0 +1,303 @@
+//! Prometheus metrics (`metrics` feature)
+//!
+//! The engine counts as it works:
+//!
+//! - `ffi_calls_total{function}`: calls of the analysis and validation
+//!   entry points, through any of the bridges
+//! - `analysis_duration_seconds`: a histogram of single analyses, batch
+//!   items included, with observers and hooks
+//! - `validation_failures_total`: full validations that found a problem
+//!
+//! `metrics_text` renders them in the Prometheus text format, and
+//! `start_metrics_endpoint` serves that as `GET /metrics` (see
+//! http_server.rs) for the ops team to scrape. Counting is a relaxed
+//! atomic add. Without the feature the hooks below compile to nothing.
+
+#[cfg(feature = "metrics")]
+use std::fmt::Write;
+#[cfg(feature = "metrics")]
+use std::net::SocketAddr;
+#[cfg(feature = "metrics")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "metrics")]
+use std::time::Instant;
+
+#[cfg(feature = "metrics")]
+use crate::error::Error;
+#[cfg(feature = "metrics")]
+use crate::http_server::{Reply, Request, Server, Service};
+#[cfg(feature = "metrics")]
+use crate::state::Global;
+
+/// The entry points `ffi_calls_total` counts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FfiCall {
+    ProcessPerson,
+    SnapshotPerson,
+    AnalyzeHealth,
+    AnalyzeHealthBatch,
+    ValidateContact,
+    ValidatePersonFull,
+}
+
+#[cfg(feature = "metrics")]
+const FFI_CALLS: [FfiCall; 6] = [
+    FfiCall::ProcessPerson,
+    FfiCall::SnapshotPerson,
+    FfiCall::AnalyzeHealth,
+    FfiCall::AnalyzeHealthBatch,
+    FfiCall::ValidateContact,
+    FfiCall::ValidatePersonFull,
+];
+
+#[cfg(feature = "metrics")]
+impl FfiCall {
+    fn name(self) -> &'static str {
+        match self {
+            FfiCall::ProcessPerson => "process_person",
+            FfiCall::SnapshotPerson => "snapshot_person",
+            FfiCall::AnalyzeHealth => "analyze_health",
+            FfiCall::AnalyzeHealthBatch => "analyze_health_batch",
+            FfiCall::ValidateContact => "validate_contact",
+            FfiCall::ValidatePersonFull => "validate_person_full",
+        }
+    }
+}
+
+/// Upper bounds of the `analysis_duration_seconds` buckets; an analysis
+/// takes well under a microsecond unless a hook calls out
+#[cfg(feature = "metrics")]
+const DURATION_BUCKETS: [f64; 9] = [1e-6, 5e-6, 1e-5, 5e-5, 1e-4, 5e-4, 1e-3, 5e-3, 1e-2];
+
+#[cfg(feature = "metrics")]
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(feature = "metrics")]
+static CALLS: [AtomicU64; FFI_CALLS.len()] = [ZERO; FFI_CALLS.len()];
+/// Per bucket, not cumulative; the last one is +Inf
+#[cfg(feature = "metrics")]
+static DURATIONS: [AtomicU64; DURATION_BUCKETS.len() + 1] = [ZERO; DURATION_BUCKETS.len() + 1];
+#[cfg(feature = "metrics")]
+static DURATION_SUM_NS: AtomicU64 = AtomicU64::new(0);
+#[cfg(feature = "metrics")]
+static VALIDATION_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(feature = "metrics")]
+pub(crate) fn ffi_call(call: FfiCall) {
+    CALLS[call as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn ffi_call(_: FfiCall) {}
+
+/// Started when an analysis begins; see analysis_finished
+#[cfg(feature = "metrics")]
+pub(crate) struct AnalysisTimer(Instant);
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) struct AnalysisTimer;
+
+#[cfg(feature = "metrics")]
+pub(crate) fn analysis_started() -> AnalysisTimer {
+    AnalysisTimer(Instant::now())
+}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn analysis_started() -> AnalysisTimer {
+    AnalysisTimer
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn analysis_finished(AnalysisTimer(started): AnalysisTimer) {
+    let elapsed = started.elapsed();
+    let seconds = elapsed.as_secs_f64();
+    let bucket = DURATION_BUCKETS
+        .iter()
+        .position(|&bound| seconds <= bound)
+        .unwrap_or(DURATION_BUCKETS.len());
+    DURATIONS[bucket].fetch_add(1, Ordering::Relaxed);
+    let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+    DURATION_SUM_NS.fetch_add(nanos, Ordering::Relaxed);
+}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn analysis_finished(_: AnalysisTimer) {}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn validation_failed() {
+    VALIDATION_FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) fn validation_failed() {}
+
+/// Every metric in the Prometheus text exposition format (version 0.0.4)
+#[cfg(feature = "metrics")]
+pub(crate) fn metrics_text() -> String {
+    let mut text = String::new();
+    text.push_str("# HELP ffi_calls_total Calls of the engine's entry points.\n");
+    text.push_str("# TYPE ffi_calls_total counter\n");
+    for call in FFI_CALLS {
+        let count = CALLS[call as usize].load(Ordering::Relaxed);
+        let _ = writeln!(
+            text,
+            "ffi_calls_total{{function=\"{}\"}} {count}",
+            call.name()
+        );
+    }
+
+    text.push_str("# HELP analysis_duration_seconds Time taken by one health analysis.\n");
+    text.push_str("# TYPE analysis_duration_seconds histogram\n");
+    let mut cumulative = 0;
+    for (index, bucket) in DURATIONS.iter().enumerate() {
+        cumulative += bucket.load(Ordering::Relaxed);
+        match DURATION_BUCKETS.get(index) {
+            Some(bound) => {
+                let _ = writeln!(
+                    text,
+                    "analysis_duration_seconds_bucket{{le=\"{bound}\"}} {cumulative}"
+                );
+            }
+            None => {
+                let _ = writeln!(
+                    text,
+                    "analysis_duration_seconds_bucket{{le=\"+Inf\"}} {cumulative}"
+                );
+            }
+        }
+    }
+    let sum = DURATION_SUM_NS.load(Ordering::Relaxed) as f64 / 1e9;
+    let _ = writeln!(text, "analysis_duration_seconds_sum {sum}");
+    let _ = writeln!(text, "analysis_duration_seconds_count {cumulative}");
+
+    text.push_str("# HELP validation_failures_total Full validations that found a problem.\n");
+    text.push_str("# TYPE validation_failures_total counter\n");
+    let failures = VALIDATION_FAILURES.load(Ordering::Relaxed);
+    let _ = writeln!(text, "validation_failures_total {failures}");
+    text
+}
+
+#[cfg(feature = "metrics")]
+static ENDPOINT: Global<Option<Server>> = Global::new(|| None);
+
+/// `GET /metrics` as a Service
+#[cfg(feature = "metrics")]
+struct Scrape;
+
+#[cfg(feature = "metrics")]
+impl Service for Scrape {
+    fn respond(&self, request: &Request) -> Reply {
+        let path = request.path.split('?').next().unwrap_or_default();
+        match (request.method.as_str(), path) {
+            ("GET", "/metrics") => Reply {
+                status: 200,
+                content_type: "text/plain; version=0.0.4",
+                body: metrics_text().into_bytes(),
+            },
+            (_, "/metrics") => self.reject(405, "use GET"),
+            _ => self.reject(404, "only /metrics is served"),
+        }
+    }
+
+    fn reject(&self, status: u16, message: &str) -> Reply {
+        Reply {
+            status,
+            content_type: "text/plain",
+            body: format!("{message}\n").into_bytes(),
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn start_metrics_endpoint(addr: &str) -> Result<(), Error> {
+    start(addr).map(|_| ())
+}
+
+/// Bind `addr` and start accepting; the bound address, for port 0
+#[cfg(feature = "metrics")]
+fn start(addr: &str) -> Result<SocketAddr, Error> {
+    let mut endpoint = ENDPOINT.write();
+    if endpoint.is_some() {
+        return Err(Error::AlreadyRunning("metrics endpoint"));
+    }
+    let bound = Server::bind(addr, "metrics", Scrape)?;
+    let addr = bound.addr();
+    *endpoint = Some(bound);
+    Ok(addr)
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn stop_metrics_endpoint() {
+    let endpoint = ENDPOINT.write().take();
+    if let Some(endpoint) = endpoint {
+        endpoint.stop();
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpStream;
+
+    /// The value of one sample line in `text`
+    fn sample(text: &str, name: &str) -> u64 {
+        text.lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+            .unwrap_or_else(|| panic!("no sample {name}"))
+            .parse()
+            .unwrap()
+    }
+
+    // The counters are process-wide and other tests run analyses in
+    // parallel, so only lower bounds are checked
+    #[test]
+    fn test_metrics_count_calls_analyses_and_failures() {
+        let before = metrics_text();
+        ffi_call(FfiCall::ValidatePersonFull);
+        let person = crate::batch::tests::snapshot(200, 1.75, "Boston");
+        crate::analyze_snapshot(&person, 70.0);
+        crate::validation::validate_reported(&person);
+        let after = metrics_text();
+
+        let calls = r#"ffi_calls_total{function="validate_person_full"}"#;
+        assert!(sample(&after, calls) > sample(&before, calls));
+        let count = "analysis_duration_seconds_count";
+        assert!(sample(&after, count) > sample(&before, count));
+        let infinite = r#"analysis_duration_seconds_bucket{le="+Inf"}"#;
+        assert_eq!(sample(&after, infinite), sample(&after, count));
+        let failures = "validation_failures_total";
+        assert!(sample(&after, failures) > sample(&before, failures));
+    }
+
+    #[test]
+    fn test_metrics_endpoint() {
+        assert!(start_metrics_endpoint("nowhere").is_err());
+        let addr = start("127.0.0.1:0").unwrap();
+        assert!(matches!(
+            start_metrics_endpoint("127.0.0.1:0"),
+            Err(Error::AlreadyRunning(_))
+        ));
+
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            std::io::Write::write_all(
+                &mut stream,
+                format!("GET {path} HTTP/1.1\r\n\r\n").as_bytes(),
+            )
+            .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let response = get("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: text/plain; version=0.0.4\r\n"));
+        assert!(response.contains("# TYPE analysis_duration_seconds histogram\n"));
+        assert!(get("/").starts_with("HTTP/1.1 404 "));
+
+        stop_metrics_endpoint();
+        assert!(ENDPOINT.read().is_none());
+    }
+}
@@ -0,0 +1,149 @@
+//! Geocoding through a Nominatim server (`nominatim` feature)
+//!
+//! Nominatim is the OpenStreetMap geocoder; its `/search` endpoint takes
+//! the street, city and postal code separately and answers with a JSON
+//! array of matches. Requests go through http_client.rs, to an `http://`
+//! server on the local network or an `https://` one such as
+//! nominatim.openstreetmap.org.
+
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::error::Error;
+use crate::ffi;
+use crate::geocode::{self, AddressQuery, Geocoder};
+use crate::http_client::Endpoint;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct NominatimGeocoder {
+    server: Endpoint,
+}
+
+pub(crate) fn use_nominatim_geocoder(base_url: &str) -> Result<(), Error> {
+    geocode::set_geocoder(Arc::new(NominatimGeocoder::new(base_url)?));
+    Ok(())
+}
+
+impl NominatimGeocoder {
+    /// A client for the server at `base_url`, like "http://localhost:8088"
+    /// or "https://nominatim.openstreetmap.org"
+    pub(crate) fn new(base_url: &str) -> Result<Self, Error> {
+        Ok(NominatimGeocoder {
+            server: Endpoint::parse(base_url, "Nominatim URL")?,
+        })
+    }
+
+    /// Path and query asking for the best match of `address`
+    fn target(&self, address: &AddressQuery<'_>) -> String {
+        let mut target = format!("{}/search?format=jsonv2&limit=1", self.server.path());
+        for (name, value) in [
+            ("street", address.street),
+            ("city", address.city),
+            ("postalcode", address.postal_code),
+        ] {
+            let value = value.trim();
+            if !value.is_empty() {
+                target.push_str(&format!("&{name}={}", percent_encode(value)));
+            }
+        }
+        target
+    }
+}
+
+impl Geocoder for NominatimGeocoder {
+    fn geocode(&self, address: &AddressQuery<'_>) -> Result<ffi::GeoPoint, Error> {
+        let response = self.server.send("GET", &self.target(address), None)?;
+        if response.status != 200 {
+            return Err(Error::Io(format!(
+                "Nominatim answered HTTP {}",
+                response.status
+            )));
+        }
+        parse_matches(&response.body)
+    }
+}
+
+/// The first match in a `/search` response body
+fn parse_matches(body: &[u8]) -> Result<ffi::GeoPoint, Error> {
+    let malformed = |why: &str| Error::Io(format!("Nominatim response: {why}"));
+    let matches: Value = serde_json::from_slice(body).map_err(|e| malformed(&e.to_string()))?;
+    let Some(best) = matches.as_array().and_then(|matches| matches.first()) else {
+        return Err(Error::AddressNotFound("no Nominatim match".to_string()));
+    };
+    // Nominatim sends the coordinates as strings
+    let coordinate = |name| match &best[name] {
+        Value::String(text) => text.parse::<f64>().ok(),
+        value => value.as_f64(),
+    };
+    match (coordinate("lat"), coordinate("lon")) {
+        (Some(lat), Some(lon)) => Ok(ffi::GeoPoint { lat, lon }),
+        _ => Err(malformed("match without lat/lon")),
+    }
+}
+
+/// `text` as a URL query value
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::tests::fake_server;
+
+    const QUERY: AddressQuery<'static> = AddressQuery {
+        street: "1 Main St",
+        city: "São Paulo",
+        postal_code: "",
+    };
+
+    #[test]
+    fn test_target() {
+        let geocoder = NominatimGeocoder::new("http://proxy/nominatim/").unwrap();
+        assert_eq!(
+            geocoder.target(&QUERY),
+            "/nominatim/search?format=jsonv2&limit=1&street=1%20Main%20St&city=S%C3%A3o%20Paulo"
+        );
+        let geocoder = NominatimGeocoder::new("https://nominatim.openstreetmap.org").unwrap();
+        assert!(geocoder.target(&QUERY).starts_with("/search?format=jsonv2"));
+        assert!(NominatimGeocoder::new("nominatim.openstreetmap.org").is_err());
+    }
+
+    #[test]
+    fn test_parse_matches() {
+        let point =
+            parse_matches(br#"[{"lat": "-23.55", "lon": "-46.63", "display_name": "x"}]"#).unwrap();
+        assert_eq!((point.lat, point.lon), (-23.55, -46.63));
+
+        let none = parse_matches(b"[]").unwrap_err();
+        assert!(matches!(none, Error::AddressNotFound(_)));
+        assert!(parse_matches(b"[{}]").is_err());
+        assert!(parse_matches(b"<html>").is_err());
+    }
+
+    #[test]
+    fn test_geocode_round_trip() {
+        let (url, server) = fake_server(vec![
+            "HTTP/1.0 200 OK\r\n\r\n[{\"lat\": \"1.5\", \"lon\": \"2.5\"}]",
+            "HTTP/1.0 429 Too Many Requests\r\n\r\n",
+        ]);
+        let geocoder = NominatimGeocoder::new(&url).unwrap();
+        let point = geocoder.geocode(&QUERY).unwrap();
+        assert_eq!((point.lat, point.lon), (1.5, 2.5));
+        let busy = geocoder.geocode(&QUERY).unwrap_err();
+        assert!(busy.to_string().contains("429"));
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /search?format=jsonv2&limit=1&street="));
+    }
+}
@@ -0,0 +1,360 @@
+//! Unicode normalization for comparing and hashing people
+//!
+//! The same name can reach us as different code points: "José" with a
+//! precomposed é from one C++ form, or as "e" plus a combining acute accent
+//! from another. Both are NFC-normalized before any comparison, and
+//! optionally case-folded, so `person_equals`, `hash_person` and
+//! `register_person_unique` treat them as the same person. The `_with_options`
+//! variants can also compare by Latin transliteration (transliterate.rs), so
+//! "Иван" matches "Ivan". The Unicode data comes from the generated
+//! `unicode_tables.rs`.
+
+use std::borrow::Cow;
+
+use crate::ffi;
+use crate::transliterate::transliterate_name;
+use crate::unicode_tables::{COMBINING_CLASSES, COMPOSITIONS, DECOMPOSITIONS};
+
+// Hangul syllables decompose arithmetically (Unicode §3.12)
+pub(crate) const S_BASE: u32 = 0xAC00;
+const L_BASE: u32 = 0x1100;
+const V_BASE: u32 = 0x1161;
+const T_BASE: u32 = 0x11A7;
+const L_COUNT: u32 = 19;
+const V_COUNT: u32 = 21;
+pub(crate) const T_COUNT: u32 = 28;
+const N_COUNT: u32 = V_COUNT * T_COUNT;
+pub(crate) const S_COUNT: u32 = L_COUNT * N_COUNT;
+
+pub(crate) fn combining_class(c: char) -> u8 {
+    let c = c as u32;
+    COMBINING_CLASSES
+        .binary_search_by(|&(first, last, _)| {
+            if last < c {
+                std::cmp::Ordering::Less
+            } else if first > c {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .map_or(0, |index| COMBINING_CLASSES[index].2)
+}
+
+/// Append the full canonical decomposition of `c`
+fn decompose_into(c: char, out: &mut Vec<char>) {
+    let code = c as u32;
+    if (S_BASE..S_BASE + S_COUNT).contains(&code) {
+        let index = code - S_BASE;
+        let jamo = [
+            L_BASE + index / N_COUNT,
+            V_BASE + index % N_COUNT / T_COUNT,
+            T_BASE + index % T_COUNT,
+        ];
+        let count = if jamo[2] == T_BASE { 2 } else { 3 };
+        out.extend(
+            jamo[..count]
+                .iter()
+                .filter_map(|&jamo| char::from_u32(jamo)),
+        );
+        return;
+    }
+    match DECOMPOSITIONS.binary_search_by_key(&code, |&(composite, _, _)| composite) {
+        Ok(index) => {
+            let (_, first, second) = DECOMPOSITIONS[index];
+            for part in [first, second].into_iter().filter(|&part| part != 0) {
+                decompose_into(char::from_u32(part).unwrap_or(c), out);
+            }
+        }
+        Err(_) => out.push(c),
+    }
+}
+
+/// Canonical decomposition with combining marks in canonical order (NFD)
+pub(crate) fn decompose(chars: impl Iterator<Item = char>) -> Vec<char> {
+    let mut out = Vec::new();
+    for c in chars {
+        decompose_into(c, &mut out);
+    }
+    // Stable sort of each run of non-starters by combining class
+    let mut start = 0;
+    while start < out.len() {
+        if combining_class(out[start]) == 0 {
+            start += 1;
+            continue;
+        }
+        let end = (start..out.len())
+            .find(|&i| combining_class(out[i]) == 0)
+            .unwrap_or(out.len());
+        out[start..end].sort_by_key(|&c| combining_class(c));
+        start = end;
+    }
+    out
+}
+
+fn compose_pair(first: char, second: char) -> Option<char> {
+    let (first, second) = (first as u32, second as u32);
+    if (L_BASE..L_BASE + L_COUNT).contains(&first) && (V_BASE..V_BASE + V_COUNT).contains(&second) {
+        let index = (first - L_BASE) * N_COUNT + (second - V_BASE) * T_COUNT;
+        return char::from_u32(S_BASE + index);
+    }
+    if (S_BASE..S_BASE + S_COUNT).contains(&first)
+        && (first - S_BASE).is_multiple_of(T_COUNT)
+        && (T_BASE + 1..T_BASE + T_COUNT).contains(&second)
+    {
+        return char::from_u32(first + second - T_BASE);
+    }
+    COMPOSITIONS
+        .binary_search_by_key(&(first, second), |&(a, b, _)| (a, b))
+        .ok()
+        .and_then(|index| char::from_u32(COMPOSITIONS[index].2))
+}
+
+/// Canonical composition of an NFD sequence (the second half of NFC)
+fn compose(decomposed: Vec<char>) -> String {
+    let mut out: Vec<char> = Vec::with_capacity(decomposed.len());
+    let mut starter: Option<usize> = None;
+    let mut last_class = 0;
+    for c in decomposed {
+        let class = combining_class(c);
+        if let Some(index) = starter {
+            // Blocked by anything in between with the same or a higher class,
+            // or by an uncombined starter
+            let adjacent = out.len() == index + 1;
+            if adjacent || (last_class != 0 && last_class < class) {
+                if let Some(composite) = compose_pair(out[index], c) {
+                    out[index] = composite;
+                    continue;
+                }
+            }
+        }
+        if class == 0 {
+            starter = Some(out.len());
+        }
+        last_class = class;
+        out.push(c);
+    }
+    out.into_iter().collect()
+}
+
+/// `text` in Unicode Normalization Form C; borrowed when already NFC
+pub(crate) fn nfc(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    let normalized = compose(decompose(text.chars()));
+    if normalized == text {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(normalized)
+    }
+}
+
+/// NFC of the case-folded text: "STRASSE", "Straße" and "straße" all match
+///
+/// Full case folding is approximated by lowercasing, plus the foldings that
+/// lowercasing misses (ß and ẞ to "ss", final ς to σ).
+pub(crate) fn nfc_casefold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in decompose(text.chars()) {
+        match c {
+            'ß' | 'ẞ' => folded.push_str("ss"),
+            'ς' => folded.push('σ'),
+            _ => folded.extend(c.to_lowercase()),
+        }
+    }
+    compose(decompose(folded.chars()))
+}
+
+fn comparison_key<'a>(text: &'a str, options: &ffi::MatchOptions) -> Cow<'a, str> {
+    if options.transliterate {
+        let latin = transliterate_name(text);
+        return Cow::Owned(if options.fold_case {
+            nfc_casefold(&latin)
+        } else {
+            latin
+        });
+    }
+    if options.fold_case {
+        Cow::Owned(nfc_casefold(text))
+    } else {
+        nfc(text)
+    }
+}
+
+fn text_fields(person: &ffi::PersonSnapshot) -> [&str; 6] {
+    [
+        &person.name,
+        &person.email,
+        &person.phone,
+        &person.street,
+        &person.city,
+        &person.postal_code,
+    ]
+}
+
+fn case_only(fold_case: bool) -> ffi::MatchOptions {
+    ffi::MatchOptions {
+        fold_case,
+        ..Default::default()
+    }
+}
+
+/// Same age and height, and every text field equal after normalization
+pub(crate) fn person_equals(
+    a: &ffi::PersonSnapshot,
+    b: &ffi::PersonSnapshot,
+    fold_case: bool,
+) -> bool {
+    person_equals_with_options(a, b, &case_only(fold_case))
+}
+
+pub(crate) fn person_equals_with_options(
+    a: &ffi::PersonSnapshot,
+    b: &ffi::PersonSnapshot,
+    options: &ffi::MatchOptions,
+) -> bool {
+    a.age == b.age
+        && a.height_m == b.height_m
+        && text_fields(a)
+            .into_iter()
+            .zip(text_fields(b))
+            .all(|(a, b)| comparison_key(a, options) == comparison_key(b, options))
+}
+
+/// Hash that agrees with `person_equals` for the same `fold_case`
+///
+/// FNV-1a over the normalized fields, so it is the same in every process
+/// and on every platform and may be stored by the host.
+pub(crate) fn hash_person(person: &ffi::PersonSnapshot, fold_case: bool) -> u64 {
+    hash_person_with_options(person, &case_only(fold_case))
+}
+
+/// Hash that agrees with `person_equals_with_options` for the same options
+pub(crate) fn hash_person_with_options(
+    person: &ffi::PersonSnapshot,
+    options: &ffi::MatchOptions,
+) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    let mut hash = OFFSET;
+    let mut feed = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash = (hash ^ byte as u64).wrapping_mul(PRIME);
+        }
+    };
+
+    feed(&person.age.to_le_bytes());
+    // + 0.0 turns -0.0 into 0.0, which person_equals treats as equal
+    feed(&(person.height_m + 0.0).to_bits().to_le_bytes());
+    for field in text_fields(person) {
+        feed(comparison_key(field, options).as_bytes());
+        // 0xFF never occurs in UTF-8, so fields cannot run into each other
+        feed(&[0xFF]);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+
+    #[test]
+    fn test_nfc() {
+        assert_eq!(nfc("Jose\u{301}"), "Jos\u{e9}");
+        // Singletons such as the Angstrom sign map to the letter
+        assert_eq!(nfc("\u{212b}"), "\u{c5}");
+        // Marks are reordered by combining class before composing
+        assert_eq!(nfc("q\u{307}\u{323}"), "q\u{323}\u{307}");
+        assert_eq!(nfc("\u{1e0b}\u{323}"), "\u{1e0d}\u{307}");
+        assert_eq!(nfc("a\u{302}\u{323}"), "\u{1ead}");
+        // A second acute has nothing left to combine with
+        assert_eq!(nfc("e\u{301}\u{301}"), "\u{e9}\u{301}");
+        // Composition exclusions stay decomposed
+        assert_eq!(nfc("\u{958}"), "\u{915}\u{93c}");
+        assert_eq!(nfc("\u{1100}\u{1161}\u{11a8}"), "\u{ac01}");
+    }
+
+    #[test]
+    fn test_nfc_borrows_normalized_text() {
+        assert!(matches!(nfc("Alice"), Cow::Borrowed(_)));
+        assert!(matches!(nfc("Jos\u{e9} 山田 👍"), Cow::Borrowed(_)));
+        assert!(matches!(nfc("Jose\u{301}"), Cow::Owned(_)));
+    }
+
+    #[test]
+    fn test_nfc_casefold() {
+        assert_eq!(nfc_casefold("JOSE\u{301}"), "jos\u{e9}");
+        assert_eq!(nfc_casefold("Straße"), nfc_casefold("STRASSE"));
+        assert_eq!(nfc_casefold("ΟΔΥΣΣΕΥΣ"), nfc_casefold("οδυσσευς"));
+    }
+
+    #[test]
+    fn test_person_equals_and_hash_agree() {
+        let mut composed = snapshot(30, 1.75, "Bogot\u{e1}");
+        composed.name = "Jos\u{e9}".to_string();
+        let mut decomposed = composed.clone();
+        decomposed.name = "Jose\u{301}".to_string();
+        decomposed.city = "Bogota\u{301}".to_string();
+
+        assert!(person_equals(&composed, &decomposed, false));
+        assert_eq!(
+            hash_person(&composed, false),
+            hash_person(&decomposed, false)
+        );
+
+        let mut shouted = decomposed.clone();
+        shouted.name = "JOSÉ".to_string();
+        assert!(!person_equals(&composed, &shouted, false));
+        assert!(person_equals(&composed, &shouted, true));
+        assert_eq!(hash_person(&composed, true), hash_person(&shouted, true));
+
+        let mut older = composed.clone();
+        older.age += 1;
+        assert!(!person_equals(&composed, &older, true));
+    }
+
+    #[test]
+    fn test_transliterated_matching() {
+        let mut latin = snapshot(35, 1.80, "Moskva");
+        latin.name = "Ivan Petrov".to_string();
+        let mut cyrillic = latin.clone();
+        cyrillic.name = "Иван Петров".to_string();
+        cyrillic.city = "Москва".to_string();
+        let options = ffi::MatchOptions {
+            fold_case: false,
+            transliterate: true,
+        };
+
+        assert!(!person_equals(&latin, &cyrillic, true));
+        assert!(person_equals_with_options(&latin, &cyrillic, &options));
+        assert_eq!(
+            hash_person_with_options(&latin, &options),
+            hash_person_with_options(&cyrillic, &options)
+        );
+        // Without transliteration the options match the plain functions
+        assert_eq!(
+            hash_person_with_options(&latin, &case_only(true)),
+            hash_person(&latin, true)
+        );
+
+        let mut shouted = cyrillic.clone();
+        shouted.name = "ИВАН ПЕТРОВ".to_string();
+        assert!(!person_equals_with_options(&latin, &shouted, &options));
+        let folded = ffi::MatchOptions {
+            fold_case: true,
+            ..options
+        };
+        assert!(person_equals_with_options(&latin, &shouted, &folded));
+    }
+
+    #[test]
+    fn test_hash_is_stable() {
+        // Stored by hosts, so it must never change for the same input
+        assert_eq!(
+            hash_person(&snapshot(30, 1.75, "Boston"), false),
+            0xf7fc_5f2e_b09b_1780
+        );
+    }
+}
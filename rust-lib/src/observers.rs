@@ -0,0 +1,227 @@
+//! Risk observers: Rust notifying C++ of high-risk results
+//!
+//! Every analysis goes through `compute_health`, which calls `notify`. Each
+//! observer whose threshold the risk score exceeds is invoked right away, on
+//! whichever thread ran the analysis (the caller's thread, or a worker for
+//! background jobs).
+//!
+//! Observers that send analyses out of the process (the webhook and remote
+//! sync) register with `add_sharing_observer`, and are skipped for an
+//! analysis run inside `without_sharing`: one of a registered person who
+//! has not consented to sharing (see consent.rs).
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::ffi;
+use crate::leak_check::{self, Handle};
+use crate::state::Global;
+
+type Observer = Arc<dyn Fn(&ffi::HealthAnalysis) + Send + Sync>;
+
+struct Registration {
+    id: u64,
+    threshold: f64,
+    observer: Observer,
+    /// Sends analyses out of the process
+    shares: bool,
+}
+
+static OBSERVERS: Global<Vec<Registration>> = Global::new(Vec::new);
+
+/// Fast path: skip the lock entirely while nobody is listening
+static OBSERVER_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+static NEXT_OBSERVER: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// Set while an analysis must not reach sharing observers
+    static WITHHOLD_SHARING: Cell<bool> = const { Cell::new(false) };
+}
+
+// Observers are called from any analysis thread, possibly concurrently;
+// callbacks.h documents that the wrapped C++ function must allow this.
+#[cfg(not(feature = "stub-cpp"))]
+unsafe impl Send for ffi::RiskObserver {}
+#[cfg(not(feature = "stub-cpp"))]
+unsafe impl Sync for ffi::RiskObserver {}
+
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn register_risk_observer(
+    threshold: f64,
+    callback: cxx::UniquePtr<ffi::RiskObserver>,
+) -> u64 {
+    add_observer(
+        threshold,
+        Arc::new(move |analysis| {
+            if let Some(callback) = callback.as_ref() {
+                callback.on_high_risk(analysis);
+            }
+        }),
+    )
+}
+
+pub(crate) fn add_observer(threshold: f64, observer: Observer) -> u64 {
+    register(threshold, observer, false)
+}
+
+/// add_observer for an observer that sends analyses out of the process
+#[cfg(any(feature = "webhook", feature = "sync"))]
+pub(crate) fn add_sharing_observer(threshold: f64, observer: Observer) -> u64 {
+    register(threshold, observer, true)
+}
+
+fn register(threshold: f64, observer: Observer, shares: bool) -> u64 {
+    let id = NEXT_OBSERVER.fetch_add(1, Ordering::Relaxed);
+    let mut observers = OBSERVERS.write();
+    observers.push(Registration {
+        id,
+        threshold,
+        observer,
+        shares,
+    });
+    OBSERVER_COUNT.store(observers.len(), Ordering::Release);
+    leak_check::issued(Handle::RiskObserver, id);
+    id
+}
+
+/// Stop notifying an observer; false if the id was unknown
+pub(crate) fn unregister_risk_observer(id: u64) -> bool {
+    let mut observers = OBSERVERS.write();
+    let before = observers.len();
+    observers.retain(|registration| registration.id != id);
+    OBSERVER_COUNT.store(observers.len(), Ordering::Release);
+    let removed = observers.len() != before;
+    if removed {
+        leak_check::released(Handle::RiskObserver, id);
+    }
+    removed
+}
+
+/// Whether any observer is registered, so an analysis may be passed to one
+pub(crate) fn any() -> bool {
+    OBSERVER_COUNT.load(Ordering::Acquire) > 0
+}
+
+/// Call every observer whose threshold `analysis` exceeds
+pub(crate) fn notify(analysis: &ffi::HealthAnalysis) {
+    if !any() {
+        return;
+    }
+
+    let withheld = WITHHOLD_SHARING.with(Cell::get);
+    // Collect first so no lock is held while running C++ code
+    let matching: Vec<Observer> = OBSERVERS
+        .read()
+        .iter()
+        .filter(|registration| analysis.risk_score > registration.threshold)
+        .filter(|registration| !(withheld && registration.shares))
+        .map(|registration| Arc::clone(&registration.observer))
+        .collect();
+
+    for observer in matching {
+        observer(analysis);
+    }
+}
+
+/// Run `analyze` with its analyses kept from sharing observers
+#[cfg(feature = "registry")]
+pub(crate) fn without_sharing<T>(analyze: impl FnOnce() -> T) -> T {
+    let _restore = Withheld(WITHHOLD_SHARING.with(|withheld| withheld.replace(true)));
+    analyze()
+}
+
+/// Puts back the flag it holds, also when the analysis panics: job workers
+/// outlive a panicking job (see jobs.rs)
+#[cfg(feature = "registry")]
+struct Withheld(bool);
+
+#[cfg(feature = "registry")]
+impl Drop for Withheld {
+    fn drop(&mut self) {
+        WITHHOLD_SHARING.with(|withheld| withheld.set(self.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_observer_sees_only_scores_above_threshold() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        // Far above anything the model produces, so other tests running in
+        // parallel never trigger it
+        let id = add_observer(
+            1000.0,
+            Arc::new(move |analysis: &ffi::HealthAnalysis| {
+                sink.lock().unwrap().push(analysis.risk_score)
+            }),
+        );
+
+        let person = crate::batch::tests::snapshot(30, 1.75, "Boston");
+        let mut analysis = crate::compute_health(&person, 70.0);
+        notify(&analysis);
+        analysis.risk_score = 1000.5;
+        notify(&analysis);
+
+        assert!(unregister_risk_observer(id));
+        notify(&analysis);
+
+        assert_eq!(*seen.lock().unwrap(), vec![1000.5]);
+        assert!(!unregister_risk_observer(id));
+    }
+
+    #[cfg(feature = "registry")]
+    #[test]
+    fn test_sharing_observers_can_be_withheld() {
+        let seen = Arc::new(AtomicUsize::new(0));
+        let sink = Arc::clone(&seen);
+        let count: Observer = Arc::new(move |_: &ffi::HealthAnalysis| {
+            sink.fetch_add(1, Ordering::Relaxed);
+        });
+        let sharing = register(2000.0, Arc::clone(&count), true);
+        let local = add_observer(2000.0, count);
+
+        let person = crate::batch::tests::snapshot(30, 1.75, "Boston");
+        let mut analysis = crate::compute_health(&person, 70.0);
+        analysis.risk_score = 2000.5;
+        // Only the local observer, and only inside
+        without_sharing(|| notify(&analysis));
+        assert_eq!(seen.load(Ordering::Relaxed), 1);
+        notify(&analysis);
+        assert_eq!(seen.load(Ordering::Relaxed), 3);
+
+        assert!(unregister_risk_observer(sharing));
+        assert!(unregister_risk_observer(local));
+    }
+
+    #[cfg(feature = "leak-check")]
+    #[test]
+    fn test_unregister_twice() {
+        let is_open = |id: u64| match leak_check::assert_no_leaked_handles() {
+            Ok(()) => false,
+            Err(crate::error::Error::LeakedHandles(open)) => {
+                open.contains(&format!("risk observer {id}"))
+            }
+            Err(other) => panic!("unexpected error {other}"),
+        };
+        let id = add_observer(1000.0, Arc::new(|_: &ffi::HealthAnalysis| {}));
+        assert!(is_open(id));
+        assert!(unregister_risk_observer(id));
+        assert!(!is_open(id));
+        assert!(!unregister_risk_observer(id));
+        assert!(!is_open(id));
+    }
+
+    #[cfg(feature = "registry")]
+    #[test]
+    fn test_withholding_ends_with_a_panic() {
+        let panicked = std::panic::catch_unwind(|| without_sharing(|| panic!("analysis")));
+        assert!(panicked.is_err());
+        assert!(!WITHHOLD_SHARING.with(Cell::get));
+    }
+}
@@ -0,0 +1,163 @@
+//! OpenAPI 3 description of the HTTP API (`http` feature)
+//!
+//! Served by http.rs as `GET /openapi.json`, so integrators can generate a
+//! client or read the contract without the Rust source. utoipa would derive
+//! this from the handlers but is not a dependency, so the document is
+//! written out here, and http.rs's tests hold it to the router and to the
+//! JSON the handlers actually produce.
+
+use serde_json::{json, Map, Value};
+
+/// The whole document
+pub(crate) fn document() -> Value {
+    let mut paths = Map::new();
+    paths.insert(
+        "/analyze".to_string(),
+        json!({
+            "post": {
+                "summary": "Health analysis of one person",
+                "requestBody": body("AnalyzeRequest"),
+                "responses": responses("HealthAnalysis"),
+            }
+        }),
+    );
+    paths.insert(
+        "/validate".to_string(),
+        json!({
+            "post": {
+                "summary": "Every field rule of validate_person_full",
+                "requestBody": body("PersonSnapshot"),
+                "responses": responses("ValidationReport"),
+            }
+        }),
+    );
+    #[cfg(feature = "registry")]
+    paths.insert(
+        "/registry".to_string(),
+        json!({
+            "get": {
+                "summary": "Every registered person with sharing consent, by id",
+                "responses": responses_with(json!({
+                    "type": "array",
+                    "items": schema("RegistryEntry"),
+                })),
+            },
+            "post": {
+                "summary": "Register a person",
+                "requestBody": body("PersonSnapshot"),
+                "responses": responses("RegisteredId"),
+            }
+        }),
+    );
+    paths.insert(
+        "/openapi.json".to_string(),
+        json!({
+            "get": {
+                "summary": "This document",
+                "responses": responses_with(json!({ "type": "object" })),
+            }
+        }),
+    );
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "rust-lib embedded API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "The engine of the C++ app as JSON over HTTP. Plain HTTP \
+                            without authentication, for trusted networks.",
+        },
+        "paths": paths,
+        "components": { "schemas": schemas() },
+    })
+}
+
+fn schema(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{name}") })
+}
+
+fn body(name: &str) -> Value {
+    json!({
+        "required": true,
+        "content": { "application/json": { "schema": schema(name) } },
+    })
+}
+
+fn responses(name: &str) -> Value {
+    responses_with(schema(name))
+}
+
+/// `ok` for 200, and the error answers every endpoint can give
+fn responses_with(ok: Value) -> Value {
+    let error = |description: &str| {
+        json!({
+            "description": description,
+            "content": { "application/json": { "schema": schema("Error") } },
+        })
+    };
+    json!({
+        "200": {
+            "description": "Success",
+            "content": { "application/json": { "schema": ok } },
+        },
+        "400": error("Malformed request or JSON"),
+        "429": error("Rate limit of the endpoint reached"),
+        "503": error("Too many requests in flight"),
+    })
+}
+
+fn schemas() -> Value {
+    let string = json!({ "type": "string" });
+    let number = json!({ "type": "number", "format": "double" });
+    let count = json!({ "type": "integer", "format": "int32", "minimum": 0 });
+    let snapshot = json!({
+        "name": string,
+        "age": count,
+        "height_m": number,
+        "email": string,
+        "phone": string,
+        "street": string,
+        "city": string,
+        "postal_code": string,
+    });
+    let mut analyze = snapshot.clone();
+    analyze["weight_kg"] = number.clone();
+
+    json!({
+        "PersonSnapshot": object(snapshot),
+        "AnalyzeRequest": object(analyze),
+        "HealthAnalysis": object(json!({
+            "bmi": number,
+            "risk_score": number,
+            "recommendation": string,
+            "city_risk_factor": number,
+            "extra_risk_factor": number,
+            "risk_factors": count,
+        })),
+        "ValidationReport": object(json!({
+            "valid": { "type": "boolean" },
+            "issues": {
+                "type": "array",
+                "items": object(json!({
+                    "field": string,
+                    "message": string,
+                    "severity": { "type": "string", "enum": ["error", "warning"] },
+                })),
+            },
+        })),
+        "RegistryEntry": object(json!({
+            "id": { "type": "integer", "format": "int64" },
+            "person": schema("PersonSnapshot"),
+        })),
+        "RegisteredId": object(json!({ "id": { "type": "integer", "format": "int64" } })),
+        "Error": object(json!({ "error": string })),
+    })
+}
+
+/// An object schema in which every property is required
+fn object(properties: Value) -> Value {
+    let required: Vec<&String> = properties
+        .as_object()
+        .map_or(Vec::new(), |p| p.keys().collect());
+    json!({ "type": "object", "required": required, "properties": properties })
+}
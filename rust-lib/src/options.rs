@@ -0,0 +1,165 @@
+//! Rust-side helpers for `AnalysisOptions`
+//!
+//! The bridge struct carries every knob of the extended entry points, so a
+//! new one is a new field rather than a new parameter on each of them.
+//! Rust callers build it with `AnalysisOptions::builder()`; C++ callers
+//! value-initialize it (`AnalysisOptions options{};`, as a plain
+//! declaration leaves `timeout_ms` undefined) and set the fields they need.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::ffi;
+use crate::runtime_options;
+
+/// Pounds to kilograms, exactly by the international definition
+const KG_PER_POUND: f64 = 0.453_592_37;
+
+impl ffi::AnalysisOptions {
+    /// Options with every knob at its default, to adjust from there
+    pub fn builder() -> AnalysisOptionsBuilder {
+        AnalysisOptionsBuilder::default()
+    }
+
+    /// Absolute deadline for work starting now, if a timeout is set
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        (self.timeout_ms > 0).then(|| Instant::now() + Duration::from_millis(self.timeout_ms))
+    }
+
+    /// Reject options no analysis can honor, before any work starts
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        if !self.locale.is_empty() && !runtime_options::is_locale(&self.locale) {
+            return Err(Error::InvalidArgument(format!(
+                "locale {:?}: expected a locale name such as \"de-CH\"",
+                self.locale
+            )));
+        }
+        // Only the configured model exists so far; the field reserves the
+        // choice for models loaded next to it
+        if !matches!(self.model.as_str(), "" | "default") {
+            return Err(Error::InvalidArgument(format!(
+                "unknown model {:?}; expected \"default\"",
+                self.model
+            )));
+        }
+        Ok(())
+    }
+
+    /// The locale of the recommendations
+    pub(crate) fn locale(&self) -> Arc<str> {
+        if self.locale.is_empty() {
+            runtime_options::locale()
+        } else {
+            Arc::from(self.locale.as_str())
+        }
+    }
+
+    /// The locale of the recommendations, or None when they are left to
+    /// render_recommendation
+    pub(crate) fn recommendation_locale(&self) -> Option<Arc<str>> {
+        (!self.lazy_recommendations).then(|| self.locale())
+    }
+
+    /// A weight given in `units`, in kilograms
+    pub(crate) fn weight_kg(&self, weight: f64) -> f64 {
+        match self.units {
+            ffi::Units::Imperial => weight * KG_PER_POUND,
+            _ => weight,
+        }
+    }
+}
+
+/// Builds `AnalysisOptions` one knob at a time
+#[derive(Clone, Debug, Default)]
+#[must_use = "call build() for the options"]
+pub struct AnalysisOptionsBuilder {
+    options: ffi::AnalysisOptions,
+}
+
+impl AnalysisOptionsBuilder {
+    /// The unit of the weights passed in; kilograms by default
+    pub fn units(mut self, units: ffi::Units) -> Self {
+        self.options.units = units;
+        self
+    }
+
+    /// The language of the recommendations; the locale option by default
+    pub fn locale(mut self, locale: &str) -> Self {
+        self.options.locale = locale.to_string();
+        self
+    }
+
+    /// Stop a batch after `timeout`, rounded down to milliseconds; no
+    /// deadline by default
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout_ms = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+        self
+    }
+
+    /// The risk model to analyze with; the configured one by default
+    pub fn model(mut self, model: &str) -> Self {
+        self.options.model = model.to_string();
+        self
+    }
+
+    /// Whether to explain each risk score; off by default
+    pub fn include_explanations(mut self, include: bool) -> Self {
+        self.options.include_explanations = include;
+        self
+    }
+
+    /// Whether to leave the recommendations to render_recommendation;
+    /// off by default
+    pub fn lazy_recommendations(mut self, lazy: bool) -> Self {
+        self.options.lazy_recommendations = lazy;
+        self
+    }
+
+    /// The options as set
+    pub fn build(self) -> ffi::AnalysisOptions {
+        self.options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let options = ffi::AnalysisOptions::builder()
+            .units(ffi::Units::Imperial)
+            .locale("de-CH")
+            .timeout(Duration::from_secs(2))
+            .include_explanations(true)
+            .lazy_recommendations(true)
+            .build();
+        assert_eq!(options.units, ffi::Units::Imperial);
+        assert_eq!(options.timeout_ms, 2000);
+        assert!(options.include_explanations);
+        assert!(options.lazy_recommendations);
+        assert_eq!(&*options.locale(), "de-CH");
+        assert!(options.check().is_ok());
+        assert!((options.weight_kg(154.0) - 69.853).abs() < 1e-3);
+
+        let defaults = ffi::AnalysisOptions::builder().build();
+        assert_eq!(defaults.units, ffi::Units::Metric);
+        assert_eq!(defaults.weight_kg(70.0), 70.0);
+        assert_eq!(defaults.deadline(), None);
+        assert!(defaults.check().is_ok());
+    }
+
+    #[test]
+    fn test_check_rejects_unknown_choices() {
+        let model = ffi::AnalysisOptions::builder().model("neural").build();
+        assert!(matches!(model.check(), Err(Error::InvalidArgument(_))));
+        assert!(ffi::AnalysisOptions::builder()
+            .model("default")
+            .build()
+            .check()
+            .is_ok());
+        let locale = ffi::AnalysisOptions::builder().locale("de CH").build();
+        assert!(locale.check().is_err());
+    }
+}
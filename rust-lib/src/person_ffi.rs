@@ -0,0 +1,620 @@
+//! Bridge for the C++ Person model and the registry built around it
+//!
+//! Declares the opaque C++ types from person.h and everything that works on
+//! people as such: snapshots, the registry, ingest, filtering and the event
+//! bus. health_ffi and validation_ffi refer back to the types declared here.
+
+#[cxx::bridge]
+mod bridge {
+    // ============================================================================
+    // OPAQUE C++ TYPES - Defined in C++ code (person.h)
+    // Rust can hold references but cannot see inside these types
+    // With `stub-cpp`, Rust structs stand in for them (see stub_cpp.rs)
+    // ============================================================================
+    #[cfg(not(feature = "stub-cpp"))]
+    unsafe extern "C++" {
+        include!("cpp-app/person.h");
+
+        // Opaque C++ types - these are existing C++ classes
+        // Rust cannot see inside these types, only hold references
+        type Person;
+        type ContactInfo;
+        type Address;
+
+        // Getter functions to access C++ object data from Rust
+        // These are the bridge between opaque C++ types and Rust
+        // @generated begin: cargo xtask codegen (cpp-app/person.schema.yaml)
+        fn get_person_age(person: &Person) -> u32;
+        fn get_person_height(person: &Person) -> f64;
+        fn get_person_name(person: &Person) -> &CxxString;
+        fn get_person_contact(person: &Person) -> &ContactInfo;
+
+        fn get_contact_email(contact: &ContactInfo) -> &CxxString;
+        fn get_contact_phone(contact: &ContactInfo) -> &CxxString;
+        fn get_contact_address(contact: &ContactInfo) -> &Address;
+
+        fn get_address_street(address: &Address) -> &CxxString;
+        fn get_address_city(address: &Address) -> &CxxString;
+        fn get_address_postal_code(address: &Address) -> &CxxString;
+        // @generated end
+    }
+
+    // Every field of a Person in one crossing (person_snapshot.h), compiled
+    // with the glue so that it also serves a prebuilt libperson
+    #[cfg(not(feature = "stub-cpp"))]
+    unsafe extern "C++" {
+        include!("cpp-app/person_snapshot.h");
+
+        fn fill_person_snapshot(person: &Person, snapshot: &mut PersonSnapshot);
+    }
+
+    // Owning pointers to the C++ types, for other bridges that return them
+    // (the fixtures of the integration tests)
+    #[cfg(not(feature = "stub-cpp"))]
+    impl UniquePtr<Person> {}
+    #[cfg(not(feature = "stub-cpp"))]
+    impl UniquePtr<ContactInfo> {}
+    #[cfg(not(feature = "stub-cpp"))]
+    impl UniquePtr<Address> {}
+
+    // ============================================================================
+    // C++ CALLBACKS - Defined in C++ code (callbacks.h)
+    // Rust holds a reference and calls back into C++ while it works
+    // ============================================================================
+    #[cfg(not(feature = "stub-cpp"))]
+    unsafe extern "C++" {
+        include!("cpp-app/callbacks.h");
+
+        type ResultSink;
+
+        fn on_person_info(self: &ResultSink, info: &PersonInfo);
+
+        type PersonPredicate;
+
+        fn matches(self: &PersonPredicate, person: &PersonSnapshot) -> bool;
+
+        type EventSubscriber;
+
+        fn on_event(self: &EventSubscriber, event: &BusEvent);
+    }
+
+    // ============================================================================
+    // BRIDGE STRUCTS - For data exchange between Rust and C++
+    // These are new structs created specifically for passing results
+    // ============================================================================
+
+    /// Result struct with computed information from Rust
+    /// This is NOT a C++ type - it's a bridge type for communication
+    #[derive(Clone, Debug)]
+    struct PersonInfo {
+        is_adult: bool,
+        bmi_category: u8,      // 0=underweight, 1=normal, 2=overweight
+        name_length: usize,    // UTF-8 bytes
+        name_graphemes: usize, // Characters as displayed (graphemes.rs)
+        city: String,          // Extracted from nested C++ structs
+    }
+
+    /// Plain copy of every field of a C++ Person
+    /// Unlike the opaque Person, a snapshot can be stored, batched and sent
+    /// across threads on the Rust side
+    /// Serializable with the `serde` feature (see snapshot_serde.rs)
+    #[derive(Clone, Debug, Default)]
+    struct PersonSnapshot {
+        name: String,
+        age: u32,
+        height_m: f64,
+        email: String,
+        phone: String,
+        street: String,
+        city: String,
+        postal_code: String,
+    }
+
+    /// A position in decimal degrees (WGS 84)
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
+    struct GeoPoint {
+        lat: f64,
+        lon: f64,
+    }
+
+    /// The likeliest country of a contact (see infer_country)
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct CountryGuess {
+        /// ISO 3166-1 alpha-2 ("US"); "" when nothing hints at one
+        code: String,
+        /// From 0 (no evidence) to 1 (phone and postal code agree)
+        confidence: f64,
+    }
+
+    /// People of a cohort in one region (see region_counts)
+    #[derive(Clone, Debug, Default)]
+    struct RegionCount {
+        /// As postal_region returns it; "" for no region
+        region: String,
+        people: usize,
+    }
+
+    /// Registered persons at one address (see group_households)
+    #[cfg(feature = "registry")]
+    #[derive(Clone, Debug, Default)]
+    struct Household {
+        /// The normalized address: "12 main street, boston, 02101"
+        address_key: String,
+        member_ids: Vec<u64>,
+    }
+
+    /// One read of personal data, recorded while auditing is enabled
+    #[derive(Clone, Debug, Default)]
+    struct AuditEntry {
+        /// The reading thread's set_audit_caller tag; empty if never set
+        caller: String,
+        /// Milliseconds since the Unix epoch
+        timestamp_ms: u64,
+        /// The function that read, such as "snapshot_person"
+        operation: String,
+        /// The registry id read by, or 0
+        person_id: u64,
+        /// Names of the fields read, as in PersonSnapshot
+        fields: Vec<String>,
+    }
+
+    /// What a registered person agreed to (see consent.rs); value-initialized
+    /// flags are no consent at all
+    #[cfg(feature = "registry")]
+    #[derive(Clone, Debug, Default)]
+    struct ConsentFlags {
+        /// Health analysis by analyze_registered
+        analysis: bool,
+        /// File exports by export_registered
+        exports: bool,
+        /// Their analyses reaching the webhook and remote sync
+        sharing: bool,
+    }
+
+    /// What erase_subject deleted for one person id
+    #[cfg(feature = "registry")]
+    #[derive(Clone, Debug, Default)]
+    struct EraseReport {
+        /// The registered snapshot
+        registry_entry: bool,
+        /// The record that the C++ Person was destroyed
+        destroyed_record: bool,
+        /// The consent recorded with set_consent
+        consent_record: bool,
+        /// Re-validation alerts not yet polled
+        validation_alerts: usize,
+        /// Analyses cached for the registered snapshot
+        cached_analyses: usize,
+    }
+
+    /// What a retention sweep deleted, or would delete for a preview
+    #[cfg(feature = "registry")]
+    #[derive(Clone, Debug, Default)]
+    struct RetentionReport {
+        /// Registered persons not updated within the retention period,
+        /// by id; their consent goes with them
+        expired_persons: Vec<u64>,
+        /// Ids whose C++ Person was destroyed before the period
+        destroyed_records: Vec<u64>,
+        /// Re-validation alerts of those ids not yet polled
+        validation_alerts: usize,
+        /// Analyses cached before the period or for an expired person
+        cached_analyses: usize,
+    }
+
+    /// How the `_with_options` comparisons match two people
+    #[derive(Clone, Debug, Default)]
+    struct MatchOptions {
+        /// Ignore case, like the `fold_case` argument of person_equals
+        fold_case: bool,
+        /// Compare Cyrillic and Greek text by its Latin transliteration
+        /// (see transliterate_name), so "Иван" matches "Ivan"
+        transliterate: bool,
+    }
+
+    /// How redact_snapshot treats a snapshot's name, email and phone
+    enum RedactionPolicy {
+        /// Leave them as they are
+        Keep,
+        /// Initials, the email's domain and the phone's last two digits
+        Mask,
+        /// "[redacted]" in place of each
+        Remove,
+    }
+
+    /// Kinds of event published on the event bus
+    enum EventKind {
+        PersonRegistered,
+        AnalysisCompleted,
+        ValidationFailed,
+        ConfigReloaded,
+    }
+
+    /// One event bus message
+    /// Fields that do not apply to `kind` are 0 / empty; `person_id` is 0
+    /// when the event is not tied to a registered person
+    #[derive(Clone)]
+    struct BusEvent {
+        kind: EventKind,
+        person_id: u64,
+        risk_score: f64,
+        detail: String,
+    }
+
+    // ============================================================================
+    // RUST FUNCTIONS - New functionality exposed to C++
+    // These work with opaque C++ types and return bridge structs
+    // ============================================================================
+    extern "Rust" {
+        /// Process a C++ Person object and return computed information
+        /// Demonstrates: Rust receiving opaque C++ type and extracting data via getters
+        #[cfg(not(feature = "stub-cpp"))]
+        fn process_person(person: &Person) -> PersonInfo;
+
+        /// Simple greeting function
+        fn greet_person(name: &str) -> usize;
+
+        /// The greeting for a UTF-16 name; returns its length in code units
+        fn greet_person_utf16(name: &[u16]) -> usize;
+
+        /// Copy of a snapshot with its name, email and phone redacted, for
+        /// logs and exports; other values than the enum's redact as Remove
+        fn redact_snapshot(snapshot: &PersonSnapshot, policy: RedactionPolicy) -> PersonSnapshot;
+
+        /// Redact the names the library prints (greet_person) by `policy`;
+        /// Mask until this is called
+        fn set_log_redaction(policy: RedactionPolicy);
+
+        /// Secret key of anonymous_id, at least 16 bytes; keep it out of
+        /// the exports it protects
+        fn set_anonymization_key(key: &[u8]) -> Result<()>;
+
+        /// Stable pseudonym of a person for analytics: 64 hex digits of an
+        /// HMAC-SHA-256 of the normalized name and email
+        /// Throws if no anonymization key is set
+        #[cfg(not(feature = "stub-cpp"))]
+        fn anonymous_id(person: &Person) -> Result<String>;
+
+        /// anonymous_id of a snapshot
+        fn anonymous_snapshot_id(snapshot: &PersonSnapshot) -> Result<String>;
+
+        /// The person's name as UTF-16 (invalid UTF-8 becomes U+FFFD)
+        #[cfg(not(feature = "stub-cpp"))]
+        fn person_name_utf16(person: &Person) -> Vec<u16>;
+
+        /// Copy all fields of a C++ Person into a snapshot
+        #[cfg(not(feature = "stub-cpp"))]
+        fn snapshot_person(person: &Person) -> PersonSnapshot;
+
+        /// snapshot_person for strings in a legacy encoding such as "ISO-8859-1"
+        /// Throws for an unknown encoding or, with "UTF-8", invalid bytes
+        #[cfg(not(feature = "stub-cpp"))]
+        fn snapshot_person_with_encoding(person: &Person, encoding: &str)
+            -> Result<PersonSnapshot>;
+
+        /// `bytes` decoded from `encoding`: "ISO-8859-1", "ISO-8859-15",
+        /// "windows-1252" or "UTF-8" (case and punctuation are ignored)
+        fn decode_legacy(bytes: &[u8], encoding: &str) -> Result<String>;
+
+        /// Same age and height, and every text field equal after Unicode
+        /// NFC normalization (and case folding when `fold_case`), so a
+        /// precomposed "é" matches "e" plus a combining accent
+        fn person_equals(a: &PersonSnapshot, b: &PersonSnapshot, fold_case: bool) -> bool;
+
+        /// Hash consistent with person_equals for the same `fold_case`;
+        /// identical across runs and platforms, so hosts may store it
+        fn hash_person(person: &PersonSnapshot, fold_case: bool) -> u64;
+
+        /// person_equals with the matching rules spelled out in `options`
+        fn person_equals_with_options(
+            a: &PersonSnapshot,
+            b: &PersonSnapshot,
+            options: &MatchOptions,
+        ) -> bool;
+
+        /// Hash consistent with person_equals_with_options for the same options
+        fn hash_person_with_options(person: &PersonSnapshot, options: &MatchOptions) -> u64;
+
+        /// Cyrillic and Greek letters replaced by Latin ones ("Ελένη" -> "Eleni")
+        /// Other scripts are unchanged; the result is NFC-normalized
+        fn transliterate_name(name: &str) -> String;
+
+        // Registry - only with the `registry` feature
+
+        /// Store a snapshot in the registry and return its id
+        #[cfg(feature = "registry")]
+        fn register_person(snapshot: PersonSnapshot) -> u64;
+
+        /// Like register_person, but returns the existing id when an equal
+        /// person (see person_equals) is already registered
+        #[cfg(feature = "registry")]
+        fn register_person_unique(snapshot: PersonSnapshot, fold_case: bool) -> u64;
+
+        /// register_person_unique comparing as person_equals_with_options
+        #[cfg(feature = "registry")]
+        fn register_person_unique_with_options(
+            snapshot: PersonSnapshot,
+            options: &MatchOptions,
+        ) -> u64;
+
+        /// Replace the stored snapshot for a registered id
+        #[cfg(feature = "registry")]
+        fn update_registered_person(id: u64, snapshot: PersonSnapshot) -> Result<()>;
+
+        /// Remove a person from the registry; false if the id was unknown
+        #[cfg(feature = "registry")]
+        fn unregister_person(id: u64) -> bool;
+
+        /// The C++ Person registered as `id` was destroyed
+        /// Removes the entry; later lookups of the id throw "has been
+        /// destroyed". Returns false if the id was not registered.
+        #[cfg(feature = "registry")]
+        fn notify_person_dropped(id: u64) -> bool;
+
+        /// Stored snapshot for a registered id
+        #[cfg(feature = "registry")]
+        fn get_registered_person(id: u64) -> Result<PersonSnapshot>;
+
+        /// Number of persons currently registered
+        #[cfg(feature = "registry")]
+        fn registered_count() -> usize;
+
+        /// Registered persons grouped by normalized address (see
+        /// address_similarity), sorted by address; persons without a
+        /// street and a city or postal code are left out
+        #[cfg(feature = "registry")]
+        fn group_households() -> Vec<Household>;
+
+        /// Record every read of personal data from now on (see audit.rs);
+        /// off initially
+        fn set_audit_enabled(enabled: bool);
+
+        /// Tag the calling thread's later reads with `caller` in the audit log
+        fn set_audit_caller(caller: &str);
+
+        /// Audit entries recorded since the last drain, oldest first
+        fn drain_audit_log() -> Vec<AuditEntry>;
+
+        /// Record what a registered person consents to, replacing the record
+        /// before; throws for an id that is not registered
+        #[cfg(feature = "registry")]
+        fn set_consent(person_id: u64, flags: ConsentFlags) -> Result<()>;
+
+        /// What a registered person consents to; no flags unless recorded
+        #[cfg(feature = "registry")]
+        fn get_consent(person_id: u64) -> ConsentFlags;
+
+        /// Everything the library holds about a registered person id, as a
+        /// JSON document: the snapshot, validation state and cached analyses
+        /// (see subject_rights.rs); for data access requests
+        #[cfg(all(feature = "registry", feature = "serde"))]
+        fn export_subject_data(person_id: u64) -> String;
+
+        /// Delete everything export_subject_data reports for an id; the id is
+        /// unknown afterwards
+        #[cfg(feature = "registry")]
+        fn erase_subject(person_id: u64) -> EraseReport;
+
+        /// Keep registered persons, destroyed-person records and cached
+        /// analyses for `days` after their last update, sweeping hourly on a
+        /// background thread; 0 keeps everything (see retention.rs)
+        /// Throws, leaving no policy, if the thread cannot be started
+        #[cfg(feature = "registry")]
+        fn set_retention_policy(days: u32) -> Result<()>;
+
+        /// Sweep now under the policy; empty without one
+        #[cfg(feature = "registry")]
+        fn enforce_retention() -> RetentionReport;
+
+        /// What a sweep with a policy of `days` would delete now, deleting
+        /// nothing; for operators to check a policy before setting it
+        #[cfg(feature = "registry")]
+        fn preview_retention(days: u32) -> RetentionReport;
+
+        /// Start the ingest consumer thread with a bounded queue
+        /// Each queued snapshot is processed and handed to `sink` on that thread
+        #[cfg(not(feature = "stub-cpp"))]
+        fn start_ingest(capacity: usize, sink: UniquePtr<ResultSink>) -> Result<()>;
+
+        /// Queue a snapshot for the ingest consumer without blocking
+        /// Returns false when the queue is full (backpressure) or not started
+        fn enqueue_person(snapshot: PersonSnapshot) -> bool;
+
+        /// Stop accepting snapshots, drain the queue and join the consumer
+        fn stop_ingest();
+
+        /// Coordinates of an address from the current geocoder, by default
+        /// a built-in table of city centres
+        /// Throws if the geocoder has no coordinates for it
+        #[cfg(not(feature = "stub-cpp"))]
+        fn geocode_address(address: &Address) -> Result<GeoPoint>;
+
+        /// Great-circle distance in kilometres between two addresses,
+        /// geocoded as by geocode_address
+        /// Throws if the geocoder has no coordinates for either
+        #[cfg(not(feature = "stub-cpp"))]
+        fn distance_km(a: &Address, b: &Address) -> Result<f64>;
+
+        /// How alike two addresses are, from 0 to 1, comparing normalized
+        /// tokens of street, city and postal code; for deduplication and
+        /// household grouping
+        #[cfg(not(feature = "stub-cpp"))]
+        fn address_similarity(a: &Address, b: &Address) -> f64;
+
+        /// IANA time zone ("America/New_York") of an address, from the
+        /// reference city nearest to its geocoded position
+        /// Throws if it cannot be geocoded or no reference city is near
+        #[cfg(not(feature = "stub-cpp"))]
+        fn timezone_for_address(address: &Address) -> Result<String>;
+
+        /// Replace the service areas with the features of a GeoJSON
+        /// FeatureCollection, each a Polygon or MultiPolygon with an id;
+        /// `source` is GeoJSON text if it starts with '{', else a file path
+        /// Throws, keeping the areas in effect, if it is unreadable or
+        /// invalid
+        fn load_service_areas(source: &str) -> Result<()>;
+
+        /// Whether an address, geocoded as by geocode_address, lies in the
+        /// loaded service area `area_id`
+        /// Throws if the area is unknown or the address cannot be geocoded
+        #[cfg(not(feature = "stub-cpp"))]
+        fn is_in_service_area(address: &Address, area_id: &str) -> Result<bool>;
+
+        /// State or province of a postal code in `country` ("US" or "CA"),
+        /// as an ISO 3166-2 code without the country ("MA", "ON")
+        /// Empty for other countries and codes the built-in table lacks
+        fn postal_region(code: &str, country: &str) -> String;
+
+        /// Country of a contact from its phone number's country code and
+        /// its postal code's format, for a model without a country field
+        #[cfg(not(feature = "stub-cpp"))]
+        fn infer_country(contact: &ContactInfo) -> CountryGuess;
+
+        /// How many of `people` live in each region of `country`, by
+        /// postal_region and sorted by it; "" counts those without one
+        /// An empty `country` is inferred for each person as by
+        /// infer_country
+        fn region_counts(people: &[PersonSnapshot], country: &str) -> Vec<RegionCount>;
+
+        /// Geocode with the built-in city table again
+        fn use_offline_geocoder();
+
+        /// Geocode through the Nominatim server at `base_url`, over http or
+        /// https ("http://localhost:8088" or
+        /// "https://nominatim.openstreetmap.org", say)
+        #[cfg(feature = "nominatim")]
+        fn use_nominatim_geocoder(base_url: &str) -> Result<()>;
+
+        /// Serve /analyze, /validate and /registry as JSON on `addr`
+        /// ("0.0.0.0:8080", say) from background threads
+        /// Throws if the address is invalid or taken, or a server is
+        /// already running
+        #[cfg(feature = "http")]
+        fn start_http_server(addr: &str) -> Result<()>;
+
+        /// Stop accepting connections and join the server's accept thread
+        #[cfg(feature = "http")]
+        fn stop_http_server();
+
+        /// Cap the requests the HTTP and gRPC servers serve at once, over
+        /// all endpoints; 0 removes the cap
+        fn set_service_max_in_flight(max: usize);
+
+        /// Limit `endpoint` ("/analyze", say) to `per_second` requests in
+        /// bursts of up to `burst`, and to `max_in_flight` at once; zeros
+        /// remove a limit. Refused requests get 429 or 503
+        fn set_endpoint_limits(
+            endpoint: &str,
+            per_second: f64,
+            burst: u32,
+            max_in_flight: usize,
+        ) -> Result<()>;
+
+        /// Remove every service limit
+        fn clear_service_limits();
+
+        /// Serve the engine's Prometheus metrics as GET /metrics on `addr`
+        /// ("0.0.0.0:9100", say); throws if the address is invalid or
+        /// taken, or the endpoint is already running
+        #[cfg(feature = "metrics")]
+        fn start_metrics_endpoint(addr: &str) -> Result<()>;
+
+        /// Stop serving /metrics; the counters keep counting
+        #[cfg(feature = "metrics")]
+        fn stop_metrics_endpoint();
+
+        /// The metrics in the Prometheus text format, for hosts that
+        /// export them through their own endpoint
+        #[cfg(feature = "metrics")]
+        fn metrics_text() -> String;
+
+        /// The people for whom `predicate` returns true, in input order
+        /// Large inputs are tested in parallel, so the predicate may run
+        /// on several threads at once
+        #[cfg(not(feature = "stub-cpp"))]
+        fn filter_people(
+            people: &[PersonSnapshot],
+            predicate: &PersonPredicate,
+        ) -> Vec<PersonSnapshot>;
+
+        /// Receive every event of `kind` on the publishing thread
+        /// Returns a subscription id for unsubscribe
+        #[cfg(not(feature = "stub-cpp"))]
+        fn subscribe(kind: EventKind, subscriber: UniquePtr<EventSubscriber>) -> u64;
+
+        /// Cancel a subscription; false if the id was unknown
+        fn unsubscribe(id: u64) -> bool;
+
+        /// True when every bridge function may be called from any thread
+        fn is_thread_safe() -> bool;
+
+        /// Throw, listing them, if any id the bridge handed out is still open:
+        /// uncollected jobs, sessions, registry entries, cancel tokens,
+        /// subscriptions and risk observers. Never throws without the
+        /// `leak-check` feature; not cfg-gated because cxx-build does not
+        /// match hyphenated feature names when generating the header
+        fn assert_no_leaked_handles() -> Result<()>;
+    }
+}
+
+pub use bridge::*;
+
+#[cfg(not(feature = "stub-cpp"))]
+use crate::address_similarity::address_similarity;
+#[cfg(not(feature = "stub-cpp"))]
+use crate::anonymize::anonymous_id;
+use crate::anonymize::{anonymous_snapshot_id, set_anonymization_key};
+use crate::audit::{drain_audit_log, set_audit_caller, set_audit_enabled};
+#[cfg(not(feature = "stub-cpp"))]
+use crate::country::infer_country;
+use crate::encoding::decode_legacy;
+#[cfg(not(feature = "stub-cpp"))]
+use crate::events::subscribe;
+use crate::events::unsubscribe;
+#[cfg(not(feature = "stub-cpp"))]
+use crate::filter::filter_people;
+#[cfg(not(feature = "stub-cpp"))]
+use crate::geocode::{distance_km, geocode_address};
+use crate::geocode::use_offline_geocoder;
+#[cfg(feature = "registry")]
+use crate::consent::{get_consent, set_consent};
+#[cfg(feature = "registry")]
+use crate::households::group_households;
+#[cfg(feature = "http")]
+use crate::http::{start_http_server, stop_http_server};
+#[cfg(not(feature = "stub-cpp"))]
+use crate::ingest::start_ingest;
+use crate::ingest::{enqueue_person, stop_ingest};
+use crate::leak_check::assert_no_leaked_handles;
+#[cfg(feature = "metrics")]
+use crate::metrics::{metrics_text, start_metrics_endpoint, stop_metrics_endpoint};
+#[cfg(feature = "nominatim")]
+use crate::nominatim::use_nominatim_geocoder;
+use crate::redaction::{redact_snapshot, set_log_redaction};
+use crate::regions::{postal_region, region_counts};
+use crate::normalize::{
+    hash_person, hash_person_with_options, person_equals, person_equals_with_options,
+};
+#[cfg(feature = "registry")]
+use crate::registry::{
+    get_registered_person, notify_person_dropped, register_person, register_person_unique,
+    register_person_unique_with_options, registered_count, unregister_person,
+    update_registered_person,
+};
+#[cfg(feature = "registry")]
+use crate::retention::{enforce_retention, preview_retention, set_retention_policy};
+use crate::service_areas::load_service_areas;
+#[cfg(feature = "registry")]
+use crate::subject_rights::erase_subject;
+#[cfg(all(feature = "registry", feature = "serde"))]
+use crate::subject_rights::export_subject_data;
+#[cfg(not(feature = "stub-cpp"))]
+use crate::service_areas::is_in_service_area;
+use crate::service_limits::{clear_service_limits, set_endpoint_limits, set_service_max_in_flight};
+use crate::state::is_thread_safe;
+#[cfg(not(feature = "stub-cpp"))]
+use crate::timezone::timezone_for_address;
+use crate::transliterate::transliterate_name;
+use crate::{greet_person, greet_person_utf16};
+#[cfg(not(feature = "stub-cpp"))]
+use crate::{person_name_utf16, process_person, snapshot_person, snapshot_person_with_encoding};
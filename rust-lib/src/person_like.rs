@@ -0,0 +1,213 @@
+//! Read access to a person, whoever owns it
+//!
+//! The analysis and validation code is generic over `PersonLike`, so the
+//! same logic runs on a C++ Person (through `CxxPerson`), on a Rust-owned
+//! `PersonSnapshot`, and in unit tests on a `MockPerson` that needs no C++
+//! object at all.
+
+use crate::ffi;
+
+pub(crate) trait PersonLike {
+    fn age(&self) -> u32;
+    fn height_m(&self) -> f64;
+    fn name(&self) -> &str;
+    fn email(&self) -> &str;
+    fn phone(&self) -> &str;
+    fn street(&self) -> &str;
+    fn city(&self) -> &str;
+    fn postal_code(&self) -> &str;
+
+    /// Owned copy of every field, e.g. for the C++ RiskFactorHook
+    fn to_snapshot(&self) -> ffi::PersonSnapshot {
+        ffi::PersonSnapshot {
+            name: self.name().to_string(),
+            age: self.age(),
+            height_m: self.height_m(),
+            email: self.email().to_string(),
+            phone: self.phone().to_string(),
+            street: self.street().to_string(),
+            city: self.city().to_string(),
+            postal_code: self.postal_code().to_string(),
+        }
+    }
+}
+
+/// A C++ Person read through the getter shims
+///
+/// Strings that are not valid UTF-8 read as empty; `snapshot_person` is the
+/// lossy alternative.
+pub(crate) struct CxxPerson<'a> {
+    person: &'a ffi::Person,
+    contact: &'a ffi::ContactInfo,
+    address: &'a ffi::Address,
+}
+
+impl<'a> CxxPerson<'a> {
+    pub(crate) fn new(person: &'a ffi::Person) -> Self {
+        let contact = ffi::get_person_contact(person);
+        CxxPerson {
+            person,
+            contact,
+            address: ffi::get_contact_address(contact),
+        }
+    }
+}
+
+impl PersonLike for CxxPerson<'_> {
+    fn age(&self) -> u32 {
+        ffi::get_person_age(self.person)
+    }
+
+    fn height_m(&self) -> f64 {
+        ffi::get_person_height(self.person)
+    }
+
+    fn name(&self) -> &str {
+        ffi::get_person_name(self.person).to_str().unwrap_or("")
+    }
+
+    fn email(&self) -> &str {
+        ffi::get_contact_email(self.contact).to_str().unwrap_or("")
+    }
+
+    fn phone(&self) -> &str {
+        ffi::get_contact_phone(self.contact).to_str().unwrap_or("")
+    }
+
+    fn street(&self) -> &str {
+        ffi::get_address_street(self.address).to_str().unwrap_or("")
+    }
+
+    fn city(&self) -> &str {
+        ffi::get_address_city(self.address).to_str().unwrap_or("")
+    }
+
+    fn postal_code(&self) -> &str {
+        ffi::get_address_postal_code(self.address)
+            .to_str()
+            .unwrap_or("")
+    }
+}
+
+impl PersonLike for ffi::PersonSnapshot {
+    fn age(&self) -> u32 {
+        self.age
+    }
+
+    fn height_m(&self) -> f64 {
+        self.height_m
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn email(&self) -> &str {
+        &self.email
+    }
+
+    fn phone(&self) -> &str {
+        &self.phone
+    }
+
+    fn street(&self) -> &str {
+        &self.street
+    }
+
+    fn city(&self) -> &str {
+        &self.city
+    }
+
+    fn postal_code(&self) -> &str {
+        &self.postal_code
+    }
+
+    fn to_snapshot(&self) -> ffi::PersonSnapshot {
+        self.clone()
+    }
+}
+
+/// Plain Rust person for unit tests; the default is a valid adult
+#[cfg(test)]
+#[derive(Clone, Debug)]
+pub(crate) struct MockPerson {
+    pub(crate) age: u32,
+    pub(crate) height_m: f64,
+    pub(crate) name: &'static str,
+    pub(crate) email: &'static str,
+    pub(crate) phone: &'static str,
+    pub(crate) street: &'static str,
+    pub(crate) city: &'static str,
+    pub(crate) postal_code: &'static str,
+}
+
+#[cfg(test)]
+impl Default for MockPerson {
+    fn default() -> Self {
+        MockPerson {
+            age: 30,
+            height_m: 1.75,
+            name: "Mock",
+            email: "mock@example.com",
+            phone: "555-0000",
+            street: "1 Mock St",
+            city: "Boston",
+            postal_code: "02101",
+        }
+    }
+}
+
+#[cfg(test)]
+impl PersonLike for MockPerson {
+    fn age(&self) -> u32 {
+        self.age
+    }
+
+    fn height_m(&self) -> f64 {
+        self.height_m
+    }
+
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn email(&self) -> &str {
+        self.email
+    }
+
+    fn phone(&self) -> &str {
+        self.phone
+    }
+
+    fn street(&self) -> &str {
+        self.street
+    }
+
+    fn city(&self) -> &str {
+        self.city
+    }
+
+    fn postal_code(&self) -> &str {
+        self.postal_code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_snapshot_copies_every_field() {
+        let mock = MockPerson {
+            name: "Ann",
+            city: "Denver",
+            ..MockPerson::default()
+        };
+        let snapshot = mock.to_snapshot();
+        assert_eq!(snapshot.name, "Ann");
+        assert_eq!(snapshot.age, 30);
+        assert_eq!(snapshot.city, "Denver");
+        assert_eq!(snapshot.postal_code, "02101");
+        assert_eq!(snapshot.to_snapshot().street, "1 Mock St");
+    }
+}
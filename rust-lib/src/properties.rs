@@ -0,0 +1,158 @@
+//! Property tests for the health math
+//!
+//! Each property is checked on `CASES` generated inputs: boundary values
+//! first (BMI 18.5 / 25, ages 17/18/65/66, zero and tiny heights), then seeded
+//! pseudo-random ones. A failure names the input and the seed; rerun with
+//! `RUST_LIB_PROP_SEED=<seed>` to reproduce it. These guard the formulas
+//! once the risk model becomes configurable.
+
+use crate::person_like::MockPerson;
+use crate::{calculate_bmi, compute_health, compute_person_info};
+
+const CASES: usize = 2000;
+const DEFAULT_SEED: u64 = 0x5eed_cafe_f00d_1234;
+
+/// xorshift64*: small, deterministic and good enough to spread inputs
+struct Rng(u64);
+
+impl Rng {
+    fn from_env() -> (Self, u64) {
+        let seed = std::env::var("RUST_LIB_PROP_SEED")
+            .ok()
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or(DEFAULT_SEED);
+        (Rng(seed.max(1)), seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in [low, high)
+    fn range(&mut self, low: f64, high: f64) -> f64 {
+        low + (self.next() >> 11) as f64 / (1u64 << 53) as f64 * (high - low)
+    }
+
+    fn age(&mut self) -> u32 {
+        (self.next() % 121) as u32
+    }
+}
+
+/// (age, height_m, weight_kg) cases, boundaries first
+fn cases() -> (Vec<(u32, f64, f64)>, u64) {
+    let (mut rng, seed) = Rng::from_env();
+    let mut cases = Vec::with_capacity(CASES);
+    for age in [0, 17, 18, 65, 66, 150] {
+        for height in [0.0, 0.01, 1.0, 1.75, 2.5] {
+            for bmi in [10.0, 18.5, 25.0, 40.0] {
+                cases.push((age, height, bmi * height * height));
+            }
+        }
+    }
+    while cases.len() < CASES {
+        cases.push((rng.age(), rng.range(0.3, 2.6), rng.range(1.0, 300.0)));
+    }
+    (cases, seed)
+}
+
+fn person(age: u32, height_m: f64) -> MockPerson {
+    MockPerson {
+        age,
+        height_m,
+        // Not one of the names the risk hook test reacts to
+        name: "property",
+        ..MockPerson::default()
+    }
+}
+
+#[test]
+fn test_bmi_is_monotonic_in_weight() {
+    let (cases, seed) = cases();
+    for (age, height, weight) in cases {
+        let heavier = weight * 1.01 + 0.1;
+        let person = person(age, height);
+        assert!(
+            compute_health(&person, heavier).bmi >= compute_health(&person, weight).bmi,
+            "seed {seed}: height {height}, weights {weight} and {heavier}"
+        );
+        assert!(calculate_bmi(heavier, height) >= calculate_bmi(weight, height));
+    }
+}
+
+#[test]
+fn test_analysis_bmi_matches_calculate_bmi() {
+    let (cases, seed) = cases();
+    for (age, height, weight) in cases {
+        let analysis = compute_health(&person(age, height), weight);
+        assert_eq!(
+            analysis.bmi,
+            calculate_bmi(weight, height),
+            "seed {seed}: age {age}, height {height}, weight {weight}"
+        );
+    }
+}
+
+#[test]
+fn test_risk_score_is_at_least_one() {
+    let (cases, seed) = cases();
+    for (age, height, weight) in cases {
+        for city in ["Boston", "New York", "Los Angeles"] {
+            let person = MockPerson {
+                city,
+                ..person(age, height)
+            };
+            let analysis = compute_health(&person, weight);
+            assert!(
+                analysis.risk_score >= 1.0 && analysis.risk_score.is_finite(),
+                "seed {seed}: age {age}, height {height}, weight {weight}, {city}: {}",
+                analysis.risk_score
+            );
+        }
+    }
+}
+
+#[test]
+fn test_recommendation_follows_risk_score() {
+    let (cases, seed) = cases();
+    for (age, height, weight) in cases {
+        let analysis = compute_health(&person(age, height), weight);
+        let expected = if analysis.risk_score < 1.2 {
+            "Excellent"
+        } else if analysis.risk_score < 1.5 {
+            "Good"
+        } else {
+            "Elevated"
+        };
+        assert!(
+            analysis.recommendation.starts_with(expected),
+            "seed {seed}: risk {} gave {:?}",
+            analysis.risk_score,
+            analysis.recommendation
+        );
+    }
+}
+
+#[test]
+fn test_bmi_category_matches_calculate_bmi() {
+    // PersonInfo assumes 70 kg
+    let (cases, seed) = cases();
+    for (age, height, _) in cases {
+        let bmi = calculate_bmi(70.0, height);
+        let expected = if bmi < 18.5 {
+            0
+        } else if bmi < 25.0 {
+            1
+        } else {
+            2
+        };
+        let info = compute_person_info(&person(age, height));
+        assert_eq!(
+            info.bmi_category, expected,
+            "seed {seed}: height {height}, BMI {bmi}"
+        );
+        assert_eq!(info.is_adult, age >= 18);
+    }
+}
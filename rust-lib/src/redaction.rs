@@ -0,0 +1,154 @@
+//! Redacting personal data for logs and exports
+//!
+//! `redact_snapshot` masks the name, email and phone number of a snapshot
+//! by a `RedactionPolicy`. `Mask` keeps enough to tell records apart in a
+//! log ("A. S.", "a***@example.com", "***-**04"); `Remove` keeps nothing
+//! of them. Age, height and the address are left as they are, since the
+//! analyses depend on them.
+//!
+//! What the library itself prints goes through the log policy, `Mask`
+//! unless the host calls `set_log_redaction`, so a name never reaches a
+//! log file in clear by default. Exports write what they are given; map
+//! the snapshots through `redact_snapshot` first for a redacted export.
+
+use crate::ffi::{self, RedactionPolicy};
+use crate::state::Global;
+
+/// What takes the place of a removed field
+const REMOVED: &str = "[redacted]";
+
+static LOG_POLICY: Global<RedactionPolicy> = Global::new(|| RedactionPolicy::Mask);
+
+/// A copy of `snapshot` with its name, email and phone redacted by
+/// `policy`; values outside the enum redact as `Remove`
+pub fn redact_snapshot(
+    snapshot: &ffi::PersonSnapshot,
+    policy: RedactionPolicy,
+) -> ffi::PersonSnapshot {
+    ffi::PersonSnapshot {
+        name: redact(&snapshot.name, policy, mask_name),
+        email: redact(&snapshot.email, policy, mask_email),
+        phone: redact(&snapshot.phone, policy, mask_phone),
+        ..snapshot.clone()
+    }
+}
+
+/// Apply `policy` to the names the library logs from now on
+pub(crate) fn set_log_redaction(policy: RedactionPolicy) {
+    *LOG_POLICY.write() = policy;
+}
+
+/// `name` redacted by the log policy, for printing
+pub(crate) fn name_for_log(name: &str) -> String {
+    redact(name, *LOG_POLICY.read(), mask_name)
+}
+
+fn redact(value: &str, policy: RedactionPolicy, mask: fn(&str) -> String) -> String {
+    match policy {
+        RedactionPolicy::Keep => value.to_string(),
+        _ if value.is_empty() => String::new(),
+        RedactionPolicy::Mask => mask(value),
+        _ => REMOVED.to_string(),
+    }
+}
+
+/// The initial of each word: "Alice Smith" is "A. S."
+fn mask_name(name: &str) -> String {
+    let initials: Vec<String> = name
+        .split_whitespace()
+        .filter_map(|word| word.chars().next())
+        .map(|initial| format!("{initial}."))
+        .collect();
+    initials.join(" ")
+}
+
+/// The first character of the local part and the domain:
+/// "alice@example.com" is "a***@example.com"
+fn mask_email(email: &str) -> String {
+    match email.rsplit_once('@') {
+        Some((local, domain)) => {
+            let initial: String = local.chars().take(1).collect();
+            format!("{initial}***@{domain}")
+        }
+        None => "***".to_string(),
+    }
+}
+
+/// Every digit but the last two hidden, the layout kept: "555-0104" is
+/// "***-**04"
+fn mask_phone(phone: &str) -> String {
+    let digits = phone.chars().filter(char::is_ascii_digit).count();
+    let mut seen = 0;
+    phone
+        .chars()
+        .map(|c| {
+            if !c.is_ascii_digit() {
+                return c;
+            }
+            seen += 1;
+            if seen + 2 > digits {
+                c
+            } else {
+                '*'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+
+    fn person() -> ffi::PersonSnapshot {
+        let mut person = snapshot(30, 1.75, "Boston");
+        person.name = "Alice van Dyke".to_string();
+        person.email = "alice.vd@example.com".to_string();
+        person.phone = "+1 (617) 555-0104".to_string();
+        person
+    }
+
+    #[test]
+    fn test_mask_keeps_a_hint() {
+        let masked = redact_snapshot(&person(), RedactionPolicy::Mask);
+        assert_eq!(masked.name, "A. v. D.");
+        assert_eq!(masked.email, "a***@example.com");
+        assert_eq!(masked.phone, "+* (***) ***-**04");
+        // Everything else is untouched
+        assert_eq!(masked.street, "1 Test St");
+        assert_eq!(masked.city, "Boston");
+        assert_eq!(masked.age, 30);
+
+        assert_eq!(mask_name("  Светлана  "), "С.");
+        assert_eq!(mask_email("no-at-sign"), "***");
+        assert_eq!(mask_email("@example.com"), "***@example.com");
+        assert_eq!(mask_phone("04"), "04");
+    }
+
+    #[test]
+    fn test_keep_and_remove() {
+        let original = person();
+        let kept = redact_snapshot(&original, RedactionPolicy::Keep);
+        assert_eq!(kept.name, original.name);
+        assert_eq!(kept.phone, original.phone);
+
+        let mut removed = redact_snapshot(&original, RedactionPolicy::Remove);
+        assert_eq!(
+            [removed.name, removed.email, removed.phone],
+            ["[redacted]"; 3]
+        );
+        // Out-of-range values from C++ are the strictest policy
+        removed = redact_snapshot(&original, RedactionPolicy { repr: 7 });
+        assert_eq!(removed.email, "[redacted]");
+
+        // An empty field stays empty, so missing data still shows
+        let mut empty = original;
+        empty.phone.clear();
+        assert_eq!(redact_snapshot(&empty, RedactionPolicy::Remove).phone, "");
+    }
+
+    #[test]
+    fn test_logs_are_masked_by_default() {
+        assert_eq!(name_for_log("Alice Smith"), "A. S.");
+    }
+}
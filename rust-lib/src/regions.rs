@@ -0,0 +1,253 @@
+//! Regions of postal codes
+//!
+//! `region_for_postal` maps a postal code to the state or province it
+//! belongs to, from a table of postal prefixes compiled into the library:
+//! the first three digits of a US ZIP code, the first letter of a Canadian
+//! postal code. Regions are the ISO 3166-2 subdivision codes without the
+//! country ("MA", "ON"). `region_counts` groups a cohort by region, for
+//! statistics finer than the country and coarser than the city; without a
+//! country it guesses each person's from their phone number and postal
+//! code (see country.rs).
+//!
+//! A prefix says where the post office is, not where the border runs, so
+//! a handful of codes near a state line come out as the neighbouring
+//! state. Military and other non-geographic prefixes have no region.
+
+use std::collections::BTreeMap;
+
+use crate::country::guess_country;
+use crate::ffi;
+
+/// (first ZIP3, last ZIP3, state), sorted and without overlaps
+const US: &[(u16, u16, &str)] = &[
+    (5, 5, "NY"),
+    (6, 9, "PR"),
+    (10, 27, "MA"),
+    (28, 29, "RI"),
+    (30, 38, "NH"),
+    (39, 49, "ME"),
+    (50, 54, "VT"),
+    (55, 55, "MA"),
+    (56, 59, "VT"),
+    (60, 69, "CT"),
+    (70, 89, "NJ"),
+    (100, 149, "NY"),
+    (150, 196, "PA"),
+    (197, 199, "DE"),
+    (200, 200, "DC"),
+    (201, 201, "VA"),
+    (202, 205, "DC"),
+    (206, 219, "MD"),
+    (220, 246, "VA"),
+    (247, 268, "WV"),
+    (270, 289, "NC"),
+    (290, 299, "SC"),
+    (300, 319, "GA"),
+    (320, 339, "FL"),
+    (341, 349, "FL"),
+    (350, 369, "AL"),
+    (370, 385, "TN"),
+    (386, 397, "MS"),
+    (398, 399, "GA"),
+    (400, 427, "KY"),
+    (430, 459, "OH"),
+    (460, 479, "IN"),
+    (480, 499, "MI"),
+    (500, 528, "IA"),
+    (530, 549, "WI"),
+    (550, 567, "MN"),
+    (569, 569, "DC"),
+    (570, 577, "SD"),
+    (580, 588, "ND"),
+    (590, 599, "MT"),
+    (600, 629, "IL"),
+    (630, 658, "MO"),
+    (660, 679, "KS"),
+    (680, 693, "NE"),
+    (700, 714, "LA"),
+    (716, 729, "AR"),
+    (730, 732, "OK"),
+    (733, 733, "TX"),
+    (734, 749, "OK"),
+    (750, 799, "TX"),
+    (800, 816, "CO"),
+    (820, 831, "WY"),
+    (832, 838, "ID"),
+    (840, 847, "UT"),
+    (850, 865, "AZ"),
+    (870, 884, "NM"),
+    (885, 885, "TX"),
+    (889, 898, "NV"),
+    (900, 961, "CA"),
+    (967, 968, "HI"),
+    (969, 969, "GU"),
+    (970, 979, "OR"),
+    (980, 994, "WA"),
+    (995, 999, "AK"),
+];
+
+/// (first letter, province); X is split further in `canadian_region`
+const CA: &[(char, &str)] = &[
+    ('A', "NL"),
+    ('B', "NS"),
+    ('C', "PE"),
+    ('E', "NB"),
+    ('G', "QC"),
+    ('H', "QC"),
+    ('J', "QC"),
+    ('K', "ON"),
+    ('L', "ON"),
+    ('M', "ON"),
+    ('N', "ON"),
+    ('P', "ON"),
+    ('R', "MB"),
+    ('S', "SK"),
+    ('T', "AB"),
+    ('V', "BC"),
+    ('X', "NT"),
+    ('Y', "YT"),
+];
+
+/// The region of `code` in `country` (ISO 3166-1 alpha-2, "US" or "CA";
+/// case is ignored); None for other countries and codes not in the table
+pub fn region_for_postal(code: &str, country: &str) -> Option<String> {
+    let code = code.trim();
+    let region = match country.trim().to_ascii_uppercase().as_str() {
+        "US" => us_region(code),
+        "CA" => canadian_region(code),
+        _ => None,
+    };
+    region.map(str::to_string)
+}
+
+/// ZIP codes are five digits, optionally followed by "-" and four more
+fn us_region(code: &str) -> Option<&'static str> {
+    let (zip, plus_four) = code.split_once('-').unwrap_or((code, "0000"));
+    let digits = |text: &str, len| text.len() == len && text.bytes().all(|b| b.is_ascii_digit());
+    if !digits(zip, 5) || !digits(plus_four, 4) {
+        return None;
+    }
+    let prefix: u16 = zip[..3].parse().ok()?;
+    let index = US.partition_point(|&(_, last, _)| last < prefix);
+    US.get(index)
+        .filter(|&&(first, _, _)| first <= prefix)
+        .map(|&(_, _, state)| state)
+}
+
+/// Canadian postal codes are "A1A 1A1"; only the first two characters are
+/// checked, as the rest does not change the province
+fn canadian_region(code: &str) -> Option<&'static str> {
+    let mut chars = code.chars().map(|c| c.to_ascii_uppercase());
+    let (letter, digit) = (chars.next()?, chars.next()?);
+    if !digit.is_ascii_digit() {
+        return None;
+    }
+    // X0A to X0C is Nunavut, the rest of X the Northwest Territories
+    if letter == 'X' && digit == '0' && matches!(chars.next(), Some('A'..='C')) {
+        return Some("NU");
+    }
+    CA.iter()
+        .find(|&&(first, _)| first == letter)
+        .map(|&(_, province)| province)
+}
+
+/// How many of `people` live in each region of `country`, by region; the
+/// people whose postal code has no region are counted under "". An empty
+/// `country` is guessed for each person
+pub fn region_counts(people: &[ffi::PersonSnapshot], country: &str) -> Vec<ffi::RegionCount> {
+    let mut counts = BTreeMap::new();
+    for person in people {
+        let region = if country.trim().is_empty() {
+            let guess = guess_country(&person.phone, &person.postal_code);
+            region_for_postal(&person.postal_code, &guess.code)
+        } else {
+            region_for_postal(&person.postal_code, country)
+        };
+        let region = region.unwrap_or_default();
+        *counts.entry(region).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(region, people)| ffi::RegionCount { region, people })
+        .collect()
+}
+
+/// region_for_postal for C++, with "" for no region
+pub(crate) fn postal_region(code: &str, country: &str) -> String {
+    region_for_postal(code, country).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+
+    #[test]
+    fn test_us_zip_codes() {
+        let region = |code| region_for_postal(code, "US");
+        assert_eq!(region("02101").as_deref(), Some("MA"));
+        assert_eq!(region("10004-1234").as_deref(), Some("NY"));
+        assert_eq!(region("20500").as_deref(), Some("DC"));
+        assert_eq!(region("20101").as_deref(), Some("VA"));
+        assert_eq!(region("73301").as_deref(), Some("TX"));
+        assert_eq!(region("99501").as_deref(), Some("AK"));
+        assert_eq!(region(" 94105 ").as_deref(), Some("CA"));
+        assert_eq!(region_for_postal("60601", "us").as_deref(), Some("IL"));
+
+        // Military mail, unassigned prefixes and malformed codes
+        assert_eq!(region("09001"), None);
+        assert_eq!(region("00100"), None);
+        assert_eq!(region("0210"), None);
+        assert_eq!(region("02101-12"), None);
+        assert_eq!(region("0210A"), None);
+        assert_eq!(region_for_postal("02101", "DE"), None);
+    }
+
+    #[test]
+    fn test_canadian_postal_codes() {
+        let region = |code| region_for_postal(code, "CA");
+        assert_eq!(region("M5V 3L9").as_deref(), Some("ON"));
+        assert_eq!(region("h2x 1y4").as_deref(), Some("QC"));
+        assert_eq!(region("V6B 1A1").as_deref(), Some("BC"));
+        assert_eq!(region("X0A 0H0").as_deref(), Some("NU"));
+        assert_eq!(region("X1A 2P5").as_deref(), Some("NT"));
+        assert_eq!(region("D1A 1A1"), None);
+        assert_eq!(region("MM5 3L9"), None);
+        assert_eq!(region(""), None);
+    }
+
+    #[test]
+    fn test_tables_are_sorted() {
+        assert!(US.iter().all(|&(first, last, _)| first <= last));
+        assert!(US.windows(2).all(|pair| pair[0].1 < pair[1].0));
+        assert!(CA.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn test_region_counts() {
+        let mut people = vec![
+            snapshot(30, 1.75, "Boston"),
+            snapshot(40, 1.70, "Cambridge"),
+            snapshot(50, 1.65, "Chicago"),
+            snapshot(60, 1.60, "Atlantis"),
+        ];
+        for (person, code) in people.iter_mut().zip(["02101", "02139", "60601", "n/a"]) {
+            person.postal_code = code.to_string();
+        }
+        let counts = region_counts(&people, "US");
+        let counts: Vec<_> = counts
+            .iter()
+            .map(|count| (count.region.as_str(), count.people))
+            .collect();
+        assert_eq!(counts, [("", 1), ("IL", 1), ("MA", 2)]);
+
+        // Without a country, Canadian and US codes are told apart
+        people[3].postal_code = "M5V 3L9".to_string();
+        let counts = region_counts(&people, "");
+        let counts: Vec<_> = counts
+            .iter()
+            .map(|count| (count.region.as_str(), count.people))
+            .collect();
+        assert_eq!(counts, [("IL", 1), ("MA", 2), ("ON", 1)]);
+    }
+}
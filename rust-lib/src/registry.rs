@@ -0,0 +1,393 @@
+//! Registry of known persons
+//!
+//! C++ registers a snapshot once and refers to the person by id afterwards.
+//! Ids are never reused within a process.
+//!
+//! When the C++ Person behind an id is destroyed, C++ calls
+//! `notify_person_dropped` (the `RegisteredPerson` helper in
+//! cpp-app/registered_person.h does this from its destructor). The entry is
+//! removed and the id is remembered, so later lookups report the person as
+//! dropped instead of using stale data or looking like a typo. The
+//! analyses cached for the entry are forgotten with it.
+//!
+//! `register_person_unique` returns the id of an equal person, compared
+//! after Unicode normalization, instead of adding a duplicate entry.
+//! `register_person_unique_with_options` can also match across scripts.
+//!
+//! Each entry remembers when it was last registered or updated, and each
+//! dropped id when it was dropped, for the retention policy (see
+//! retention.rs).
+
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use crate::analysis_cache;
+use crate::audit;
+use crate::consent;
+use crate::error::Error;
+use crate::events;
+use crate::ffi;
+use crate::leak_check::{self, Handle};
+use crate::memory::{self, HeapSize};
+use crate::normalize::person_equals_with_options;
+use crate::state::Global;
+
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    entries: HashMap<u64, ffi::PersonSnapshot>,
+    /// When each entry was registered or last updated
+    updated: HashMap<u64, SystemTime>,
+    /// Ids whose C++ Person was destroyed, and when; kept until erased or
+    /// expired by the retention policy
+    dropped: HashMap<u64, SystemTime>,
+}
+
+impl Registry {
+    fn missing(&self, id: u64) -> Error {
+        if self.dropped.contains_key(&id) {
+            Error::PersonDropped(id)
+        } else {
+            Error::UnknownPerson(id)
+        }
+    }
+
+    fn insert(&mut self, snapshot: ffi::PersonSnapshot) -> u64 {
+        self.next_id += 1;
+        self.entries.insert(self.next_id, snapshot);
+        self.updated.insert(self.next_id, SystemTime::now());
+        self.next_id
+    }
+
+    fn remove(&mut self, id: u64) -> Option<ffi::PersonSnapshot> {
+        self.updated.remove(&id);
+        self.entries.remove(&id)
+    }
+
+    /// The entries not updated since `cutoff`, and the ids dropped before
+    /// it, each ordered by id
+    fn stale(&self, cutoff: SystemTime) -> (Vec<u64>, Vec<u64>) {
+        let before = |times: &HashMap<u64, SystemTime>| {
+            let mut ids: Vec<u64> = times
+                .iter()
+                .filter(|(_, &time)| time < cutoff)
+                .map(|(&id, _)| id)
+                .collect();
+            ids.sort_unstable();
+            ids
+        };
+        (before(&self.updated), before(&self.dropped))
+    }
+}
+
+static REGISTRY: Global<Registry> = Global::new(Registry::default);
+
+pub(crate) fn register_person(snapshot: ffi::PersonSnapshot) -> u64 {
+    let id = REGISTRY.write().insert(snapshot);
+    announce(id);
+    id
+}
+
+/// Register `snapshot` unless an equal person is already registered, in
+/// which case that person's id (the oldest, if several) is returned
+///
+/// Scans every entry, normalizing as `person_equals` does.
+pub(crate) fn register_person_unique(snapshot: ffi::PersonSnapshot, fold_case: bool) -> u64 {
+    let options = ffi::MatchOptions {
+        fold_case,
+        ..Default::default()
+    };
+    register_person_unique_with_options(snapshot, &options)
+}
+
+pub(crate) fn register_person_unique_with_options(
+    snapshot: ffi::PersonSnapshot,
+    options: &ffi::MatchOptions,
+) -> u64 {
+    let id = {
+        let mut registry = REGISTRY.write();
+        let existing = registry
+            .entries
+            .iter()
+            .filter(|(_, entry)| person_equals_with_options(entry, &snapshot, options))
+            .map(|(&id, _)| id)
+            .min();
+        if let Some(id) = existing {
+            return id;
+        }
+        registry.insert(snapshot)
+    };
+    announce(id);
+    id
+}
+
+fn announce(id: u64) {
+    leak_check::issued(Handle::RegisteredPerson, id);
+    events::person_registered(id);
+}
+
+pub(crate) fn update_registered_person(
+    id: u64,
+    snapshot: ffi::PersonSnapshot,
+) -> Result<(), Error> {
+    let mut registry = REGISTRY.write();
+    match registry.entries.get_mut(&id) {
+        Some(entry) => {
+            *entry = snapshot;
+            registry.updated.insert(id, SystemTime::now());
+            Ok(())
+        }
+        None => Err(registry.missing(id)),
+    }
+}
+
+pub(crate) fn unregister_person(id: u64) -> bool {
+    let removed = REGISTRY.write().remove(id).is_some();
+    if removed {
+        // Outside the registry lock: set_consent takes it inside the
+        // consent lock
+        consent::forget(id);
+        leak_check::released(Handle::RegisteredPerson, id);
+    }
+    removed
+}
+
+/// The C++ Person behind `id` was destroyed; false if the id was not registered
+pub(crate) fn notify_person_dropped(id: u64) -> bool {
+    let mut registry = REGISTRY.write();
+    let Some(person) = registry.remove(id) else {
+        return false;
+    };
+    registry.dropped.insert(id, SystemTime::now());
+    drop(registry);
+    consent::forget(id);
+    // Only the id is kept, so erase_subject could not find these later
+    analysis_cache::forget_person(&person);
+    leak_check::released(Handle::RegisteredPerson, id);
+    true
+}
+
+/// Forget `id` entirely, for erasure: its entry, and that its C++ Person
+/// was destroyed. Returns the entry, if there was one, and whether the id
+/// was remembered as destroyed; later lookups treat the id as unknown.
+pub(crate) fn erase_person(id: u64) -> (Option<ffi::PersonSnapshot>, bool) {
+    let mut registry = REGISTRY.write();
+    let entry = registry.remove(id);
+    let dropped = registry.dropped.remove(&id).is_some();
+    drop(registry);
+    if entry.is_some() {
+        leak_check::released(Handle::RegisteredPerson, id);
+    }
+    (entry, dropped)
+}
+
+/// What expire_stale would remove for `cutoff`: the ids and entries not
+/// updated since, and the ids dropped before it
+pub(crate) fn stale_entries(
+    cutoff: SystemTime,
+) -> (Vec<(u64, ffi::PersonSnapshot)>, Vec<u64>) {
+    let registry = REGISTRY.read();
+    let (stale, dropped) = registry.stale(cutoff);
+    let entries = stale
+        .into_iter()
+        .map(|id| (id, registry.entries[&id].clone()))
+        .collect();
+    (entries, dropped)
+}
+
+/// Remove the entries not updated since `cutoff`, and forget the ids
+/// dropped before it, as erase_person does; returns what was removed
+pub(crate) fn expire_stale(cutoff: SystemTime) -> (Vec<(u64, ffi::PersonSnapshot)>, Vec<u64>) {
+    let mut registry = REGISTRY.write();
+    let (stale, dropped) = registry.stale(cutoff);
+    let entries: Vec<_> = stale
+        .into_iter()
+        .filter_map(|id| Some((id, registry.remove(id)?)))
+        .collect();
+    for id in &dropped {
+        registry.dropped.remove(id);
+    }
+    drop(registry);
+    for &(id, _) in &entries {
+        leak_check::released(Handle::RegisteredPerson, id);
+    }
+    (entries, dropped)
+}
+
+pub(crate) fn is_registered(id: u64) -> bool {
+    REGISTRY.read().entries.contains_key(&id)
+}
+
+pub(crate) fn get_registered_person(id: u64) -> Result<ffi::PersonSnapshot, Error> {
+    audit::record("get_registered_person", id, audit::PERSON_FIELDS);
+    lookup(id)
+}
+
+/// get_registered_person without an audit entry, for callers that record
+/// their own
+pub(crate) fn lookup(id: u64) -> Result<ffi::PersonSnapshot, Error> {
+    let registry = REGISTRY.read();
+    registry
+        .entries
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| registry.missing(id))
+}
+
+pub(crate) fn registered_count() -> usize {
+    REGISTRY.read().entries.len()
+}
+
+/// Heap of the entries, their times and the ids of dropped persons
+pub(crate) fn heap_bytes() -> usize {
+    let registry = REGISTRY.read();
+    let snapshots: usize = registry.entries.values().map(HeapSize::heap_bytes).sum();
+    memory::table_bytes::<(u64, ffi::PersonSnapshot)>(registry.entries.capacity())
+        + snapshots
+        + memory::table_bytes::<(u64, SystemTime)>(registry.updated.capacity())
+        + memory::table_bytes::<(u64, SystemTime)>(registry.dropped.capacity())
+}
+
+/// Copy of every entry, so callers can work without holding the lock
+pub(crate) fn registry_entries() -> Vec<(u64, ffi::PersonSnapshot)> {
+    REGISTRY
+        .read()
+        .entries
+        .iter()
+        .map(|(&id, snapshot)| (id, snapshot.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+
+    #[test]
+    fn test_register_update_unregister() {
+        let id = register_person(snapshot(30, 1.75, "Boston"));
+        assert_eq!(get_registered_person(id).unwrap().city, "Boston");
+
+        update_registered_person(id, snapshot(31, 1.75, "Chicago")).unwrap();
+        assert_eq!(get_registered_person(id).unwrap().city, "Chicago");
+
+        assert!(unregister_person(id));
+        assert!(!unregister_person(id));
+        assert_eq!(
+            get_registered_person(id).unwrap_err(),
+            Error::UnknownPerson(id)
+        );
+        assert!(update_registered_person(id, snapshot(30, 1.75, "Boston")).is_err());
+    }
+
+    #[test]
+    fn test_dropped_person_is_invalidated() {
+        let id = register_person(snapshot(30, 1.75, "Boston"));
+        assert!(notify_person_dropped(id));
+        assert!(!notify_person_dropped(id));
+
+        assert_eq!(
+            get_registered_person(id).unwrap_err(),
+            Error::PersonDropped(id)
+        );
+        assert_eq!(
+            update_registered_person(id, snapshot(30, 1.75, "Boston")).unwrap_err(),
+            Error::PersonDropped(id)
+        );
+        assert!(!unregister_person(id));
+    }
+
+    #[test]
+    fn test_erased_person_is_forgotten() {
+        let id = register_person(snapshot(30, 1.75, "Boston"));
+        assert!(is_registered(id));
+        let (entry, dropped) = erase_person(id);
+        assert_eq!(entry.unwrap().city, "Boston");
+        assert!(!dropped);
+        assert!(!is_registered(id));
+
+        let other = register_person(snapshot(30, 1.75, "Boston"));
+        notify_person_dropped(other);
+        assert!(matches!(erase_person(other), (None, true)));
+        assert_eq!(
+            get_registered_person(other).unwrap_err(),
+            Error::UnknownPerson(other)
+        );
+        assert!(matches!(erase_person(other), (None, false)));
+    }
+
+    // On a registry of its own, whose times it can move back
+    #[test]
+    fn test_stale_entries() {
+        let day = std::time::Duration::from_secs(86_400);
+        let mut registry = Registry::default();
+        let old = registry.insert(snapshot(30, 1.75, "Boston"));
+        let fresh = registry.insert(snapshot(40, 1.80, "Chicago"));
+        let gone = registry.insert(snapshot(50, 1.70, "Denver"));
+        registry.remove(gone);
+        registry.dropped.insert(gone, SystemTime::now() - 3 * day);
+        *registry.updated.get_mut(&old).unwrap() -= 2 * day;
+
+        let cutoff = SystemTime::now() - day;
+        assert_eq!(registry.stale(cutoff), (vec![old], vec![gone]));
+        assert_eq!(registry.stale(cutoff - 3 * day), (vec![], vec![]));
+        registry.remove(old);
+        assert_eq!(registry.stale(cutoff), (vec![], vec![gone]));
+        assert!(registry.updated.contains_key(&fresh));
+    }
+
+    #[test]
+    fn test_ids_are_not_reused() {
+        let first = register_person(snapshot(30, 1.75, "Boston"));
+        unregister_person(first);
+        let second = register_person(snapshot(30, 1.75, "Boston"));
+        assert_ne!(first, second);
+        unregister_person(second);
+    }
+
+    #[test]
+    fn test_unique_registration_matches_normalized_names() {
+        // A name no other test registers, since tests share the registry
+        let mut composed = snapshot(44, 1.62, "Montr\u{e9}al");
+        composed.name = "Ren\u{e9}e Unique".to_string();
+        let mut decomposed = composed.clone();
+        decomposed.name = "Rene\u{301}e Unique".to_string();
+        decomposed.city = "Montre\u{301}al".to_string();
+
+        let id = register_person_unique(composed.clone(), false);
+        assert_eq!(register_person_unique(decomposed, false), id);
+
+        let mut upper = composed.clone();
+        upper.name = "REN\u{c9}E UNIQUE".to_string();
+        assert_eq!(register_person_unique(upper.clone(), true), id);
+        let other = register_person_unique(upper, false);
+        assert_ne!(other, id);
+
+        assert!(unregister_person(id));
+        assert!(unregister_person(other));
+    }
+
+    #[test]
+    fn test_unique_registration_across_scripts() {
+        let mut latin = snapshot(52, 1.68, "Sankt-Peterburg");
+        latin.name = "Svetlana Unikalnaia".to_string();
+        let mut cyrillic = latin.clone();
+        cyrillic.name = "Светлана Уникальная".to_string();
+        cyrillic.city = "Санкт-Петербург".to_string();
+        let options = ffi::MatchOptions {
+            fold_case: false,
+            transliterate: true,
+        };
+
+        let id = register_person_unique_with_options(latin, &options);
+        assert_eq!(
+            register_person_unique_with_options(cyrillic.clone(), &options),
+            id
+        );
+        let other = register_person_unique(cyrillic, false);
+        assert_ne!(other, id);
+
+        assert!(unregister_person(id));
+        assert!(unregister_person(other));
+    }
+}
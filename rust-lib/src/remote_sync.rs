@@ -0,0 +1,371 @@
+//! Upload of analysis results to a remote endpoint (`sync` feature)
+//!
+//! `configure_sync` registers a risk observer with no threshold (see
+//! observers.rs), so every analysis is recorded as
+//!
+//! ```json
+//! {"analyzed_at_ms": 1760000000000, "analysis": {"bmi": ..., ...}}
+//! ```
+//!
+//! in an offline queue: one record per line of the queue file, written as
+//! each analysis finishes, so results survive a restart of the host while
+//! the endpoint is unreachable. A background thread POSTs the records as a
+//! JSON array in batches every `SYNC_INTERVAL`, or, after a failed upload,
+//! after a backoff that doubles with each further failure. `sync_now`
+//! uploads right away on the calling thread.
+//!
+//! Requests go through http_client.rs, to an `http://` or `https://`
+//! endpoint.
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::error::Error;
+use crate::ffi;
+use crate::http_client::Endpoint;
+use crate::observers;
+use crate::state::{Global, StopSignal};
+
+/// Records per upload request
+const BATCH_SIZE: usize = 500;
+/// Records kept while the endpoint is unreachable; later ones are dropped
+const MAX_PENDING: usize = 100_000;
+/// Pause between uploads while they succeed
+const SYNC_INTERVAL: Duration = Duration::from_secs(60);
+/// Pause after the first failed upload; doubled for each further one
+const FIRST_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+
+/// The pending records, in memory and in the queue file
+struct Queue {
+    records: VecDeque<String>,
+    path: PathBuf,
+    file: File,
+}
+
+impl Queue {
+    /// Open the queue file at `path`, keeping the records already in it
+    fn open(path: &Path) -> Result<Self, Error> {
+        let mut records = VecDeque::new();
+        if let Ok(existing) = File::open(path) {
+            for line in BufReader::new(existing).lines() {
+                let line = line?;
+                // A crash can leave a torn last line; it is dropped
+                if serde_json::from_str::<Value>(&line).is_ok() {
+                    records.push_back(line);
+                }
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Queue {
+            records,
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    fn push(&mut self, record: String) -> Result<(), Error> {
+        if self.records.len() >= MAX_PENDING {
+            return Ok(());
+        }
+        self.file.write_all(format!("{record}\n").as_bytes())?;
+        self.records.push_back(record);
+        Ok(())
+    }
+
+    /// Drop the first `count` records, which have been uploaded
+    fn remove_front(&mut self, count: usize) -> Result<(), Error> {
+        self.records.drain(..count.min(self.records.len()));
+        // Rewrite the file aside and rename it over, so a crash leaves
+        // either the old or the new queue
+        let rewritten = self.path.with_extension("tmp");
+        let mut contents = String::new();
+        for record in &self.records {
+            contents.push_str(record);
+            contents.push('\n');
+        }
+        fs::write(&rewritten, contents)?;
+        fs::rename(&rewritten, &self.path)?;
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        Ok(())
+    }
+}
+
+/// The queue and where to upload it
+struct Uploader {
+    endpoint: Endpoint,
+    queue: Mutex<Queue>,
+    /// Held for a whole upload, so the worker and sync_now never send the
+    /// same records twice
+    uploading: Mutex<()>,
+}
+
+impl Uploader {
+    fn queue(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn record(&self, analysis: &ffi::HealthAnalysis) -> Result<(), Error> {
+        let analyzed_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64);
+        let record = json!({ "analyzed_at_ms": analyzed_at_ms, "analysis": analysis });
+        self.queue().push(record.to_string())
+    }
+
+    fn pending(&self) -> usize {
+        self.queue().records.len()
+    }
+
+    /// Upload every record pending when called; the number uploaded
+    ///
+    /// Records stay queued unless the endpoint answers 2xx.
+    fn upload(&self) -> Result<usize, Error> {
+        let _uploading = self
+            .uploading
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut remaining = self.pending();
+        let mut uploaded = 0;
+        while remaining > 0 {
+            // Copied out so analyses can queue more records meanwhile
+            let batch: Vec<String> = self
+                .queue()
+                .records
+                .iter()
+                .take(BATCH_SIZE.min(remaining))
+                .cloned()
+                .collect();
+            let body = format!("[{}]", batch.join(","));
+            let target = match self.endpoint.path() {
+                "" => "/",
+                path => path,
+            };
+            let response = self.endpoint.send("POST", target, Some(&body))?;
+            if !(200..300).contains(&response.status) {
+                return Err(Error::Io(format!(
+                    "sync endpoint answered HTTP {}",
+                    response.status
+                )));
+            }
+            self.queue().remove_front(batch.len())?;
+            remaining -= batch.len();
+            uploaded += batch.len();
+        }
+        Ok(uploaded)
+    }
+}
+
+struct RemoteSync {
+    uploader: Arc<Uploader>,
+    observer_id: u64,
+    stop: Arc<StopSignal>,
+    worker: JoinHandle<()>,
+}
+
+static SYNC: Global<Option<RemoteSync>> = Global::new(|| None);
+
+/// Queue every analysis in `queue_path` and upload it to `url`, replacing
+/// any sync configured before
+pub(crate) fn configure_sync(url: &str, queue_path: &str) -> Result<(), Error> {
+    configure(url, Path::new(queue_path), SYNC_INTERVAL)
+}
+
+fn configure(url: &str, queue_path: &Path, interval: Duration) -> Result<(), Error> {
+    let endpoint = Endpoint::parse(url, "sync URL")?;
+    disable_sync();
+    let uploader = Arc::new(Uploader {
+        endpoint,
+        queue: Mutex::new(Queue::open(queue_path)?),
+        uploading: Mutex::new(()),
+    });
+
+    let stop = Arc::new(StopSignal::default());
+    let worker = {
+        let (uploader, stop) = (Arc::clone(&uploader), Arc::clone(&stop));
+        thread::spawn(move || run(&uploader, &stop, interval))
+    };
+    let observer_id = {
+        let uploader = Arc::clone(&uploader);
+        // An analysis must not fail because its record could not be
+        // written; the record is lost instead
+        observers::add_sharing_observer(
+            f64::NEG_INFINITY,
+            Arc::new(move |analysis| {
+                let _ = uploader.record(analysis);
+            }),
+        )
+    };
+
+    *SYNC.write() = Some(RemoteSync {
+        uploader,
+        observer_id,
+        stop,
+        worker,
+    });
+    Ok(())
+}
+
+/// The worker: upload on every interval, backing off while uploads fail
+fn run(uploader: &Uploader, stop: &StopSignal, interval: Duration) {
+    let mut delay = interval;
+    let mut backoff = FIRST_BACKOFF;
+    while !stop.wait(delay) {
+        if uploader.upload().is_ok() {
+            delay = interval;
+            backoff = FIRST_BACKOFF;
+        } else {
+            delay = backoff;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+}
+
+/// Upload every pending record now; the number uploaded
+pub(crate) fn sync_now() -> Result<usize, Error> {
+    let uploader = match SYNC.read().as_ref() {
+        Some(sync) => Arc::clone(&sync.uploader),
+        None => return Err(Error::NotConfigured("sync")),
+    };
+    uploader.upload()
+}
+
+/// Records waiting for upload; 0 when sync is not configured
+pub(crate) fn pending_upload_count() -> usize {
+    match SYNC.read().as_ref() {
+        Some(sync) => sync.uploader.pending(),
+        None => 0,
+    }
+}
+
+/// Stop recording and uploading; pending records stay in the queue file
+/// for the next configure_sync
+pub(crate) fn disable_sync() {
+    let sync = SYNC.write().take();
+    if let Some(sync) = sync {
+        observers::unregister_risk_observer(sync.observer_id);
+        sync.stop.stop();
+        let _ = sync.worker.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::tests::{fake_server, fake_tls_server, trust_test_ca};
+
+    fn queue_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("rust_lib_{name}_{}.ndjson", std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn analysis(risk_score: f64) -> ffi::HealthAnalysis {
+        let person = crate::batch::tests::snapshot(30, 1.75, "Boston");
+        ffi::HealthAnalysis {
+            risk_score,
+            ..crate::compute_health(&person, 70.0)
+        }
+    }
+
+    #[test]
+    fn test_queue_survives_reopening() {
+        let path = queue_path("sync_queue");
+        let mut queue = Queue::open(&path).unwrap();
+        for record in [r#"{"n":1}"#, r#"{"n":2}"#, r#"{"n":3}"#] {
+            queue.push(record.to_string()).unwrap();
+        }
+        queue.remove_front(1).unwrap();
+        queue.push(r#"{"n":4}"#.to_string()).unwrap();
+        drop(queue);
+
+        // A torn line, as a crash while writing leaves it
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"n\":")
+            .unwrap();
+        let queue = Queue::open(&path).unwrap();
+        assert_eq!(queue.records, [r#"{"n":2}"#, r#"{"n":3}"#, r#"{"n":4}"#]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_upload_keeps_records_until_accepted() {
+        let path = queue_path("sync_upload");
+        let (url, server) = fake_server(vec![
+            "HTTP/1.0 503 Service Unavailable\r\n\r\n",
+            "HTTP/1.0 200 OK\r\n\r\n",
+        ]);
+        let uploader = Uploader {
+            endpoint: Endpoint::parse(&format!("{url}/results"), "URL").unwrap(),
+            queue: Mutex::new(Queue::open(&path).unwrap()),
+            uploading: Mutex::new(()),
+        };
+        uploader.record(&analysis(1.5)).unwrap();
+        uploader.record(&analysis(2.5)).unwrap();
+
+        assert!(uploader.upload().unwrap_err().to_string().contains("503"));
+        assert_eq!(uploader.pending(), 2);
+        assert_eq!(uploader.upload().unwrap(), 2);
+        assert_eq!(uploader.pending(), 0);
+        assert_eq!(uploader.upload().unwrap(), 0);
+
+        let requests = server.join().unwrap();
+        assert!(requests[1].starts_with("POST /results HTTP/1.0\r\n"));
+        let body = &requests[1][requests[1].find("\r\n\r\n").unwrap() + 4..];
+        let records: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(records[1]["analysis"]["risk_score"], 2.5);
+        assert!(records[0]["analyzed_at_ms"].as_u64().unwrap() > 0);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_upload_over_https() {
+        trust_test_ca();
+        let path = queue_path("sync_https");
+        let (url, server) =
+            fake_tls_server(false, vec!["HTTP/1.0 200 OK\r\nContent-Length: 0\r\n\r\n"]);
+        let uploader = Uploader {
+            endpoint: Endpoint::parse(&format!("{url}/v1/results"), "URL").unwrap(),
+            queue: Mutex::new(Queue::open(&path).unwrap()),
+            uploading: Mutex::new(()),
+        };
+        uploader.record(&analysis(1.5)).unwrap();
+        assert_eq!(uploader.upload().unwrap(), 1);
+        assert_eq!(uploader.pending(), 0);
+        assert!(server.join().unwrap()[0].starts_with("POST /v1/results HTTP/1.0\r\n"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    // Sync is process-wide and records every analysis, including those
+    // of tests running in parallel, so this checks only the lifecycle
+    #[test]
+    fn test_sync_lifecycle() {
+        assert_eq!(sync_now(), Err(Error::NotConfigured("sync")));
+        assert_eq!(pending_upload_count(), 0);
+        let path = queue_path("sync_lifecycle");
+        assert!(configure_sync("ftp://example.com", path.to_str().unwrap()).is_err());
+
+        configure("http://127.0.0.1:1", &path, Duration::from_secs(3600)).unwrap();
+        observers::notify(&analysis(1.0));
+        assert!(pending_upload_count() >= 1);
+        assert!(sync_now().is_err());
+
+        disable_sync();
+        assert_eq!(pending_upload_count(), 0);
+        assert!(fs::read_to_string(&path).unwrap().lines().count() >= 1);
+        disable_sync();
+        fs::remove_file(&path).unwrap();
+    }
+}
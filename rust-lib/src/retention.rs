@@ -0,0 +1,188 @@
+//! Retention policy for what the library holds about registered persons
+//!
+//! With a policy of N days (`set_retention_policy`), a worker thread sweeps
+//! every hour, starting when the policy is set, and deletes:
+//!
+//! - registry entries not registered or updated in the last N days, with
+//!   their consent, re-validation state and unpolled alerts, as
+//!   erase_subject does
+//! - the records of ids whose C++ Person was destroyed more than N days ago
+//! - analyses cached more than N days ago, and those of expired persons
+//!
+//! An expired id is unknown afterwards. `preview_retention` reports what a
+//! policy would delete without deleting anything, so operators can check
+//! one before setting it. The audit log is drained by the compliance team
+//! and is not swept.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::analysis_cache;
+use crate::consent;
+use crate::error::Error;
+use crate::ffi;
+use crate::registry;
+use crate::revalidation;
+use crate::state::Global;
+
+/// Time between sweeps
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The policy in days; 0 for none
+static POLICY_DAYS: AtomicU32 = AtomicU32::new(0);
+
+struct Worker {
+    stop: Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+static WORKER: Global<Option<Worker>> = Global::new(|| None);
+
+/// Keep data for `days` after its last update, sweeping now and hourly;
+/// 0 removes the policy and stops the sweeps. If the sweep thread cannot
+/// be started there is no policy afterwards.
+pub(crate) fn set_retention_policy(days: u32) -> Result<(), Error> {
+    let mut worker = WORKER.write();
+    POLICY_DAYS.store(days, Ordering::Relaxed);
+    if let Some(Worker { stop, handle }) = worker.take() {
+        let _ = stop.send(());
+        let _ = handle.join();
+    }
+    if days == 0 {
+        return Ok(());
+    }
+
+    let (stop, stopped) = mpsc::channel::<()>();
+    let handle = thread::Builder::new()
+        .name("rust-lib-retention".to_string())
+        .spawn(move || loop {
+            enforce_retention();
+            // Any message or a dropped sender ends the loop
+            if stopped.recv_timeout(SWEEP_INTERVAL) != Err(RecvTimeoutError::Timeout) {
+                break;
+            }
+        })
+        .map_err(|e| {
+            POLICY_DAYS.store(0, Ordering::Relaxed);
+            Error::from(e)
+        })?;
+    *worker = Some(Worker { stop, handle });
+    Ok(())
+}
+
+/// Delete what the policy expires now; nothing without a policy
+pub(crate) fn enforce_retention() -> ffi::RetentionReport {
+    match POLICY_DAYS.load(Ordering::Relaxed) {
+        0 => ffi::RetentionReport::default(),
+        days => sweep(cutoff(days), true),
+    }
+}
+
+/// What a policy of `days` would delete now; 0 for none
+pub(crate) fn preview_retention(days: u32) -> ffi::RetentionReport {
+    match days {
+        0 => ffi::RetentionReport::default(),
+        days => sweep(cutoff(days), false),
+    }
+}
+
+/// The time before which data is older than `days`
+fn cutoff(days: u32) -> SystemTime {
+    let period = Duration::from_secs(u64::from(days) * SECONDS_PER_DAY);
+    SystemTime::now().checked_sub(period).unwrap_or(UNIX_EPOCH)
+}
+
+/// Delete, or with `remove` false only count, what is older than `cutoff`
+fn sweep(cutoff: SystemTime, remove: bool) -> ffi::RetentionReport {
+    let (entries, destroyed_records) = if remove {
+        registry::expire_stale(cutoff)
+    } else {
+        registry::stale_entries(cutoff)
+    };
+    let expired_persons: Vec<u64> = entries.iter().map(|&(id, _)| id).collect();
+
+    // After the registry, so a set_consent or a re-validation sweep in
+    // progress cannot bring a record back
+    let mut validation_alerts = 0;
+    for &person_id in expired_persons.iter().chain(&destroyed_records) {
+        validation_alerts += if remove {
+            consent::forget(person_id);
+            revalidation::forget_person(person_id)
+        } else {
+            revalidation::pending_alert_count(person_id)
+        };
+    }
+
+    let people: Vec<ffi::PersonSnapshot> = entries.into_iter().map(|(_, person)| person).collect();
+    let cached_analyses = if remove {
+        analysis_cache::expire_analyses(cutoff, &people)
+    } else {
+        analysis_cache::expired_count(cutoff, &people)
+    };
+
+    ffi::RetentionReport {
+        expired_persons,
+        destroyed_records,
+        validation_alerts,
+        cached_analyses,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+
+    #[test]
+    fn test_preview_deletes_nothing() {
+        let mut person = snapshot(30, 1.75, "");
+        person.name = "retention-preview".to_string();
+        let id = registry::register_person(person.clone());
+        let dropped = registry::register_person(snapshot(40, 1.80, "Boston"));
+        registry::notify_person_dropped(dropped);
+        analysis_cache::get_or_compute(&person, 70.0, &crate::config::current(), "en", || {
+            crate::analyze_snapshot(&person, 70.0)
+        });
+
+        // Nothing of this test is a day old
+        let report = preview_retention(1);
+        assert!(!report.expired_persons.contains(&id));
+        assert!(!report.destroyed_records.contains(&dropped));
+
+        // A cutoff ahead of now finds all of it, and only counts
+        let report = sweep(SystemTime::now() + Duration::from_secs(1), false);
+        assert!(report.expired_persons.contains(&id));
+        assert!(report.destroyed_records.contains(&dropped));
+        assert!(report.cached_analyses >= 1);
+        assert!(registry::is_registered(id));
+        assert_eq!(
+            analysis_cache::expired_count(UNIX_EPOCH, std::slice::from_ref(&person)),
+            1
+        );
+
+        assert_eq!(preview_retention(0).expired_persons, Vec::<u64>::new());
+        registry::unregister_person(id);
+    }
+
+    // The policy is process-wide, so this is the only test that sets it,
+    // and only to a period no test's data is older than
+    #[test]
+    fn test_policy_lifecycle() {
+        let id = registry::register_person(snapshot(30, 1.75, "Boston"));
+        assert_eq!(enforce_retention().expired_persons, Vec::<u64>::new());
+
+        set_retention_policy(3650).unwrap();
+        set_retention_policy(365).unwrap();
+        assert!(!enforce_retention().expired_persons.contains(&id));
+        assert!(registry::is_registered(id));
+
+        set_retention_policy(0).unwrap();
+        assert!(WORKER.read().is_none());
+        assert_eq!(enforce_retention().cached_analyses, 0);
+        registry::unregister_person(id);
+    }
+}
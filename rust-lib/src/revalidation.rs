@@ -0,0 +1,198 @@
+//! Background re-validation of registry entries
+//!
+//! Records that were valid when registered can go bad later (updated by the
+//! host, or checked against stricter rules). When enabled, a worker thread
+//! re-runs `validate_fields` over the whole registry at a fixed interval
+//! and queues an alert for every record that has become invalid. Each
+//! record alerts once per transition to invalid, not on every sweep.
+
+use std::collections::HashSet;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::events;
+use crate::ffi;
+use crate::registry::{is_registered, registry_entries};
+use crate::state::Global;
+use crate::validation::validate_fields;
+
+#[derive(Default)]
+struct Alerts {
+    /// Ids that were invalid at the last sweep
+    known_invalid: HashSet<u64>,
+    pending: Vec<ffi::ValidationAlert>,
+}
+
+static ALERTS: Global<Alerts> = Global::new(Alerts::default);
+
+struct Worker {
+    stop: Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+static WORKER: Global<Option<Worker>> = Global::new(|| None);
+
+/// Validate every registry entry once and queue alerts for new failures
+pub(crate) fn revalidate_now() -> usize {
+    let results: Vec<_> = registry_entries()
+        .into_iter()
+        .map(|(id, snapshot)| (id, validate_fields(&snapshot)))
+        .collect();
+
+    let mut alerts = ALERTS.write();
+    let mut still_invalid = HashSet::new();
+    let mut new_alerts = 0;
+
+    for (person_id, report) in results {
+        // Checked under the lock, so a person erased during the sweep does
+        // not alert again after erase_subject
+        if report.valid || !is_registered(person_id) {
+            continue;
+        }
+        still_invalid.insert(person_id);
+        if !alerts.known_invalid.contains(&person_id) {
+            alerts.pending.push(ffi::ValidationAlert {
+                person_id,
+                issues: report.issues,
+            });
+            new_alerts += 1;
+        }
+    }
+
+    // Records that were fixed or unregistered may alert again later
+    alerts.known_invalid = still_invalid;
+
+    // Publish after releasing the lock so subscribers may call back in
+    let published: Vec<_> = alerts.pending[alerts.pending.len() - new_alerts..].to_vec();
+    drop(alerts);
+    for alert in published {
+        events::validation_failed(alert.person_id, &alert.issues);
+    }
+    new_alerts
+}
+
+pub(crate) fn start_revalidation(interval_ms: u64) -> Result<(), Error> {
+    if interval_ms == 0 {
+        return Err(Error::InvalidArgument(
+            "revalidation interval must be at least 1 ms".to_string(),
+        ));
+    }
+
+    let mut worker = WORKER.write();
+    if worker.is_some() {
+        return Err(Error::AlreadyRunning("revalidation"));
+    }
+
+    let (stop, stopped) = mpsc::channel::<()>();
+    let interval = Duration::from_millis(interval_ms);
+    let handle = thread::Builder::new()
+        .name("rust-lib-revalidation".to_string())
+        .spawn(move || {
+            // Any message or a dropped sender ends the loop
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                revalidate_now();
+            }
+        })
+        .map_err(Error::from)?;
+
+    *worker = Some(Worker { stop, handle });
+    Ok(())
+}
+
+pub(crate) fn stop_revalidation() {
+    let worker = WORKER.write().take();
+    if let Some(Worker { stop, handle }) = worker {
+        let _ = stop.send(());
+        let _ = handle.join();
+    }
+}
+
+/// Drain the alerts queued since the last poll
+pub(crate) fn poll_validation_alerts() -> Vec<ffi::ValidationAlert> {
+    std::mem::take(&mut ALERTS.write().pending)
+}
+
+/// Whether `person_id` was invalid at the last sweep, and its alerts not
+/// yet polled
+#[cfg(feature = "serde")]
+pub(crate) fn validation_state(person_id: u64) -> (bool, Vec<ffi::ValidationAlert>) {
+    let alerts = ALERTS.read();
+    let pending = alerts
+        .pending
+        .iter()
+        .filter(|alert| alert.person_id == person_id)
+        .cloned()
+        .collect();
+    (alerts.known_invalid.contains(&person_id), pending)
+}
+
+/// How many alerts for `person_id` are not yet polled
+pub(crate) fn pending_alert_count(person_id: u64) -> usize {
+    ALERTS
+        .read()
+        .pending
+        .iter()
+        .filter(|alert| alert.person_id == person_id)
+        .count()
+}
+
+/// Forget `person_id`'s state and unpolled alerts; returns how many
+/// alerts were dropped
+pub(crate) fn forget_person(person_id: u64) -> usize {
+    let mut alerts = ALERTS.write();
+    alerts.known_invalid.remove(&person_id);
+    let before = alerts.pending.len();
+    alerts.pending.retain(|alert| alert.person_id != person_id);
+    before - alerts.pending.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+    use crate::registry::{register_person, unregister_person, update_registered_person};
+
+    // Alerts are process-wide, so one test drives the whole sequence
+    #[test]
+    fn test_alerts_once_per_transition() {
+        let id = register_person(snapshot(30, 1.75, "Boston"));
+        revalidate_now();
+        assert!(!poll_validation_alerts().iter().any(|a| a.person_id == id));
+
+        update_registered_person(id, snapshot(30, 1.75, "")).unwrap();
+        revalidate_now();
+        let alerts = poll_validation_alerts();
+        let alert = alerts.iter().find(|a| a.person_id == id).unwrap();
+        assert_eq!(alert.issues[0].field, "city");
+
+        // Still invalid: no second alert
+        revalidate_now();
+        assert!(!poll_validation_alerts().iter().any(|a| a.person_id == id));
+
+        // Fixed, then broken again: alerts again
+        update_registered_person(id, snapshot(30, 1.75, "Boston")).unwrap();
+        revalidate_now();
+        update_registered_person(id, snapshot(30, 0.0, "Boston")).unwrap();
+        revalidate_now();
+        // Forgotten, as for erasure: nothing left to poll
+        assert_eq!(forget_person(id), 1);
+        assert!(!poll_validation_alerts().iter().any(|a| a.person_id == id));
+
+        unregister_person(id);
+    }
+
+    #[test]
+    fn test_worker_lifecycle() {
+        assert!(start_revalidation(0).is_err());
+        start_revalidation(1).unwrap();
+        assert_eq!(
+            start_revalidation(1),
+            Err(Error::AlreadyRunning("revalidation"))
+        );
+        stop_revalidation();
+        start_revalidation(1).unwrap();
+        stop_revalidation();
+    }
+}
@@ -0,0 +1,104 @@
+//! Host-supplied risk factor strategy
+//!
+//! Some risk inputs (insurance data, site policy) live only in the C++ host.
+//! The host can register a `RiskFactorHook`; its `extra_risk(snapshot)` is
+//! multiplied into every risk score the model produces.
+
+use std::sync::Arc;
+
+use crate::analysis_cache;
+use crate::ffi;
+use crate::person_like::PersonLike;
+use crate::state::Global;
+
+type Hook = Arc<dyn Fn(&ffi::PersonSnapshot) -> f64 + Send + Sync>;
+
+static HOOK: Global<Option<Hook>> = Global::new(|| None);
+
+// The hook runs on any analysis thread, possibly concurrently;
+// callbacks.h documents that the wrapped C++ function must allow this.
+#[cfg(not(feature = "stub-cpp"))]
+unsafe impl Send for ffi::RiskFactorHook {}
+#[cfg(not(feature = "stub-cpp"))]
+unsafe impl Sync for ffi::RiskFactorHook {}
+
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn set_extra_risk_hook(hook: cxx::UniquePtr<ffi::RiskFactorHook>) {
+    if hook.is_null() {
+        clear_extra_risk_hook();
+        return;
+    }
+    set_hook(Some(Arc::new(move |snapshot| match hook.as_ref() {
+        Some(hook) => hook.extra_risk(snapshot),
+        None => 1.0,
+    })));
+    analysis_cache::clear_analysis_cache();
+}
+
+fn set_hook(hook: Option<Hook>) {
+    *HOOK.write() = hook;
+}
+
+/// Remove the hook; cached analyses used it, so they go too
+pub(crate) fn clear_extra_risk_hook() {
+    set_hook(None);
+    analysis_cache::clear_analysis_cache();
+}
+
+/// Factor to multiply into the risk score for `person`
+///
+/// 1.0 without a hook. A hook returning a negative or non-finite value is
+/// ignored rather than allowed to corrupt the score. The person is only
+/// copied into a snapshot when a hook is set.
+pub(crate) fn extra_risk(person: &impl PersonLike) -> f64 {
+    // Clone out of the lock so the C++ call runs without holding it
+    let hook = HOOK.read().clone();
+    match hook {
+        Some(hook) => {
+            let factor = hook(&person.to_snapshot());
+            if factor.is_finite() && factor >= 0.0 {
+                factor
+            } else {
+                1.0
+            }
+        }
+        None => 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+
+    // The hook is process-wide and would affect concurrently running
+    // analysis tests, so only `extra_risk` itself is exercised here, with
+    // a hook that is neutral for every snapshot but the marked one. It is
+    // set without clear_extra_risk_hook's emptying of the analysis cache,
+    // which the cache's tests rely on.
+    #[test]
+    fn test_hook_factor_and_sanitizing() {
+        assert_eq!(extra_risk(&snapshot(30, 1.75, "Boston")), 1.0);
+
+        set_hook(Some(Arc::new(
+            |snapshot: &ffi::PersonSnapshot| match snapshot.name.as_str() {
+                "hook-double" => 2.0,
+                "hook-nan" => f64::NAN,
+                "hook-negative" => -1.0,
+                _ => 1.0,
+            },
+        )));
+
+        let mut person = snapshot(30, 1.75, "Boston");
+        person.name = "hook-double".to_string();
+        assert_eq!(extra_risk(&person), 2.0);
+        person.name = "hook-nan".to_string();
+        assert_eq!(extra_risk(&person), 1.0);
+        person.name = "hook-negative".to_string();
+        assert_eq!(extra_risk(&person), 1.0);
+
+        set_hook(None);
+        person.name = "hook-double".to_string();
+        assert_eq!(extra_risk(&person), 1.0);
+    }
+}
@@ -0,0 +1,135 @@
+//! Engine settings the host changes at runtime, by key
+//!
+//! `set_option` and `get_option` back the C++ settings dialog with string
+//! keys and values, so a new setting needs no new bridge function:
+//!
+//! - `locale`: language of `HealthAnalysis.recommendation`, "en" initially
+//!   (see templates.rs for the translations)
+//! - `worker_threads`: size of the background job pool (see scheduler.rs),
+//!   at least 2; one per CPU initially
+//! - `validation_profile`: as set_validation_profile, "strict" initially
+//! - `cache_size`: analyses analyze_health keeps for repeated calls (see
+//!   analysis_cache.rs), 1024 initially; 0 turns the cache off
+//!
+//! A change applies to work that starts afterwards. The model's cutoffs
+//! and factors are configuration rather than options; see config.rs.
+
+use std::sync::Arc;
+
+use crate::analysis_cache;
+use crate::config;
+use crate::error::Error;
+use crate::scheduler;
+use crate::state::Global;
+
+/// The most workers `worker_threads` accepts
+const MAX_WORKERS: usize = 256;
+
+/// The largest `cache_size` accepted
+const MAX_CACHE_SIZE: usize = 1 << 20;
+
+static LOCALE: Global<Arc<str>> = Global::new(|| Arc::from("en"));
+
+/// The `locale` option
+pub(crate) fn locale() -> Arc<str> {
+    Arc::clone(&LOCALE.read())
+}
+
+/// Set the option `key` from its text form
+pub(crate) fn set_option(key: &str, value: &str) -> Result<(), Error> {
+    let invalid = |why: &str| Error::InvalidArgument(format!("{key} = {value:?}: {why}"));
+    match key {
+        "locale" => {
+            if !is_locale(value) {
+                return Err(invalid("expected a locale name such as \"de-CH\""));
+            }
+            *LOCALE.write() = Arc::from(value);
+        }
+        "worker_threads" => {
+            let count = value
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|count| (scheduler::MIN_WORKERS..=MAX_WORKERS).contains(count))
+                .ok_or_else(|| {
+                    invalid(&format!(
+                        "expected {} to {MAX_WORKERS}",
+                        scheduler::MIN_WORKERS
+                    ))
+                })?;
+            scheduler::set_worker_count(count);
+        }
+        "validation_profile" => config::set_validation_profile(value)?,
+        "cache_size" => {
+            let size = value
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|&size| size <= MAX_CACHE_SIZE)
+                .ok_or_else(|| invalid(&format!("expected 0 to {MAX_CACHE_SIZE}")))?;
+            analysis_cache::set_capacity(size);
+        }
+        _ => return Err(Error::InvalidArgument(format!("unknown option {key}"))),
+    }
+    Ok(())
+}
+
+/// The option `key` in the text form set_option takes; empty for unknown
+/// keys
+pub(crate) fn get_option(key: &str) -> String {
+    match key {
+        "locale" => locale().to_string(),
+        "worker_threads" => scheduler::worker_count().to_string(),
+        "validation_profile" => config::current().strictness.name().to_string(),
+        "cache_size" => analysis_cache::capacity().to_string(),
+        _ => String::new(),
+    }
+}
+
+/// "pt_BR.UTF-8", "sr-Latn-RS" and the like, as format.rs parses them
+pub(crate) fn is_locale(name: &str) -> bool {
+    name.len() <= 64
+        && name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Options are process-wide, so only values that change nothing for
+    // the tests running in parallel are set here
+    #[test]
+    fn test_options() {
+        set_option("locale", "en_US.UTF-8").unwrap();
+        assert_eq!(get_option("locale"), "en_US.UTF-8");
+        set_option("locale", "en").unwrap();
+        assert!(set_option("locale", "").is_err());
+        assert!(set_option("locale", "en US").is_err());
+
+        let workers = get_option("worker_threads");
+        set_option("worker_threads", &workers).unwrap();
+        assert_eq!(get_option("worker_threads"), workers);
+        for count in ["1", "257", "many", "-2"] {
+            assert!(set_option("worker_threads", count).is_err(), "{count}");
+        }
+
+        set_option("validation_profile", "strict").unwrap();
+        assert_eq!(get_option("validation_profile"), "strict");
+        assert!(set_option("validation_profile", "kiosk").is_err());
+
+        set_option("cache_size", "1024").unwrap();
+        assert_eq!(get_option("cache_size"), "1024");
+        for size in ["-1", "lots", "1048577"] {
+            assert!(set_option("cache_size", size).is_err(), "{size}");
+        }
+
+        assert!(matches!(
+            set_option("cache", "1"),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert_eq!(get_option("cache"), "");
+    }
+}
@@ -0,0 +1,256 @@
+//! Worker pool with priority classes for background jobs
+//!
+//! Interactive work (one person, a clinician waiting on the answer) is
+//! always taken before batch work. One worker only ever runs interactive
+//! jobs, so even when every other worker is busy with a long nightly batch
+//! an interactive job starts as soon as it is submitted.
+//!
+//! A task that panics is abandoned and its worker takes the next one
+//! (jobs.rs turns the panic into the job's failure). The pool counts only
+//! workers that are running: one that could not be spawned or that exits
+//! some other way is not counted.
+
+use std::collections::VecDeque;
+use std::io;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::thread;
+
+use crate::ffi;
+
+pub(crate) type Task = Box<dyn FnOnce() + Send>;
+
+/// The interactive-only worker and at least one for everything
+pub(crate) const MIN_WORKERS: usize = 2;
+
+/// The queued tasks, and the pool's size under the same lock so that a
+/// worker can check whether to retire before it waits
+#[derive(Default)]
+struct Queues {
+    interactive: VecDeque<Task>,
+    batch: VecDeque<Task>,
+    /// Workers running
+    workers: usize,
+    /// Workers the pool should have; see set_worker_count
+    wanted: usize,
+}
+
+impl Queues {
+    fn push(&mut self, priority: ffi::JobPriority, task: Task) {
+        if priority == ffi::JobPriority::Interactive {
+            self.interactive.push_back(task);
+        } else {
+            self.batch.push_back(task);
+        }
+    }
+
+    /// Next task for a worker, interactive first
+    fn pop(&mut self, interactive_only: bool) -> Option<Task> {
+        self.interactive.pop_front().or_else(|| {
+            if interactive_only {
+                None
+            } else {
+                self.batch.pop_front()
+            }
+        })
+    }
+
+    /// Whether a worker should exit because the pool is too large; the
+    /// interactive-only worker never does
+    fn retire(&mut self, interactive_only: bool) -> bool {
+        if interactive_only || self.workers <= self.wanted {
+            return false;
+        }
+        self.workers -= 1;
+        true
+    }
+}
+
+// The queues need a Condvar, so unlike the other globals they sit behind a
+// Mutex rather than a `state::Global`. The lock is still poison-tolerant.
+struct Scheduler {
+    queues: Mutex<Queues>,
+    ready: Condvar,
+}
+
+impl Scheduler {
+    fn lock(&self) -> MutexGuard<'_, Queues> {
+        self.queues.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn scheduler() -> &'static Scheduler {
+    static SCHEDULER: OnceLock<Scheduler> = OnceLock::new();
+    SCHEDULER.get_or_init(|| {
+        let wanted =
+            thread::available_parallelism().map_or(MIN_WORKERS, |n| n.get().max(MIN_WORKERS));
+        // Without an interactive-only worker interactive jobs would wait
+        // behind batches, and without any worker nothing would run
+        spawn_worker(true).expect("failed to spawn the interactive worker thread");
+        let workers = 1 + (1..wanted).filter(|_| spawn_worker(false).is_ok()).count();
+        assert!(workers > 1, "failed to spawn any batch worker thread");
+        Scheduler {
+            queues: Mutex::new(Queues {
+                workers,
+                wanted,
+                ..Queues::default()
+            }),
+            ready: Condvar::new(),
+        }
+    })
+}
+
+/// Start a worker; the caller counts it in `Queues::workers`
+fn spawn_worker(interactive_only: bool) -> io::Result<()> {
+    static NEXT_INDEX: AtomicUsize = AtomicUsize::new(0);
+    let index = NEXT_INDEX.fetch_add(1, Ordering::Relaxed);
+    thread::Builder::new()
+        .name(format!("rust-lib-worker-{index}"))
+        .spawn(move || worker_loop(interactive_only))
+        .map(drop)
+}
+
+/// A running worker's place in `Queues::workers`, given up if the worker
+/// exits other than by retiring
+struct Counted;
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        scheduler().lock().workers -= 1;
+    }
+}
+
+fn worker_loop(interactive_only: bool) {
+    let scheduler = scheduler();
+    let counted = Counted;
+    loop {
+        let task = {
+            let mut queues = scheduler.lock();
+            loop {
+                if queues.retire(interactive_only) {
+                    // retire took it off the count already
+                    std::mem::forget(counted);
+                    return;
+                }
+                if let Some(task) = queues.pop(interactive_only) {
+                    break task;
+                }
+                queues = scheduler
+                    .ready
+                    .wait(queues)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+        };
+        // The task owns whatever it shares with its submitter, and is gone
+        // after a panic, so nothing half-updated is seen again
+        let _ = catch_unwind(AssertUnwindSafe(task));
+    }
+}
+
+/// Queue `task` to run on the worker pool
+pub(crate) fn schedule(priority: ffi::JobPriority, task: Task) {
+    let scheduler = scheduler();
+    scheduler.lock().push(priority, task);
+    // Wake everyone: the interactive-only worker must not swallow the
+    // wakeup meant for a batch job.
+    scheduler.ready.notify_all();
+}
+
+/// Grow or shrink the pool to `count` workers, at least MIN_WORKERS;
+/// surplus workers exit once they finish their current task
+pub(crate) fn set_worker_count(count: usize) {
+    let scheduler = scheduler();
+    let mut queues = scheduler.lock();
+    queues.wanted = count.max(MIN_WORKERS);
+    while queues.workers < queues.wanted {
+        // Tried again by the next call; the pool works with the workers
+        // it has
+        if spawn_worker(false).is_err() {
+            break;
+        }
+        queues.workers += 1;
+    }
+    drop(queues);
+    // Idle workers check whether to retire when woken
+    scheduler.ready.notify_all();
+}
+
+/// The pool's size, once surplus workers have exited; fewer than asked
+/// for if some could not be spawned
+pub(crate) fn worker_count() -> usize {
+    let queues = scheduler().lock();
+    queues.workers.min(queues.wanted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn tagged(tag: &'static str, out: &mpsc::Sender<&'static str>) -> Task {
+        let out = out.clone();
+        Box::new(move || out.send(tag).unwrap())
+    }
+
+    #[test]
+    fn test_interactive_taken_before_batch() {
+        let (tx, rx) = mpsc::channel();
+        let mut queues = Queues::default();
+        queues.push(ffi::JobPriority::Batch, tagged("batch-1", &tx));
+        queues.push(ffi::JobPriority::Batch, tagged("batch-2", &tx));
+        queues.push(ffi::JobPriority::Interactive, tagged("interactive", &tx));
+
+        while let Some(task) = queues.pop(false) {
+            task();
+        }
+        let order: Vec<_> = rx.try_iter().collect();
+        assert_eq!(order, vec!["interactive", "batch-1", "batch-2"]);
+    }
+
+    #[test]
+    fn test_interactive_worker_skips_batch() {
+        let (tx, _rx) = mpsc::channel();
+        let mut queues = Queues::default();
+        queues.push(ffi::JobPriority::Batch, tagged("batch", &tx));
+
+        assert!(queues.pop(true).is_none());
+        assert!(queues.pop(false).is_some());
+    }
+
+    #[test]
+    fn test_surplus_workers_retire() {
+        let mut queues = Queues {
+            workers: 3,
+            wanted: 2,
+            ..Queues::default()
+        };
+        assert!(!queues.retire(true));
+        assert!(queues.retire(false));
+        assert!(!queues.retire(false));
+        assert_eq!(queues.workers, 2);
+    }
+
+    #[test]
+    fn test_scheduled_task_runs() {
+        let (tx, rx) = mpsc::channel();
+        schedule(ffi::JobPriority::Batch, tagged("ran", &tx));
+        assert_eq!(rx.recv().unwrap(), "ran");
+    }
+
+    #[test]
+    fn test_workers_survive_panicking_tasks() {
+        // More panics than any pool has workers, on both kinds of worker
+        for _ in 0..=worker_count() {
+            schedule(ffi::JobPriority::Interactive, Box::new(|| panic!("task failed")));
+            schedule(ffi::JobPriority::Batch, Box::new(|| panic!("task failed")));
+        }
+        let (tx, rx) = mpsc::channel();
+        schedule(ffi::JobPriority::Interactive, tagged("interactive", &tx));
+        schedule(ffi::JobPriority::Batch, tagged("batch", &tx));
+        let mut ran: Vec<_> = rx.iter().take(2).collect();
+        ran.sort();
+        assert_eq!(ran, ["batch", "interactive"]);
+        assert!(worker_count() >= MIN_WORKERS);
+    }
+}
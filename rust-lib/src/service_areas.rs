@@ -0,0 +1,314 @@
+//! Service areas: is an address inside one?
+//!
+//! `load_service_areas` reads a GeoJSON FeatureCollection whose features
+//! are the areas: a Polygon or MultiPolygon geometry and an id, the
+//! feature's `id` or else its `id` property. `is_in_service_area`
+//! geocodes an address with the current backend (see geocode.rs) and
+//! tests its position against the area's polygons, so eligibility rules
+//! live in one place instead of in each C++ caller.
+//!
+//! Rings follow GeoJSON: positions are [longitude, latitude], each ring is
+//! closed, and the rings after a polygon's first are holes in it. The
+//! test is even-odd on the plain longitude/latitude plane, which is exact
+//! enough for areas the size of a city or a state but does not handle an
+//! area across the antimeridian. A point on an edge can fall either way.
+
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::error::Error;
+use crate::ffi;
+use crate::state::Global;
+
+/// [longitude, latitude], as in GeoJSON
+type Position = [f64; 2];
+
+/// A polygon's rings: the outline, then its holes
+type Polygon = Vec<Vec<Position>>;
+
+/// The loaded areas by id
+static AREAS: Global<BTreeMap<String, Vec<Polygon>>> = Global::new(BTreeMap::new);
+
+/// Make the features of `source` the service areas, replacing all areas
+/// loaded before
+///
+/// `source` is GeoJSON text when it starts with '{' and otherwise the path
+/// of a GeoJSON file. On any error the areas in effect stay.
+pub(crate) fn load_service_areas(source: &str) -> Result<(), Error> {
+    let (name, areas) = if source.trim_start().starts_with('{') {
+        ("service area GeoJSON", parse_geojson(source))
+    } else {
+        let text = fs::read_to_string(source).map_err(|e| Error::Io(format!("{source}: {e}")))?;
+        (source, parse_geojson(&text))
+    };
+    let areas = areas.map_err(|message| Error::InvalidArgument(format!("{name}: {message}")))?;
+    *AREAS.write() = areas;
+    Ok(())
+}
+
+/// Whether `point` lies in the service area `area_id`
+pub fn point_in_service_area(point: &ffi::GeoPoint, area_id: &str) -> Result<bool, Error> {
+    let areas = AREAS.read();
+    let polygons = areas
+        .get(area_id)
+        .ok_or_else(|| Error::UnknownServiceArea(area_id.to_string()))?;
+    Ok(polygons
+        .iter()
+        .any(|polygon| polygon_contains(polygon, [point.lon, point.lat])))
+}
+
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn is_in_service_area(address: &ffi::Address, area_id: &str) -> Result<bool, Error> {
+    // An unknown area fails before the geocoder is asked
+    if !AREAS.read().contains_key(area_id) {
+        return Err(Error::UnknownServiceArea(area_id.to_string()));
+    }
+    let point = crate::geocode::geocode_address(address)?;
+    point_in_service_area(&point, area_id)
+}
+
+/// Even-odd rule over all rings, so a point in a hole is outside
+fn polygon_contains(polygon: &Polygon, [lon, lat]: Position) -> bool {
+    let mut inside = false;
+    for edge in polygon.iter().flat_map(|ring| ring.windows(2)) {
+        let ([lon1, lat1], [lon2, lat2]) = (edge[0], edge[1]);
+        let spans = (lat1 > lat) != (lat2 > lat);
+        if spans && lon < lon1 + (lat - lat1) * (lon2 - lon1) / (lat2 - lat1) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+#[cfg(feature = "serde")]
+fn parse_geojson(text: &str) -> Result<BTreeMap<String, Vec<Polygon>>, String> {
+    use serde_json::Value;
+
+    let root: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if root["type"] != "FeatureCollection" {
+        return Err("expected a GeoJSON FeatureCollection".to_string());
+    }
+    let features = root["features"]
+        .as_array()
+        .ok_or("the FeatureCollection has no \"features\" array")?;
+    let mut areas = BTreeMap::new();
+    for (index, feature) in features.iter().enumerate() {
+        let id = match &feature["id"] {
+            Value::Null => &feature["properties"]["id"],
+            id => id,
+        };
+        let id = match id {
+            Value::String(id) if !id.is_empty() => id.clone(),
+            Value::Number(id) => id.to_string(),
+            _ => return Err(format!("feature {index} has no id")),
+        };
+        let polygons = geometry(&feature["geometry"]).map_err(|e| format!("area {id:?}: {e}"))?;
+        if areas.insert(id.clone(), polygons).is_some() {
+            return Err(format!("area {id:?} is defined twice"));
+        }
+    }
+    Ok(areas)
+}
+
+#[cfg(not(feature = "serde"))]
+fn parse_geojson(_: &str) -> Result<BTreeMap<String, Vec<Polygon>>, String> {
+    Err("GeoJSON service areas need the serde feature".to_string())
+}
+
+#[cfg(feature = "serde")]
+fn geometry(geometry: &serde_json::Value) -> Result<Vec<Polygon>, String> {
+    let coordinates = &geometry["coordinates"];
+    match geometry["type"].as_str() {
+        Some("Polygon") => Ok(vec![polygon(coordinates)?]),
+        Some("MultiPolygon") => coordinates
+            .as_array()
+            .ok_or("MultiPolygon coordinates must be an array of polygons")?
+            .iter()
+            .map(polygon)
+            .collect(),
+        Some(other) => Err(format!("expected a Polygon or MultiPolygon, not {other}")),
+        None => Err("expected a Polygon or MultiPolygon geometry".to_string()),
+    }
+}
+
+#[cfg(feature = "serde")]
+fn polygon(rings: &serde_json::Value) -> Result<Polygon, String> {
+    let rings = rings
+        .as_array()
+        .filter(|rings| !rings.is_empty())
+        .ok_or("a polygon must be a non-empty array of rings")?;
+    rings.iter().map(ring).collect()
+}
+
+#[cfg(feature = "serde")]
+fn ring(positions: &serde_json::Value) -> Result<Vec<Position>, String> {
+    let ring = positions
+        .as_array()
+        .ok_or("a ring must be an array of positions")?
+        .iter()
+        .map(position)
+        .collect::<Result<Vec<_>, _>>()?;
+    if ring.len() < 4 || ring.first() != ring.last() {
+        return Err("a ring needs four or more positions, the last equal to the first".to_string());
+    }
+    Ok(ring)
+}
+
+/// [longitude, latitude], ignoring an altitude
+#[cfg(feature = "serde")]
+fn position(position: &serde_json::Value) -> Result<Position, String> {
+    let numbers = position.as_array().map(|numbers| {
+        let number = |index: usize| numbers.get(index).and_then(serde_json::Value::as_f64);
+        (number(0), number(1))
+    });
+    match numbers {
+        Some((Some(lon), Some(lat)))
+            if (-180.0..=180.0).contains(&lon) && (-90.0..=90.0).contains(&lat) =>
+        {
+            Ok([lon, lat])
+        }
+        _ => Err(format!(
+            "{position} is not a [longitude, latitude] position"
+        )),
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    fn point(lat: f64, lon: f64) -> ffi::GeoPoint {
+        ffi::GeoPoint { lat, lon }
+    }
+
+    /// A square around Boston with a hole over Cambridge, and two squares
+    /// around New York and Philadelphia
+    const AREAS_JSON: &str = r#"{
+        "type": "FeatureCollection",
+        "features": [
+            {
+                "type": "Feature",
+                "id": "boston",
+                "properties": {},
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [
+                        [[-71.3, 42.2], [-70.9, 42.2], [-70.9, 42.5], [-71.3, 42.5], [-71.3, 42.2]],
+                        [[-71.15, 42.36], [-71.08, 42.36], [-71.08, 42.4], [-71.15, 42.4],
+                         [-71.15, 42.36]]
+                    ]
+                }
+            },
+            {
+                "type": "Feature",
+                "properties": {"id": "corridor"},
+                "geometry": {
+                    "type": "MultiPolygon",
+                    "coordinates": [
+                        [[[-74.3, 40.5], [-73.7, 40.5], [-73.7, 40.9], [-74.3, 40.9],
+                          [-74.3, 40.5]]],
+                        [[[-75.3, 39.8, 10], [-75.0, 39.8, 10], [-75.0, 40.1, 10],
+                          [-75.3, 40.1, 10], [-75.3, 39.8, 10]]]
+                    ]
+                }
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_polygons_and_holes() {
+        let areas = parse_geojson(AREAS_JSON).unwrap();
+        let inside = |id: &str, lat, lon| {
+            areas[id]
+                .iter()
+                .any(|polygon| polygon_contains(polygon, [lon, lat]))
+        };
+        assert!(inside("boston", 42.3601, -71.0589));
+        // Cambridge is in the hole, Chicago nowhere near
+        assert!(!inside("boston", 42.3736, -71.1097));
+        assert!(!inside("boston", 41.8781, -87.6298));
+        assert!(inside("corridor", 40.7128, -74.0060));
+        assert!(inside("corridor", 39.9526, -75.1652));
+        // Between the two squares
+        assert!(!inside("corridor", 40.2, -74.7));
+    }
+
+    #[test]
+    fn test_invalid_geojson() {
+        let error = |text: &str| parse_geojson(text).unwrap_err();
+        assert_eq!(
+            error(r#"{"type": "Feature"}"#),
+            "expected a GeoJSON FeatureCollection"
+        );
+        let collection =
+            |feature: &str| format!(r#"{{"type": "FeatureCollection", "features": [{feature}]}}"#);
+        assert_eq!(
+            error(&collection(r#"{"geometry": null}"#)),
+            "feature 0 has no id"
+        );
+        assert_eq!(
+            error(&collection(
+                r#"{"id": "a", "geometry": {"type": "Point", "coordinates": [0, 0]}}"#
+            )),
+            "area \"a\": expected a Polygon or MultiPolygon, not Point"
+        );
+        let open_ring = r#"{"id": 7, "geometry": {"type": "Polygon",
+            "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 1]]]}}"#;
+        assert_eq!(
+            error(&collection(open_ring)),
+            "area \"7\": a ring needs four or more positions, the last equal to the first"
+        );
+        let out_of_range = r#"{"id": "a", "geometry": {"type": "Polygon",
+            "coordinates": [[[0, 0], [1, 95], [1, 1], [0, 0]]]}}"#;
+        assert_eq!(
+            error(&collection(out_of_range)),
+            "area \"a\": [1,95] is not a [longitude, latitude] position"
+        );
+        let square = r#"{"id": "a", "geometry": {"type": "Polygon",
+            "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 0]]]}}"#;
+        assert_eq!(
+            error(&collection(&format!("{square}, {square}"))),
+            "area \"a\" is defined twice"
+        );
+    }
+
+    // The areas are process-wide, so this is the only test that loads them
+    #[test]
+    fn test_load_service_areas() {
+        let path =
+            std::env::temp_dir().join(format!("rust_lib_areas_{}.geojson", std::process::id()));
+        fs::write(&path, AREAS_JSON).unwrap();
+        load_service_areas(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            point_in_service_area(&point(42.3601, -71.0589), "boston"),
+            Ok(true)
+        );
+        assert_eq!(
+            point_in_service_area(&point(42.3601, -71.0589), "corridor"),
+            Ok(false)
+        );
+
+        // A failed load keeps the areas in effect
+        let error = load_service_areas(r#"{"type": "FeatureCollection"}"#).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid argument: service area GeoJSON: \
+             the FeatureCollection has no \"features\" array"
+        );
+        assert!(matches!(
+            load_service_areas(path.to_str().unwrap()),
+            Err(Error::Io(_))
+        ));
+        assert_eq!(
+            point_in_service_area(&point(42.3601, -71.0589), "boston"),
+            Ok(true)
+        );
+
+        load_service_areas(r#"{"type": "FeatureCollection", "features": []}"#).unwrap();
+        assert_eq!(
+            point_in_service_area(&point(42.3601, -71.0589), "boston"),
+            Err(Error::UnknownServiceArea("boston".to_string()))
+        );
+    }
+}
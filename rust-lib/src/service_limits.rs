@@ -0,0 +1,210 @@
+//! Rate limits and in-flight caps for the service mode
+//!
+//! The HTTP API (http.rs) and the gRPC server (rust-lib/grpc) run on the
+//! engine the C++ UI uses too, so a client flooding them must not take all
+//! of it. Every request they serve first calls `admit_request` with its
+//! endpoint, "/analyze" or "/rust_lib.v1.Analysis/AnalyzeHealth", say, and
+//! is refused with `Error::RateLimited` or `Error::Overloaded` when that
+//! would exceed:
+//!
+//! - `set_service_max_in_flight`: requests being served at once, over all
+//!   endpoints of both servers
+//! - `set_endpoint_limits`: a token bucket of `per_second` requests with
+//!   room for bursts of `burst`, and its own in-flight cap
+//!
+//! No limit is set initially; 0 means unlimited throughout. Limits apply to
+//! the next request, without restarting a server.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+use crate::error::Error;
+use crate::state::Global;
+
+struct EndpointLimit {
+    per_second: f64,
+    burst: u32,
+    max_in_flight: usize,
+    /// Kept when the limits are changed, as admitted requests release it
+    in_flight: Arc<AtomicUsize>,
+    bucket: Mutex<Bucket>,
+}
+
+/// Tokens left, as of `refilled`
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl EndpointLimit {
+    /// Take a token, if one has accumulated
+    fn take_token(&self) -> bool {
+        if self.per_second == 0.0 {
+            return true;
+        }
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let earned = now.duration_since(bucket.refilled).as_secs_f64() * self.per_second;
+        bucket.tokens = (bucket.tokens + earned).min(f64::from(self.burst));
+        bucket.refilled = now;
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+}
+
+static ENDPOINTS: Global<HashMap<String, Arc<EndpointLimit>>> = Global::new(HashMap::new);
+static MAX_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+/// Cap the requests served at once by the HTTP and gRPC servers together
+pub fn set_service_max_in_flight(max: usize) {
+    MAX_IN_FLIGHT.store(max, Ordering::Relaxed);
+}
+
+/// Limit `endpoint` to `per_second` requests, in bursts of up to `burst`,
+/// and to `max_in_flight` at once; zeros remove the limit
+pub fn set_endpoint_limits(
+    endpoint: &str,
+    per_second: f64,
+    burst: u32,
+    max_in_flight: usize,
+) -> Result<(), Error> {
+    if !per_second.is_finite() || per_second < 0.0 {
+        return Err(Error::InvalidArgument(format!(
+            "{endpoint}: per_second must be zero or positive, not {per_second}"
+        )));
+    }
+    if per_second > 0.0 && burst == 0 {
+        return Err(Error::InvalidArgument(format!(
+            "{endpoint}: a rate limit needs a burst of at least 1"
+        )));
+    }
+    let mut endpoints = ENDPOINTS.write();
+    let in_flight = endpoints
+        .get(endpoint)
+        .map_or_else(Arc::default, |limit| Arc::clone(&limit.in_flight));
+    let limit = EndpointLimit {
+        per_second,
+        burst,
+        max_in_flight,
+        in_flight,
+        bucket: Mutex::new(Bucket {
+            tokens: f64::from(burst),
+            refilled: Instant::now(),
+        }),
+    };
+    endpoints.insert(endpoint.to_string(), Arc::new(limit));
+    Ok(())
+}
+
+/// Remove every limit
+pub fn clear_service_limits() {
+    set_service_max_in_flight(0);
+    ENDPOINTS.write().clear();
+}
+
+/// A request being served; dropping it ends the request
+#[must_use = "the request counts as in flight until this is dropped"]
+pub struct Admission {
+    endpoint: Option<Arc<EndpointLimit>>,
+}
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::Relaxed);
+        if let Some(endpoint) = &self.endpoint {
+            endpoint.in_flight.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Admit one request to `endpoint`, or refuse it over the limits
+pub fn admit_request(endpoint: &str) -> Result<Admission, Error> {
+    if !claim(&IN_FLIGHT, MAX_IN_FLIGHT.load(Ordering::Relaxed)) {
+        return Err(Error::Overloaded(endpoint.to_string()));
+    }
+    let Some(limit) = ENDPOINTS.read().get(endpoint).cloned() else {
+        return Ok(Admission { endpoint: None });
+    };
+    if !claim(&limit.in_flight, limit.max_in_flight) {
+        IN_FLIGHT.fetch_sub(1, Ordering::Relaxed);
+        return Err(Error::Overloaded(endpoint.to_string()));
+    }
+    // From here on a refusal releases both counts through the drop
+    let admission = Admission {
+        endpoint: Some(Arc::clone(&limit)),
+    };
+    if limit.take_token() {
+        Ok(admission)
+    } else {
+        Err(Error::RateLimited(endpoint.to_string()))
+    }
+}
+
+/// Whether the servers are serving as many requests as they may at once,
+/// so a new one would be refused
+#[cfg(feature = "http")]
+pub(crate) fn at_capacity() -> bool {
+    let max = MAX_IN_FLIGHT.load(Ordering::Relaxed);
+    max != 0 && IN_FLIGHT.load(Ordering::Relaxed) >= max
+}
+
+/// Count one more in `in_flight` unless that would pass `max` (0: no cap)
+fn claim(in_flight: &AtomicUsize, max: usize) -> bool {
+    in_flight
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+            (max == 0 || count < max).then_some(count + 1)
+        })
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claim() {
+        let in_flight = AtomicUsize::new(0);
+        assert!(claim(&in_flight, 2));
+        assert!(claim(&in_flight, 2));
+        assert!(!claim(&in_flight, 2));
+        assert_eq!(in_flight.load(Ordering::Relaxed), 2);
+        assert!(claim(&in_flight, 0));
+    }
+
+    // The limits are process-wide, so these endpoints are used by no
+    // other test; the global cap is covered by test_claim only
+    #[test]
+    fn test_endpoint_limits() {
+        assert!(set_endpoint_limits("/test/rate", -1.0, 1, 0).is_err());
+        assert!(set_endpoint_limits("/test/rate", 1.0, 0, 0).is_err());
+
+        set_endpoint_limits("/test/rate", 0.001, 2, 0).unwrap();
+        let first = admit_request("/test/rate").unwrap();
+        drop(admit_request("/test/rate").unwrap());
+        assert_eq!(
+            admit_request("/test/rate").err(),
+            Some(Error::RateLimited("/test/rate".to_string()))
+        );
+        drop(first);
+
+        set_endpoint_limits("/test/in_flight", 0.0, 0, 1).unwrap();
+        let running = admit_request("/test/in_flight").unwrap();
+        assert!(matches!(
+            admit_request("/test/in_flight"),
+            Err(Error::Overloaded(_))
+        ));
+        // Changing the limits keeps counting the request already admitted
+        set_endpoint_limits("/test/in_flight", 0.0, 0, 1).unwrap();
+        assert!(admit_request("/test/in_flight").is_err());
+        drop(running);
+        drop(admit_request("/test/in_flight").unwrap());
+
+        assert!(admit_request("/test/unlimited").is_ok());
+    }
+}
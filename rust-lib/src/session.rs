@@ -0,0 +1,255 @@
+//! Streaming sessions for interactive front ends
+//!
+//! A kiosk opens a session, pushes people one at a time as they step up,
+//! and receives each analysis as soon as it is ready. Every session has its
+//! own worker thread, so results always arrive in push order and one slow
+//! session never holds up another. Results go to the session's
+//! `SessionSink` when one is set; otherwise they are buffered until C++
+//! collects them with `session_pull`, so push and pull can interleave freely.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use crate::error::Error;
+use crate::ffi;
+use crate::leak_check::{self, Handle};
+use crate::state::Global;
+
+type Sink = Arc<dyn Fn(&ffi::SessionResult) + Send + Sync>;
+
+enum Message {
+    Analyze {
+        sequence: u64,
+        snapshot: ffi::PersonSnapshot,
+        weight_kg: f64,
+    },
+    /// A sink was installed; hand it whatever is buffered
+    Flush,
+}
+
+struct Session {
+    sender: Sender<Message>,
+    next_sequence: u64,
+    sink: Option<Sink>,
+    pending: Vec<ffi::SessionResult>,
+}
+
+static SESSIONS: Global<HashMap<u64, Session>> = Global::new(HashMap::new);
+
+static NEXT_SESSION: AtomicU64 = AtomicU64::new(1);
+
+// The sink runs on the session's worker thread; callbacks.h documents the
+// thread-safety requirement.
+#[cfg(not(feature = "stub-cpp"))]
+unsafe impl Send for ffi::SessionSink {}
+#[cfg(not(feature = "stub-cpp"))]
+unsafe impl Sync for ffi::SessionSink {}
+
+pub(crate) fn open_session() -> u64 {
+    let id = NEXT_SESSION.fetch_add(1, Ordering::Relaxed);
+    let (sender, receiver) = mpsc::channel::<Message>();
+
+    thread::spawn(move || {
+        for message in receiver {
+            let result = match message {
+                Message::Analyze {
+                    sequence,
+                    snapshot,
+                    weight_kg,
+                } => Some(ffi::SessionResult {
+                    sequence,
+                    analysis: crate::compute_health(&snapshot, weight_kg),
+                }),
+                Message::Flush => None,
+            };
+            deliver(id, result);
+        }
+    });
+    leak_check::issued(Handle::Session, id);
+
+    SESSIONS.write().insert(
+        id,
+        Session {
+            sender,
+            next_sequence: 0,
+            sink: None,
+            pending: Vec::new(),
+        },
+    );
+    id
+}
+
+/// Buffer `result`, then hand everything buffered to the sink if one is set
+///
+/// Only the session's worker thread delivers, so results reach the sink
+/// in push order even when the sink is installed mid-stream. Results for a
+/// closed session are dropped.
+fn deliver(id: u64, result: Option<ffi::SessionResult>) {
+    let (sink, ready) = {
+        let mut sessions = SESSIONS.write();
+        let Some(session) = sessions.get_mut(&id) else {
+            return;
+        };
+        session.pending.extend(result);
+        match &session.sink {
+            Some(sink) => (Arc::clone(sink), std::mem::take(&mut session.pending)),
+            None => return,
+        }
+    };
+    for result in &ready {
+        sink(result);
+    }
+}
+
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn set_session_sink(
+    session_id: u64,
+    sink: cxx::UniquePtr<ffi::SessionSink>,
+) -> Result<(), Error> {
+    if sink.is_null() {
+        return Err(Error::InvalidArgument("session sink is null".to_string()));
+    }
+    set_sink_with(
+        session_id,
+        Arc::new(move |result: &ffi::SessionResult| sink.on_result(result)),
+    )
+}
+
+/// Install a Rust sink; results buffered so far are flushed to it first
+fn set_sink_with(session_id: u64, sink: Sink) -> Result<(), Error> {
+    let mut sessions = SESSIONS.write();
+    let session = sessions
+        .get_mut(&session_id)
+        .ok_or(Error::UnknownSession(session_id))?;
+    session.sink = Some(sink);
+    // The worker only exits once the sender is dropped, so this cannot fail
+    let _ = session.sender.send(Message::Flush);
+    Ok(())
+}
+
+pub(crate) fn session_push(
+    session_id: u64,
+    person: &ffi::Person,
+    weight_kg: f64,
+) -> Result<u64, Error> {
+    push_snapshot(session_id, crate::snapshot_person(person), weight_kg)
+}
+
+pub(crate) fn push_snapshot(
+    session_id: u64,
+    snapshot: ffi::PersonSnapshot,
+    weight_kg: f64,
+) -> Result<u64, Error> {
+    let mut sessions = SESSIONS.write();
+    let session = sessions
+        .get_mut(&session_id)
+        .ok_or(Error::UnknownSession(session_id))?;
+
+    let sequence = session.next_sequence;
+    session.next_sequence += 1;
+    // The worker only exits once the sender is dropped, so this cannot fail
+    let _ = session.sender.send(Message::Analyze {
+        sequence,
+        snapshot,
+        weight_kg,
+    });
+    Ok(sequence)
+}
+
+pub(crate) fn session_pull(session_id: u64) -> Result<Vec<ffi::SessionResult>, Error> {
+    SESSIONS
+        .write()
+        .get_mut(&session_id)
+        .map(|session| std::mem::take(&mut session.pending))
+        .ok_or(Error::UnknownSession(session_id))
+}
+
+/// End a session; results not yet delivered or pulled are discarded
+pub(crate) fn close_session(session_id: u64) -> bool {
+    // Dropping the sender lets the worker exit once its queue drains
+    let closed = SESSIONS.write().remove(&session_id).is_some();
+    if closed {
+        leak_check::released(Handle::Session, session_id);
+    }
+    closed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    fn pull_at_least(session: u64, count: usize) -> Vec<ffi::SessionResult> {
+        let mut results = Vec::new();
+        while results.len() < count {
+            results.extend(session_pull(session).unwrap());
+            thread::sleep(Duration::from_millis(1));
+        }
+        results
+    }
+
+    #[test]
+    fn test_push_then_pull_in_order() {
+        let session = open_session();
+        assert_eq!(
+            push_snapshot(session, snapshot(30, 1.75, "Boston"), 70.0),
+            Ok(0)
+        );
+        assert_eq!(
+            push_snapshot(session, snapshot(70, 1.75, "Boston"), 70.0),
+            Ok(1)
+        );
+
+        let first = pull_at_least(session, 1);
+        push_snapshot(session, snapshot(16, 1.75, "Boston"), 70.0).unwrap();
+        let mut results = first;
+        results.extend(pull_at_least(session, 3 - results.len()));
+
+        let sequences: Vec<_> = results.iter().map(|r| r.sequence).collect();
+        assert_eq!(sequences, vec![0, 1, 2]);
+        assert!(results[1].analysis.risk_score > results[0].analysis.risk_score);
+        assert!(close_session(session));
+    }
+
+    #[test]
+    fn test_sink_receives_buffered_and_new_results() {
+        let session = open_session();
+        push_snapshot(session, snapshot(30, 1.75, "Boston"), 70.0).unwrap();
+        while SESSIONS.read()[&session].pending.is_empty() {
+            thread::yield_now();
+        }
+
+        let (tx, rx) = channel();
+        set_sink_with(
+            session,
+            Arc::new(move |result: &ffi::SessionResult| tx.send(result.sequence).unwrap()),
+        )
+        .unwrap();
+        push_snapshot(session, snapshot(30, 1.75, "Boston"), 70.0).unwrap();
+
+        assert_eq!(rx.recv().unwrap(), 0);
+        assert_eq!(rx.recv().unwrap(), 1);
+        assert!(session_pull(session).unwrap().is_empty());
+        close_session(session);
+    }
+
+    #[test]
+    fn test_closed_session_is_unknown() {
+        let session = open_session();
+        assert!(close_session(session));
+        assert!(!close_session(session));
+        assert_eq!(
+            push_snapshot(session, snapshot(30, 1.75, "Boston"), 70.0),
+            Err(Error::UnknownSession(session))
+        );
+        assert_eq!(
+            session_pull(session).unwrap_err(),
+            Error::UnknownSession(session)
+        );
+    }
+}
@@ -0,0 +1,70 @@
+//! serde support for PersonSnapshot and HealthAnalysis (`serde` feature)
+//!
+//! cxx bridges cannot make a derive conditional, so the impls are derived
+//! here through remote definitions that mirror the bridge structs field
+//! for field; the compiler rejects them if the two ever drift apart.
+//! HealthAnalysis is only ever written (by the HTTP server and webhooks),
+//! so it only gets Serialize.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ffi;
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "ffi::PersonSnapshot")]
+struct PersonSnapshotDef {
+    name: String,
+    age: u32,
+    height_m: f64,
+    email: String,
+    phone: String,
+    street: String,
+    city: String,
+    postal_code: String,
+}
+
+impl Serialize for ffi::PersonSnapshot {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PersonSnapshotDef::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ffi::PersonSnapshot {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        PersonSnapshotDef::deserialize(deserializer)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(remote = "ffi::HealthAnalysis")]
+struct HealthAnalysisDef {
+    bmi: f64,
+    risk_score: f64,
+    recommendation: String,
+    city_risk_factor: f64,
+    extra_risk_factor: f64,
+    risk_factors: u32,
+}
+
+impl Serialize for ffi::HealthAnalysis {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        HealthAnalysisDef::serialize(self, serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::tests::snapshot;
+    use crate::ffi;
+
+    #[test]
+    fn test_snapshot_round_trips_through_json() {
+        let original = snapshot(30, 1.75, "Boston");
+        let json = serde_json::to_string(&original).unwrap();
+        let parsed: ffi::PersonSnapshot = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.name, original.name);
+        assert_eq!(parsed.age, 30);
+        assert_eq!(parsed.city, "Boston");
+    }
+}
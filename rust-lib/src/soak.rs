@@ -0,0 +1,264 @@
+//! Soak tests for the thread-safety contract in `state.rs`
+//!
+//! Many threads hammer the registry, observer registration, sessions, the
+//! event bus and cancel tokens at once, released together by a `Barrier`
+//! so the calls really overlap. Each test checks what contention must not
+//! break: ids stay unique, reads never see a half-applied write, results
+//! keep push order and every registration can be removed again. Each thread
+//! runs `RUST_LIB_SOAK_ROUNDS` rounds (200 by default); raise it for a
+//! longer soak. Other tests run alongside, so only this module's own ids
+//! and events are checked.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+use crate::batch::tests::snapshot;
+use crate::cancel::{cancel, create_cancel_token, release_cancel_token, CancelFlag};
+use crate::ffi;
+use crate::observers::{add_observer, notify, unregister_risk_observer};
+use crate::session::{close_session, open_session, push_snapshot, session_pull};
+
+const THREADS: usize = 8;
+const DEFAULT_ROUNDS: usize = 200;
+
+/// Between the 1000.0 threshold of the observer unit test and the scores
+/// real analyses produce, so neither sees the other's notifications
+const SOAK_THRESHOLD: f64 = 400.0;
+const SOAK_RISK: f64 = 500.0;
+
+fn rounds() -> usize {
+    std::env::var("RUST_LIB_SOAK_ROUNDS")
+        .ok()
+        .and_then(|rounds| rounds.parse().ok())
+        .unwrap_or(DEFAULT_ROUNDS)
+}
+
+/// Run `work(thread_index)` on `THREADS` threads started together
+fn on_threads<T: Send + 'static>(work: impl Fn(usize) -> T + Send + Sync + 'static) -> Vec<T> {
+    let work = Arc::new(work);
+    let barrier = Arc::new(Barrier::new(THREADS));
+    let threads: Vec<_> = (0..THREADS)
+        .map(|index| {
+            let (work, barrier) = (Arc::clone(&work), Arc::clone(&barrier));
+            thread::spawn(move || {
+                barrier.wait();
+                work(index)
+            })
+        })
+        .collect();
+    threads.into_iter().map(|t| t.join().unwrap()).collect()
+}
+
+// Callback wrappers cross threads; losing one of these impls would only
+// show up as a compile error in some distant module
+#[cfg(not(feature = "stub-cpp"))]
+#[test]
+fn test_callback_types_are_send_and_sync() {
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    // Called from any analysis or worker thread, possibly concurrently
+    assert_send::<ffi::RiskObserver>();
+    assert_sync::<ffi::RiskObserver>();
+    assert_send::<ffi::EventSubscriber>();
+    assert_sync::<ffi::EventSubscriber>();
+    assert_send::<ffi::SessionSink>();
+    assert_sync::<ffi::SessionSink>();
+    assert_send::<ffi::RiskFactorHook>();
+    assert_sync::<ffi::RiskFactorHook>();
+    // Moved to the one thread that calls them
+    assert_send::<ffi::ResultSink>();
+    assert_send::<ffi::JobCallback>();
+    // Shared by the batch workers for the duration of one call
+    assert_sync::<ffi::PersonPredicate>();
+}
+
+#[cfg(feature = "registry")]
+#[test]
+fn test_registry_under_contention() {
+    use crate::events::{add_handler, unsubscribe};
+    use crate::registry::{
+        get_registered_person, notify_person_dropped, register_person, registry_entries,
+        unregister_person, update_registered_person,
+    };
+
+    let registered_events = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&registered_events);
+    let subscription = add_handler(
+        ffi::EventKind::PersonRegistered,
+        Arc::new(move |_: &ffi::BusEvent| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }),
+    );
+
+    // A reader walking every entry while the writers run
+    let stop = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let stop = Arc::clone(&stop);
+        thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                for (_, entry) in registry_entries() {
+                    if let Some(age) = entry.name.strip_prefix("soak-age-") {
+                        assert_eq!(age.parse::<u32>().unwrap(), entry.age, "torn entry");
+                    }
+                }
+            }
+        })
+    };
+
+    let rounds = rounds();
+    let ids = on_threads(move |index| {
+        let mut ids = Vec::with_capacity(rounds);
+        for round in 0..rounds {
+            let age = (index * rounds + round) as u32 % 100;
+            let mut person = snapshot(age, 1.75, "Boston");
+            person.name = format!("soak-age-{age}");
+            let id = register_person(person.clone());
+            assert_eq!(get_registered_person(id).unwrap().age, age);
+
+            person.age = (age + 1) % 100;
+            person.name = format!("soak-age-{}", person.age);
+            update_registered_person(id, person.clone()).unwrap();
+            assert_eq!(get_registered_person(id).unwrap().name, person.name);
+
+            // Half go through each removal path
+            if round % 2 == 0 {
+                assert!(unregister_person(id));
+            } else {
+                assert!(notify_person_dropped(id));
+            }
+            assert!(!unregister_person(id));
+            assert!(get_registered_person(id).is_err());
+            ids.push(id);
+        }
+        ids
+    });
+    stop.store(true, Ordering::Relaxed);
+    reader.join().unwrap();
+    assert!(unsubscribe(subscription));
+
+    let all: HashSet<u64> = ids.iter().flatten().copied().collect();
+    assert_eq!(all.len(), THREADS * rounds, "an id was issued twice");
+    assert!(registered_events.load(Ordering::Relaxed) >= THREADS * rounds);
+}
+
+#[test]
+fn test_observers_register_while_notifying() {
+    fn soak_analysis() -> ffi::HealthAnalysis {
+        let mut analysis = crate::compute_health(&snapshot(30, 1.75, "Boston"), 70.0);
+        analysis.risk_score = SOAK_RISK;
+        analysis
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let notifiers: Vec<_> = (0..4)
+        .map(|_| {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                let analysis = soak_analysis();
+                while !stop.load(Ordering::Relaxed) {
+                    notify(&analysis);
+                }
+            })
+        })
+        .collect();
+
+    let rounds = rounds();
+    on_threads(move |_| {
+        let analysis = soak_analysis();
+        for _ in 0..rounds {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let counter = Arc::clone(&calls);
+            let id = add_observer(
+                SOAK_THRESHOLD,
+                Arc::new(move |analysis: &ffi::HealthAnalysis| {
+                    if analysis.risk_score == SOAK_RISK {
+                        counter.fetch_add(1, Ordering::Relaxed);
+                    }
+                }),
+            );
+            // Visible to every notification that starts after add_observer
+            notify(&analysis);
+            assert!(calls.load(Ordering::Relaxed) > 0);
+            assert!(unregister_risk_observer(id));
+            assert!(!unregister_risk_observer(id));
+        }
+    });
+    stop.store(true, Ordering::Relaxed);
+    for notifier in notifiers {
+        notifier.join().unwrap();
+    }
+}
+
+#[test]
+fn test_sessions_in_parallel() {
+    let pushes = rounds() as u64;
+    on_threads(move |_| {
+        let session = open_session();
+        let mut results = Vec::new();
+        for _ in 0..pushes {
+            push_snapshot(session, snapshot(30, 1.75, "Boston"), 70.0).unwrap();
+            results.extend(session_pull(session).unwrap());
+        }
+        while (results.len() as u64) < pushes {
+            results.extend(session_pull(session).unwrap());
+            thread::yield_now();
+        }
+        assert!(close_session(session));
+
+        let sequences: Vec<u64> = results.iter().map(|result| result.sequence).collect();
+        assert_eq!(sequences, (0..pushes).collect::<Vec<_>>());
+    });
+}
+
+#[test]
+fn test_one_session_many_pushers() {
+    let session = open_session();
+    let pushes = rounds();
+    let sequences = on_threads(move |_| {
+        (0..pushes)
+            .map(|_| push_snapshot(session, snapshot(30, 1.75, "Boston"), 70.0).unwrap())
+            .collect::<Vec<_>>()
+    });
+    let issued: HashSet<u64> = sequences.iter().flatten().copied().collect();
+    assert_eq!(
+        issued.len(),
+        THREADS * pushes,
+        "a sequence number was issued twice"
+    );
+
+    let mut results = Vec::new();
+    while results.len() < THREADS * pushes {
+        results.extend(session_pull(session).unwrap());
+        thread::yield_now();
+    }
+    assert!(close_session(session));
+
+    // The worker analyzes in sequence order, whichever thread pushed
+    let delivered: Vec<u64> = results.iter().map(|result| result.sequence).collect();
+    assert_eq!(
+        delivered,
+        (0..(THREADS * pushes) as u64).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_cancel_tokens_under_contention() {
+    let rounds = rounds();
+    on_threads(move |_| {
+        for _ in 0..rounds {
+            let token = create_cancel_token();
+            let flag = CancelFlag::for_token(token);
+            assert!(!flag.is_cancelled());
+            cancel(token);
+            assert!(flag.is_cancelled());
+
+            // A flag taken before release keeps its final state
+            release_cancel_token(token);
+            assert!(flag.is_cancelled());
+            assert!(!CancelFlag::for_token(token).is_cancelled());
+        }
+    });
+}
@@ -0,0 +1,128 @@
+//! Process-wide state shared by every bridge call
+//!
+//! # Thread-safety contract
+//!
+//! Every function exported through the bridge may be called from any C++
+//! thread, including several threads at once:
+//!
+//! - All Rust-side global state (cancel tokens, jobs, the ingest queue and
+//!   anything added later) lives in a [`Global`], a lazily initialised
+//!   `RwLock`. Lookups take the read lock, so concurrent readers never
+//!   block each other. The one exception is the job scheduler queue, which
+//!   needs a `Condvar` and therefore a `Mutex` (see `scheduler.rs`).
+//! - Locks are poison-tolerant. A panic on one thread never turns into a
+//!   panic (and therefore an abort across the FFI boundary) on another.
+//! - No lock is held while calling back into C++, so a callback may call
+//!   back into the bridge without deadlocking.
+//! - Opaque C++ types (`Person`, `ContactInfo`, `Address`) are only
+//!   borrowed for the duration of a call and are never sent to another
+//!   thread. Work that outlives the call copies them into a `PersonSnapshot`.
+//! - C++ callback objects are `Send` only when Rust can invoke them from a
+//!   background thread (see `callbacks.h`); the wrapped C++ function must
+//!   be thread-safe in that case.
+
+#[cfg(any(feature = "webhook", feature = "sync", feature = "watch"))]
+use std::sync::{Condvar, Mutex};
+use std::sync::{OnceLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+#[cfg(any(feature = "webhook", feature = "sync", feature = "watch"))]
+use std::time::Duration;
+
+/// A lazily initialised, poison-tolerant `RwLock` for use in statics
+pub(crate) struct Global<T> {
+    cell: OnceLock<RwLock<T>>,
+    init: fn() -> T,
+}
+
+impl<T> Global<T> {
+    pub(crate) const fn new(init: fn() -> T) -> Self {
+        Global {
+            cell: OnceLock::new(),
+            init,
+        }
+    }
+
+    fn lock(&self) -> &RwLock<T> {
+        self.cell.get_or_init(|| RwLock::new((self.init)()))
+    }
+
+    pub(crate) fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.lock().read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.lock().write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Tells a background thread to exit, waking it from a timed wait
+#[cfg(any(feature = "webhook", feature = "sync", feature = "watch"))]
+#[derive(Default)]
+pub(crate) struct StopSignal {
+    stopped: Mutex<bool>,
+    wake: Condvar,
+}
+
+#[cfg(any(feature = "webhook", feature = "sync", feature = "watch"))]
+impl StopSignal {
+    /// Sleep for `delay` unless stopped first; true if stopped
+    pub(crate) fn wait(&self, delay: Duration) -> bool {
+        let stopped = self.stopped.lock().unwrap_or_else(PoisonError::into_inner);
+        let (stopped, _) = self
+            .wake
+            .wait_timeout_while(stopped, delay, |stopped| !*stopped)
+            .unwrap_or_else(PoisonError::into_inner);
+        *stopped
+    }
+
+    pub(crate) fn stop(&self) {
+        *self.stopped.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        self.wake.notify_all();
+    }
+}
+
+/// Whether the bridge may be called from several threads at once
+///
+/// Always true for this build; exported so C++ hosts can assert the
+/// contract at startup instead of relying on documentation.
+pub(crate) fn is_thread_safe() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    static COUNTER: Global<u64> = Global::new(|| 0);
+
+    #[test]
+    fn test_concurrent_writers() {
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                thread::spawn(|| {
+                    for _ in 0..1000 {
+                        *COUNTER.write() += 1;
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(*COUNTER.read(), 8000);
+    }
+
+    #[test]
+    fn test_poisoned_lock_still_usable() {
+        static POISONED: Global<Vec<u32>> = Global::new(Vec::new);
+
+        let _ = thread::spawn(|| {
+            let _guard = POISONED.write();
+            panic!("poison the lock");
+        })
+        .join();
+
+        POISONED.write().push(1);
+        assert_eq!(*POISONED.read(), vec![1]);
+    }
+}
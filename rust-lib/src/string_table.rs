@@ -0,0 +1,208 @@
+//! String tables of pooled batch results
+//!
+//! A batch of a million people has a million recommendations and cities,
+//! but only a few dozen distinct ones: the recommendation depends on the
+//! score band and the number of risk factors alone (see templates.rs), and
+//! a cohort lives in a handful of cities. `analyze_health_batch_pooled`
+//! therefore renders each recommendation once and stores every distinct
+//! string once, back to back in one buffer, and its results hold indexes
+//! into that table instead of Strings of their own. The nightly run then
+//! allocates per distinct string rather than per person.
+//!
+//! Tables outlive the call that built them, so C++ can look strings up
+//! with `get_string(table_id, index)` while it walks the results; it
+//! frees a table with `release_string_table`.
+
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::error::Error;
+use crate::leak_check::{self, Handle};
+use crate::memory;
+use crate::state::Global;
+
+/// Distinct strings, one after the other in `text`
+#[derive(Debug, Default)]
+pub(crate) struct StringTable {
+    text: String,
+    spans: Vec<Range<usize>>,
+}
+
+impl StringTable {
+    pub(crate) fn get(&self, index: u32) -> Option<&str> {
+        let span = self.spans.get(index as usize)?;
+        Some(&self.text[span.clone()])
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// Length of all strings together
+    pub(crate) fn bytes(&self) -> usize {
+        self.text.len()
+    }
+}
+
+/// Fills a StringTable, storing each string once
+#[derive(Default)]
+pub(crate) struct StringTableBuilder {
+    table: StringTable,
+    indexes: HashMap<Box<str>, u32>,
+    /// Indexes by recommendation id
+    recommendations: HashMap<u32, u32>,
+}
+
+impl StringTableBuilder {
+    /// Make room for `strings` more strings of `bytes` in all
+    pub(crate) fn reserve(&mut self, strings: usize, bytes: usize) {
+        self.table.text.reserve(bytes);
+        self.table.spans.reserve(strings);
+        self.indexes.reserve(strings);
+    }
+
+    /// Index of `text`, added if it is not in the table yet
+    pub(crate) fn intern(&mut self, text: &str) -> u32 {
+        if let Some(&index) = self.indexes.get(text) {
+            return index;
+        }
+        let index = self.table.spans.len() as u32;
+        let start = self.table.text.len();
+        self.table.text.push_str(text);
+        self.table.spans.push(start..self.table.text.len());
+        self.indexes.insert(text.into(), index);
+        index
+    }
+
+    /// Index of the recommendation with `id` (see templates.rs), rendered
+    /// by `render` the first time it is asked for
+    pub(crate) fn recommendation(&mut self, id: u32, render: impl FnOnce() -> String) -> u32 {
+        if let Some(&index) = self.recommendations.get(&id) {
+            return index;
+        }
+        let index = self.intern(&render());
+        self.recommendations.insert(id, index);
+        index
+    }
+
+    pub(crate) fn get(&self, index: u32) -> &str {
+        self.table.get(index).unwrap_or_default()
+    }
+
+    /// The table built so far; the builder starts over, and its lookups
+    /// keep their capacity
+    pub(crate) fn take(&mut self) -> StringTable {
+        self.indexes.clear();
+        self.recommendations.clear();
+        std::mem::take(&mut self.table)
+    }
+
+    /// Heap of the builder's buffers and lookups
+    pub(crate) fn heap_bytes(&self) -> usize {
+        self.table.text.capacity()
+            + self.table.spans.capacity() * size_of::<Range<usize>>()
+            + memory::table_bytes::<(Box<str>, u32)>(self.indexes.capacity())
+            + self.indexes.keys().map(|text| text.len()).sum::<usize>()
+            + memory::table_bytes::<(u32, u32)>(self.recommendations.capacity())
+    }
+}
+
+static TABLES: Global<HashMap<u64, Arc<StringTable>>> = Global::new(HashMap::new);
+
+static NEXT_TABLE: AtomicU64 = AtomicU64::new(1);
+
+/// Keep `table` for lookups from C++ and return its id
+pub(crate) fn publish(table: StringTable) -> u64 {
+    let id = NEXT_TABLE.fetch_add(1, Ordering::Relaxed);
+    TABLES.write().insert(id, Arc::new(table));
+    leak_check::issued(Handle::StringTable, id);
+    id
+}
+
+/// The string at `index` of table `table_id`, for the indexes of a
+/// PooledBatch
+pub fn get_string(table_id: u64, index: u32) -> Result<String, Error> {
+    let table = TABLES
+        .read()
+        .get(&table_id)
+        .cloned()
+        .ok_or(Error::UnknownStringTable(table_id))?;
+    table.get(index).map(str::to_string).ok_or_else(|| {
+        Error::InvalidArgument(format!(
+            "string table {table_id} has {} strings, no index {index}",
+            table.len()
+        ))
+    })
+}
+
+/// Heap of every table not yet released
+pub(crate) fn heap_bytes() -> usize {
+    let tables = TABLES.read();
+    let texts: usize = tables
+        .values()
+        .map(|table| table.text.capacity() + table.spans.capacity() * size_of::<Range<usize>>())
+        .sum();
+    memory::table_bytes::<(u64, Arc<StringTable>)>(tables.capacity())
+        + tables.len() * size_of::<StringTable>()
+        + texts
+}
+
+/// Free a table; false if the id was unknown
+pub fn release_string_table(table_id: u64) -> bool {
+    let released = TABLES.write().remove(&table_id).is_some();
+    if released {
+        leak_check::released(Handle::StringTable, table_id);
+    }
+    released
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_stores_each_string_once() {
+        let mut strings = StringTableBuilder::default();
+        let boston = strings.intern("Boston");
+        assert_eq!(strings.intern("Zürich"), 1);
+        assert_eq!(strings.intern("Boston"), boston);
+
+        let mut rendered = 0;
+        for _ in 0..3 {
+            strings.recommendation(9, || {
+                rendered += 1;
+                "Good health.".to_string()
+            });
+        }
+        assert_eq!(rendered, 1);
+        // A recommendation equal to a city shares its entry
+        assert_eq!(strings.recommendation(2, || "Boston".into()), boston);
+
+        let table = strings.take();
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.get(1), Some("Zürich"));
+        assert_eq!(table.get(2), Some("Good health."));
+        assert_eq!(table.text, "BostonZürichGood health.");
+        assert_eq!(table.get(3), None);
+
+        // The builder starts over for the next table
+        assert_eq!(strings.intern("Zürich"), 0);
+        assert_eq!(strings.take().text, "Zürich");
+    }
+
+    #[test]
+    fn test_published_table_until_released() {
+        let mut strings = StringTableBuilder::default();
+        strings.intern("Boston");
+        let id = publish(strings.take());
+
+        assert_eq!(get_string(id, 0).unwrap(), "Boston");
+        assert!(matches!(get_string(id, 1), Err(Error::InvalidArgument(_))));
+        assert!(release_string_table(id));
+        assert!(!release_string_table(id));
+        assert_eq!(get_string(id, 0), Err(Error::UnknownStringTable(id)));
+    }
+}
@@ -0,0 +1,194 @@
+//! Pure-Rust stand-ins for the C++ Person model (`stub-cpp` feature)
+//!
+//! The opaque C++ types become plain structs and the getter shims read their
+//! fields, so process_person, analyze_health, snapshot_person and the
+//! validation run without building or calling any of this crate's C++:
+//! under `cargo miri test`, or as a quick logic-only test run. The C++
+//! callbacks (sinks, predicates, observers, hooks) and the bridge functions
+//! taking them are left out of this build. Like the real shims, the structs
+//! and getters are generated from cpp-app/person.schema.yaml.
+
+use std::borrow::Cow;
+use std::str::Utf8Error;
+
+/// The bytes of a std::string, with the CxxString methods the crate uses
+/// Like the real one it can hold NULs and invalid UTF-8
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StubString(Vec<u8>);
+
+impl StubString {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn to_str(&self) -> Result<&str, Utf8Error> {
+        std::str::from_utf8(&self.0)
+    }
+
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.0)
+    }
+}
+
+impl From<&str> for StubString {
+    fn from(value: &str) -> Self {
+        StubString(value.as_bytes().to_vec())
+    }
+}
+
+impl From<&[u8]> for StubString {
+    fn from(value: &[u8]) -> Self {
+        StubString(value.to_vec())
+    }
+}
+
+// @generated begin: cargo xtask codegen (cpp-app/person.schema.yaml)
+#[derive(Clone, Debug, Default)]
+pub struct Person {
+    pub age: u32,
+    pub height: f64,
+    pub name: StubString,
+    pub contact: ContactInfo,
+}
+
+pub fn get_person_age(person: &Person) -> u32 {
+    person.age
+}
+
+pub fn get_person_height(person: &Person) -> f64 {
+    person.height
+}
+
+pub fn get_person_name(person: &Person) -> &StubString {
+    &person.name
+}
+
+pub fn get_person_contact(person: &Person) -> &ContactInfo {
+    &person.contact
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ContactInfo {
+    pub email: StubString,
+    pub phone: StubString,
+    pub address: Address,
+}
+
+pub fn get_contact_email(contact: &ContactInfo) -> &StubString {
+    &contact.email
+}
+
+pub fn get_contact_phone(contact: &ContactInfo) -> &StubString {
+    &contact.phone
+}
+
+pub fn get_contact_address(contact: &ContactInfo) -> &Address {
+    &contact.address
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Address {
+    pub street: StubString,
+    pub city: StubString,
+    pub postal_code: StubString,
+}
+
+pub fn get_address_street(address: &Address) -> &StubString {
+    &address.street
+}
+
+pub fn get_address_city(address: &Address) -> &StubString {
+    &address.city
+}
+
+pub fn get_address_postal_code(address: &Address) -> &StubString {
+    &address.postal_code
+}
+// @generated end
+
+/// Stands in for the C++ fill_person_snapshot (cpp-app/person_snapshot.h)
+pub fn fill_person_snapshot(person: &Person, snapshot: &mut crate::ffi::PersonSnapshot) {
+    let contact = &person.contact;
+    let address = &contact.address;
+    snapshot.name = person.name.to_string_lossy().into_owned();
+    snapshot.age = person.age;
+    snapshot.height_m = person.height;
+    snapshot.email = contact.email.to_string_lossy().into_owned();
+    snapshot.phone = contact.phone.to_string_lossy().into_owned();
+    snapshot.street = address.street.to_string_lossy().into_owned();
+    snapshot.city = address.city.to_string_lossy().into_owned();
+    snapshot.postal_code = address.postal_code.to_string_lossy().into_owned();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analyze_health, process_person, snapshot_person, snapshot_person_with_encoding,
+        validate_contact,
+    };
+
+    fn alice() -> Person {
+        Person {
+            age: 30,
+            height: 1.75,
+            name: "Alice".into(),
+            contact: ContactInfo {
+                email: "alice@example.com".into(),
+                phone: "555-0100".into(),
+                address: Address {
+                    street: "1 Main St".into(),
+                    city: "Boston".into(),
+                    postal_code: "02101".into(),
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn test_entry_points_on_stub_person() {
+        let person = alice();
+        let info = process_person(&person);
+        assert!(info.is_adult);
+        assert_eq!(info.bmi_category, 1);
+        assert_eq!(info.city, "Boston");
+
+        let snapshot = snapshot_person(&person);
+        assert_eq!(snapshot.name, "Alice");
+        assert_eq!(snapshot.postal_code, "02101");
+
+        let analysis = analyze_health(&person, 70.0);
+        assert!((analysis.bmi - 22.86).abs() < 0.01);
+        assert!(validate_contact(&person.contact));
+    }
+
+    #[test]
+    fn test_invalid_utf8_reads_like_cpp() {
+        let mut person = alice();
+        person.name = b"caf\xe9"[..].into();
+        person.contact.email = "not-an-email".into();
+        assert_eq!(process_person(&person).name_length, 0);
+        assert_eq!(snapshot_person(&person).name, "caf\u{fffd}");
+        assert!(!validate_contact(&person.contact));
+    }
+
+    #[test]
+    fn test_latin1_snapshot() {
+        let mut person = alice();
+        person.name = b"M\xfcller"[..].into();
+        person.contact.address.city = b"Z\xfcrich"[..].into();
+        let snapshot = snapshot_person_with_encoding(&person, "ISO-8859-1").unwrap();
+        assert_eq!(snapshot.name, "Müller");
+        assert_eq!(snapshot.city, "Zürich");
+        assert_eq!(snapshot.email, "alice@example.com");
+        assert!(snapshot_person_with_encoding(&person, "UTF-8").is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! Data subject requests: access and erasure (GDPR articles 15 and 17)
+//!
+//! `export_subject_data` collects what the library holds about a
+//! registered person into one JSON document, and `erase_subject` deletes
+//! it. Both go by registry id, and cover:
+//!
+//! - the registry entry, or the record that the C++ Person was destroyed
+//! - the consent recorded with set_consent
+//! - the re-validation state and the alerts not yet polled
+//! - the cached analyses of the registered snapshot
+//!
+//! A destroyed person's cached analyses are forgotten by
+//! notify_person_dropped, which keeps only the id; erasing the record then
+//! reports none.
+//!
+//! The analysis cache is keyed by a person's fields rather than an id, so
+//! entries for versions replaced by update_registered_person are not found
+//! and stay until they are evicted; call clear_analysis_cache as well to be
+//! sure. The webhook and sync queues hold analyses without anything that
+//! identifies a person, and the ingest queue and sessions hold snapshots
+//! that were never registered. What the host or an endpoint has already
+//! received is for them to erase.
+
+#[cfg(feature = "serde")]
+use serde_json::{json, Value};
+
+use crate::analysis_cache;
+#[cfg(feature = "serde")]
+use crate::error::Error;
+use crate::ffi;
+use crate::registry;
+use crate::revalidation;
+
+/// Everything held about `person_id`, as JSON:
+///
+/// ```json
+/// {"person_id": 7, "status": "registered", "person": {"name": ...},
+///  "consent": {"analysis": true, "exports": false, "sharing": false},
+///  "validation": {"invalid": false, "pending_alerts": []},
+///  "cached_analyses": [{"weight_kg": 70.0, "locale": "en", "analysis": {...}}]}
+/// ```
+///
+/// `status` is "registered", "destroyed" (notify_person_dropped) or
+/// "unknown", and `person` is null unless registered.
+#[cfg(feature = "serde")]
+pub(crate) fn export_subject_data(person_id: u64) -> String {
+    crate::audit::record("export_subject_data", person_id, crate::audit::PERSON_FIELDS);
+    let (status, person) = match registry::lookup(person_id) {
+        Ok(person) => ("registered", Some(person)),
+        Err(Error::PersonDropped(_)) => ("destroyed", None),
+        Err(_) => ("unknown", None),
+    };
+    let cached: Vec<Value> = person
+        .iter()
+        .flat_map(analysis_cache::cached_analyses_of)
+        .map(|(weight_kg, locale, analysis)| {
+            json!({ "weight_kg": weight_kg, "locale": &*locale, "analysis": analysis })
+        })
+        .collect();
+    let consent = crate::consent::get_consent(person_id);
+    let (invalid, alerts) = revalidation::validation_state(person_id);
+    let alerts: Vec<Value> = alerts
+        .iter()
+        .map(|alert| json!({ "issues": issues_json(&alert.issues) }))
+        .collect();
+    json!({
+        "person_id": person_id,
+        "status": status,
+        "person": person,
+        "consent": {
+            "analysis": consent.analysis,
+            "exports": consent.exports,
+            "sharing": consent.sharing,
+        },
+        "validation": { "invalid": invalid, "pending_alerts": alerts },
+        "cached_analyses": cached,
+    })
+    .to_string()
+}
+
+#[cfg(feature = "serde")]
+fn issues_json(issues: &[ffi::ValidationIssue]) -> Vec<Value> {
+    issues
+        .iter()
+        .map(|issue| {
+            let severity = if issue.severity == ffi::Severity::Error {
+                "error"
+            } else {
+                "warning"
+            };
+            json!({ "field": issue.field, "message": issue.message, "severity": severity })
+        })
+        .collect()
+}
+
+/// Delete what export_subject_data would report for `person_id`
+///
+/// Afterwards the id is unknown, as if it had never been registered.
+/// Erasing an unknown id does nothing and reports so.
+pub(crate) fn erase_subject(person_id: u64) -> ffi::EraseReport {
+    let (entry, was_destroyed) = registry::erase_person(person_id);
+    // After the registry, so a set_consent or a sweep in progress cannot
+    // bring a record back
+    let consent_record = crate::consent::forget(person_id);
+    let validation_alerts = revalidation::forget_person(person_id);
+    ffi::EraseReport {
+        registry_entry: entry.is_some(),
+        destroyed_record: was_destroyed,
+        consent_record,
+        validation_alerts,
+        cached_analyses: entry.as_ref().map_or(0, analysis_cache::forget_person),
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+
+    #[test]
+    fn test_export_and_erase() {
+        let mut person = snapshot(30, 1.75, "");
+        person.name = "subject-rights".to_string();
+        let id = registry::register_person(person.clone());
+        crate::consent::set_consent(
+            id,
+            ffi::ConsentFlags {
+                analysis: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        analysis_cache::get_or_compute(&person, 70.0, &crate::config::current(), "en", || {
+            crate::analyze_snapshot(&person, 70.0)
+        });
+        revalidation::revalidate_now();
+
+        let data: Value = serde_json::from_str(&export_subject_data(id)).unwrap();
+        assert_eq!(data["person_id"], json!(id));
+        assert_eq!(data["status"], "registered");
+        assert_eq!(data["person"]["name"], "subject-rights");
+        assert_eq!(
+            data["consent"],
+            json!({ "analysis": true, "exports": false, "sharing": false })
+        );
+        assert_eq!(data["validation"]["invalid"], json!(true));
+        // The revalidation test may poll the alert first
+        if let Some(alert) = data["validation"]["pending_alerts"].get(0) {
+            assert_eq!(alert["issues"][0]["field"], "city");
+        }
+        let cached = data["cached_analyses"].as_array().unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0]["weight_kg"], json!(70.0));
+
+        let report = erase_subject(id);
+        assert!(report.registry_entry);
+        assert!(!report.destroyed_record);
+        assert!(report.consent_record);
+        assert_eq!(report.cached_analyses, 1);
+        assert!(analysis_cache::cached_analyses_of(&person).is_empty());
+
+        let data: Value = serde_json::from_str(&export_subject_data(id)).unwrap();
+        assert_eq!(data["status"], "unknown");
+        assert_eq!(data["person"], Value::Null);
+        assert_eq!(
+            data["validation"],
+            json!({ "invalid": false, "pending_alerts": [] })
+        );
+        // A sweep after erasure has nothing to alert about
+        revalidation::revalidate_now();
+        assert_eq!(erase_subject(id).validation_alerts, 0);
+    }
+
+    #[test]
+    fn test_destroyed_person() {
+        let mut person = snapshot(30, 1.75, "Boston");
+        person.name = "subject-rights-destroyed".to_string();
+        let id = registry::register_person(person.clone());
+        analysis_cache::get_or_compute(&person, 70.0, &crate::config::current(), "en", || {
+            crate::analyze_snapshot(&person, 70.0)
+        });
+        registry::notify_person_dropped(id);
+        assert!(analysis_cache::cached_analyses_of(&person).is_empty());
+        let data: Value = serde_json::from_str(&export_subject_data(id)).unwrap();
+        assert_eq!(data["status"], "destroyed");
+
+        let report = erase_subject(id);
+        assert!(!report.registry_entry);
+        assert!(report.destroyed_record);
+        assert_eq!(report.cached_analyses, 0);
+        assert_eq!(
+            registry::get_registered_person(id).unwrap_err(),
+            Error::UnknownPerson(id)
+        );
+    }
+}
@@ -0,0 +1,438 @@
+//! Message templates for recommendations, with plurals
+//!
+//! Recommendation text is rendered from per-language templates instead of
+//! being pasted together, so a translation can put the count where its
+//! grammar needs it and pick the right plural form: "1 risk factor", but
+//! "2 фактора риска" and "5 факторов риска" in Russian.
+//!
+//! Templates use a subset of ICU MessageFormat: `{name}` inserts an
+//! argument, and `{n, plural, =0 {...} one {...} other {...}}` picks a
+//! branch by exact value first, then by the language's CLDR plural
+//! category, falling back to `other`; `#` in a branch is the number. There
+//! is no quoting, so templates cannot contain literal braces or `#`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::error::Error;
+use crate::ffi;
+use crate::format::parse_locale;
+
+/// Most texts RENDERED holds per thread before it starts over; three bands
+/// times a handful of factor counts per language come nowhere near it
+const MAX_RENDERED: usize = 1024;
+
+/// What a recommendation's text depends on: band, number of risk factors
+/// and language
+type TextKey = (Band, u32, &'static str);
+
+thread_local! {
+    /// Texts already rendered
+    ///
+    /// A batch renders the same few texts for every person; copying one out
+    /// of here skips parsing the templates, and the copy is allocated at its
+    /// exact length instead of keeping the capacity a rendering grew to.
+    static RENDERED: RefCell<HashMap<TextKey, Box<str>>> = RefCell::new(HashMap::new());
+}
+
+/// A template argument
+#[derive(Clone, Copy)]
+pub(crate) enum Arg<'a> {
+    Count(u64),
+    Text(&'a str),
+}
+
+/// CLDR plural category of the integer `n` in `language`
+fn plural_category(language: &str, n: u64) -> &'static str {
+    let (last, last_two) = (n % 10, n % 100);
+    match language {
+        "fr" if n <= 1 => "one",
+        "ru" | "uk" if last == 1 && last_two != 11 => "one",
+        "ru" | "uk" | "pl" if (2..=4).contains(&last) && !(12..=14).contains(&last_two) => "few",
+        "ru" | "uk" => "many",
+        "pl" if n == 1 => "one",
+        "pl" => "many",
+        "fr" => "other",
+        _ if n == 1 => "one",
+        _ => "other",
+    }
+}
+
+/// Index of the `}` closing the `{` at `open`
+fn closing_brace(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The body of the `{n, plural, ...}` branch for `n`
+fn plural_branch<'t>(branches: &'t str, n: u64, language: &str) -> Option<&'t str> {
+    let exact = format!("={n}");
+    let category = plural_category(language, n);
+    let (mut by_category, mut other) = (None, None);
+    let mut rest = branches;
+    while let Some(open) = rest.find('{') {
+        let selector = rest[..open].trim();
+        let close = closing_brace(rest, open)?;
+        let body = &rest[open + 1..close];
+        if selector == exact {
+            return Some(body);
+        }
+        if selector == category {
+            by_category = Some(body);
+        }
+        if selector == "other" {
+            other = Some(body);
+        }
+        rest = &rest[close + 1..];
+    }
+    by_category.or(other)
+}
+
+fn render_into(
+    out: &mut String,
+    template: &str,
+    args: &[(&str, Arg)],
+    language: &str,
+    number: Option<u64>,
+) {
+    let mut rest = template;
+    while let Some(special) = rest.find(['{', '#']) {
+        out.push_str(&rest[..special]);
+        if rest[special..].starts_with('#') {
+            match number {
+                Some(n) => out.push_str(&n.to_string()),
+                None => out.push('#'),
+            }
+            rest = &rest[special + 1..];
+            continue;
+        }
+        let Some(close) = closing_brace(rest, special) else {
+            // Unbalanced: the rest is plain text
+            rest = &rest[special..];
+            break;
+        };
+        let placeholder = &rest[special + 1..close];
+        let (name, format) = placeholder.split_once(',').unwrap_or((placeholder, ""));
+        let value = args
+            .iter()
+            .find(|(arg, _)| *arg == name.trim())
+            .map(|&(_, value)| value);
+        match (value, format.trim_start().strip_prefix("plural,")) {
+            (Some(Arg::Count(n)), Some(branches)) => {
+                if let Some(body) = plural_branch(branches, n, language) {
+                    render_into(out, body, args, language, Some(n));
+                }
+            }
+            (Some(Arg::Count(n)), None) => out.push_str(&n.to_string()),
+            (Some(Arg::Text(text)), None) => out.push_str(text),
+            // Unknown argument or format: left visible rather than dropped
+            _ => out.push_str(&rest[special..=close]),
+        }
+        rest = &rest[close + 1..];
+    }
+    out.push_str(rest);
+}
+
+/// `template` with its placeholders filled from `args`
+pub(crate) fn render(template: &str, args: &[(&str, Arg)], language: &str) -> String {
+    let mut out = String::with_capacity(template.len());
+    render_into(&mut out, template, args, language, None);
+    out
+}
+
+/// One language's recommendation templates
+struct Messages {
+    excellent: &'static str,
+    good: &'static str,
+    elevated: &'static str,
+    /// Inserted as `{factors}` into the three above
+    risk_factors: &'static str,
+}
+
+const ENGLISH: Messages = Messages {
+    excellent: "Excellent health profile. Maintain current lifestyle. {factors}",
+    good: "Good health. Consider minor lifestyle adjustments. {factors}",
+    elevated: "Elevated risk factors. Recommend consultation with healthcare provider. {factors}",
+    risk_factors: "{n, plural, =0 {No risk factors found.} one {# risk factor found.} \
+                   other {# risk factors found.}}",
+};
+
+const CATALOG: &[(&str, Messages)] = &[
+    ("en", ENGLISH),
+    (
+        "de",
+        Messages {
+            excellent: "Ausgezeichnetes Gesundheitsprofil. Behalten Sie Ihren Lebensstil bei. \
+                        {factors}",
+            good: "Gute Gesundheit. Erwägen Sie kleine Anpassungen Ihres Lebensstils. {factors}",
+            elevated: "Erhöhte Risikofaktoren. Wir empfehlen eine ärztliche Beratung. {factors}",
+            risk_factors: "{n, plural, =0 {Keine Risikofaktoren gefunden.} \
+                           one {# Risikofaktor gefunden.} other {# Risikofaktoren gefunden.}}",
+        },
+    ),
+    (
+        "es",
+        Messages {
+            excellent: "Excelente perfil de salud. Mantenga su estilo de vida actual. {factors}",
+            good: "Buena salud. Considere pequeños ajustes en su estilo de vida. {factors}",
+            elevated: "Factores de riesgo elevados. Se recomienda consultar a un profesional \
+                       de la salud. {factors}",
+            risk_factors: "{n, plural, =0 {No se encontraron factores de riesgo.} \
+                           one {Se encontró # factor de riesgo.} \
+                           other {Se encontraron # factores de riesgo.}}",
+        },
+    ),
+    (
+        "fr",
+        Messages {
+            excellent: "Excellent profil de santé. Conservez votre mode de vie actuel. {factors}",
+            good: "Bonne santé. Envisagez de légers ajustements de votre mode de vie. {factors}",
+            elevated: "Facteurs de risque élevés. Une consultation médicale est recommandée. \
+                       {factors}",
+            risk_factors: "{n, plural, =0 {Aucun facteur de risque détecté.} \
+                           one {# facteur de risque détecté.} \
+                           other {# facteurs de risque détectés.}}",
+        },
+    ),
+    (
+        "pl",
+        Messages {
+            excellent: "Doskonały profil zdrowia. Utrzymuj obecny styl życia. {factors}",
+            good: "Dobre zdrowie. Rozważ drobne zmiany stylu życia. {factors}",
+            elevated: "Podwyższone czynniki ryzyka. Zalecana konsultacja z lekarzem. {factors}",
+            risk_factors: "{n, plural, =0 {Nie znaleziono czynników ryzyka.} \
+                           one {Znaleziono # czynnik ryzyka.} few {Znaleziono # czynniki ryzyka.} \
+                           many {Znaleziono # czynników ryzyka.} other {Znaleziono # czynnika ryzyka.}}",
+        },
+    ),
+    (
+        "ru",
+        Messages {
+            excellent: "Отличный профиль здоровья. Сохраняйте текущий образ жизни. {factors}",
+            good: "Хорошее здоровье. Рассмотрите небольшие изменения образа жизни. {factors}",
+            elevated: "Повышенные факторы риска. Рекомендуется консультация врача. {factors}",
+            risk_factors: "{n, plural, =0 {Факторов риска не найдено.} \
+                           one {Найден # фактор риска.} few {Найдено # фактора риска.} \
+                           many {Найдено # факторов риска.} other {Найдено # фактора риска.}}",
+        },
+    ),
+];
+
+/// Which of the three recommendations a risk score gets
+///
+/// With the number of risk factors and the locale, this is all the text
+/// depends on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Band {
+    Excellent,
+    Good,
+    Elevated,
+}
+
+impl Band {
+    fn of(risk_score: f64) -> Band {
+        if risk_score < 1.2 {
+            Band::Excellent
+        } else if risk_score < 1.5 {
+            Band::Good
+        } else {
+            Band::Elevated
+        }
+    }
+}
+
+/// `HealthAnalysis.recommendation_id`: the band in the low two bits, the
+/// number of risk factors above them
+pub(crate) fn recommendation_id(risk_score: f64, risk_factors: u32) -> u32 {
+    risk_factors << 2 | Band::of(risk_score) as u32
+}
+
+/// The recommendation `id` stands for, in `locale`
+pub fn render_recommendation(id: u32, locale: &str) -> Result<String, Error> {
+    let band = match id & 3 {
+        0 => Band::Excellent,
+        1 => Band::Good,
+        2 => Band::Elevated,
+        _ => {
+            return Err(Error::InvalidArgument(format!(
+                "{id} is not a recommendation id"
+            )))
+        }
+    };
+    Ok(text(band, id >> 2, locale))
+}
+
+/// Recommendation for a risk score and the number of factors behind it
+///
+/// Languages without a translation get English.
+pub(crate) fn recommendation(risk_score: f64, risk_factors: u32, locale: &str) -> String {
+    text(Band::of(risk_score), risk_factors, locale)
+}
+
+fn text(band: Band, risk_factors: u32, locale: &str) -> String {
+    let (language, _) = parse_locale(locale);
+    let (language, messages) = CATALOG
+        .iter()
+        .find(|(code, _)| *code == language)
+        .map_or(("en", &ENGLISH), |(code, messages)| (*code, messages));
+
+    let template = match band {
+        Band::Excellent => messages.excellent,
+        Band::Good => messages.good,
+        Band::Elevated => messages.elevated,
+    };
+    RENDERED.with_borrow_mut(|rendered| {
+        if rendered.len() >= MAX_RENDERED {
+            rendered.clear();
+        }
+        let text = rendered.entry((band, risk_factors, language)).or_insert_with(|| {
+            let factors = render(
+                messages.risk_factors,
+                &[("n", Arg::Count(risk_factors.into()))],
+                language,
+            );
+            render(template, &[("factors", Arg::Text(&factors))], language).into_boxed_str()
+        });
+        String::from(&**text)
+    })
+}
+
+/// `analysis.recommendation` in another language
+pub(crate) fn localized_recommendation(analysis: &ffi::HealthAnalysis, locale: &str) -> String {
+    recommendation(analysis.risk_score, analysis.risk_factors, locale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let args = [("name", Arg::Text("Alice")), ("n", Arg::Count(3))];
+        assert_eq!(
+            render("Hi {name}, {n} left", &args, "en"),
+            "Hi Alice, 3 left"
+        );
+        assert_eq!(
+            render("{n, plural, =3 {three} other {# items}}", &args, "en"),
+            "three"
+        );
+        assert_eq!(
+            render("{n, plural, one {# item} other {# items}}", &args, "en"),
+            "3 items"
+        );
+        // Unknown arguments stay visible; stray text is kept
+        assert_eq!(render("{missing} # {", &args, "en"), "{missing} # {");
+        assert_eq!(
+            render("{name, plural, other {x}}", &args, "en"),
+            "{name, plural, other {x}}"
+        );
+    }
+
+    #[test]
+    fn test_plural_categories() {
+        let categories = |language| -> Vec<&str> {
+            [0, 1, 2, 5, 11, 21, 22, 25, 112]
+                .iter()
+                .map(|&n| plural_category(language, n))
+                .collect()
+        };
+        assert_eq!(
+            categories("en"),
+            ["other", "one", "other", "other", "other", "other", "other", "other", "other"]
+        );
+        assert_eq!(
+            categories("fr"),
+            ["one", "one", "other", "other", "other", "other", "other", "other", "other"]
+        );
+        assert_eq!(
+            categories("ru"),
+            ["many", "one", "few", "many", "many", "one", "few", "many", "many"]
+        );
+        assert_eq!(
+            categories("pl"),
+            ["many", "one", "few", "many", "many", "many", "few", "many", "many"]
+        );
+    }
+
+    #[test]
+    fn test_recommendation_counts() {
+        assert_eq!(
+            recommendation(1.0, 0, "en"),
+            "Excellent health profile. Maintain current lifestyle. No risk factors found."
+        );
+        assert_eq!(
+            recommendation(1.3, 1, "en-GB"),
+            "Good health. Consider minor lifestyle adjustments. 1 risk factor found."
+        );
+        assert_eq!(
+            recommendation(2.34, 3, "ru_RU.UTF-8"),
+            "Повышенные факторы риска. Рекомендуется консультация врача. Найдено 3 фактора риска."
+        );
+        assert_eq!(
+            recommendation(1.5, 1, "fr"),
+            "Facteurs de risque élevés. Une consultation médicale est recommandée. \
+             1 facteur de risque détecté."
+        );
+        // No translation: English
+        assert_eq!(recommendation(1.0, 0, "ja"), recommendation(1.0, 0, "en"));
+    }
+
+    #[test]
+    fn test_recommendation_ids_render_the_recommendation() {
+        for (risk_score, risk_factors) in [(1.0, 0), (1.3, 1), (2.34, 3), (3.0, 25)] {
+            let id = recommendation_id(risk_score, risk_factors);
+            assert_eq!(
+                render_recommendation(id, "pl").unwrap(),
+                recommendation(risk_score, risk_factors, "pl")
+            );
+        }
+        assert_eq!(recommendation_id(1.3, 1), recommendation_id(1.4, 1));
+        assert_ne!(recommendation_id(1.3, 1), recommendation_id(1.3, 2));
+        assert!(matches!(
+            render_recommendation(7, "en"),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_translations() {
+        let mut text = String::new();
+        for (language, _) in CATALOG {
+            for (risk_score, risk_factors) in
+                [(1.0, 0), (1.1, 1), (1.3, 1), (1.56, 2), (2.34, 3), (3.0, 5)]
+            {
+                text.push_str(&format!(
+                    "{language} {risk_score} {risk_factors}: {}\n",
+                    recommendation(risk_score, risk_factors, language)
+                ));
+            }
+        }
+        crate::golden::assert_golden("recommendations_localized", &text);
+    }
+
+    #[test]
+    fn test_every_template_renders() {
+        for (language, _) in CATALOG {
+            for risk_factors in 0..=25 {
+                for risk_score in [1.0, 1.3, 2.0] {
+                    let text = recommendation(risk_score, risk_factors, language);
+                    assert!(!text.contains(['{', '}', '#']), "{language}: {text}");
+                    assert!(text.ends_with('.'), "{language}: {text}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Time zones of addresses
+//!
+//! `timezone_for_address` geocodes an address with the current backend
+//! (see geocode.rs) and returns the IANA zone of the nearest city in a
+//! built-in table of reference cities, so scheduling code can propose
+//! appointment times in a person's local time. The table holds every
+//! city of the offline geocoder and enough others that a point within a
+//! few hundred kilometres of any large city lands in that city's zone.
+//!
+//! There are no zone borders in the library, so an address close to one
+//! can get the neighbouring zone, and a point farther than MAX_DISTANCE_KM
+//! from every reference city has no zone. The zone names are all the
+//! host needs; its own time zone database turns them into offsets.
+
+use crate::error::Error;
+use crate::ffi;
+use crate::geocode::haversine_km;
+
+/// (city, latitude, longitude, IANA zone), sorted by city
+const CITIES: &[(&str, f64, f64, &str)] = &[
+    ("Albuquerque", 35.0844, -106.6504, "America/Denver"),
+    ("Amsterdam", 52.3676, 4.9041, "Europe/Amsterdam"),
+    ("Anchorage", 61.2181, -149.9003, "America/Anchorage"),
+    ("Athens", 37.9838, 23.7275, "Europe/Athens"),
+    ("Atlanta", 33.7490, -84.3880, "America/New_York"),
+    ("Auckland", -36.8485, 174.7633, "Pacific/Auckland"),
+    ("Berlin", 52.5200, 13.4050, "Europe/Berlin"),
+    ("Bogotá", 4.7110, -74.0721, "America/Bogota"),
+    ("Boise", 43.6150, -116.2023, "America/Boise"),
+    ("Boston", 42.3601, -71.0589, "America/New_York"),
+    (
+        "Buenos Aires",
+        -34.6037,
+        -58.3816,
+        "America/Argentina/Buenos_Aires",
+    ),
+    ("Cairo", 30.0444, 31.2357, "Africa/Cairo"),
+    ("Calgary", 51.0447, -114.0719, "America/Edmonton"),
+    ("Chicago", 41.8781, -87.6298, "America/Chicago"),
+    ("Dallas", 32.7767, -96.7970, "America/Chicago"),
+    ("Delhi", 28.6139, 77.2090, "Asia/Kolkata"),
+    ("Denver", 39.7392, -104.9903, "America/Denver"),
+    ("Detroit", 42.3314, -83.0458, "America/Detroit"),
+    ("Dubai", 25.2048, 55.2708, "Asia/Dubai"),
+    ("Dublin", 53.3498, -6.2603, "Europe/Dublin"),
+    ("Halifax", 44.6488, -63.5752, "America/Halifax"),
+    ("Honolulu", 21.3069, -157.8583, "Pacific/Honolulu"),
+    ("Houston", 29.7604, -95.3698, "America/Chicago"),
+    (
+        "Indianapolis",
+        39.7684,
+        -86.1581,
+        "America/Indiana/Indianapolis",
+    ),
+    ("Istanbul", 41.0082, 28.9784, "Europe/Istanbul"),
+    ("Johannesburg", -26.2041, 28.0473, "Africa/Johannesburg"),
+    ("Kansas City", 39.0997, -94.5786, "America/Chicago"),
+    ("Kyiv", 50.4501, 30.5234, "Europe/Kyiv"),
+    ("Lagos", 6.5244, 3.3792, "Africa/Lagos"),
+    ("Las Vegas", 36.1699, -115.1398, "America/Los_Angeles"),
+    ("Lima", -12.0464, -77.0428, "America/Lima"),
+    ("Lisbon", 38.7223, -9.1393, "Europe/Lisbon"),
+    ("London", 51.5074, -0.1278, "Europe/London"),
+    ("Los Angeles", 34.0522, -118.2437, "America/Los_Angeles"),
+    ("Madrid", 40.4168, -3.7038, "Europe/Madrid"),
+    ("Mexico City", 19.4326, -99.1332, "America/Mexico_City"),
+    ("Miami", 25.7617, -80.1918, "America/New_York"),
+    ("Minneapolis", 44.9778, -93.2650, "America/Chicago"),
+    ("Montreal", 45.5019, -73.5674, "America/Toronto"),
+    ("Moscow", 55.7558, 37.6173, "Europe/Moscow"),
+    ("Nashville", 36.1627, -86.7816, "America/Chicago"),
+    ("New Orleans", 29.9511, -90.0715, "America/Chicago"),
+    ("New York", 40.7128, -74.0060, "America/New_York"),
+    ("Paris", 48.8566, 2.3522, "Europe/Paris"),
+    ("Perth", -31.9505, 115.8605, "Australia/Perth"),
+    ("Philadelphia", 39.9526, -75.1652, "America/New_York"),
+    ("Phoenix", 33.4484, -112.0740, "America/Phoenix"),
+    ("Portland", 45.5152, -122.6784, "America/Los_Angeles"),
+    ("Regina", 50.4452, -104.6189, "America/Regina"),
+    ("Rome", 41.9028, 12.4964, "Europe/Rome"),
+    ("Salt Lake City", 40.7608, -111.8910, "America/Denver"),
+    ("San Antonio", 29.4241, -98.4936, "America/Chicago"),
+    ("San Diego", 32.7157, -117.1611, "America/Los_Angeles"),
+    ("San Francisco", 37.7749, -122.4194, "America/Los_Angeles"),
+    ("Seattle", 47.6062, -122.3321, "America/Los_Angeles"),
+    ("Seoul", 37.5665, 126.9780, "Asia/Seoul"),
+    ("Shanghai", 31.2304, 121.4737, "Asia/Shanghai"),
+    ("Singapore", 1.3521, 103.8198, "Asia/Singapore"),
+    ("St. John's", 47.5615, -52.7126, "America/St_Johns"),
+    ("Stockholm", 59.3293, 18.0686, "Europe/Stockholm"),
+    ("Sydney", -33.8688, 151.2093, "Australia/Sydney"),
+    ("São Paulo", -23.5505, -46.6333, "America/Sao_Paulo"),
+    ("Tokyo", 35.6762, 139.6503, "Asia/Tokyo"),
+    ("Toronto", 43.6532, -79.3832, "America/Toronto"),
+    ("Vancouver", 49.2827, -123.1207, "America/Vancouver"),
+    ("Warsaw", 52.2297, 21.0122, "Europe/Warsaw"),
+    ("Washington", 38.9072, -77.0369, "America/New_York"),
+    ("Winnipeg", 49.8951, -97.1384, "America/Winnipeg"),
+];
+
+/// Farther than this from every reference city, a point has no zone
+const MAX_DISTANCE_KM: f64 = 800.0;
+
+/// The IANA zone of the reference city nearest to `point`, if one is
+/// within MAX_DISTANCE_KM
+pub fn timezone_at(point: &ffi::GeoPoint) -> Option<&'static str> {
+    CITIES
+        .iter()
+        .map(|&(_, lat, lon, zone)| (haversine_km(point, &ffi::GeoPoint { lat, lon }), zone))
+        .filter(|&(distance, _)| distance <= MAX_DISTANCE_KM)
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, zone)| zone)
+}
+
+#[cfg(not(feature = "stub-cpp"))]
+pub(crate) fn timezone_for_address(address: &ffi::Address) -> Result<String, Error> {
+    let point = crate::geocode::geocode_address(address)?;
+    timezone_of(&point)
+}
+
+fn timezone_of(point: &ffi::GeoPoint) -> Result<String, Error> {
+    timezone_at(point).map(str::to_string).ok_or_else(|| {
+        Error::TimezoneNotFound(format!(
+            "no reference city within {MAX_DISTANCE_KM} km of ({}, {})",
+            point.lat, point.lon
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geocode;
+
+    fn point(lat: f64, lon: f64) -> ffi::GeoPoint {
+        ffi::GeoPoint { lat, lon }
+    }
+
+    #[test]
+    fn test_every_reference_city_gets_its_own_zone() {
+        for &(city, lat, lon, zone) in CITIES {
+            assert_eq!(timezone_at(&point(lat, lon)), Some(zone), "{city}");
+        }
+        // The offline geocoder's cities are reference cities themselves
+        for &(city, lat, lon) in geocode::CITIES {
+            let reference = CITIES.iter().find(|entry| entry.0 == city);
+            assert_eq!(
+                reference.map(|entry| (entry.1, entry.2)),
+                Some((lat, lon)),
+                "{city}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_points_between_cities() {
+        // Cambridge, MA and Oakland, CA
+        assert_eq!(
+            timezone_at(&point(42.3736, -71.1097)),
+            Some("America/New_York")
+        );
+        assert_eq!(
+            timezone_at(&point(37.8044, -122.2712)),
+            Some("America/Los_Angeles")
+        );
+        // Lyon is nearer to Paris than to Rome or Madrid
+        assert_eq!(timezone_at(&point(45.7640, 4.8357)), Some("Europe/Paris"));
+
+        // Mid-Atlantic and mid-Pacific
+        assert_eq!(timezone_at(&point(35.0, -40.0)), None);
+        let error = timezone_of(&point(0.0, -140.0)).unwrap_err();
+        assert!(matches!(error, Error::TimezoneNotFound(_)));
+    }
+
+    #[test]
+    fn test_city_table_is_sorted_and_in_range() {
+        assert!(CITIES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        for &(city, lat, lon, zone) in CITIES {
+            assert!((-90.0..=90.0).contains(&lat), "{city}");
+            assert!((-180.0..=180.0).contains(&lon), "{city}");
+            assert!(zone.contains('/'), "{city}");
+        }
+    }
+}
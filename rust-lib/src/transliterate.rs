@@ -0,0 +1,202 @@
+//! Latin transliteration of Cyrillic and Greek names
+//!
+//! The same person may be entered as "Дмитрий" in one system and "Dmitrii"
+//! in another. `transliterate_name` maps both scripts to Latin letters so
+//! deduplication can match across them (`MatchOptions.transliterate`).
+//! Cyrillic follows the ICAO Doc 9303 passport table, with Russian values
+//! for letters that other languages romanize differently (г is "g", not the
+//! Ukrainian "h"). Greek follows ELOT 743 without the voicing rules, so αυ
+//! and ευ are always "av" and "ev". Other scripts are left unchanged.
+
+use crate::normalize::{combining_class, decompose, nfc};
+
+/// Latin for a lowercase Cyrillic or Greek letter
+fn latin(lower: char) -> Option<&'static str> {
+    Some(match lower {
+        // Cyrillic
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' | 'ґ' | 'ѓ' => "g",
+        'д' | 'ђ' => "d",
+        'е' | 'э' => "e",
+        'є' => "ie",
+        'ж' => "zh",
+        'з' => "z",
+        'и' | 'і' => "i",
+        'ј' => "j",
+        'к' | 'ќ' => "k",
+        'л' => "l",
+        'љ' => "lj",
+        'м' => "m",
+        'н' => "n",
+        'њ' => "nj",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'ћ' => "c",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'џ' | 'ѕ' => "dz",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' => "ie",
+        'ы' => "y",
+        'ь' => "",
+        'ю' => "iu",
+        'я' => "ia",
+        // Greek
+        'α' => "a",
+        'β' => "v",
+        'γ' => "g",
+        'δ' => "d",
+        'ε' => "e",
+        'ζ' => "z",
+        'η' | 'ι' => "i",
+        'θ' => "th",
+        'κ' => "k",
+        'λ' => "l",
+        'μ' => "m",
+        'ν' => "n",
+        'ξ' => "x",
+        'ο' | 'ω' => "o",
+        'π' => "p",
+        'ρ' => "r",
+        'σ' | 'ς' => "s",
+        'τ' => "t",
+        'υ' => "y",
+        'φ' => "f",
+        'χ' => "ch",
+        'ψ' => "ps",
+        _ => return None,
+    })
+}
+
+/// Greek letter pairs romanized together
+fn latin_pair(first: char, second: char) -> Option<&'static str> {
+    Some(match (first, second) {
+        ('ο', 'υ') => "ou",
+        ('α', 'υ') => "av",
+        ('ε', 'υ') => "ev",
+        ('η', 'υ') => "iv",
+        ('γ', 'γ') => "ng",
+        ('γ', 'ξ') => "nx",
+        ('γ', 'χ') => "nch",
+        _ => return None,
+    })
+}
+
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// `text` for a source letter: "Zh" for Ж, or "ZH" inside an all-caps word
+fn push_cased(out: &mut String, text: &str, upper: bool, all_caps: bool) {
+    if all_caps {
+        out.push_str(&text.to_uppercase());
+    } else if upper {
+        let mut chars = text.chars();
+        out.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+        out.extend(chars);
+    } else {
+        out.push_str(text);
+    }
+}
+
+/// `name` with Cyrillic and Greek letters replaced by Latin ones, in NFC
+///
+/// Accents on transliterated letters (й, ё, Greek tonos) are dropped, as
+/// the passport tables do; accents on Latin letters are kept.
+pub(crate) fn transliterate_name(name: &str) -> String {
+    // Decomposed so a letter's accents follow it as separate marks
+    let chars = decompose(name.chars());
+    let mut out = String::with_capacity(name.len());
+    let mut previous_upper = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        // A diaeresis on the second letter splits a Greek pair ("αϋ")
+        let pair = chars
+            .get(i + 1)
+            .filter(|_| chars.get(i + 2) != Some(&'\u{308}'))
+            .and_then(|&next| latin_pair(lower(c), lower(next)))
+            .map(|text| (text, 2));
+        let unit = pair.or_else(|| latin(lower(c)).map(|text| (text, 1)));
+        let Some((text, consumed)) = unit else {
+            out.push(c);
+            previous_upper = c.is_uppercase();
+            i += 1;
+            continue;
+        };
+
+        i += consumed;
+        while chars.get(i).is_some_and(|&mark| combining_class(mark) != 0) {
+            i += 1;
+        }
+        let upper = c.is_uppercase();
+        let next_upper = chars.get(i).is_some_and(|next| next.is_uppercase());
+        push_cased(
+            &mut out,
+            text,
+            upper,
+            upper && (previous_upper || next_upper),
+        );
+        previous_upper = upper;
+    }
+    nfc(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cyrillic() {
+        assert_eq!(
+            transliterate_name("Дмитрий Шостакович"),
+            "Dmitrii Shostakovich"
+        );
+        assert_eq!(transliterate_name("Юлия Щербакова"), "Iuliia Shcherbakova");
+        assert_eq!(transliterate_name("Любовь"), "Liubov");
+        assert_eq!(transliterate_name("Ђорђе Њего"), "Dorde Njego");
+        // Decomposed й transliterates like the precomposed one
+        assert_eq!(transliterate_name("Андреи\u{306}"), "Andrei");
+    }
+
+    #[test]
+    fn test_greek() {
+        assert_eq!(
+            transliterate_name("Ελένη Παπαδοπούλου"),
+            "Eleni Papadopoulou"
+        );
+        assert_eq!(transliterate_name("Ευάγγελος"), "Evangelos");
+        assert_eq!(
+            transliterate_name("Νίκος Χριστοδούλου"),
+            "Nikos Christodoulou"
+        );
+        // No pair across a diaeresis
+        assert_eq!(transliterate_name("Ταΰγετος"), "Taygetos");
+    }
+
+    #[test]
+    fn test_case() {
+        assert_eq!(transliterate_name("ЖУКОВ"), "ZHUKOV");
+        assert_eq!(transliterate_name("Жуков"), "Zhukov");
+        assert_eq!(transliterate_name("ΘΗΣΕΑΣ"), "THISEAS");
+        assert_eq!(transliterate_name("Ж"), "Zh");
+    }
+
+    #[test]
+    fn test_other_scripts_unchanged() {
+        assert_eq!(transliterate_name("José Núñez"), "José Núñez");
+        assert_eq!(transliterate_name("Jose\u{301}"), "Jos\u{e9}");
+        assert_eq!(transliterate_name("山田太郎"), "山田太郎");
+        assert_eq!(transliterate_name(""), "");
+        assert_eq!(transliterate_name("Anna Иванова"), "Anna Ivanova");
+    }
+}
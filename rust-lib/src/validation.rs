@@ -0,0 +1,257 @@
+//! Full-record validation
+//!
+//! `validate_contact` answers yes/no for one ContactInfo. The functions here
+//! check every field of a person and report each problem separately, so
+//! callers can show the user what to fix.
+//!
+//! The validation profile (`validation.strictness` in config.rs, or
+//! set_validation_profile) decides how severe each problem is. A report
+//! with only warnings is valid:
+//!
+//! | Problem                              | strict | standard | lenient |
+//! |--------------------------------------|--------|----------|---------|
+//! | name, age, height or city            | error  | error    | error   |
+//! | email, phone or postal code missing  | error  | warning  | -       |
+//! | email, phone or postal code invalid  | error  | error    | warning |
+
+use crate::audit;
+use crate::config::{self, RiskModelConfig, Strictness};
+use crate::events;
+use crate::ffi;
+use crate::metrics::{self, FfiCall};
+use crate::person_like::{CxxPerson, PersonLike};
+
+// Field rules, shared with `validate_contact` (public for the fuzz targets)
+pub use rust_lib_formulas::{city_valid, email_valid, phone_valid, postal_valid};
+
+/// Validate every field of a C++ Person
+pub(crate) fn validate_person_full(person: &ffi::Person) -> ffi::ValidationReport {
+    metrics::ffi_call(FfiCall::ValidatePersonFull);
+    audit::record("validate_person_full", 0, audit::PERSON_FIELDS);
+    validate_reported(&CxxPerson::new(person))
+}
+
+/// validate_fields, announcing a failed report to event subscribers
+pub(crate) fn validate_reported(person: &impl PersonLike) -> ffi::ValidationReport {
+    let report = validate_fields(person);
+    if !report.valid {
+        events::validation_failed(0, &report.issues);
+        metrics::validation_failed();
+    }
+    report
+}
+
+/// Validate every field of a person
+pub(crate) fn validate_fields(person: &impl PersonLike) -> ffi::ValidationReport {
+    validate_with(person, &config::current())
+}
+
+/// The kinds of problem the profiles tell apart
+#[derive(Clone, Copy)]
+enum Rule {
+    /// Name, age, height and city, which analysis depends on
+    Record,
+    ContactMissing,
+    ContactInvalid,
+}
+
+/// How `profile` rates a problem, or None if it is not reported at all
+fn severity(profile: Strictness, rule: Rule) -> Option<ffi::Severity> {
+    match (profile, rule) {
+        (Strictness::Strict, _) | (_, Rule::Record) => Some(ffi::Severity::Error),
+        (Strictness::Standard, Rule::ContactMissing) => Some(ffi::Severity::Warning),
+        (Strictness::Standard, Rule::ContactInvalid) => Some(ffi::Severity::Error),
+        (Strictness::Lenient, Rule::ContactMissing) => None,
+        (Strictness::Lenient, Rule::ContactInvalid) => Some(ffi::Severity::Warning),
+    }
+}
+
+fn validate_with(person: &impl PersonLike, config: &RiskModelConfig) -> ffi::ValidationReport {
+    let mut issues = Vec::new();
+    let mut check = |field: &str, problem: Option<(Rule, String)>| {
+        let Some((rule, message)) = problem else {
+            return;
+        };
+        if let Some(severity) = severity(config.strictness, rule) {
+            issues.push(ffi::ValidationIssue {
+                field: field.to_string(),
+                message,
+                severity,
+            });
+        }
+    };
+    let record = |ok: bool, message: &str| (!ok).then(|| (Rule::Record, message.to_string()));
+
+    check(
+        "name",
+        record(!person.name().trim().is_empty(), "name is empty"),
+    );
+    check(
+        "age",
+        record(
+            person.age() <= config.max_age,
+            &format!("age is above {}", config.max_age),
+        ),
+    );
+    check(
+        "height_m",
+        record(
+            person.height_m() > 0.0 && person.height_m() < config.max_height_m,
+            &format!(
+                "height must be between 0 and {} meters",
+                config.max_height_m
+            ),
+        ),
+    );
+    check(
+        "email",
+        contact(
+            person.email(),
+            email_valid,
+            "email address is missing",
+            "email address is malformed",
+        ),
+    );
+    check(
+        "phone",
+        contact(
+            person.phone(),
+            phone_valid,
+            "phone number is missing",
+            "phone number is too short",
+        ),
+    );
+    check("city", record(city_valid(person.city()), "city is empty"));
+    check(
+        "postal_code",
+        contact(
+            person.postal_code(),
+            postal_valid,
+            "postal code is missing",
+            "postal code is too short",
+        ),
+    );
+
+    ffi::ValidationReport {
+        valid: !issues
+            .iter()
+            .any(|issue| issue.severity == ffi::Severity::Error),
+        issues,
+    }
+}
+
+/// The problem with a contact field, if it is missing or not `valid`
+fn contact(
+    value: &str,
+    valid: fn(&str) -> bool,
+    missing: &str,
+    invalid: &str,
+) -> Option<(Rule, String)> {
+    if value.is_empty() {
+        Some((Rule::ContactMissing, missing.to_string()))
+    } else {
+        (!valid(value)).then(|| (Rule::ContactInvalid, invalid.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::tests::snapshot;
+
+    #[test]
+    fn test_valid_snapshot_has_no_issues() {
+        let report = validate_fields(&snapshot(30, 1.75, "Boston"));
+        assert!(report.valid);
+        assert!(report.issues.is_empty());
+    }
+
+    #[test]
+    fn test_mock_person_is_validated() {
+        let person = crate::person_like::MockPerson {
+            name: "  ",
+            phone: "555",
+            ..Default::default()
+        };
+        let report = validate_fields(&person);
+        let fields: Vec<_> = report.issues.iter().map(|i| i.field.as_str()).collect();
+        assert_eq!(fields, vec!["name", "phone"]);
+    }
+
+    #[test]
+    fn test_profiles_rate_contact_problems() {
+        let mut person = snapshot(30, 1.75, "Boston");
+        person.email = String::new();
+        person.phone = "555".to_string();
+        let rated = |person: &ffi::PersonSnapshot, strictness| {
+            let config = RiskModelConfig {
+                strictness,
+                ..RiskModelConfig::default()
+            };
+            let report = validate_with(person, &config);
+            let issues: Vec<_> = report
+                .issues
+                .iter()
+                .map(|issue| (issue.field.clone(), issue.severity))
+                .collect();
+            (report.valid, issues)
+        };
+        let (error, warning) = (ffi::Severity::Error, ffi::Severity::Warning);
+        let field = |name: &str| name.to_string();
+
+        assert_eq!(
+            rated(&person, Strictness::Strict),
+            (
+                false,
+                vec![(field("email"), error), (field("phone"), error)]
+            )
+        );
+        assert_eq!(
+            rated(&person, Strictness::Standard),
+            (
+                false,
+                vec![(field("email"), warning), (field("phone"), error)]
+            )
+        );
+        assert_eq!(
+            rated(&person, Strictness::Lenient),
+            (true, vec![(field("phone"), warning)])
+        );
+        // Problems with the record itself are errors in every profile
+        person.city = String::new();
+        assert!(!rated(&person, Strictness::Lenient).0);
+    }
+
+    #[test]
+    fn test_lenient_validation_allows_missing_contact_fields() {
+        let mut person = snapshot(30, 1.75, "Boston");
+        person.email = String::new();
+        person.phone = "555".to_string();
+        person.postal_code = String::new();
+        let config = RiskModelConfig {
+            strictness: Strictness::Lenient,
+            max_age: 25,
+            ..RiskModelConfig::default()
+        };
+
+        let report = validate_with(&person, &config);
+        let fields: Vec<_> = report.issues.iter().map(|i| i.field.as_str()).collect();
+        assert_eq!(fields, vec!["age", "phone"]);
+        assert_eq!(report.issues[0].message, "age is above 25");
+    }
+
+    #[test]
+    fn test_every_bad_field_is_reported() {
+        let mut person = snapshot(200, 0.0, "");
+        person.email = "bad".to_string();
+        person.postal_code = "123".to_string();
+
+        let report = validate_fields(&person);
+        let fields: Vec<_> = report.issues.iter().map(|i| i.field.as_str()).collect();
+        assert!(!report.valid);
+        assert_eq!(
+            fields,
+            vec!["age", "height_m", "email", "city", "postal_code"]
+        );
+    }
+}
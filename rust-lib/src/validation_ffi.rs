@@ -0,0 +1,103 @@
+//! Bridge for contact and person validation
+//!
+//! One-off checks of C++ objects plus the background re-validation of
+//! everything in the registry.
+
+#[cxx::bridge]
+mod bridge {
+    // Types owned by person_ffi
+    #[cfg(not(feature = "stub-cpp"))]
+    unsafe extern "C++" {
+        include!("rust-lib/src/person_ffi.rs.h");
+
+        type Person = crate::person_ffi::Person;
+        type ContactInfo = crate::person_ffi::ContactInfo;
+    }
+
+    // ============================================================================
+    // BRIDGE STRUCTS - For data exchange between Rust and C++
+    // ============================================================================
+
+    /// How serious a validation problem is under the validation profile
+    #[derive(Debug)]
+    enum Severity {
+        /// The record is invalid
+        Error,
+        /// Worth showing, but the record is valid
+        Warning,
+    }
+
+    /// One problem found by validate_person_full
+    #[derive(Clone, Debug)]
+    struct ValidationIssue {
+        field: String,
+        message: String,
+        severity: Severity,
+    }
+
+    /// Field-by-field validation result
+    /// `valid` unless an issue is an Error
+    #[derive(Clone, Debug)]
+    struct ValidationReport {
+        valid: bool,
+        issues: Vec<ValidationIssue>,
+    }
+
+    /// A registered person that failed re-validation
+    #[cfg(feature = "registry")]
+    #[derive(Clone, Debug)]
+    struct ValidationAlert {
+        person_id: u64,
+        issues: Vec<ValidationIssue>,
+    }
+
+    // ============================================================================
+    // RUST FUNCTIONS - New functionality exposed to C++
+    // ============================================================================
+    extern "Rust" {
+        /// Validate contact info - demonstrates deep access into nested C++ objects
+        #[cfg(not(feature = "stub-cpp"))]
+        fn validate_contact(contact: &ContactInfo) -> bool;
+
+        /// Validate every field of a C++ Person, listing each problem
+        #[cfg(not(feature = "stub-cpp"))]
+        fn validate_person_full(person: &Person) -> ValidationReport;
+
+        /// Validate with the bundled profile `name`: "strict" (the default),
+        /// "standard" or "lenient"; see Severity
+        /// Throws for other names; a later load_config sets the file's profile
+        fn set_validation_profile(name: &str) -> Result<()>;
+
+        // Re-validation - only with the `registry` feature
+
+        /// Start re-validating the registry every `interval_ms` milliseconds
+        #[cfg(feature = "registry")]
+        fn start_revalidation(interval_ms: u64) -> Result<()>;
+
+        /// Stop the re-validation worker and wait for it to exit
+        #[cfg(feature = "registry")]
+        fn stop_revalidation();
+
+        /// Run one re-validation sweep now; returns the number of new alerts
+        #[cfg(feature = "registry")]
+        fn revalidate_now() -> usize;
+
+        /// Registered persons that became invalid since the last poll
+        #[cfg(feature = "registry")]
+        fn poll_validation_alerts() -> Vec<ValidationAlert>;
+    }
+}
+
+#[cfg(feature = "registry")]
+pub use bridge::ValidationAlert;
+pub use bridge::{Severity, ValidationIssue, ValidationReport};
+
+use crate::config::set_validation_profile;
+#[cfg(feature = "registry")]
+use crate::revalidation::{
+    poll_validation_alerts, revalidate_now, start_revalidation, stop_revalidation,
+};
+#[cfg(not(feature = "stub-cpp"))]
+use crate::validate_contact;
+#[cfg(not(feature = "stub-cpp"))]
+use crate::validation::validate_person_full;
@@ -0,0 +1,208 @@
+//! High-risk alerts to an HTTP endpoint (`webhook` feature)
+//!
+//! `configure_webhook` registers a risk observer (see observers.rs) whose
+//! threshold is the webhook's. Each analysis above it is queued as a JSON
+//! payload,
+//!
+//! ```json
+//! {"event": "high_risk_analysis", "threshold": 2.0, "analysis": {"bmi": ..., ...}}
+//! ```
+//!
+//! and a delivery thread POSTs it to the URL, retrying with exponential
+//! backoff on connection errors, 5xx and 429 answers. So the analyzing
+//! thread never waits on the network; when the queue is full because the
+//! endpoint is down, new alerts are dropped instead.
+
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde_json::json;
+
+use crate::error::Error;
+use crate::ffi;
+use crate::http_client::Endpoint;
+use crate::observers;
+use crate::state::{Global, StopSignal};
+
+/// Alerts waiting for delivery before new ones are dropped
+const QUEUE_CAPACITY: usize = 256;
+/// Tries per alert, the first included
+const MAX_ATTEMPTS: u32 = 5;
+/// Wait before the first retry; doubled for each further one
+const FIRST_BACKOFF: Duration = Duration::from_millis(500);
+
+struct Webhook {
+    observer_id: u64,
+    stop: Arc<StopSignal>,
+    delivery: JoinHandle<()>,
+}
+
+static WEBHOOK: Global<Option<Webhook>> = Global::new(|| None);
+
+/// POST analyses with a risk score above `threshold` to `url`, replacing
+/// any webhook configured before
+pub(crate) fn configure_webhook(url: &str, threshold: f64) -> Result<(), Error> {
+    configure(url, threshold, FIRST_BACKOFF)
+}
+
+fn configure(url: &str, threshold: f64, first_backoff: Duration) -> Result<(), Error> {
+    let endpoint = Endpoint::parse(url, "webhook URL")?;
+    if !threshold.is_finite() {
+        return Err(Error::InvalidArgument(format!(
+            "webhook threshold {threshold} is not finite"
+        )));
+    }
+    // Held for the whole replace, so a concurrent configure cannot install
+    // a webhook that this one then overwrites without tearing it down
+    let mut webhook = WEBHOOK.write();
+    if let Some(old) = webhook.take() {
+        old.tear_down();
+    }
+
+    let (sender, receiver) = mpsc::sync_channel(QUEUE_CAPACITY);
+    let stop = Arc::new(StopSignal::default());
+    let delivery = {
+        let stop = Arc::clone(&stop);
+        thread::Builder::new()
+            .name("rust-lib-webhook".to_string())
+            .spawn(move || deliver(&endpoint, &receiver, &stop, first_backoff))
+            .map_err(Error::from)?
+    };
+    let observer_id = observers::add_sharing_observer(threshold, alert(threshold, sender));
+
+    *webhook = Some(Webhook {
+        observer_id,
+        stop,
+        delivery,
+    });
+    Ok(())
+}
+
+/// Stop alerting and join the delivery thread; alerts not yet delivered,
+/// including one between retries, are dropped
+pub(crate) fn disable_webhook() {
+    let webhook = WEBHOOK.write().take();
+    if let Some(webhook) = webhook {
+        webhook.tear_down();
+    }
+}
+
+impl Webhook {
+    fn tear_down(self) {
+        // Unregistering drops the observer's sender, which ends the queue
+        observers::unregister_risk_observer(self.observer_id);
+        self.stop.stop();
+        let _ = self.delivery.join();
+    }
+}
+
+/// The observer: queue the payload without ever blocking the analysis
+fn alert(
+    threshold: f64,
+    sender: SyncSender<String>,
+) -> Arc<dyn Fn(&ffi::HealthAnalysis) + Send + Sync> {
+    Arc::new(move |analysis| {
+        let payload = json!({
+            "event": "high_risk_analysis",
+            "threshold": threshold,
+            "analysis": analysis,
+        });
+        // Full: the endpoint has been failing for a while; drop the alert
+        let _ = sender.try_send(payload.to_string());
+    })
+}
+
+fn deliver(
+    endpoint: &Endpoint,
+    queue: &Receiver<String>,
+    stop: &StopSignal,
+    first_backoff: Duration,
+) {
+    let target = match endpoint.path() {
+        "" => "/",
+        path => path,
+    };
+    for payload in queue {
+        if stop.wait(Duration::ZERO) {
+            return;
+        }
+        let mut backoff = first_backoff;
+        for attempt in 1..=MAX_ATTEMPTS {
+            let retry = match endpoint.send("POST", target, Some(&payload)) {
+                Ok(response) => response.status == 429 || response.status >= 500,
+                Err(_) => true,
+            };
+            if !retry || attempt == MAX_ATTEMPTS {
+                break;
+            }
+            if stop.wait(backoff) {
+                return;
+            }
+            backoff *= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::tests::{fake_server, fake_tls_server, trust_test_ca};
+    use serde_json::Value;
+
+    // The webhook is process-wide, so its whole lifecycle is this one test.
+    // Its threshold is far above anything the model produces, so analyses
+    // in tests running in parallel never trigger it.
+    #[test]
+    fn test_webhook_lifecycle() {
+        assert!(configure_webhook("ftp://alerts.example.com", 1000.0).is_err());
+        assert!(configure_webhook("http://localhost:1", f64::NAN).is_err());
+        assert!(WEBHOOK.read().is_none());
+
+        let (url, server) = fake_server(vec![
+            "HTTP/1.0 503 Service Unavailable\r\n\r\n",
+            "HTTP/1.0 204 No Content\r\n\r\n",
+        ]);
+        configure(&format!("{url}/alerts"), 1000.0, Duration::from_millis(1)).unwrap();
+
+        let person = crate::batch::tests::snapshot(30, 1.75, "Boston");
+        let mut analysis = crate::compute_health(&person, 70.0);
+        observers::notify(&analysis);
+        analysis.risk_score = 1000.5;
+        observers::notify(&analysis);
+
+        // The 503 was retried; the analysis below the threshold never sent
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0], requests[1]);
+        assert!(requests[0].starts_with("POST /alerts HTTP/1.0\r\n"));
+        let body = &requests[0][requests[0].find("\r\n\r\n").unwrap() + 4..];
+        let payload: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(payload["event"], "high_risk_analysis");
+        assert_eq!(payload["threshold"], 1000.0);
+        assert_eq!(payload["analysis"]["risk_score"], 1000.5);
+
+        disable_webhook();
+        assert!(WEBHOOK.read().is_none());
+        disable_webhook();
+    }
+
+    #[test]
+    fn test_deliver_over_https() {
+        trust_test_ca();
+        let (url, server) = fake_tls_server(
+            false,
+            vec!["HTTP/1.0 204 No Content\r\nContent-Length: 0\r\n\r\n"],
+        );
+        let endpoint = Endpoint::parse(&format!("{url}/hooks/health"), "URL").unwrap();
+        let (sender, receiver) = mpsc::sync_channel(1);
+        sender.send(r#"{"event": "high_risk_analysis"}"#.to_string()).unwrap();
+        drop(sender);
+
+        deliver(&endpoint, &receiver, &StopSignal::default(), Duration::from_millis(1));
+        let request = &server.join().unwrap()[0];
+        assert!(request.starts_with("POST /hooks/health HTTP/1.0\r\n"));
+        assert!(request.ends_with(r#"{"event": "high_risk_analysis"}"#));
+    }
+}
@@ -0,0 +1,63 @@
+//! Wiping keys and personal data from memory
+//!
+//! Memory that is freed keeps its bytes until it is reused, so a key or a
+//! person's fields could turn up in a core dump or a swapped-out page long
+//! after use. `Zeroize` and `Zeroizing` are those of the `zeroize` crate,
+//! which the AES-GCM cipher relies on as well: `Zeroize` overwrites a value
+//! with zeros in a way the optimizer cannot remove as dead stores, and
+//! `Zeroizing` does so when its value is dropped. They hold the
+//! anonymization key and the plain text that exports and anonymous ids
+//! pass through; the export key's cipher wipes itself (see
+//! export_crypto.rs).
+//!
+//! A buffer that grows is copied and the old one freed without wiping, so
+//! buffers for secrets are sized up front. `scrub_sensitive_memory`, for a
+//! host shutting down, wipes and forgets the keys and the cached analyses.
+//! What the host passed in or received stays in the host's own buffers.
+
+pub(crate) use ::zeroize::{Zeroize, Zeroizing};
+
+use crate::ffi;
+
+impl Zeroize for ffi::PersonSnapshot {
+    fn zeroize(&mut self) {
+        self.name.zeroize();
+        self.email.zeroize();
+        self.phone.zeroize();
+        self.street.zeroize();
+        self.city.zeroize();
+        self.postal_code.zeroize();
+        self.age.zeroize();
+        self.height_m.zeroize();
+    }
+}
+
+/// Wipe and forget the export and anonymization keys and the cached
+/// analyses, for a host shutting down
+///
+/// Exports and anonymous ids fail until the keys are set again; exports
+/// still running keep their key until they finish, and wipe it then.
+pub(crate) fn scrub_sensitive_memory() {
+    #[cfg(feature = "crypto")]
+    crate::export_crypto::clear_export_key();
+    crate::anonymize::clear_anonymization_key();
+    crate::analysis_cache::scrub();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zeroize() {
+        let mut key = Zeroizing::new([[7u8; 4]; 3]);
+        key[1][2] = 9;
+        key.zeroize();
+        assert_eq!(*key, [[0; 4]; 3]);
+
+        let mut person = crate::batch::tests::snapshot(30, 1.75, "Boston");
+        person.zeroize();
+        assert_eq!((&*person.name, &*person.city, person.age), ("", "", 0));
+        assert_eq!(person.height_m, 0.0);
+    }
+}
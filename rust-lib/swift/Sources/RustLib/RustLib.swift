@@ -0,0 +1,154 @@
+// Swift API over the `c-abi` functions of rust_lib
+//
+// The Person model stays in C++; a Swift app copies the fields it has into
+// a PersonSnapshot, as the C++ app's snapshot() does, and gets the same
+// analysis back. Strings are passed as UTF-8 for the duration of a call,
+// and no result needs freeing.
+
+import RustLibC
+
+/// A person as the health analysis reads them, with the fields of the
+/// bridge's PersonSnapshot
+public struct PersonSnapshot: Equatable, Sendable {
+    public var name: String
+    public var age: UInt32
+    public var heightM: Double
+    public var email: String
+    public var phone: String
+    public var street: String
+    public var city: String
+    public var postalCode: String
+
+    public init(
+        name: String = "", age: UInt32, heightM: Double, email: String = "", phone: String = "",
+        street: String = "", city: String = "", postalCode: String = ""
+    ) {
+        self.name = name
+        self.age = age
+        self.heightM = heightM
+        self.email = email
+        self.phone = phone
+        self.street = street
+        self.city = city
+        self.postalCode = postalCode
+    }
+}
+
+/// Result of `RustLib.analyze`, with the fields of the bridge's
+/// HealthAnalysis
+public struct HealthAnalysis: Equatable, Sendable {
+    public let bmi: Double
+    public let riskScore: Double
+    public let recommendation: String
+    public let cityRiskFactor: Double
+    /// Factor from the host's RiskFactorHook (1.0 when none is set)
+    public let extraRiskFactor: Double
+    /// How many of age, BMI, city and the hook raised the score
+    public let riskFactors: UInt32
+}
+
+/// A RustLibStatus other than RUST_LIB_STATUS_OK
+public enum RustLibError: Error, Equatable {
+    /// `people` and `weightsKg` differ in length
+    case lengthMismatch(expected: Int, actual: Int)
+    /// RUST_LIB_STATUS_NULL_ARGUMENT or RUST_LIB_STATUS_INVALID_UTF8, which
+    /// this wrapper's arguments never cause
+    case invalidArgument
+    /// An internal error in rust_lib
+    case panic
+}
+
+public enum RustLib {
+    /// BMI for a weight and height; 0 for a height of 0 or less
+    public static func calculateBmi(weightKg: Double, heightM: Double) -> Double {
+        rust_lib_calculate_bmi(weightKg, heightM)
+    }
+
+    /// Health analysis of one person, as the C++ app's analyze_health
+    public static func analyze(
+        _ person: PersonSnapshot, weightKg: Double, locale: String = ""
+    ) throws -> HealthAnalysis {
+        try analyzeBatch([person], weightsKg: [weightKg], locale: locale)[0]
+    }
+
+    /// Health analysis of many people in one call, recommendations in
+    /// `locale` ("" for English)
+    public static func analyzeBatch(
+        _ people: [PersonSnapshot], weightsKg: [Double], locale: String = ""
+    ) throws -> [HealthAnalysis] {
+        guard people.count == weightsKg.count else {
+            throw RustLibError.lengthMismatch(expected: people.count, actual: weightsKg.count)
+        }
+        var analyses = [RustLibHealthAnalysis](repeating: RustLibHealthAnalysis(), count: people.count)
+        let status = withSnapshots(people) { snapshots in
+            rust_lib_analyze_snapshot_batch(snapshots, weightsKg, people.count, &analyses)
+        }
+        try check(status)
+        return try analyses.map { analysis in
+            HealthAnalysis(
+                bmi: analysis.bmi,
+                riskScore: analysis.risk_score,
+                recommendation: try recommendation(analysis, locale: locale),
+                cityRiskFactor: analysis.city_risk_factor,
+                extraRiskFactor: analysis.extra_risk_factor,
+                riskFactors: analysis.risk_factors
+            )
+        }
+    }
+
+    /// The recommendation text, retried with a larger buffer when the
+    /// first one is too small
+    private static func recommendation(_ analysis: RustLibHealthAnalysis, locale: String) throws -> String {
+        var analysis = analysis
+        var length = 0
+        var text = [CChar](repeating: 0, count: 256)
+        var capacity = text.count
+        var status = rust_lib_recommendation(&analysis, locale, &text, capacity, &length)
+        if status == RUST_LIB_STATUS_BUFFER_TOO_SMALL {
+            text = [CChar](repeating: 0, count: length + 1)
+            capacity = text.count
+            status = rust_lib_recommendation(&analysis, locale, &text, capacity, &length)
+        }
+        try check(status)
+        return String(decoding: text.prefix(length).map { UInt8(bitPattern: $0) }, as: UTF8.self)
+    }
+}
+
+private func check(_ status: RustLibStatus) throws {
+    switch status {
+    case RUST_LIB_STATUS_OK:
+        return
+    case RUST_LIB_STATUS_PANIC:
+        throw RustLibError.panic
+    default:
+        throw RustLibError.invalidArgument
+    }
+}
+
+/// Calls `body` with C snapshots of `people`, whose six strings each all
+/// live in one NUL-separated buffer for the duration of the call
+private func withSnapshots<Result>(
+    _ people: [PersonSnapshot], _ body: ([RustLibPersonSnapshot]) -> Result
+) -> Result {
+    var text = ContiguousArray<CChar>()
+    var offsets: [Int] = []
+    for person in people {
+        for field in [person.name, person.email, person.phone, person.street, person.city, person.postalCode] {
+            offsets.append(text.count)
+            text.append(contentsOf: field.utf8CString)
+        }
+    }
+    return text.withUnsafeBufferPointer { buffer in
+        guard let base = buffer.baseAddress else {
+            return body([])
+        }
+        let snapshots = people.enumerated().map { index, person in
+            let field = { (number: Int) in base + offsets[index * 6 + number] }
+            return RustLibPersonSnapshot(
+                name: field(0), age: person.age, height_m: person.heightM, email: field(1),
+                phone: field(2), street: field(3), city: field(4), postal_code: field(5)
+            )
+        }
+        return body(snapshots)
+    }
+}
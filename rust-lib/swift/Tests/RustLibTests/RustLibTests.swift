@@ -0,0 +1,40 @@
+// XCTest suite for the Swift wrapper; `swift test -Xlinker -Ltarget/release`
+// from rust-lib/ after `cargo build --release --features c-abi`. The
+// expected values are those of cpp-app/tests/c_abi_test.c.
+
+import RustLib
+import XCTest
+
+final class RustLibTests: XCTestCase {
+    func testCalculateBmi() {
+        XCTAssertEqual(RustLib.calculateBmi(weightKg: 70, heightM: 1.75), 22.857, accuracy: 0.001)
+        XCTAssertEqual(RustLib.calculateBmi(weightKg: 70, heightM: 0), 0)
+    }
+
+    func testAnalyze() throws {
+        let senior = PersonSnapshot(name: "Alice", age: 70, heightM: 1.75, city: "New York")
+        let analysis = try RustLib.analyze(senior, weightKg: 70)
+        XCTAssertEqual(analysis.cityRiskFactor, 1.2)
+        XCTAssertEqual(analysis.riskFactors, 2)
+        XCTAssertEqual(analysis.riskScore, 1.5 * 1.2 * analysis.extraRiskFactor, accuracy: 1e-9)
+        XCTAssertTrue(analysis.recommendation.hasSuffix("2 risk factors found."))
+    }
+
+    func testAnalyzeBatch() throws {
+        let people = [
+            PersonSnapshot(name: "Alice", age: 30, heightM: 1.75, city: "Boston"),
+            PersonSnapshot(name: "Bob", age: 30, heightM: 1.75, city: "Los Angeles"),
+        ]
+        let analyses = try RustLib.analyzeBatch(people, weightsKg: [70, 90])
+        XCTAssertEqual(analyses.count, 2)
+        XCTAssertEqual(analyses[0].bmi, RustLib.calculateBmi(weightKg: 70, heightM: 1.75))
+        XCTAssertEqual(analyses[0].recommendation,
+                       "Excellent health profile. Maintain current lifestyle. No risk factors found.")
+        XCTAssertEqual(analyses[1].cityRiskFactor, 1.1)
+        XCTAssertEqual(try RustLib.analyzeBatch([], weightsKg: []), [])
+
+        XCTAssertThrowsError(try RustLib.analyzeBatch(people, weightsKg: [70])) { error in
+            XCTAssertEqual(error as? RustLibError, .lengthMismatch(expected: 2, actual: 1))
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Heap allocations of the small per-person calls
+//!
+//! process_person and validate_contact read the C++ strings in place:
+//! `CxxString::to_str` borrows the std::string's bytes, so the only
+//! allocation is the `PersonInfo.city` String handed back to C++. These
+//! tests pin that down, so an intermediate String creeping into the hot
+//! path fails here rather than in a profile. Likewise, a pooled batch on a
+//! batch context allocates less than one on fresh buffers.
+
+// Needs the C++ fixtures, which `stub-cpp` does not build; the library
+// installs its own global allocator with `host-allocator`
+#![cfg(not(any(feature = "stub-cpp", feature = "host-allocator")))]
+
+mod common;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use rust_lib::{
+    analyze_health_batch_pooled, analyze_health_batch_pooled_in_context, create_batch_context, ffi,
+    process_person, release_batch_context, release_string_table, snapshot_person, validate_contact,
+};
+
+/// Counts the allocations of the current thread, as the tests of this
+/// file run in parallel
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// What `run` returns and how many allocations it made
+fn allocations<T>(run: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let value = run();
+    (value, ALLOCATIONS.with(Cell::get) - before)
+}
+
+#[test]
+fn test_validate_contact_does_not_allocate() {
+    let contact = common::valid_contact();
+    // The first call sets up the process-wide state it reads
+    assert!(validate_contact(&contact));
+
+    let (valid, count) = allocations(|| validate_contact(&contact));
+    assert!(valid);
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn test_process_person_allocates_only_the_city() {
+    let person = common::person_with(
+        40,
+        1.70,
+        "Dana Ølsen",
+        "dana@example.com",
+        "555-0104",
+        "København",
+        "1050",
+    );
+    process_person(&person);
+
+    let (info, count) = allocations(|| process_person(&person));
+    assert_eq!(info.city, "København");
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn test_batch_context_reuses_its_buffers() {
+    let people: Vec<_> = (0..300)
+        .map(|_| snapshot_person(&common::adult_person()))
+        .collect();
+    let weights = vec![70.0; people.len()];
+    let context = create_batch_context(&ffi::BatchCapacity {
+        people: people.len(),
+        distinct_strings: 2,
+    });
+    let pooled = |in_context: bool| {
+        let batch = if in_context {
+            analyze_health_batch_pooled_in_context(context, &people, &weights, 0)
+        } else {
+            analyze_health_batch_pooled(&people, &weights, 0)
+        };
+        release_string_table(batch.unwrap().table_id);
+    };
+    pooled(false);
+    pooled(true);
+
+    let (_, fresh) = allocations(|| pooled(false));
+    let (_, reused) = allocations(|| pooled(true));
+    // At least the kernel's four chunk buffers and the table's lookups
+    assert!(
+        reused + 6 <= fresh,
+        "{reused} allocations in the context, {fresh} without"
+    );
+    release_batch_context(context);
+}
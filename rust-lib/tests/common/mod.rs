@@ -0,0 +1,48 @@
+//! C++ fixtures shared by the integration tests
+//!
+//! The builders live in tests/test_fixtures.cpp; build.rs compiles them and
+//! this bridge into the rust_lib_test_fixtures archive. Each test crate
+//! uses only some of them.
+
+#![allow(dead_code)]
+
+#[cxx::bridge]
+mod bridge {
+    unsafe extern "C++" {
+        include!("rust-lib/tests/test_fixtures.h");
+
+        type Person = rust_lib::ffi::Person;
+        type ContactInfo = rust_lib::ffi::ContactInfo;
+        type Address = rust_lib::ffi::Address;
+
+        fn adult_person() -> UniquePtr<Person>;
+        fn minor_person() -> UniquePtr<Person>;
+        fn person_with(
+            age: u32,
+            height: f64,
+            name: &str,
+            email: &str,
+            phone: &str,
+            city: &str,
+            postal_code: &str,
+        ) -> UniquePtr<Person>;
+
+        fn valid_contact() -> UniquePtr<ContactInfo>;
+        fn contact_with(
+            email: &str,
+            phone: &str,
+            city: &str,
+            postal_code: &str,
+        ) -> UniquePtr<ContactInfo>;
+        fn address_with(street: &str, city: &str, postal_code: &str) -> UniquePtr<Address>;
+
+        fn person_named(name: &str) -> UniquePtr<Person>;
+        fn person_named_bytes(name: &[u8]) -> UniquePtr<Person>;
+        fn std_string_from(bytes: &[u8]) -> UniquePtr<CxxString>;
+        fn bytes_of(value: &CxxString) -> Vec<u8>;
+        fn rust_string_from(value: &CxxString) -> Result<String>;
+        fn snapshot_name_round_trips(person: &Person) -> bool;
+    }
+}
+
+pub use bridge::*;
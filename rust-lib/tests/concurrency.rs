@@ -0,0 +1,81 @@
+//! The public entry points called from many threads at once
+//!
+//! Each thread builds its own C++ people (they are never shared across
+//! threads, as state.rs requires) and checks every result against one
+//! computed up front on the main thread. The in-crate soak tests (src/soak.rs)
+//! cover the registry, observers and sessions.
+
+// Needs the C++ fixtures, which `stub-cpp` does not build
+#![cfg(not(feature = "stub-cpp"))]
+
+mod common;
+
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+use rust_lib::{analyze_health, analyze_health_batch, process_person, snapshot_person};
+
+const THREADS: usize = 8;
+const ROUNDS: usize = 500;
+
+#[test]
+fn test_entry_points_from_many_threads() {
+    let expected_info = process_person(&common::adult_person());
+    let expected_risk = analyze_health(&common::minor_person(), 70.0).risk_score;
+    let barrier = Arc::new(Barrier::new(THREADS));
+
+    let threads: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let barrier = Arc::clone(&barrier);
+            let expected_info = expected_info.clone();
+            thread::spawn(move || {
+                let adult = common::adult_person();
+                let minor = common::minor_person();
+                barrier.wait();
+                for _ in 0..ROUNDS {
+                    let info = process_person(&adult);
+                    assert_eq!(info.bmi_category, expected_info.bmi_category);
+                    assert_eq!(info.city, expected_info.city);
+                    assert_eq!(analyze_health(&minor, 70.0).risk_score, expected_risk);
+                    assert_eq!(snapshot_person(&adult).name, "Alice");
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+fn test_batches_run_concurrently() {
+    let people: Arc<Vec<_>> = Arc::new(
+        (0..1000)
+            .map(|i| {
+                let person = if i % 2 == 0 {
+                    common::adult_person()
+                } else {
+                    common::minor_person()
+                };
+                snapshot_person(&person)
+            })
+            .collect(),
+    );
+    let weights: Arc<Vec<f64>> = Arc::new((0..1000).map(|i| 50.0 + (i % 40) as f64).collect());
+    let expected = analyze_health_batch(&people, &weights, 0).unwrap();
+
+    let threads: Vec<_> = (0..THREADS)
+        .map(|_| {
+            let (people, weights) = (Arc::clone(&people), Arc::clone(&weights));
+            thread::spawn(move || analyze_health_batch(&people, &weights, 0).unwrap())
+        })
+        .collect();
+    for thread in threads {
+        let results = thread.join().unwrap();
+        assert_eq!(results.len(), expected.len());
+        for (result, expected) in results.iter().zip(&expected) {
+            assert_eq!(result.risk_score, expected.risk_score);
+            assert_eq!(result.recommendation, expected.recommendation);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! process_person, analyze_health, validate_contact and snapshot_person on
+//! real C++ objects
+
+// Needs the C++ fixtures, which `stub-cpp` does not build
+#![cfg(not(feature = "stub-cpp"))]
+
+mod common;
+
+use rust_lib::{analyze_health, ffi, process_person, snapshot_person, validate_contact};
+
+#[test]
+fn test_process_adult() {
+    let person = common::adult_person();
+    let info = process_person(&person);
+
+    assert!(info.is_adult);
+    assert_eq!(info.bmi_category, 1); // 70 kg at 1.75 m
+    assert_eq!(info.name_length, "Alice".len());
+    assert_eq!(info.city, "Boston");
+}
+
+#[test]
+fn test_process_minor() {
+    let info = process_person(&common::minor_person());
+
+    assert!(!info.is_adult);
+    assert_eq!(info.bmi_category, 2); // 70 kg at 1.45 m
+    assert_eq!(info.city, "Chicago");
+}
+
+#[test]
+fn test_process_name_length_is_bytes() {
+    let person = common::person_with(40, 1.80, "José", "jose@example.com", "555-0102", "Madrid", "28001");
+    assert_eq!(process_person(&person).name_length, "José".len());
+}
+
+#[test]
+fn test_process_name_graphemes() {
+    // A decomposed é and a family emoji each count as one
+    let name = "Jose\u{301} 👩\u{200d}👩\u{200d}👧";
+    let info = process_person(&common::person_named(name));
+    assert_eq!(info.name_graphemes, 6);
+    assert_eq!(info.name_length, name.len());
+}
+
+#[test]
+fn test_analyze_health_risk_factors() {
+    let healthy = analyze_health(&common::adult_person(), 70.0);
+    assert!((healthy.bmi - 22.86).abs() < 0.01);
+    assert_eq!(healthy.risk_score, 1.0);
+    assert_eq!(healthy.city_risk_factor, 1.0);
+    assert!(healthy.recommendation.starts_with("Excellent"));
+
+    // Over 65, underweight and in New York: 1.5 * 1.3 * 1.2
+    let person = common::person_with(70, 1.90, "Bob", "bob@example.com", "555-0103", "New York", "10001");
+    let risky = analyze_health(&person, 55.0);
+    assert_eq!(risky.city_risk_factor, 1.2);
+    assert!((risky.risk_score - 2.34).abs() < 1e-9);
+    assert!(risky.recommendation.starts_with("Elevated"));
+}
+
+#[test]
+fn test_analyze_health_zero_height() {
+    let person = common::person_with(30, 0.0, "Zed", "zed@example.com", "555-0104", "Boston", "02101");
+    assert_eq!(analyze_health(&person, 70.0).bmi, 0.0);
+}
+
+#[test]
+fn test_validate_contact() {
+    assert!(validate_contact(&common::valid_contact()));
+
+    let bad_email = common::contact_with("not-an-email", "555-0100", "Boston", "02101");
+    assert!(!validate_contact(&bad_email));
+    let no_city = common::contact_with("alice@example.com", "555-0100", "", "02101");
+    assert!(!validate_contact(&no_city));
+}
+
+#[test]
+fn test_snapshot_matches_the_getters() {
+    let person = common::person_with(70, 1.90, "Bob", "bob@example.com", "555-0103", "New York", "10001");
+    let contact = ffi::get_person_contact(&person);
+    let address = ffi::get_contact_address(contact);
+
+    // fill_person_snapshot copies on the C++ side; it must read the same
+    let snapshot = snapshot_person(&person);
+    assert_eq!(snapshot.name, ffi::get_person_name(&person).to_str().unwrap());
+    assert_eq!(snapshot.age, ffi::get_person_age(&person));
+    assert_eq!(snapshot.height_m, ffi::get_person_height(&person));
+    assert_eq!(snapshot.email, ffi::get_contact_email(contact).to_str().unwrap());
+    assert_eq!(snapshot.phone, ffi::get_contact_phone(contact).to_str().unwrap());
+    assert_eq!(snapshot.street, ffi::get_address_street(address).to_str().unwrap());
+    assert_eq!(snapshot.city, "New York");
+    assert_eq!(snapshot.postal_code, "10001");
+}
@@ -0,0 +1,152 @@
+//! The batch APIs on a large synthetic cohort
+//!
+//! Ignored by default: run with `cargo xtask stress [--people N]`, which is
+//! `cargo test --release --test stress -- --ignored --nocapture`. The cohort
+//! has RUST_LIB_STRESS_PEOPLE people (1,000,000 by default). Each run must
+//! stay under a heap ceiling per person, measured by the counting allocator
+//! below, and, in optimized builds, above a throughput floor.
+
+// The library installs its own global allocator with `host-allocator`
+#![cfg(not(feature = "host-allocator"))]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use rust_lib::ffi::{AnalysisOptions, PersonSnapshot};
+use rust_lib::{
+    analyze_health_batch, analyze_health_batch_pooled, analyze_health_batch_with_options,
+    release_string_table,
+};
+
+const DEFAULT_PEOPLE: usize = 1_000_000;
+
+/// Extra heap a batch run may use per person: one HealthAnalysis and its
+/// recommendation string take about 120 bytes
+const MAX_BATCH_BYTES_PER_PERSON: usize = 192;
+
+/// Heap of the synthetic cohort itself, about 230 bytes per snapshot with
+/// its seven short strings
+const MAX_COHORT_BYTES_PER_PERSON: usize = 384;
+
+/// Analyses per second an optimized build must reach; a quarter of what one
+/// current x86-64 core does, so only real regressions trip it
+const MIN_PEOPLE_PER_SEC: f64 = 1_000_000.0;
+
+/// Counts live and peak heap bytes on top of the system allocator
+struct CountingAllocator;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let live = LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Peak heap growth and wall time of `run`
+fn measure<T>(run: impl FnOnce() -> T) -> (T, usize, Duration) {
+    let baseline = LIVE_BYTES.load(Ordering::Relaxed);
+    PEAK_BYTES.store(baseline, Ordering::Relaxed);
+    let start = Instant::now();
+    let value = run();
+    let elapsed = start.elapsed();
+    (value, PEAK_BYTES.load(Ordering::Relaxed) - baseline, elapsed)
+}
+
+fn cohort_size() -> usize {
+    std::env::var("RUST_LIB_STRESS_PEOPLE")
+        .ok()
+        .and_then(|people| people.parse().ok())
+        .unwrap_or(DEFAULT_PEOPLE)
+}
+
+/// Deterministic, varied people: every age band, BMI class and city factor
+fn synthetic_person(i: usize) -> PersonSnapshot {
+    const CITIES: [&str; 4] = ["Boston", "New York", "Los Angeles", "Chicago"];
+    PersonSnapshot {
+        name: format!("person-{i}"),
+        age: (i % 97) as u32,
+        height_m: 1.40 + (i % 61) as f64 / 100.0,
+        email: format!("p{i}@example.com"),
+        phone: "555-0100".to_string(),
+        street: "1 Main St".to_string(),
+        city: CITIES[i % CITIES.len()].to_string(),
+        postal_code: "02101".to_string(),
+    }
+}
+
+fn check_limits(what: &str, people: usize, bytes: usize, elapsed: Duration) {
+    let bytes_per_person = bytes / people.max(1);
+    let rate = people as f64 / elapsed.as_secs_f64();
+    println!("{what:<36} {elapsed:>10.2?} {rate:>14.0} people/s {bytes_per_person:>6} B/person");
+
+    assert!(
+        bytes_per_person <= MAX_BATCH_BYTES_PER_PERSON,
+        "{what}: {bytes_per_person} heap bytes per person, ceiling {MAX_BATCH_BYTES_PER_PERSON}"
+    );
+    if cfg!(debug_assertions) {
+        println!("{what:<36} throughput floor not checked in a debug build");
+    } else {
+        assert!(rate >= MIN_PEOPLE_PER_SEC, "{what}: {rate:.0} people/s, floor {MIN_PEOPLE_PER_SEC}");
+    }
+}
+
+#[test]
+#[ignore = "stress test; run with `cargo xtask stress`"]
+fn stress_batch_apis() {
+    let people = cohort_size();
+    let (cohort, cohort_bytes, _) =
+        measure(|| (0..people).map(synthetic_person).collect::<Vec<_>>());
+    let weights: Vec<f64> = (0..people).map(|i| 45.0 + (i % 80) as f64).collect();
+    println!("cohort of {people}: {} B/person", cohort_bytes / people.max(1));
+    assert!(
+        cohort_bytes / people.max(1) <= MAX_COHORT_BYTES_PER_PERSON,
+        "cohort: {} heap bytes per person, ceiling {MAX_COHORT_BYTES_PER_PERSON}",
+        cohort_bytes / people.max(1)
+    );
+
+    let (results, bytes, elapsed) = measure(|| analyze_health_batch(&cohort, &weights, 0).unwrap());
+    assert_eq!(results.len(), people);
+    assert!(results.iter().all(|analysis| analysis.risk_score >= 1.0));
+    check_limits("analyze_health_batch", people, bytes, elapsed);
+    drop(results);
+
+    // A deadline far beyond the run must not cut it short
+    let options = AnalysisOptions::builder()
+        .timeout(Duration::from_secs(600))
+        .build();
+    let (batch, bytes, elapsed) =
+        measure(|| analyze_health_batch_with_options(&cohort, &weights, &options, 0).unwrap());
+    assert!(!batch.timed_out);
+    assert_eq!(batch.results.len(), people);
+    check_limits("analyze_health_batch_with_options", people, bytes, elapsed);
+    drop(batch);
+
+    let lazy = AnalysisOptions::builder().lazy_recommendations(true).build();
+    let (batch, bytes, elapsed) =
+        measure(|| analyze_health_batch_with_options(&cohort, &weights, &lazy, 0).unwrap());
+    assert!(batch.results.iter().all(|analysis| analysis.recommendation.is_empty()));
+    check_limits("lazy_recommendations", people, bytes, elapsed);
+    drop(batch);
+
+    let (pooled, bytes, elapsed) =
+        measure(|| analyze_health_batch_pooled(&cohort, &weights, 0).unwrap());
+    assert_eq!(pooled.results.len(), people);
+    check_limits("analyze_health_batch_pooled", people, bytes, elapsed);
+    assert!(release_string_table(pooled.table_id));
+}
@@ -0,0 +1,117 @@
+//! Strings across the bridge in both directions
+//!
+//! &str -> std::string (rust::Str), std::string -> &CxxString / String,
+//! Rust String -> rust::String read back in C++, std::string -> UTF-16
+//! for wide-string hosts, and Latin-1 std::strings decoded as such. Every
+//! case compares bytes: interior NULs and long
+//! strings must survive unchanged, not be cut at the first NUL or at some
+//! buffer size.
+
+// Needs the C++ fixtures, which `stub-cpp` does not build
+#![cfg(not(feature = "stub-cpp"))]
+
+mod common;
+
+use cxx::let_cxx_string;
+use rust_lib::{
+    ffi, person_name_utf16, process_person, snapshot_person, snapshot_person_with_encoding,
+};
+
+const UTF8_SAMPLES: [&str; 6] = [
+    "Alice",
+    "José Núñez",
+    "Ελένη Παπαδοπούλου",
+    "山田太郎",
+    "👩‍👩‍👧 family",
+    "Ann\0Lee",
+];
+
+/// About 1 MB with multi-byte characters and NULs spread through it
+fn large_string() -> String {
+    let mut value = String::with_capacity(1 << 20);
+    while value.len() < 1 << 20 {
+        value.push_str("abcdefgh\0é日👍");
+    }
+    value
+}
+
+/// &str into a C++ Person and back out through every read path
+fn assert_round_trip(name: &str) {
+    let person = common::person_named(name);
+
+    let cxx_name = ffi::get_person_name(&person);
+    assert_eq!(cxx_name.as_bytes(), name.as_bytes());
+    assert_eq!(cxx_name.to_str(), Ok(name));
+    assert_eq!(common::bytes_of(cxx_name), name.as_bytes());
+    assert_eq!(common::rust_string_from(cxx_name).unwrap(), name);
+
+    assert_eq!(snapshot_person(&person).name, name);
+    let info = process_person(&person);
+    assert_eq!(info.name_length, name.len());
+    assert!(info.name_graphemes <= name.chars().count());
+    assert!(common::snapshot_name_round_trips(&person));
+
+    let wide = person_name_utf16(&person);
+    assert_eq!(wide, name.encode_utf16().collect::<Vec<_>>());
+    assert_eq!(String::from_utf16(&wide).unwrap(), name);
+}
+
+#[test]
+fn test_utf8_round_trips() {
+    for name in UTF8_SAMPLES {
+        assert_round_trip(name);
+    }
+}
+
+#[test]
+fn test_interior_nuls_round_trip() {
+    assert_round_trip("\0");
+    assert_round_trip("\0leading");
+    assert_round_trip("trailing\0");
+    assert_round_trip("a\0\0b");
+}
+
+#[test]
+fn test_large_string_round_trips() {
+    assert_round_trip(&large_string());
+}
+
+#[test]
+fn test_cxx_string_from_rust_keeps_bytes() {
+    let value = large_string();
+    let_cxx_string!(cxx_value = value.as_bytes());
+    assert_eq!(cxx_value.len(), value.len());
+    assert_eq!(common::bytes_of(&cxx_value), value.as_bytes());
+    assert_eq!(common::rust_string_from(&cxx_value).unwrap(), value);
+}
+
+#[test]
+fn test_std_string_from_cpp_keeps_bytes() {
+    let bytes = b"left\0right\0";
+    let value = common::std_string_from(bytes);
+    assert_eq!(value.as_bytes(), bytes);
+    assert_eq!(value.to_str(), Ok("left\0right\0"));
+    assert_eq!(common::rust_string_from(&value).unwrap(), "left\0right\0");
+}
+
+#[test]
+fn test_invalid_utf8_from_cpp() {
+    let value = common::std_string_from(b"caf\xe9");
+    assert!(value.to_str().is_err());
+    assert_eq!(value.to_string_lossy(), "caf\u{fffd}");
+    // rust::String validates on construction and throws
+    assert!(common::rust_string_from(&value).is_err());
+}
+
+#[test]
+fn test_latin1_name_from_cpp() {
+    let person = common::person_named_bytes(b"Fran\xe7ois M\xfcller");
+    // Read as UTF-8, each accented byte is lost
+    assert_eq!(snapshot_person(&person).name, "Fran\u{fffd}ois M\u{fffd}ller");
+
+    let snapshot = snapshot_person_with_encoding(&person, "ISO-8859-1").unwrap();
+    assert_eq!(snapshot.name, "François Müller");
+    assert_eq!(snapshot.city, "Boston");
+    assert!(snapshot_person_with_encoding(&person, "UTF-8").is_err());
+    assert!(snapshot_person_with_encoding(&person, "KOI8-R").is_err());
+}
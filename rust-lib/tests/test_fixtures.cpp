@@ -0,0 +1,68 @@
+#include "rust-lib/tests/test_fixtures.h"
+#include "rust-lib/src/person_ffi.rs.h"
+
+std::unique_ptr<Person> adult_person() {
+    return person_with(30, 1.75, "Alice", "alice@example.com", "555-0100", "Boston", "02101");
+}
+
+std::unique_ptr<Person> minor_person() {
+    return person_with(12, 1.45, "Tim", "tim@example.com", "555-0101", "Chicago", "60601");
+}
+
+std::unique_ptr<Person> person_with(uint32_t age,
+                                    double height,
+                                    rust::Str name,
+                                    rust::Str email,
+                                    rust::Str phone,
+                                    rust::Str city,
+                                    rust::Str postal_code) {
+    std::shared_ptr<ContactInfo> contact = contact_with(email, phone, city, postal_code);
+    return create_person(age, height, std::string(name), contact);
+}
+
+std::unique_ptr<ContactInfo> valid_contact() {
+    return contact_with("alice@example.com", "555-0100", "Boston", "02101");
+}
+
+std::unique_ptr<ContactInfo> contact_with(rust::Str email,
+                                          rust::Str phone,
+                                          rust::Str city,
+                                          rust::Str postal_code) {
+    std::shared_ptr<Address> address = address_with("1 Main St", city, postal_code);
+    return create_contact_info(std::string(email), std::string(phone), address);
+}
+
+std::unique_ptr<Address> address_with(rust::Str street, rust::Str city, rust::Str postal_code) {
+    return create_address(std::string(street), std::string(city), std::string(postal_code));
+}
+
+std::unique_ptr<Person> person_named(rust::Str name) {
+    return person_with(30, 1.75, name, "alice@example.com", "555-0100", "Boston", "02101");
+}
+
+std::unique_ptr<Person> person_named_bytes(rust::Slice<const uint8_t> name) {
+    std::string bytes(reinterpret_cast<const char*>(name.data()), name.size());
+    return create_person(30, 1.75, bytes, valid_contact());
+}
+
+std::unique_ptr<std::string> std_string_from(rust::Slice<const uint8_t> bytes) {
+    return std::make_unique<std::string>(reinterpret_cast<const char*>(bytes.data()), bytes.size());
+}
+
+rust::Vec<uint8_t> bytes_of(const std::string& value) {
+    rust::Vec<uint8_t> bytes;
+    bytes.reserve(value.size());
+    for (char c : value) {
+        bytes.push_back(static_cast<uint8_t>(c));
+    }
+    return bytes;
+}
+
+rust::String rust_string_from(const std::string& value) {
+    return rust::String(value);
+}
+
+bool snapshot_name_round_trips(const Person& person) {
+    PersonSnapshot snapshot = snapshot_person(person);
+    return std::string(snapshot.name) == person.name();
+}
@@ -0,0 +1,58 @@
+#pragma once
+
+// Canned C++ objects for the Rust integration tests (tests/common/mod.rs)
+// build.rs compiles this with person.cpp; the tests link it through the
+// rust_lib_test_fixtures archive.
+
+#include "cpp-app/person.h"
+#include "rust/cxx.h"
+
+#include <memory>
+
+/// Alice, 30, 1.75 m, Boston, with a valid contact
+std::unique_ptr<Person> adult_person();
+
+/// Tim, 12, 1.45 m, Chicago
+std::unique_ptr<Person> minor_person();
+
+/// Person with every field given
+std::unique_ptr<Person> person_with(uint32_t age,
+                                    double height,
+                                    rust::Str name,
+                                    rust::Str email,
+                                    rust::Str phone,
+                                    rust::Str city,
+                                    rust::Str postal_code);
+
+/// Contact that passes validate_contact
+std::unique_ptr<ContactInfo> valid_contact();
+
+/// Contact with every field given; the street is fixed
+std::unique_ptr<ContactInfo> contact_with(rust::Str email,
+                                          rust::Str phone,
+                                          rust::Str city,
+                                          rust::Str postal_code);
+
+/// Address with every field given
+std::unique_ptr<Address> address_with(rust::Str street, rust::Str city, rust::Str postal_code);
+
+// String round trips (tests/strings.rs)
+
+/// Valid adult whose name is `name`, byte for byte
+std::unique_ptr<Person> person_named(rust::Str name);
+
+/// Like person_named, from raw bytes that need not be UTF-8
+std::unique_ptr<Person> person_named_bytes(rust::Slice<const uint8_t> name);
+
+/// std::string holding exactly `bytes`, NULs and invalid UTF-8 included
+std::unique_ptr<std::string> std_string_from(rust::Slice<const uint8_t> bytes);
+
+/// Bytes of `value` as C++ sees them
+rust::Vec<uint8_t> bytes_of(const std::string& value);
+
+/// `value` as a rust::String; throws std::invalid_argument if not UTF-8
+rust::String rust_string_from(const std::string& value);
+
+/// Whether the name in Rust's snapshot_person(person), read back in C++,
+/// equals person.name() byte for byte
+bool snapshot_name_round_trips(const Person& person);
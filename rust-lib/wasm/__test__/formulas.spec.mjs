@@ -0,0 +1,36 @@
+// node:test smoke test of the module as JavaScript sees it; in
+// rust-lib/wasm/, `wasm-pack build --target nodejs --out-dir pkg-node`, then
+// `node --test __test__/formulas.spec.mjs`. The expected values are those of
+// the Rust tests in rust-lib/formulas/src/lib.rs.
+
+import assert from "node:assert/strict";
+import { createRequire } from "node:module";
+import test from "node:test";
+
+const {
+  ActivityLevel,
+  Sex,
+  calculateBmi,
+  calculateBmr,
+  calculateTdee,
+  isPostalValid,
+  validateContact,
+} = createRequire(import.meta.url)("../pkg-node/rust_lib_wasm.js");
+
+test("calculateBmi", () => {
+  assert.ok(Math.abs(calculateBmi(70, 1.75) - 22.86) < 0.01);
+  assert.equal(calculateBmi(70, 0), 0);
+});
+
+test("calculateBmr and calculateTdee", () => {
+  assert.equal(calculateBmr(70, 1.75, 30, Sex.Male), 1648.75);
+  assert.equal(calculateBmr(70, 1.75, 30, Sex.Female), 1482.75);
+  const tdee = calculateTdee(calculateBmr(70, 1.75, 30, Sex.Male), ActivityLevel.Moderate);
+  assert.ok(Math.abs(tdee - 2555.5625) < 1e-9);
+});
+
+test("validateContact", () => {
+  assert.equal(validateContact("a@example.com", "555-0100", "Boston", "02101"), true);
+  assert.equal(validateContact("nobody", "555-0100", "Boston", "02101"), false);
+  assert.equal(isPostalValid("021"), false);
+});
@@ -0,0 +1,95 @@
+//! WebAssembly module for the web front end
+//!
+//! wasm-bindgen exports of rust-lib-formulas, the crate behind the C++
+//! app's calculate_bmi, calculate_bmr, calculate_tdee and validate_contact,
+//! so the browser computes exactly what the desktop app does. Names are
+//! camelCase on the JavaScript side.
+
+use rust_lib_formulas as formulas;
+use wasm_bindgen::prelude::*;
+
+/// Sex as the BMR equation distinguishes it
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sex {
+    Female = 0,
+    Male = 1,
+}
+
+/// Physical activity level, for the TDEE multiplier
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActivityLevel {
+    Sedentary = 0,
+    Light = 1,
+    Moderate = 2,
+    Active = 3,
+    VeryActive = 4,
+}
+
+impl From<Sex> for formulas::Sex {
+    fn from(sex: Sex) -> Self {
+        match sex {
+            Sex::Female => formulas::Sex::Female,
+            Sex::Male => formulas::Sex::Male,
+        }
+    }
+}
+
+impl From<ActivityLevel> for formulas::ActivityLevel {
+    fn from(activity: ActivityLevel) -> Self {
+        match activity {
+            ActivityLevel::Sedentary => formulas::ActivityLevel::Sedentary,
+            ActivityLevel::Light => formulas::ActivityLevel::Light,
+            ActivityLevel::Moderate => formulas::ActivityLevel::Moderate,
+            ActivityLevel::Active => formulas::ActivityLevel::Active,
+            ActivityLevel::VeryActive => formulas::ActivityLevel::VeryActive,
+        }
+    }
+}
+
+/// BMI for a weight and height; 0 for a height of 0 or less
+#[wasm_bindgen(js_name = calculateBmi)]
+pub fn calculate_bmi(weight_kg: f64, height_m: f64) -> f64 {
+    formulas::calculate_bmi(weight_kg, height_m)
+}
+
+/// Basal metabolic rate in kcal/day (Mifflin-St Jeor)
+#[wasm_bindgen(js_name = calculateBmr)]
+pub fn calculate_bmr(weight_kg: f64, height_m: f64, age: u32, sex: Sex) -> f64 {
+    formulas::calculate_bmr(weight_kg, height_m, age, sex.into())
+}
+
+/// Total daily energy expenditure in kcal/day
+#[wasm_bindgen(js_name = calculateTdee)]
+pub fn calculate_tdee(bmr: f64, activity: ActivityLevel) -> f64 {
+    formulas::calculate_tdee(bmr, activity.into())
+}
+
+/// All four contact rules, as the C++ app's validate_contact applies them
+#[wasm_bindgen(js_name = validateContact)]
+pub fn validate_contact(email: &str, phone: &str, city: &str, postal_code: &str) -> bool {
+    formulas::contact_valid(email, phone, city, postal_code)
+}
+
+// Single-field rules, for validating a form as it is filled in
+
+#[wasm_bindgen(js_name = isEmailValid)]
+pub fn email_valid(email: &str) -> bool {
+    formulas::email_valid(email)
+}
+
+#[wasm_bindgen(js_name = isPhoneValid)]
+pub fn phone_valid(phone: &str) -> bool {
+    formulas::phone_valid(phone)
+}
+
+#[wasm_bindgen(js_name = isCityValid)]
+pub fn city_valid(city: &str) -> bool {
+    formulas::city_valid(city)
+}
+
+#[wasm_bindgen(js_name = isPostalValid)]
+pub fn postal_valid(postal_code: &str) -> bool {
+    formulas::postal_valid(postal_code)
+}
@@ -0,0 +1,161 @@
+#!/usr/bin/env python3
+"""Writes rust-lib/src/unicode_tables.rs from Python's unicodedata.
+
+No Unicode data crate is vendored, so the tables the text helpers need are
+generated here and checked in. unicodedata has no grapheme break data, so
+the two properties graphemes.rs needs come from Perl's Unicode::UCD, which
+must report the same Unicode version. Rerun after a Python upgrade to move to a newer
+Unicode version:
+
+    python3 rust-lib/xtask/gen_unicode_tables.py
+"""
+
+import pathlib
+import subprocess
+import sys
+import unicodedata
+
+OUTPUT = pathlib.Path(__file__).resolve().parent.parent / "src" / "unicode_tables.rs"
+
+# Hangul syllables are composed and decomposed arithmetically, not by table
+HANGUL = range(0xAC00, 0xD7A4)
+LINE_WIDTH = 100
+
+# Grapheme_Cluster_Break values with their own rules; the rest are Other.
+# LV and LVT are the Hangul syllables, classified arithmetically.
+# Mapped to the Rust variant names.
+GRAPHEME_BREAKS = {"CR": "Cr", "LF": "Lf", "Control": "Control", "Extend": "Extend", "ZWJ": "Zwj",
+                   "Regional_Indicator": "RegionalIndicator", "Prepend": "Prepend",
+                   "SpacingMark": "SpacingMark", "L": "L", "V": "V", "T": "T"}
+
+PERL_INVMAP = """
+use Unicode::UCD qw(prop_invmap prop_invlist);
+print Unicode::UCD::UnicodeVersion(), "\n";
+my ($starts, $values) = prop_invmap("GCB");
+print "GCB $starts->[$_] $values->[$_]\n" for 0 .. $#$starts;
+my @pictographic = prop_invlist("Extended_Pictographic");
+print "ExtPict @pictographic\n";
+"""
+
+
+def canonical_decomposition(cp):
+    """The one- or two-code-point canonical mapping of cp, or None"""
+    if cp in HANGUL:
+        return None
+    fields = unicodedata.decomposition(chr(cp)).split()
+    if not fields or fields[0].startswith("<"):
+        return None
+    return [int(field, 16) for field in fields]
+
+
+def tables():
+    decompositions, compositions, classes = [], [], []
+    for cp in range(sys.maxunicode + 1):
+        if 0xD800 <= cp <= 0xDFFF:
+            continue
+        ch = chr(cp)
+        ccc = unicodedata.combining(ch)
+        if ccc:
+            classes.append((cp, ccc))
+        mapping = canonical_decomposition(cp)
+        if mapping is None:
+            continue
+        decompositions.append((cp, mapping[0], mapping[1] if len(mapping) == 2 else 0))
+        # Primary composites: pairs that NFC puts back together (this skips
+        # singletons, non-starter decompositions and composition exclusions)
+        if len(mapping) == 2 and unicodedata.normalize("NFC", chr(mapping[0]) + chr(mapping[1])) == ch:
+            compositions.append((mapping[0], mapping[1], cp))
+    compositions.sort()
+    return decompositions, compositions, ranges(classes)
+
+
+def grapheme_tables():
+    """(first, last, break) ranges and Extended_Pictographic ranges"""
+    lines = subprocess.run(["perl", "-e", PERL_INVMAP], check=True, capture_output=True,
+                           text=True).stdout.splitlines()
+    if lines[0] != unicodedata.unidata_version:
+        sys.exit(f"Perl has Unicode {lines[0]}, Python {unicodedata.unidata_version}")
+
+    starts = [line.split()[1:] for line in lines if line.startswith("GCB ")]
+    breaks = []
+    for index, (start, value) in enumerate(starts):
+        if value in GRAPHEME_BREAKS:
+            end = int(starts[index + 1][0]) - 1 if index + 1 < len(starts) else sys.maxunicode
+            breaks.append((int(start), end, GRAPHEME_BREAKS[value]))
+
+    # An inversion list: ranges start at even and end before odd positions
+    bounds = [int(bound) for bound in lines[-1].split()[1:]] + [sys.maxunicode + 1]
+    pictographic = [(bounds[i], bounds[i + 1] - 1) for i in range(0, len(bounds) - 1, 2)]
+    return breaks, pictographic
+
+
+def ranges(classes):
+    """Merge consecutive code points with the same class"""
+    merged = []
+    for cp, ccc in classes:
+        if merged and merged[-1][1] == cp - 1 and merged[-1][2] == ccc:
+            merged[-1] = (merged[-1][0], cp, ccc)
+        else:
+            merged.append((cp, cp, ccc))
+    return merged
+
+
+def rows(entries):
+    def cell(value):
+        return f"0x{value:X}" if isinstance(value, int) else f"GraphemeBreak::{value}"
+    line = ""
+    for entry in entries:
+        entry_cell = "(" + ", ".join(cell(value) for value in entry) + "),"
+        if line and len(line) + 1 + len(entry_cell) > LINE_WIDTH:
+            yield line
+            line = ""
+        line = f"{line} {entry_cell}" if line else f"    {entry_cell}"
+    if line:
+        yield line
+
+
+def main():
+    decompositions, compositions, classes = tables()
+    breaks, pictographic = grapheme_tables()
+    out = [
+        "// @generated by rust-lib/xtask/gen_unicode_tables.py; do not edit",
+        f"// Unicode {unicodedata.unidata_version}; lib.rs skips rustfmt for this file",
+        "",
+        "/// (code point, first, second): canonical decompositions, second 0 for",
+        "/// singletons; sorted by code point",
+        "pub(crate) const DECOMPOSITIONS: &[(u32, u32, u32)] = &[",
+        *rows(decompositions),
+        "];",
+        "",
+        "/// (first, second, composite): primary composites, sorted by pair",
+        "pub(crate) const COMPOSITIONS: &[(u32, u32, u32)] = &[",
+        *rows(compositions),
+        "];",
+        "",
+        "/// (first, last, class): canonical combining classes other than 0",
+        "pub(crate) const COMBINING_CLASSES: &[(u32, u32, u8)] = &[",
+        *rows(classes),
+        "];",
+        "",
+        "/// Grapheme_Cluster_Break values other than Other, LV and LVT",
+        "#[derive(Clone, Copy, Debug, PartialEq, Eq)]",
+        "pub(crate) enum GraphemeBreak {",
+        *(f"    {value}," for value in GRAPHEME_BREAKS.values()),
+        "}",
+        "",
+        "/// (first, last, break), sorted",
+        "pub(crate) const GRAPHEME_BREAKS: &[(u32, u32, GraphemeBreak)] = &[",
+        *rows(breaks),
+        "];",
+        "",
+        "/// (first, last): Extended_Pictographic code points, sorted",
+        "pub(crate) const EXTENDED_PICTOGRAPHIC: &[(u32, u32)] = &[",
+        *rows(pictographic),
+        "];",
+        "",
+    ]
+    OUTPUT.write_text("\n".join(out))
+
+
+if __name__ == "__main__":
+    main()
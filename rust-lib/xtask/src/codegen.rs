@@ -0,0 +1,312 @@
+//! `cargo xtask codegen`: getter shims from cpp-app/person.schema.yaml
+//!
+//! Every schema field becomes a free function get_<prefix>_<field>, which is
+//! what cxx can call on an opaque C++ type. The functions are written
+//! between the `@generated begin` / `@generated end` markers of
+//! cpp-app/person.h, cpp-app/person.cpp and rust-lib/src/person_ffi.rs,
+//! and the plain structs and getters that stand in for them with the
+//! `stub-cpp` feature in rust-lib/src/stub_cpp.rs; the rest of those files
+//! stays hand-written. The output is checked in, so
+//! CMake, Bazel and prebuilt-libperson builds need no extra step;
+//! `--check` (and the test below) fails when it is out of date. The same
+//! run regenerates the C# bindings from the C header (see csharp.rs).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::csharp;
+
+const SCHEMA: &str = "cpp-app/person.schema.yaml";
+const BEGIN_MARKER: &str = "@generated begin";
+const END_MARKER: &str = "@generated end";
+
+#[derive(Debug, PartialEq)]
+struct SchemaType {
+    name: String,
+    prefix: String,
+    fields: Vec<(String, String)>,
+}
+
+/// How one field type is spelled on each side of the bridge
+struct FieldType {
+    rust: String,
+    cpp: String,
+    /// Field type of the stub struct; read by reference unless `copy`
+    stub: String,
+    copy: bool,
+}
+
+pub fn codegen(check: bool) -> Result<(), String> {
+    let root = project_root();
+    let schema = fs::read_to_string(root.join(SCHEMA)).map_err(|e| format!("{SCHEMA}: {e}"))?;
+    let types = parse_schema(&schema).map_err(|e| format!("{SCHEMA}: {e}"))?;
+
+    let mut files = Vec::new();
+    for (path, generated) in outputs(&types)? {
+        let current =
+            fs::read_to_string(root.join(path)).map_err(|e| format!("{path}: {e}"))?;
+        let updated = splice(&current, &generated).map_err(|e| format!("{path}: {e}"))?;
+        files.push((path, current, updated));
+    }
+    // Generated whole, from the C header rather than the schema
+    let header = fs::read_to_string(root.join(csharp::HEADER))
+        .map_err(|e| format!("{}: {e}", csharp::HEADER))?;
+    let bindings = csharp::bindings(&header).map_err(|e| format!("{}: {e}", csharp::HEADER))?;
+    let current = fs::read_to_string(root.join(csharp::BINDINGS)).unwrap_or_default();
+    files.push((csharp::BINDINGS, current, bindings));
+
+    let mut stale = Vec::new();
+    for (path, current, updated) in files {
+        if updated == current {
+            continue;
+        }
+        if check {
+            stale.push(path);
+        } else {
+            fs::write(root.join(path), updated).map_err(|e| format!("{path}: {e}"))?;
+            println!("generated {path}");
+        }
+    }
+
+    if stale.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("out of date, run `cargo xtask codegen`: {}", stale.join(", ")))
+    }
+}
+
+/// The generated section of each output file
+fn outputs(types: &[SchemaType]) -> Result<[(&'static str, String); 4], String> {
+    Ok([
+        ("cpp-app/person.h", cpp_declarations(types)?),
+        ("cpp-app/person.cpp", cpp_definitions(types)?),
+        ("rust-lib/src/person_ffi.rs", rust_declarations(types)?),
+        ("rust-lib/src/stub_cpp.rs", rust_stubs(types)?),
+    ])
+}
+
+/// Parse the schema: a mapping of type name to `prefix` and `fields`
+///
+/// Only the block-style YAML subset the schema uses is understood: two
+/// spaces of indentation per level, `key: value` pairs and `#` comments.
+fn parse_schema(source: &str) -> Result<Vec<SchemaType>, String> {
+    let mut types: Vec<SchemaType> = Vec::new();
+    let mut in_fields = false;
+
+    for (index, line) in source.lines().enumerate() {
+        let error = |message: &str| format!("line {}: {message}", index + 1);
+        let line = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        }
+        .trim_end();
+        if line.is_empty() {
+            continue;
+        }
+
+        let content = line.trim_start();
+        let indent = line.len() - content.len();
+        let (key, value) = content.split_once(':').ok_or_else(|| error("expected `key: value`"))?;
+        let (key, value) = (key.trim(), value.trim());
+        if !is_identifier(key) {
+            return Err(error(&format!("`{key}` is not an identifier")));
+        }
+
+        match indent {
+            0 if value.is_empty() => {
+                if types.iter().any(|known| known.name == key) {
+                    return Err(error(&format!("type {key} is declared twice")));
+                }
+                types.push(SchemaType {
+                    name: key.to_string(),
+                    prefix: String::new(),
+                    fields: Vec::new(),
+                });
+                in_fields = false;
+            }
+            2 if !types.is_empty() => {
+                let current = types.last_mut().unwrap();
+                match key {
+                    "prefix" if is_identifier(value) => current.prefix = value.to_string(),
+                    "fields" if value.is_empty() => in_fields = true,
+                    _ => return Err(error(&format!("unexpected `{content}`"))),
+                }
+            }
+            4 if in_fields && !value.is_empty() => {
+                let fields = &mut types.last_mut().unwrap().fields;
+                if fields.iter().any(|(name, _)| name == key) {
+                    return Err(error(&format!("field {key} is declared twice")));
+                }
+                fields.push((key.to_string(), value.to_string()));
+            }
+            _ => return Err(error(&format!("unexpected `{content}`"))),
+        }
+    }
+
+    if let Some(incomplete) = types.iter().find(|ty| ty.prefix.is_empty() || ty.fields.is_empty()) {
+        return Err(format!("type {} needs a prefix and fields", incomplete.name));
+    }
+    Ok(types)
+}
+
+fn is_identifier(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn field_type(types: &[SchemaType], name: &str) -> Result<FieldType, String> {
+    let (rust, cpp) = match name {
+        "bool" => ("bool", "bool"),
+        "i32" => ("i32", "int32_t"),
+        "i64" => ("i64", "int64_t"),
+        "u32" => ("u32", "uint32_t"),
+        "u64" => ("u64", "uint64_t"),
+        "f32" => ("f32", "float"),
+        "f64" => ("f64", "double"),
+        "string" => {
+            return Ok(FieldType {
+                rust: "&CxxString".to_string(),
+                cpp: "const std::string&".to_string(),
+                stub: "StubString".to_string(),
+                copy: false,
+            });
+        }
+        // Opaque types of the schema are returned by reference
+        _ if types.iter().any(|ty| ty.name == name) => {
+            return Ok(FieldType {
+                rust: format!("&{name}"),
+                cpp: format!("const {name}&"),
+                stub: name.to_string(),
+                copy: false,
+            });
+        }
+        _ => return Err(format!("unknown field type {name}")),
+    };
+    Ok(FieldType { rust: rust.to_string(), cpp: cpp.to_string(), stub: rust.to_string(), copy: true })
+}
+
+/// One block per type, separated by blank lines
+fn per_type(
+    types: &[SchemaType],
+    mut line: impl FnMut(&SchemaType, &str, &FieldType) -> String,
+) -> Result<String, String> {
+    let mut blocks = Vec::new();
+    for ty in types {
+        let mut block = String::new();
+        for (field, field_ty) in &ty.fields {
+            block.push_str(&line(ty, field, &field_type(types, field_ty)?));
+        }
+        blocks.push(block);
+    }
+    Ok(blocks.join("\n"))
+}
+
+fn rust_declarations(types: &[SchemaType]) -> Result<String, String> {
+    per_type(types, |ty, field, field_ty| {
+        format!(
+            "        fn get_{}_{field}({}: &{}) -> {};\n",
+            ty.prefix, ty.prefix, ty.name, field_ty.rust
+        )
+    })
+}
+
+/// A struct per type and a getter per field, mirroring the C++ side
+fn rust_stubs(types: &[SchemaType]) -> Result<String, String> {
+    let mut blocks = Vec::new();
+    for ty in types {
+        let mut fields = String::new();
+        let mut getters = String::new();
+        for (field, field_ty) in &ty.fields {
+            let field_ty = field_type(types, field_ty)?;
+            fields.push_str(&format!("    pub {field}: {},\n", field_ty.stub));
+            let (returns, borrow) = if field_ty.copy {
+                (field_ty.stub, "")
+            } else {
+                (format!("&{}", field_ty.stub), "&")
+            };
+            getters.push_str(&format!(
+                "\npub fn get_{}_{field}({}: &{}) -> {returns} {{\n    {borrow}{}.{field}\n}}\n",
+                ty.prefix, ty.prefix, ty.name, ty.prefix
+            ));
+        }
+        blocks.push(format!(
+            "#[derive(Clone, Debug, Default)]\npub struct {} {{\n{fields}}}\n{getters}",
+            ty.name
+        ));
+    }
+    Ok(blocks.join("\n"))
+}
+
+fn cpp_declarations(types: &[SchemaType]) -> Result<String, String> {
+    per_type(types, |ty, field, field_ty| {
+        format!(
+            "{} get_{}_{field}(const {}& {});\n",
+            field_ty.cpp, ty.prefix, ty.name, ty.prefix
+        )
+    })
+}
+
+fn cpp_definitions(types: &[SchemaType]) -> Result<String, String> {
+    let blocks = per_type(types, |ty, field, field_ty| {
+        format!(
+            "{} get_{}_{field}(const {}& {}) {{\n    return {}.{field}();\n}}\n\n",
+            field_ty.cpp, ty.prefix, ty.name, ty.prefix, ty.prefix
+        )
+    })?;
+    // Definitions are already separated by blank lines
+    Ok(blocks.replace("\n\n\n", "\n\n").trim_end().to_string() + "\n")
+}
+
+/// Replace the lines between the generated-section markers of `contents`
+fn splice(contents: &str, generated: &str) -> Result<String, String> {
+    let begin = contents.find(BEGIN_MARKER).ok_or(format!("no `{BEGIN_MARKER}` marker"))?;
+    let begin = begin + contents[begin..].find('\n').ok_or("unterminated marker")? + 1;
+    let end = begin + contents[begin..].find(END_MARKER).ok_or(format!("no `{END_MARKER}` marker"))?;
+    // Back to the start of the end marker's line, keeping its indentation
+    let end = contents[..end].rfind('\n').map_or(end, |newline| newline + 1);
+    Ok(format!("{}{generated}{}", &contents[..begin], &contents[end..]))
+}
+
+/// The repository root, parent of rust-lib/
+fn project_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join("..")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_schema() {
+        let types = parse_schema(
+            "# comment\nPoint:\n  prefix: point\n  fields:\n    x: f64  # metres\n    label: string\n",
+        )
+        .unwrap();
+        assert_eq!(
+            types,
+            vec![SchemaType {
+                name: "Point".to_string(),
+                prefix: "point".to_string(),
+                fields: vec![
+                    ("x".to_string(), "f64".to_string()),
+                    ("label".to_string(), "string".to_string()),
+                ],
+            }]
+        );
+        assert_eq!(
+            cpp_definitions(&types).unwrap(),
+            "double get_point_x(const Point& point) {\n    return point.x();\n}\n\n\
+             const std::string& get_point_label(const Point& point) {\n    return point.label();\n}\n"
+        );
+
+        assert!(parse_schema("Point:\n  fields:\n    x: f64\n").is_err());
+        assert!(parse_schema("Point:\n  prefix: point\n  fields:\n   x: f64\n").is_err());
+        let unknown = parse_schema("Point:\n  prefix: point\n  fields:\n    x: Vec3\n").unwrap();
+        assert!(rust_declarations(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_generated_code_is_up_to_date() {
+        codegen(true).unwrap();
+    }
+}
@@ -0,0 +1,422 @@
+//! C# bindings for the plain C API, from rust-lib/include/rust_lib_c.h
+//!
+//! Part of `cargo xtask codegen`. Every enum, struct and function of the
+//! header becomes its blittable C# counterpart in csharp/RustLib.cs: enums
+//! over `int`, sequential structs with raw pointer fields, and `DllImport`s
+//! of the static class NativeMethods taking raw pointers, the way csbindgen
+//! would emit them. Only the cbindgen layout of that header is understood:
+//! `typedef enum` and `typedef struct` blocks with one declaration per
+//! line, `/** */` doc comments, and prototypes that end at `;`.
+
+use std::fmt::Write;
+
+pub const HEADER: &str = "rust-lib/include/rust_lib_c.h";
+pub const BINDINGS: &str = "rust-lib/csharp/RustLib.cs";
+
+#[derive(Debug, PartialEq)]
+enum Item {
+    Enum {
+        name: String,
+        docs: Vec<String>,
+        variants: Vec<(Vec<String>, String, String)>,
+    },
+    Struct {
+        name: String,
+        docs: Vec<String>,
+        fields: Vec<(Vec<String>, String, String)>,
+    },
+    Function {
+        name: String,
+        docs: Vec<String>,
+        returns: String,
+        params: Vec<(String, String)>,
+    },
+}
+
+/// RustLib.cs for the header `source`
+pub fn bindings(source: &str) -> Result<String, String> {
+    let items = parse_header(source)?;
+    let mut types = Vec::new();
+    let mut out = String::from(
+        "// <auto-generated>\n\
+         // C# bindings for rust_lib's plain C API (the `c-abi` feature), generated\n\
+         // by `cargo xtask codegen` from rust-lib/include/rust_lib_c.h; do not\n\
+         // edit by hand.\n\
+         // </auto-generated>\n\n\
+         using System.Runtime.InteropServices;\n\n\
+         namespace RustLib;\n",
+    );
+    let mut functions = String::new();
+
+    for item in &items {
+        match item {
+            Item::Enum {
+                name,
+                docs,
+                variants,
+            } => {
+                let prefix = format!("{}_", screaming_snake(name));
+                write!(
+                    out,
+                    "\n{}public enum {name} : int\n{{\n",
+                    doc_comment(docs, "")
+                )
+                .unwrap();
+                for (docs, variant, value) in variants {
+                    let variant = variant.strip_prefix(&prefix).unwrap_or(variant);
+                    let variant = pascal_case(variant);
+                    writeln!(out, "{}    {variant} = {value},", doc_comment(docs, "    ")).unwrap();
+                }
+                out.push_str("}\n");
+                types.push(name.as_str());
+            }
+            Item::Struct { name, docs, fields } => {
+                let mut body = String::new();
+                for (docs, ctype, field) in fields {
+                    let field_type = csharp_type(ctype, &types)?;
+                    writeln!(
+                        body,
+                        "{}    public {field_type} {};",
+                        doc_comment(docs, "    "),
+                        identifier(field)
+                    )
+                    .unwrap();
+                }
+                let unsafe_ = if body.contains('*') { "unsafe " } else { "" };
+                write!(
+                    out,
+                    "\n{}[StructLayout(LayoutKind.Sequential)]\npublic {unsafe_}struct {name}\n{{\n{body}}}\n",
+                    doc_comment(docs, "")
+                )
+                .unwrap();
+                types.push(name.as_str());
+            }
+            Item::Function {
+                name,
+                docs,
+                returns,
+                params,
+            } => {
+                let params = params
+                    .iter()
+                    .map(|(ctype, param)| {
+                        Ok(format!(
+                            "{} {}",
+                            csharp_type(ctype, &types)?,
+                            identifier(param)
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                write!(
+                    functions,
+                    "\n{}    [DllImport(Library, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]\n    \
+                     public static extern {} {name}({});\n",
+                    doc_comment(docs, "    "),
+                    csharp_type(returns, &types)?,
+                    params.join(", ")
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    write!(
+        out,
+        "\npublic static unsafe class NativeMethods\n{{\n    private const string Library = \"rust_lib\";\n{functions}}}\n"
+    )
+    .unwrap();
+    Ok(out)
+}
+
+fn parse_header(source: &str) -> Result<Vec<Item>, String> {
+    let mut items = Vec::new();
+    let mut lines = source.lines().map(str::trim).enumerate();
+    let mut docs = Vec::new();
+    let mut prototype = String::new();
+
+    while let Some((index, line)) = lines.next() {
+        let error = |message: &str| format!("line {}: {message}", index + 1);
+        if line.starts_with("/**") {
+            docs = doc_lines(line, &mut lines);
+        } else if line.starts_with("/*") {
+            // A plain comment, to its end
+            let mut line = line;
+            while !line.ends_with("*/") {
+                line = lines.next().ok_or_else(|| error("unterminated comment"))?.1;
+            }
+        } else if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("extern \"C\"")
+            || line.starts_with('}')
+        {
+            // Preprocessor lines and the extern "C" block around the functions
+        } else if line == "typedef enum {" {
+            let (name, variants) = block(&mut lines, |line| {
+                let (variant, value) =
+                    line.split_once('=').ok_or("enum variant without a value")?;
+                Ok((
+                    variant.trim().to_string(),
+                    value.trim_end_matches(',').trim().to_string(),
+                ))
+            })
+            .map_err(|e| error(&e))?;
+            items.push(Item::Enum {
+                name,
+                docs: std::mem::take(&mut docs),
+                variants,
+            });
+        } else if line == "typedef struct {" {
+            let (name, fields) = block(&mut lines, |line| {
+                declaration(line.strip_suffix(';').ok_or("struct field without a `;`")?)
+            })
+            .map_err(|e| error(&e))?;
+            items.push(Item::Struct {
+                name,
+                docs: std::mem::take(&mut docs),
+                fields,
+            });
+        } else {
+            prototype.push_str(line);
+            prototype.push(' ');
+            let Some(declaration_end) = prototype.find(';') else {
+                continue;
+            };
+            let text = prototype[..declaration_end].trim().to_string();
+            prototype.clear();
+            let (head, params) = text
+                .strip_suffix(')')
+                .and_then(|text| text.split_once('('))
+                .ok_or_else(|| error("not a prototype"))?;
+            let (returns, name) = declaration(head).map_err(|e| error(&e))?;
+            let params = match params.trim() {
+                "" | "void" => Vec::new(),
+                params => params
+                    .split(',')
+                    .map(declaration)
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| error(&e))?,
+            };
+            items.push(Item::Function {
+                name,
+                docs: std::mem::take(&mut docs),
+                returns,
+                params,
+            });
+        }
+    }
+    if !prototype.is_empty() {
+        return Err(format!("unterminated declaration: {}", prototype.trim()));
+    }
+    Ok(items)
+}
+
+/// The text of a `/** ... */` comment starting at `first`
+fn doc_lines<'a>(
+    first: &'a str,
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+) -> Vec<String> {
+    let mut docs = Vec::new();
+    let mut line = first;
+    loop {
+        let end = line.ends_with("*/");
+        let text = line
+            .trim_start_matches("/**")
+            .trim_end_matches("*/")
+            .trim_start_matches('*')
+            .trim();
+        docs.push(text.to_string());
+        if end {
+            break;
+        }
+        match lines.next() {
+            Some((_, next)) => line = next,
+            None => break,
+        }
+    }
+    // The `/**` and ` */` lines
+    while docs.first().is_some_and(String::is_empty) {
+        docs.remove(0);
+    }
+    while docs.last().is_some_and(String::is_empty) {
+        docs.pop();
+    }
+    docs
+}
+
+/// The entries of a typedef block up to its `} Name;`, each with the doc
+/// comment before it
+#[allow(clippy::type_complexity)]
+fn block<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    entry: impl Fn(&str) -> Result<(String, String), String>,
+) -> Result<(String, Vec<(Vec<String>, String, String)>), String> {
+    let mut entries = Vec::new();
+    let mut docs = Vec::new();
+    while let Some((_, line)) = lines.next() {
+        if let Some(name) = line.strip_prefix('}') {
+            let name = name
+                .trim()
+                .strip_suffix(';')
+                .ok_or("typedef without a `;`")?;
+            return Ok((name.to_string(), entries));
+        } else if line.starts_with("/**") {
+            docs = doc_lines(line, lines);
+        } else if !line.is_empty() {
+            let (left, right) = entry(line)?;
+            entries.push((std::mem::take(&mut docs), left, right));
+        }
+    }
+    Err("unterminated typedef".to_string())
+}
+
+/// Split `const char *name` into its type and name
+fn declaration(text: &str) -> Result<(String, String), String> {
+    let text = text.trim();
+    let split = text
+        .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .map(|index| index + 1)
+        .ok_or_else(|| format!("declaration without a type: {text}"))?;
+    let (ctype, name) = text.split_at(split);
+    if name.is_empty() || ctype.trim().is_empty() {
+        return Err(format!("not a declaration: {text}"));
+    }
+    Ok((ctype.trim().to_string(), name.to_string()))
+}
+
+/// The blittable C# type for a C type; `types` are the header's own
+fn csharp_type(ctype: &str, types: &[&str]) -> Result<String, String> {
+    let pointers = ctype.matches('*').count();
+    let base: Vec<&str> = ctype
+        .split(|c: char| c.is_whitespace() || c == '*')
+        .filter(|word| !word.is_empty() && *word != "const")
+        .collect();
+    let base = match base.as_slice() {
+        ["double"] => "double",
+        ["float"] => "float",
+        ["char"] | ["uint8_t"] => "byte",
+        ["int8_t"] => "sbyte",
+        ["uint16_t"] => "ushort",
+        ["int16_t"] => "short",
+        ["uint32_t"] => "uint",
+        ["int32_t"] => "int",
+        ["uint64_t"] => "ulong",
+        ["int64_t"] => "long",
+        ["size_t"] => "nuint",
+        ["void"] => "void",
+        [name] if types.contains(name) => name,
+        _ => return Err(format!("no blittable C# type for `{ctype}`")),
+    };
+    Ok(format!("{base}{}", "*".repeat(pointers)))
+}
+
+/// `name`, escaped with `@` where it is a C# keyword
+fn identifier(name: &str) -> String {
+    const KEYWORDS: [&str; 24] = [
+        "base",
+        "bool",
+        "byte",
+        "checked",
+        "class",
+        "decimal",
+        "event",
+        "fixed",
+        "in",
+        "internal",
+        "is",
+        "lock",
+        "namespace",
+        "object",
+        "operator",
+        "out",
+        "override",
+        "params",
+        "ref",
+        "string",
+        "this",
+        "unchecked",
+        "virtual",
+        "volatile",
+    ];
+    if KEYWORDS.contains(&name) {
+        format!("@{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+/// `RustLibStatus` -> `RUST_LIB_STATUS`, the prefix cbindgen gives variants
+fn screaming_snake(name: &str) -> String {
+    let mut out = String::new();
+    for (index, c) in name.chars().enumerate() {
+        if index > 0 && c.is_ascii_uppercase() {
+            out.push('_');
+        }
+        out.push(c.to_ascii_uppercase());
+    }
+    out
+}
+
+/// `NULL_ARGUMENT` -> `NullArgument`
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => {
+                    first.to_ascii_uppercase().to_string() + &chars.as_str().to_ascii_lowercase()
+                }
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn doc_comment(docs: &[String], indent: &str) -> String {
+    if docs.is_empty() {
+        return String::new();
+    }
+    let mut out = format!("{indent}/// <summary>\n");
+    for line in docs {
+        let line = line
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        writeln!(out, "{indent}/// {line}").unwrap();
+    }
+    writeln!(out, "{indent}/// </summary>").unwrap();
+    out.replace(" \n", "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bindings() {
+        let header = "/* Comment */\n#include <stdint.h>\n\n\
+                      /**\n * Outcome\n */\ntypedef enum {\n  POINT_STATUS_OK = 0,\n  /**\n   * Bad <input>\n   */\n  \
+                      POINT_STATUS_BAD_INPUT = 1,\n} PointStatus;\n\n\
+                      typedef struct {\n  const char *label;\n  uint32_t x;\n} Point;\n\n\
+                      #ifdef __cplusplus\nextern \"C\" {\n#endif\n\n\
+                      PointStatus point_move(const Point *point,\n                       size_t out);\n\n\
+                      double point_zero(void);\n\n#ifdef __cplusplus\n}  // extern \"C\"\n#endif\n";
+        let generated = bindings(header).unwrap();
+        let body = generated.split_once("namespace RustLib;\n").unwrap().1;
+        assert_eq!(
+            body,
+            "\n/// <summary>\n/// Outcome\n/// </summary>\npublic enum PointStatus : int\n{\n    Ok = 0,\n    \
+             /// <summary>\n    /// Bad &lt;input&gt;\n    /// </summary>\n    BadInput = 1,\n}\n\n\
+             [StructLayout(LayoutKind.Sequential)]\npublic unsafe struct Point\n{\n    public byte* label;\n    \
+             public uint x;\n}\n\n\
+             public static unsafe class NativeMethods\n{\n    private const string Library = \"rust_lib\";\n\n    \
+             [DllImport(Library, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]\n    \
+             public static extern PointStatus point_move(Point* point, nuint @out);\n\n    \
+             [DllImport(Library, CallingConvention = CallingConvention.Cdecl, ExactSpelling = true)]\n    \
+             public static extern double point_zero();\n}\n"
+        );
+
+        assert!(bindings("typedef struct {\n  bool flag;\n} Flags;\n").is_err());
+        assert!(bindings("double unterminated(void)\n").is_err());
+        assert!(bindings("typedef enum {\n  A,\n} E;\n").is_err());
+    }
+}
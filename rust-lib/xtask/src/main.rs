@@ -0,0 +1,339 @@
+//! Project automation, run from rust-lib/ as `cargo xtask <command>`
+//!
+//! install [--prefix DIR] [--features LIST] [--no-default-features]
+//!     Build rust-lib in release mode and install it for non-Cargo C++
+//!     builds (Make, Meson, ...):
+//!       DIR/lib/librust_lib.a, librust_lib.so.<version> (+ soname links)
+//!       DIR/lib/pkgconfig/rust_lib.pc
+//!       DIR/include/rust_lib/{rust-lib/src/*.rs.h, rust/cxx.h, cpp-app/*.h}
+//!     DIR defaults to /usr/local. `DESTDIR` is prepended to every installed
+//!     path but not to the prefix recorded in rust_lib.pc, as with
+//!     `make install`.
+//!
+//! codegen [--check]
+//!     Regenerate the getter shims from cpp-app/person.schema.yaml and the
+//!     C# bindings csharp/RustLib.cs from include/rust_lib_c.h; with
+//!     --check, fail instead if the checked-in code is out of date.
+//!
+//! stress [--people N]
+//!     Run the ignored stress test (tests/stress.rs) in release mode on N
+//!     synthetic people (default 1,000,000), checking its memory ceilings
+//!     and throughput floors.
+//!
+//! test-all
+//!     Run the Rust test suite and the C++ GoogleTest suite
+//!     (cpp-app/tests/) against the library, with one summary and exit
+//!     status for both.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode, Stdio};
+
+use serde_json::Value;
+
+mod codegen;
+mod csharp;
+mod test_all;
+
+/// Checked-in cbindgen output, see rust-lib/src/c_abi.rs
+const C_HEADER: &str = "rust_lib_c.h";
+/// Clang module over C_HEADER, for Swift
+const MODULE_MAP: &str = "module.modulemap";
+
+/// Headers of the C++ side that the generated bridge headers include
+const CPP_HEADERS: [&str; 5] = [
+    "person.h",
+    "person_snapshot.h",
+    "callbacks.h",
+    "registered_person.h",
+    "host_allocator.h",
+];
+
+/// Must match the version of the rust-lib package
+const VERSION: &str = "0.1.0";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("install") => InstallOptions::parse(&args[1..]).and_then(|options| install(&options)),
+        Some("codegen") => match &args[1..] {
+            [] => codegen::codegen(false),
+            [check] if check == "--check" => codegen::codegen(true),
+            _ => Err("usage: cargo xtask codegen [--check]".to_string()),
+        },
+        Some("stress") => match &args[1..] {
+            [] => stress(None),
+            [flag, people] if flag == "--people" => stress(Some(people)),
+            _ => Err("usage: cargo xtask stress [--people N]".to_string()),
+        },
+        Some("test-all") if args.len() == 1 => test_all::test_all(),
+        _ => Err("usage: cargo xtask install [--prefix DIR] [--features LIST] \
+                  [--no-default-features]\n       cargo xtask codegen [--check]\n       \
+                  cargo xtask stress [--people N]\n       cargo xtask test-all"
+            .to_string()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("xtask: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct InstallOptions {
+    prefix: PathBuf,
+    /// Extra arguments for `cargo build`
+    cargo_args: Vec<String>,
+}
+
+impl InstallOptions {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = InstallOptions {
+            prefix: PathBuf::from("/usr/local"),
+            cargo_args: Vec::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--prefix" => options.prefix = PathBuf::from(value()?),
+                "--features" => {
+                    let features = value()?.clone();
+                    options.cargo_args.extend(["--features".to_string(), features]);
+                }
+                "--no-default-features" => options.cargo_args.push(arg.clone()),
+                _ => return Err(format!("unknown install option {arg}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// What `cargo build` produced for rust-lib
+struct BuildOutput {
+    libraries: Vec<PathBuf>,
+    out_dir: PathBuf,
+}
+
+fn install(options: &InstallOptions) -> Result<(), String> {
+    let build = build_release(&options.cargo_args)?;
+
+    let prefix = env::current_dir().map_err(|e| e.to_string())?.join(&options.prefix);
+    // DESTDIR is a plain string prefix: /stage + /usr/local = /stage/usr/local
+    let root = match env::var_os("DESTDIR").filter(|destdir| !destdir.is_empty()) {
+        Some(destdir) => Path::new(&destdir).join(prefix.strip_prefix("/").unwrap_or(&prefix)),
+        None => prefix.clone(),
+    };
+    let lib_dir = root.join("lib");
+    let include_dir = root.join("include").join("rust_lib");
+
+    for library in &build.libraries {
+        install_library(library, &lib_dir)?;
+    }
+
+    let generated = build.out_dir.join("cxxbridge").join("include");
+    let generated_headers = fs::read_dir(generated.join("rust-lib").join("src"))
+        .map_err(|e| format!("no generated headers in {}: {e}", generated.display()))?;
+    for entry in generated_headers {
+        let name = entry.map_err(|e| e.to_string())?.file_name();
+        // cxx-build keeps the bridge sources next to their headers
+        if !name.to_string_lossy().ends_with(".h") {
+            continue;
+        }
+        let header = Path::new("rust-lib").join("src").join(name);
+        copy(&generated.join(&header), &include_dir.join(&header))?;
+    }
+    copy(&generated.join("rust").join("cxx.h"), &include_dir.join("rust").join("cxx.h"))?;
+    let cpp_app = workspace_dir().join("..").join("cpp-app");
+    for header in CPP_HEADERS {
+        copy(&cpp_app.join(header), &include_dir.join("cpp-app").join(header))?;
+    }
+    // Declares the `c-abi` functions, for C hosts
+    copy(&workspace_dir().join("include").join(C_HEADER), &include_dir.join(C_HEADER))?;
+    copy(&workspace_dir().join("include").join(MODULE_MAP), &include_dir.join(MODULE_MAP))?;
+
+    let pc_path = lib_dir.join("pkgconfig").join("rust_lib.pc");
+    fs::create_dir_all(pc_path.parent().unwrap()).map_err(|e| e.to_string())?;
+    fs::write(&pc_path, pkg_config(&prefix)).map_err(|e| e.to_string())?;
+    println!("installed {}", pc_path.display());
+    Ok(())
+}
+
+/// `cargo build --release` of rust-lib, reading the artifacts from cargo's
+/// JSON messages so cached builds report them too
+fn build_release(cargo_args: &[String]) -> Result<BuildOutput, String> {
+    let cargo = env::var("CARGO").unwrap_or("cargo".to_string());
+    let output = Command::new(cargo)
+        .current_dir(workspace_dir())
+        .args(["build", "--release", "--package", "rust-lib"])
+        .arg("--message-format=json-render-diagnostics")
+        .args(cargo_args)
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("failed to run cargo: {e}"))?;
+    if !output.status.success() {
+        return Err("cargo build failed".to_string());
+    }
+
+    let mut libraries = Vec::new();
+    let mut out_dir = None;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Ok(message) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        let package = message["package_id"].as_str().unwrap_or_default();
+        if !package.contains("rust-lib#") {
+            continue;
+        }
+        match message["reason"].as_str() {
+            Some("build-script-executed") => {
+                out_dir = message["out_dir"].as_str().map(PathBuf::from);
+            }
+            Some("compiler-artifact") if message["target"]["name"] == "rust_lib" => {
+                let filenames = message["filenames"].as_array().cloned().unwrap_or_default();
+                libraries.extend(filenames.iter().filter_map(Value::as_str).map(PathBuf::from));
+            }
+            _ => {}
+        }
+    }
+
+    let out_dir = out_dir.ok_or("cargo reported no build script output for rust-lib")?;
+    Ok(BuildOutput { libraries, out_dir })
+}
+
+/// `cargo test --release --test stress -- --ignored`, optionally resized
+fn stress(people: Option<&str>) -> Result<(), String> {
+    let mut command = Command::new(env::var("CARGO").unwrap_or("cargo".to_string()));
+    command
+        .current_dir(workspace_dir())
+        .args(["test", "--release", "--package", "rust-lib", "--test", "stress"])
+        .args(["--", "--ignored", "--nocapture"]);
+    if let Some(people) = people {
+        people.parse::<usize>().map_err(|_| format!("--people needs a count, not {people}"))?;
+        command.env("RUST_LIB_STRESS_PEOPLE", people);
+    }
+    let status = command.status().map_err(|e| format!("failed to run cargo: {e}"))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err("stress test failed".to_string())
+    }
+}
+
+/// Copy one library into `lib_dir`; shared ELF libraries get the versioned
+/// file name plus the soname and development links
+fn install_library(library: &Path, lib_dir: &Path) -> Result<(), String> {
+    let name = library.file_name().unwrap().to_string_lossy();
+    if !name.ends_with(".so") {
+        // Windows DLLs export no C++ API; see DYNAMIC_LINKING.md. The rlib
+        // is for Rust and the integration tests only.
+        let skipped = [".dll", ".dll.lib", ".pdb", ".rlib"];
+        if !skipped.iter().any(|suffix| name.ends_with(suffix)) {
+            copy(library, &lib_dir.join(&*name))?;
+        }
+        return Ok(());
+    }
+
+    let major = VERSION.split('.').next().unwrap();
+    let versioned = format!("{name}.{VERSION}");
+    let soname = format!("{name}.{major}");
+    copy(library, &lib_dir.join(&versioned))?;
+    symlink(&versioned, &lib_dir.join(&soname))?;
+    symlink(&soname, &lib_dir.join(&*name))
+}
+
+fn copy(from: &Path, to: &Path) -> Result<(), String> {
+    fs::create_dir_all(to.parent().unwrap()).map_err(|e| e.to_string())?;
+    fs::copy(from, to).map_err(|e| format!("failed to copy {}: {e}", from.display()))?;
+    println!("installed {}", to.display());
+    Ok(())
+}
+
+#[cfg(unix)]
+fn symlink(target: &str, link: &Path) -> Result<(), String> {
+    let _ = fs::remove_file(link);
+    std::os::unix::fs::symlink(target, link).map_err(|e| e.to_string())?;
+    println!("installed {} -> {target}", link.display());
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn symlink(_: &str, _: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+/// rust-lib/, the workspace root
+fn workspace_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("..")
+}
+
+/// Contents of rust_lib.pc for `prefix`
+///
+/// Libs.private lists what the staticlib needs (`rustc --print
+/// native-static-libs`); `pkg-config --static` adds it.
+fn pkg_config(prefix: &Path) -> String {
+    let native_libs = native_libs();
+    format!(
+        "prefix={}
+exec_prefix=${{prefix}}
+libdir=${{exec_prefix}}/lib
+includedir=${{prefix}}/include
+
+Name: rust_lib
+Description: Rust health analysis and validation with a cxx C++ API
+Version: {VERSION}
+Cflags: -I${{includedir}}/rust_lib
+Libs: -L${{libdir}} -lrust_lib
+Libs.private: {native_libs}
+",
+        prefix.display()
+    )
+}
+
+/// What linking the staticlib needs besides it (`rustc --print
+/// native-static-libs`)
+fn native_libs() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "-lc++ -framework Security -framework CoreFoundation -liconv -lSystem -lc -lm"
+    } else if cfg!(windows) {
+        "-lws2_32 -luserenv -lbcrypt -lntdll -ldbghelp"
+    } else {
+        "-lstdc++ -lgcc_s -lutil -lrt -lpthread -lm -ldl -lc"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_install_options() {
+        let options =
+            InstallOptions::parse(&args(&["--prefix", "/opt/rust_lib", "--features", "sanitize"]))
+                .unwrap();
+        assert_eq!(options.prefix, PathBuf::from("/opt/rust_lib"));
+        assert_eq!(options.cargo_args, args(&["--features", "sanitize"]));
+
+        assert!(InstallOptions::parse(&args(&["--prefix"])).is_err());
+        assert!(InstallOptions::parse(&args(&["--frobnicate"])).is_err());
+    }
+
+    #[test]
+    fn test_pkg_config_paths() {
+        let pc = pkg_config(Path::new("/opt/rust_lib"));
+        assert!(pc.starts_with("prefix=/opt/rust_lib\n"));
+        assert!(pc.contains("\nCflags: -I${includedir}/rust_lib\n"));
+        assert!(pc.contains(&format!("\nVersion: {VERSION}\n")));
+
+        let manifest = include_str!("../../Cargo.toml");
+        assert!(manifest.contains(&format!("\nversion = \"{VERSION}\"\n")));
+    }
+}
@@ -0,0 +1,196 @@
+//! `cargo xtask test-all`: the Rust and the C++ test suites in one run
+//!
+//! 1. `cargo test --workspace`: the Rust unit tests and the integration
+//!    tests against the C++ fixtures (rust-lib/tests/).
+//! 2. The GoogleTest suite cpp-app/tests/bridge_test.cpp, compiled against
+//!    the release librust_lib.a and the generated headers, then run. The
+//!    library is built with `leak-check`, so the suite also fails when a
+//!    test leaves a job, session or registry id open, and with `crypto`
+//!    and `parquet` for the encrypted and Parquet export tests.
+//! 3. The C suite cpp-app/tests/c_abi_test.c, compiled with `$CC` (default
+//!    `cc`) against rust-lib/include/rust_lib_c.h and the same library,
+//!    which is also built with `c-abi` for it.
+//! 4. The Java suite rust-lib/java/src/test/, compiled with `javac` together
+//!    with the RustLib wrapper and run with `java` against the same build's
+//!    librust_lib cdylib, built with `jni` for it.
+//!
+//! Every suite always runs; the summary lists each one and the command
+//! fails if any did. GoogleTest is found through `pkg-config gtest_main`, then
+//! GTEST_ROOT (include/ and lib/ below it), then the default search paths.
+//! The C++ suite is compiled with `$CXX` (default `c++`), which must accept
+//! GCC/Clang-style flags.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{build_release, native_libs, workspace_dir, BuildOutput};
+
+const CPP_SUITE: &str = "cpp-app/tests/bridge_test.cpp";
+const C_SUITE: &str = "cpp-app/tests/c_abi_test.c";
+const JAVA_SOURCES: &str = "java/src";
+const JAVA_SUITE: &str = "com.example.rustlib.RustLibTest";
+const LIBRARY_FEATURES: &str = "leak-check,c-abi,jni,crypto,parquet";
+
+pub fn test_all() -> Result<(), String> {
+    let results = [
+        ("rust", rust_suite()),
+        ("c++ (gtest)", cpp_suite()),
+        ("c (c-abi)", c_suite()),
+        ("java (jni)", java_suite()),
+    ];
+
+    println!("\ntest-all summary:");
+    for (suite, result) in &results {
+        match result {
+            Ok(()) => println!("  {suite:<12} passed"),
+            Err(message) => println!("  {suite:<12} FAILED: {message}"),
+        }
+    }
+    if results.iter().all(|(_, result)| result.is_ok()) {
+        Ok(())
+    } else {
+        Err("test-all failed".to_string())
+    }
+}
+
+fn rust_suite() -> Result<(), String> {
+    let cargo = env::var("CARGO").unwrap_or("cargo".to_string());
+    run(Command::new(cargo).current_dir(workspace_dir()).args(["test", "--workspace"]))
+}
+
+/// The release build the C, C++ and Java suites link, its librust_lib.a, and
+/// the directory for their test binaries
+fn release_library() -> Result<(BuildOutput, PathBuf, PathBuf), String> {
+    let build = build_release(&["--features".to_string(), LIBRARY_FEATURES.to_string()])?;
+    let static_lib = build
+        .libraries
+        .iter()
+        .find(|library| library.file_name().is_some_and(|name| name == "librust_lib.a"))
+        .ok_or("cargo built no librust_lib.a")?
+        .clone();
+    let out_dir = workspace_dir().join("target").join("cpp-tests");
+    std::fs::create_dir_all(&out_dir).map_err(|e| e.to_string())?;
+    Ok((build, static_lib, out_dir))
+}
+
+fn cpp_suite() -> Result<(), String> {
+    let (build, static_lib, out_dir) = release_library()?;
+    let root = workspace_dir().join("..");
+    let binary = out_dir.join("bridge_test");
+
+    let (gtest_cflags, gtest_libs) = gtest_flags();
+    let compiler = env::var("CXX").unwrap_or("c++".to_string());
+    let mut compile = Command::new(compiler);
+    compile
+        .args(["-std=c++17", "-Wall", "-Wextra"])
+        .arg("-I")
+        .arg(&root)
+        .arg("-I")
+        .arg(build.out_dir.join("cxxbridge").join("include"))
+        .args(&gtest_cflags)
+        .arg(root.join(CPP_SUITE))
+        .arg(&static_lib)
+        .args(&gtest_libs)
+        .args(native_libs().split(' '))
+        .arg("-o")
+        .arg(&binary);
+    run(&mut compile).map_err(|e| format!("building {CPP_SUITE}: {e}"))?;
+
+    run(&mut Command::new(&binary))
+}
+
+fn c_suite() -> Result<(), String> {
+    let (_, static_lib, out_dir) = release_library()?;
+    let root = workspace_dir().join("..");
+    let binary = out_dir.join("c_abi_test");
+
+    let compiler = env::var("CC").unwrap_or("cc".to_string());
+    let mut compile = Command::new(compiler);
+    compile
+        .args(["-std=c99", "-Wall", "-Wextra", "-pedantic"])
+        .arg("-I")
+        .arg(&root)
+        .arg(root.join(C_SUITE))
+        .arg(&static_lib)
+        // The cxx glue in the archive needs the C++ runtime
+        .args(native_libs().split(' '))
+        .arg("-o")
+        .arg(&binary);
+    run(&mut compile).map_err(|e| format!("building {C_SUITE}: {e}"))?;
+
+    run(&mut Command::new(&binary))
+}
+
+fn java_suite() -> Result<(), String> {
+    let (_, static_lib, out_dir) = release_library()?;
+    // The cdylib is built next to the static library
+    let library_dir = static_lib.parent().ok_or("librust_lib.a has no directory")?;
+    let classes = out_dir.join("java");
+
+    let sources = java_files(&workspace_dir().join(JAVA_SOURCES))?;
+    let mut compile = Command::new("javac");
+    compile.arg("-d").arg(&classes).args(&sources);
+    run(&mut compile).map_err(|e| format!("building {JAVA_SOURCES}: {e}"))?;
+
+    let mut test = Command::new("java");
+    test.arg(format!("-Djava.library.path={}", library_dir.display()))
+        .arg("-cp")
+        .arg(&classes)
+        .arg(JAVA_SUITE);
+    run(&mut test)
+}
+
+/// Every .java file below `dir`
+fn java_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(|e| format!("{}: {e}", dir.display()))? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.is_dir() {
+            files.extend(java_files(&path)?);
+        } else if path.extension().is_some_and(|extension| extension == "java") {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Compiler and linker flags for GoogleTest with its main()
+fn gtest_flags() -> (Vec<String>, Vec<String>) {
+    let pkg_config = |flag: &str| {
+        Command::new("pkg-config")
+            .args([flag, "gtest_main"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| {
+                let flags = String::from_utf8_lossy(&output.stdout);
+                flags.split_whitespace().map(str::to_string).collect::<Vec<_>>()
+            })
+    };
+    if let (Some(cflags), Some(libs)) = (pkg_config("--cflags"), pkg_config("--libs")) {
+        return (cflags, libs);
+    }
+
+    let libs = vec!["-lgtest_main".to_string(), "-lgtest".to_string()];
+    match env::var_os("GTEST_ROOT") {
+        Some(gtest_root) => {
+            let gtest_root = PathBuf::from(gtest_root);
+            let dir_flag = |flag: &str, dir: &Path| format!("{flag}{}", gtest_root.join(dir).display());
+            let mut link = vec![dir_flag("-L", Path::new("lib"))];
+            link.extend(libs);
+            (vec![dir_flag("-I", Path::new("include"))], link)
+        }
+        None => (Vec::new(), libs),
+    }
+}
+
+fn run(command: &mut Command) -> Result<(), String> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let status = command.status().map_err(|e| format!("failed to run {program}: {e}"))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{program} exited with {status}"))
+    }
+}